@@ -0,0 +1,177 @@
+//! # Architecture Detection
+//!
+//! Picks which frozen binary (`x86_64`, `arm64`, or `armv7`) to use,
+//! without shelling out to `uname`.
+//!
+//! `armv7` covers 32-bit Raspberry Pi OS and other `armv7-unknown-linux-gnueabihf`
+//! devices. Older ARM cores (Raspberry Pi Zero and 1) aren't supported.
+//!
+//! ## Resolution Order
+//!
+//! 1. `FROZEN_DUCKDB_ARCH=x86_64|arm64|armv7` always wins
+//! 2. In a build script, the architecture being compiled for
+//!    (`CARGO_CFG_TARGET_ARCH`), since that is what the library is linked into
+//! 3. Otherwise the native architecture of the machine. An `x86_64` process
+//!    running under Rosetta 2 on Apple Silicon (`sysctl.proc_translated`) is
+//!    treated as `arm64`.
+//!
+//! Running under emulation is always logged, because an `x86_64` toolchain
+//! under Rosetta builds `x86_64` binaries that run translated. Installing the
+//! `aarch64-apple-darwin` toolchain links the native library instead.
+
+use crate::error::BuildError;
+use anyhow::Result;
+use std::env;
+use tracing::{info, warn};
+
+/// Set to `x86_64`, `arm64`, or `armv7` to override architecture detection.
+pub const ARCH_ENV: &str = "FROZEN_DUCKDB_ARCH";
+
+/// Detects the architecture of the frozen binary to use.
+pub fn detect_architecture() -> Result<String> {
+    let translated = is_translated();
+    if translated {
+        info!("Running under Rosetta 2 emulation on an arm64 machine");
+    }
+
+    let arch = resolve(
+        env::var(ARCH_ENV).ok().as_deref(),
+        env::var("CARGO_CFG_TARGET_ARCH").ok().as_deref(),
+        env::consts::ARCH,
+        translated,
+    )?;
+
+    if translated && arch == "x86_64" {
+        warn!(
+            "Using the x86_64 DuckDB library, which runs under Rosetta. \
+             Install the aarch64-apple-darwin toolchain (or set {}=arm64 outside a build) to use the native library",
+            ARCH_ENV
+        );
+    }
+    Ok(arch)
+}
+
+/// Resolves the architecture from an explicit override, the build target,
+/// and the host architecture.
+pub fn resolve(
+    override_arch: Option<&str>,
+    target_arch: Option<&str>,
+    host_arch: &str,
+    translated: bool,
+) -> Result<String> {
+    if let Some(arch) = override_arch.filter(|arch| !arch.trim().is_empty()) {
+        return normalize(arch.trim()).ok_or_else(|| {
+            anyhow::Error::from(BuildError::UnsupportedArchitecture(arch.to_string()))
+                .context(format!("Invalid {}={}", ARCH_ENV, arch))
+        });
+    }
+    if let Some(arch) = target_arch {
+        return normalize(arch)
+            .ok_or_else(|| BuildError::UnsupportedArchitecture(arch.to_string()).into());
+    }
+    match normalize(host_arch) {
+        // The process is translated, but the machine itself is arm64
+        Some(arch) if translated && arch == "x86_64" => Ok("arm64".to_string()),
+        Some(arch) => Ok(arch),
+        None => Err(BuildError::UnsupportedArchitecture(host_arch.to_string()).into()),
+    }
+}
+
+/// Maps Rust and `uname` architecture names to frozen binary names.
+pub fn normalize(arch: &str) -> Option<String> {
+    match arch {
+        "x86_64" | "amd64" => Some("x86_64".to_string()),
+        "arm64" | "aarch64" => Some("arm64".to_string()),
+        // `arm` is Cargo's name for armv7-unknown-linux-gnueabihf, `armv7l` uname's
+        "arm" | "armv7" | "armv7l" | "armhf" => Some("armv7".to_string()),
+        _ => None,
+    }
+}
+
+/// Returns whether this process runs under Rosetta 2 translation.
+#[cfg(target_os = "macos")]
+pub fn is_translated() -> bool {
+    use std::os::raw::{c_char, c_int, c_void};
+
+    extern "C" {
+        fn sysctlbyname(
+            name: *const c_char,
+            oldp: *mut c_void,
+            oldlenp: *mut usize,
+            newp: *mut c_void,
+            newlen: usize,
+        ) -> c_int;
+    }
+
+    let mut value: c_int = 0;
+    let mut size = std::mem::size_of::<c_int>();
+    // SAFETY: the name is NUL-terminated and `value`/`size` describe a valid c_int buffer
+    let status = unsafe {
+        sysctlbyname(
+            c"sysctl.proc_translated".as_ptr(),
+            (&mut value as *mut c_int).cast(),
+            &mut size,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    // The sysctl doesn't exist on Intel Macs, which are never translated
+    status == 0 && value == 1
+}
+
+/// Returns whether this process runs under Rosetta 2 translation.
+#[cfg(not(target_os = "macos"))]
+pub fn is_translated() -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("aarch64").as_deref(), Some("arm64"));
+        assert_eq!(normalize("arm64").as_deref(), Some("arm64"));
+        assert_eq!(normalize("x86_64").as_deref(), Some("x86_64"));
+        assert_eq!(normalize("arm").as_deref(), Some("armv7"));
+        assert_eq!(normalize("armv7l").as_deref(), Some("armv7"));
+        assert_eq!(normalize("armv6l"), None);
+        assert_eq!(normalize("riscv64"), None);
+    }
+
+    #[test]
+    fn test_resolve_order() {
+        // Native host
+        assert_eq!(resolve(None, None, "aarch64", false).unwrap(), "arm64");
+        // Rosetta prefers the native architecture
+        assert_eq!(resolve(None, None, "x86_64", true).unwrap(), "arm64");
+        // The build target wins over the host, even under Rosetta
+        assert_eq!(
+            resolve(None, Some("x86_64"), "x86_64", true).unwrap(),
+            "x86_64"
+        );
+        assert_eq!(
+            resolve(None, Some("aarch64"), "x86_64", false).unwrap(),
+            "arm64"
+        );
+        assert_eq!(
+            resolve(None, Some("arm"), "x86_64", false).unwrap(),
+            "armv7"
+        );
+        // The override wins over everything
+        assert_eq!(
+            resolve(Some("x86_64"), Some("aarch64"), "aarch64", false).unwrap(),
+            "x86_64"
+        );
+        assert_eq!(resolve(Some(""), None, "x86_64", false).unwrap(), "x86_64");
+    }
+
+    #[test]
+    fn test_resolve_rejects_unsupported() {
+        let invalid = resolve(Some("sparc"), None, "x86_64", false).unwrap_err();
+        assert!(invalid.to_string().contains(ARCH_ENV));
+        assert!(resolve(None, Some("riscv64"), "x86_64", false).is_err());
+        assert!(resolve(None, None, "powerpc64", false).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+//! # Release Artifact Names
+//!
+//! Every file published on GitHub Releases names the OS and architecture it
+//! was built for, so a Linux build never downloads a macOS library:
+//!
+//! | Artifact | Name |
+//! |----------|------|
+//! | Shared library | `libduckdb-linux-x86_64.so`, `libduckdb-macos-arm64.dylib` |
+//! | Library + headers | `libduckdb-linux-x86_64.tar.zst` (or `.tar.gz`) |
+//! | Static archive | `libduckdb-static-macos-arm64.a` |
+//!
+//! The OS is the one being compiled for, so cross-compiling to a phone
+//! fetches its artifacts (see [`crate::mobile`]):
+//!
+//! | Rust target | OS name | Published |
+//! |-------------|---------|-----------|
+//! | `aarch64-apple-ios` | `ios` | `libduckdb-static-ios-arm64.a` |
+//! | `aarch64-linux-android` | `android` | `libduckdb-android-arm64.so`, `libduckdb-static-android-arm64.a` |
+//! | `armv7-unknown-linux-gnueabihf` | `linux` | `libduckdb-linux-armv7.so`, `libduckdb-static-linux-armv7.a` |
+//!
+//! The lite variant prefixes every name with `libduckdb-lite-` (see
+//! [`crate::variant`]).
+//!
+//! Releases before this scheme only published macOS libraries named
+//! `libduckdb_{arch}.dylib`; they are still downloaded on macOS, and never
+//! on Linux.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use frozen_duckdb_builder::artifact::{library_asset, ArtifactOs};
+//!
+//! assert_eq!(library_asset(ArtifactOs::Linux, "x86_64"), "libduckdb-linux-x86_64.so");
+//! assert_eq!(library_asset(ArtifactOs::MacOs, "arm64"), "libduckdb-macos-arm64.dylib");
+//! ```
+
+use crate::headers::ArchiveCompression;
+use anyhow::Result;
+
+/// Operating system an artifact was built for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactOs {
+    /// ELF shared objects (`.so`)
+    Linux,
+    /// Mach-O dynamic libraries (`.dylib`)
+    MacOs,
+    /// iOS devices; only static archives are published
+    Ios,
+    /// Android (bionic) shared objects (`.so`)
+    Android,
+}
+
+impl ArtifactOs {
+    /// All operating systems artifacts are published for.
+    pub const ALL: [Self; 4] = [Self::Linux, Self::MacOs, Self::Ios, Self::Android];
+
+    /// Returns the OS this builder was compiled for.
+    pub fn current() -> Result<Self> {
+        if cfg!(target_os = "macos") {
+            Ok(Self::MacOs)
+        } else if cfg!(target_os = "linux") {
+            Ok(Self::Linux)
+        } else {
+            anyhow::bail!(
+                "No prebuilt DuckDB artifacts for {}; only Linux and macOS are published",
+                std::env::consts::OS
+            )
+        }
+    }
+
+    /// Returns the OS a build script is compiling for
+    /// (`CARGO_CFG_TARGET_OS`), or the host OS outside a build script.
+    ///
+    /// `None` means no artifacts are published for the target, which can
+    /// still use a system install or a local build.
+    pub fn for_target() -> Result<Option<Self>> {
+        let Ok(target_os) = std::env::var("CARGO_CFG_TARGET_OS") else {
+            return Ok(Self::current().ok());
+        };
+        let os = Self::parse(&target_os);
+        let abi = std::env::var("CARGO_CFG_TARGET_ABI").unwrap_or_default();
+        if os == Some(Self::Ios) && abi == "sim" {
+            anyhow::bail!(
+                "No prebuilt DuckDB artifacts for the iOS simulator; build for aarch64-apple-ios, \
+                 or link a simulator build of libduckdb.a with FROZEN_DUCKDB_USE_SYSTEM=1"
+            );
+        }
+        Ok(os)
+    }
+
+    /// Parses a `CARGO_CFG_TARGET_OS` value, or a name from [`ArtifactOs::as_str`].
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "linux" => Some(Self::Linux),
+            "macos" => Some(Self::MacOs),
+            "ios" => Some(Self::Ios),
+            "android" => Some(Self::Android),
+            _ => None,
+        }
+    }
+
+    /// Name used in artifact file names.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Linux => "linux",
+            Self::MacOs => "macos",
+            Self::Ios => "ios",
+            Self::Android => "android",
+        }
+    }
+
+    /// Shared library extension, without the dot.
+    pub fn library_extension(&self) -> &'static str {
+        match self {
+            Self::Linux | Self::Android => "so",
+            Self::MacOs | Self::Ios => "dylib",
+        }
+    }
+
+    /// Whether this is a phone OS, which is always cross-compiled for and
+    /// never has its library loaded or built on the build host.
+    pub fn is_mobile(&self) -> bool {
+        matches!(self, Self::Ios | Self::Android)
+    }
+}
+
+/// Shared library asset, e.g. `libduckdb-linux-x86_64.so`.
+pub fn library_asset(os: ArtifactOs, arch: &str) -> String {
+    format!(
+        "libduckdb-{}-{}.{}",
+        os.as_str(),
+        arch,
+        os.library_extension()
+    )
+}
+
+/// Library-plus-headers archive asset, e.g. `libduckdb-macos-arm64.tar.zst`.
+pub fn archive_asset(os: ArtifactOs, arch: &str, compression: ArchiveCompression) -> String {
+    format!(
+        "libduckdb-{}-{}.{}",
+        os.as_str(),
+        arch,
+        compression.extension()
+    )
+}
+
+/// Static archive asset, e.g. `libduckdb-static-linux-arm64.a`.
+pub fn static_asset(os: ArtifactOs, arch: &str) -> String {
+    format!("libduckdb-static-{}-{}.a", os.as_str(), arch)
+}
+
+/// Library name used by releases before OS-specific names, which only
+/// shipped macOS builds.
+pub fn legacy_library_asset(os: ArtifactOs, arch: &str) -> Option<String> {
+    (os == ArtifactOs::MacOs).then(|| format!("libduckdb_{}.dylib", arch))
+}
+
+/// Download URL of `asset` in the release for `version`.
+pub fn release_url(version: &str, asset: &str) -> String {
+    format!(
+        "https://github.com/seanchatmangpt/frozen-duckdb/releases/download/v{}/{}",
+        version, asset
+    )
+}
+
+/// Download URL of `asset` on a mirror serving the same layout as GitHub
+/// Releases, `{mirror}/v{version}/{asset}`.
+pub fn mirror_url(mirror: &str, version: &str, asset: &str) -> String {
+    format!("{}/v{}/{}", mirror.trim_end_matches('/'), version, asset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_os_matches_compile_target() {
+        let os = ArtifactOs::current().unwrap();
+        assert_eq!(
+            os.as_str(),
+            if cfg!(target_os = "macos") {
+                "macos"
+            } else {
+                "linux"
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_round_trips() {
+        for os in ArtifactOs::ALL {
+            assert_eq!(ArtifactOs::parse(os.as_str()), Some(os));
+        }
+        assert_eq!(ArtifactOs::parse("windows"), None);
+        assert!(ArtifactOs::Android.is_mobile() && !ArtifactOs::MacOs.is_mobile());
+    }
+
+    #[test]
+    fn test_mirror_url() {
+        assert_eq!(
+            mirror_url(
+                "https://mirror.example.com/duckdb/",
+                "1.4.0",
+                "libduckdb-macos-arm64.tar.zst"
+            ),
+            "https://mirror.example.com/duckdb/v1.4.0/libduckdb-macos-arm64.tar.zst"
+        );
+    }
+
+    #[test]
+    fn test_release_url() {
+        assert_eq!(
+            release_url("1.4.0", "libduckdb-linux-arm64.so"),
+            "https://github.com/seanchatmangpt/frozen-duckdb/releases/download/v1.4.0/libduckdb-linux-arm64.so"
+        );
+    }
+}
@@ -0,0 +1,388 @@
+//! # Bundling the DuckDB Library with an Application
+//!
+//! Executables linked against the frozen DuckDB library look it up by the
+//! install name (macOS) or soname (Linux) it was built with, which points
+//! into `~/.frozen-duckdb/cache/` on the build machine. To ship an app, the
+//! library has to be copied next to the executable and both binaries
+//! rewritten so the loader finds it there:
+//!
+//! - **macOS**: the library id becomes `@rpath/libduckdb.dylib`, the
+//!   executable's reference is changed to match and `@executable_path` is
+//!   added to its rpaths (`install_name_tool`), then both are ad-hoc
+//!   re-signed (`codesign`), which Apple Silicon requires after edits.
+//! - **Linux**: the library soname becomes `libduckdb.so` and the
+//!   executable's `RUNPATH` is set to `$ORIGIN` (`patchelf`).
+//!
+//! The same API backs `frozen-duckdb bundle` and can be called from an
+//! application's build script.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use frozen_duckdb_builder::bundle::{bundle, BundleOptions};
+//!
+//! let report = bundle(&BundleOptions {
+//!     target_dir: "dist".into(),
+//!     executable: Some("target/release/my-app".into()),
+//!     ..Default::default()
+//! })?;
+//! println!("Bundled {}", report.library.display());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::info;
+
+/// Platform whose loader conventions the bundle follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    /// Mach-O binaries, patched with `install_name_tool`
+    MacOs,
+    /// ELF binaries, patched with `patchelf`
+    Linux,
+}
+
+impl Platform {
+    /// Returns the platform this code was compiled for, if supported.
+    pub fn current() -> Result<Self> {
+        if cfg!(target_os = "macos") {
+            Ok(Self::MacOs)
+        } else if cfg!(target_os = "linux") {
+            Ok(Self::Linux)
+        } else {
+            anyhow::bail!("Bundling is only supported on macOS and Linux")
+        }
+    }
+
+    /// File name the bundled library is given.
+    pub fn library_name(&self) -> &'static str {
+        match self {
+            Self::MacOs => "libduckdb.dylib",
+            Self::Linux => "libduckdb.so",
+        }
+    }
+}
+
+/// What to bundle and where.
+#[derive(Debug, Clone, Default)]
+pub struct BundleOptions {
+    /// Directory the library (and executable) are placed in
+    pub target_dir: PathBuf,
+    /// Executable to patch; copied into `target_dir` if it lives elsewhere
+    pub executable: Option<PathBuf>,
+    /// Library to bundle; defaults to the cached frozen binary
+    pub library: Option<PathBuf>,
+    /// Print the commands without copying or patching anything
+    pub dry_run: bool,
+}
+
+/// A single external tool invocation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolCommand {
+    /// Program to run
+    pub program: String,
+    /// Arguments
+    pub args: Vec<String>,
+}
+
+impl ToolCommand {
+    fn new(program: &str, args: &[&str]) -> Self {
+        Self {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+
+    /// Runs the command, failing with its stderr if it exits unsuccessfully.
+    pub fn run(&self) -> Result<String> {
+        let output = Command::new(&self.program)
+            .args(&self.args)
+            .output()
+            .with_context(|| format!("Failed to run {} (is it installed?)", self.program))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "{} failed: {}",
+                self,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+impl std::fmt::Display for ToolCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            if arg.contains(' ') || arg.contains('$') {
+                write!(f, " '{}'", arg)?;
+            } else {
+                write!(f, " {}", arg)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Result of a bundle run.
+#[derive(Debug, Clone)]
+pub struct BundleReport {
+    /// Bundled library path
+    pub library: PathBuf,
+    /// Patched executable path, if one was given
+    pub executable: Option<PathBuf>,
+    /// Patch commands that were run (or would run, for a dry run)
+    pub commands: Vec<ToolCommand>,
+}
+
+/// Copies the DuckDB library (and optionally an executable) into
+/// `options.target_dir` and rewrites them so the executable loads the
+/// library from its own directory.
+pub fn bundle(options: &BundleOptions) -> Result<BundleReport> {
+    let platform = Platform::current()?;
+    let source_library = match &options.library {
+        Some(library) => library.clone(),
+        None => crate::ensure_binary()?,
+    };
+    if !source_library.exists() {
+        anyhow::bail!("Library not found: {}", source_library.display());
+    }
+
+    let library = options.target_dir.join(platform.library_name());
+    let executable = options.executable.as_ref().map(|exe| {
+        let in_target = exe
+            .parent()
+            .is_some_and(|dir| same_dir(dir, &options.target_dir));
+        if in_target {
+            exe.clone()
+        } else {
+            options
+                .target_dir
+                .join(exe.file_name().unwrap_or(exe.as_os_str()))
+        }
+    });
+
+    if !options.dry_run {
+        fs::create_dir_all(&options.target_dir).with_context(|| {
+            format!(
+                "Failed to create target directory: {}",
+                options.target_dir.display()
+            )
+        })?;
+        fs::copy(&source_library, &library)
+            .with_context(|| format!("Failed to copy {}", source_library.display()))?;
+        if let (Some(source), Some(dest)) = (&options.executable, &executable) {
+            if source != dest {
+                fs::copy(source, dest)
+                    .with_context(|| format!("Failed to copy {}", source.display()))?;
+            }
+        }
+    }
+
+    // The executable's current reference to DuckDB, which must be replaced
+    let current_reference = match (&executable, options.dry_run) {
+        (Some(exe), false) => linked_duckdb_reference(platform, exe)?,
+        _ => None,
+    };
+    let commands = relocation_commands(
+        platform,
+        &library,
+        executable.as_deref(),
+        current_reference.as_deref(),
+    );
+
+    if !options.dry_run {
+        for command in &commands {
+            info!("Running: {}", command);
+            command.run()?;
+        }
+    }
+
+    Ok(BundleReport {
+        library,
+        executable,
+        commands,
+    })
+}
+
+/// Builds the commands that rewrite `library` and `executable` for loading
+/// from the same directory. `current_reference` is the executable's
+/// existing DuckDB dependency, if it differs from the bundled name.
+pub fn relocation_commands(
+    platform: Platform,
+    library: &Path,
+    executable: Option<&Path>,
+    current_reference: Option<&str>,
+) -> Vec<ToolCommand> {
+    let library = library.to_string_lossy();
+    let name = platform.library_name();
+    let mut commands = Vec::new();
+
+    match platform {
+        Platform::MacOs => {
+            let rpath_name = format!("@rpath/{}", name);
+            commands.push(ToolCommand::new(
+                "install_name_tool",
+                &["-id", &rpath_name, &library],
+            ));
+            if let Some(exe) = executable {
+                let exe = exe.to_string_lossy();
+                if let Some(reference) = current_reference.filter(|r| *r != rpath_name) {
+                    commands.push(ToolCommand::new(
+                        "install_name_tool",
+                        &["-change", reference, &rpath_name, &exe],
+                    ));
+                }
+                commands.push(ToolCommand::new(
+                    "install_name_tool",
+                    &["-add_rpath", "@executable_path", &exe],
+                ));
+                commands.push(ToolCommand::new(
+                    "codesign",
+                    &["--force", "--sign", "-", &exe],
+                ));
+            }
+            commands.push(ToolCommand::new(
+                "codesign",
+                &["--force", "--sign", "-", &library],
+            ));
+        }
+        Platform::Linux => {
+            commands.push(ToolCommand::new(
+                "patchelf",
+                &["--set-soname", name, &library],
+            ));
+            if let Some(exe) = executable {
+                let exe = exe.to_string_lossy();
+                if let Some(reference) = current_reference.filter(|r| *r != name) {
+                    commands.push(ToolCommand::new(
+                        "patchelf",
+                        &["--replace-needed", reference, name, &exe],
+                    ));
+                }
+                commands.push(ToolCommand::new(
+                    "patchelf",
+                    &["--set-rpath", "$ORIGIN", &exe],
+                ));
+            }
+        }
+    }
+    commands
+}
+
+/// Finds the executable's dependency on a DuckDB library, if any.
+fn linked_duckdb_reference(platform: Platform, executable: &Path) -> Result<Option<String>> {
+    let exe = executable.to_string_lossy();
+    let output = match platform {
+        Platform::MacOs => ToolCommand::new("otool", &["-L", &exe]).run()?,
+        Platform::Linux => ToolCommand::new("patchelf", &["--print-needed", &exe]).run()?,
+    };
+    Ok(find_duckdb_reference(&output))
+}
+
+/// Picks the DuckDB entry out of `otool -L` or `patchelf --print-needed` output.
+fn find_duckdb_reference(output: &str) -> Option<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .find(|entry| {
+            Path::new(entry)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("libduckdb"))
+        })
+        .map(str::to_string)
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_macos_relocation_commands() {
+        let commands = relocation_commands(
+            Platform::MacOs,
+            Path::new("dist/libduckdb.dylib"),
+            Some(Path::new("dist/app")),
+            Some("/Users/me/.frozen-duckdb/cache/v1.4.0-arm64/libduckdb_arm64.dylib"),
+        );
+        let rendered: Vec<String> = commands.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "install_name_tool -id @rpath/libduckdb.dylib dist/libduckdb.dylib",
+                "install_name_tool -change /Users/me/.frozen-duckdb/cache/v1.4.0-arm64/libduckdb_arm64.dylib @rpath/libduckdb.dylib dist/app",
+                "install_name_tool -add_rpath @executable_path dist/app",
+                "codesign --force --sign - dist/app",
+                "codesign --force --sign - dist/libduckdb.dylib",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_linux_relocation_commands() {
+        let commands = relocation_commands(
+            Platform::Linux,
+            Path::new("dist/libduckdb.so"),
+            Some(Path::new("dist/app")),
+            Some("libduckdb.so"),
+        );
+        let rendered: Vec<String> = commands.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "patchelf --set-soname libduckdb.so dist/libduckdb.so",
+                "patchelf --set-rpath '$ORIGIN' dist/app",
+            ]
+        );
+
+        let library_only =
+            relocation_commands(Platform::Linux, Path::new("libduckdb.so"), None, None);
+        assert_eq!(library_only.len(), 1);
+    }
+
+    #[test]
+    fn test_find_duckdb_reference() {
+        let otool = "dist/app:\n\t/Users/me/.frozen-duckdb/cache/libduckdb_arm64.dylib (compatibility version 0.0.0)\n\t/usr/lib/libSystem.B.dylib (compatibility version 1.0.0)\n";
+        assert_eq!(
+            find_duckdb_reference(otool).as_deref(),
+            Some("/Users/me/.frozen-duckdb/cache/libduckdb_arm64.dylib")
+        );
+        assert_eq!(
+            find_duckdb_reference("libc.so.6\nlibduckdb.so\n").as_deref(),
+            Some("libduckdb.so")
+        );
+        assert_eq!(find_duckdb_reference("libc.so.6\n"), None);
+    }
+
+    #[test]
+    fn test_dry_run_copies_nothing() {
+        let temp = tempfile::tempdir().unwrap();
+        let library = temp.path().join("libduckdb_x86_64.so");
+        fs::write(&library, b"lib").unwrap();
+        let target_dir = temp.path().join("dist");
+
+        let report = bundle(&BundleOptions {
+            target_dir: target_dir.clone(),
+            executable: Some(temp.path().join("app")),
+            library: Some(library),
+            dry_run: true,
+        })
+        .unwrap();
+
+        assert_eq!(report.executable, Some(target_dir.join("app")));
+        assert!(report.library.starts_with(&target_dir));
+        assert!(!report.commands.is_empty());
+        assert!(!target_dir.exists());
+    }
+}
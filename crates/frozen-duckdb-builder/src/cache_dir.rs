@@ -0,0 +1,171 @@
+//! # Choosing the Cache Directory
+//!
+//! The cache defaults to `~/.frozen-duckdb/cache`, which doesn't work in
+//! containers with a read-only home or in CI runners that want a per-job
+//! cache. The first applicable entry wins:
+//!
+//! 1. [`BuildConfig::cache_dir`](crate::BuildConfig::cache_dir), when set
+//!    through the builder API
+//! 2. `FROZEN_DUCKDB_CACHE_DIR`
+//! 3. `$HOME/.frozen-duckdb/cache`, if it is writable
+//! 4. `$XDG_CACHE_HOME/frozen-duckdb`, if `HOME` is unset or not writable
+//!
+//! An explicitly chosen directory (1 or 2) is never silently replaced: if
+//! it can't be written, the build fails and names it.
+
+use crate::config::CACHE_DIR_ENV;
+use anyhow::{Context, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// Directory created inside `XDG_CACHE_HOME`.
+pub const XDG_SUBDIR: &str = "frozen-duckdb";
+
+/// Returns the cache directory, creating it and checking that it is
+/// writable. `explicit` comes from the builder API and takes precedence.
+pub fn resolve(explicit: Option<&Path>) -> Result<PathBuf> {
+    let env_override = env::var(CACHE_DIR_ENV).ok();
+    let home = env::var("HOME").ok();
+    let xdg = env::var("XDG_CACHE_HOME").ok();
+    resolve_from(
+        explicit,
+        env_override.as_deref(),
+        home.as_deref(),
+        xdg.as_deref(),
+    )
+}
+
+/// Applies the precedence rules to the given settings.
+pub fn resolve_from(
+    explicit: Option<&Path>,
+    env_override: Option<&str>,
+    home: Option<&str>,
+    xdg_cache_home: Option<&str>,
+) -> Result<PathBuf> {
+    let non_empty = |value: Option<&str>| {
+        value
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    };
+
+    if let Some(dir) = explicit {
+        ensure_writable(dir).context("The configured cache directory is not usable")?;
+        return Ok(dir.to_path_buf());
+    }
+    if let Some(dir) = non_empty(env_override) {
+        ensure_writable(&dir).with_context(|| format!("{} is not usable", CACHE_DIR_ENV))?;
+        return Ok(dir);
+    }
+
+    let mut tried = Vec::new();
+    let defaults = [
+        non_empty(home).map(|home| home.join(crate::CACHE_DIR).join("cache")),
+        non_empty(xdg_cache_home).map(|xdg| xdg.join(XDG_SUBDIR)),
+    ];
+    for dir in defaults.into_iter().flatten() {
+        match ensure_writable(&dir) {
+            Ok(()) => {
+                if !tried.is_empty() {
+                    warn!("Using fallback cache directory {}", dir.display());
+                }
+                return Ok(dir);
+            }
+            Err(e) => {
+                debug!("Cache directory {} is not usable: {:#}", dir.display(), e);
+                tried.push(format!("{} ({:#})", dir.display(), e));
+            }
+        }
+    }
+
+    if tried.is_empty() {
+        anyhow::bail!(
+            "Neither HOME nor XDG_CACHE_HOME is set; set {} to a writable directory",
+            CACHE_DIR_ENV
+        );
+    }
+    anyhow::bail!(
+        "No writable cache directory (tried {}); set {} to a writable directory",
+        tried.join(", "),
+        CACHE_DIR_ENV
+    )
+}
+
+/// Creates `dir` if needed and checks that files can be created in it.
+pub fn ensure_writable(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create cache directory {}", dir.display()))?;
+    let probe = dir.join(format!(".write-test-{}", std::process::id()));
+    fs::write(&probe, b"")
+        .with_context(|| format!("Cache directory {} is not writable", dir.display()))?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_precedence() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = |name: &str| temp.path().join(name);
+        let home = path("home");
+        let home = home.to_str().unwrap();
+        let xdg = path("xdg");
+        let xdg = xdg.to_str().unwrap();
+        let from_env = path("from-env");
+
+        // The builder API wins over everything
+        let explicit = path("explicit");
+        assert_eq!(
+            resolve_from(Some(&explicit), from_env.to_str(), Some(home), Some(xdg)).unwrap(),
+            explicit
+        );
+        // Then the environment variable
+        assert_eq!(
+            resolve_from(None, from_env.to_str(), Some(home), Some(xdg)).unwrap(),
+            from_env
+        );
+        // Then the home directory, even when XDG_CACHE_HOME is set
+        assert_eq!(
+            resolve_from(None, Some(" "), Some(home), Some(xdg)).unwrap(),
+            path("home").join(".frozen-duckdb").join("cache")
+        );
+        // XDG_CACHE_HOME only without a home
+        assert_eq!(
+            resolve_from(None, None, None, Some(xdg)).unwrap(),
+            path("xdg").join(XDG_SUBDIR)
+        );
+
+        let error = resolve_from(None, None, None, None).unwrap_err();
+        assert!(error.to_string().contains(CACHE_DIR_ENV));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_read_only_home_falls_back_to_xdg() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::tempdir().unwrap();
+        let home = temp.path().join("home");
+        fs::create_dir_all(&home).unwrap();
+        fs::set_permissions(&home, fs::Permissions::from_mode(0o555)).unwrap();
+        // Root can write anywhere, so the fallback can't be observed
+        if ensure_writable(&home.join("probe")).is_ok() {
+            return;
+        }
+
+        let xdg = temp.path().join("xdg");
+        let dir = resolve_from(None, None, home.to_str(), xdg.to_str()).unwrap();
+        assert_eq!(dir, xdg.join(XDG_SUBDIR));
+
+        // An explicitly chosen directory is never replaced by a fallback
+        let error =
+            resolve_from(None, home.join("cache").to_str(), None, xdg.to_str()).unwrap_err();
+        assert!(error.to_string().contains(CACHE_DIR_ENV));
+        fs::set_permissions(&home, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+}
@@ -0,0 +1,149 @@
+//! # Cache Directory Locking
+//!
+//! Several build scripts can run at once (parallel workspace builds, two
+//! projects building side by side) and all miss the cache together. An
+//! advisory lock on `<versioned cache>/.lock` makes sure only one process
+//! downloads, compiles, or copies into a cache directory while the others
+//! wait and then reuse its result.
+//!
+//! Files are also installed atomically ([`install_file`]): written to a
+//! temporary name and renamed into place, so a reader that checks the cache
+//! without the lock never sees a partially written binary.
+
+use anyhow::{Context, Result};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+const LOCK_FILE: &str = ".lock";
+
+/// Exclusive advisory lock on a cache directory, released on drop.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use frozen_duckdb_builder::cache_lock::CacheLock;
+///
+/// let _lock = CacheLock::acquire("/home/me/.frozen-duckdb/cache/v1.4.0-arm64".as_ref())?;
+/// // Re-check the cache, then populate it
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct CacheLock {
+    _file: File,
+    path: PathBuf,
+}
+
+impl CacheLock {
+    /// Locks `dir`, waiting for any other process holding the lock.
+    pub fn acquire(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir).context("Failed to create cache directory")?;
+        let path = dir.join(LOCK_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open cache lock: {}", path.display()))?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                info!(
+                    "Waiting for another build to finish populating {}",
+                    dir.display()
+                );
+                file.lock()
+                    .with_context(|| format!("Failed to lock {}", path.display()))?;
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("Failed to lock {}", path.display()));
+            }
+        }
+
+        Ok(Self { _file: file, path })
+    }
+
+    /// Path of the lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Writes `dest` atomically from `write`, which receives a temporary path
+/// in the same directory to fill.
+pub fn install_with<F>(dest: &Path, write: F) -> Result<()>
+where
+    F: FnOnce(&Path) -> Result<()>,
+{
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).context("Failed to create cache directory")?;
+    }
+    let file_name = dest
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("download");
+    let partial = dest.with_file_name(format!(".{}.{}.partial", file_name, std::process::id()));
+
+    let result = write(&partial).and_then(|()| {
+        fs::rename(&partial, dest)
+            .with_context(|| format!("Failed to move file into place: {}", dest.display()))
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+/// Copies `src` to `dest` atomically.
+pub fn install_file(src: &Path, dest: &Path) -> Result<()> {
+    install_with(dest, |partial| {
+        fs::copy(src, partial).with_context(|| format!("Failed to copy {}", src.display()))?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_lock_excludes_second_holder() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().join("v1.4.0-x86_64");
+        let first = CacheLock::acquire(&dir).unwrap();
+        assert!(first.path().exists());
+
+        let (sender, receiver) = mpsc::channel();
+        let waiter_dir = dir.clone();
+        let waiter = thread::spawn(move || {
+            let _second = CacheLock::acquire(&waiter_dir).unwrap();
+            sender.send(()).unwrap();
+        });
+
+        // The second lock can't be taken while the first is held
+        assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+        drop(first);
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        waiter.join().unwrap();
+    }
+
+    #[test]
+    fn test_install_is_atomic() {
+        let temp = tempfile::tempdir().unwrap();
+        let src = temp.path().join("src.so");
+        fs::write(&src, "binary").unwrap();
+        let dest = temp.path().join("cache").join("libduckdb.so");
+
+        install_file(&src, &dest).unwrap();
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "binary");
+
+        let failed = install_with(&dest, |_| anyhow::bail!("network error"));
+        assert!(failed.is_err());
+        // The existing file is untouched and no partial file is left behind
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "binary");
+        assert_eq!(fs::read_dir(dest.parent().unwrap()).unwrap().count(), 1);
+    }
+}
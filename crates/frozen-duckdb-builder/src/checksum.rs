@@ -0,0 +1,186 @@
+//! # Cache Integrity Checks
+//!
+//! Every cached library has a SHA-256 checksum stored next to it
+//! (`<binary>.sha256`, in `shasum -a 256` format). The checksum is checked
+//! on every cache hit, and a binary that is missing its checksum or doesn't
+//! match it is discarded and fetched again, so a truncated or corrupted
+//! file heals itself on the next build instead of being reused forever.
+//!
+//! Downloads are also checked against the `.sha256` file published next to
+//! each release asset, when one is available.
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Result of checking a cached file against its stored checksum.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verification {
+    /// The file matches its checksum
+    Valid,
+    /// No checksum is stored for the file
+    Missing,
+    /// The file's contents don't match the stored checksum
+    Mismatch {
+        /// Stored checksum
+        expected: String,
+        /// Checksum of the file on disk
+        actual: String,
+    },
+}
+
+/// Returns the checksum file for `binary`, i.e. `<binary>.sha256`.
+pub fn checksum_path(binary: &Path) -> PathBuf {
+    let mut path = binary.as_os_str().to_owned();
+    path.push(".sha256");
+    PathBuf::from(path)
+}
+
+/// Computes the lowercase hex SHA-256 of a file.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Parses a checksum from `shasum`-style content (`<hex>  <file name>`) or a bare hex digest.
+pub fn parse_checksum(content: &str) -> Option<String> {
+    let digest = content.split_whitespace().next()?.to_lowercase();
+    (digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit())).then_some(digest)
+}
+
+/// Stores the checksum of `binary` next to it.
+pub fn write_checksum(binary: &Path) -> Result<String> {
+    let digest = sha256_file(binary)?;
+    let file_name = binary
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    fs::write(
+        checksum_path(binary),
+        format!("{}  {}\n", digest, file_name),
+    )
+    .context("Failed to write checksum file")?;
+    Ok(digest)
+}
+
+/// Returns the checksum stored next to `binary`, if any.
+pub fn stored_checksum(binary: &Path) -> Option<String> {
+    parse_checksum(&fs::read_to_string(checksum_path(binary)).ok()?)
+}
+
+/// Checks `binary` against its stored checksum.
+pub fn verify(binary: &Path) -> Result<Verification> {
+    let Some(expected) = stored_checksum(binary) else {
+        return Ok(Verification::Missing);
+    };
+    let actual = sha256_file(binary)?;
+    Ok(if actual == expected {
+        Verification::Valid
+    } else {
+        Verification::Mismatch { expected, actual }
+    })
+}
+
+/// Returns whether `binary` exists and matches its checksum, logging why not.
+pub fn is_cached_and_valid(binary: &Path) -> bool {
+    if !binary.exists() {
+        return false;
+    }
+    match verify(binary) {
+        Ok(Verification::Valid) => true,
+        Ok(Verification::Missing) => {
+            warn!(
+                "Cached binary has no checksum, fetching it again: {}",
+                binary.display()
+            );
+            false
+        }
+        Ok(Verification::Mismatch { expected, actual }) => {
+            warn!(
+                "Cached binary is corrupted (sha256 {} != {}), fetching it again: {}",
+                actual,
+                expected,
+                binary.display()
+            );
+            false
+        }
+        Err(e) => {
+            warn!("Failed to verify cached binary {}: {}", binary.display(), e);
+            false
+        }
+    }
+}
+
+/// Removes `binary` and its checksum so the cache entry is rebuilt.
+pub fn discard(binary: &Path) -> Result<()> {
+    for path in [binary.to_path_buf(), checksum_path(binary)] {
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_and_parse() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("abc.txt");
+        fs::write(&file, "abc").unwrap();
+        let digest = sha256_file(&file).unwrap();
+        assert_eq!(
+            digest,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        assert_eq!(
+            parse_checksum(&format!("{}  libduckdb.so\n", digest.to_uppercase())),
+            Some(digest)
+        );
+        assert_eq!(parse_checksum("not-a-checksum"), None);
+        assert_eq!(parse_checksum(""), None);
+    }
+
+    #[test]
+    fn test_verify_detects_missing_and_corrupt_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let binary = temp.path().join("libduckdb_x86_64.so");
+        fs::write(&binary, "complete binary").unwrap();
+
+        assert_eq!(verify(&binary).unwrap(), Verification::Missing);
+        assert!(!is_cached_and_valid(&binary));
+
+        write_checksum(&binary).unwrap();
+        assert_eq!(
+            checksum_path(&binary),
+            temp.path().join("libduckdb_x86_64.so.sha256")
+        );
+        assert!(is_cached_and_valid(&binary));
+
+        // Simulate a truncated download
+        fs::write(&binary, "compl").unwrap();
+        assert!(matches!(
+            verify(&binary).unwrap(),
+            Verification::Mismatch { .. }
+        ));
+        assert!(!is_cached_and_valid(&binary));
+
+        discard(&binary).unwrap();
+        assert!(!binary.exists() && !checksum_path(&binary).exists());
+        discard(&binary).unwrap();
+    }
+}
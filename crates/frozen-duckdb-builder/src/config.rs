@@ -0,0 +1,247 @@
+//! # Build Configuration and Provenance Reports
+//!
+//! [`BuildConfig`] controls how [`crate::ensure_binary_with`] finds the
+//! DuckDB library, and [`BinaryReport`] describes what it found: where the
+//! library came from, how long that took, and its checksum, so build scripts
+//! and diagnostics can log the provenance of the binary they link.
+//!
+//! [`BuildConfig::from_env`] reads the same settings from environment
+//! variables, for build scripts that don't construct a config themselves:
+//!
+//! | Variable | Effect |
+//! |----------|--------|
+//! | `FROZEN_DUCKDB_LINKAGE` | `dynamic` (default) or `static` (default for iOS and Android) |
+//! | `FROZEN_DUCKDB_OFFLINE=1` | Never download or clone; use the cache, `prebuilt/`, or a system install |
+//! | `FROZEN_DUCKDB_MIRROR` | Base URL to download release artifacts from instead of GitHub |
+//! | `FROZEN_DUCKDB_CACHE_DIR` | Cache directory instead of `~/.frozen-duckdb/cache` (see [`crate::cache_dir`]) |
+//! | `FROZEN_DUCKDB_VARIANT` | `full` (default) or `lite` (see [`crate::variant`]) |
+//! | `FROZEN_DUCKDB_BUILD_JOBS` | Parallel jobs of a local DuckDB build; 2 on armv7, otherwise 4 |
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use frozen_duckdb_builder::{ensure_binary_with, BuildConfig};
+//!
+//! let report = ensure_binary_with(BuildConfig {
+//!     offline: true,
+//!     mirror: Some("https://mirror.example.com/frozen-duckdb".to_string()),
+//!     ..Default::default()
+//! })?;
+//! println!("{}", report.summary());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::artifact::ArtifactOs;
+use crate::linkage::Linkage;
+use crate::telemetry::BinarySource;
+use crate::variant::Variant;
+use anyhow::Result;
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Set to `1` to never download release artifacts or clone DuckDB.
+pub const OFFLINE_ENV: &str = "FROZEN_DUCKDB_OFFLINE";
+
+/// Base URL of a mirror of the GitHub release assets.
+pub const MIRROR_ENV: &str = "FROZEN_DUCKDB_MIRROR";
+
+/// Cache directory overriding `~/.frozen-duckdb/cache`.
+pub const CACHE_DIR_ENV: &str = "FROZEN_DUCKDB_CACHE_DIR";
+
+/// Number of parallel jobs when compiling DuckDB locally.
+pub const BUILD_JOBS_ENV: &str = "FROZEN_DUCKDB_BUILD_JOBS";
+
+/// Options for [`crate::ensure_binary_with`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildConfig {
+    /// DuckDB version, e.g. `1.4.0`
+    pub version: String,
+    /// `x86_64`, `arm64`, or `armv7`; detected when `None`
+    pub arch: Option<String>,
+    /// OS the library is for; detected with [`ArtifactOs::for_target`] when `None`
+    pub os: Option<ArtifactOs>,
+    /// Shared library or static archive
+    pub linkage: Linkage,
+    /// Every extension, or the lite subset
+    pub variant: Variant,
+    /// Parallel jobs of a local build; [`default_build_jobs`] when `None`
+    pub jobs: Option<usize>,
+    /// Never touch the network; fail instead of downloading or compiling
+    pub offline: bool,
+    /// Base URL serving `v{version}/{asset}`, replacing GitHub Releases
+    pub mirror: Option<String>,
+    /// Cache directory; chosen as described in [`crate::cache_dir`] when `None`
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl Default for BuildConfig {
+    fn default() -> Self {
+        Self {
+            version: crate::VERSION.to_string(),
+            arch: None,
+            os: None,
+            linkage: Linkage::Dynamic,
+            variant: Variant::Full,
+            jobs: None,
+            offline: false,
+            mirror: None,
+            cache_dir: None,
+        }
+    }
+}
+
+impl BuildConfig {
+    /// Reads the configuration from the environment variables listed in the
+    /// module docs.
+    pub fn from_env() -> Result<Self> {
+        let non_empty = |name: &str| env::var(name).ok().filter(|value| !value.trim().is_empty());
+        Ok(Self {
+            linkage: Linkage::for_target(&env::var("CARGO_CFG_TARGET_OS").unwrap_or_default())?,
+            variant: Variant::from_env()?,
+            jobs: non_empty(BUILD_JOBS_ENV)
+                .map(|value| parse_jobs(&value))
+                .transpose()?,
+            offline: non_empty(OFFLINE_ENV)
+                .is_some_and(|value| matches!(value.trim(), "1" | "true")),
+            mirror: non_empty(MIRROR_ENV),
+            ..Self::default()
+        })
+    }
+}
+
+/// Parses a positive job count.
+fn parse_jobs(value: &str) -> Result<usize> {
+    match value.trim().parse() {
+        Ok(jobs) if jobs > 0 => Ok(jobs),
+        _ => anyhow::bail!(
+            "Invalid {}={:?}: expected a positive number",
+            BUILD_JOBS_ENV,
+            value
+        ),
+    }
+}
+
+/// Parallel jobs of a local DuckDB build on `arch`. Each compiler process
+/// can take over a gigabyte, so 32-bit ARM boards get fewer.
+pub fn default_build_jobs(arch: &str) -> usize {
+    if arch == "armv7" {
+        2
+    } else {
+        4
+    }
+}
+
+/// What [`crate::ensure_binary_with`] found, and how.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryReport {
+    /// Shared library or static archive to link
+    pub library: PathBuf,
+    /// Directory containing `duckdb/duckdb.h`, for `-I` and `DUCKDB_INCLUDE_DIR`
+    pub include_dir: PathBuf,
+    /// DuckDB version provided
+    pub version: String,
+    /// Architecture of the library (`x86_64`, `arm64`, or `armv7`)
+    pub arch: String,
+    /// Linkage the library is for
+    pub linkage: Linkage,
+    /// Extensions the library bundles
+    pub variant: Variant,
+    /// Where the library came from
+    pub source: BinarySource,
+    /// Time spent downloading, if a download was attempted
+    pub download_time: Option<Duration>,
+    /// Total time spent providing the library
+    pub elapsed: Duration,
+    /// SHA-256 of the library, if it is in the frozen cache
+    pub sha256: Option<String>,
+}
+
+impl BinaryReport {
+    /// One-line description for build logs, e.g.
+    /// `DuckDB 1.4.0 (arm64, dynamic) from cache in 3ms, sha256 1f2e3d4c5b6a: /path`.
+    /// Lite libraries say so after the linkage.
+    pub fn summary(&self) -> String {
+        let checksum = self
+            .sha256
+            .as_deref()
+            .map(|digest| format!(", sha256 {}", &digest[..digest.len().min(12)]))
+            .unwrap_or_default();
+        let variant = match self.variant {
+            Variant::Full => "",
+            Variant::Lite => ", lite",
+        };
+        format!(
+            "DuckDB {} ({}, {}{}) from {} in {}ms{}: {}",
+            self.version,
+            self.arch,
+            self.linkage.as_str(),
+            variant,
+            self.source.as_str(),
+            self.elapsed.as_millis(),
+            checksum,
+            self.library.display()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config() {
+        let config = BuildConfig::default();
+        assert_eq!(config.version, crate::VERSION);
+        assert_eq!(config.linkage, Linkage::Dynamic);
+        assert!(!config.offline && config.mirror.is_none() && config.cache_dir.is_none());
+        assert_eq!(config.variant, Variant::Full);
+    }
+
+    #[test]
+    fn test_report_summary() {
+        let report = BinaryReport {
+            library: PathBuf::from("/cache/v1.4.0-arm64/libduckdb_arm64.dylib"),
+            include_dir: PathBuf::from("/cache/v1.4.0-arm64/include"),
+            version: "1.4.0".to_string(),
+            arch: "arm64".to_string(),
+            linkage: Linkage::Dynamic,
+            variant: Variant::Full,
+            source: BinarySource::Cache,
+            download_time: None,
+            elapsed: Duration::from_millis(3),
+            sha256: Some("1f2e3d4c5b6a".repeat(5) + "abcd"),
+        };
+        assert_eq!(
+            report.summary(),
+            "DuckDB 1.4.0 (arm64, dynamic) from cache in 3ms, sha256 1f2e3d4c5b6a: /cache/v1.4.0-arm64/libduckdb_arm64.dylib"
+        );
+
+        let system = BinaryReport {
+            source: BinarySource::System,
+            linkage: Linkage::Static,
+            sha256: None,
+            ..report
+        };
+        assert!(system
+            .summary()
+            .starts_with("DuckDB 1.4.0 (arm64, static) from system in 3ms: "));
+
+        let lite = BinaryReport {
+            arch: "armv7".to_string(),
+            variant: Variant::Lite,
+            ..system
+        };
+        assert!(lite
+            .summary()
+            .starts_with("DuckDB 1.4.0 (armv7, static, lite) from system"));
+    }
+
+    #[test]
+    fn test_build_jobs() {
+        assert_eq!(default_build_jobs("armv7"), 2);
+        assert_eq!(default_build_jobs("x86_64"), 4);
+        assert_eq!(parse_jobs(" 3 ").unwrap(), 3);
+        assert!(parse_jobs("0").is_err() && parse_jobs("all").is_err());
+    }
+}
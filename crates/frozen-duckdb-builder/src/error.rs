@@ -0,0 +1,106 @@
+//! # Build Errors
+//!
+//! [`crate::ensure_binary_with`] and the other `ensure_*` functions return a
+//! [`BuildError`], so callers can tell apart failures that need different
+//! fixes:
+//!
+//! | Variant | Typical fix |
+//! |---------|-------------|
+//! | [`BuildError::UnsupportedArchitecture`] | Set `FROZEN_DUCKDB_ARCH`, or use an x86_64/arm64/armv7 machine |
+//! | [`BuildError::Offline`] | Populate the cache or `prebuilt/`, or unset `FROZEN_DUCKDB_OFFLINE` |
+//! | [`BuildError::DownloadFailed`] | Check the network or the mirror |
+//! | [`BuildError::ChecksumMismatch`] | Retry, or check the mirror serves the right release |
+//! | [`BuildError::CompileFailed`] | Install git, cmake, make, and a C++ compiler |
+//! | [`BuildError::SmokeTestFailed`] | Clear the cache; the library is for the wrong platform or version |
+//!
+//! Everything else, such as I/O errors on the cache, is
+//! [`BuildError::Other`].
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use frozen_duckdb_builder::{ensure_binary, BuildError};
+//!
+//! match ensure_binary() {
+//!     Ok(path) => println!("DuckDB at {}", path.display()),
+//!     Err(BuildError::Offline { .. }) => eprintln!("Run once with network access first"),
+//!     Err(e) => eprintln!("{}", e),
+//! }
+//! ```
+
+use crate::config::OFFLINE_ENV;
+
+/// Why a DuckDB library couldn't be provided.
+#[derive(Debug, thiserror::Error)]
+pub enum BuildError {
+    /// There is no frozen DuckDB build for the architecture
+    #[error("Unsupported architecture: {0} (expected x86_64, arm64, or armv7)")]
+    UnsupportedArchitecture(String),
+    /// Offline mode is on, but the library or headers aren't available locally
+    #[error(
+        "Offline mode ({}=1) does not allow {action}; populate the cache or a prebuilt/ directory first",
+        OFFLINE_ENV
+    )]
+    Offline {
+        /// What would have needed the network
+        action: String,
+    },
+    /// A release asset couldn't be downloaded
+    #[error("Failed to download {url}: {reason}")]
+    DownloadFailed {
+        /// URL of the asset
+        url: String,
+        /// HTTP or network error
+        reason: String,
+    },
+    /// A download doesn't match the checksum published next to it
+    #[error("Downloaded file {url} failed verification (sha256 {actual} != published {expected})")]
+    ChecksumMismatch {
+        /// URL of the asset
+        url: String,
+        /// Published sha256
+        expected: String,
+        /// sha256 of the downloaded bytes
+        actual: String,
+    },
+    /// Compiling DuckDB from source failed
+    #[error("Failed to compile DuckDB locally: {0}")]
+    CompileFailed(String),
+    /// The library was found but can't be loaded or reports the wrong version
+    #[error("DuckDB library failed the post-link smoke test: {0}")]
+    SmokeTestFailed(String),
+    /// Any other failure
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+/// Recovers the [`BuildError`] raised inside the builder, which may be
+/// wrapped in context; other errors become [`BuildError::Other`].
+impl From<anyhow::Error> for BuildError {
+    fn from(error: anyhow::Error) -> Self {
+        error.downcast().unwrap_or_else(BuildError::Other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_from_anyhow_keeps_kind() {
+        let error = Err::<(), _>(anyhow::Error::from(BuildError::UnsupportedArchitecture(
+            "sparc".to_string(),
+        )))
+        .context("Invalid FROZEN_DUCKDB_ARCH")
+        .unwrap_err();
+        assert!(matches!(
+            BuildError::from(error),
+            BuildError::UnsupportedArchitecture(arch) if arch == "sparc"
+        ));
+
+        let other = BuildError::from(anyhow::anyhow!("disk full"));
+        assert!(matches!(other, BuildError::Other(_)));
+        assert_eq!(other.to_string(), "disk full");
+    }
+}
@@ -0,0 +1,355 @@
+//! # DuckDB Headers in the Cache
+//!
+//! Bindgen in `frozen-duckdb-sys` includes `duckdb/duckdb.h`, so every
+//! versioned cache directory keeps the headers in one place:
+//!
+//! ```text
+//! ~/.frozen-duckdb/cache/v1.4.0-arm64/
+//! ├── libduckdb_arm64.dylib
+//! ├── static/libduckdb.a
+//! └── include/duckdb/{duckdb.h, duckdb.hpp}
+//! ```
+//!
+//! The headers come from, in order: the release archive
+//! (`libduckdb_{arch}.tar.zst` or `.tar.gz` with `lib/` and `include/`), a project
+//! `prebuilt/` directory found from any subdirectory of the project, the
+//! DuckDB source tree of a local build, or the official DuckDB release
+//! archive of the same version. Headers left in the cache root by earlier
+//! versions of the builder are moved into place.
+
+use crate::cache_lock::{install_file, install_with};
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Header files installed into the cache; only `duckdb.h` is required.
+pub const HEADERS: [&str; 2] = ["duckdb.h", "duckdb.hpp"];
+
+/// Returns the include directory of a versioned cache directory, the one to
+/// pass to the C compiler (`-I`) and export as `DUCKDB_INCLUDE_DIR`.
+pub fn include_dir(versioned_cache: &Path) -> PathBuf {
+    versioned_cache.join("include")
+}
+
+/// Returns whether `include_dir` contains `duckdb/duckdb.h`.
+pub fn has_headers(include_dir: &Path) -> bool {
+    include_dir.join("duckdb").join(HEADERS[0]).exists()
+}
+
+/// Copies the headers found in `src_dir` into `include_dir/duckdb/`.
+pub fn install_headers(src_dir: &Path, include_dir: &Path) -> Result<()> {
+    let required = src_dir.join(HEADERS[0]);
+    if !required.exists() {
+        anyhow::bail!("{} not found", required.display());
+    }
+    for name in HEADERS {
+        let src = src_dir.join(name);
+        if src.exists() {
+            install_file(&src, &include_dir.join("duckdb").join(name))
+                .with_context(|| format!("Failed to install header {}", name))?;
+        }
+    }
+    info!("Installed DuckDB headers into {}", include_dir.display());
+    Ok(())
+}
+
+/// Installs headers from `versioned_cache` itself, where earlier builder
+/// versions copied them (`duckdb.h` or `include/duckdb.h`). Returns whether
+/// any were found.
+pub fn migrate_legacy_headers(versioned_cache: &Path) -> Result<bool> {
+    for dir in [versioned_cache.to_path_buf(), include_dir(versioned_cache)] {
+        if dir.join(HEADERS[0]).exists() {
+            install_headers(&dir, &include_dir(versioned_cache))?;
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Compression of a release archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveCompression {
+    /// `.tar.zst`, published since zstd support was added
+    Zstd,
+    /// `.tar.gz`
+    Gzip,
+}
+
+impl ArchiveCompression {
+    /// Preferred formats, smallest download first.
+    pub const ALL: [Self; 2] = [Self::Zstd, Self::Gzip];
+
+    /// File extension of the archive, e.g. `tar.zst`.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Zstd => "tar.zst",
+            Self::Gzip => "tar.gz",
+        }
+    }
+
+    fn decoder<'a>(&self, archive: &'a [u8]) -> Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Self::Zstd => Box::new(
+                zstd::Decoder::new(Cursor::new(archive))
+                    .context("Failed to start zstd decompression")?,
+            ),
+            Self::Gzip => Box::new(GzDecoder::new(Cursor::new(archive))),
+        })
+    }
+}
+
+/// Extracts a release archive (`lib/<library>` and `include/duckdb.h[pp]`),
+/// installing the library at `library_dest` and the headers into
+/// `include_dir/duckdb/`.
+///
+/// If `library_sha256` is given, the decompressed library must match it,
+/// otherwise nothing is installed.
+pub fn extract_release_archive(
+    archive: &[u8],
+    compression: ArchiveCompression,
+    library_sha256: Option<&str>,
+    library_dest: &Path,
+    include_dir: &Path,
+) -> Result<()> {
+    let mut library = None;
+    let mut headers = Vec::new();
+
+    let mut tar = tar::Archive::new(compression.decoder(archive)?);
+    for entry in tar.entries().context("Failed to read release archive")? {
+        let mut entry = entry.context("Failed to read release archive entry")?;
+        let path = entry.path()?.into_owned();
+        let mut components = path.iter().filter_map(|c| c.to_str()).filter(|c| *c != ".");
+        let (Some(dir), Some(name), None) =
+            (components.next(), components.next(), components.next())
+        else {
+            continue;
+        };
+
+        let mut content = Vec::new();
+        match (dir, name) {
+            ("lib", name) if name.starts_with("libduckdb") => {
+                entry.read_to_end(&mut content)?;
+                library = Some(content);
+            }
+            ("include", name) if HEADERS.contains(&name) => {
+                entry.read_to_end(&mut content)?;
+                headers.push((name.to_string(), content));
+            }
+            _ => {}
+        }
+    }
+
+    let Some(library) = library else {
+        anyhow::bail!("Release archive contains no lib/libduckdb* file");
+    };
+    if !headers.iter().any(|(name, _)| name == HEADERS[0]) {
+        anyhow::bail!("Release archive contains no include/duckdb.h");
+    }
+    if let Some(expected) = library_sha256 {
+        let actual = format!("{:x}", Sha256::digest(&library));
+        if actual != expected {
+            anyhow::bail!(
+                "Decompressed library failed verification (sha256 {} != published {})",
+                actual,
+                expected
+            );
+        }
+    }
+
+    for (name, content) in &headers {
+        install_with(&include_dir.join("duckdb").join(name), |partial| {
+            fs::write(partial, content).context("Failed to write header")
+        })?;
+    }
+    install_with(library_dest, |partial| {
+        fs::write(partial, &library).context("Failed to write library")
+    })
+}
+
+/// Extracts the headers from an official DuckDB `libduckdb-*.zip` release
+/// into `include_dir/duckdb/`.
+pub fn extract_headers_from_zip(archive: &[u8], include_dir: &Path) -> Result<()> {
+    let mut zip =
+        zip::ZipArchive::new(Cursor::new(archive)).context("Failed to read DuckDB release zip")?;
+    let mut found = false;
+    for name in HEADERS {
+        let Ok(mut file) = zip.by_name(name) else {
+            continue;
+        };
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        install_with(&include_dir.join("duckdb").join(name), |partial| {
+            fs::write(partial, &content).context("Failed to write header")
+        })?;
+        found |= name == HEADERS[0];
+    }
+    if !found {
+        anyhow::bail!("DuckDB release zip contains no duckdb.h");
+    }
+    Ok(())
+}
+
+/// Name of the official DuckDB C/C++ library release for this platform.
+pub fn official_release_asset(arch: &str) -> String {
+    if cfg!(target_os = "macos") {
+        "libduckdb-osx-universal.zip".to_string()
+    } else {
+        let arch = if arch == "arm64" { "arm64" } else { "amd64" };
+        format!("libduckdb-linux-{}.zip", arch)
+    }
+}
+
+/// Walks up from `start` looking for a `prebuilt/` directory, so builds
+/// started from any crate of the project (build scripts run in the crate's
+/// directory) find it.
+pub fn find_prebuilt_dir(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join("prebuilt"))
+        .find(|dir| dir.is_dir())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn release_archive(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(&tarball(files)).unwrap();
+        gzip.finish().unwrap()
+    }
+
+    fn tarball(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_extract_release_archive() {
+        let temp = tempfile::tempdir().unwrap();
+        let archive = release_archive(&[
+            ("./lib/libduckdb_arm64.dylib", "library"),
+            ("./include/duckdb.h", "c header"),
+            ("./include/duckdb.hpp", "c++ header"),
+            ("README.md", "ignored"),
+        ]);
+        let library = temp.path().join("libduckdb_arm64.dylib");
+        let include = include_dir(temp.path());
+
+        extract_release_archive(&archive, ArchiveCompression::Gzip, None, &library, &include)
+            .unwrap();
+        assert_eq!(fs::read_to_string(&library).unwrap(), "library");
+        assert!(has_headers(&include));
+        assert_eq!(
+            fs::read_to_string(include.join("duckdb").join("duckdb.hpp")).unwrap(),
+            "c++ header"
+        );
+    }
+
+    #[test]
+    fn test_extract_release_archive_requires_header() {
+        let temp = tempfile::tempdir().unwrap();
+        let archive = release_archive(&[("lib/libduckdb_x86_64.so", "library")]);
+        let library = temp.path().join("libduckdb_x86_64.so");
+
+        let error = extract_release_archive(
+            &archive,
+            ArchiveCompression::Gzip,
+            None,
+            &library,
+            &include_dir(temp.path()),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("duckdb.h"));
+        // Nothing is installed from an incomplete archive
+        assert!(!library.exists());
+    }
+
+    #[test]
+    fn test_extract_zstd_archive_verifies_library() {
+        let temp = tempfile::tempdir().unwrap();
+        let files = [
+            ("lib/libduckdb_x86_64.so", "library"),
+            ("include/duckdb.h", "c header"),
+        ];
+        let archive = zstd::encode_all(Cursor::new(tarball(&files)), 19).unwrap();
+        let library = temp.path().join("libduckdb_x86_64.so");
+        let include = include_dir(temp.path());
+
+        let wrong = "0".repeat(64);
+        let error = extract_release_archive(
+            &archive,
+            ArchiveCompression::Zstd,
+            Some(&wrong),
+            &library,
+            &include,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("failed verification"));
+        assert!(!library.exists() && !has_headers(&include));
+
+        let expected = format!("{:x}", Sha256::digest(b"library"));
+        extract_release_archive(
+            &archive,
+            ArchiveCompression::Zstd,
+            Some(&expected),
+            &library,
+            &include,
+        )
+        .unwrap();
+        assert_eq!(fs::read_to_string(&library).unwrap(), "library");
+        assert!(has_headers(&include));
+    }
+
+    #[test]
+    fn test_extract_headers_from_zip() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in [("duckdb.h", "c header"), ("libduckdb.so", "library")] {
+            zip.start_file(name, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        let archive = zip.finish().unwrap().into_inner();
+
+        let include = include_dir(temp.path());
+        extract_headers_from_zip(&archive, &include).unwrap();
+        assert!(has_headers(&include));
+        assert!(!include.join("duckdb").join("libduckdb.so").exists());
+    }
+
+    #[test]
+    fn test_migrate_legacy_headers_and_find_prebuilt() {
+        let temp = tempfile::tempdir().unwrap();
+        let cache = temp.path().join("v1.4.0-x86_64");
+        fs::create_dir_all(&cache).unwrap();
+        assert!(!migrate_legacy_headers(&cache).unwrap());
+
+        fs::write(cache.join("duckdb.h"), "legacy").unwrap();
+        assert!(migrate_legacy_headers(&cache).unwrap());
+        assert!(has_headers(&include_dir(&cache)));
+
+        let project = temp.path().join("project");
+        let nested = project.join("crates").join("frozen-duckdb-sys");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(find_prebuilt_dir(&nested), None);
+        fs::create_dir_all(project.join("prebuilt")).unwrap();
+        assert_eq!(find_prebuilt_dir(&nested), Some(project.join("prebuilt")));
+    }
+}
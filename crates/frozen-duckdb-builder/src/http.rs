@@ -0,0 +1,79 @@
+//! # Downloading Release Artifacts
+//!
+//! Every download goes through [`get`], a small wrapper around a blocking
+//! `ureq` agent with rustls, so the builder doesn't pull a full async HTTP
+//! stack and OpenSSL into every downstream build graph.
+//!
+//! | Feature | Effect |
+//! |---------|--------|
+//! | `download` (default) | Fetch artifacts from GitHub Releases or a mirror |
+//! | `no-download` | Never download, even if another crate enables `download` |
+//!
+//! Without downloads the library comes from the cache, `prebuilt/`, a system
+//! install, or a local build of DuckDB. To drop the HTTP client from the
+//! dependency graph entirely, disable default features:
+//!
+//! ```toml
+//! [build-dependencies]
+//! frozen-duckdb-builder = { version = "1.4", default-features = false }
+//! ```
+
+use anyhow::Result;
+
+/// Whether this build of the builder can download artifacts.
+pub fn downloads_enabled() -> bool {
+    cfg!(feature = "download") && !cfg!(feature = "no-download")
+}
+
+/// Downloads `url` into memory, failing on any non-success status.
+#[cfg(all(feature = "download", not(feature = "no-download")))]
+pub fn get(url: &str) -> Result<Vec<u8>> {
+    use anyhow::Context;
+    use std::io::Read;
+    use std::time::Duration;
+
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(Duration::from_secs(30))
+        .timeout_read(Duration::from_secs(300))
+        .build();
+    let response = match agent.get(url).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(code, _)) => anyhow::bail!("HTTP error {} for {}", code, url),
+        Err(e) => return Err(e).with_context(|| format!("Failed to download {}", url)),
+    };
+
+    let mut content = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut content)
+        .context("Failed to read response body")?;
+    Ok(content)
+}
+
+/// Downloads `url` into memory, failing on any non-success status.
+#[cfg(not(all(feature = "download", not(feature = "no-download"))))]
+pub fn get(url: &str) -> Result<Vec<u8>> {
+    anyhow::bail!(
+        "Not downloading {}: frozen-duckdb-builder was built {}",
+        url,
+        if cfg!(feature = "no-download") {
+            "with the `no-download` feature"
+        } else {
+            "without the `download` feature"
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_downloads_fail_without_network() {
+        if downloads_enabled() {
+            return;
+        }
+        let error = get("https://example.invalid/libduckdb.so").unwrap_err();
+        assert!(error.to_string().contains("feature"));
+    }
+}
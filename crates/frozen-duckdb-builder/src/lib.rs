@@ -3,43 +3,348 @@
 //! This crate handles downloading prebuilt mega-libraries from GitHub Releases
 //! or compiling them locally as a fallback. It manages caching in `~/.frozen-duckdb/`
 //! to ensure fast subsequent builds.
+//!
+//! Set `FROZEN_DUCKDB_TELEMETRY=1` to record local build-time metrics
+//! (see [`telemetry`]), and `FROZEN_DUCKDB_LINKAGE=static` to link a static
+//! archive instead of the shared library (see [`linkage`]). With
+//! `FROZEN_DUCKDB_USE_SYSTEM=1`, an installed DuckDB of the same version is
+//! reused instead of downloading (see [`system`]). The shared library is
+//! loaded once after it is located, to catch a binary for the wrong platform
+//! or version early (see [`smoke_test`]). The DuckDB headers are kept in
+//! the cache next to the library (see [`headers`]). Downloads can be
+//! compiled out with the `no-download` feature (see [`http`]). Building
+//! for `aarch64-apple-ios` or `aarch64-linux-android` fetches the phone's
+//! library instead of the host's (see [`mobile`]). Raspberry Pi and other
+//! `armv7` boards can use the smaller `lite` library (see [`variant`]).
+//!
+//! [`ensure_binary_with`] takes a [`BuildConfig`] (version, architecture,
+//! offline mode, mirror, cache directory) and returns a [`BinaryReport`]
+//! with both paths and the provenance of the library (see [`config`]).
+//! Failures are reported as a [`BuildError`] (see [`error`]).
+
+pub mod architecture;
+pub mod artifact;
+pub mod bundle;
+pub mod cache_dir;
+pub mod cache_lock;
+pub mod checksum;
+pub mod config;
+pub mod error;
+pub mod headers;
+pub mod http;
+pub mod linkage;
+pub mod mobile;
+pub mod smoke_test;
+pub mod system;
+pub mod telemetry;
+pub mod variant;
 
 use anyhow::{Context, Result};
+use architecture::detect_architecture;
+use artifact::ArtifactOs;
+use cache_lock::{install_file, install_with, CacheLock};
+use checksum::Verification;
+pub use config::{BinaryReport, BuildConfig};
+pub use error::BuildError;
+use headers::ArchiveCompression;
+use linkage::Linkage;
+use sha2::{Digest, Sha256};
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::{Duration, Instant};
+use telemetry::{BinarySource, BuildEvent};
 use tracing::{debug, info, warn};
+use variant::Variant;
 
 const VERSION: &str = "1.4.0";
 const CACHE_DIR: &str = ".frozen-duckdb";
 const BINARY_NAME: &str = "libduckdb";
+const STATIC_DIR: &str = "static";
+const STATIC_ARCHIVE: &str = "libduckdb.a";
+const SYSTEM_DIR: &str = "system";
 
 /// Ensure the prebuilt DuckDB binary is available
 /// 
 /// This function:
-/// 1. Checks for cached binary in ~/.frozen-duckdb/cache/v1.4.0-{arch}/
-/// 2. If missing, tries to download from GitHub Release
-/// 3. If download fails, compiles locally as fallback
-/// 4. Returns path to the binary
-pub fn ensure_binary() -> Result<PathBuf> {
-    let arch = detect_architecture()?;
-    let cache_dir = get_cache_dir()?;
-    let versioned_cache = cache_dir.join(format!("v{}-{}", VERSION, arch));
-    let binary_path = get_binary_path(&versioned_cache, &arch);
-
-    // Check if we already have a cached binary
-    if binary_path.exists() {
+/// 1. If enabled, reuses a system DuckDB with exactly the same version
+/// 2. Checks for cached binary in ~/.frozen-duckdb/cache/v1.4.0-{arch}/
+/// 3. If missing, tries to download from GitHub Release
+/// 4. If download fails, compiles locally as fallback
+/// 5. Loads the binary and checks that it reports the expected version
+/// 6. Returns path to the binary
+///
+/// When telemetry is enabled, the source of the binary and the time spent
+/// are appended to the local metrics log.
+pub fn ensure_binary() -> Result<PathBuf, BuildError> {
+    ensure_library(Linkage::Dynamic)
+}
+
+/// Ensure the static DuckDB archive (`libduckdb.a`) is available
+///
+/// The archive lives in ~/.frozen-duckdb/cache/v1.4.0-{arch}/static/ so it
+/// can be linked with `rustc-link-lib=static=duckdb` without the shared
+/// library shadowing it. It is found the same way as the shared library:
+/// cache, then `prebuilt/libduckdb-static-{os}-{arch}.a`, then GitHub Release,
+/// then a local `make bundle-library` build of DuckDB.
+pub fn ensure_static_library() -> Result<PathBuf, BuildError> {
+    ensure_library(Linkage::Static)
+}
+
+/// Ensure the DuckDB library for `linkage` is available, returning its path
+pub fn ensure_library(linkage: Linkage) -> Result<PathBuf, BuildError> {
+    let config = BuildConfig {
+        linkage,
+        ..BuildConfig::default()
+    };
+    Ok(ensure_binary_with(config)?.library)
+}
+
+/// Ensure the DuckDB library described by `config` and its headers are
+/// available, reporting where the library came from
+///
+/// Headers are looked up in the same place no matter where the library came
+/// from, so build scripts can always use [`BinaryReport::include_dir`].
+pub fn ensure_binary_with(config: BuildConfig) -> Result<BinaryReport, BuildError> {
+    provide(config).map_err(BuildError::from)
+}
+
+fn provide(config: BuildConfig) -> Result<BinaryReport> {
+    let started = Instant::now();
+    let arch = match &config.arch {
+        Some(arch) => architecture::normalize(arch)
+            .ok_or_else(|| BuildError::UnsupportedArchitecture(arch.clone()))?,
+        None => detect_architecture()?,
+    };
+    let os = match config.os {
+        Some(os) => Some(os),
+        None => ArtifactOs::for_target()?,
+    };
+    if let Some(os) = os {
+        mobile::check_linkage(os, config.linkage)?;
+    }
+    let cache_dir = cache_dir::resolve(config.cache_dir.as_deref())?;
+    // Phone libraries share architectures with the host's, so the OS is in their name
+    let platform = match os.filter(ArtifactOs::is_mobile) {
+        Some(os) => format!("{}-{}", os.as_str(), arch),
+        None => arch.clone(),
+    };
+    let versioned_cache = cache_dir.join(format!(
+        "v{}-{}{}",
+        config.version,
+        platform,
+        config.variant.cache_suffix()
+    ));
+    let target = Target {
+        versioned_cache,
+        arch,
+        os,
+        config: &config,
+    };
+
+    let (path, source, download) = match config.linkage {
+        Linkage::Dynamic => resolve_binary(&target)?,
+        Linkage::Static => resolve_static_archive(&target)?,
+    };
+
+    // System installs are linked together with their headers
+    let include_dir = match source {
+        BinarySource::System => path
+            .parent()
+            .context("System library link has no parent directory")?
+            .to_path_buf(),
+        _ => ensure_headers(&target)?,
+    };
+
+    // A static archive can't be loaded, and a cross-compiled library can't run here
+    if config.linkage == Linkage::Dynamic && smoke_test::can_load_on_host() {
+        smoke_test::validate_library(&path, &config.version)
+            .map_err(|e| BuildError::SmokeTestFailed(format!("{:#}", e)))?;
+    }
+
+    let event = BuildEvent {
+        version: config.version.clone(),
+        arch: target.arch.clone(),
+        source,
+        download,
+        total: started.elapsed(),
+    };
+    // Telemetry must never fail a build
+    if let Err(e) = telemetry::record(&event) {
+        warn!("Failed to record build telemetry: {}", e);
+    }
+
+    Ok(BinaryReport {
+        sha256: checksum::stored_checksum(&path),
+        library: path,
+        include_dir,
+        version: config.version.clone(),
+        arch: target.arch,
+        linkage: config.linkage,
+        variant: config.variant,
+        source,
+        download_time: download,
+        elapsed: event.total,
+    })
+}
+
+/// What is being provided, and where it is cached
+struct Target<'a> {
+    config: &'a BuildConfig,
+    arch: String,
+    /// OS being built for; `None` when no artifacts are published for it
+    os: Option<ArtifactOs>,
+    /// `<cache>/v{version}-{arch}`, or `<cache>/v{version}-{os}-{arch}` for
+    /// phones, with `-lite` appended for the lite variant
+    versioned_cache: PathBuf,
+}
+
+impl Target<'_> {
+    fn version(&self) -> &str {
+        &self.config.version
+    }
+
+    /// Name of a release asset in the configured variant
+    fn asset(&self, name: String) -> String {
+        self.config.variant.asset(&name)
+    }
+
+    /// Parallel jobs of a local build
+    fn build_jobs(&self) -> usize {
+        self.config
+            .jobs
+            .unwrap_or_else(|| config::default_build_jobs(&self.arch))
+    }
+
+    /// OS to download artifacts for, failing on targets without any
+    fn artifact_os(&self) -> Result<ArtifactOs> {
+        match self.os {
+            Some(os) => Ok(os),
+            None => ArtifactOs::current(),
+        }
+    }
+
+    /// Fails for phones, whose libraries can't be built with the host's
+    /// toolchain, naming the asset that would have been needed
+    fn require_local_build(&self) -> Result<()> {
+        let Some(os) = self.os.filter(ArtifactOs::is_mobile) else {
+            return Ok(());
+        };
+        let asset = self.asset(match self.config.linkage {
+            Linkage::Dynamic => artifact::library_asset(os, &self.arch),
+            Linkage::Static => artifact::static_asset(os, &self.arch),
+        });
+        Err(BuildError::CompileFailed(mobile::missing_artifact(os, &asset)).into())
+    }
+
+    /// Download URL of a release asset, from the mirror if one is configured
+    fn release_url(&self, asset: &str) -> String {
+        match &self.config.mirror {
+            Some(mirror) => artifact::mirror_url(mirror, self.version(), asset),
+            None => artifact::release_url(self.version(), asset),
+        }
+    }
+
+    /// Fails in offline mode, naming what would have needed the network
+    fn require_network(&self, action: &str) -> Result<()> {
+        if self.config.offline {
+            return Err(BuildError::Offline {
+                action: action.to_string(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Makes sure `include/duckdb/duckdb.h` exists in the versioned cache,
+/// returning the include directory
+fn ensure_headers(target: &Target) -> Result<PathBuf> {
+    let versioned_cache = target.versioned_cache.as_path();
+    let include_dir = headers::include_dir(versioned_cache);
+    if headers::has_headers(&include_dir) {
+        return Ok(include_dir);
+    }
+
+    let _lock = CacheLock::acquire(versioned_cache)?;
+    if headers::has_headers(&include_dir) || headers::migrate_legacy_headers(versioned_cache)? {
+        return Ok(include_dir);
+    }
+
+    let prebuilt = env::current_dir()
+        .ok()
+        .and_then(|dir| headers::find_prebuilt_dir(&dir));
+    if let Some(prebuilt) = prebuilt.filter(|dir| dir.join(headers::HEADERS[0]).exists()) {
+        headers::install_headers(&prebuilt, &include_dir)?;
+        return Ok(include_dir);
+    }
+
+    target.require_network("downloading the DuckDB headers")?;
+    let url = format!(
+        "https://github.com/duckdb/duckdb/releases/download/v{}/{}",
+        target.version(),
+        headers::official_release_asset(&target.arch)
+    );
+    info!("Fetching DuckDB headers from: {}", url);
+    let archive = download_bytes(&url).context(
+        "No DuckDB headers in the cache or a prebuilt/ directory, and downloading them failed",
+    )?;
+    headers::extract_headers_from_zip(&archive, &include_dir)?;
+    Ok(include_dir)
+}
+
+/// Locates, downloads, or compiles the binary, returning its path, where it
+/// came from, and how long a download attempt took.
+fn resolve_binary(target: &Target) -> Result<(PathBuf, BinarySource, Option<Duration>)> {
+    let arch = target.arch.as_str();
+    let versioned_cache = target.versioned_cache.clone();
+    let binary_path = get_binary_path(&versioned_cache, arch, target.os);
+
+    // Reuse an installed DuckDB of the same version if enabled
+    if system::is_enabled() {
+        match system::find_system_library(target.version()) {
+            Some(found) => {
+                let _lock = CacheLock::acquire(&versioned_cache)?;
+                let path = link_system_library(&found, &versioned_cache.join(SYSTEM_DIR))?;
+                info!(
+                    "Using system DuckDB {}: {}",
+                    found.version,
+                    found.library.display()
+                );
+                return Ok((path, BinarySource::System, None));
+            }
+            None => info!(
+                "No system DuckDB {} found, using the frozen copy",
+                target.version()
+            ),
+        }
+    }
+
+    // Check if we already have a cached binary that passes verification
+    if checksum::is_cached_and_valid(&binary_path) {
         info!("Using cached DuckDB binary: {}", binary_path.display());
-        return Ok(binary_path);
+        return Ok((binary_path, BinarySource::Cache, None));
+    }
+
+    // Only one process populates the cache; others wait, then find it populated
+    let _lock = CacheLock::acquire(&versioned_cache)?;
+    if populated_by_other_build(&binary_path) {
+        info!(
+            "Another build populated the cache: {}",
+            binary_path.display()
+        );
+        return Ok((binary_path, BinarySource::Cache, None));
     }
+    checksum::discard(&binary_path)?;
 
     // Check if prebuilt binary exists in project directory
-    if let Ok(prebuilt_path) = check_prebuilt_binary(&arch) {
+    if let Ok(prebuilt_path) = check_prebuilt_binary(target) {
         info!("Found prebuilt binary, copying to cache: {}", prebuilt_path.display());
         copy_prebuilt_to_cache(&prebuilt_path, &binary_path)?;
+        checksum::write_checksum(&binary_path)?;
         info!("Successfully set up prebuilt binary and headers");
-        return Ok(binary_path);
+        return Ok((binary_path, BinarySource::Prebuilt, None));
     }
 
     info!("No cached binary found at: {}", binary_path.display());
@@ -48,20 +353,22 @@ pub fn ensure_binary() -> Result<PathBuf> {
     // Debug: show what's in the cache directory
     if let Ok(entries) = fs::read_dir(&versioned_cache) {
         info!("Versioned cache directory contents:");
-        for entry in entries {
-            if let Ok(entry) = entry {
-                info!("  {}", entry.path().display());
-            }
+        for entry in entries.flatten() {
+            info!("  {}", entry.path().display());
         }
     }
 
+    target.require_network("downloading or compiling DuckDB")?;
     info!("Attempting to download...");
     
     // Try to download from GitHub Release
-    match download_from_github_release(&versioned_cache, &arch) {
+    let download_started = Instant::now();
+    let download = download_from_github_release(target);
+    let download_time = download_started.elapsed();
+    match download {
         Ok(path) => {
             info!("Successfully downloaded frozen DuckDB binary: {}", path.display());
-            return Ok(path);
+            return Ok((path, BinarySource::Download, Some(download_time)));
         }
         Err(e) => {
             warn!("Failed to download from GitHub Release: {}", e);
@@ -70,45 +377,158 @@ pub fn ensure_binary() -> Result<PathBuf> {
     }
     
     // Fallback to local compilation
-    let path = compile_duckdb_locally(&versioned_cache, &arch)
-        .context("Failed to compile DuckDB locally")?;
+    target.require_local_build()?;
+    let path = compile_duckdb_locally(target)
+        .map_err(|e| BuildError::CompileFailed(format!("{:#}", e)))?;
     
     info!("Successfully compiled DuckDB binary: {}", path.display());
-    Ok(path)
+    Ok((path, BinarySource::Compile, Some(download_time)))
 }
 
-/// Check if prebuilt binary exists in project directory
-fn check_prebuilt_binary(arch: &str) -> Result<PathBuf> {
-    // Try to find the project root by looking for prebuilt directory
-    let current_dir = env::current_dir()
-        .context("Failed to get current directory")?;
+/// Re-checks the cache after waiting for the lock, without logging a
+/// verification failure a second time
+fn populated_by_other_build(path: &Path) -> bool {
+    path.exists() && checksum::verify(path).is_ok_and(|v| v == Verification::Valid)
+}
+
+/// Locates, downloads, or compiles the static archive, like [`resolve_binary`].
+fn resolve_static_archive(target: &Target) -> Result<(PathBuf, BinarySource, Option<Duration>)> {
+    let arch = target.arch.as_str();
+    let static_dir = target.versioned_cache.join(STATIC_DIR);
+    let archive_path = static_dir.join(STATIC_ARCHIVE);
+
+    if checksum::is_cached_and_valid(&archive_path) {
+        info!(
+            "Using cached static DuckDB archive: {}",
+            archive_path.display()
+        );
+        return Ok((archive_path, BinarySource::Cache, None));
+    }
 
-    let prebuilt_dir = current_dir.join("prebuilt");
-    if !prebuilt_dir.exists() {
-        anyhow::bail!("Prebuilt directory not found: {}", prebuilt_dir.display());
+    let _lock = CacheLock::acquire(&static_dir)?;
+    if populated_by_other_build(&archive_path) {
+        info!(
+            "Another build populated the cache: {}",
+            archive_path.display()
+        );
+        return Ok((archive_path, BinarySource::Cache, None));
     }
+    checksum::discard(&archive_path)?;
 
-    let binary_name = format!("libduckdb_{}.dylib", arch);
-    let binary_path = prebuilt_dir.join(&binary_name);
+    let static_asset = target.asset(artifact::static_asset(target.artifact_os()?, arch));
+    let prebuilt_path = find_prebuilt_dir().map(|dir| dir.join(&static_asset));
+    if let Some(prebuilt_path) = prebuilt_path.filter(|path| path.exists()) {
+        info!(
+            "Found prebuilt static archive, copying to cache: {}",
+            prebuilt_path.display()
+        );
+        install_file(&prebuilt_path, &archive_path)
+            .context("Failed to copy prebuilt static archive to cache")?;
+        checksum::write_checksum(&archive_path)?;
+        return Ok((archive_path, BinarySource::Prebuilt, None));
+    }
 
-    if binary_path.exists() {
-        Ok(binary_path)
-    } else {
-        anyhow::bail!("Prebuilt binary not found: {}", binary_path.display());
+    target.require_network("downloading or compiling the static DuckDB archive")?;
+    let url = target.release_url(&static_asset);
+    let download_started = Instant::now();
+    let download = download_file(&url, &archive_path);
+    let download_time = download_started.elapsed();
+    match download {
+        Ok(()) => {
+            info!(
+                "Successfully downloaded static DuckDB archive: {}",
+                archive_path.display()
+            );
+            return Ok((archive_path, BinarySource::Download, Some(download_time)));
+        }
+        Err(e) => {
+            warn!(
+                "Failed to download static archive from GitHub Release: {}",
+                e
+            );
+            info!("Falling back to local compilation...");
+        }
     }
+
+    target.require_local_build()?;
+    compile_static_archive_locally(&archive_path, target).map_err(|e| {
+        BuildError::CompileFailed(format!(
+            "{:#}. Install git, cmake, make, and a C++ compiler, or unset FROZEN_DUCKDB_LINKAGE to link dynamically",
+            e
+        ))
+    })?;
+    checksum::write_checksum(&archive_path)?;
+    Ok((archive_path, BinarySource::Compile, Some(download_time)))
 }
 
-/// Copy prebuilt binary and headers to cache directory
-fn copy_prebuilt_to_cache(prebuilt_path: &Path, cache_path: &Path) -> Result<()> {
-    // Ensure cache directory exists
-    if let Some(parent) = cache_path.parent() {
-        fs::create_dir_all(parent)
-            .context("Failed to create cache directory")?;
+/// Link a system library and its header into `link_dir` using the layout
+/// the sys crate expects (`libduckdb.*` and `duckdb/duckdb.h`)
+///
+/// The links are recreated on every build so an upgraded or removed system
+/// installation is never used stale.
+fn link_system_library(found: &system::SystemLibrary, link_dir: &Path) -> Result<PathBuf> {
+    let Some(include_dir) = &found.include_dir else {
+        anyhow::bail!(
+            "System DuckDB {} has no duckdb.h next to it; install the development headers or set {}=1",
+            found.library.display(),
+            system::FORCE_FROZEN_ENV
+        );
+    };
+
+    let header_dir = link_dir.join("duckdb");
+    fs::create_dir_all(&header_dir).context("Failed to create system library directory")?;
+
+    let library = link_dir.join(found.library.file_name().unwrap_or_default());
+    replace_link(&found.library, &library)?;
+    replace_link(&include_dir.join("duckdb.h"), &header_dir.join("duckdb.h"))?;
+    Ok(library)
+}
+
+fn replace_link(target: &Path, link: &Path) -> Result<()> {
+    if link.symlink_metadata().is_ok() {
+        fs::remove_file(link).with_context(|| format!("Failed to replace {}", link.display()))?;
     }
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(target, link)
+        .with_context(|| format!("Failed to link {}", target.display()))?;
+    #[cfg(not(unix))]
+    fs::copy(target, link).with_context(|| format!("Failed to copy {}", target.display()))?;
+    Ok(())
+}
+
+/// Find the project's `prebuilt/` directory from the current directory or
+/// any parent
+fn find_prebuilt_dir() -> Option<PathBuf> {
+    headers::find_prebuilt_dir(&env::current_dir().ok()?)
+}
+
+/// Check if prebuilt binary exists in project directory
+fn check_prebuilt_binary(target: &Target) -> Result<PathBuf> {
+    let Some(prebuilt_dir) = find_prebuilt_dir() else {
+        anyhow::bail!("Prebuilt directory not found");
+    };
+
+    let (os, arch) = (target.artifact_os()?, target.arch.as_str());
+    let candidates = std::iter::once(target.asset(artifact::library_asset(os, arch)))
+        .chain(legacy_library_asset(target, os));
+    for binary_name in candidates {
+        let binary_path = prebuilt_dir.join(&binary_name);
+        if binary_path.exists() {
+            return Ok(binary_path);
+        }
+    }
+    anyhow::bail!(
+        "Prebuilt binary not found: {}",
+        prebuilt_dir
+            .join(target.asset(artifact::library_asset(os, arch)))
+            .display()
+    );
+}
 
+/// Copy prebuilt binary and headers to cache directory
+fn copy_prebuilt_to_cache(prebuilt_path: &Path, cache_path: &Path) -> Result<()> {
     // Copy the binary
-    fs::copy(prebuilt_path, cache_path)
-        .context("Failed to copy prebuilt binary to cache")?;
+    install_file(prebuilt_path, cache_path).context("Failed to copy prebuilt binary to cache")?;
 
     // Make binary executable on Unix systems
     #[cfg(unix)]
@@ -120,69 +540,30 @@ fn copy_prebuilt_to_cache(prebuilt_path: &Path, cache_path: &Path) -> Result<()>
     }
 
     // Copy headers as well
-    copy_prebuilt_headers(cache_path)?;
+    copy_prebuilt_headers(prebuilt_path, cache_path)?;
 
     info!("Copied prebuilt binary and headers to cache: {}", cache_path.display());
     Ok(())
 }
 
-/// Copy prebuilt headers to cache directory
-fn copy_prebuilt_headers(cache_path: &Path) -> Result<()> {
-    let current_dir = env::current_dir()
-        .context("Failed to get current directory")?;
-
-    let prebuilt_dir = current_dir.join("prebuilt");
-    let headers_dest = cache_path.parent().unwrap();
-
-    // Copy header files directly to cache directory (expected by bindgen)
-    let header_files = ["duckdb.h", "duckdb.hpp"];
-    for header_name in &header_files {
-        let src_path = prebuilt_dir.join(header_name);
-        if src_path.exists() {
-            let dest_path = headers_dest.join(header_name);
-            fs::copy(&src_path, &dest_path)
-                .context("Failed to copy header file")?;
-            info!("Copied header: {}", dest_path.display());
-        }
+/// Copy the headers next to a prebuilt binary into the cache's include directory
+fn copy_prebuilt_headers(prebuilt_path: &Path, cache_path: &Path) -> Result<()> {
+    let (Some(prebuilt_dir), Some(versioned_cache)) = (prebuilt_path.parent(), cache_path.parent())
+    else {
+        return Ok(());
+    };
+    if prebuilt_dir.join(headers::HEADERS[0]).exists() {
+        headers::install_headers(prebuilt_dir, &headers::include_dir(versioned_cache))?;
     }
-
     Ok(())
 }
 
-/// Detect the current system architecture
-fn detect_architecture() -> Result<String> {
-    let output = Command::new("uname")
-        .arg("-m")
-        .output()
-        .context("Failed to run uname command")?;
-    
-    let arch = String::from_utf8(output.stdout)
-        .context("Invalid UTF-8 in uname output")?
-        .trim()
-        .to_string();
-    
-    match arch.as_str() {
-        "x86_64" => Ok("x86_64".to_string()),
-        "arm64" | "aarch64" => Ok("arm64".to_string()),
-        _ => anyhow::bail!("Unsupported architecture: {}", arch),
-    }
-}
-
-/// Get the cache directory (~/.frozen-duckdb)
-fn get_cache_dir() -> Result<PathBuf> {
-    let home = env::var("HOME")
-        .context("HOME environment variable not set")?;
-    
-    let cache_dir = Path::new(&home).join(CACHE_DIR).join("cache");
-    fs::create_dir_all(&cache_dir)
-        .context("Failed to create cache directory")?;
-    
-    Ok(cache_dir)
-}
-
-/// Get the expected binary path for the given architecture
-fn get_binary_path(cache_dir: &Path, arch: &str) -> PathBuf {
-    let extension = if cfg!(target_os = "macos") {
+/// Get the expected binary path for the given architecture and target OS,
+/// falling back to the host's library extension
+fn get_binary_path(cache_dir: &Path, arch: &str, os: Option<ArtifactOs>) -> PathBuf {
+    let extension = if let Some(os) = os {
+        os.library_extension()
+    } else if cfg!(target_os = "macos") {
         "dylib"
     } else if cfg!(target_os = "linux") {
         "so"
@@ -196,33 +577,64 @@ fn get_binary_path(cache_dir: &Path, arch: &str) -> PathBuf {
 }
 
 /// Download prebuilt binary from GitHub Release
-fn download_from_github_release(cache_dir: &Path, arch: &str) -> Result<PathBuf> {
-    let binary_path = get_binary_path(cache_dir, arch);
-    let url = format!(
-        "https://github.com/seanchatmangpt/frozen-duckdb/releases/download/v{}/libduckdb_{}.dylib",
-        VERSION, arch
-    );
-    
-    info!("Downloading from: {}", url);
-    
-    // Create cache directory
-    fs::create_dir_all(cache_dir)
-        .context("Failed to create cache directory")?;
-    
-    // Download the binary
-    let response = reqwest::blocking::get(&url)
-        .context("Failed to download binary from GitHub Release")?;
-    
-    if !response.status().is_success() {
-        anyhow::bail!("HTTP error: {}", response.status());
+///
+/// Release archives (`libduckdb-{os}-{arch}.tar.zst`, then `.tar.gz`) carry
+/// the headers as well and are much smaller than the library. The
+/// decompressed library is checked against the checksum published for the
+/// bare library. Older releases only publish the bare library, which is
+/// downloaded as is (see [`artifact`] for the naming scheme).
+fn download_from_github_release(target: &Target) -> Result<PathBuf> {
+    let (cache_dir, arch) = (target.versioned_cache.as_path(), target.arch.as_str());
+    let binary_path = get_binary_path(cache_dir, arch, target.os);
+    let os = target.artifact_os()?;
+    let library_url = target.release_url(&target.asset(artifact::library_asset(os, arch)));
+
+    for compression in ArchiveCompression::ALL {
+        let archive_url =
+            target.release_url(&target.asset(artifact::archive_asset(os, arch, compression)));
+        let archive = match download_bytes(&archive_url) {
+            Ok(archive) => archive,
+            Err(e) => {
+                debug!("No {} release archive: {}", compression.extension(), e);
+                continue;
+            }
+        };
+        let expected = fetch_published_checksum(&library_url);
+        headers::extract_release_archive(
+            &archive,
+            compression,
+            expected.as_deref(),
+            &binary_path,
+            &headers::include_dir(cache_dir),
+        )?;
+        checksum::write_checksum(&binary_path)?;
+        return finish_download(binary_path);
     }
-    
-    let content = response.bytes()
-        .context("Failed to read response body")?;
-    
-    fs::write(&binary_path, content)
-        .context("Failed to write downloaded binary")?;
-    
+
+    debug!("No release archive, downloading the library alone");
+    let mut result = download_file(&library_url, &binary_path);
+    if let (Err(e), Some(legacy)) = (&result, legacy_library_asset(target, os)) {
+        debug!(
+            "No {} library ({}), trying the legacy name {}",
+            os.as_str(),
+            e,
+            legacy
+        );
+        result = download_file(&target.release_url(&legacy), &binary_path);
+    }
+    result?;
+    finish_download(binary_path)
+}
+
+/// Legacy library name to fall back to; legacy releases had no lite variant
+fn legacy_library_asset(target: &Target, os: ArtifactOs) -> Option<String> {
+    (target.config.variant == Variant::Full)
+        .then(|| artifact::legacy_library_asset(os, &target.arch))
+        .flatten()
+}
+
+/// Mark a downloaded library executable
+fn finish_download(binary_path: PathBuf) -> Result<PathBuf> {
     // Make binary executable on Unix systems
     #[cfg(unix)]
     {
@@ -236,9 +648,122 @@ fn download_from_github_release(cache_dir: &Path, arch: &str) -> Result<PathBuf>
     Ok(binary_path)
 }
 
+/// Download `url` to `dest`, creating its directory
+fn download_file(url: &str, dest: &Path) -> Result<()> {
+    let content = download_bytes(url)?;
+    install_with(dest, |partial| {
+        fs::write(partial, &content).context("Failed to write downloaded binary")
+    })?;
+    checksum::write_checksum(dest)?;
+    Ok(())
+}
+
+/// Download `url` into memory, verifying it against a published checksum
+fn download_bytes(url: &str) -> Result<Vec<u8>> {
+    info!("Downloading from: {}", url);
+
+    let content = http::get(url).map_err(|e| BuildError::DownloadFailed {
+        url: url.to_string(),
+        reason: format!("{:#}", e),
+    })?;
+
+    // Check against the published checksum, if the release has one
+    if let Some(expected) = fetch_published_checksum(url) {
+        let actual = format!("{:x}", Sha256::digest(&content));
+        if actual != expected {
+            return Err(BuildError::ChecksumMismatch {
+                url: url.to_string(),
+                expected,
+                actual,
+            }
+            .into());
+        }
+    }
+
+    Ok(content)
+}
+
+/// Fetch `<url>.sha256` published alongside a release asset
+fn fetch_published_checksum(url: &str) -> Option<String> {
+    let Ok(response) = http::get(&format!("{}.sha256", url)) else {
+        debug!("No published checksum for {}", url);
+        return None;
+    };
+    checksum::parse_checksum(&String::from_utf8_lossy(&response))
+}
+
+/// Build DuckDB's single static archive (`make bundle-library`), which
+/// merges the core library, extensions, and third-party code
+fn compile_static_archive_locally(archive_path: &Path, target: &Target) -> Result<()> {
+    let (version, variant) = (target.version(), target.config.variant);
+    info!(
+        "Compiling static DuckDB archive ({}) locally...",
+        variant.as_str()
+    );
+
+    let temp_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+    let duckdb_dir = temp_dir.path().join("duckdb");
+
+    run_checked(
+        Command::new("git")
+            .args(["clone", "--depth", "1", "--branch"])
+            .arg(format!("v{}", version))
+            .arg("https://github.com/duckdb/duckdb.git")
+            .arg(&duckdb_dir),
+        "clone DuckDB repository",
+    )?;
+    run_checked(
+        Command::new("make")
+            .arg("bundle-library")
+            .env("BUILD_EXTENSIONS", variant.bundle_extensions())
+            .env(
+                "CMAKE_BUILD_PARALLEL_LEVEL",
+                target.build_jobs().to_string(),
+            )
+            .current_dir(&duckdb_dir),
+        "build DuckDB bundle library",
+    )?;
+
+    let built = duckdb_dir
+        .join("build")
+        .join("release")
+        .join("libduckdb_bundle.a");
+    if !built.exists() {
+        anyhow::bail!("make bundle-library did not produce {}", built.display());
+    }
+
+    install_file(&built, archive_path).context("Failed to copy static archive to cache")?;
+    info!(
+        "Compiled static DuckDB archive to: {}",
+        archive_path.display()
+    );
+    Ok(())
+}
+
+/// Run a command, failing with its stderr if it exits unsuccessfully
+fn run_checked(command: &mut Command, action: &str) -> Result<()> {
+    let output = command
+        .output()
+        .with_context(|| format!("Failed to {}", action))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to {}: {}",
+            action,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
 /// Compile DuckDB locally as fallback
-fn compile_duckdb_locally(cache_dir: &Path, arch: &str) -> Result<PathBuf> {
-    info!("Compiling DuckDB locally for {}...", arch);
+fn compile_duckdb_locally(target: &Target) -> Result<PathBuf> {
+    let (cache_dir, arch) = (target.versioned_cache.as_path(), target.arch.as_str());
+    let (version, variant) = (target.version(), target.config.variant);
+    info!(
+        "Compiling DuckDB ({}) locally for {}...",
+        variant.as_str(),
+        arch
+    );
 
     // Create cache directory
     fs::create_dir_all(cache_dir)
@@ -255,48 +780,34 @@ fn compile_duckdb_locally(cache_dir: &Path, arch: &str) -> Result<PathBuf> {
     let duckdb_dir = temp_path.join("duckdb");
 
     Command::new("git")
-        .args(["clone", "--depth", "1", "--branch", "v1.4.0", "https://github.com/duckdb/duckdb.git"])
+        .args(["clone", "--depth", "1", "--branch"])
+        .arg(format!("v{}", version))
+        .arg("https://github.com/duckdb/duckdb.git")
         .arg(&duckdb_dir)
         .current_dir(temp_path)
         .output()
         .context("Failed to clone DuckDB repository")?;
 
-    // Build DuckDB with all features
-    info!("Building DuckDB with all features...");
+    // Build DuckDB with the variant's extensions
+    info!(
+        "Building DuckDB with the {} extensions...",
+        variant.as_str()
+    );
     let build_dir = duckdb_dir.join("build");
     fs::create_dir_all(&build_dir)
         .context("Failed to create build directory")?;
 
-    // Configure with CMake - enable all extensions
+    // Configure with CMake
     Command::new("cmake")
-        .args([
-            "..",
-            "-DCMAKE_BUILD_TYPE=Release",
-            "-DBUILD_EXTENSIONS=ON",
-            "-DBUILD_PARQUET=ON",
-            "-DBUILD_JSON=ON",
-            "-DBUILD_ICU=ON",
-            "-DBUILD_HTTPFS=ON",
-            "-DBUILD_VISUALIZER=ON",
-            "-DBUILD_TPCH=ON",
-            "-DBUILD_TPCDS=ON",
-            "-DBUILD_FTS=ON",
-            "-DBUILD_INET=ON",
-            "-DBUILD_EXCEL=ON",
-            "-DBUILD_SQLSMITH=ON",
-            "-DBUILD_TPCE=ON",
-            "-DBUILD_JEMALLOC=ON",
-            "-DBUILD_AUTOLOAD=ON",
-            "-DBUILD_ARROW=ON",
-            "-DBUILD_POLARS=ON",
-        ])
+        .args(["..", "-DCMAKE_BUILD_TYPE=Release"])
+        .args(variant.cmake_flags())
         .current_dir(&build_dir)
         .output()
         .context("Failed to configure DuckDB with CMake")?;
 
-    // Build with all available cores (use 4 as default)
+    // Fewer jobs on small boards, which run out of memory otherwise
     Command::new("make")
-        .args(["-j4"])
+        .arg(format!("-j{}", target.build_jobs()))
         .current_dir(&build_dir)
         .output()
         .context("Failed to build DuckDB")?;
@@ -306,34 +817,20 @@ fn compile_duckdb_locally(cache_dir: &Path, arch: &str) -> Result<PathBuf> {
         .context("Failed to find built library")?;
 
     // Copy library to cache directory with proper name
-    let binary_path = get_binary_path(cache_dir, arch);
-    fs::copy(&built_lib, &binary_path)
-        .context("Failed to copy built library to cache")?;
+    let binary_path = get_binary_path(cache_dir, arch, target.os);
+    install_file(&built_lib, &binary_path).context("Failed to copy built library to cache")?;
+    checksum::write_checksum(&binary_path)?;
 
     // Also copy header files for FFI bindings generation
-    let headers_dir = cache_dir.join("include");
-    fs::create_dir_all(&headers_dir)?;
-
-    // Copy DuckDB headers
-    let duckdb_headers = [
-        duckdb_dir.join("src").join("include").join("duckdb.h"),
-        duckdb_dir.join("src").join("include").join("duckdb.hpp"),
-    ];
-
-    for header in &duckdb_headers {
-        if header.exists() {
-            let dest = headers_dir.join(header.file_name().unwrap());
-            fs::copy(header, dest)?;
-            info!("Copied header: {}", header.display());
-        }
-    }
+    headers::install_headers(
+        &duckdb_dir.join("src").join("include"),
+        &headers::include_dir(cache_dir),
+    )?;
 
     info!("Compiled DuckDB binary to: {}", binary_path.display());
     Ok(binary_path)
 }
 
-/// Ensure header files are available for FFI bindings
-
 /// Find the built library in the build directory
 fn find_built_library(build_dir: &Path, _arch: &str) -> Result<PathBuf> {
     // Look for the main DuckDB library - check multiple possible locations
@@ -382,12 +879,62 @@ mod tests {
     #[test]
     fn test_detect_architecture() {
         let arch = detect_architecture().unwrap();
-        assert!(arch == "x86_64" || arch == "arm64");
+        assert!(
+            ["x86_64", "arm64", "armv7"].contains(&arch.as_str()),
+            "{}",
+            arch
+        );
     }
     
+    #[test]
+    fn test_offline_mode_never_downloads() {
+        let temp = tempfile::tempdir().unwrap();
+        let config = BuildConfig {
+            arch: Some("x86_64".to_string()),
+            offline: true,
+            cache_dir: Some(temp.path().to_path_buf()),
+            ..BuildConfig::default()
+        };
+
+        let error = ensure_binary_with(config).unwrap_err();
+        assert!(error.to_string().contains("Offline mode"), "{}", error);
+        assert!(matches!(error, BuildError::Offline { .. }), "{:?}", error);
+        assert!(temp.path().join(format!("v{}-x86_64", VERSION)).exists());
+    }
+
+    #[test]
+    fn test_lite_armv7_has_its_own_cache_and_assets() {
+        let temp = tempfile::tempdir().unwrap();
+        let config = BuildConfig {
+            arch: Some("armv7l".to_string()),
+            os: Some(ArtifactOs::Linux),
+            variant: Variant::Lite,
+            offline: true,
+            cache_dir: Some(temp.path().to_path_buf()),
+            ..BuildConfig::default()
+        };
+        let error = ensure_binary_with(config.clone()).unwrap_err();
+        assert!(matches!(error, BuildError::Offline { .. }), "{:?}", error);
+        assert!(temp
+            .path()
+            .join(format!("v{}-armv7-lite", VERSION))
+            .exists());
+
+        let target = Target {
+            config: &config,
+            arch: "armv7".to_string(),
+            os: Some(ArtifactOs::Linux),
+            versioned_cache: temp.path().to_path_buf(),
+        };
+        let library = target.asset(artifact::library_asset(ArtifactOs::Linux, "armv7"));
+        assert_eq!(library, "libduckdb-lite-linux-armv7.so");
+        assert_eq!(legacy_library_asset(&target, ArtifactOs::MacOs), None);
+        assert_eq!(target.build_jobs(), 2);
+    }
+
     #[test]
     fn test_get_cache_dir() {
-        let cache_dir = get_cache_dir().unwrap();
+        let cache_dir = cache_dir::resolve(None).unwrap();
         assert!(cache_dir.to_string_lossy().contains(CACHE_DIR));
     }
     
@@ -395,12 +942,90 @@ mod tests {
     fn test_get_binary_path() {
         let cache_dir = Path::new("/tmp/test");
         let arch = "x86_64";
-        let path = get_binary_path(cache_dir, arch);
+        let path = get_binary_path(cache_dir, arch, ArtifactOs::current().ok());
         
         if cfg!(target_os = "macos") {
             assert!(path.to_string_lossy().ends_with("libduckdb_x86_64.dylib"));
         } else if cfg!(target_os = "linux") {
             assert!(path.to_string_lossy().ends_with("libduckdb_x86_64.so"));
         }
+
+        let android = get_binary_path(cache_dir, "arm64", Some(ArtifactOs::Android));
+        assert!(android.to_string_lossy().ends_with("libduckdb_arm64.so"));
+    }
+
+    #[test]
+    fn test_mobile_targets_are_cached_apart_and_never_compiled() {
+        let temp = tempfile::tempdir().unwrap();
+        let config = BuildConfig {
+            arch: Some("aarch64".to_string()),
+            os: Some(ArtifactOs::Ios),
+            linkage: Linkage::Static,
+            offline: true,
+            cache_dir: Some(temp.path().to_path_buf()),
+            ..BuildConfig::default()
+        };
+        let error = ensure_binary_with(config.clone()).unwrap_err();
+        assert!(matches!(error, BuildError::Offline { .. }), "{:?}", error);
+        assert!(temp
+            .path()
+            .join(format!("v{}-ios-arm64", VERSION))
+            .join(STATIC_DIR)
+            .exists());
+
+        let dynamic = BuildConfig {
+            linkage: Linkage::Dynamic,
+            ..config
+        };
+        let error = ensure_binary_with(dynamic).unwrap_err().to_string();
+        assert!(error.contains("iOS apps can't load"), "{}", error);
+
+        let target = Target {
+            config: &BuildConfig::default(),
+            arch: "arm64".to_string(),
+            os: Some(ArtifactOs::Android),
+            versioned_cache: temp.path().to_path_buf(),
+        };
+        let error = target.require_local_build().unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("libduckdb-android-arm64.so in prebuilt/"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_link_system_library() {
+        let temp = tempfile::tempdir().unwrap();
+        let prefix = temp.path().join("prefix");
+        fs::create_dir_all(prefix.join("lib")).unwrap();
+        fs::create_dir_all(prefix.join("include")).unwrap();
+        fs::write(prefix.join("lib").join("libduckdb.so"), "lib").unwrap();
+        fs::write(prefix.join("include").join("duckdb.h"), "header").unwrap();
+
+        let found = system::SystemLibrary {
+            library: prefix.join("lib").join("libduckdb.so"),
+            include_dir: Some(prefix.join("include")),
+            version: VERSION.to_string(),
+        };
+        let link_dir = temp.path().join(SYSTEM_DIR);
+        let library = link_system_library(&found, &link_dir).unwrap();
+        // Linking twice replaces the existing links
+        link_system_library(&found, &link_dir).unwrap();
+
+        assert_eq!(fs::read_to_string(&library).unwrap(), "lib");
+        assert_eq!(
+            fs::read_to_string(link_dir.join("duckdb").join("duckdb.h")).unwrap(),
+            "header"
+        );
+
+        let headerless = system::SystemLibrary {
+            include_dir: None,
+            ..found
+        };
+        assert!(link_system_library(&headerless, &link_dir).is_err());
     }
 }
@@ -0,0 +1,163 @@
+//! # Static and Dynamic Linkage
+//!
+//! By default the DuckDB library is linked dynamically. Setting
+//! `FROZEN_DUCKDB_LINKAGE=static` switches to a static archive
+//! (`libduckdb.a`) so the final binary has no DuckDB shared library to
+//! ship. DuckDB is C++, so a static link also needs the platform's C++
+//! runtime and a few system libraries, which [`static_link_libs`] provides
+//! per target.
+//!
+//! ## Supported targets
+//!
+//! | Target | Extra libraries |
+//! |--------|-----------------|
+//! | macOS / iOS | `c++` |
+//! | Linux (glibc) | `stdc++`, `pthread`, `dl`, `m` |
+//! | Linux (musl) | static `stdc++`, `pthread`, `dl`, `m` |
+//! | FreeBSD | `c++`, `pthread`, `m` |
+//! | Android | `c++_static`, `c++abi`, `dl`, `m`, `log` |
+//!
+//! iOS and Android builds link statically unless [`LINKAGE_ENV`] is set
+//! (see [`Linkage::for_target`] and [`crate::mobile`]).
+//!
+//! Other targets (notably Windows) fail with a diagnostic explaining how to
+//! fall back to dynamic linking.
+
+use anyhow::Result;
+use std::env;
+
+/// Environment variable selecting the linkage (`dynamic` or `static`).
+pub const LINKAGE_ENV: &str = "FROZEN_DUCKDB_LINKAGE";
+
+/// How DuckDB is linked into the final binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Linkage {
+    /// Link the shared library (`libduckdb.dylib` / `libduckdb.so`)
+    #[default]
+    Dynamic,
+    /// Link the static archive (`libduckdb.a`)
+    Static,
+}
+
+impl Linkage {
+    /// Reads [`LINKAGE_ENV`]; unset means [`Linkage::Dynamic`].
+    pub fn from_env() -> Result<Self> {
+        match env::var(LINKAGE_ENV) {
+            Ok(value) => Self::parse(&value),
+            Err(_) => Ok(Self::Dynamic),
+        }
+    }
+
+    /// Reads [`LINKAGE_ENV`]; unset means the default for `target_os`
+    /// (`CARGO_CFG_TARGET_OS`), which is [`Linkage::Static`] for iOS and
+    /// Android and [`Linkage::Dynamic`] everywhere else.
+    pub fn for_target(target_os: &str) -> Result<Self> {
+        match env::var(LINKAGE_ENV) {
+            Ok(value) => Self::parse(&value),
+            Err(_) if matches!(target_os, "ios" | "android") => Ok(Self::Static),
+            Err(_) => Ok(Self::Dynamic),
+        }
+    }
+
+    /// Parses `dynamic` or `static`.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "" | "dynamic" | "dylib" => Ok(Self::Dynamic),
+            "static" => Ok(Self::Static),
+            other => anyhow::bail!(
+                "Invalid {}={:?}: expected \"dynamic\" or \"static\"",
+                LINKAGE_ENV,
+                other
+            ),
+        }
+    }
+
+    /// Name accepted by [`Linkage::parse`], `dynamic` or `static`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Dynamic => "dynamic",
+            Self::Static => "static",
+        }
+    }
+
+    /// Library kind for `cargo:rustc-link-lib`.
+    pub fn link_kind(&self) -> &'static str {
+        match self {
+            Self::Dynamic => "dylib",
+            Self::Static => "static",
+        }
+    }
+}
+
+/// Returns the extra `cargo:rustc-link-lib` values a static DuckDB link
+/// needs on the given target (`CARGO_CFG_TARGET_OS` / `CARGO_CFG_TARGET_ENV`).
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb_builder::linkage::static_link_libs;
+///
+/// assert_eq!(static_link_libs("macos", "").unwrap(), vec!["c++"]);
+/// assert!(static_link_libs("windows", "msvc").is_err());
+/// ```
+pub fn static_link_libs(target_os: &str, target_env: &str) -> Result<Vec<&'static str>> {
+    match (target_os, target_env) {
+        ("macos" | "ios", _) => Ok(vec!["c++"]),
+        ("linux", "musl") => Ok(vec!["static=stdc++", "pthread", "dl", "m"]),
+        ("linux", _) => Ok(vec!["stdc++", "pthread", "dl", "m"]),
+        ("freebsd", _) => Ok(vec!["c++", "pthread", "m"]),
+        // Bionic has no separate libpthread; DuckDB's own Android build adds liblog
+        ("android", _) => Ok(vec!["c++_static", "c++abi", "dl", "m", "log"]),
+        ("windows", _) => anyhow::bail!(
+            "Static DuckDB linking is not supported on Windows targets. \
+             Unset {} to link dynamically.",
+            LINKAGE_ENV
+        ),
+        _ => anyhow::bail!(
+            "Static DuckDB linking is not supported on target os {:?} (env {:?}). \
+             Supported: macOS, iOS, Linux (glibc/musl), FreeBSD, Android. \
+             Unset {} to link dynamically.",
+            target_os,
+            target_env,
+            LINKAGE_ENV
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_linkage() {
+        assert_eq!(Linkage::parse("static").unwrap(), Linkage::Static);
+        assert_eq!(Linkage::parse(" Dynamic ").unwrap(), Linkage::Dynamic);
+        assert_eq!(Linkage::parse("").unwrap(), Linkage::Dynamic);
+        assert!(Linkage::parse("shared-ish").is_err());
+        assert_eq!(Linkage::Static.link_kind(), "static");
+    }
+
+    #[test]
+    fn test_static_link_libs_per_target() {
+        assert_eq!(
+            static_link_libs("linux", "gnu").unwrap(),
+            vec!["stdc++", "pthread", "dl", "m"]
+        );
+        assert_eq!(
+            static_link_libs("linux", "musl").unwrap()[0],
+            "static=stdc++"
+        );
+        assert_eq!(
+            static_link_libs("freebsd", "").unwrap(),
+            vec!["c++", "pthread", "m"]
+        );
+        assert_eq!(static_link_libs("ios", "").unwrap(), vec!["c++"]);
+        assert!(!static_link_libs("android", "")
+            .unwrap()
+            .contains(&"pthread"));
+
+        let err = static_link_libs("windows", "msvc").unwrap_err().to_string();
+        assert!(err.contains("Windows") && err.contains(LINKAGE_ENV));
+        assert!(static_link_libs("wasi", "p1").is_err());
+    }
+}
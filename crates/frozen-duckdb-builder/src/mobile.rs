@@ -0,0 +1,110 @@
+//! # iOS and Android Builds
+//!
+//! Cross-compiling to a phone fetches DuckDB built for the phone: the OS is
+//! read from `CARGO_CFG_TARGET_OS` (see [`ArtifactOs::for_target`]), and the
+//! library is cached apart from the host's in `v{version}-{os}-{arch}`.
+//! Phones always link statically unless `FROZEN_DUCKDB_LINKAGE` says
+//! otherwise, so the app ships one library containing DuckDB.
+//!
+//! | Rust target | Tool | Linkage | What the app project needs |
+//! |-------------|------|---------|----------------------------|
+//! | `aarch64-apple-ios` | Xcode | static only | `-lc++` in *Other Linker Flags* |
+//! | `aarch64-linux-android` | cargo-ndk | static (default) | Nothing; `libc++` is linked into the `.so` |
+//! | `aarch64-linux-android` | cargo-ndk | dynamic | `libduckdb.so` and `libc++_shared.so` in `jniLibs/arm64-v8a/` |
+//!
+//! DuckDB is never compiled locally for a phone, since that needs the
+//! platform's CMake toolchain rather than the host's. Without network
+//! access, put the release asset in `prebuilt/` or serve it from a mirror.
+//! The iOS simulator has no published artifacts.
+//!
+//! The smoke test is skipped, because a phone library can't be loaded on
+//! the build host.
+//!
+//! ## Usage Examples
+//!
+//! ```bash
+//! # Android: builds target/aarch64-linux-android/release/examples/libmobile_ffi.so
+//! cargo ndk -t arm64-v8a build -p frozen-duckdb --example mobile_ffi --release
+//!
+//! # iOS: builds target/aarch64-apple-ios/release/examples/libmobile_ffi.a for Xcode
+//! cargo build -p frozen-duckdb --example mobile_ffi --target aarch64-apple-ios --release
+//! ```
+//!
+//! # Examples
+//!
+//! ```rust
+//! use frozen_duckdb_builder::artifact::ArtifactOs;
+//! use frozen_duckdb_builder::linkage::Linkage;
+//! use frozen_duckdb_builder::mobile::{app_link_flags, check_linkage};
+//!
+//! assert_eq!(app_link_flags(ArtifactOs::Ios, Linkage::Static), ["-lc++"]);
+//! assert!(check_linkage(ArtifactOs::Ios, Linkage::Dynamic).is_err());
+//! ```
+
+use crate::artifact::ArtifactOs;
+use crate::linkage::{Linkage, LINKAGE_ENV};
+use anyhow::Result;
+
+/// Fails for linkages a phone app can't ship: iOS apps can't load a bare
+/// `libduckdb.dylib` from outside a framework.
+pub fn check_linkage(os: ArtifactOs, linkage: Linkage) -> Result<()> {
+    if os == ArtifactOs::Ios && linkage == Linkage::Dynamic {
+        anyhow::bail!(
+            "iOS apps can't load libduckdb.dylib; unset {} or set it to static",
+            LINKAGE_ENV
+        );
+    }
+    Ok(())
+}
+
+/// Flags the app project must pass to its own linker, which Cargo can't
+/// add to a `staticlib`.
+pub fn app_link_flags(os: ArtifactOs, linkage: Linkage) -> &'static [&'static str] {
+    match (os, linkage) {
+        (ArtifactOs::Ios, Linkage::Static) => &["-lc++"],
+        _ => &[],
+    }
+}
+
+/// Libraries the app must package next to the Rust library.
+pub fn packaged_libraries(os: ArtifactOs, linkage: Linkage) -> &'static [&'static str] {
+    match (os, linkage) {
+        (ArtifactOs::Android, Linkage::Dynamic) => &["libduckdb.so", "libc++_shared.so"],
+        _ => &[],
+    }
+}
+
+/// Error for a phone library that isn't in the cache, `prebuilt/`, or the
+/// release, naming the asset to provide.
+pub fn missing_artifact(os: ArtifactOs, asset: &str) -> String {
+    format!(
+        "DuckDB can't be compiled locally for {}; put {} in prebuilt/ or on a mirror",
+        os.as_str(),
+        asset
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ios_links_statically() {
+        assert!(check_linkage(ArtifactOs::Ios, Linkage::Static).is_ok());
+        let err = check_linkage(ArtifactOs::Ios, Linkage::Dynamic).unwrap_err();
+        assert!(err.to_string().contains(LINKAGE_ENV), "{}", err);
+        assert!(check_linkage(ArtifactOs::Android, Linkage::Dynamic).is_ok());
+        assert!(packaged_libraries(ArtifactOs::Ios, Linkage::Static).is_empty());
+    }
+
+    #[test]
+    fn test_android_packaging() {
+        assert!(app_link_flags(ArtifactOs::Android, Linkage::Static).is_empty());
+        assert!(packaged_libraries(ArtifactOs::Android, Linkage::Static).is_empty());
+        assert_eq!(
+            packaged_libraries(ArtifactOs::Android, Linkage::Dynamic),
+            ["libduckdb.so", "libc++_shared.so"]
+        );
+        assert!(app_link_flags(ArtifactOs::Linux, Linkage::Static).is_empty());
+    }
+}
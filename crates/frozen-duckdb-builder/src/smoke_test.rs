@@ -0,0 +1,149 @@
+//! # Post-Link Smoke Test
+//!
+//! A cached file can exist and pass its checksum and still be unusable, for
+//! example a macOS dylib copied by hand onto a Linux machine, or an `arm64`
+//! build in the `x86_64` cache. Such a file otherwise surfaces much later as
+//! a confusing linker error or a crash at runtime.
+//!
+//! After the shared library is located, it is loaded with `dlopen`, the
+//! `duckdb_open` and `duckdb_library_version` symbols are resolved, and the
+//! version DuckDB reports is compared to the frozen version.
+//!
+//! The check is skipped when cross-compiling, since a library built for the
+//! target can't be loaded on the build host.
+
+use crate::system::parse_version;
+use anyhow::{bail, Result};
+use libloading::{Library, Symbol};
+use std::env;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::path::Path;
+use tracing::{debug, info};
+
+type LibraryVersionFn = unsafe extern "C" fn() -> *const c_char;
+
+/// Returns whether the library can be loaded on this host, i.e. whether
+/// the build isn't a cross-compilation (`HOST` and `TARGET` are set by Cargo
+/// for build scripts).
+pub fn can_load_on_host() -> bool {
+    match (env::var("HOST"), env::var("TARGET")) {
+        (Ok(host), Ok(target)) => host == target,
+        _ => true,
+    }
+}
+
+/// Loads `library`, resolves the DuckDB C API, and checks that it reports
+/// `expected_version`. Returns the reported version.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use frozen_duckdb_builder::smoke_test::validate_library;
+///
+/// let version = validate_library(
+///     "/home/me/.frozen-duckdb/cache/v1.4.0-x86_64/libduckdb.so".as_ref(),
+///     "1.4.0",
+/// )?;
+/// assert_eq!(version, "v1.4.0");
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn validate_library(library: &Path, expected_version: &str) -> Result<String> {
+    let remedy = format!(
+        "Delete {} and rebuild to fetch the correct binary for this platform",
+        library.display()
+    );
+
+    // SAFETY: loading DuckDB runs no initialization code with preconditions
+    let lib = match unsafe { Library::new(library) } {
+        Ok(lib) => lib,
+        Err(e) => bail!(
+            "DuckDB library {} can't be loaded on this machine (wrong OS or architecture?): {}. {}",
+            library.display(),
+            e,
+            remedy
+        ),
+    };
+
+    // SAFETY: the symbols are only resolved here, and called with their C API signatures
+    unsafe {
+        if let Err(e) = lib.get::<*const ()>(b"duckdb_open\0") {
+            bail!(
+                "{} is not a DuckDB library (missing duckdb_open: {}). {}",
+                library.display(),
+                e,
+                remedy
+            );
+        }
+        let library_version: Symbol<LibraryVersionFn> = match lib.get(b"duckdb_library_version\0") {
+            Ok(symbol) => symbol,
+            Err(e) => bail!(
+                "{} is not a DuckDB library (missing duckdb_library_version: {}). {}",
+                library.display(),
+                e,
+                remedy
+            ),
+        };
+
+        let raw = library_version();
+        if raw.is_null() {
+            bail!("{} did not report a version. {}", library.display(), remedy);
+        }
+        let reported = CStr::from_ptr(raw).to_string_lossy().into_owned();
+        debug!("{} reports DuckDB {}", library.display(), reported);
+
+        check_version(&reported, expected_version)
+            .map_err(|e| anyhow::anyhow!("{}. {}", e, remedy))?;
+        info!(
+            "Smoke test passed: {} is DuckDB {}",
+            library.display(),
+            reported
+        );
+        Ok(reported)
+    }
+}
+
+/// Compares a version reported by `duckdb_library_version` (e.g. `v1.4.0`)
+/// to the expected `major.minor.patch` version.
+pub fn check_version(reported: &str, expected: &str) -> Result<()> {
+    match parse_version(reported) {
+        Some(version) if version == expected => Ok(()),
+        Some(version) => bail!(
+            "DuckDB library is version {}, expected {}",
+            version,
+            expected
+        ),
+        None => bail!(
+            "DuckDB library reported an unrecognized version {:?}, expected {}",
+            reported,
+            expected
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_check_version() {
+        assert!(check_version("v1.4.0", "1.4.0").is_ok());
+        assert!(check_version("1.4.0", "1.4.0").is_ok());
+
+        let mismatch = check_version("v1.3.2", "1.4.0").unwrap_err().to_string();
+        assert!(mismatch.contains("1.3.2") && mismatch.contains("1.4.0"));
+        assert!(check_version("v1.4.0-dev42", "1.4.0").is_err());
+    }
+
+    #[test]
+    fn test_unloadable_library_fails_with_remedy() {
+        let temp = tempfile::tempdir().unwrap();
+        let library = temp.path().join("libduckdb.so");
+        fs::write(&library, "not an ELF or Mach-O file").unwrap();
+
+        let error = validate_library(&library, "1.4.0").unwrap_err().to_string();
+        assert!(error.contains("can't be loaded"));
+        assert!(error.contains(&format!("Delete {}", library.display())));
+    }
+}
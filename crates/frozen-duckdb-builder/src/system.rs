@@ -0,0 +1,238 @@
+//! # Reusing a System DuckDB Installation
+//!
+//! If DuckDB is already installed (Homebrew, apt, a manual install), the
+//! build can link that library instead of downloading the frozen copy.
+//! This is opt-in with `FROZEN_DUCKDB_USE_SYSTEM=1`, and only a library
+//! whose version matches the frozen version exactly is accepted.
+//! `FROZEN_DUCKDB_FORCE_FROZEN=1` always wins and forces the frozen copy,
+//! e.g. in CI where the environment may enable system reuse globally.
+//!
+//! ## Discovery
+//!
+//! 1. `pkg-config duckdb` (library directory, include directory, version)
+//! 2. Common install prefixes: `/opt/homebrew`, `/usr/local`, `/usr`, and
+//!    the multiarch `lib` directories
+//!
+//! A library's version comes from pkg-config, from a versioned install path
+//! such as Homebrew's `Cellar/duckdb/1.4.0/`, or from the `duckdb` CLI
+//! installed alongside it. A library whose version can't be determined is
+//! never reused.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{debug, info};
+
+/// Set to `1` to reuse a system DuckDB with a matching version.
+pub const USE_SYSTEM_ENV: &str = "FROZEN_DUCKDB_USE_SYSTEM";
+
+/// Set to `1` to always use the frozen copy, overriding [`USE_SYSTEM_ENV`].
+pub const FORCE_FROZEN_ENV: &str = "FROZEN_DUCKDB_FORCE_FROZEN";
+
+/// Library directories probed when pkg-config doesn't know DuckDB.
+const SEARCH_DIRS: &[&str] = &[
+    "/opt/homebrew/lib",
+    "/usr/local/lib",
+    "/usr/lib",
+    "/usr/lib64",
+    "/usr/lib/x86_64-linux-gnu",
+    "/usr/lib/aarch64-linux-gnu",
+];
+
+/// A DuckDB library found on the system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemLibrary {
+    /// Shared library path
+    pub library: PathBuf,
+    /// Directory containing `duckdb.h`, if found
+    pub include_dir: Option<PathBuf>,
+    /// Detected DuckDB version, e.g. `1.4.0`
+    pub version: String,
+}
+
+/// Returns whether system reuse is enabled and not overridden.
+pub fn is_enabled() -> bool {
+    flag(USE_SYSTEM_ENV) && !flag(FORCE_FROZEN_ENV)
+}
+
+fn flag(name: &str) -> bool {
+    env::var(name).is_ok_and(|value| matches!(value.trim(), "1" | "true"))
+}
+
+/// Looks for an installed DuckDB whose version is exactly `version`.
+pub fn find_system_library(version: &str) -> Option<SystemLibrary> {
+    let library_name = shared_library_name();
+
+    if let Some(found) = from_pkg_config(library_name) {
+        if found.version == version {
+            return Some(found);
+        }
+        info!(
+            "Ignoring system DuckDB {} (version {}, need {})",
+            found.library.display(),
+            found.version,
+            version
+        );
+    }
+
+    for dir in SEARCH_DIRS {
+        let library = Path::new(dir).join(library_name);
+        if !library.exists() {
+            continue;
+        }
+        match detect_version(&library) {
+            Some(found) if found == version => {
+                return Some(SystemLibrary {
+                    include_dir: include_dir_for(&library),
+                    library,
+                    version: found,
+                });
+            }
+            Some(found) => info!(
+                "Ignoring system DuckDB {} (version {}, need {})",
+                library.display(),
+                found,
+                version
+            ),
+            None => info!(
+                "Ignoring system DuckDB {} (could not determine its version)",
+                library.display()
+            ),
+        }
+    }
+    None
+}
+
+fn shared_library_name() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "libduckdb.dylib"
+    } else {
+        "libduckdb.so"
+    }
+}
+
+fn from_pkg_config(library_name: &str) -> Option<SystemLibrary> {
+    let query = |args: &[&str]| -> Option<String> {
+        let output = Command::new("pkg-config")
+            .args(args)
+            .arg("duckdb")
+            .output()
+            .ok()?;
+        let value = String::from_utf8(output.stdout).ok()?.trim().to_string();
+        (output.status.success() && !value.is_empty()).then_some(value)
+    };
+
+    let version = parse_version(&query(&["--modversion"])?)?;
+    let library = Path::new(&query(&["--variable=libdir"])?).join(library_name);
+    if !library.exists() {
+        debug!(
+            "pkg-config points to a missing library: {}",
+            library.display()
+        );
+        return None;
+    }
+    let include_dir = query(&["--variable=includedir"])
+        .map(PathBuf::from)
+        .filter(|dir| dir.join("duckdb.h").exists())
+        .or_else(|| include_dir_for(&library));
+
+    Some(SystemLibrary {
+        library,
+        include_dir,
+        version,
+    })
+}
+
+/// Determines the version of an installed library from its resolved path
+/// or the `duckdb` CLI in the sibling `bin` directory.
+fn detect_version(library: &Path) -> Option<String> {
+    let resolved = library.canonicalize().ok()?;
+    if let Some(version) = version_from_path(&resolved) {
+        return Some(version);
+    }
+
+    let cli = library.parent()?.parent()?.join("bin").join("duckdb");
+    let output = Command::new(cli).arg("--version").output().ok()?;
+    parse_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Extracts the version from a versioned install path, e.g.
+/// `/opt/homebrew/Cellar/duckdb/1.4.0/lib/libduckdb.dylib` or
+/// `/usr/lib/libduckdb.so.1.4.0`.
+pub fn version_from_path(path: &Path) -> Option<String> {
+    let components: Vec<&str> = path.iter().filter_map(|c| c.to_str()).collect();
+    let from_dir = components
+        .windows(2)
+        .find(|pair| pair[0] == "duckdb")
+        .and_then(|pair| parse_version(pair[1]));
+    from_dir.or_else(|| {
+        let file_name = path.file_name()?.to_str()?;
+        let suffix = file_name.split_once(".so.")?.1;
+        parse_version(suffix)
+    })
+}
+
+/// Parses a `major.minor.patch` version from text such as
+/// `v1.4.0 (Andium) b8a06e4a22` or `1.4.0`.
+pub fn parse_version(text: &str) -> Option<String> {
+    let token = text.split_whitespace().next()?;
+    let token = token.strip_prefix('v').unwrap_or(token);
+    let parts: Vec<&str> = token.split('.').collect();
+    let valid = parts.len() == 3
+        && parts
+            .iter()
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+    valid.then(|| token.to_string())
+}
+
+fn include_dir_for(library: &Path) -> Option<PathBuf> {
+    let include = library.parent()?.parent()?.join("include");
+    include.join("duckdb.h").exists().then_some(include)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(
+            parse_version("v1.4.0 (Andium) b8a06e4a22").as_deref(),
+            Some("1.4.0")
+        );
+        assert_eq!(parse_version("1.4.0\n").as_deref(), Some("1.4.0"));
+        assert_eq!(parse_version("1.4").as_deref(), None);
+        assert_eq!(parse_version("v1.4.0-dev123").as_deref(), None);
+        assert_eq!(parse_version("").as_deref(), None);
+    }
+
+    #[test]
+    fn test_version_from_path() {
+        assert_eq!(
+            version_from_path(Path::new(
+                "/opt/homebrew/Cellar/duckdb/1.4.0/lib/libduckdb.dylib"
+            ))
+            .as_deref(),
+            Some("1.4.0")
+        );
+        assert_eq!(
+            version_from_path(Path::new("/usr/lib/x86_64-linux-gnu/libduckdb.so.1.3.2")).as_deref(),
+            Some("1.3.2")
+        );
+        assert_eq!(version_from_path(Path::new("/usr/lib/libduckdb.so")), None);
+    }
+
+    #[test]
+    fn test_include_dir_for() {
+        let temp = tempfile::tempdir().unwrap();
+        let lib_dir = temp.path().join("lib");
+        let include = temp.path().join("include");
+        std::fs::create_dir_all(&lib_dir).unwrap();
+        std::fs::create_dir_all(&include).unwrap();
+        let library = lib_dir.join("libduckdb.so");
+
+        assert_eq!(include_dir_for(&library), None);
+        std::fs::write(include.join("duckdb.h"), "").unwrap();
+        assert_eq!(include_dir_for(&library), Some(include));
+    }
+}
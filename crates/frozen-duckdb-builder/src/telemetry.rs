@@ -0,0 +1,174 @@
+//! # Local Build-Time Telemetry
+//!
+//! Opt-in recording of how the DuckDB binary was obtained during each build
+//! script run, so the speedup over compiling from source can be measured on
+//! real machines. Enable it with `FROZEN_DUCKDB_TELEMETRY=1`.
+//!
+//! Events are appended as JSON Lines to `~/.frozen-duckdb/metrics.jsonl`.
+//! The builder cannot link DuckDB itself (it is what provides DuckDB), so
+//! `frozen-duckdb stats` moves these events into
+//! `~/.frozen-duckdb/cache/metrics.duckdb` and aggregates them there. Nothing is
+//! ever sent over the network.
+
+use anyhow::{Context, Result};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Environment variable that enables telemetry when set to `1` or `true`.
+pub const TELEMETRY_ENV: &str = "FROZEN_DUCKDB_TELEMETRY";
+
+/// File that build events are appended to, inside `~/.frozen-duckdb/`.
+pub const METRICS_LOG: &str = "metrics.jsonl";
+
+/// Where the build script got the DuckDB binary from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinarySource {
+    /// Already in `~/.frozen-duckdb/cache/` (cache hit)
+    Cache,
+    /// Copied from the project's `prebuilt/` directory
+    Prebuilt,
+    /// Downloaded from GitHub Releases
+    Download,
+    /// Compiled from source
+    Compile,
+    /// An installed system DuckDB with the same version
+    System,
+}
+
+impl BinarySource {
+    /// Name stored in the metrics log.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Cache => "cache",
+            Self::Prebuilt => "prebuilt",
+            Self::Download => "download",
+            Self::Compile => "compile",
+            Self::System => "system",
+        }
+    }
+}
+
+/// One build script run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildEvent {
+    /// DuckDB version being provided
+    pub version: String,
+    /// Target architecture (`x86_64` or `arm64`)
+    pub arch: String,
+    /// Where the binary came from
+    pub source: BinarySource,
+    /// Time spent downloading, if a download was attempted
+    pub download: Option<Duration>,
+    /// Total time spent in [`crate::ensure_binary`]
+    pub total: Duration,
+}
+
+impl BuildEvent {
+    /// Formats the event as a single JSON object line.
+    pub fn to_json_line(&self, recorded_at: u64) -> String {
+        let download_ms = self
+            .download
+            .map_or_else(|| "null".to_string(), |d| d.as_millis().to_string());
+        format!(
+            "{{\"recorded_at\":{},\"version\":\"{}\",\"arch\":\"{}\",\"source\":\"{}\",\"download_ms\":{},\"total_ms\":{}}}",
+            recorded_at,
+            escape_json(&self.version),
+            escape_json(&self.arch),
+            self.source.as_str(),
+            download_ms,
+            self.total.as_millis()
+        )
+    }
+}
+
+/// Returns whether telemetry was enabled with [`TELEMETRY_ENV`].
+pub fn is_enabled() -> bool {
+    env::var(TELEMETRY_ENV).is_ok_and(|value| matches!(value.trim(), "1" | "true"))
+}
+
+/// Returns the metrics log path, `~/.frozen-duckdb/metrics.jsonl`.
+pub fn metrics_log_path() -> Result<PathBuf> {
+    let home = env::var("HOME").context("HOME environment variable not set")?;
+    Ok(Path::new(&home).join(crate::CACHE_DIR).join(METRICS_LOG))
+}
+
+/// Appends `event` to the metrics log if telemetry is enabled.
+pub fn record(event: &BuildEvent) -> Result<()> {
+    if !is_enabled() {
+        return Ok(());
+    }
+    record_to(&metrics_log_path()?, event)
+}
+
+/// Appends `event` to the log at `path`.
+pub fn record_to(path: &Path, event: &BuildEvent) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("Failed to create metrics directory")?;
+    }
+    let recorded_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+
+    let mut log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open metrics log: {}", path.display()))?;
+    writeln!(log, "{}", event.to_json_line(recorded_at))?;
+    Ok(())
+}
+
+fn escape_json(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_json_line() {
+        let event = BuildEvent {
+            version: "1.4.0".to_string(),
+            arch: "arm64".to_string(),
+            source: BinarySource::Download,
+            download: Some(Duration::from_millis(2300)),
+            total: Duration::from_millis(2450),
+        };
+        assert_eq!(
+            event.to_json_line(1_700_000_000),
+            "{\"recorded_at\":1700000000,\"version\":\"1.4.0\",\"arch\":\"arm64\",\"source\":\"download\",\"download_ms\":2300,\"total_ms\":2450}"
+        );
+
+        let cached = BuildEvent {
+            source: BinarySource::Cache,
+            download: None,
+            ..event
+        };
+        assert!(cached.to_json_line(0).contains("\"download_ms\":null"));
+    }
+
+    #[test]
+    fn test_record_appends_lines() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("nested").join(METRICS_LOG);
+        let event = BuildEvent {
+            version: "1.4.0".to_string(),
+            arch: "x86_64".to_string(),
+            source: BinarySource::Cache,
+            download: None,
+            total: Duration::from_millis(3),
+        };
+
+        record_to(&path, &event).unwrap();
+        record_to(&path, &event).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 2);
+        assert!(content
+            .lines()
+            .all(|line| line.contains("\"source\":\"cache\"")));
+    }
+}
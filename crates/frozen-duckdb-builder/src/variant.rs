@@ -0,0 +1,167 @@
+//! # Full and Lite Mega-Libraries
+//!
+//! The full mega-library bundles every extension, which makes it large to
+//! download and slow to compile on small machines. The lite variant keeps
+//! only the extensions most applications read data with, for edge devices
+//! such as a Raspberry Pi:
+//!
+//! | Variant | Extensions | Assets |
+//! |---------|------------|--------|
+//! | `full` (default) | All, including httpfs, ICU, TPC-H/TPC-DS, FTS, and Arrow | `libduckdb-linux-armv7.so` |
+//! | `lite` | parquet, json | `libduckdb-lite-linux-armv7.so` |
+//!
+//! Lite libraries have no ICU, so `TIMESTAMPTZ` time zone conversions and
+//! locale collations aren't available. Each variant has its own cache
+//! directory (`v{version}-{arch}-lite`), so switching never reuses the
+//! other's library.
+//!
+//! ## Usage Examples
+//!
+//! ```bash
+//! FROZEN_DUCKDB_VARIANT=lite cargo build --release
+//!
+//! # Compile locally on a Raspberry Pi with two jobs (the armv7 default)
+//! FROZEN_DUCKDB_VARIANT=lite FROZEN_DUCKDB_BUILD_JOBS=2 cargo build --release
+//! ```
+//!
+//! # Examples
+//!
+//! ```rust
+//! use frozen_duckdb_builder::variant::Variant;
+//!
+//! assert_eq!(Variant::parse("lite")?, Variant::Lite);
+//! assert_eq!(Variant::Lite.asset("libduckdb-linux-armv7.so"), "libduckdb-lite-linux-armv7.so");
+//! assert_eq!(Variant::Full.asset("libduckdb-linux-armv7.so"), "libduckdb-linux-armv7.so");
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::Result;
+use std::env;
+
+/// Environment variable selecting the variant (`full` or `lite`).
+pub const VARIANT_ENV: &str = "FROZEN_DUCKDB_VARIANT";
+
+/// Which extensions the DuckDB library bundles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Variant {
+    /// Every extension
+    #[default]
+    Full,
+    /// parquet and json only
+    Lite,
+}
+
+impl Variant {
+    /// Reads [`VARIANT_ENV`]; unset means [`Variant::Full`].
+    pub fn from_env() -> Result<Self> {
+        match env::var(VARIANT_ENV) {
+            Ok(value) => Self::parse(&value),
+            Err(_) => Ok(Self::Full),
+        }
+    }
+
+    /// Parses `full` or `lite`.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "" | "full" => Ok(Self::Full),
+            "lite" => Ok(Self::Lite),
+            other => anyhow::bail!(
+                "Invalid {}={:?}: expected \"full\" or \"lite\"",
+                VARIANT_ENV,
+                other
+            ),
+        }
+    }
+
+    /// Name accepted by [`Variant::parse`], `full` or `lite`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::Lite => "lite",
+        }
+    }
+
+    /// Name of a release asset in this variant: lite assets are prefixed
+    /// with `libduckdb-lite-`, full ones keep their name.
+    pub fn asset(&self, name: &str) -> String {
+        match (self, name.strip_prefix("libduckdb-")) {
+            (Self::Lite, Some(rest)) => format!("libduckdb-lite-{}", rest),
+            _ => name.to_string(),
+        }
+    }
+
+    /// Suffix of the versioned cache directory, empty for the full variant.
+    pub fn cache_suffix(&self) -> &'static str {
+        match self {
+            Self::Full => "",
+            Self::Lite => "-lite",
+        }
+    }
+
+    /// `BUILD_EXTENSIONS` for DuckDB's `make bundle-library`.
+    pub fn bundle_extensions(&self) -> &'static str {
+        match self {
+            Self::Full => "parquet;json;icu",
+            Self::Lite => "parquet;json",
+        }
+    }
+
+    /// CMake flags choosing the extensions of a local build.
+    pub fn cmake_flags(&self) -> &'static [&'static str] {
+        match self {
+            Self::Full => &[
+                "-DBUILD_EXTENSIONS=ON",
+                "-DBUILD_PARQUET=ON",
+                "-DBUILD_JSON=ON",
+                "-DBUILD_ICU=ON",
+                "-DBUILD_HTTPFS=ON",
+                "-DBUILD_VISUALIZER=ON",
+                "-DBUILD_TPCH=ON",
+                "-DBUILD_TPCDS=ON",
+                "-DBUILD_FTS=ON",
+                "-DBUILD_INET=ON",
+                "-DBUILD_EXCEL=ON",
+                "-DBUILD_SQLSMITH=ON",
+                "-DBUILD_TPCE=ON",
+                "-DBUILD_JEMALLOC=ON",
+                "-DBUILD_AUTOLOAD=ON",
+                "-DBUILD_ARROW=ON",
+                "-DBUILD_POLARS=ON",
+            ],
+            Self::Lite => &["-DBUILD_EXTENSIONS=parquet;json", "-DBUILD_JEMALLOC=OFF"],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_variant() {
+        assert_eq!(Variant::parse(" Lite ").unwrap(), Variant::Lite);
+        assert_eq!(Variant::parse("").unwrap(), Variant::Full);
+        let err = Variant::parse("tiny").unwrap_err().to_string();
+        assert!(err.contains(VARIANT_ENV), "{}", err);
+        assert_eq!(Variant::Lite.as_str(), "lite");
+    }
+
+    #[test]
+    fn test_lite_assets_and_extensions() {
+        assert_eq!(
+            Variant::Lite.asset("libduckdb-static-linux-armv7.a"),
+            "libduckdb-lite-static-linux-armv7.a"
+        );
+        // Legacy names have no lite counterpart
+        assert_eq!(
+            Variant::Lite.asset("libduckdb_arm64.dylib"),
+            "libduckdb_arm64.dylib"
+        );
+        assert!(!Variant::Lite.bundle_extensions().contains("icu"));
+        assert!(Variant::Lite
+            .cmake_flags()
+            .iter()
+            .all(|flag| !flag.contains("HTTPFS")));
+        assert_eq!(Variant::Full.cache_suffix(), "");
+    }
+}
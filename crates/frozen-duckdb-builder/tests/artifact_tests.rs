@@ -0,0 +1,93 @@
+//! Release artifact naming across the supported platform matrix
+
+use frozen_duckdb_builder::artifact::{
+    archive_asset, legacy_library_asset, library_asset, static_asset, ArtifactOs,
+};
+use frozen_duckdb_builder::headers::ArchiveCompression;
+use frozen_duckdb_builder::variant::Variant;
+
+const ARCHES: [&str; 3] = ["x86_64", "arm64", "armv7"];
+
+#[test]
+fn test_every_platform_has_distinct_assets() {
+    let mut names = Vec::new();
+    for os in ArtifactOs::ALL {
+        for arch in ARCHES {
+            for variant in [Variant::Full, Variant::Lite] {
+                names.push(variant.asset(&library_asset(os, arch)));
+                names.push(variant.asset(&static_asset(os, arch)));
+                for compression in ArchiveCompression::ALL {
+                    names.push(variant.asset(&archive_asset(os, arch, compression)));
+                }
+            }
+        }
+    }
+    let count = names.len();
+    names.sort();
+    names.dedup();
+    assert_eq!(names.len(), count, "artifact names collide: {:?}", names);
+}
+
+#[test]
+fn test_asset_names_include_os_and_arch() {
+    for os in ArtifactOs::ALL {
+        for arch in ARCHES {
+            for name in [library_asset(os, arch), static_asset(os, arch)] {
+                assert!(
+                    name.contains(os.as_str()) && name.contains(arch),
+                    "{}",
+                    name
+                );
+            }
+        }
+    }
+    assert_eq!(
+        archive_asset(ArtifactOs::Linux, "arm64", ArchiveCompression::Zstd),
+        "libduckdb-linux-arm64.tar.zst"
+    );
+    assert_eq!(
+        static_asset(ArtifactOs::MacOs, "x86_64"),
+        "libduckdb-static-macos-x86_64.a"
+    );
+}
+
+#[test]
+fn test_linux_never_downloads_a_dylib() {
+    for arch in ARCHES {
+        assert!(library_asset(ArtifactOs::Linux, arch).ends_with(".so"));
+        assert_eq!(legacy_library_asset(ArtifactOs::Linux, arch), None);
+    }
+    assert_eq!(
+        legacy_library_asset(ArtifactOs::MacOs, "arm64").as_deref(),
+        Some("libduckdb_arm64.dylib")
+    );
+}
+
+#[test]
+fn test_mobile_assets() {
+    assert_eq!(
+        static_asset(ArtifactOs::Ios, "arm64"),
+        "libduckdb-static-ios-arm64.a"
+    );
+    assert_eq!(
+        library_asset(ArtifactOs::Android, "arm64"),
+        "libduckdb-android-arm64.so"
+    );
+    assert_eq!(legacy_library_asset(ArtifactOs::Ios, "arm64"), None);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_current_platform_is_linux() {
+    let os = ArtifactOs::current().unwrap();
+    assert_eq!(os, ArtifactOs::Linux);
+    assert!(library_asset(os, "x86_64").ends_with(".so"));
+}
+
+#[test]
+#[cfg(target_os = "macos")]
+fn test_current_platform_is_macos() {
+    let os = ArtifactOs::current().unwrap();
+    assert_eq!(os, ArtifactOs::MacOs);
+    assert!(library_asset(os, "arm64").ends_with(".dylib"));
+}
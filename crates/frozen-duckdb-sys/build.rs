@@ -1,3 +1,11 @@
+use frozen_duckdb_builder::architecture::ARCH_ENV;
+use frozen_duckdb_builder::artifact::ArtifactOs;
+use frozen_duckdb_builder::config::{BUILD_JOBS_ENV, CACHE_DIR_ENV, MIRROR_ENV, OFFLINE_ENV};
+use frozen_duckdb_builder::BuildConfig;
+use frozen_duckdb_builder::linkage::{static_link_libs, Linkage, LINKAGE_ENV};
+use frozen_duckdb_builder::mobile;
+use frozen_duckdb_builder::system::{FORCE_FROZEN_ENV, USE_SYSTEM_ENV};
+use frozen_duckdb_builder::variant::VARIANT_ENV;
 use std::{env, path::Path};
 
 /// Tells whether we're building for Windows. This is more suitable than a plain
@@ -21,30 +29,52 @@ fn is_compiler(compiler_name: &str) -> bool {
 }
 
 fn main() {
-    // Ensure the frozen DuckDB mega-library is available
-    let binary_path = frozen_duckdb_builder::ensure_binary()
-        .expect("Failed to get frozen DuckDB binary");
+    // FROZEN_DUCKDB_LINKAGE=static links libduckdb.a instead of the dylib;
+    // offline mode, a mirror, the cache directory, and the variant are also read here
+    let config = BuildConfig::from_env().unwrap_or_else(|e| panic!("{:#}", e));
+    let linkage = config.linkage;
+
+    // Check static support before fetching anything, so unsupported targets fail fast
+    let runtime_libs = match linkage {
+        Linkage::Static => {
+            let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+            let target_env = env::var("CARGO_CFG_TARGET_ENV").unwrap_or_default();
+            static_link_libs(&target_os, &target_env).unwrap_or_else(|e| panic!("{:#}", e))
+        }
+        Linkage::Dynamic => Vec::new(),
+    };
+
+    // Ensure the frozen DuckDB mega-library and its headers are available
+    let report = frozen_duckdb_builder::ensure_binary_with(config)
+        .unwrap_or_else(|e| panic!("Failed to get frozen DuckDB binary: {:#}", e));
+    let binary_path = &report.library;
+    let include_dir = report.include_dir.as_path();
 
-    // Get the directory containing the binary and headers
-    let lib_dir = binary_path.parent()
+    // Get the directory containing the binary
+    let link_dir = binary_path.parent()
         .expect("Binary path has no parent directory");
 
     // Tell rustc where to find the library
-    println!("cargo:rustc-link-search=native={}", lib_dir.display());
+    println!("cargo:rustc-link-search=native={}", link_dir.display());
 
     // Link against the DuckDB library
-    println!("cargo:rustc-link-lib=dylib=duckdb");
+    println!("cargo:rustc-link-lib={}=duckdb", linkage.link_kind());
+
+    // A static DuckDB needs the C++ runtime and system libraries it was built against
+    for lib in runtime_libs {
+        println!("cargo:rustc-link-lib={}", lib);
+    }
 
     // Set environment variables for dependent crates
-    println!("cargo:DUCKDB_LIB_DIR={}", lib_dir.display());
-    println!("cargo:DUCKDB_INCLUDE_DIR={}", lib_dir.display());
+    println!("cargo:DUCKDB_LIB_DIR={}", link_dir.display());
+    println!("cargo:DUCKDB_INCLUDE_DIR={}", include_dir.display());
 
     // Generate bindings using the headers from the builder
     let out_dir = env::var("OUT_DIR").unwrap();
     let out_path = Path::new(&out_dir).join("bindgen.rs");
 
     // Use the linked build approach with the headers from the builder
-    build_linked::main(&out_dir, &out_path, lib_dir);
+    build_linked::main(&out_dir, &out_path, include_dir);
 
     // Re-run if the binary changes
     println!("cargo:rerun-if-changed={}", binary_path.display());
@@ -52,8 +82,29 @@ fn main() {
     // Re-run if environment variables change
     println!("cargo:rerun-if-env-changed=DUCKDB_LIB_DIR");
     println!("cargo:rerun-if-env-changed=DUCKDB_INCLUDE_DIR");
-
-    println!("cargo:warning=Using prebuilt DuckDB binary: {}", binary_path.display());
+    println!("cargo:rerun-if-env-changed={}", LINKAGE_ENV);
+    println!("cargo:rerun-if-env-changed={}", USE_SYSTEM_ENV);
+    println!("cargo:rerun-if-env-changed={}", FORCE_FROZEN_ENV);
+    println!("cargo:rerun-if-env-changed={}", ARCH_ENV);
+    println!("cargo:rerun-if-env-changed={}", OFFLINE_ENV);
+    println!("cargo:rerun-if-env-changed={}", MIRROR_ENV);
+    println!("cargo:rerun-if-env-changed={}", CACHE_DIR_ENV);
+    println!("cargo:rerun-if-env-changed={}", VARIANT_ENV);
+    println!("cargo:rerun-if-env-changed={}", BUILD_JOBS_ENV);
+
+    // Log where the library came from
+    println!("cargo:warning=Using {}", report.summary());
+
+    // Phone apps link or package some of it themselves, which Cargo can't do
+    let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    if let Some(os) = ArtifactOs::parse(&target_os).filter(ArtifactOs::is_mobile) {
+        for flag in mobile::app_link_flags(os, linkage) {
+            println!("cargo:warning=Add {} to the app's linker flags", flag);
+        }
+        for lib in mobile::packaged_libraries(os, linkage) {
+            println!("cargo:warning=Package {} with the app", lib);
+        }
+    }
 }
 
 #[cfg(not(feature = "bundled"))]
@@ -62,9 +113,9 @@ mod build_linked {
 
     use super::{bindings, HeaderLocation};
 
-    pub fn main(_out_dir: &str, out_path: &Path, lib_dir: &Path) {
-        // Use the frozen library directory as include directory
-        let header = HeaderLocation::FromPath(lib_dir.to_string_lossy().to_string());
+    pub fn main(_out_dir: &str, out_path: &Path, include_dir: &Path) {
+        // Use the include directory the builder keeps in the cache
+        let header = HeaderLocation::FromPath(include_dir.to_string_lossy().to_string());
         bindings::write_to_out_dir(header, out_path);
     }
 }
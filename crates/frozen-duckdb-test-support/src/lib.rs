@@ -0,0 +1,57 @@
+//! # Test Support for Frozen DuckDB
+//!
+//! Fixtures shared by the frozen-duckdb integration tests. The main one is
+//! [`MockOllama`], a small HTTP server speaking enough of the Ollama (and
+//! OpenAI-compatible) API for Flock and the HTTP embedding backend, so the
+//! LLM tests can run in CI without a GPU or multi-gigabyte models.
+//!
+//! ## Choosing a Server
+//!
+//! Tests call [`ollama_url`] instead of hard-coding `localhost:11434`:
+//!
+//! | Environment | Server used |
+//! |-------------|-------------|
+//! | `FROZEN_DUCKDB_OLLAMA_URL=<url>` | The given server, e.g. a real Ollama |
+//! | `FROZEN_DUCKDB_MOCK_OLLAMA=1` | A [`MockOllama`] shared by the test binary |
+//! | neither | `http://localhost:11434` |
+//!
+//! ```bash
+//! # Run the Flock tests against canned responses
+//! FROZEN_DUCKDB_MOCK_OLLAMA=1 cargo test --test flock_tests
+//! ```
+
+pub mod ollama;
+
+pub use ollama::{mock_embedding, MockOllama, MockOptions, MockRequest};
+
+use std::env;
+use std::sync::OnceLock;
+
+/// Environment variable naming the Ollama server the tests should use.
+///
+/// Also read by `frozen-duckdb validate-ffi`.
+pub const OLLAMA_URL_ENV: &str = "FROZEN_DUCKDB_OLLAMA_URL";
+
+/// Environment variable that makes [`ollama_url`] start a mock server.
+pub const MOCK_OLLAMA_ENV: &str = "FROZEN_DUCKDB_MOCK_OLLAMA";
+
+/// Ollama server used when neither environment variable is set.
+pub const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
+
+/// Returns the URL of the Ollama server the tests should target.
+///
+/// The mock server is started on first use and lives until the test
+/// binary exits, so every test in the binary shares it.
+pub fn ollama_url() -> String {
+    static MOCK: OnceLock<MockOllama> = OnceLock::new();
+
+    if let Ok(url) = env::var(OLLAMA_URL_ENV) {
+        return url;
+    }
+    if env::var(MOCK_OLLAMA_ENV).is_ok_and(|value| !value.is_empty() && value != "0") {
+        return MOCK
+            .get_or_init(|| MockOllama::start().expect("Failed to start mock Ollama server"))
+            .url();
+    }
+    DEFAULT_OLLAMA_URL.to_string()
+}
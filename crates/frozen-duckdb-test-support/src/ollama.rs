@@ -0,0 +1,433 @@
+//! # Mock Ollama Server
+//!
+//! [`MockOllama`] listens on an ephemeral localhost port and answers with
+//! canned, deterministic responses:
+//!
+//! | Endpoint | Response |
+//! |----------|----------|
+//! | `GET /api/tags`, `GET /api/version` | Configured models, a mock version |
+//! | `POST /api/generate`, `POST /api/chat` | [`MockOptions::completion`] |
+//! | `POST /api/embed`, `POST /api/embeddings` | [`mock_embedding`] of each input |
+//! | `POST /v1/chat/completions` | [`MockOptions::completion`], OpenAI format |
+//! | `POST /v1/embeddings` | [`mock_embedding`] of each input, OpenAI format |
+//!
+//! When a generate or chat request asks for structured output (a JSON
+//! schema in `format`) with an `items` array, as Flock does for batched
+//! rows, the completion is repeated once per tuple found in the prompt so
+//! the batch sizes line up.
+//!
+//! Every request is recorded and can be inspected with
+//! [`MockOllama::requests`].
+//!
+//! ## Example
+//!
+//! ```rust
+//! use frozen_duckdb_test_support::MockOllama;
+//!
+//! let ollama = MockOllama::start()?;
+//! let url = ollama.url(); // e.g. http://127.0.0.1:40123
+//! // ... CREATE SECRET (TYPE OLLAMA, API_URL '<url>') and run Flock queries
+//! assert!(ollama.requests().iter().all(|r| r.path.starts_with("/")));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use tiny_http::{Header, Method, Response, Server};
+
+/// Canned responses served by a [`MockOllama`].
+#[derive(Debug, Clone)]
+pub struct MockOptions {
+    /// Text returned for every completion
+    pub completion: String,
+    /// Number of dimensions of every embedding
+    pub dimension: usize,
+    /// Model names listed by `/api/tags`
+    pub models: Vec<String>,
+}
+
+impl Default for MockOptions {
+    fn default() -> Self {
+        Self {
+            completion: "Quack! This is a mock completion.".to_string(),
+            dimension: 16,
+            models: vec![
+                "qwen3-coder:30b".to_string(),
+                "qwen3-embedding:8b".to_string(),
+            ],
+        }
+    }
+}
+
+/// A request received by a [`MockOllama`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockRequest {
+    /// HTTP method, e.g. `POST`
+    pub method: String,
+    /// Request path, e.g. `/api/embed`
+    pub path: String,
+    /// JSON request body, or `Null` for empty or non-JSON bodies
+    pub body: Value,
+}
+
+/// A mock Ollama server running on a background thread.
+///
+/// The server shuts down when the value is dropped.
+pub struct MockOllama {
+    server: Arc<Server>,
+    port: u16,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MockOllama {
+    /// Starts a server with the default canned responses.
+    pub fn start() -> Result<Self> {
+        Self::start_with(MockOptions::default())
+    }
+
+    /// Starts a server answering with `options`.
+    pub fn start_with(options: MockOptions) -> Result<Self> {
+        let server = Server::http("127.0.0.1:0")
+            .map_err(|e| anyhow!("Failed to start mock Ollama server: {}", e))?;
+        let port = server
+            .server_addr()
+            .to_ip()
+            .map(|addr| addr.port())
+            .ok_or_else(|| anyhow!("Mock Ollama server is not listening on TCP"))?;
+        let server = Arc::new(server);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let handle = {
+            let server = Arc::clone(&server);
+            let requests = Arc::clone(&requests);
+            std::thread::spawn(move || {
+                for mut request in server.incoming_requests() {
+                    let mut body = String::new();
+                    let _ = request.as_reader().read_to_string(&mut body);
+                    let received = MockRequest {
+                        method: request.method().as_str().to_string(),
+                        path: request.url().split('?').next().unwrap_or("").to_string(),
+                        body: serde_json::from_str(&body).unwrap_or(Value::Null),
+                    };
+                    let (status, reply) = respond(&options, request.method(), &received);
+                    requests
+                        .lock()
+                        .expect("request log poisoned")
+                        .push(received);
+
+                    let content_type =
+                        Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                            .expect("valid header");
+                    let response = Response::from_string(reply.to_string())
+                        .with_status_code(status)
+                        .with_header(content_type);
+                    let _ = request.respond(response);
+                }
+            })
+        };
+
+        Ok(Self {
+            server,
+            port,
+            requests,
+            handle: Some(handle),
+        })
+    }
+
+    /// Base URL of the server, e.g. `http://127.0.0.1:40123`.
+    pub fn url(&self) -> String {
+        format!("http://127.0.0.1:{}", self.port)
+    }
+
+    /// Requests received so far, oldest first.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().expect("request log poisoned").clone()
+    }
+}
+
+impl Drop for MockOllama {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Returns the status code and JSON body answering `request`.
+fn respond(options: &MockOptions, method: &Method, request: &MockRequest) -> (u16, Value) {
+    let body = &request.body;
+    let model = body.get("model").and_then(Value::as_str).unwrap_or("mock");
+
+    match (method, request.path.as_str()) {
+        (Method::Get, "/api/tags") => {
+            let models: Vec<Value> = options
+                .models
+                .iter()
+                .map(|name| json!({ "name": name, "model": name }))
+                .collect();
+            (200, json!({ "models": models }))
+        }
+        (Method::Get, "/api/version") => (200, json!({ "version": "0.0.0-mock" })),
+        (Method::Post, "/api/generate") => {
+            let prompt = body.get("prompt").and_then(Value::as_str).unwrap_or("");
+            let response = completion(options, body, prompt);
+            (
+                200,
+                json!({ "model": model, "response": response, "done": true }),
+            )
+        }
+        (Method::Post, "/api/chat") | (Method::Post, "/v1/chat/completions") => {
+            let prompt = body
+                .get("messages")
+                .and_then(Value::as_array)
+                .map(|messages| {
+                    messages
+                        .iter()
+                        .filter_map(|m| m.get("content").and_then(Value::as_str))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })
+                .unwrap_or_default();
+            let message =
+                json!({ "role": "assistant", "content": completion(options, body, &prompt) });
+            if request.path == "/api/chat" {
+                (
+                    200,
+                    json!({ "model": model, "message": message, "done": true }),
+                )
+            } else {
+                (
+                    200,
+                    json!({
+                        "object": "chat.completion",
+                        "model": model,
+                        "choices": [{ "index": 0, "message": message, "finish_reason": "stop" }],
+                    }),
+                )
+            }
+        }
+        (Method::Post, "/api/embed") => {
+            let embeddings: Vec<Vec<f32>> = inputs(body.get("input"))
+                .iter()
+                .map(|text| mock_embedding(text, options.dimension))
+                .collect();
+            (200, json!({ "model": model, "embeddings": embeddings }))
+        }
+        (Method::Post, "/api/embeddings") => {
+            let prompt = body.get("prompt").and_then(Value::as_str).unwrap_or("");
+            (
+                200,
+                json!({ "embedding": mock_embedding(prompt, options.dimension) }),
+            )
+        }
+        (Method::Post, "/v1/embeddings") => {
+            let data: Vec<Value> = inputs(body.get("input"))
+                .iter()
+                .enumerate()
+                .map(|(index, text)| {
+                    json!({
+                        "object": "embedding",
+                        "index": index,
+                        "embedding": mock_embedding(text, options.dimension),
+                    })
+                })
+                .collect();
+            (
+                200,
+                json!({ "object": "list", "model": model, "data": data }),
+            )
+        }
+        _ => (
+            404,
+            json!({ "error": format!("mock Ollama has no route for {} {}", method, request.path) }),
+        ),
+    }
+}
+
+/// Completion for a generate or chat request with the given prompt.
+fn completion(options: &MockOptions, body: &Value, prompt: &str) -> String {
+    let wants_items = body
+        .get("format")
+        .and_then(|format| format.pointer("/properties/items"))
+        .is_some();
+    if wants_items {
+        let items = vec![options.completion.as_str(); tuple_count(prompt)];
+        json!({ "items": items }).to_string()
+    } else {
+        options.completion.clone()
+    }
+}
+
+/// Number of tuples in the first JSON array embedded in `prompt`, at least 1.
+fn tuple_count(prompt: &str) -> usize {
+    prompt
+        .match_indices('[')
+        .find_map(|(start, _)| {
+            serde_json::Deserializer::from_str(&prompt[start..])
+                .into_iter::<Vec<Value>>()
+                .next()
+                .and_then(|parsed| parsed.ok())
+        })
+        .map_or(1, |tuples| tuples.len().max(1))
+}
+
+/// Texts of an embedding request's `input`, which may be a string or an array.
+fn inputs(input: Option<&Value>) -> Vec<String> {
+    match input {
+        Some(Value::String(text)) => vec![text.clone()],
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Deterministic unit-length embedding of `text`.
+///
+/// Each lowercased word is hashed into one of `dimension` buckets, so texts
+/// sharing words have a higher cosine similarity than unrelated texts, which
+/// is enough for similarity search tests to produce meaningful rankings.
+pub fn mock_embedding(text: &str, dimension: usize) -> Vec<f32> {
+    let mut embedding = vec![0.0f32; dimension.max(1)];
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        // FNV-1a, stable across runs and platforms
+        let hash = word
+            .to_lowercase()
+            .bytes()
+            .fold(0xcbf29ce484222325u64, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            });
+        let bucket = (hash % embedding.len() as u64) as usize;
+        embedding[bucket] += if hash >> 63 == 0 { 1.0 } else { -1.0 };
+    }
+
+    let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        embedding.iter_mut().for_each(|x| *x /= norm);
+    } else {
+        embedding[0] = 1.0;
+    }
+    embedding
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(url: &str, body: Value) -> Value {
+        let response = ureq::post(url)
+            .set("Content-Type", "application/json")
+            .send_string(&body.to_string())
+            .unwrap();
+        serde_json::from_str(&response.into_string().unwrap()).unwrap()
+    }
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    #[test]
+    fn test_mock_embedding_is_deterministic_and_normalized() {
+        let a = mock_embedding("DuckDB is an analytical database", 16);
+        assert_eq!(a, mock_embedding("duckdb is an analytical database", 16));
+        assert!((cosine(&a, &a) - 1.0).abs() < 1e-5);
+
+        let related = mock_embedding("an analytical database engine", 16);
+        let unrelated = mock_embedding("bread baking with sourdough", 16);
+        assert!(cosine(&a, &related) > cosine(&a, &unrelated));
+
+        assert_eq!(mock_embedding("", 4), vec![1.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_tuple_count() {
+        assert_eq!(tuple_count("no tuples here"), 1);
+        assert_eq!(
+            tuple_count(r#"Classify: [{"data": "a"}, {"data": "b"}, {"data": "c"}]"#),
+            3
+        );
+        assert_eq!(tuple_count(r#"see [note] then [{"x": 1}, {"x": 2}]"#), 2);
+    }
+
+    #[test]
+    fn test_ollama_endpoints() {
+        let ollama = MockOllama::start_with(MockOptions {
+            completion: "quack".to_string(),
+            dimension: 8,
+            ..Default::default()
+        })
+        .unwrap();
+        let url = ollama.url();
+
+        let tags: Value = serde_json::from_str(
+            &ureq::get(&format!("{}/api/tags", url))
+                .call()
+                .unwrap()
+                .into_string()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(tags["models"].as_array().unwrap().len(), 2);
+
+        let generated = post(
+            &format!("{}/api/generate", url),
+            json!({ "model": "coder", "prompt": "Say hi" }),
+        );
+        assert_eq!(generated["response"], "quack");
+
+        let structured = post(
+            &format!("{}/api/generate", url),
+            json!({
+                "model": "coder",
+                "prompt": r#"Tuples: [{"data": "a"}, {"data": "b"}]"#,
+                "format": { "type": "object", "properties": { "items": { "type": "array" } } },
+            }),
+        );
+        let items: Value = serde_json::from_str(structured["response"].as_str().unwrap()).unwrap();
+        assert_eq!(items, json!({ "items": ["quack", "quack"] }));
+
+        let chat = post(
+            &format!("{}/api/chat", url),
+            json!({ "model": "coder", "messages": [{ "role": "user", "content": "hi" }] }),
+        );
+        assert_eq!(chat["message"]["content"], "quack");
+
+        let embedded = post(
+            &format!("{}/api/embed", url),
+            json!({ "model": "embedder", "input": ["one", "two"] }),
+        );
+        assert_eq!(embedded["embeddings"].as_array().unwrap().len(), 2);
+        assert_eq!(embedded["embeddings"][0].as_array().unwrap().len(), 8);
+
+        let openai = post(
+            &format!("{}/v1/embeddings", url),
+            json!({ "model": "embedder", "input": "one" }),
+        );
+        assert_eq!(openai["data"][0]["index"], 0);
+
+        let requests = ollama.requests();
+        assert_eq!(requests.len(), 6);
+        assert_eq!(requests[1].path, "/api/generate");
+        assert_eq!(requests[1].body["prompt"], "Say hi");
+    }
+
+    #[test]
+    fn test_unknown_route_is_not_found() {
+        let ollama = MockOllama::start().unwrap();
+        match ureq::get(&format!("{}/api/unknown", ollama.url())).call() {
+            Err(ureq::Error::Status(code, _)) => assert_eq!(code, 404),
+            other => panic!("expected 404, got {:?}", other.map(|r| r.status())),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! Table function example for frozen DuckDB binary
+//!
+//! This example exposes a Rust iterator (the Fibonacci sequence) as a SQL
+//! table function, demonstrating that the frozen binary supports the
+//! extension entry points used for user-defined functions.
+//!
+//! Run with: `cargo run --example iterator_table --features vtab`
+
+use anyhow::Result;
+use frozen_duckdb::vtab::{self, ColumnType, IteratorTable, TableValue};
+use frozen_duckdb::Connection;
+use tracing::info;
+
+/// `fibonacci('n')` returns the first `n` Fibonacci numbers.
+struct Fibonacci;
+
+impl IteratorTable for Fibonacci {
+    type Rows = Box<dyn Iterator<Item = Vec<TableValue>> + Send>;
+
+    fn columns() -> Vec<(String, ColumnType)> {
+        vec![
+            ("idx".to_string(), ColumnType::BigInt),
+            ("value".to_string(), ColumnType::BigInt),
+        ]
+    }
+
+    fn parameter_count() -> usize {
+        1
+    }
+
+    fn rows(params: &[String]) -> Result<Self::Rows, Box<dyn std::error::Error>> {
+        let count: usize = params[0].parse()?;
+        let sequence = std::iter::successors(Some((0i64, 1i64)), |&(a, b)| {
+            a.checked_add(b).map(|c| (b, c))
+        })
+        .map(|(a, _)| a)
+        .take(count)
+        .enumerate()
+        .map(|(idx, value)| vec![TableValue::BigInt(idx as i64), TableValue::BigInt(value)]);
+        Ok(Box::new(sequence))
+    }
+}
+
+fn main() -> Result<()> {
+    // Initialize tracing
+    tracing_subscriber::fmt::init();
+
+    info!("🚀 Starting table function example");
+
+    let conn = Connection::open_in_memory()?;
+    vtab::register_iterator_table::<Fibonacci>(&conn, "fibonacci")?;
+    info!("✅ Registered fibonacci() table function");
+
+    // Query the iterator like any other table
+    let mut stmt = conn.prepare("SELECT idx, value FROM fibonacci('15') WHERE value % 2 = 0")?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))?;
+
+    info!("📊 Even Fibonacci numbers:");
+    for row in rows {
+        let (idx, value) = row?;
+        println!("  F({}) = {}", idx, value);
+    }
+
+    // Table functions compose with the rest of SQL
+    let total: i64 = conn.query_row("SELECT SUM(value) FROM fibonacci('30')", [], |row| {
+        row.get(0)
+    })?;
+    info!("🔍 Sum of first 30 Fibonacci numbers: {}", total);
+
+    info!("🎉 Table function example completed successfully!");
+    Ok(())
+}
@@ -0,0 +1,173 @@
+//! C API for using frozen DuckDB from an iOS or Android app
+//!
+//! Swift and Kotlin can't call Rust directly, so an app links a small
+//! library exporting C functions. This example is built as a `staticlib`
+//! for Xcode and a `cdylib` for Android, with DuckDB linked statically
+//! into it (the default for phone targets):
+//!
+//! ```c
+//! typedef struct FdbDatabase FdbDatabase;
+//!
+//! FdbDatabase *fdb_open(const char *path);          // NULL on error
+//! int32_t fdb_execute(FdbDatabase *db, const char *sql);  // 0, or -1 on error
+//! char *fdb_query_json(FdbDatabase *db, const char *sql); // JSON array of rows, NULL on error
+//! char *fdb_last_error(void);                       // NULL if the last call succeeded
+//! void fdb_string_free(char *s);
+//! void fdb_close(FdbDatabase *db);
+//! ```
+//!
+//! Every returned string must be freed with `fdb_string_free`. Errors are
+//! kept per thread, so read `fdb_last_error` on the thread that failed.
+//! Put the declarations in the Swift bridging header; on Android, load the
+//! library with JNA or call the functions from a JNI wrapper.
+//!
+//! Build with:
+//! - Android: `cargo ndk -t arm64-v8a build -p frozen-duckdb --example mobile_ffi --release`,
+//!   then copy `libmobile_ffi.so` to `app/src/main/jniLibs/arm64-v8a/`
+//! - iOS: `cargo build -p frozen-duckdb --example mobile_ffi --target aarch64-apple-ios --release`,
+//!   then add `libmobile_ffi.a` to the Xcode target and `-lc++` to *Other Linker Flags*
+//!
+//! See `frozen_duckdb_builder::mobile` for how the phone's DuckDB is found.
+
+use frozen_duckdb::Connection;
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+/// An open database, owned by the app between `fdb_open` and `fdb_close`.
+pub struct FdbDatabase {
+    conn: Connection,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Runs `f`, recording its error for `fdb_last_error`.
+fn record<T>(f: impl FnOnce() -> Result<T, String>) -> Option<T> {
+    let result = f();
+    LAST_ERROR.with(|last| {
+        *last.borrow_mut() = result.as_ref().err().cloned();
+    });
+    result.ok()
+}
+
+/// Reads a C string argument.
+///
+/// # Safety
+///
+/// `s` must be null or a valid NUL-terminated string.
+unsafe fn read_str<'a>(s: *const c_char, name: &str) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err(format!("{} is null", name));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| format!("{} is not UTF-8", name))
+}
+
+/// Reads a database handle argument.
+///
+/// # Safety
+///
+/// `db` must be null or a handle returned by `fdb_open` and not yet closed.
+unsafe fn read_db<'a>(db: *mut FdbDatabase) -> Result<&'a FdbDatabase, String> {
+    db.as_ref().ok_or_else(|| "database is null".to_string())
+}
+
+fn into_c_string(s: String) -> Result<*mut c_char, String> {
+    CString::new(s)
+        .map(CString::into_raw)
+        .map_err(|_| "result contains a NUL byte".to_string())
+}
+
+/// Opens the database at `path`, or an in-memory one for `":memory:"`.
+///
+/// # Safety
+///
+/// `path` must be null or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn fdb_open(path: *const c_char) -> *mut FdbDatabase {
+    record(|| {
+        let path = read_str(path, "path")?;
+        let conn = match path {
+            ":memory:" => Connection::open_in_memory(),
+            path => Connection::open(path),
+        }
+        .map_err(|e| e.to_string())?;
+        Ok(Box::into_raw(Box::new(FdbDatabase { conn })))
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// Runs one or more statements, returning 0 on success and -1 on error.
+///
+/// # Safety
+///
+/// `db` must come from `fdb_open`, and `sql` must be a valid
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn fdb_execute(db: *mut FdbDatabase, sql: *const c_char) -> i32 {
+    record(|| {
+        let (db, sql) = (read_db(db)?, read_str(sql, "sql")?);
+        db.conn.execute_batch(sql).map_err(|e| e.to_string())
+    })
+    .map_or(-1, |()| 0)
+}
+
+/// Runs a query and returns its rows as a JSON array of objects, built by
+/// DuckDB's json extension.
+///
+/// # Safety
+///
+/// `db` must come from `fdb_open`, and `sql` must be a valid
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn fdb_query_json(db: *mut FdbDatabase, sql: *const c_char) -> *mut c_char {
+    record(|| {
+        let (db, sql) = (read_db(db)?, read_str(sql, "sql")?);
+        let wrapped = format!(
+            "SELECT CAST(COALESCE(json_group_array(to_json(q)), '[]') AS VARCHAR) FROM ({}) AS q",
+            sql.trim().trim_end_matches(';')
+        );
+        let json: String = db
+            .conn
+            .query_row(&wrapped, [], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        into_c_string(json)
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// Returns the error of the last failed call on this thread, or null.
+#[no_mangle]
+pub extern "C" fn fdb_last_error() -> *mut c_char {
+    LAST_ERROR
+        .with(|last| last.borrow().clone())
+        .and_then(|message| into_c_string(message).ok())
+        .unwrap_or(ptr::null_mut())
+}
+
+/// Frees a string returned by this library.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by this library, freed only once.
+#[no_mangle]
+pub unsafe extern "C" fn fdb_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Closes a database opened with `fdb_open`.
+///
+/// # Safety
+///
+/// `db` must be null or a handle returned by `fdb_open`, closed only once.
+#[no_mangle]
+pub unsafe extern "C" fn fdb_close(db: *mut FdbDatabase) {
+    if !db.is_null() {
+        drop(Box::from_raw(db));
+    }
+}
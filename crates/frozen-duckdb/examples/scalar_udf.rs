@@ -0,0 +1,68 @@
+//! Scalar UDF example for frozen DuckDB binary
+//!
+//! This example registers Rust closures as SQL scalar functions, including
+//! the `normalize_text` built-in that the CLI `query` command exposes.
+//!
+//! Run with: `cargo run --example scalar_udf --features vscalar`
+
+use anyhow::Result;
+use frozen_duckdb::{scalar, scalar_args, Connection};
+use tracing::info;
+
+fn main() -> Result<()> {
+    // Initialize tracing
+    tracing_subscriber::fmt::init();
+
+    info!("🚀 Starting scalar UDF example");
+
+    let conn = Connection::open_in_memory()?;
+
+    // Register the CLI built-ins (normalize_text)
+    scalar::register_builtins(&conn)?;
+    info!("✅ Registered built-in scalar functions");
+
+    // Register a custom function with typed arguments
+    conn.register_scalar("initials", |args| {
+        let (first, last) = scalar_args!(args; String, Option<String>);
+        let first_initial = first.chars().next().unwrap_or('?');
+        Ok(match last.and_then(|l| l.chars().next()) {
+            Some(last_initial) => format!("{}.{}.", first_initial, last_initial),
+            None => format!("{}.", first_initial),
+        })
+    })?;
+    info!("✅ Registered initials() scalar function");
+
+    conn.execute_batch(
+        "CREATE TABLE customers (first_name VARCHAR, last_name VARCHAR, city VARCHAR);
+         INSERT INTO customers VALUES
+             ('Alice', 'Smith', '  New   York '),
+             ('Bob', NULL, 'new york'),
+             ('Carol', 'Jones', 'BOSTON');",
+    )?;
+
+    // Group on normalized text to merge inconsistent spellings
+    let mut stmt = conn.prepare(
+        "SELECT normalize_text(city) AS city, COUNT(*) AS customers,
+                string_agg(initials(first_name, last_name), ', ')
+         FROM customers GROUP BY 1 ORDER BY 1",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, String>(2)?,
+        ))
+    })?;
+
+    info!("📊 Customers by normalized city:");
+    for row in rows {
+        let (city, count, initials) = row?;
+        println!("  {}: {} {}", city, count, initials);
+    }
+
+    info!("💡 The same function is available from the CLI:");
+    info!("   frozen-duckdb query --sql \"SELECT normalize_text('  Hello   WORLD ')\"");
+
+    info!("🎉 Scalar UDF example completed successfully!");
+    Ok(())
+}
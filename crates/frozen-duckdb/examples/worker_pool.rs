@@ -0,0 +1,81 @@
+//! Worker pool sharing one DuckDB database across threads
+//!
+//! A `Connection` is `Send` but not `Sync`: it can be moved to another
+//! thread, but not used from two threads at once. To work on one database
+//! from several threads, give each worker its own connection with
+//! `Connection::try_clone`. All clones see the same data, and the database
+//! stays open until the last clone is dropped.
+//!
+//! Run with: `cargo run --example worker_pool`
+
+use frozen_duckdb::{params, Connection, Result};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Work handed to the pool: insert `count` readings for `sensor`.
+struct Job {
+    sensor: i32,
+    count: i64,
+}
+
+fn main() -> Result<()> {
+    let workers = 4;
+    let dir = tempfile::tempdir().expect("Failed to create a temporary directory");
+    let conn = Connection::open(dir.path().join("readings.duckdb"))?;
+    conn.execute_batch("CREATE TABLE readings (sensor INTEGER, seq BIGINT, value DOUBLE)")?;
+
+    let (jobs, queue) = mpsc::channel::<Job>();
+    let queue = Arc::new(Mutex::new(queue));
+
+    let handles: Vec<_> = (0..workers)
+        .map(|worker| {
+            // One connection per worker, opened before the thread starts
+            let conn = conn.try_clone()?;
+            let queue = Arc::clone(&queue);
+            Ok(thread::spawn(move || -> Result<usize> {
+                let mut done = 0;
+                loop {
+                    let job = match queue.lock().unwrap().recv() {
+                        Ok(job) => job,
+                        // The sender is gone: no more work
+                        Err(_) => return Ok(done),
+                    };
+                    let mut appender = conn.appender("readings")?;
+                    for seq in 0..job.count {
+                        appender.append_row(params![job.sensor, seq, seq as f64 * 0.5])?;
+                    }
+                    appender.flush()?;
+                    println!("worker {} loaded sensor {}", worker, job.sensor);
+                    done += 1;
+                }
+            }))
+        })
+        .collect::<Result<_>>()?;
+
+    for sensor in 0..16 {
+        jobs.send(Job {
+            sensor,
+            count: 1_000,
+        })
+        .expect("All workers stopped");
+    }
+    drop(jobs);
+
+    let mut jobs_done = 0;
+    for handle in handles {
+        jobs_done += handle.join().expect("Worker panicked")?;
+    }
+
+    let (rows, sensors): (i64, i64) = conn.query_row(
+        "SELECT count(*), count(DISTINCT sensor) FROM readings",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    println!(
+        "{} jobs done by {} workers: {} rows from {} sensors",
+        jobs_done, workers, rows, sensors
+    );
+    assert_eq!(rows, 16_000);
+    Ok(())
+}
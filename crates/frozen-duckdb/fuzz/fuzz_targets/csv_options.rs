@@ -0,0 +1,48 @@
+//! Fuzzes the CSV dialect options turned into `read_csv` calls.
+//!
+//! Input is split on `\x1f` into the delimiter, quote, escape, path, date
+//! format, and column names. Whatever they contain, the generated call
+//! must stay a single SQL statement: no option may escape its literal.
+//!
+//! ```bash
+//! cargo +nightly fuzz run csv_options
+//! ```
+
+#![no_main]
+
+use frozen_duckdb::cli::dataset_manager::{split_statements, CsvColumn, CsvSchema, QueryOutput};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    let mut fields = text.split('\u{1f}').map(str::to_string);
+    let mut next = || fields.next().unwrap_or_default();
+
+    let delimiter = next();
+    let quote = next();
+    let escape = next();
+    let path = next();
+    let date_format = Some(next()).filter(|format| !format.is_empty());
+    let schema = CsvSchema {
+        delimiter,
+        quote,
+        escape,
+        has_header: data.first().is_some_and(|b| b & 1 == 1),
+        skip_rows: data.len() as u64,
+        date_format,
+        timestamp_format: None,
+        columns: fields
+            .map(|name| CsvColumn {
+                name,
+                data_type: "VARCHAR".to_string(),
+            })
+            .collect(),
+        sample: QueryOutput {
+            columns: Vec::new(),
+            rows: Vec::new(),
+        },
+    };
+
+    let query = format!("SELECT * FROM {}; SELECT 1", schema.read_csv(&path));
+    assert_eq!(split_statements(&query).len(), 2, "{}", query);
+});
@@ -53,6 +53,47 @@
 //!     ((time2.as_millis() - time1.as_millis()) as f64 / time2.as_millis() as f64) * 100.0);
 //! ```
 //!
+//! ### Repeated Runs with Statistics
+//!
+//! ```rust
+//! use frozen_duckdb::benchmark::Benchmark;
+//!
+//! let stats = Benchmark::new("parse_config")
+//!     .warmup(3)
+//!     .iterations(50)
+//!     .run(|| {
+//!         // Operation under test
+//!         Ok(())
+//!     })?;
+//!
+//! println!("{}", stats.format_report());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! ### Performance Budgets in Integration Tests
+//!
+//! Statistics can be checked against a fixed budget or against baselines
+//! stored in a JSON file, so regressions fail `cargo test` without
+//! needing the unstable `#[bench]` harness:
+//!
+//! ```rust
+//! use frozen_duckdb::benchmark::{Baselines, Benchmark};
+//! use std::time::Duration;
+//!
+//! let stats = Benchmark::new("open_in_memory").iterations(20).run(|| Ok(()))?;
+//! stats.check_p95(Duration::from_millis(50))?;
+//!
+//! let path = std::env::temp_dir().join("frozen_duckdb_baselines.json");
+//! let mut baselines = Baselines::load(&path)?;
+//! if let Some(baseline) = baselines.get("open_in_memory") {
+//!     // Fail if the median is more than 20% slower than the baseline
+//!     stats.check_regression(&baseline, 20.0)?;
+//! }
+//! baselines.record(&stats);
+//! baselines.save(&path)?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
 //! ## Performance Characteristics
 //!
 //! - **Timing precision**: Microsecond-level accuracy
@@ -67,8 +108,15 @@
 //! 3. **Consistent environment**: Run benchmarks in controlled conditions
 //! 4. **Statistical significance**: Use proper statistical analysis for comparisons
 
-use anyhow::Result;
-use std::time::Instant;
+use crate::duckdb::{params, Connection};
+use crate::cli::dataset_manager::PerformanceOptions;
+use crate::ingest::BulkInsert;
+use crate::memory;
+use crate::profiling::{profile, QueryProfile};
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use std::path::Path;
+use std::time::{Duration, Instant};
 
 /// Measures the execution time of a build operation with high precision.
 ///
@@ -209,6 +257,550 @@ where
     Ok((time1, time2))
 }
 
+/// Repeated timing of an operation with warm-up runs.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::benchmark::Benchmark;
+///
+/// let stats = Benchmark::new("sum").warmup(2).iterations(100).run(|| {
+///     let _total: u64 = (0..1000).sum();
+///     Ok(())
+/// })?;
+/// assert_eq!(stats.iterations, 100);
+/// assert!(stats.median <= stats.p95);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Benchmark {
+    name: String,
+    warmup: usize,
+    iterations: usize,
+}
+
+impl Benchmark {
+    /// Creates a benchmark with 1 warm-up run and 10 measured iterations.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            warmup: 1,
+            iterations: 10,
+        }
+    }
+
+    /// Sets the number of unmeasured warm-up runs.
+    pub fn warmup(mut self, runs: usize) -> Self {
+        self.warmup = runs;
+        self
+    }
+
+    /// Sets the number of measured iterations (at least 1).
+    pub fn iterations(mut self, runs: usize) -> Self {
+        self.iterations = runs.max(1);
+        self
+    }
+
+    /// Runs the operation and returns timing statistics.
+    ///
+    /// Unlike [`measure_build_time`], an error from the operation stops the
+    /// benchmark and is returned, since timings of a failing operation are
+    /// rarely meaningful.
+    pub fn run<F>(&self, mut operation: F) -> Result<BenchmarkStats>
+    where
+        F: FnMut() -> Result<()>,
+    {
+        memory::reset_peak_rss();
+        for run in 0..self.warmup {
+            operation().with_context(|| {
+                format!("Benchmark '{}' failed during warm-up run {}", self.name, run + 1)
+            })?;
+        }
+
+        let mut samples = Vec::with_capacity(self.iterations);
+        for run in 0..self.iterations {
+            let start = Instant::now();
+            operation().with_context(|| {
+                format!("Benchmark '{}' failed on iteration {}", self.name, run + 1)
+            })?;
+            samples.push(start.elapsed());
+        }
+
+        Ok(BenchmarkStats {
+            peak_rss: memory::peak_rss(),
+            ..BenchmarkStats::from_samples(&self.name, &samples)
+        })
+    }
+}
+
+/// Summary statistics for a benchmark's measured iterations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchmarkStats {
+    /// Benchmark name
+    pub name: String,
+    /// Number of measured iterations
+    pub iterations: usize,
+    /// Arithmetic mean
+    pub mean: Duration,
+    /// Median (average of the middle two for an even count)
+    pub median: Duration,
+    /// 95th percentile (nearest rank)
+    pub p95: Duration,
+    /// Fastest iteration
+    pub min: Duration,
+    /// Slowest iteration
+    pub max: Duration,
+    /// Sample standard deviation
+    pub stddev: Duration,
+    /// Peak resident set size in bytes while the benchmark ran, if the
+    /// platform reports it (see [`memory::peak_rss`])
+    pub peak_rss: Option<u64>,
+}
+
+impl BenchmarkStats {
+    /// Computes statistics from raw samples.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `samples` is empty.
+    pub fn from_samples(name: &str, samples: &[Duration]) -> Self {
+        assert!(!samples.is_empty(), "benchmark statistics need at least one sample");
+
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let n = sorted.len();
+
+        let secs: Vec<f64> = sorted.iter().map(Duration::as_secs_f64).collect();
+        let mean = secs.iter().sum::<f64>() / n as f64;
+        let variance = if n > 1 {
+            secs.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1) as f64
+        } else {
+            0.0
+        };
+        let median = if n.is_multiple_of(2) {
+            (sorted[n / 2 - 1] + sorted[n / 2]) / 2
+        } else {
+            sorted[n / 2]
+        };
+        let p95_rank = ((n as f64 * 0.95).ceil() as usize).clamp(1, n);
+
+        Self {
+            name: name.to_string(),
+            iterations: n,
+            mean: Duration::from_secs_f64(mean),
+            median,
+            p95: sorted[p95_rank - 1],
+            min: sorted[0],
+            max: sorted[n - 1],
+            stddev: Duration::from_secs_f64(variance.sqrt()),
+            peak_rss: None,
+        }
+    }
+
+    /// Fails if the 95th percentile exceeds `budget`.
+    pub fn check_p95(&self, budget: Duration) -> Result<()> {
+        if self.p95 > budget {
+            return Err(anyhow::anyhow!(
+                "Benchmark '{}' exceeded its budget: p95 {:?} > {:?}",
+                self.name,
+                self.p95,
+                budget
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fails if the peak memory exceeds `budget` bytes; passes when the
+    /// peak is unknown.
+    pub fn check_peak_rss(&self, budget: u64) -> Result<()> {
+        if let Some(peak) = self.peak_rss {
+            memory::check_budget(&format!("Benchmark '{}'", self.name), peak, budget)?;
+        }
+        Ok(())
+    }
+
+    /// Percentage change of the median relative to `baseline`; positive is slower.
+    pub fn change_from(&self, baseline: &BenchmarkStats) -> f64 {
+        let base = baseline.median.as_secs_f64();
+        if base == 0.0 {
+            return 0.0;
+        }
+        (self.median.as_secs_f64() - base) / base * 100.0
+    }
+
+    /// Fails if the median is more than `max_regression_pct` percent slower
+    /// than `baseline`.
+    pub fn check_regression(&self, baseline: &BenchmarkStats, max_regression_pct: f64) -> Result<()> {
+        let change = self.change_from(baseline);
+        if change > max_regression_pct {
+            return Err(anyhow::anyhow!(
+                "Benchmark '{}' regressed {:.1}% (median {:?} vs baseline {:?}, allowed {:.1}%)",
+                self.name,
+                change,
+                self.median,
+                baseline.median,
+                max_regression_pct
+            ));
+        }
+        Ok(())
+    }
+
+    /// Formats the statistics as a short human-readable report.
+    pub fn format_report(&self) -> String {
+        let mut report = format!(
+            "📊 {} ({} iterations)\n  mean: {:?} ± {:?}\n  median: {:?}\n  p95: {:?}\n  min/max: {:?} / {:?}",
+            self.name,
+            self.iterations,
+            self.mean,
+            self.stddev,
+            self.median,
+            self.p95,
+            self.min,
+            self.max
+        );
+        if let Some(peak) = self.peak_rss {
+            report.push_str(&format!("\n  peak memory: {}", memory::format_bytes(peak)));
+        }
+        report
+    }
+
+    /// Serializes the statistics (without the name) with durations in
+    /// nanoseconds and the peak memory, when known, in bytes.
+    pub fn to_json(&self) -> Value {
+        let mut value = json!({
+            "iterations": self.iterations,
+            "mean_ns": self.mean.as_nanos() as u64,
+            "median_ns": self.median.as_nanos() as u64,
+            "p95_ns": self.p95.as_nanos() as u64,
+            "min_ns": self.min.as_nanos() as u64,
+            "max_ns": self.max.as_nanos() as u64,
+            "stddev_ns": self.stddev.as_nanos() as u64,
+        });
+        if let Some(peak) = self.peak_rss {
+            value["peak_rss_bytes"] = json!(peak);
+        }
+        value
+    }
+
+    /// Parses statistics written by [`BenchmarkStats::to_json`].
+    pub fn from_json(name: &str, value: &Value) -> Result<Self> {
+        let nanos = |key: &str| {
+            value
+                .get(key)
+                .and_then(Value::as_u64)
+                .map(Duration::from_nanos)
+                .ok_or_else(|| anyhow::anyhow!("Baseline '{}' is missing '{}'", name, key))
+        };
+        Ok(Self {
+            name: name.to_string(),
+            iterations: value.get("iterations").and_then(Value::as_u64).unwrap_or(0) as usize,
+            mean: nanos("mean_ns")?,
+            median: nanos("median_ns")?,
+            p95: nanos("p95_ns")?,
+            min: nanos("min_ns")?,
+            max: nanos("max_ns")?,
+            stddev: nanos("stddev_ns")?,
+            peak_rss: value.get("peak_rss_bytes").and_then(Value::as_u64),
+        })
+    }
+}
+
+/// Stored benchmark baselines, keyed by benchmark name.
+///
+/// Baselines are kept in a JSON object mapping each name to its
+/// [`BenchmarkStats::to_json`] form, so the file can be committed and
+/// reviewed alongside the code it measures.
+#[derive(Debug, Clone, Default)]
+pub struct Baselines {
+    entries: Map<String, Value>,
+}
+
+impl Baselines {
+    /// Loads baselines from `path`; a missing file yields no baselines.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read baselines: {}", path.display()))?;
+        let entries = match serde_json::from_str(&content)
+            .with_context(|| format!("Invalid baselines file: {}", path.display()))?
+        {
+            Value::Object(entries) => entries,
+            _ => return Err(anyhow::anyhow!("Baselines file must contain a JSON object: {}", path.display())),
+        };
+        Ok(Self { entries })
+    }
+
+    /// Returns the baseline for `name`, if one is stored and valid.
+    pub fn get(&self, name: &str) -> Option<BenchmarkStats> {
+        self.entries
+            .get(name)
+            .and_then(|value| BenchmarkStats::from_json(name, value).ok())
+    }
+
+    /// Stores `stats` as the baseline for its benchmark, replacing any previous one.
+    pub fn record(&mut self, stats: &BenchmarkStats) {
+        self.entries.insert(stats.name.clone(), stats.to_json());
+    }
+
+    /// Writes the baselines to `path` as pretty-printed JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(&Value::Object(self.entries.clone()))?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write baselines: {}", path.display()))
+    }
+}
+
+/// Per-row `execute` versus [`BulkInsert`] throughput for the same rows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InsertThroughput {
+    /// Rows inserted per iteration
+    pub rows: usize,
+    /// Appender flush interval used
+    pub flush_interval: usize,
+    /// One prepared `INSERT` executed per row
+    pub execute: BenchmarkStats,
+    /// Rows appended with [`BulkInsert`]
+    pub appender: BenchmarkStats,
+}
+
+impl InsertThroughput {
+    /// Rows per second for per-row `execute`, from the median iteration.
+    pub fn execute_rows_per_sec(&self) -> f64 {
+        rows_per_sec(self.rows, self.execute.median)
+    }
+
+    /// Rows per second for the Appender, from the median iteration.
+    pub fn appender_rows_per_sec(&self) -> f64 {
+        rows_per_sec(self.rows, self.appender.median)
+    }
+
+    /// How many times faster the Appender is than per-row `execute`.
+    pub fn speedup(&self) -> f64 {
+        self.appender_rows_per_sec() / self.execute_rows_per_sec()
+    }
+
+    /// Formats both measurements and the speedup.
+    pub fn format_report(&self) -> String {
+        format!(
+            "{}\n{}\n🚀 {} rows: execute {:.0} rows/s, appender {:.0} rows/s (flush every {}), {:.1}x faster",
+            self.execute.format_report(),
+            self.appender.format_report(),
+            self.rows,
+            self.execute_rows_per_sec(),
+            self.appender_rows_per_sec(),
+            self.flush_interval,
+            self.speedup()
+        )
+    }
+}
+
+fn rows_per_sec(rows: usize, elapsed: Duration) -> f64 {
+    rows as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+}
+
+/// Inserts `rows` rows into an in-memory table, once with a prepared
+/// statement executed per row and once with [`BulkInsert`], and reports
+/// the throughput of each.
+///
+/// ```rust
+/// use frozen_duckdb::benchmark::measure_insert_throughput;
+///
+/// let throughput = measure_insert_throughput(1_000, 3, 500)?;
+/// println!("{}", throughput.format_report());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn measure_insert_throughput(
+    rows: usize,
+    iterations: usize,
+    flush_interval: usize,
+) -> Result<InsertThroughput> {
+    let conn = Connection::open_in_memory()?;
+    let reset = || {
+        conn.execute_batch(
+            "CREATE OR REPLACE TABLE bench_insert (id INTEGER, name VARCHAR, value DOUBLE)",
+        )
+    };
+    let row = |i: usize| (i as i32, format!("row_{}", i), i as f64 * 0.5);
+
+    let execute = Benchmark::new("insert (execute per row)")
+        .iterations(iterations)
+        .run(|| {
+            reset()?;
+            let mut stmt = conn.prepare("INSERT INTO bench_insert VALUES (?, ?, ?)")?;
+            for i in 0..rows {
+                let (id, name, value) = row(i);
+                stmt.execute(params![id, name, value])?;
+            }
+            Ok(())
+        })?;
+
+    let appender = Benchmark::new("insert (appender)")
+        .iterations(iterations)
+        .run(|| {
+            reset()?;
+            let mut insert = BulkInsert::new(&conn, "bench_insert", flush_interval)?;
+            for i in 0..rows {
+                let (id, name, value) = row(i);
+                insert.append(params![id, name, value])?;
+            }
+            insert.finish()?;
+            Ok(())
+        })?;
+
+    Ok(InsertThroughput {
+        rows,
+        flush_interval,
+        execute,
+        appender,
+    })
+}
+
+/// Parquet export with DuckDB's default settings versus tuned ones.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportThroughput {
+    /// Rows exported per iteration
+    pub rows: usize,
+    /// Settings of the tuned run
+    pub tuned_options: PerformanceOptions,
+    /// Export with DuckDB's defaults
+    pub default: BenchmarkStats,
+    /// Export with `tuned_options`
+    pub tuned: BenchmarkStats,
+}
+
+impl ExportThroughput {
+    /// How many times faster the tuned export is, from the medians.
+    pub fn speedup(&self) -> f64 {
+        self.default.median.as_secs_f64() / self.tuned.median.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Formats both measurements and the speedup.
+    pub fn format_report(&self) -> String {
+        format!(
+            "{}\n{}\n🚀 {} rows: default {:.0} rows/s, tuned {:.0} rows/s, {:.1}x faster",
+            self.default.format_report(),
+            self.tuned.format_report(),
+            self.rows,
+            rows_per_sec(self.rows, self.default.median),
+            rows_per_sec(self.rows, self.tuned.median),
+            self.speedup()
+        )
+    }
+}
+
+/// Exports `rows` generated rows to a temporary Parquet file, once with
+/// DuckDB's default settings and once with `tuned`, and reports both.
+///
+/// Without a setting for insertion order, the tuned run turns it off,
+/// since that is what lets DuckDB write from all threads at once.
+///
+/// ```rust
+/// use frozen_duckdb::benchmark::measure_export_throughput;
+/// use frozen_duckdb::cli::dataset_manager::PerformanceOptions;
+///
+/// let export = measure_export_throughput(100_000, 3, &PerformanceOptions::default())?;
+/// println!("{}", export.format_report());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn measure_export_throughput(
+    rows: usize,
+    iterations: usize,
+    tuned: &PerformanceOptions,
+) -> Result<ExportThroughput> {
+    let tuned = PerformanceOptions {
+        preserve_insertion_order: tuned.preserve_insertion_order.or(Some(false)),
+        ..tuned.clone()
+    };
+    let dir = tempfile::tempdir().context("Failed to create a temporary directory")?;
+    let output = crate::cli::sql_path::path_literal(dir.path().join("export.parquet"));
+    let conn = Connection::open_in_memory()?;
+    conn.execute_batch(&format!(
+        "CREATE TABLE bench_export AS SELECT i AS id, 'row_' || i AS name, i * 0.5 AS value, \
+         i % 1000 AS bucket FROM range({}) t(i)",
+        rows
+    ))
+    .context("Failed to create the benchmark table")?;
+
+    let run = |name: &str, options: &PerformanceOptions| -> Result<BenchmarkStats> {
+        options.apply(&conn)?;
+        let copy = format!(
+            "COPY bench_export TO {} ({})",
+            output,
+            options.parquet_options("snappy")
+        );
+        Benchmark::new(name)
+            .iterations(iterations)
+            .run(|| Ok(conn.execute_batch(&copy)?))
+    };
+    let default = run(
+        "export (default settings)",
+        &PerformanceOptions {
+            preserve_insertion_order: Some(true),
+            ..Default::default()
+        },
+    )?;
+    let tuned_stats = run("export (tuned settings)", &tuned)?;
+
+    Ok(ExportThroughput {
+        rows,
+        tuned_options: tuned,
+        default,
+        tuned: tuned_stats,
+    })
+}
+
+/// Aggregation timed by [`measure_query_latency`]
+const BENCH_QUERY: &str = "SELECT id % 100 AS bucket, count(*), avg(value), max(name) \
+     FROM bench_query GROUP BY bucket ORDER BY bucket";
+
+/// Runs a `GROUP BY` aggregation over `rows` generated rows and reports
+/// its latency. With `profile_dir`, one more run is profiled and written
+/// there as `benchmark_query.json` and `benchmark_query.html`.
+///
+/// ```rust
+/// use frozen_duckdb::benchmark::measure_query_latency;
+///
+/// let (stats, _) = measure_query_latency(1_000, 5, None)?;
+/// println!("{}", stats.format_report());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn measure_query_latency(
+    rows: usize,
+    iterations: usize,
+    profile_dir: Option<&Path>,
+) -> Result<(BenchmarkStats, Option<QueryProfile>)> {
+    let conn = Connection::open_in_memory()?;
+    conn.execute_batch(&format!(
+        "CREATE TABLE bench_query AS SELECT i::INTEGER AS id, 'row_' || i AS name, \
+         i * 0.5 AS value FROM range({}) t(i)",
+        rows
+    ))
+    .context("Failed to create the benchmark table")?;
+    let run_query = || -> Result<()> {
+        let mut stmt = conn.prepare(BENCH_QUERY)?;
+        let mut results = stmt.query([])?;
+        while results.next()?.is_some() {}
+        Ok(())
+    };
+
+    let stats = Benchmark::new("query (group by)")
+        .warmup(1)
+        .iterations(iterations)
+        .run(run_query)?;
+    let profile = match profile_dir {
+        Some(dir) => Some(profile(&conn, dir, "benchmark_query", run_query)?.1),
+        None => None,
+    };
+    Ok((stats, profile))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -250,4 +842,123 @@ mod tests {
         // Even with an error, we should get a duration measurement
         assert!(duration >= std::time::Duration::from_millis(0));
     }
+
+    #[test]
+    fn test_stats_from_samples() {
+        let samples: Vec<Duration> = [5, 1, 3, 2, 4].iter().map(|ms| Duration::from_millis(*ms)).collect();
+        let stats = BenchmarkStats::from_samples("five", &samples);
+
+        assert_eq!(stats.iterations, 5);
+        assert_eq!(stats.mean, Duration::from_millis(3));
+        assert_eq!(stats.median, Duration::from_millis(3));
+        assert_eq!(stats.p95, Duration::from_millis(5));
+        assert_eq!(stats.min, Duration::from_millis(1));
+        assert_eq!(stats.max, Duration::from_millis(5));
+        // Sample stddev of 1..=5 is sqrt(2.5)
+        assert!((stats.stddev.as_secs_f64() - 2.5f64.sqrt() / 1000.0).abs() < 1e-9);
+
+        let even = BenchmarkStats::from_samples("even", &samples[..4]);
+        assert_eq!(even.median, Duration::from_micros(2500));
+    }
+
+    #[test]
+    fn test_benchmark_runs_warmup_and_iterations() {
+        let mut calls = 0;
+        let stats = Benchmark::new("count")
+            .warmup(2)
+            .iterations(5)
+            .run(|| {
+                calls += 1;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(calls, 7);
+        assert_eq!(stats.iterations, 5);
+
+        let failing = Benchmark::new("fail").run(|| Err(anyhow::anyhow!("boom")));
+        assert!(failing.is_err());
+    }
+
+    #[test]
+    fn test_budget_and_regression_checks() {
+        let ms = |values: &[u64]| values.iter().map(|v| Duration::from_millis(*v)).collect::<Vec<_>>();
+        let baseline = BenchmarkStats::from_samples("op", &ms(&[10, 10, 10]));
+        let current = BenchmarkStats::from_samples("op", &ms(&[11, 12, 12]));
+
+        assert!(current.check_p95(Duration::from_millis(12)).is_ok());
+        assert!(current.check_p95(Duration::from_millis(11)).is_err());
+        assert!((current.change_from(&baseline) - 20.0).abs() < 1e-9);
+        assert!(current.check_regression(&baseline, 25.0).is_ok());
+        assert!(current.check_regression(&baseline, 10.0).is_err());
+    }
+
+    #[test]
+    fn test_baselines_round_trip() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("nested").join("baselines.json");
+        let stats = BenchmarkStats::from_samples("op", &[Duration::from_micros(1500), Duration::from_micros(2500)]);
+
+        let mut baselines = Baselines::load(&path).unwrap();
+        assert!(baselines.get("op").is_none());
+        baselines.record(&stats);
+        baselines.save(&path).unwrap();
+
+        let loaded = Baselines::load(&path).unwrap();
+        assert_eq!(loaded.get("op"), Some(stats));
+    }
+
+    #[test]
+    fn test_peak_memory_json_and_budget() {
+        let stats = BenchmarkStats {
+            peak_rss: Some(150_000_000),
+            ..BenchmarkStats::from_samples("op", &[Duration::from_millis(1)])
+        };
+        let json = stats.to_json();
+        assert_eq!(json["peak_rss_bytes"], 150_000_000);
+        assert_eq!(BenchmarkStats::from_json("op", &json).unwrap(), stats);
+        assert!(stats.format_report().contains("peak memory: 150.0 MB"));
+
+        assert!(stats.check_peak_rss(200_000_000).is_ok());
+        let error = stats.check_peak_rss(100_000_000).unwrap_err();
+        assert_eq!(crate::error::exit_code(&error), 5);
+
+        let unmeasured = BenchmarkStats::from_samples("op", &[Duration::from_millis(1)]);
+        assert!(unmeasured.to_json().get("peak_rss_bytes").is_none());
+        assert!(unmeasured.check_peak_rss(1).is_ok());
+    }
+
+    #[test]
+    fn test_insert_throughput_inserts_every_row() {
+        let throughput = measure_insert_throughput(500, 2, 100).unwrap();
+        assert_eq!(throughput.rows, 500);
+        assert_eq!(throughput.execute.iterations, 2);
+        assert_eq!(throughput.appender.iterations, 2);
+        assert!(throughput.appender_rows_per_sec() > 0.0);
+        assert!(throughput.format_report().contains("rows/s"));
+    }
+
+    #[test]
+    fn test_export_throughput_turns_off_insertion_order() {
+        let export = measure_export_throughput(10_000, 2, &PerformanceOptions::default()).unwrap();
+        assert_eq!(export.default.iterations, 2);
+        assert_eq!(export.tuned.iterations, 2);
+        assert_eq!(export.tuned_options.preserve_insertion_order, Some(false));
+        assert!(export.speedup() > 0.0);
+        assert!(export.format_report().contains("rows/s"));
+    }
+
+    #[test]
+    fn test_query_latency_with_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let (stats, profile) = measure_query_latency(1_000, 2, Some(dir.path())).unwrap();
+        assert_eq!(stats.iterations, 2);
+
+        let profile = profile.expect("profile requested");
+        assert!(profile.query.contains("GROUP BY bucket"));
+        assert!(profile
+            .metrics()
+            .iter()
+            .any(|operator| operator.name.contains("GROUP_BY")));
+        assert!(dir.path().join("benchmark_query.html").exists());
+    }
 }
@@ -0,0 +1,157 @@
+//! # Runtime Capability Report
+//!
+//! The extensions available at runtime depend on how the frozen binary was
+//! built and what has been installed locally. [`capabilities`] reports them
+//! as a typed struct so applications can feature-gate behavior instead of
+//! parsing `frozen-duckdb info` output.
+//!
+//! | Field | Meaning |
+//! |-------|---------|
+//! | [`ExtensionInfo::statically_linked`] | Compiled into the binary; always loadable, even offline |
+//! | [`ExtensionInfo::installed`] | Statically linked or installed in the local extension directory |
+//! | [`ExtensionInfo::loaded`] | Loaded in the connection the report was taken from |
+//! | [`Capabilities::icu_collations`] | Locale-aware collations such as `COLLATE de` (ICU) |
+//! | [`Capabilities::icu_time_zones`] | Named time zones for `TIMESTAMPTZ` (ICU) |
+//!
+//! ## Usage Examples
+//!
+//! ```rust
+//! use frozen_duckdb::capabilities;
+//!
+//! let caps = capabilities()?;
+//! if caps.is_available("inet") {
+//!     println!("INET type supported");
+//! }
+//! if !caps.icu_time_zones {
+//!     println!("Time zone conversions unavailable; storing UTC only");
+//! }
+//! println!("{}", caps.to_json());
+//! # Ok::<(), frozen_duckdb::DuckDBError>(())
+//! ```
+
+use crate::duckdb::Connection;
+use crate::Result;
+
+/// How one extension is available to this DuckDB build.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionInfo {
+    /// Extension name, e.g. `parquet`
+    pub name: String,
+    /// Compiled into the DuckDB binary
+    pub statically_linked: bool,
+    /// Statically linked or installed locally, so `LOAD` works offline
+    pub installed: bool,
+    /// Loaded in the inspected connection
+    pub loaded: bool,
+}
+
+/// Extensions and features available at runtime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    /// DuckDB library version, e.g. `v1.4.0`
+    pub version: String,
+    /// Every extension DuckDB knows about, sorted by name
+    pub extensions: Vec<ExtensionInfo>,
+    /// Locale-aware collations are available (ICU)
+    pub icu_collations: bool,
+    /// Named time zones are available (ICU)
+    pub icu_time_zones: bool,
+}
+
+/// Reports the capabilities of the linked DuckDB library, using a fresh
+/// in-memory database.
+pub fn capabilities() -> Result<Capabilities> {
+    Capabilities::detect(&Connection::open_in_memory()?)
+}
+
+impl Capabilities {
+    /// Reports the capabilities visible to `conn`.
+    ///
+    /// ICU is loaded into `conn` if it is installed, to check collation and
+    /// time zone support.
+    pub fn detect(conn: &Connection) -> Result<Self> {
+        let version: String = conn.query_row("SELECT version()", [], |row| row.get(0))?;
+
+        // Loading ICU is the only way to see its collations and time zones
+        let _ = conn.execute_batch("LOAD icu;");
+        let icu_collations = count(
+            conn,
+            "SELECT COUNT(*) FROM pragma_collations() WHERE collname = 'de'",
+        ) > 0;
+        let icu_time_zones = count(conn, "SELECT COUNT(*) FROM pg_timezone_names()") > 1;
+
+        let mut stmt = conn.prepare(
+            "SELECT extension_name, install_mode = 'STATICALLY_LINKED', installed, loaded
+             FROM duckdb_extensions() ORDER BY extension_name",
+        )?;
+        let extensions = stmt
+            .query_map([], |row| {
+                Ok(ExtensionInfo {
+                    name: row.get(0)?,
+                    statically_linked: row.get::<_, Option<bool>>(1)?.unwrap_or(false),
+                    installed: row.get(2)?,
+                    loaded: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            version,
+            extensions,
+            icu_collations,
+            icu_time_zones,
+        })
+    }
+
+    /// Returns the extension named `name`, if DuckDB knows about it.
+    pub fn extension(&self, name: &str) -> Option<&ExtensionInfo> {
+        self.extensions.iter().find(|e| e.name == name)
+    }
+
+    /// Whether `name` can be loaded without network access.
+    pub fn is_available(&self, name: &str) -> bool {
+        self.extension(name)
+            .is_some_and(|e| e.statically_linked || e.installed || e.loaded)
+    }
+
+    /// Names of extensions compiled into the binary.
+    pub fn statically_linked(&self) -> Vec<&str> {
+        self.extensions
+            .iter()
+            .filter(|e| e.statically_linked)
+            .map(|e| e.name.as_str())
+            .collect()
+    }
+
+    /// Names of extensions installed locally but not compiled in.
+    pub fn loadable(&self) -> Vec<&str> {
+        self.extensions
+            .iter()
+            .filter(|e| e.installed && !e.statically_linked)
+            .map(|e| e.name.as_str())
+            .collect()
+    }
+
+    /// Converts the report to JSON, e.g. for `frozen-duckdb info --format json`.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "version": self.version,
+            "statically_linked": self.statically_linked(),
+            "loadable": self.loadable(),
+            "extensions": self.extensions.iter().map(|e| serde_json::json!({
+                "name": e.name,
+                "statically_linked": e.statically_linked,
+                "installed": e.installed,
+                "loaded": e.loaded,
+            })).collect::<Vec<_>>(),
+            "icu": {
+                "collations": self.icu_collations,
+                "time_zones": self.icu_time_zones,
+            },
+        })
+    }
+}
+
+fn count(conn: &Connection, sql: &str) -> i64 {
+    conn.query_row(sql, [], |row| row.get(0)).unwrap_or(0)
+}
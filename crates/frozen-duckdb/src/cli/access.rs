@@ -0,0 +1,642 @@
+//! # Role-Based Access Control for the HTTP Server
+//!
+//! A single `--token` is all or nothing. To expose `frozen-duckdb serve`
+//! beyond localhost, API keys can instead be mapped to roles, each allowed
+//! a list of statement types and, optionally, a list of schemas:
+//!
+//! | Setting | Description |
+//! |---------|-------------|
+//! | `statements` | Statement types the role may run, e.g. `select`, `insert`, `create` |
+//! | `schemas` | Schemas whose tables and views the role may read; any schema without it |
+//!
+//! Requests send the key as `Authorization: Bearer <key>`. Before running
+//! a query the server parses it with DuckDB's own parser: every statement
+//! must be of an allowed type, so `SELECT 1; DROP TABLE sales` is refused
+//! as a whole. Nothing runs until all statements pass. Refused queries get
+//! `403`; keys that match no role get `401`.
+//!
+//! ## Schemas
+//!
+//! Table references can only be resolved for `SELECT`, so a role with
+//! `schemas` may only run `select`. Every table or view a query reads must
+//! exist in one of the schemas; unqualified names are looked up in `main`,
+//! and names of the query's own `WITH` clauses are always allowed. Table
+//! functions such as `read_csv` and file paths in `FROM` are refused,
+//! since they reach outside the database. Views are checked by name, not by
+//! what they read, so a view in an allowed schema can expose other tables.
+//!
+//! ## Configuration
+//!
+//! Roles and keys live in the `serve` section of the config file. A key is
+//! given either inline or, preferably, as the name of an environment
+//! variable holding it:
+//!
+//! ```json
+//! {
+//!   "serve": {
+//!     "roles": {
+//!       "analyst": { "statements": ["select"], "schemas": ["reporting"] },
+//!       "loader": { "statements": ["select", "insert", "create"] }
+//!     },
+//!     "api_keys": [
+//!       { "role": "analyst", "key_env": "ANALYST_API_KEY" },
+//!       { "role": "loader", "key": "d1c4e5f0a7b2" }
+//!     ]
+//!   }
+//! }
+//! ```
+//!
+//! Roles only apply to the HTTP API. `--token` keeps working alongside them
+//! and allows every statement, like an administrator key. Statements that
+//! write still need `--read-write`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use duckdb::{Connection, StatementType};
+//! use frozen_duckdb::cli::access::{AccessControl, Role, Verdict};
+//!
+//! let conn = Connection::open_in_memory()?;
+//! conn.execute_batch("CREATE SCHEMA reporting; CREATE TABLE reporting.sales(amount INTEGER)")?;
+//!
+//! let access = AccessControl::default()
+//!     .with_role(Role::new("analyst", vec![StatementType::Select]).with_schemas(["reporting"]))
+//!     .with_key("s3cret", "analyst")?;
+//! let role = access.role("s3cret").unwrap();
+//!
+//! assert_eq!(role.check(&conn, "SELECT SUM(amount) FROM reporting.sales")?, Verdict::Allowed);
+//! assert!(matches!(role.check(&conn, "DELETE FROM reporting.sales")?, Verdict::Denied(_)));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use super::server::constant_time_eq;
+use anyhow::{anyhow, bail, Context, Result};
+use duckdb::{params, Connection, StatementType};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Schema of unqualified table names.
+const DEFAULT_SCHEMA: &str = "main";
+
+/// Table reference types that read from outside the database's tables.
+const EXTERNAL_TABLE_REFS: &[&str] = &["TABLE_FUNCTION", "SHOW_REF", "COLUMN_DATA"];
+
+/// Parses a statement type as written in the config file, e.g. `select`.
+pub fn parse_statement_type(value: &str) -> Result<StatementType> {
+    Ok(match value.to_lowercase().as_str() {
+        "select" => StatementType::Select,
+        "insert" => StatementType::Insert,
+        "update" => StatementType::Update,
+        "delete" => StatementType::Delete,
+        "explain" => StatementType::Explain,
+        "create" => StatementType::Create,
+        "create_func" => StatementType::CreateFunc,
+        "alter" => StatementType::Alter,
+        "drop" => StatementType::Drop,
+        "copy" => StatementType::Copy,
+        "export" => StatementType::Export,
+        "transaction" => StatementType::Transaction,
+        "prepare" => StatementType::Prepare,
+        "execute" => StatementType::Execute,
+        "analyze" => StatementType::Analyze,
+        "vacuum" => StatementType::Vacuum,
+        "pragma" => StatementType::Pragma,
+        "call" => StatementType::Call,
+        "set" => StatementType::Set,
+        "variable_set" => StatementType::VariableSet,
+        "load" => StatementType::Load,
+        "attach" => StatementType::Attach,
+        "detach" => StatementType::Detach,
+        other => bail!(
+            "Unknown statement type: {} (use e.g. select, insert, update, delete, create)",
+            other
+        ),
+    })
+}
+
+/// Outcome of checking a query against a [`Role`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// Every statement may run
+    Allowed,
+    /// The query is refused, for the given reason
+    Denied(String),
+}
+
+/// What the holders of an API key may run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Role {
+    /// Name of the role, used in logs and refusals
+    pub name: String,
+    /// Statement types the role may run
+    pub statements: Vec<StatementType>,
+    /// Schemas the role may read from, or `None` for any
+    pub schemas: Option<Vec<String>>,
+}
+
+impl Role {
+    /// Creates a role allowed `statements` in any schema.
+    pub fn new(name: &str, statements: Vec<StatementType>) -> Self {
+        Self {
+            name: name.to_string(),
+            statements,
+            schemas: None,
+        }
+    }
+
+    /// Restricts the role to tables and views in `schemas`.
+    pub fn with_schemas<I, S>(mut self, schemas: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.schemas = Some(schemas.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Reads a role from its entry in `serve.roles`.
+    fn from_value(name: &str, value: &Value) -> Result<Self> {
+        let names = |key: &str| -> Result<Option<Vec<&str>>> {
+            let Some(value) = value.get(key) else {
+                return Ok(None);
+            };
+            let list = value
+                .as_array()
+                .and_then(|items| items.iter().map(Value::as_str).collect::<Option<Vec<_>>>())
+                .ok_or_else(|| anyhow!("serve.roles.{}.{} must be a list of strings", name, key))?;
+            Ok(Some(list))
+        };
+
+        let statements = names("statements")?
+            .ok_or_else(|| anyhow!("serve.roles.{} needs a statements list", name))?
+            .into_iter()
+            .map(parse_statement_type)
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("Invalid serve.roles.{}.statements", name))?;
+        let mut role = Self::new(name, statements);
+        if let Some(schemas) = names("schemas")? {
+            role = role.with_schemas(schemas);
+        }
+        role.validate()?;
+        Ok(role)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.statements.is_empty() {
+            bail!("Role {} allows no statements", self.name);
+        }
+        if let Some(schemas) = &self.schemas {
+            if schemas.is_empty() {
+                bail!("Role {} allows no schemas", self.name);
+            }
+            if let Some(other) = self
+                .statements
+                .iter()
+                .find(|t| **t != StatementType::Select)
+            {
+                bail!(
+                    "Role {} limits schemas, which only works for select statements, \
+                     but also allows {}",
+                    self.name,
+                    other.as_str().to_lowercase()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks whether the role may run `sql`, parsing it with the DuckDB
+    /// parser of `conn` without running any of it.
+    ///
+    /// Fails if `sql` doesn't parse or refers to objects that don't exist.
+    pub fn check(&self, conn: &Connection, sql: &str) -> Result<Verdict> {
+        // Tables are vetted before any statement is bound, since binding a
+        // table function already reads its input
+        if let Some(schemas) = &self.schemas {
+            let verdict = self.check_tables(conn, schemas, sql)?;
+            if verdict != Verdict::Allowed {
+                return Ok(verdict);
+            }
+        }
+
+        let types = conn.statement_types(sql).context("Failed to parse query")?;
+        match types.iter().find(|t| !self.statements.contains(t)) {
+            Some(denied) => Ok(Verdict::Denied(format!(
+                "Role {} may not run {} statements",
+                self.name,
+                denied.as_str()
+            ))),
+            None => Ok(Verdict::Allowed),
+        }
+    }
+
+    fn check_tables(&self, conn: &Connection, schemas: &[String], sql: &str) -> Result<Verdict> {
+        let Some(references) = TableReferences::parse(conn, sql)? else {
+            return Ok(Verdict::Denied(format!(
+                "Role {} may only run SELECT statements",
+                self.name
+            )));
+        };
+        if let Some(kind) = references.external.first() {
+            return Ok(Verdict::Denied(format!(
+                "Role {} may only read tables and views, not a {}",
+                self.name,
+                kind.to_lowercase()
+            )));
+        }
+
+        for table in &references.tables {
+            let schema = table.schema.as_deref().unwrap_or(DEFAULT_SCHEMA);
+            let allowed = schemas.iter().any(|s| s.eq_ignore_ascii_case(schema))
+                && table_exists(conn, table.catalog.as_deref(), schema, &table.name)?;
+            if !allowed {
+                return Ok(Verdict::Denied(format!(
+                    "Role {} may not read {}.{}",
+                    self.name, schema, table.name
+                )));
+            }
+        }
+        Ok(Verdict::Allowed)
+    }
+}
+
+/// A table or view named in a query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TableName {
+    catalog: Option<String>,
+    schema: Option<String>,
+    name: String,
+}
+
+/// What the `FROM` clauses of a query read.
+#[derive(Debug, Default)]
+struct TableReferences {
+    /// Tables and views, without references to `WITH` clauses in scope
+    tables: Vec<TableName>,
+    /// Names of the `WITH` clauses in scope while visiting, lowercased
+    ctes: Vec<String>,
+    /// Types of references reaching outside the database
+    external: Vec<String>,
+}
+
+impl TableReferences {
+    /// Collects the references of `sql` from the syntax tree of
+    /// `json_serialize_sql`, or returns `None` if `sql` has statements other
+    /// than `SELECT`, which it can't serialize.
+    fn parse(conn: &Connection, sql: &str) -> Result<Option<Self>> {
+        let tree: String = conn
+            .query_row("SELECT json_serialize_sql(?)", [sql], |row| row.get(0))
+            .context("Failed to parse query")?;
+        let tree: Value = serde_json::from_str(&tree).context("Invalid syntax tree")?;
+        let error_type = tree["error_type"].as_str().unwrap_or_default();
+        if error_type.eq_ignore_ascii_case("not implemented") {
+            return Ok(None);
+        }
+        if tree["error"].as_bool() != Some(false) {
+            bail!(
+                "Failed to parse query: {}",
+                tree["error_message"].as_str().unwrap_or("unknown error")
+            );
+        }
+
+        let mut references = Self::default();
+        references.visit(&tree["statements"]);
+        Ok(Some(references))
+    }
+
+    /// Visits a node of the syntax tree. A `WITH` clause is only in scope
+    /// within the query node defining it, and in the clauses after it.
+    fn visit(&mut self, value: &Value) {
+        let object = match value {
+            Value::Array(items) => return items.iter().for_each(|item| self.visit(item)),
+            Value::Object(object) => object,
+            _ => return,
+        };
+        let outer_scope = self.ctes.len();
+        match object.get("type").and_then(Value::as_str) {
+            Some("BASE_TABLE") => {
+                let part = |key: &str| {
+                    object
+                        .get(key)
+                        .and_then(Value::as_str)
+                        .filter(|s| !s.is_empty())
+                        .map(str::to_string)
+                };
+                let table = TableName {
+                    catalog: part("catalog_name"),
+                    schema: part("schema_name"),
+                    name: part("table_name").unwrap_or_default(),
+                };
+                let unqualified = table.catalog.is_none() && table.schema.is_none();
+                if !(unqualified && self.ctes.contains(&table.name.to_lowercase())) {
+                    self.tables.push(table);
+                }
+            }
+            Some(kind) if EXTERNAL_TABLE_REFS.contains(&kind) => {
+                self.external.push(kind.to_string());
+            }
+            // The recursive part of `WITH RECURSIVE` reads the clause itself
+            Some("RECURSIVE_CTE_NODE") => {
+                let name = object.get("cte_name").or_else(|| object.get("ctename"));
+                self.ctes
+                    .extend(name.and_then(Value::as_str).map(str::to_lowercase));
+            }
+            _ => {}
+        }
+
+        let ctes = object.get("cte_map").and_then(|m| m["map"].as_array());
+        for entry in ctes.into_iter().flatten() {
+            self.visit(&entry["value"]);
+            self.ctes
+                .extend(entry["key"].as_str().map(str::to_lowercase));
+        }
+        for (key, child) in object {
+            if key != "cte_map" {
+                self.visit(child);
+            }
+        }
+        self.ctes.truncate(outer_scope);
+    }
+}
+
+/// Returns whether a table or view `schema.name` exists in `catalog`, or
+/// in the current database without one.
+fn table_exists(
+    conn: &Connection,
+    catalog: Option<&str>,
+    schema: &str,
+    name: &str,
+) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM information_schema.tables
+         WHERE lower(table_catalog) = lower(COALESCE(?, current_database()))
+           AND lower(table_schema) = lower(?) AND lower(table_name) = lower(?)",
+        params![catalog, schema, name],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// API keys and the roles they grant, from the `serve` section of the
+/// config file.
+#[derive(Clone, Default)]
+pub struct AccessControl {
+    roles: BTreeMap<String, Role>,
+    keys: Vec<(String, String)>,
+}
+
+impl AccessControl {
+    /// Adds `role`, replacing any role of the same name.
+    pub fn with_role(mut self, role: Role) -> Self {
+        self.roles.insert(role.name.clone(), role);
+        self
+    }
+
+    /// Grants `role`, which must have been added, to holders of `key`.
+    pub fn with_key(mut self, key: impl Into<String>, role: &str) -> Result<Self> {
+        let key = key.into();
+        if key.is_empty() {
+            bail!("The API key of role {} is empty", role);
+        }
+        if !self.roles.contains_key(role) {
+            bail!("Unknown role: {}", role);
+        }
+        if self.keys.iter().any(|(k, _)| *k == key) {
+            bail!("The same API key is listed more than once");
+        }
+        self.keys.push((key, role.to_string()));
+        Ok(self)
+    }
+
+    /// Reads the `serve` section; `None` if it lists no API keys.
+    ///
+    /// Keys given by `key_env` are read from the environment now, and a
+    /// missing variable is an error rather than a key that never matches.
+    pub fn from_section(section: &Map<String, Value>) -> Result<Option<Self>> {
+        let Some(keys) = section.get("api_keys") else {
+            return Ok(None);
+        };
+        let keys = keys
+            .as_array()
+            .ok_or_else(|| anyhow!("serve.api_keys must be a list"))?;
+
+        let mut access = Self::default();
+        if let Some(roles) = section.get("roles") {
+            let roles = roles
+                .as_object()
+                .ok_or_else(|| anyhow!("serve.roles must be an object"))?;
+            for (name, value) in roles {
+                access = access.with_role(Role::from_value(name, value)?);
+            }
+        }
+
+        for (i, entry) in keys.iter().enumerate() {
+            let field = |key: &str| entry.get(key).and_then(Value::as_str);
+            let role =
+                field("role").ok_or_else(|| anyhow!("serve.api_keys[{}] needs a role", i))?;
+            let key = match (field("key"), field("key_env")) {
+                (Some(key), None) => key.to_string(),
+                (None, Some(var)) => std::env::var(var).map_err(|_| {
+                    anyhow!(
+                        "serve.api_keys[{}]: environment variable {} is not set",
+                        i,
+                        var
+                    )
+                })?,
+                _ => bail!("serve.api_keys[{}] needs either key or key_env", i),
+            };
+            access = access
+                .with_key(key, role)
+                .with_context(|| format!("Invalid serve.api_keys[{}]", i))?;
+        }
+        if access.keys.is_empty() {
+            bail!("serve.api_keys lists no keys");
+        }
+        Ok(Some(access))
+    }
+
+    /// Returns the role granted by `key`, comparing against every key in
+    /// constant time.
+    pub fn role(&self, key: &str) -> Option<&Role> {
+        let mut found = None;
+        for (candidate, role) in &self.keys {
+            if constant_time_eq(key.as_bytes(), candidate.as_bytes()) {
+                found = Some(role);
+            }
+        }
+        found.and_then(|role| self.roles.get(role))
+    }
+
+    /// Returns the configured roles.
+    pub fn roles(&self) -> impl Iterator<Item = &Role> {
+        self.roles.values()
+    }
+}
+
+impl fmt::Debug for AccessControl {
+    // Keys are secrets; only say how many there are
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AccessControl")
+            .field("roles", &self.roles)
+            .field("keys", &self.keys.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(json: &str) -> Map<String, Value> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE SCHEMA reporting;
+             CREATE TABLE reporting.sales(region VARCHAR, amount INTEGER);
+             CREATE VIEW reporting.totals AS SELECT region, SUM(amount) AS total
+                 FROM reporting.sales GROUP BY region;
+             CREATE TABLE main.salaries(name VARCHAR, salary INTEGER);",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn denied(verdict: Verdict) -> bool {
+        matches!(verdict, Verdict::Denied(_))
+    }
+
+    #[test]
+    fn test_config_section() -> Result<()> {
+        assert!(AccessControl::from_section(&Map::new())?.is_none());
+
+        let access = AccessControl::from_section(&section(
+            r#"{
+                "roles": {
+                    "analyst": { "statements": ["select"], "schemas": ["reporting"] },
+                    "loader": { "statements": ["SELECT", "insert", "create"] }
+                },
+                "api_keys": [
+                    { "role": "analyst", "key": "a-key" },
+                    { "role": "loader", "key": "l-key" }
+                ]
+            }"#,
+        ))?
+        .unwrap();
+
+        let analyst = access.role("a-key").unwrap();
+        assert_eq!(analyst.statements, vec![StatementType::Select]);
+        assert_eq!(analyst.schemas, Some(vec!["reporting".to_string()]));
+        let loader = access.role("l-key").unwrap();
+        assert_eq!(loader.statements.len(), 3);
+        assert_eq!(loader.schemas, None);
+        assert!(access.role("a-ke").is_none());
+        assert!(access.role("").is_none());
+
+        // Keys don't leak through Debug
+        assert!(!format!("{:?}", access).contains("a-key"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let invalid = [
+            r#"{"api_keys": {}}"#,
+            r#"{"api_keys": []}"#,
+            r#"{"api_keys": [{"role": "nobody", "key": "k"}]}"#,
+            r#"{"roles": {"r": {"statements": ["select"]}}, "api_keys": [{"role": "r"}]}"#,
+            r#"{"roles": {"r": {"statements": ["select"]}},
+                "api_keys": [{"role": "r", "key": "k"}, {"role": "r", "key": "k"}]}"#,
+            r#"{"roles": {"r": {"statements": ["select"]}},
+                "api_keys": [{"role": "r", "key_env": "FROZEN_DUCKDB_TEST_UNSET_KEY"}]}"#,
+            r#"{"roles": {"r": {"statements": []}}, "api_keys": []}"#,
+            r#"{"roles": {"r": {"statements": ["selec"]}}, "api_keys": []}"#,
+            r#"{"roles": {"r": {"statements": ["select", "insert"], "schemas": ["main"]}},
+                "api_keys": []}"#,
+        ];
+        for json in invalid {
+            assert!(
+                AccessControl::from_section(&section(json)).is_err(),
+                "{}",
+                json
+            );
+        }
+    }
+
+    #[test]
+    fn test_statement_types() -> Result<()> {
+        let conn = database();
+        let reader = Role::new("reader", vec![StatementType::Select]);
+
+        assert_eq!(
+            reader.check(&conn, "SELECT * FROM salaries")?,
+            Verdict::Allowed
+        );
+        assert_eq!(
+            reader.check(&conn, "FROM reporting.sales LIMIT 1")?,
+            Verdict::Allowed
+        );
+        assert!(denied(reader.check(&conn, "DELETE FROM salaries")?));
+        assert!(denied(
+            reader.check(&conn, "SELECT 1; DROP TABLE salaries")?
+        ));
+        assert!(denied(reader.check(&conn, "ATTACH 'other.duckdb'")?));
+        assert!(reader.check(&conn, "SELEC 1").is_err());
+
+        // Nothing ran while checking
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM information_schema.tables WHERE table_name = 'salaries'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(count, 1);
+
+        let loader = Role::new("loader", vec![StatementType::Select, StatementType::Insert]);
+        assert_eq!(
+            loader.check(&conn, "INSERT INTO salaries VALUES ('a', 1); SELECT 1")?,
+            Verdict::Allowed
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_schemas() -> Result<()> {
+        let conn = database();
+        let analyst = Role::new("analyst", vec![StatementType::Select]).with_schemas(["reporting"]);
+
+        let allowed = [
+            "SELECT SUM(amount) FROM reporting.sales",
+            "SELECT * FROM REPORTING.Totals",
+            "SELECT 42",
+            "WITH big AS (SELECT * FROM reporting.sales WHERE amount > 10) SELECT * FROM big",
+            "SELECT * FROM reporting.sales s JOIN reporting.totals t USING (region)",
+        ];
+        for sql in allowed {
+            assert_eq!(analyst.check(&conn, sql)?, Verdict::Allowed, "{}", sql);
+        }
+
+        let refused = [
+            "SELECT * FROM salaries",
+            "SELECT * FROM main.salaries",
+            "SELECT * FROM reporting.sales WHERE amount > (SELECT MAX(salary) FROM salaries)",
+            "SELECT * FROM reporting.sales UNION ALL SELECT name, salary FROM salaries",
+            // A WITH clause inside a subquery doesn't hide the table outside it
+            "SELECT * FROM (WITH salaries AS (SELECT 'x', 1) SELECT * FROM salaries) \
+             UNION ALL SELECT * FROM salaries",
+            "WITH salaries AS (SELECT * FROM salaries) SELECT * FROM salaries",
+            "WITH a AS (SELECT * FROM b), b AS (SELECT 1) SELECT * FROM a",
+            "SELECT * FROM read_csv('/etc/passwd')",
+            "SELECT * FROM 'data.parquet'",
+        ];
+        for sql in refused {
+            assert!(denied(analyst.check(&conn, sql)?), "{}", sql);
+        }
+        assert!(denied(analyst.check(&conn, "DELETE FROM reporting.sales")?));
+        assert!(denied(analyst.check(&conn, "PRAGMA database_list")?));
+        assert!(analyst.check(&conn, "SELECT * FROM").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,472 @@
+//! # Audit Log of LLM Interactions
+//!
+//! This module records every prompt sent to a model and the response it
+//! produced, for compliance review. Auditing is opt-in: it is enabled with
+//! `frozen-duckdb audit enable`, which stores the policy in the `audit`
+//! section of `~/.frozen-duckdb/config.json`.
+//!
+//! ## Record Schema
+//!
+//! ```text
+//! llm_audit_log (logged_at TIMESTAMP, command VARCHAR, model VARCHAR,
+//!                prompt_hash VARCHAR, prompt VARCHAR, response VARCHAR,
+//!                latency_ms BIGINT, cached BOOLEAN)
+//! ```
+//!
+//! Under the `hash` policy only the SHA-256 hash of each prompt is kept
+//! (`prompt` is NULL); under the `full` policy the prompt text is kept too.
+//! Responses served from the response cache are logged with `cached` set.
+//! Embedding calls are logged with a `[embedding: N dims]` response.
+//!
+//! [Guardrail](super::guardrails) detections are kept apart from model
+//! calls, in a `guardrail_events (logged_at, command, stage, rule, action,
+//! detail)` table, or as JSONL lines with `"event": "guardrail"`.
+//!
+//! ## Storage
+//!
+//! Records go to a DuckDB database (default `~/.frozen-duckdb/audit.duckdb`)
+//! or are appended to a JSONL file, one JSON object per line.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use frozen_duckdb::cli::audit_log::{AuditConfig, AuditLog, AuditPolicy, AuditSink};
+//! use std::time::Duration;
+//!
+//! let config = AuditConfig {
+//!     policy: AuditPolicy::Full,
+//!     sink: AuditSink::Jsonl("audit.jsonl".into()),
+//! };
+//! let log = AuditLog::open(&config, "complete")?;
+//! log.record("coder", "Explain recursion", "Recursion is...", Duration::from_millis(850), false)?;
+//! ```
+
+use super::dedupe::copy_format;
+use super::sql_path::path_literal;
+use anyhow::{Context, Result};
+use duckdb::Connection;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const CONFIG_DIR: &str = ".frozen-duckdb";
+const AUDIT_DATABASE: &str = "audit.duckdb";
+
+/// How much of each prompt is kept in the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditPolicy {
+    /// Keep only the SHA-256 hash of the prompt
+    Hash,
+    /// Keep the full prompt text
+    Full,
+}
+
+impl AuditPolicy {
+    /// Parses `hash` or `full`.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "hash" => Ok(Self::Hash),
+            "full" => Ok(Self::Full),
+            other => Err(anyhow::anyhow!(
+                "Unknown audit policy: {} (use hash or full)",
+                other
+            )),
+        }
+    }
+
+    /// Returns the name used in the config file.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hash => "hash",
+            Self::Full => "full",
+        }
+    }
+}
+
+/// Where audit records are written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditSink {
+    /// `llm_audit_log` table in a DuckDB database
+    Database(PathBuf),
+    /// JSONL file, one record per line
+    Jsonl(PathBuf),
+}
+
+impl AuditSink {
+    /// Returns the default sink, `~/.frozen-duckdb/audit.duckdb`.
+    pub fn default_database() -> Result<Self> {
+        let home = env::var("HOME").context("HOME environment variable not set")?;
+        Ok(Self::Database(
+            Path::new(&home).join(CONFIG_DIR).join(AUDIT_DATABASE),
+        ))
+    }
+
+    /// Returns the file records are written to.
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Database(path) | Self::Jsonl(path) => path,
+        }
+    }
+}
+
+/// Audit settings stored in the `audit` section of the config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditConfig {
+    /// How much of each prompt is kept
+    pub policy: AuditPolicy,
+    /// Where records are written
+    pub sink: AuditSink,
+}
+
+/// Append-only log of LLM interactions for one CLI command.
+pub struct AuditLog {
+    /// Connection used to hash prompts and, for database sinks, store records
+    conn: Connection,
+    config: AuditConfig,
+    /// CLI command the interactions belong to
+    command: String,
+}
+
+impl AuditLog {
+    /// Opens the configured sink for logging interactions of `command`.
+    pub fn open(config: &AuditConfig, command: &str) -> Result<Self> {
+        let conn = match &config.sink {
+            AuditSink::Database(path) => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let conn = Connection::open(path).with_context(|| {
+                    format!("Failed to open audit database: {}", path.display())
+                })?;
+                conn.execute_batch(CREATE_AUDIT_TABLE)
+                    .context("Failed to create audit log table")?;
+                conn
+            }
+            AuditSink::Jsonl(path) => {
+                if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    fs::create_dir_all(parent)?;
+                }
+                Connection::open_in_memory()?
+            }
+        };
+
+        Ok(Self {
+            conn,
+            config: config.clone(),
+            command: command.to_string(),
+        })
+    }
+
+    /// Appends one prompt/response pair to the log.
+    pub fn record(
+        &self,
+        model: &str,
+        prompt: &str,
+        response: &str,
+        latency: Duration,
+        cached: bool,
+    ) -> Result<()> {
+        let prompt_text = match self.config.policy {
+            AuditPolicy::Full => Some(prompt),
+            AuditPolicy::Hash => None,
+        };
+        let latency_ms = latency.as_millis().to_string();
+
+        match &self.config.sink {
+            AuditSink::Database(_) => {
+                self.conn.execute(
+                    "INSERT INTO llm_audit_log
+                     VALUES (current_timestamp::TIMESTAMP, ?, ?, sha256(?), ?, ?, CAST(? AS BIGINT), CAST(? AS BOOLEAN))",
+                    duckdb::params_from_iter([
+                        Some(self.command.as_str()),
+                        Some(model),
+                        Some(prompt),
+                        prompt_text,
+                        Some(response),
+                        Some(latency_ms.as_str()),
+                        Some(if cached { "true" } else { "false" }),
+                    ]),
+                )?;
+            }
+            AuditSink::Jsonl(path) => {
+                let (logged_at, prompt_hash): (String, String) = self.conn.query_row(
+                    "SELECT strftime(current_timestamp::TIMESTAMP, '%Y-%m-%d %H:%M:%S.%g'), sha256(?)",
+                    [prompt],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )?;
+                let entry = serde_json::json!({
+                    "logged_at": logged_at,
+                    "command": self.command,
+                    "model": model,
+                    "prompt_hash": prompt_hash,
+                    "prompt": prompt_text,
+                    "response": response,
+                    "latency_ms": latency.as_millis() as u64,
+                    "cached": cached,
+                });
+
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Failed to open audit log: {}", path.display()))?;
+                writeln!(file, "{}", entry)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends a guardrail event: `rule` matched text at `stage` (e.g.
+    /// `context` or `output`) and `action` was taken.
+    pub fn record_guardrail(
+        &self,
+        stage: &str,
+        rule: &str,
+        action: &str,
+        detail: &str,
+    ) -> Result<()> {
+        match &self.config.sink {
+            AuditSink::Database(_) => {
+                self.conn.execute(
+                    "INSERT INTO guardrail_events VALUES (current_timestamp::TIMESTAMP, ?, ?, ?, ?, ?)",
+                    [self.command.as_str(), stage, rule, action, detail],
+                )?;
+            }
+            AuditSink::Jsonl(path) => {
+                let logged_at: String = self.conn.query_row(
+                    "SELECT strftime(current_timestamp::TIMESTAMP, '%Y-%m-%d %H:%M:%S.%g')",
+                    [],
+                    |row| row.get(0),
+                )?;
+                let entry = serde_json::json!({
+                    "logged_at": logged_at,
+                    "event": "guardrail",
+                    "command": self.command,
+                    "stage": stage,
+                    "rule": rule,
+                    "action": action,
+                    "detail": detail,
+                });
+
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Failed to open audit log: {}", path.display()))?;
+                writeln!(file, "{}", entry)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+const CREATE_AUDIT_TABLE: &str = "CREATE TABLE IF NOT EXISTS llm_audit_log (
+    logged_at TIMESTAMP,
+    command VARCHAR,
+    model VARCHAR,
+    prompt_hash VARCHAR,
+    prompt VARCHAR,
+    response VARCHAR,
+    latency_ms BIGINT,
+    cached BOOLEAN
+);
+CREATE TABLE IF NOT EXISTS guardrail_events (
+    logged_at TIMESTAMP,
+    command VARCHAR,
+    stage VARCHAR,
+    rule VARCHAR,
+    action VARCHAR,
+    detail VARCHAR
+);";
+
+/// Filters applied when exporting audit records.
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    /// Only records newer than this
+    pub since: Option<Duration>,
+    /// Only records of this CLI command
+    pub command: Option<String>,
+    /// Only records of this model
+    pub model: Option<String>,
+}
+
+/// Copies audit records to a CSV, Parquet, or JSON file, returning how many were exported.
+pub fn export(config: &AuditConfig, output: &str, filter: &ExportFilter) -> Result<usize> {
+    let conn = Connection::open_in_memory()?;
+    let path = config.sink.path();
+    if !path.exists() {
+        return Err(anyhow::anyhow!("No audit log found at {}", path.display()));
+    }
+
+    let literal = path_literal(path);
+    let source = match &config.sink {
+        AuditSink::Database(_) => {
+            conn.execute_batch(&format!("ATTACH {} AS audit (READ_ONLY);", literal))?;
+            "audit.llm_audit_log".to_string()
+        }
+        // Guardrail events share the file; they have no prompt hash
+        AuditSink::Jsonl(_) => format!(
+            "(SELECT * FROM read_json({}, format = 'newline_delimited', columns = {{
+                 logged_at: 'TIMESTAMP', command: 'VARCHAR', model: 'VARCHAR',
+                 prompt_hash: 'VARCHAR', prompt: 'VARCHAR', response: 'VARCHAR',
+                 latency_ms: 'BIGINT', cached: 'BOOLEAN'
+             }}) WHERE prompt_hash IS NOT NULL)",
+            literal
+        ),
+    };
+
+    let mut conditions = Vec::new();
+    let mut params = Vec::new();
+    if let Some(since) = filter.since {
+        conditions
+            .push("epoch(current_timestamp::TIMESTAMP) - epoch(logged_at) <= CAST(? AS BIGINT)");
+        params.push(since.as_secs().to_string());
+    }
+    if let Some(command) = &filter.command {
+        conditions.push("command = ?");
+        params.push(command.clone());
+    }
+    if let Some(model) = &filter.model {
+        conditions.push("model = ?");
+        params.push(model.clone());
+    }
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    };
+
+    conn.execute_batch(&format!(
+        "CREATE TEMP TABLE audit_export AS SELECT * FROM {} LIMIT 0",
+        source
+    ))?;
+    let exported = conn.execute(
+        &format!(
+            "INSERT INTO audit_export SELECT * FROM {}{} ORDER BY logged_at",
+            source, where_clause
+        ),
+        duckdb::params_from_iter(params),
+    )?;
+    conn.execute_batch(&format!(
+        "COPY audit_export TO {} ({})",
+        path_literal(output),
+        copy_format(output)?
+    ))
+    .with_context(|| format!("Failed to export audit log to {}", output))?;
+
+    Ok(exported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_three(config: &AuditConfig) {
+        let complete = AuditLog::open(config, "complete").unwrap();
+        complete
+            .record(
+                "coder",
+                "Explain recursion",
+                "It calls itself.",
+                Duration::from_millis(900),
+                false,
+            )
+            .unwrap();
+        complete
+            .record(
+                "coder",
+                "Explain recursion",
+                "It calls itself.",
+                Duration::ZERO,
+                true,
+            )
+            .unwrap();
+        AuditLog::open(config, "filter")
+            .unwrap()
+            .record(
+                "fast",
+                "Is this spam?",
+                "false",
+                Duration::from_millis(120),
+                false,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_database_sink_hash_policy() {
+        let temp = tempfile::tempdir().unwrap();
+        let config = AuditConfig {
+            policy: AuditPolicy::Hash,
+            sink: AuditSink::Database(temp.path().join("audit.duckdb")),
+        };
+        log_three(&config);
+
+        let output = temp.path().join("export.csv");
+        let exported = export(&config, output.to_str().unwrap(), &ExportFilter::default()).unwrap();
+        assert_eq!(exported, 3);
+
+        let conn = Connection::open(temp.path().join("audit.duckdb")).unwrap();
+        let (prompts, hashes, cached): (i64, i64, i64) = conn
+            .query_row(
+                "SELECT COUNT(prompt), COUNT(DISTINCT prompt_hash), COUNT(*) FILTER (WHERE cached)
+                 FROM llm_audit_log",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!((prompts, hashes, cached), (0, 2, 1));
+    }
+
+    #[test]
+    fn test_jsonl_sink_full_policy_and_filtered_export() {
+        let temp = tempfile::tempdir().unwrap();
+        let log_path = temp.path().join("audit.jsonl");
+        let config = AuditConfig {
+            policy: AuditPolicy::Full,
+            sink: AuditSink::Jsonl(log_path.clone()),
+        };
+        log_three(&config);
+        AuditLog::open(&config, "rag")
+            .unwrap()
+            .record_guardrail("output", "pii", "redacted", "email")
+            .unwrap();
+
+        let lines: Vec<serde_json::Value> = fs::read_to_string(&log_path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["prompt"], "Explain recursion");
+        assert_eq!(lines[2]["command"], "filter");
+        assert_eq!(lines[3]["event"], "guardrail");
+
+        // Guardrail events aren't exported as LLM interactions
+        let output = temp.path().join("all.csv");
+        let exported = export(&config, output.to_str().unwrap(), &ExportFilter::default()).unwrap();
+        assert_eq!(exported, 3);
+
+        let output = temp.path().join("filter.json");
+        let filter = ExportFilter {
+            command: Some("filter".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            export(&config, output.to_str().unwrap(), &filter).unwrap(),
+            1
+        );
+        assert!(fs::read_to_string(&output)
+            .unwrap()
+            .contains("Is this spam?"));
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        assert_eq!(AuditPolicy::parse("full").unwrap(), AuditPolicy::Full);
+        assert_eq!(AuditPolicy::parse("hash").unwrap().as_str(), "hash");
+        assert!(AuditPolicy::parse("none").is_err());
+    }
+}
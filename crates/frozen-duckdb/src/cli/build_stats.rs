@@ -0,0 +1,310 @@
+//! # Local Build-Time Statistics
+//!
+//! This module aggregates the build events recorded by the builder crate
+//! when `FROZEN_DUCKDB_TELEMETRY=1` is set, to show how much build time the
+//! prebuilt binary actually saves on this machine.
+//!
+//! The build script appends events to `~/.frozen-duckdb/metrics.jsonl`;
+//! [`BuildMetrics::import_log`] moves them into the `build_events` table of
+//! `metrics.duckdb` in the [cache directory](super::cache_dir), where they
+//! are aggregated. All data stays local.
+//!
+//! ## Savings
+//!
+//! Every build that didn't compile DuckDB is credited with the difference
+//! between the average local compile time and its own time. Until a local
+//! compile has been recorded, [`REFERENCE_COMPILE_MS`] is used instead and
+//! the report marks the savings as estimated.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use frozen_duckdb::cli::build_stats::BuildMetrics;
+//!
+//! let metrics = BuildMetrics::open_default()?;
+//! metrics.import_log(&BuildMetrics::default_log_path()?)?;
+//! println!("{}", metrics.summary()?.format_report());
+//! ```
+
+use super::cache_dir::cache_path;
+use anyhow::{Context, Result};
+use duckdb::Connection;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const METRICS_DB: &str = "metrics.duckdb";
+
+/// Compile time assumed when none has been measured locally (~1.5 minutes,
+/// the middle of the 1-2 minute source build range).
+pub const REFERENCE_COMPILE_MS: f64 = 90_000.0;
+
+/// Build events stored in `metrics.duckdb`.
+pub struct BuildMetrics {
+    conn: Connection,
+}
+
+impl BuildMetrics {
+    /// Opens `metrics.duckdb` in the cache directory.
+    pub fn open_default() -> Result<Self> {
+        Self::open(&cache_path(METRICS_DB)?)
+    }
+
+    /// Opens (or creates) a metrics database at `path`.
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open metrics database: {}", path.display()))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS build_events (
+                recorded_at TIMESTAMP,
+                version VARCHAR,
+                arch VARCHAR,
+                source VARCHAR,
+                download_ms BIGINT,
+                total_ms BIGINT
+            );",
+        )?;
+        Ok(Self { conn })
+    }
+
+    /// Returns the builder's event log, `~/.frozen-duckdb/metrics.jsonl`.
+    pub fn default_log_path() -> Result<PathBuf> {
+        frozen_duckdb_builder::telemetry::metrics_log_path()
+    }
+
+    /// Moves events from the builder's JSON Lines log into the database,
+    /// returning how many were imported.
+    ///
+    /// The log is renamed before reading, so builds running concurrently
+    /// start a fresh log instead of losing events.
+    pub fn import_log(&self, log: &Path) -> Result<usize> {
+        if !log.exists() {
+            return Ok(0);
+        }
+        let importing = log.with_extension("jsonl.importing");
+        fs::rename(log, &importing)
+            .with_context(|| format!("Failed to read metrics log: {}", log.display()))?;
+
+        let imported = self.conn.execute(
+            "INSERT INTO build_events
+             SELECT make_timestamp(recorded_at * 1000000), version, arch, source, download_ms, total_ms
+             FROM read_json(?, format = 'newline_delimited', columns = {
+                 recorded_at: 'BIGINT', version: 'VARCHAR', arch: 'VARCHAR',
+                 source: 'VARCHAR', download_ms: 'BIGINT', total_ms: 'BIGINT'
+             })",
+            [importing.to_string_lossy().as_ref()],
+        );
+        match imported {
+            Ok(rows) => {
+                fs::remove_file(&importing)?;
+                Ok(rows)
+            }
+            Err(e) => {
+                // Put the events back so the next run can retry
+                let _ = fs::rename(&importing, log);
+                Err(e).context("Failed to import build events")
+            }
+        }
+    }
+
+    /// Aggregates all recorded builds.
+    pub fn summary(&self) -> Result<BuildSummary> {
+        let (builds, cache_hits, prebuilt, downloads, compiles): (i64, i64, i64, i64, i64) =
+            self.conn.query_row(
+                "SELECT COUNT(*),
+                        COUNT(*) FILTER (WHERE source = 'cache'),
+                        COUNT(*) FILTER (WHERE source = 'prebuilt'),
+                        COUNT(*) FILTER (WHERE source = 'download'),
+                        COUNT(*) FILTER (WHERE source = 'compile')
+                 FROM build_events",
+                [],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                },
+            )?;
+        let average = |source: &str| -> Result<Option<f64>> {
+            Ok(self.conn.query_row(
+                "SELECT AVG(total_ms) FROM build_events WHERE source = ?",
+                [source],
+                |row| row.get(0),
+            )?)
+        };
+        let avg_download_ms: Option<f64> = self.conn.query_row(
+            "SELECT AVG(download_ms) FROM build_events WHERE source = 'download'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        let avg_compile_ms = average("compile")?;
+        let baseline = avg_compile_ms.unwrap_or(REFERENCE_COMPILE_MS);
+        let saved_ms: f64 = self.conn.query_row(
+            "SELECT COALESCE(SUM(GREATEST(? - total_ms, 0)), 0)::DOUBLE
+             FROM build_events WHERE source != 'compile'",
+            [baseline],
+            |row| row.get(0),
+        )?;
+
+        Ok(BuildSummary {
+            builds: builds as usize,
+            cache_hits: cache_hits as usize,
+            prebuilt: prebuilt as usize,
+            downloads: downloads as usize,
+            compiles: compiles as usize,
+            avg_cached_ms: average("cache")?,
+            avg_download_ms,
+            avg_compile_ms,
+            saved_ms,
+        })
+    }
+}
+
+/// Aggregated local build statistics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildSummary {
+    /// Recorded build script runs
+    pub builds: usize,
+    /// Builds that found the binary in the cache
+    pub cache_hits: usize,
+    /// Builds that copied the binary from `prebuilt/`
+    pub prebuilt: usize,
+    /// Builds that downloaded the binary
+    pub downloads: usize,
+    /// Builds that compiled DuckDB from source
+    pub compiles: usize,
+    /// Average total time of cache hits
+    pub avg_cached_ms: Option<f64>,
+    /// Average download time
+    pub avg_download_ms: Option<f64>,
+    /// Average total time of local compiles
+    pub avg_compile_ms: Option<f64>,
+    /// Build time saved compared with compiling every time
+    pub saved_ms: f64,
+}
+
+impl BuildSummary {
+    /// Fraction of builds served from the cache.
+    pub fn cache_hit_rate(&self) -> f64 {
+        if self.builds == 0 {
+            0.0
+        } else {
+            self.cache_hits as f64 / self.builds as f64
+        }
+    }
+
+    /// Formats the summary as a human-readable report.
+    pub fn format_report(&self) -> String {
+        if self.builds == 0 {
+            return "📊 No builds recorded yet\n   Set FROZEN_DUCKDB_TELEMETRY=1 and rebuild to start recording".to_string();
+        }
+        let ms =
+            |value: Option<f64>| value.map_or_else(|| "-".to_string(), |v| format!("{:.0} ms", v));
+        let estimated = if self.avg_compile_ms.is_some() {
+            ""
+        } else {
+            " (estimated)"
+        };
+
+        format!(
+            "📊 Local build statistics\n\
+             Builds:          {}\n\
+             Cache hits:      {} ({:.1}%)\n\
+             Prebuilt copies: {}\n\
+             Downloads:       {}\n\
+             Compiles:        {}\n\
+             Avg cache hit:   {}\n\
+             Avg download:    {}\n\
+             Avg compile:     {}\n\
+             ⏱️  Time saved:   {:.1} min{}",
+            self.builds,
+            self.cache_hits,
+            self.cache_hit_rate() * 100.0,
+            self.prebuilt,
+            self.downloads,
+            self.compiles,
+            ms(self.avg_cached_ms),
+            ms(self.avg_download_ms),
+            ms(self.avg_compile_ms),
+            self.saved_ms / 60_000.0,
+            estimated
+        )
+    }
+
+    /// Serializes the summary as JSON.
+    pub fn to_json(&self) -> Value {
+        serde_json::json!({
+            "builds": self.builds,
+            "cache_hits": self.cache_hits,
+            "cache_hit_rate": self.cache_hit_rate(),
+            "prebuilt": self.prebuilt,
+            "downloads": self.downloads,
+            "compiles": self.compiles,
+            "avg_cached_ms": self.avg_cached_ms,
+            "avg_download_ms": self.avg_download_ms,
+            "avg_compile_ms": self.avg_compile_ms,
+            "saved_ms": self.saved_ms,
+            "savings_estimated": self.avg_compile_ms.is_none(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_and_summarize() {
+        let temp = tempfile::tempdir().unwrap();
+        let log = temp.path().join(METRICS_LOG);
+        fs::write(
+            &log,
+            "{\"recorded_at\":1700000000,\"version\":\"1.4.0\",\"arch\":\"arm64\",\"source\":\"compile\",\"download_ms\":500,\"total_ms\":60500}\n\
+             {\"recorded_at\":1700000100,\"version\":\"1.4.0\",\"arch\":\"arm64\",\"source\":\"cache\",\"download_ms\":null,\"total_ms\":500}\n\
+             {\"recorded_at\":1700000200,\"version\":\"1.4.0\",\"arch\":\"arm64\",\"source\":\"download\",\"download_ms\":2000,\"total_ms\":2500}\n",
+        )
+        .unwrap();
+
+        let metrics = BuildMetrics::open(&temp.path().join(METRICS_DB)).unwrap();
+        assert_eq!(metrics.import_log(&log).unwrap(), 3);
+        assert!(!log.exists());
+        assert_eq!(metrics.import_log(&log).unwrap(), 0);
+
+        let summary = metrics.summary().unwrap();
+        assert_eq!(summary.builds, 3);
+        assert_eq!(
+            (summary.cache_hits, summary.downloads, summary.compiles),
+            (1, 1, 1)
+        );
+        assert_eq!(summary.avg_compile_ms, Some(60500.0));
+        assert_eq!(summary.avg_download_ms, Some(2000.0));
+        // (60500 - 500) + (60500 - 2500)
+        assert_eq!(summary.saved_ms, 118000.0);
+        assert!(summary.format_report().contains("Time saved:   2.0 min"));
+    }
+
+    #[test]
+    fn test_savings_estimated_without_local_compile() {
+        let temp = tempfile::tempdir().unwrap();
+        let metrics = BuildMetrics::open(&temp.path().join(METRICS_DB)).unwrap();
+        assert!(metrics
+            .summary()
+            .unwrap()
+            .format_report()
+            .contains("No builds recorded"));
+
+        metrics
+            .conn
+            .execute_batch(
+                "INSERT INTO build_events VALUES (now()::TIMESTAMP, '1.4.0', 'x86_64', 'cache', NULL, 1000);",
+            )
+            .unwrap();
+        let summary = metrics.summary().unwrap();
+        assert_eq!(summary.saved_ms, REFERENCE_COMPILE_MS - 1000.0);
+        assert_eq!(summary.to_json()["savings_estimated"], true);
+    }
+}
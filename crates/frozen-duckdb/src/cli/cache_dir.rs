@@ -0,0 +1,40 @@
+//! # Cache Directory for CLI Stores
+//!
+//! Caches and stores the CLI keeps between runs live in the same directory
+//! as the DuckDB library, chosen by [`frozen_duckdb_builder::cache_dir`]:
+//! `FROZEN_DUCKDB_CACHE_DIR` if set, otherwise `~/.frozen-duckdb/cache`,
+//! falling back to `$XDG_CACHE_HOME/frozen-duckdb` when the home directory
+//! isn't writable.
+//!
+//! | Entry | Contents |
+//! |-------|----------|
+//! | `queries/` | Cached query results ([`super::query_cache`]) |
+//! | `datasets/` | Generated datasets ([`super::dataset_cache`]) |
+//! | `throughput.json` | Measured model speed ([`super::throughput`]) |
+//! | `metrics.duckdb` | Build statistics ([`super::build_stats`]) |
+//! | `jobs.duckdb` | Scheduled job runs ([`super::jobs`]) |
+//! | `catalog.duckdb` | Registered datasets ([`super::catalog`]) |
+//!
+//! # Examples
+//!
+//! ```rust
+//! use frozen_duckdb::cli::cache_dir::cache_path;
+//!
+//! let catalog = cache_path("catalog.duckdb")?;
+//! println!("Catalog: {}", catalog.display());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::Result;
+use frozen_duckdb_builder::cache_dir::resolve;
+use std::path::PathBuf;
+
+/// Returns `name` inside the cache directory, creating the directory if
+/// needed.
+///
+/// # Errors
+///
+/// Returns an error if no cache directory is writable.
+pub fn cache_path(name: &str) -> Result<PathBuf> {
+    Ok(resolve(None)?.join(name))
+}
@@ -0,0 +1,485 @@
+//! # Dataset Catalog
+//!
+//! Datasets end up scattered across working directories. The catalog is a
+//! small DuckDB database (`catalog.duckdb` in the
+//! [cache directory](super::cache_dir)) that remembers
+//! where each one is, so it can be found with `frozen-duckdb catalog list`
+//! and used by name instead of by path.
+//!
+//! ## Registration
+//!
+//! | Command | Registers |
+//! |---------|-----------|
+//! | `download --dataset tpch --format parquet` | Each table as `tpch_<table>`, e.g. `tpch_lineitem` |
+//! | `convert ... --register sales` | The output as `sales` (all parts of a split output) |
+//!
+//! Registering a name again replaces the entry.
+//!
+//! ## Record Schema
+//!
+//! ```text
+//! datasets (name VARCHAR PRIMARY KEY, path VARCHAR, format VARCHAR,
+//!           schema_hash VARCHAR, row_count BIGINT, created_at TIMESTAMP)
+//! ```
+//!
+//! `schema_hash` is the SHA-256 of the column names and types, so `catalog
+//! show` can tell when a file's schema changed after it was registered.
+//! Derived files also get a row in a `lineage` table, see
+//! [`lineage`](super::lineage), and `ingest --incremental` keeps the last
+//! watermark it loaded from each source in a `watermarks` table, see
+//! [`ingest`](super::ingest).
+//!
+//! ## Using Datasets by Name
+//!
+//! `query` exposes every cataloged dataset as a view in the
+//! [`CATALOG_SCHEMA`] schema, and `convert --input` accepts a cataloged
+//! name when no file of that name exists:
+//!
+//! ```bash
+//! frozen-duckdb query --sql "SELECT count(*) FROM datasets.tpch_lineitem"
+//! frozen-duckdb convert --input tpch_orders --output orders.csv --output-format csv
+//! ```
+//!
+//! # Examples
+//!
+//! ```rust
+//! use frozen_duckdb::cli::catalog::DatasetCatalog;
+//! use frozen_duckdb::cli::DatasetManager;
+//!
+//! let manager = DatasetManager::new()?;
+//! let catalog = DatasetCatalog::open_default()?;
+//! catalog.register(&manager.catalog_entry("sales", "sales.parquet", "parquet")?)?;
+//!
+//! for entry in catalog.list()? {
+//!     println!("{} {} rows at {}", entry.name, entry.row_count, entry.path);
+//! }
+//! ```
+
+use super::cache_dir::cache_path;
+use super::lineage::Lineage;
+use anyhow::{Context, Result};
+use duckdb::{params, Connection};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CATALOG_DATABASE: &str = "catalog.duckdb";
+
+/// Schema cataloged datasets are queried from, e.g. `datasets.sales`.
+pub const CATALOG_SCHEMA: &str = "datasets";
+
+/// A registered dataset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CatalogEntry {
+    /// Name the dataset is referenced by
+    pub name: String,
+    /// Absolute path of the file, or a glob over its parts
+    pub path: String,
+    /// File format (`csv`, `parquet`, ...)
+    pub format: String,
+    /// SHA-256 of the column names and types, see [`schema_hash`]
+    pub schema_hash: String,
+    /// Rows when the dataset was registered
+    pub row_count: i64,
+    /// When the dataset was registered (`YYYY-MM-DD HH:MM:SS`); `None`
+    /// until it is
+    pub created_at: Option<String>,
+}
+
+/// Registered datasets, stored in a DuckDB database.
+pub struct DatasetCatalog {
+    conn: Connection,
+}
+
+impl DatasetCatalog {
+    /// Returns the default catalog location, `catalog.duckdb` in the cache
+    /// directory.
+    pub fn default_path() -> Result<PathBuf> {
+        cache_path(CATALOG_DATABASE)
+    }
+
+    /// Opens the default catalog, creating it if needed.
+    pub fn open_default() -> Result<Self> {
+        Self::open(&Self::default_path()?)
+    }
+
+    /// Opens the default catalog if anything was ever registered, without
+    /// creating it otherwise.
+    pub fn open_existing() -> Result<Option<Self>> {
+        let path = Self::default_path()?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        Self::open(&path).map(Some)
+    }
+
+    /// Opens the catalog at `path`, creating it if needed.
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open dataset catalog: {}", path.display()))?;
+        Self::with_connection(conn)
+    }
+
+    /// Uses `conn`'s database as the catalog, creating its table if needed.
+    pub fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS datasets (
+                name VARCHAR PRIMARY KEY,
+                path VARCHAR NOT NULL,
+                format VARCHAR NOT NULL,
+                schema_hash VARCHAR NOT NULL,
+                row_count BIGINT NOT NULL,
+                created_at TIMESTAMP NOT NULL DEFAULT current_timestamp
+            );
+            CREATE TABLE IF NOT EXISTS lineage (
+                output VARCHAR PRIMARY KEY,
+                command VARCHAR NOT NULL,
+                inputs VARCHAR NOT NULL,
+                parameters VARCHAR NOT NULL,
+                git_sha VARCHAR,
+                created_at TIMESTAMP NOT NULL DEFAULT current_timestamp
+            );
+            CREATE TABLE IF NOT EXISTS watermarks (
+                source VARCHAR NOT NULL,
+                target VARCHAR NOT NULL,
+                watermark_column VARCHAR NOT NULL,
+                watermark VARCHAR NOT NULL,
+                updated_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+                PRIMARY KEY (source, target)
+            )",
+        )
+        .context("Failed to create dataset catalog tables")?;
+        Ok(Self { conn })
+    }
+
+    /// Registers a dataset, replacing any entry of the same name.
+    pub fn register(&self, entry: &CatalogEntry) -> Result<()> {
+        validate_name(&entry.name)?;
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO datasets (name, path, format, schema_hash, row_count, created_at) \
+                 VALUES (?, ?, ?, ?, ?, current_timestamp)",
+                params![
+                    entry.name,
+                    entry.path,
+                    entry.format,
+                    entry.schema_hash,
+                    entry.row_count
+                ],
+            )
+            .with_context(|| format!("Failed to register dataset {}", entry.name))?;
+        Ok(())
+    }
+
+    /// Returns the dataset registered as `name`.
+    pub fn get(&self, name: &str) -> Result<Option<CatalogEntry>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("{} WHERE name = ?", SELECT_ENTRIES))?;
+        let mut rows = stmt.query([name])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(entry_from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns every registered dataset, by name.
+    pub fn list(&self) -> Result<Vec<CatalogEntry>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("{} ORDER BY name", SELECT_ENTRIES))?;
+        let entries = stmt
+            .query_map([], entry_from_row)?
+            .collect::<duckdb::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+
+    /// Removes the entry for `name`; the dataset's files are kept. Returns
+    /// whether it was registered.
+    pub fn remove(&self, name: &str) -> Result<bool> {
+        Ok(self
+            .conn
+            .execute("DELETE FROM datasets WHERE name = ?", [name])?
+            > 0)
+    }
+
+    /// Records how a file was produced, replacing any earlier record for
+    /// the same output.
+    pub fn record_lineage(&self, lineage: &Lineage) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO lineage (output, command, inputs, parameters, git_sha, created_at) \
+                 VALUES (?, ?, ?, ?, ?, current_timestamp)",
+                params![
+                    lineage.output,
+                    lineage.command,
+                    serde_json::to_string(&lineage.inputs)?,
+                    lineage.parameters.to_string(),
+                    lineage.git_sha
+                ],
+            )
+            .with_context(|| format!("Failed to record lineage of {}", lineage.output))?;
+        Ok(())
+    }
+
+    /// Returns how the file at absolute path `output` was produced, if
+    /// recorded.
+    pub fn lineage(&self, output: &str) -> Result<Option<Lineage>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT output, command, inputs, parameters, git_sha, \
+             strftime(created_at, '%Y-%m-%d %H:%M:%S') FROM lineage WHERE output = ?",
+        )?;
+        let mut rows = stmt.query([output])?;
+        let Some(row) = rows.next()? else {
+            return Ok(None);
+        };
+        let inputs: String = row.get(2)?;
+        let parameters: String = row.get(3)?;
+        Ok(Some(Lineage {
+            output: row.get(0)?,
+            command: row.get(1)?,
+            inputs: serde_json::from_str(&inputs)?,
+            parameters: serde_json::from_str(&parameters)?,
+            git_sha: row.get(4)?,
+            created_at: row.get(5)?,
+        }))
+    }
+
+    /// Returns the highest `watermark_column` value loaded from `source`
+    /// into `target`, as text, if it was loaded incrementally before.
+    /// A watermark recorded for a different column is ignored.
+    pub fn watermark(
+        &self,
+        source: &str,
+        target: &str,
+        watermark_column: &str,
+    ) -> Result<Option<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT watermark FROM watermarks \
+             WHERE source = ? AND target = ? AND watermark_column = ?",
+        )?;
+        let mut rows = stmt.query([source, target, watermark_column])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Records the highest `watermark_column` value loaded from `source`
+    /// into `target`.
+    pub fn set_watermark(
+        &self,
+        source: &str,
+        target: &str,
+        watermark_column: &str,
+        watermark: &str,
+    ) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO watermarks (source, target, watermark_column, watermark, updated_at) \
+                 VALUES (?, ?, ?, ?, current_timestamp)",
+                params![source, target, watermark_column, watermark],
+            )
+            .with_context(|| format!("Failed to record the watermark of {}", source))?;
+        Ok(())
+    }
+
+    /// Forgets the watermark of `source` into `target`, so the next
+    /// incremental load reads every row. Returns whether one was recorded.
+    pub fn reset_watermark(&self, source: &str, target: &str) -> Result<bool> {
+        Ok(self.conn.execute(
+            "DELETE FROM watermarks WHERE source = ? AND target = ?",
+            [source, target],
+        )? > 0)
+    }
+}
+
+const SELECT_ENTRIES: &str = "SELECT name, path, format, schema_hash, row_count, \
+     strftime(created_at, '%Y-%m-%d %H:%M:%S') FROM datasets";
+
+fn entry_from_row(row: &duckdb::Row<'_>) -> duckdb::Result<CatalogEntry> {
+    Ok(CatalogEntry {
+        name: row.get(0)?,
+        path: row.get(1)?,
+        format: row.get(2)?,
+        schema_hash: row.get(3)?,
+        row_count: row.get(4)?,
+        created_at: row.get(5)?,
+    })
+}
+
+/// Checks that `name` can be used unquoted in SQL: letters, digits and
+/// underscores, not starting with a digit.
+pub fn validate_name(name: &str) -> Result<()> {
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        anyhow::bail!(
+            "Invalid dataset name: {:?} (use letters, digits and underscores)",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Catalog name for a file of a downloaded dataset, e.g. `tpch_lineitem`
+/// for `lineitem.parquet` of `tpch`.
+pub fn download_name(dataset: &str, file: &Path) -> String {
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+    let stem: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{}_{}", dataset, stem.to_lowercase())
+}
+
+/// SHA-256 of `(column name, type)` pairs, in order, as hex.
+pub fn schema_hash(columns: &[(String, String)]) -> String {
+    let mut hasher = Sha256::new();
+    for (name, data_type) in columns {
+        hasher.update(name.as_bytes());
+        hasher.update(b" ");
+        hasher.update(data_type.as_bytes());
+        hasher.update(b"\n");
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, rows: i64) -> CatalogEntry {
+        CatalogEntry {
+            name: name.to_string(),
+            path: format!("/data/{}.parquet", name),
+            format: "parquet".to_string(),
+            schema_hash: schema_hash(&[("id".to_string(), "INTEGER".to_string())]),
+            row_count: rows,
+            created_at: None,
+        }
+    }
+
+    #[test]
+    fn test_register_list_remove() {
+        let catalog =
+            DatasetCatalog::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+        catalog.register(&entry("sales", 10)).unwrap();
+        catalog.register(&entry("customers", 3)).unwrap();
+        // Registering again replaces the entry
+        catalog.register(&entry("sales", 12)).unwrap();
+
+        let names: Vec<_> = catalog
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, ["customers", "sales"]);
+
+        let sales = catalog.get("sales").unwrap().unwrap();
+        assert_eq!(sales.row_count, 12);
+        assert_eq!(sales.path, "/data/sales.parquet");
+        assert!(sales.created_at.is_some());
+
+        assert!(catalog.remove("sales").unwrap());
+        assert!(!catalog.remove("sales").unwrap());
+        assert!(catalog.get("sales").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_lineage_records() {
+        let catalog =
+            DatasetCatalog::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+        assert!(catalog.lineage("/data/out.parquet").unwrap().is_none());
+
+        let lineage = Lineage {
+            output: "/data/out.parquet".to_string(),
+            command: "join".to_string(),
+            inputs: vec!["/data/a.csv".to_string(), "/data/b.csv".to_string()],
+            parameters: serde_json::json!({"how": "left"}),
+            git_sha: None,
+            created_at: None,
+        };
+        catalog.record_lineage(&lineage).unwrap();
+        let stored = catalog.lineage("/data/out.parquet").unwrap().unwrap();
+        assert!(stored.created_at.is_some());
+        assert_eq!(
+            Lineage {
+                created_at: None,
+                ..stored
+            },
+            lineage
+        );
+    }
+
+    #[test]
+    fn test_watermarks() {
+        let catalog =
+            DatasetCatalog::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+        let (source, target) = ("/data/orders.csv", "/data/shop.duckdb#orders");
+        assert!(catalog
+            .watermark(source, target, "updated_at")
+            .unwrap()
+            .is_none());
+
+        catalog
+            .set_watermark(source, target, "updated_at", "2024-01-01 00:00:00")
+            .unwrap();
+        catalog
+            .set_watermark(source, target, "updated_at", "2024-02-01 00:00:00")
+            .unwrap();
+        assert_eq!(
+            catalog.watermark(source, target, "updated_at").unwrap(),
+            Some("2024-02-01 00:00:00".to_string())
+        );
+        assert!(catalog.watermark(source, target, "id").unwrap().is_none());
+
+        assert!(catalog.reset_watermark(source, target).unwrap());
+        assert!(catalog
+            .watermark(source, target, "updated_at")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("tpch_lineitem").is_ok());
+        assert!(validate_name("_staging2").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("2024_sales").is_err());
+        assert!(validate_name("sales; DROP TABLE x").is_err());
+    }
+
+    #[test]
+    fn test_download_name() {
+        assert_eq!(
+            download_name("tpch", Path::new("out/lineitem.parquet")),
+            "tpch_lineitem"
+        );
+        assert_eq!(
+            download_name("chinook", Path::new("out/Invoice-Lines.csv")),
+            "chinook_invoice_lines"
+        );
+    }
+
+    #[test]
+    fn test_schema_hash() {
+        let columns = |t: &str| vec![("id".to_string(), t.to_string())];
+        assert_eq!(
+            schema_hash(&columns("INTEGER")),
+            schema_hash(&columns("INTEGER"))
+        );
+        assert_ne!(
+            schema_hash(&columns("INTEGER")),
+            schema_hash(&columns("BIGINT"))
+        );
+        assert_eq!(schema_hash(&columns("INTEGER")).len(), 64);
+    }
+}
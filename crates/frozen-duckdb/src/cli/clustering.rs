@@ -0,0 +1,494 @@
+//! # Embedding Clustering for Frozen DuckDB CLI
+//!
+//! This module groups the documents of an embedding index with k-means and
+//! stores the assignments in the index, optionally naming each cluster with
+//! an LLM from a sample of its members.
+//!
+//! ## Algorithm
+//!
+//! Spherical k-means: embeddings are normalized to unit length, so squared
+//! Euclidean distance ranks like cosine distance. Centroids are seeded with
+//! k-means++ from a fixed seed, and assignment runs in parallel with rayon.
+//! Iteration stops when no centroid moves more than the tolerance.
+//!
+//! ## Index Schema
+//!
+//! ```text
+//! clusters       (doc_id VARCHAR PRIMARY KEY, cluster INTEGER, distance FLOAT)
+//! cluster_labels (cluster INTEGER PRIMARY KEY, size BIGINT, label VARCHAR)
+//! ```
+//!
+//! Both tables are replaced by each clustering run.
+
+use super::dedupe::copy_format;
+use super::embedding_index::EmbeddingIndex;
+use super::flock_manager::FlockManager;
+use super::sql_path::path_literal;
+use anyhow::{anyhow, Context, Result};
+use rayon::prelude::*;
+use tracing::info;
+
+/// Member documents shown to the labeling model per cluster.
+const LABEL_SAMPLE: usize = 8;
+
+/// Characters of each member document shown to the labeling model.
+const LABEL_SAMPLE_CHARS: usize = 500;
+
+/// Options for [`kmeans`].
+#[derive(Debug, Clone)]
+pub struct KMeansOptions {
+    /// Number of clusters
+    pub k: usize,
+    /// Iteration limit
+    pub max_iterations: usize,
+    /// Largest centroid movement at which iteration stops
+    pub tolerance: f32,
+    /// Seed for k-means++ initialization
+    pub seed: u64,
+}
+
+impl Default for KMeansOptions {
+    fn default() -> Self {
+        Self {
+            k: 8,
+            max_iterations: 100,
+            tolerance: 1e-4,
+            seed: 42,
+        }
+    }
+}
+
+/// Result of a k-means run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KMeansResult {
+    /// Cluster of each input vector
+    pub assignments: Vec<usize>,
+    /// Squared distance of each vector to its centroid
+    pub distances: Vec<f32>,
+    /// Unit-length cluster centroids
+    pub centroids: Vec<Vec<f32>>,
+    /// Iterations run
+    pub iterations: usize,
+}
+
+impl KMeansResult {
+    /// Number of members of each cluster.
+    pub fn sizes(&self) -> Vec<usize> {
+        let mut sizes = vec![0; self.centroids.len()];
+        for &cluster in &self.assignments {
+            sizes[cluster] += 1;
+        }
+        sizes
+    }
+
+    /// Sum of squared distances to the assigned centroids.
+    pub fn inertia(&self) -> f32 {
+        self.distances.iter().sum()
+    }
+}
+
+/// Clusters `vectors` with spherical k-means.
+pub fn kmeans(vectors: &[Vec<f32>], options: &KMeansOptions) -> Result<KMeansResult> {
+    let k = options.k;
+    if k == 0 {
+        return Err(anyhow!("k must be greater than zero"));
+    }
+    if vectors.len() < k {
+        return Err(anyhow!(
+            "Cannot form {} clusters from {} documents",
+            k,
+            vectors.len()
+        ));
+    }
+    let dimension = vectors[0].len();
+    if vectors.iter().any(|v| v.len() != dimension) {
+        return Err(anyhow!("Embeddings have different dimensions"));
+    }
+
+    let points: Vec<Vec<f32>> = vectors
+        .iter()
+        .map(|v| {
+            let mut v = v.clone();
+            normalize(&mut v);
+            v
+        })
+        .collect();
+    let mut centroids = initial_centroids(&points, k, options.seed);
+
+    let mut iterations = 0;
+    while iterations < options.max_iterations {
+        iterations += 1;
+        let (assignments, mut distances): (Vec<usize>, Vec<f32>) = points
+            .par_iter()
+            .map(|point| nearest(point, &centroids))
+            .unzip();
+
+        let mut sums = vec![vec![0.0f32; dimension]; k];
+        let mut counts = vec![0usize; k];
+        for (point, &cluster) in points.iter().zip(&assignments) {
+            counts[cluster] += 1;
+            for (sum, value) in sums[cluster].iter_mut().zip(point) {
+                *sum += value;
+            }
+        }
+
+        let mut largest_move = 0.0f32;
+        for (cluster, mut centroid) in sums.into_iter().enumerate() {
+            if counts[cluster] == 0 || !normalize(&mut centroid) {
+                // Reseed an empty cluster with the worst-fitting point
+                let farthest = (0..points.len())
+                    .max_by(|&a, &b| distances[a].total_cmp(&distances[b]))
+                    .unwrap_or(0);
+                centroid = points[farthest].clone();
+                distances[farthest] = 0.0;
+            }
+            largest_move = largest_move.max(squared_distance(&centroid, &centroids[cluster]));
+            centroids[cluster] = centroid;
+        }
+        if largest_move.sqrt() <= options.tolerance {
+            break;
+        }
+    }
+
+    // Final assignment against the converged centroids
+    let (assignments, distances) = points
+        .par_iter()
+        .map(|point| nearest(point, &centroids))
+        .unzip();
+    Ok(KMeansResult {
+        assignments,
+        distances,
+        centroids,
+        iterations,
+    })
+}
+
+/// k-means++ seeding: each next centroid is drawn with probability
+/// proportional to its squared distance from the nearest chosen one.
+fn initial_centroids(points: &[Vec<f32>], k: usize, seed: u64) -> Vec<Vec<f32>> {
+    let mut rng = XorShift(seed.max(1));
+    let mut centroids = vec![points[rng.below(points.len())].clone()];
+    let mut closest: Vec<f32> = points
+        .par_iter()
+        .map(|p| squared_distance(p, &centroids[0]))
+        .collect();
+    while centroids.len() < k {
+        let total: f32 = closest.iter().sum();
+        let next = if total > 0.0 {
+            let mut target = rng.unit() * total;
+            closest
+                .iter()
+                .position(|&d| {
+                    target -= d;
+                    target <= 0.0
+                })
+                .unwrap_or(points.len() - 1)
+        } else {
+            // All remaining points coincide with a centroid
+            rng.below(points.len())
+        };
+        let centroid = points[next].clone();
+        closest
+            .par_iter_mut()
+            .zip(points)
+            .for_each(|(d, p)| *d = d.min(squared_distance(p, &centroid)));
+        centroids.push(centroid);
+    }
+    centroids
+}
+
+fn nearest(point: &[f32], centroids: &[Vec<f32>]) -> (usize, f32) {
+    centroids
+        .iter()
+        .map(|c| squared_distance(point, c))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((0, 0.0))
+}
+
+fn squared_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+/// Scales `v` to unit length; returns false if it is zero.
+fn normalize(v: &mut [f32]) -> bool {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm <= f32::EPSILON {
+        return false;
+    }
+    v.iter_mut().for_each(|x| *x /= norm);
+    true
+}
+
+/// Names a cluster from a sample of its member documents.
+pub trait ClusterLabeler {
+    /// Returns a short label for documents drawn from one cluster.
+    fn label(&self, documents: &[String]) -> Result<String>;
+}
+
+/// [`FlockManager`] bound to a model, usable as a [`ClusterLabeler`].
+pub struct FlockLabeler<'a> {
+    /// Flock manager used for completions
+    pub manager: &'a FlockManager,
+    /// Model alias configured during flock-setup
+    pub model: String,
+}
+
+impl ClusterLabeler for FlockLabeler<'_> {
+    fn label(&self, documents: &[String]) -> Result<String> {
+        let mut prompt = String::from(
+            "The following documents belong to one cluster. Reply with only a short \
+             label (at most five words) describing what they have in common.\n",
+        );
+        for document in documents {
+            let excerpt: String = document.chars().take(LABEL_SAMPLE_CHARS).collect();
+            prompt.push_str(&format!("\n---\n{}", excerpt));
+        }
+        let label = self.manager.complete_text(&prompt, &self.model)?;
+        Ok(label
+            .trim()
+            .trim_matches(|c| c == '"' || c == '.')
+            .to_string())
+    }
+}
+
+/// Summary of a clustering run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterReport {
+    /// Documents clustered
+    pub documents: usize,
+    /// k-means iterations run
+    pub iterations: usize,
+    /// Members of each cluster
+    pub sizes: Vec<usize>,
+    /// Label of each cluster, if labeled
+    pub labels: Vec<Option<String>>,
+}
+
+/// Clusters the documents of `index` and stores the assignments in it.
+///
+/// With a `labeler`, each cluster is labeled from its members closest to
+/// the centroid.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::clustering::{cluster_index, FlockLabeler, KMeansOptions};
+/// use frozen_duckdb::cli::embedding_index::EmbeddingIndex;
+/// use frozen_duckdb::cli::FlockManager;
+///
+/// let index = EmbeddingIndex::open("embeddings.duckdb")?;
+/// let flock = FlockManager::new()?;
+/// let labeler = FlockLabeler { manager: &flock, model: "text_generator".to_string() };
+///
+/// let options = KMeansOptions { k: 20, ..Default::default() };
+/// let report = cluster_index(&index, &options, Some(&labeler))?;
+/// for (size, label) in report.sizes.iter().zip(&report.labels) {
+///     println!("{:>6}  {}", size, label.as_deref().unwrap_or("-"));
+/// }
+/// ```
+pub fn cluster_index(
+    index: &EmbeddingIndex,
+    options: &KMeansOptions,
+    labeler: Option<&dyn ClusterLabeler>,
+) -> Result<ClusterReport> {
+    let (ids, vectors) = index.vectors()?;
+    if ids.is_empty() {
+        return Err(anyhow!(
+            "Index is empty; build it with the index command first"
+        ));
+    }
+
+    info!(
+        "🧩 Clustering {} documents into {} clusters",
+        ids.len(),
+        options.k
+    );
+    let result = kmeans(&vectors, options)?;
+    let sizes = result.sizes();
+
+    let conn = index.connection();
+    let tx = conn.unchecked_transaction()?;
+    tx.execute_batch(
+        "CREATE OR REPLACE TABLE clusters (
+             doc_id VARCHAR PRIMARY KEY,
+             cluster INTEGER,
+             distance FLOAT
+         );
+         CREATE OR REPLACE TABLE cluster_labels (
+             cluster INTEGER PRIMARY KEY,
+             size BIGINT,
+             label VARCHAR
+         );",
+    )?;
+    let mut insert = tx.prepare("INSERT INTO clusters VALUES (?, ?, ?)")?;
+    for ((id, &cluster), &distance) in ids.iter().zip(&result.assignments).zip(&result.distances) {
+        insert.execute(duckdb::params![id, cluster as i32, distance])?;
+    }
+    drop(insert);
+    tx.commit().context("Failed to store cluster assignments")?;
+
+    let mut labels = vec![None; options.k];
+    if let Some(labeler) = labeler {
+        let mut sample = conn.prepare(
+            "SELECT e.content FROM clusters c JOIN embeddings e USING (doc_id)
+             WHERE c.cluster = ? ORDER BY c.distance, c.doc_id LIMIT CAST(? AS BIGINT)",
+        )?;
+        for (cluster, label) in labels.iter_mut().enumerate() {
+            let documents = sample
+                .query_map(
+                    duckdb::params![cluster as i32, LABEL_SAMPLE as i64],
+                    |row| row.get::<_, String>(0),
+                )?
+                .collect::<duckdb::Result<Vec<_>>>()?;
+            let text = labeler
+                .label(&documents)
+                .with_context(|| format!("Failed to label cluster {}", cluster))?;
+            info!(
+                "🏷️  Cluster {} ({} documents): {}",
+                cluster, sizes[cluster], text
+            );
+            *label = Some(text);
+        }
+    }
+
+    let mut insert = conn.prepare("INSERT INTO cluster_labels VALUES (?, ?, ?)")?;
+    for (cluster, (size, label)) in sizes.iter().zip(&labels).enumerate() {
+        insert.execute(duckdb::params![cluster as i32, *size as i64, label])?;
+    }
+
+    Ok(ClusterReport {
+        documents: ids.len(),
+        iterations: result.iterations,
+        sizes,
+        labels,
+    })
+}
+
+/// Writes the stored assignments (`doc_id, cluster, label, distance,
+/// content`) to `output`, in the format of its extension.
+pub fn export_clusters(index: &EmbeddingIndex, output: &str) -> Result<usize> {
+    let rows = index.connection().execute(
+        &format!(
+            "COPY (
+                 SELECT c.doc_id, c.cluster, l.label, c.distance, e.content
+                 FROM clusters c
+                 JOIN embeddings e USING (doc_id)
+                 LEFT JOIN cluster_labels l USING (cluster)
+                 ORDER BY c.cluster, c.distance
+             ) TO {} ({})",
+            path_literal(output),
+            copy_format(output)?
+        ),
+        [],
+    )?;
+    Ok(rows)
+}
+
+/// Small deterministic generator for k-means++ seeding.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn unit(&mut self) -> f32 {
+        (self.next() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::embedding_index::Document;
+
+    /// Labels a cluster with its first document's first word.
+    struct FirstWordLabeler;
+
+    impl ClusterLabeler for FirstWordLabeler {
+        fn label(&self, documents: &[String]) -> Result<String> {
+            Ok(documents[0].split_whitespace().next().unwrap().to_string())
+        }
+    }
+
+    /// Three groups of points around different axes.
+    fn grouped_vectors() -> Vec<Vec<f32>> {
+        (0..30)
+            .map(|i| {
+                let jitter = (i % 10) as f32 * 0.01;
+                let mut v = vec![jitter; 3];
+                v[i / 10] = 1.0;
+                v
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_kmeans_recovers_groups() {
+        let options = KMeansOptions {
+            k: 3,
+            ..Default::default()
+        };
+        let result = kmeans(&grouped_vectors(), &options).unwrap();
+        assert_eq!(result.sizes(), [10, 10, 10]);
+        for group in result.assignments.chunks(10) {
+            assert!(group.iter().all(|&c| c == group[0]));
+        }
+        assert!(result.inertia() < 0.1);
+        assert_eq!(result, kmeans(&grouped_vectors(), &options).unwrap());
+
+        let too_many = KMeansOptions {
+            k: 31,
+            ..Default::default()
+        };
+        assert!(kmeans(&grouped_vectors(), &too_many).is_err());
+    }
+
+    #[test]
+    fn test_cluster_index_stores_assignments() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let index = EmbeddingIndex::open_in_memory()?;
+        let topics = ["rust", "duckdb", "cooking"];
+        let documents: Vec<Document> = (0..30)
+            .map(|i| Document {
+                id: format!("doc-{:02}", i),
+                content: format!("{} note {}", topics[i / 10], i),
+                ..Default::default()
+            })
+            .collect();
+        index.store_batch(0, &documents, &grouped_vectors())?;
+
+        let options = KMeansOptions {
+            k: 3,
+            ..Default::default()
+        };
+        let report = cluster_index(&index, &options, Some(&FirstWordLabeler))?;
+        assert_eq!(report.documents, 30);
+        let mut labels: Vec<String> = report.labels.into_iter().flatten().collect();
+        labels.sort();
+        assert_eq!(labels, ["cooking", "duckdb", "rust"]);
+
+        let output = temp.path().join("clusters.csv");
+        assert_eq!(export_clusters(&index, output.to_str().unwrap())?, 30);
+        let csv = std::fs::read_to_string(&output)?;
+        assert!(csv.starts_with("doc_id,cluster,label,distance,content\n"));
+
+        // Reclustering replaces the previous assignments
+        let report = cluster_index(&index, &KMeansOptions { k: 2, ..options }, None)?;
+        assert_eq!(report.sizes.iter().sum::<usize>(), 30);
+        let stored: i64 =
+            index
+                .connection()
+                .query_row("SELECT COUNT(*) FROM cluster_labels", [], |row| row.get(0))?;
+        assert_eq!(stored, 2);
+        Ok(())
+    }
+}
@@ -3,7 +3,15 @@
 //! This module defines the command-line interface commands and their
 //! argument structures using clap for argument parsing.
 
-use clap::{Parser, Subcommand};
+use super::config::{ModelAlias, ModelSettings};
+use super::dataset_manager::PerformanceOptions;
+use super::federation::{RemoteAttachment, RemoteKind};
+use super::metadata_filter::MetadataFilter;
+use super::response_cache::{parse_ttl, ResponseCache};
+use super::snapshot::Retention;
+use crate::text::chunk::Chunker;
+use crate::text::context::{ContextBudget, ContextStrategy};
+use clap::{Args, Parser, Subcommand};
 
 /// Main CLI application structure for frozen DuckDB operations.
 ///
@@ -35,6 +43,43 @@ pub struct Cli {
     #[arg(short, long, action = clap::ArgAction::Count)]
     pub verbose: u8,
 
+    /// Only log errors, and hide progress bars; command results still go to stdout
+    ///
+    /// There is no `-q`, since `search -q` is the query.
+    #[arg(long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Print plain text instead of emoji (colors are turned off with `NO_COLOR`)
+    #[arg(long, global = true)]
+    pub no_emoji: bool,
+
+    /// Print the command's elapsed time and peak memory (RSS) when it finishes
+    ///
+    /// Also enabled by FROZEN_DUCKDB_TRACK_MEMORY=1.
+    #[arg(long, global = true)]
+    pub track_memory: bool,
+
+    /// Fail with exit code 5 if the command's peak memory exceeds SIZE (e.g. 100MB, 2GiB)
+    ///
+    /// Implies --track-memory. Also read from FROZEN_DUCKDB_MEMORY_BUDGET.
+    #[arg(long, global = true, value_name = "SIZE")]
+    pub memory_budget: Option<String>,
+
+    /// Maximum LLM requests per second (overrides `flock.requests_per_second` in config.json)
+    #[arg(long, global = true)]
+    pub rate_limit: Option<f64>,
+
+    /// Maximum concurrent LLM requests (overrides `flock.max_in_flight` in config.json)
+    #[arg(long, global = true)]
+    pub max_in_flight: Option<usize>,
+
+    /// Export spans of dataset and LLM operations to this OTLP/HTTP endpoint
+    ///
+    /// e.g. http://localhost:4318. Also read from OTEL_EXPORTER_OTLP_ENDPOINT.
+    /// Needs the `otlp` feature.
+    #[arg(long, global = true, value_name = "URL")]
+    pub otlp_endpoint: Option<String>,
+
     /// The command to execute
     #[command(subcommand)]
     pub command: Commands,
@@ -61,6 +106,12 @@ pub enum Commands {
     ///
     /// # Generate TPC-H dataset in Parquet format
     /// frozen-duckdb download --dataset tpch --format parquet --output-dir ./data
+    ///
+    /// # Regenerate a larger TPC-H dataset, bypassing the cache
+    /// frozen-duckdb download --dataset tpch --scale-factor 0.1 --force
+    ///
+    /// # Smaller Parquet files with zstd compression
+    /// frozen-duckdb download --dataset tpch --format parquet --compression zstd
     /// ```
     Download {
         /// Dataset name to download or generate
@@ -86,6 +137,33 @@ pub enum Commands {
         /// - `duckdb`: Native DuckDB database format (fastest for DuckDB)
         #[arg(short, long, default_value = "csv")]
         format: String,
+
+        /// TPC-H scale factor
+        ///
+        /// Controls the size of generated TPC-H data (ignored for Chinook).
+        /// 0.01 produces ~19,000 rows; 1.0 produces the standard ~8.6M rows.
+        #[arg(long, default_value = "0.01")]
+        scale_factor: f64,
+
+        /// Compression codec for Parquet output
+        ///
+        /// Available codecs:
+        /// - `snappy`: Fast, moderate compression (DuckDB default)
+        /// - `zstd`: Smaller files, slightly slower writes
+        /// - `gzip`: Widely compatible, slowest
+        /// - `uncompressed`: No compression
+        #[arg(long, default_value = "snappy")]
+        compression: String,
+
+        /// Regenerate the dataset even if a cached copy exists
+        ///
+        /// Generated datasets are cached in ~/.frozen-duckdb/cache/datasets/ keyed
+        /// by dataset, format, and scale factor.
+        #[arg(long)]
+        force: bool,
+
+        #[command(flatten)]
+        performance: PerformanceArgs,
     },
 
     /// Convert datasets between different file formats.
@@ -102,9 +180,33 @@ pub enum Commands {
     ///
     /// # Convert Parquet to CSV with explicit formats
     /// frozen-duckdb convert --input data.parquet --output data.csv --input-format parquet --output-format csv
+    ///
+    /// # Export one worksheet of an Excel workbook to Parquet
+    /// frozen-duckdb convert --input report.xlsx --output q3.parquet --input-format xlsx --sheet Q3
+    ///
+    /// # Convert GeoJSON to GeoParquet (requires the spatial extension)
+    /// frozen-duckdb convert --input places.geojson --output places.parquet \
+    ///   --input-format geojson --output-format geoparquet --geometry-column shape
+    ///
+    /// # Convert a semicolon-separated export without a header row
+    /// frozen-duckdb convert --input export.csv --output export.parquet --auto
+    ///
+    /// # Split a large export into ~512MB parts plus events/manifest.json
+    /// frozen-duckdb convert --input events.csv --output events --max-file-size 512MB
+    ///
+    /// # Convert a very large file using all cores, without keeping row order
+    /// frozen-duckdb convert --input events.csv --output events.parquet \
+    ///   --threads 16 --preserve-order=false --row-group-size 1000000
+    ///
+    /// # Export a Delta Lake table on S3 to Parquet (delta and httpfs extensions)
+    /// frozen-duckdb convert --input delta://s3://lake/events --output events.parquet
+    ///
+    /// # Convert and register the output in the dataset catalog as `sales`
+    /// frozen-duckdb convert --input sales.csv --output sales.parquet --register sales
     /// ```
     Convert {
-        /// Input file path to convert from
+        /// Input file path to convert from, or the name of a cataloged
+        /// dataset
         #[arg(short, long)]
         input: String,
 
@@ -114,271 +216,1395 @@ pub enum Commands {
 
         /// Input file format
         ///
-        /// Supported input formats: csv, parquet, json
-        #[arg(short, long, default_value = "csv")]
-        input_format: String,
+        /// Supported input formats: csv, parquet, xlsx, jsonl, geojson, geoparquet,
+        /// delta, iceberg. Defaults to the table format of a `delta://` or
+        /// `iceberg://` input or a local table directory, otherwise to csv, or
+        /// to the format of the file extension with --auto.
+        #[arg(short, long)]
+        input_format: Option<String>,
 
         /// Output file format
         ///
-        /// Supported output formats: csv, parquet, json, arrow
+        /// Supported output formats: csv, parquet, xlsx, jsonl, geojson, geoparquet
+        ///
+        /// JSON Lines output is compressed when the output path ends in
+        /// `.jsonl.gz` or `.jsonl.zst`.
         #[arg(short, long, default_value = "parquet")]
         output_format: String,
-    },
 
-    /// Display information about running tests.
-    ///
-    /// This command provides guidance on running the comprehensive test suite.
-    /// It doesn't actually run tests (use `cargo test` for that) but shows
-    /// the available test commands and options.
-    Test,
-
-    /// Benchmark operations to measure performance characteristics.
-    ///
-    /// This command runs performance benchmarks on various DuckDB operations
-    /// to help understand performance characteristics and validate that
-    /// performance targets are being met.
-    ///
-    /// # Examples
-    ///
-    /// ```bash
-    /// # Benchmark query operations
-    /// frozen-duckdb benchmark --operation query --iterations 1000
-    ///
-    /// # Benchmark with different dataset sizes
-    /// frozen-duckdb benchmark --operation insert --size large --iterations 100
-    /// ```
-    Benchmark {
-        /// Operation type to benchmark
+        /// Excel worksheet to read (xlsx input) or to write (xlsx output)
         ///
-        /// Available operations:
-        /// - `query`: SQL query execution performance
-        /// - `insert`: Data insertion performance
-        /// - `export`: Data export performance
-        #[arg(short, long, default_value = "query")]
-        operation: String,
+        /// Defaults to the first worksheet when reading and `Sheet1` when
+        /// writing.
+        #[arg(long)]
+        sheet: Option<String>,
 
-        /// Number of iterations to run for statistical accuracy
+        /// Geometry column of GeoParquet files (default: `geometry`)
         ///
-        /// Higher values provide more accurate results but take longer.
-        /// Recommended: 1000 for quick tests, 10000 for detailed analysis.
-        #[arg(short, long, default_value = "1000")]
-        iterations: usize,
+        /// GeoJSON geometries are written to this column, and GeoParquet
+        /// geometries are read from it. Requires the spatial extension.
+        #[arg(long)]
+        geometry_column: Option<String>,
 
-        /// Dataset size for benchmarking
-        ///
-        /// Available sizes:
-        /// - `small`: ~1K rows (fast, good for development)
-        /// - `medium`: ~10K rows (balanced, good for most testing)
-        /// - `large`: ~100K rows (slow, good for performance validation)
-        #[arg(short, long, default_value = "medium")]
-        size: String,
+        /// Detect the input format from the file extension, and sniff CSV
+        /// input for its delimiter, quoting, header, and column types instead
+        /// of assuming a comma-separated file with a header row
+        #[arg(long)]
+        auto: bool,
+
+        /// Write Parquet output as a directory of parts of about SIZE each
+        /// (e.g. 512MB, 1GiB), listed in OUTPUT/manifest.json
+        #[arg(long, value_name = "SIZE", conflicts_with = "rows_per_file")]
+        max_file_size: Option<String>,
+
+        /// Write Parquet output as a directory of parts of about N rows each,
+        /// listed in OUTPUT/manifest.json
+        #[arg(long, value_name = "N")]
+        rows_per_file: Option<u64>,
+
+        /// Register the output in the dataset catalog under NAME
+        #[arg(long, value_name = "NAME")]
+        register: Option<String>,
+
+        #[command(flatten)]
+        performance: PerformanceArgs,
+
+        #[command(flatten)]
+        lineage: LineageArgs,
     },
 
-    /// Show comprehensive information about frozen DuckDB configuration.
-    ///
-    /// This command displays system information, available extensions,
-    /// architecture details, and configuration status. Useful for
-    /// troubleshooting and verifying that the environment is properly set up.
+    /// Show the columns and types of a dataset file.
     ///
     /// # Examples
     ///
     /// ```bash
-    /// # Show basic information
-    /// frozen-duckdb info
+    /// # Describe a Parquet file
+    /// frozen-duckdb schema --input data.parquet --input-format parquet
     ///
-    /// # Show detailed information with verbose output
-    /// frozen-duckdb -v info
+    /// # Describe one worksheet of an Excel workbook
+    /// frozen-duckdb schema --input report.xlsx --input-format xlsx --sheet Q3
     /// ```
-    Info,
+    Schema {
+        /// Dataset file to describe
+        #[arg(short, long)]
+        input: String,
 
-    // === FLOCK/LLM COMMANDS ===
+        /// Input file format
+        ///
+        /// Supported input formats: csv, parquet, xlsx, jsonl, geojson, geoparquet,
+        /// delta, iceberg. Defaults to csv, or to the table format of a
+        /// `delta://` or `iceberg://` input or a local table directory.
+        #[arg(long)]
+        input_format: Option<String>,
 
-    /// Setup Ollama models and secrets for Flock LLM operations.
+        /// Excel worksheet to describe (xlsx only; defaults to the first)
+        #[arg(long)]
+        sheet: Option<String>,
+    },
+
+    /// Remove duplicate rows from a dataset.
     ///
-    /// This command configures the necessary models and secrets for using
-    /// Flock's LLM capabilities with Ollama. It supports both text generation
-    /// (qwen3-coder:30b) and embedding generation (qwen3-embedding:8b) models.
+    /// Exact duplicates are rows with equal key columns; the first
+    /// occurrence is kept. With `--semantic`, near-duplicates are also
+    /// removed by comparing embeddings of the key columns via Flock.
+    /// Dropped rows are written to a separate report for auditing.
     ///
     /// # Examples
     ///
     /// ```bash
-    /// # Setup default Ollama models
-    /// frozen-duckdb flock-setup
+    /// # Remove exact duplicates by key columns
+    /// frozen-duckdb dedupe --input data.parquet --key-columns a,b
     ///
-    /// # Setup with custom Ollama URL
-    /// frozen-duckdb flock-setup --ollama-url http://localhost:11434
+    /// # Also remove near-duplicates with similarity >= 0.95
+    /// frozen-duckdb dedupe --input data.parquet --key-columns title --semantic --threshold 0.95
     /// ```
-    FlockSetup {
-        /// Ollama server URL
-        ///
-        /// The URL where Ollama is running. Defaults to localhost:11434.
-        #[arg(long, default_value = "http://localhost:11434")]
-        ollama_url: String,
+    Dedupe {
+        /// Input file path (csv, parquet, or json)
+        #[arg(short, long)]
+        input: String,
 
-        /// Text generation model
-        ///
-        /// The Ollama model to use for text completion and generation.
-        /// Examples: llama3.1:8b, qwen2.5:14b, codellama:13b
-        #[arg(long, default_value = "qwen3-coder:30b")]
-        text_model: String,
+        /// Comma-separated columns identifying duplicate rows
+        #[arg(short, long, value_delimiter = ',', required = true)]
+        key_columns: Vec<String>,
 
-        /// Embedding model
+        /// Output file for the deduplicated dataset
         ///
-        /// The Ollama model to use for generating embeddings.
-        /// Examples: mxbai-embed-large, all-minilm:latest, nomic-embed-text
-        #[arg(long, default_value = "qwen3-embedding:8b")]
-        embedding_model: String,
+        /// Defaults to `<input>_deduped.<ext>`.
+        #[arg(short, long)]
+        output: Option<String>,
 
-        /// Skip model verification
+        /// Report file listing dropped rows and what they duplicate
         ///
-        /// If set, skips checking if the required models are available.
-        /// Useful for offline setup or when models will be pulled later.
+        /// Defaults to `<input>_dropped.<ext>`.
+        #[arg(short, long)]
+        report: Option<String>,
+
+        /// Also remove semantic near-duplicates using embeddings
         #[arg(long)]
-        skip_verification: bool,
+        semantic: bool,
+
+        /// Similarity threshold (0.0 to 1.0) for semantic near-duplicates
+        #[arg(short, long, default_value = "0.95", requires = "semantic")]
+        threshold: f32,
+
+        /// Model to use for embedding generation
+        #[arg(short, long, default_value = "embedder")]
+        model: String,
+
+        #[command(flatten)]
+        lineage: LineageArgs,
     },
 
-    /// Generate text completions using LLM models via Flock.
+    /// Run a SQL query and print the results.
     ///
-    /// This command uses the configured LLM models to generate text completions
-    /// for the provided prompts. Supports both single prompts and batch processing.
+    /// This command executes SQL against an in-memory database (or a DuckDB
+    /// file) with the parquet and tpch extensions loaded. Built-in scalar
+    /// functions such as `normalize_text` are available when the CLI is
+    /// built with the `vscalar` feature.
     ///
     /// # Examples
     ///
     /// ```bash
-    /// # Complete a single prompt
-    /// frozen-duckdb complete --prompt "Write a hello world function in Python"
+    /// # Query a Parquet file directly
+    /// frozen-duckdb query --sql "SELECT COUNT(*) FROM 'data.parquet'"
     ///
-    /// # Complete with specific model
-    /// frozen-duckdb complete --prompt "Explain recursion" --model coder
+    /// # Use a built-in scalar function
+    /// frozen-duckdb query --sql "SELECT normalize_text('  Hello   WORLD ')"
     ///
-    /// # Batch completion from file
-    /// frozen-duckdb complete --input prompts.txt --output responses.txt
+    /// # Query a database file and emit JSON
+    /// frozen-duckdb query --database tpch.duckdb --sql "SELECT * FROM region" --format json
+    ///
+    /// # Stream results as JSON Lines
+    /// frozen-duckdb query --sql "SELECT * FROM 'events.parquet'" --format jsonl
+    ///
+    /// # Export to a zstd-compressed JSON Lines file
+    /// frozen-duckdb query --sql "SELECT * FROM 'events.parquet'" --output events.jsonl.zst
+    ///
+    /// # Join across databases
+    /// frozen-duckdb query --attach prod=prod.duckdb --attach backup=backup.duckdb \
+    ///   --sql "SELECT id FROM prod.orders EXCEPT SELECT id FROM backup.orders"
+    ///
+    /// # Re-run a dashboard aggregate from the cache until sales.parquet changes
+    /// frozen-duckdb query --sql "SELECT region, SUM(amount) FROM 'sales.parquet' GROUP BY region" --cache
+    ///
+    /// # Monitor a dataset growing during ingestion
+    /// frozen-duckdb query --sql "SELECT COUNT(*) FROM 'events/*.parquet'" --watch 5s
+    ///
+    /// # Read a Delta Lake or Iceberg table (delta, iceberg extensions)
+    /// frozen-duckdb query --sql "SELECT COUNT(*) FROM 'delta://s3://lake/events'"
+    ///
+    /// # Query a live Postgres database (password from `secrets add`)
+    /// frozen-duckdb query --attach-postgres "secret:prod AS pg" \
+    ///   --sql "SELECT status, COUNT(*) FROM pg.public.orders GROUP BY status"
+    ///
+    /// # Load a SQL script, committing every 1000 statements
+    /// frozen-duckdb query --database app.duckdb --sql "$(cat load.sql)" --transaction-size 1000
     /// ```
-    Complete {
-        /// Text prompt for completion
+    Query {
+        /// SQL statement to execute
+        #[arg(short, long)]
+        sql: String,
+
+        /// DuckDB database file to open
         ///
-        /// The text to complete using the LLM model.
+        /// If not provided, an in-memory database is used.
         #[arg(short, long)]
-        prompt: Option<String>,
+        database: Option<String>,
 
-        /// Input file containing prompts (one per line)
+        /// Additional database to attach as ALIAS=PATH (repeatable)
         ///
-        /// If provided, each line will be treated as a separate prompt.
-        /// Cannot be used with --prompt.
-        #[arg(short, long, conflicts_with = "prompt")]
-        input: Option<String>,
+        /// Its tables are queried as `alias.table`.
+        /// Example: --attach prod=prod.duckdb --attach ref=reference.duckdb
+        #[arg(long = "attach", value_name = "ALIAS=PATH", value_parser = parse_attachment)]
+        attach: Vec<(String, String)>,
 
-        /// Output file for responses
+        /// Output format for results
         ///
-        /// If provided, responses will be written to this file.
-        /// If not provided, responses will be printed to stdout.
+        /// Available formats:
+        /// - `table`: Aligned, human-readable columns
+        /// - `csv`: Comma-separated values with a header row
+        /// - `json`: Array of objects keyed by column name
+        /// - `jsonl`: One object per line, streamed as rows are read
+        #[arg(short, long, default_value = "table")]
+        format: String,
+
+        /// Stream results into a JSON Lines file instead of printing them
+        ///
+        /// The extension selects the compression: `.jsonl`, `.jsonl.gz`,
+        /// or `.jsonl.zst`.
         #[arg(short, long)]
         output: Option<String>,
 
-        /// Model to use for completion
+        /// Run the statements as a write script, committing every N statements
         ///
-        /// Use the model alias configured during setup (default: "text_generator")
-        /// This corresponds to the text generation model set in flock-setup.
-        #[arg(short, long, default_value = "text_generator")]
-        model: String,
+        /// Statements are split at top-level semicolons and run in explicit
+        /// transactions of N statements, which is much faster than
+        /// autocommit for bulk loads. If a statement fails, its batch is
+        /// rolled back and earlier batches stay committed. Use 0 for
+        /// autocommit. Results of SELECT statements are not printed.
+        #[arg(long, value_name = "N")]
+        transaction_size: Option<usize>,
 
-        /// Maximum tokens to generate
+        /// Serve the result from the query cache while the SQL and its input
+        /// files are unchanged, storing it on a miss
         ///
-        /// Controls the length of the generated response.
-        /// Higher values allow for longer responses.
-        #[arg(long, default_value = "512")]
-        max_tokens: usize,
+        /// Also enabled by FROZEN_DUCKDB_QUERY_CACHE=1. Only applies to
+        /// table, csv, and json output.
+        #[arg(long, overrides_with = "no_cache")]
+        cache: bool,
 
-        /// Temperature for text generation
-        ///
-        /// Controls randomness in generation:
-        /// - 0.0: Deterministic (same input always gives same output)
-        /// - 1.0: Balanced creativity and coherence
-        /// - Higher: More creative but potentially less coherent
-        #[arg(short, long, default_value = "0.7")]
-        temperature: f32,
+        /// Bypass the query cache, even if FROZEN_DUCKDB_QUERY_CACHE is set
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Re-run the query on this interval (e.g. 5s, 1m) and when its input
+        /// files change, highlighting values that changed; stop with Ctrl-C
+        #[arg(long, value_name = "INTERVAL", conflicts_with_all = ["output", "transaction_size"])]
+        watch: Option<String>,
+
+        /// Profile the query and write `query.json` and a flame graph
+        /// report, `query.html`, to this directory
+        #[arg(long, value_name = "DIR", conflicts_with_all = ["watch", "transaction_size"])]
+        profile: Option<String>,
+
+        #[command(flatten)]
+        federation: FederationArgs,
+
+        #[command(flatten)]
+        performance: PerformanceArgs,
     },
 
-    /// Generate embeddings for text using LLM models via Flock.
+    /// Run a multi-statement SQL script, such as an ETL step.
     ///
-    /// This command generates vector embeddings for the provided text,
-    /// which can be used for semantic search, similarity comparison,
-    /// and other vector-based operations.
+    /// `${name}` in the script is replaced by `--var name=value`, and
+    /// `${name:-default}` falls back to a default. Each statement's timing
+    /// is printed as it finishes.
     ///
     /// # Examples
     ///
     /// ```bash
-    /// # Generate embedding for single text
-    /// frozen-duckdb embed --text "Python is a programming language"
+    /// # Load one day of events
+    /// frozen-duckdb run load.sql --database warehouse.duckdb --var date=2024-01-01
     ///
-    /// # Generate embeddings for multiple texts from file
-    /// frozen-duckdb embed --input texts.txt --output embeddings.json
+    /// # All-or-nothing load
+    /// frozen-duckdb run load.sql --database warehouse.duckdb --transaction script
+    ///
+    /// # Keep going past failing statements
+    /// frozen-duckdb run cleanup.sql --database warehouse.duckdb --on-error continue
+    ///
+    /// # Copy tables out of a MySQL database
+    /// frozen-duckdb run ingest.sql --database warehouse.duckdb \
+    ///   --attach-mysql "host=db.internal user=etl database=shop AS shop"
     /// ```
-    Embed {
-        /// Text to generate embeddings for
+    Run {
+        /// SQL script file
+        script: String,
+
+        /// DuckDB database file to run against
         ///
-        /// The text content to convert into vector embeddings.
+        /// If not provided, an in-memory database is used.
         #[arg(short, long)]
-        text: Option<String>,
+        database: Option<String>,
 
-        /// Input file containing texts (one per line)
-        ///
-        /// If provided, each line will be treated as separate text for embedding.
-        /// Cannot be used with --text.
-        #[arg(short, long, conflicts_with = "text")]
-        input: Option<String>,
+        /// Variable substituted for ${NAME} in the script (repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_variable)]
+        vars: Vec<(String, String)>,
 
-        /// Output file for embeddings
-        ///
-        /// If provided, embeddings will be written to this file in JSON format.
-        /// If not provided, embeddings will be printed to stdout.
-        #[arg(short, long)]
-        output: Option<String>,
+        /// What to do when a statement fails (stop, continue)
+        #[arg(long, default_value = "stop")]
+        on_error: String,
 
-        /// Model to use for embedding generation
+        /// Transaction wrapping: none, script, or a statement count
         ///
-        /// Use the model alias configured during setup (default: "embedder")
-        /// This corresponds to the embedding model set in flock-setup.
-        #[arg(short, long, default_value = "embedder")]
-        model: String,
+        /// `none` commits each statement on its own, `script` commits all
+        /// statements together or not at all, and N commits every N
+        /// statements.
+        #[arg(long, default_value = "none")]
+        transaction: String,
 
-        /// Normalize embeddings
-        ///
-        /// If set, embeddings will be normalized to unit length.
-        /// Useful for cosine similarity calculations.
-        #[arg(long)]
-        normalize: bool,
+        #[command(flatten)]
+        federation: FederationArgs,
+
+        #[command(flatten)]
+        performance: PerformanceArgs,
     },
 
-    /// Perform semantic search using embeddings and Flock.
+    /// Load a file or lakehouse table into a database table.
     ///
-    /// This command performs semantic similarity search by comparing
-    /// query embeddings against a corpus of documents. Results are
-    /// ranked by semantic similarity rather than just keyword matching.
+    /// The table is replaced by default. With `--incremental`, only rows
+    /// whose watermark column is above the highest value loaded last time
+    /// are read, and they are upserted on the key columns with
+    /// `INSERT ... ON CONFLICT`. Watermarks are kept in the dataset catalog.
     ///
     /// # Examples
     ///
     /// ```bash
-    /// # Search in a document corpus
-    /// frozen-duckdb search --query "machine learning algorithms" --corpus documents.txt
+    /// # Replace the orders table
+    /// frozen-duckdb ingest --input orders.parquet --database shop.duckdb --table orders
     ///
-    /// # Search with specific similarity threshold
-    /// frozen-duckdb search --query "data science" --corpus docs/ --threshold 0.8
+    /// # Merge only new and changed orders on each run
+    /// frozen-duckdb ingest --input orders.parquet --database shop.duckdb --table orders \
+    ///   --incremental --key id --watermark-column updated_at
+    ///
+    /// # Reload everything on the next incremental run
+    /// frozen-duckdb ingest --input orders.parquet --database shop.duckdb --table orders \
+    ///   --incremental --key id --watermark-column updated_at --full-refresh
     /// ```
-    Search {
-        /// Search query text
-        ///
-        /// The text query to search for semantically similar content.
+    Ingest {
+        /// Input file, table reference, or cataloged dataset name
         #[arg(short, long)]
-        query: String,
+        input: String,
 
-        /// Corpus file or directory
-        ///
-        /// File containing documents (one per line) or directory containing text files.
+        /// Input format (csv, parquet, jsonl, xlsx, delta, iceberg, ...);
+        /// detected from the input by default
+        #[arg(long)]
+        input_format: Option<String>,
+
+        /// DuckDB database file to load into
         #[arg(short, long)]
-        corpus: String,
+        database: String,
 
-        /// Similarity threshold
+        /// Target table
+        #[arg(short, long)]
+        table: String,
+
+        /// Load only new and changed rows, merging them on the keys
+        #[arg(long, requires_all = ["key_columns", "watermark_column"])]
+        incremental: bool,
+
+        /// Columns identifying a row (comma-separated)
+        #[arg(short, long = "key", value_delimiter = ',', requires = "incremental")]
+        key_columns: Vec<String>,
+
+        /// Column that increases when a row is added or changed, e.g. updated_at
+        #[arg(long, requires = "incremental")]
+        watermark_column: Option<String>,
+
+        /// Forget the recorded watermark and read every row (still merging)
+        #[arg(long, requires = "incremental")]
+        full_refresh: bool,
+
+        #[command(flatten)]
+        performance: PerformanceArgs,
+    },
+
+    /// Upsert rows from a file or table into a table or file.
+    ///
+    /// Source rows are matched to target rows on the key columns. Matched
+    /// rows are updated, ignored, or deleted, and unmatched source rows are
+    /// inserted or ignored. The SQL is `INSERT ... ON CONFLICT` when the
+    /// target table has a key constraint on the keys, `MERGE INTO`
+    /// otherwise. CSV, Parquet, and JSON target files are rewritten.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Upsert a day's changes into a table
+    /// frozen-duckdb merge --source changes.parquet --target orders \
+    ///   --database shop.duckdb --key id
+    ///
+    /// # Apply deletions listed in a CSV file
+    /// frozen-duckdb merge --source cancelled.csv --target orders --database shop.duckdb \
+    ///   --key id --when-matched delete --when-not-matched ignore
+    ///
+    /// # Upsert into a Parquet file
+    /// frozen-duckdb merge --source changes.csv --target customers.parquet --key customer_id
+    /// ```
+    Merge {
+        /// Source file, table reference, or table name
+        #[arg(short, long)]
+        source: String,
+
+        /// Target table, or a CSV, Parquet, or JSON file to rewrite
+        #[arg(short, long)]
+        target: String,
+
+        /// DuckDB database holding the target table (and source tables)
+        #[arg(short, long)]
+        database: Option<String>,
+
+        /// Columns matching source rows to target rows (comma-separated)
+        #[arg(short, long = "key", value_delimiter = ',', required = true)]
+        key_columns: Vec<String>,
+
+        /// What to do with matched target rows (update, ignore, delete)
+        #[arg(long, default_value = "update")]
+        when_matched: String,
+
+        /// What to do with unmatched source rows (insert, ignore)
+        #[arg(long, default_value = "insert")]
+        when_not_matched: String,
+    },
+
+    /// Build a directory of SQL models in dependency order.
+    ///
+    /// Each `.sql` file is a SELECT materialized as a table named after the
+    /// file. Models refer to each other with `{{ ref('name') }}`.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Build every model
+    /// frozen-duckdb build-models --models models/ --database warehouse.duckdb
+    ///
+    /// # Rebuild one model and everything downstream of it
+    /// frozen-duckdb build-models --database warehouse.duckdb --select clean_orders+
+    /// ```
+    BuildModels {
+        /// Directory of model .sql files
+        #[arg(short, long, default_value = "models")]
+        models: String,
+
+        /// DuckDB database file the models are built in
+        #[arg(short, long)]
+        database: String,
+
+        /// Models to build: NAME, NAME+ (downstream), +NAME (upstream);
+        /// repeatable or comma-separated, all models if omitted
+        #[arg(short, long)]
+        select: Vec<String>,
+    },
+
+    /// Mask sensitive columns before sharing a dataset.
+    ///
+    /// Columns are hashed, tokenized, shuffled, redacted, or replaced with
+    /// fake values as configured in a YAML mask file. With the same salt,
+    /// equal values mask identically across files, so joins keep working.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// frozen-duckdb mask --input users.parquet --config mask.yaml
+    ///
+    /// # Share a salt between related tables
+    /// FROZEN_DUCKDB_MASK_SALT=s3cret frozen-duckdb mask -i orders.csv -c mask.yaml -o orders_public.csv
+    /// ```
+    Mask {
+        /// Input file path (csv, parquet, or json)
+        #[arg(short, long)]
+        input: String,
+
+        /// YAML file mapping columns to masking methods
+        #[arg(short, long)]
+        config: String,
+
+        /// Output file for the masked dataset
+        ///
+        /// Defaults to `<input>_masked.<ext>`.
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Salt keying hashes, tokens, fakes, and shuffles
+        ///
+        /// Falls back to FROZEN_DUCKDB_MASK_SALT, then the mask file's `salt`.
+        #[arg(long)]
+        salt: Option<String>,
+
+        #[command(flatten)]
+        lineage: LineageArgs,
+    },
+
+    /// Share a per-consumer subset of a multi-tenant database.
+    ///
+    /// A YAML policy filters rows (e.g. `tenant_id: 42`) and drops columns.
+    /// The subset is created as views in a schema named after the consumer
+    /// (`--views`), exported as a database file the consumer attaches
+    /// read-only (`--output`), or, with neither, printed as SQL. Tables
+    /// without a filter column are only shared when the policy lists them.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Review what the policy would share
+    /// frozen-duckdb share --database saas.duckdb --policy acme.yaml
+    ///
+    /// # Export Acme's rows to a file of their own
+    /// frozen-duckdb share --database saas.duckdb --policy acme.yaml --output acme.duckdb
+    ///
+    /// # Create acme.* views in the database
+    /// frozen-duckdb share --database saas.duckdb --policy acme.yaml --views
+    /// ```
+    Share {
+        /// DuckDB database to subset
+        #[arg(short, long)]
+        database: String,
+
+        /// YAML share policy
+        #[arg(short, long)]
+        policy: String,
+
+        /// Export the subset to this new database file
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Create the filtered views in the consumer's schema of the database
+        #[arg(long)]
+        views: bool,
+    },
+
+    /// Join two files on key columns without writing SQL.
+    ///
+    /// Inputs may be different formats. Reports how many rows on each side
+    /// matched, samples unmatched keys, and warns about keys whose types
+    /// differ between the files.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// frozen-duckdb join --left a.csv --right b.parquet --on id --how left --output joined.parquet
+    ///
+    /// # Keys named differently on each side
+    /// frozen-duckdb join -l orders.csv -r customers.json --on customer_id=id
+    /// ```
+    Join {
+        /// Left input file (csv, parquet, or json)
+        #[arg(short, long)]
+        left: String,
+
+        /// Right input file (csv, parquet, or json)
+        #[arg(short, long)]
+        right: String,
+
+        /// Comma-separated keys: NAME, or LEFT=RIGHT when names differ
+        #[arg(long, value_delimiter = ',', required = true)]
+        on: Vec<String>,
+
+        /// Join type: inner, left, right, full, semi, or anti
+        #[arg(long, default_value = "inner")]
+        how: String,
+
+        /// Output file; defaults to `<left>_joined.<ext>`
+        #[arg(short, long)]
+        output: Option<String>,
+
+        #[command(flatten)]
+        lineage: LineageArgs,
+    },
+
+    /// Reshape a dataset between long and wide layouts.
+    ///
+    /// Built on DuckDB's PIVOT and UNPIVOT. The output keeps the input's
+    /// format unless `--output` names a different extension.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # One column per month with each customer's total amount
+    /// frozen-duckdb reshape pivot --input sales.csv --on month --values amount --group-by customer
+    ///
+    /// # Back to one row per customer and month
+    /// frozen-duckdb reshape unpivot --input sales_pivoted.csv --keep customer --name month --value amount
+    /// ```
+    Reshape {
+        #[command(subcommand)]
+        action: ReshapeAction,
+    },
+
+    /// Check a dataset against YAML validation rules.
+    ///
+    /// Runs not_null, unique, accepted_values, row_count_between, and custom
+    /// SQL rules, prints a JSON pass/fail report, and exits with status 1 if
+    /// any `error` rule fails.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// frozen-duckdb validate-data --rules rules.yaml --input data.parquet
+    ///
+    /// # Human-readable summary
+    /// frozen-duckdb validate-data --rules rules.yaml --input data.csv --format table
+    /// ```
+    ValidateData {
+        /// YAML rule file
+        #[arg(short, long)]
+        rules: String,
+
+        /// CSV, Parquet, or JSON file to validate
+        #[arg(short, long)]
+        input: String,
+
+        /// Report format: json or table
+        #[arg(short, long, default_value = "json")]
+        format: String,
+    },
+
+    /// Display information about running tests.
+    ///
+    /// This command provides guidance on running the comprehensive test suite.
+    /// It doesn't actually run tests (use `cargo test` for that) but shows
+    /// the available test commands and options.
+    Test,
+
+    /// Benchmark operations to measure performance characteristics.
+    ///
+    /// This command runs performance benchmarks on various DuckDB operations
+    /// to help understand performance characteristics and validate that
+    /// performance targets are being met.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Benchmark query operations
+    /// frozen-duckdb benchmark --operation query --iterations 1000
+    ///
+    /// # Compare per-row INSERT with the Appender, flushing every 10K rows
+    /// frozen-duckdb benchmark --operation insert --size large --iterations 5 --flush-interval 10000
+    /// ```
+    Benchmark {
+        /// Operation type to benchmark
+        ///
+        /// Available operations:
+        /// - `query`: SQL query execution performance
+        /// - `insert`: Per-row `INSERT` versus the Appender, in rows/sec
+        /// - `export`: Parquet export with DuckDB's defaults versus tuned settings
+        #[arg(short, long, default_value = "query")]
+        operation: String,
+
+        /// Number of iterations to run for statistical accuracy
+        ///
+        /// Higher values provide more accurate results but take longer.
+        /// Recommended: 1000 for quick tests, 10000 for detailed analysis.
+        #[arg(short, long, default_value = "1000")]
+        iterations: usize,
+
+        /// Dataset size for benchmarking
+        ///
+        /// Available sizes:
+        /// - `small`: ~1K rows (fast, good for development)
+        /// - `medium`: ~10K rows (balanced, good for most testing)
+        /// - `large`: ~100K rows (slow, good for performance validation)
+        #[arg(short, long, default_value = "medium")]
+        size: String,
+
+        /// Rows appended between Appender flushes for `insert` (0 flushes once at the end)
+        #[arg(long, default_value_t = crate::ingest::DEFAULT_FLUSH_INTERVAL)]
+        flush_interval: usize,
+
+        /// Profile one run of `query` and write `benchmark_query.json` and
+        /// a flame graph report, `benchmark_query.html`, to this directory
+        #[arg(long, value_name = "DIR")]
+        profile: Option<String>,
+
+        /// Output format for the statistics
+        ///
+        /// Available formats:
+        /// - `text`: Human-readable report
+        /// - `json`: Object keyed by benchmark name, with durations in
+        ///   nanoseconds and `peak_rss_bytes`; usable as a baselines file
+        #[arg(long, default_value = "text")]
+        format: String,
+
+        /// Settings for the tuned side of `export`, which turns off
+        /// insertion order unless `--preserve-order` is given
+        #[command(flatten)]
+        performance: PerformanceArgs,
+    },
+
+    /// Show comprehensive information about frozen DuckDB configuration.
+    ///
+    /// This command displays system information, available extensions,
+    /// architecture details, and configuration status. Useful for
+    /// troubleshooting and verifying that the environment is properly set up.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Show basic information
+    /// frozen-duckdb info
+    ///
+    /// # Show detailed information with verbose output
+    /// frozen-duckdb -v info
+    ///
+    /// # Print the capability report for scripts
+    /// frozen-duckdb info --format json
+    /// ```
+    Info {
+        /// Output format
+        ///
+        /// - `human`: Readable summary (default)
+        /// - `json`: Capability report with extensions and ICU support
+        #[arg(short, long, default_value = "human")]
+        format: String,
+    },
+
+    /// Copy the DuckDB library next to an executable for distribution.
+    ///
+    /// Executables linked against frozen DuckDB look for the library in
+    /// `~/.frozen-duckdb/cache/` on the build machine. This command copies
+    /// the library into the target directory and rewrites install names
+    /// (macOS, `install_name_tool`) or the soname and RPATH (Linux,
+    /// `patchelf`) so the executable loads it from its own directory.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Bundle a release build into dist/
+    /// frozen-duckdb bundle --target-dir dist/ --executable target/release/my-app
+    ///
+    /// # Show the patch commands without changing anything
+    /// frozen-duckdb bundle --target-dir dist/ --executable target/release/my-app --dry-run
+    /// ```
+    Bundle {
+        /// Directory to place the library (and executable) in
+        #[arg(short, long)]
+        target_dir: String,
+
+        /// Executable to patch; copied into the target directory if needed
+        #[arg(short, long)]
+        executable: Option<String>,
+
+        /// Library to bundle (default: the cached frozen DuckDB binary)
+        #[arg(short, long)]
+        library: Option<String>,
+
+        /// Print the patch commands without copying or modifying files
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Show local build-time statistics recorded by build telemetry.
+    ///
+    /// Build telemetry is opt-in: set `FROZEN_DUCKDB_TELEMETRY=1` when
+    /// building and the build script records whether the DuckDB binary came
+    /// from the cache, a download, or a local compile, and how long it took.
+    /// This command aggregates those local records into cache hit rates and
+    /// estimated time saved. Nothing is sent over the network.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Record builds, then view the savings
+    /// FROZEN_DUCKDB_TELEMETRY=1 cargo build
+    /// frozen-duckdb stats
+    ///
+    /// # Machine-readable output
+    /// frozen-duckdb stats --format json
+    /// ```
+    Stats {
+        /// Output format (human, json)
+        #[arg(short, long, default_value = "human")]
+        format: String,
+    },
+
+    // === FLOCK/LLM COMMANDS ===
+
+    /// Setup Ollama models and secrets for Flock LLM operations.
+    ///
+    /// This command configures the necessary models and secrets for using
+    /// Flock's LLM capabilities with Ollama. It supports both text generation
+    /// (qwen3-coder:30b) and embedding generation (qwen3-embedding:8b) models.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Setup default Ollama models
+    /// frozen-duckdb flock-setup
+    ///
+    /// # Setup with custom Ollama URL
+    /// frozen-duckdb flock-setup --ollama-url http://localhost:11434
+    /// ```
+    FlockSetup {
+        /// Ollama server URL
+        ///
+        /// The URL where Ollama is running. Defaults to localhost:11434.
+        #[arg(long, default_value = "http://localhost:11434")]
+        ollama_url: String,
+
+        /// Text generation model
+        ///
+        /// The Ollama model to use for text completion and generation.
+        /// Examples: llama3.1:8b, qwen2.5:14b, codellama:13b
+        #[arg(long, default_value = "qwen3-coder:30b")]
+        text_model: String,
+
+        /// Embedding model
+        ///
+        /// The Ollama model to use for generating embeddings.
+        /// Examples: mxbai-embed-large, all-minilm:latest, nomic-embed-text
+        #[arg(long, default_value = "qwen3-embedding:8b")]
+        embedding_model: String,
+
+        /// Skip model verification
+        ///
+        /// If set, skips checking if the required models are available.
+        /// Useful for offline setup or when models will be pulled later.
+        #[arg(long)]
+        skip_verification: bool,
+
+        /// Additional model alias as NAME=MODEL (repeatable)
+        ///
+        /// Aliases are saved to the config file and can be passed to `--model`.
+        /// Example: --alias fast=llama3.2:1b --alias accurate=llama3.1:70b
+        #[arg(long = "alias", value_name = "NAME=MODEL", value_parser = parse_alias)]
+        aliases: Vec<ModelAlias>,
+
+        #[command(flatten)]
+        settings: ModelSettingsArgs,
+    },
+
+    /// Manage named model aliases.
+    ///
+    /// Aliases map a short name to a provider model and are stored in
+    /// `~/.frozen-duckdb/config.json`. Any alias can be passed to the
+    /// `--model` option of the LLM commands.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Add a fast model for filtering
+    /// frozen-duckdb models add fast llama3.2:1b --temperature 0.2
+    ///
+    /// # Give a model a larger context window without recreating it
+    /// frozen-duckdb models update accurate --num-ctx 16384 --model-param top_p=0.9
+    ///
+    /// # Use it
+    /// frozen-duckdb filter --criteria "Is this spam?" --input emails.txt --model fast
+    ///
+    /// # List and remove aliases
+    /// frozen-duckdb models list
+    /// frozen-duckdb models remove fast
+    /// ```
+    Models {
+        #[command(subcommand)]
+        action: ModelsAction,
+    },
+
+    /// Manage the audit log of LLM interactions.
+    ///
+    /// When enabled, every prompt and response of the LLM commands is
+    /// recorded with its command, model, and latency. The `hash` policy
+    /// keeps only a SHA-256 hash of each prompt; `full` keeps the text.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Record prompt hashes in ~/.frozen-duckdb/audit.duckdb
+    /// frozen-duckdb audit enable
+    ///
+    /// # Record full prompts in a JSONL file
+    /// frozen-duckdb audit enable --policy full --jsonl /var/log/frozen-duckdb/audit.jsonl
+    ///
+    /// # Export the last week of filter calls
+    /// frozen-duckdb audit export --output audit.parquet --since 7d --command filter
+    /// ```
+    Audit {
+        #[command(subcommand)]
+        action: AuditAction,
+    },
+
+    /// Manage materialized views stored in a database.
+    ///
+    /// A materialized view is a table rebuilt from a stored SELECT. Views
+    /// that read other views are refreshed after them.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Define and build a view
+    /// frozen-duckdb views --database analytics.duckdb define daily_sales \
+    ///   "SELECT day, SUM(amount) AS total FROM 'sales/*.parquet' GROUP BY day"
+    ///
+    /// # Rebuild every view in dependency order
+    /// frozen-duckdb views --database analytics.duckdb refresh
+    ///
+    /// # Show definitions and when they were last refreshed
+    /// frozen-duckdb views --database analytics.duckdb list
+    /// ```
+    Views {
+        /// DuckDB database file holding the views
+        #[arg(short, long)]
+        database: String,
+
+        #[command(subcommand)]
+        action: ViewsAction,
+    },
+
+    /// Serve a database over an HTTP query API.
+    ///
+    /// `POST /query` runs the SQL in the request body and returns JSON, or
+    /// an Arrow IPC stream with `Accept: application/vnd.apache.arrow.stream`.
+    /// `GET /healthz` reports whether the database is usable, and
+    /// `GET /metrics` exposes Prometheus metrics.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Serve read-only on localhost, requiring a bearer token
+    /// frozen-duckdb serve --database analytics.duckdb --listen 127.0.0.1:8080 --token s3cret
+    ///
+    /// # Query it
+    /// curl -H "Authorization: Bearer s3cret" -d "SELECT 42" http://127.0.0.1:8080/query
+    ///
+    /// # Let psql and BI tools connect over the PostgreSQL protocol (experimental)
+    /// frozen-duckdb serve --database analytics.duckdb --protocol postgres --listen 127.0.0.1:5432
+    ///
+    /// # Cache results and scrape metrics on a separate port
+    /// frozen-duckdb serve --database analytics.duckdb --cache --metrics-listen 127.0.0.1:9187
+    ///
+    /// # Run up to 8 queries at once, each for at most 30 seconds
+    /// frozen-duckdb serve --database analytics.duckdb --max-connections 8 --timeout 30s
+    /// ```
+    Serve {
+        /// DuckDB database file to serve
+        #[arg(short, long)]
+        database: String,
+
+        /// Address to listen on
+        #[arg(short, long, default_value = "127.0.0.1:8080")]
+        listen: String,
+
+        /// Protocol to serve (http, postgres)
+        ///
+        /// `postgres` is experimental: simple query protocol and text
+        /// results only, with --token as the password.
+        #[arg(long, default_value = "http")]
+        protocol: String,
+
+        /// Bearer token clients must send on /query (the password with
+        /// --protocol postgres)
+        ///
+        /// Also read from FROZEN_DUCKDB_SERVE_TOKEN. API keys limited to
+        /// roles are configured in the `serve` section of the config file.
+        #[arg(long)]
+        token: Option<String>,
+
+        /// Allow statements that modify the database
+        ///
+        /// By default the database is opened read-only.
+        #[arg(long)]
+        read_write: bool,
+
+        /// Let queries read and write other files, ATTACH databases, and
+        /// INSTALL or LOAD extensions
+        ///
+        /// By default queries can only reach the served database.
+        #[arg(long)]
+        allow_file_access: bool,
+
+        /// Serve JSON query results from the query cache
+        ///
+        /// Entries are reused until the SQL or one of its input files
+        /// changes. Not allowed with --read-write.
+        #[arg(long)]
+        cache: bool,
+
+        /// Also serve /metrics on this address, e.g. for --protocol postgres
+        #[arg(long, value_name = "ADDR")]
+        metrics_listen: Option<String>,
+
+        /// Queries to run at once, each on its own database connection
+        ///
+        /// PostgreSQL clients hold a connection until they disconnect.
+        #[arg(long, default_value = "4")]
+        max_connections: usize,
+
+        /// Requests to queue while all connections are busy
+        ///
+        /// Requests beyond the queue are answered with 503 and Retry-After,
+        /// and PostgreSQL clients are refused with too_many_connections.
+        #[arg(long, default_value = "64")]
+        max_queue: usize,
+
+        /// Longest a request waits for a connection (e.g. 30s, 1m)
+        #[arg(long, default_value = "30s")]
+        queue_timeout: String,
+
+        /// Interrupt queries running longer than this (e.g. 30s, 5m)
+        #[arg(long)]
+        timeout: Option<String>,
+
+        /// Reject results with more rows than this
+        #[arg(long)]
+        max_rows: Option<usize>,
+    },
+
+    /// Run recurring dataset tasks defined in a TOML job file.
+    ///
+    /// Jobs run SQL scripts, convert files, refresh embedding indexes, or
+    /// back up databases on cron-like schedules. Every run is recorded in a
+    /// DuckDB history table.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Run the jobs that are due, then exit (e.g. from system cron)
+    /// frozen-duckdb jobs --file jobs.toml run --once
+    ///
+    /// # Run as a long-lived scheduler
+    /// frozen-duckdb jobs --file jobs.toml run
+    ///
+    /// # Run one job now, whether or not it is due
+    /// frozen-duckdb jobs --file jobs.toml run --job nightly-load
+    ///
+    /// # Show recent runs
+    /// frozen-duckdb jobs --file jobs.toml history --limit 20
+    /// ```
+    Jobs {
+        /// TOML file defining the jobs
+        #[arg(short, long, default_value = "jobs.toml")]
+        file: String,
+
+        #[command(subcommand)]
+        action: JobsAction,
+    },
+
+    /// Manage cached query results.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Remove every cached query result
+    /// frozen-duckdb cache invalidate
+    /// ```
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+
+    /// Find and manage registered datasets.
+    ///
+    /// Downloads and `convert --register` add datasets to the catalog in
+    /// `~/.frozen-duckdb/cache/catalog.duckdb`. Queries read them as
+    /// `datasets.<name>`, and `convert --input` accepts their names.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # List registered datasets
+    /// frozen-duckdb catalog list
+    ///
+    /// # Show one dataset and whether its file changed since registration
+    /// frozen-duckdb catalog show tpch_lineitem
+    ///
+    /// # Forget a dataset (its files are kept)
+    /// frozen-duckdb catalog rm tpch_lineitem
+    /// ```
+    Catalog {
+        #[command(subcommand)]
+        action: CatalogAction,
+    },
+
+    /// Take, list, and restore point-in-time copies of a database file.
+    ///
+    /// Snapshots are kept in `<database>.snapshots/`. Each is taken after a
+    /// checkpoint while the database is locked, so it is consistent.
+    /// Restoring snapshots the current state first, as `pre_restore`.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Snapshot before a risky load, keeping the newest 10
+    /// frozen-duckdb snapshot create --database warehouse.duckdb --label before_backfill --keep 10
+    ///
+    /// # List snapshots
+    /// frozen-duckdb snapshot list --database warehouse.duckdb
+    ///
+    /// # Go back
+    /// frozen-duckdb snapshot restore --database warehouse.duckdb before_backfill
+    ///
+    /// # Drop snapshots older than 30 days
+    /// frozen-duckdb snapshot prune --database warehouse.duckdb --max-age 30d
+    /// ```
+    Snapshot {
+        #[command(subcommand)]
+        action: SnapshotAction,
+    },
+
+    /// Trace how a derived file was produced.
+    ///
+    /// `convert`, `join`, `dedupe`, and `mask` record their inputs,
+    /// parameters, time, and git commit in the dataset catalog.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Show the chain of commands behind a file
+    /// frozen-duckdb lineage show customers_masked.parquet
+    /// ```
+    Lineage {
+        #[command(subcommand)]
+        action: LineageAction,
+    },
+
+    /// Store credentials for Postgres and MySQL databases.
+    ///
+    /// Secrets are DuckDB persistent secrets in `~/.frozen-duckdb/secrets`,
+    /// used by `--attach-postgres` and `--attach-mysql` as `secret:NAME`.
+    /// The password is read from FROZEN_DUCKDB_SECRET_PASSWORD or prompted
+    /// for, so it never appears in shell history.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Store the production database's credentials
+    /// frozen-duckdb secrets add --type postgres --name prod \
+    ///   --host db.internal --user analyst --database shop
+    ///
+    /// # List stored secrets
+    /// frozen-duckdb secrets list
+    ///
+    /// # Delete one
+    /// frozen-duckdb secrets rm prod
+    /// ```
+    Secrets {
+        #[command(subcommand)]
+        action: SecretsAction,
+    },
+
+    /// Generate text completions using LLM models via Flock.
+    ///
+    /// This command uses the configured LLM models to generate text completions
+    /// for the provided prompts. Supports both single prompts and batch processing.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Complete a single prompt
+    /// frozen-duckdb complete --prompt "Write a hello world function in Python"
+    ///
+    /// # Complete with specific model
+    /// frozen-duckdb complete --prompt "Explain recursion" --model coder
+    ///
+    /// # Batch completion from file
+    /// frozen-duckdb complete --input prompts.txt --output responses.txt
+    ///
+    /// # Describe an image with a multimodal model
+    /// frozen-duckdb complete --prompt "Describe this diagram" --image diagram.png --model vision
+    /// ```
+    Complete {
+        /// Text prompt for completion
+        ///
+        /// The text to complete using the LLM model.
+        #[arg(short, long)]
+        prompt: Option<String>,
+
+        /// Input file containing prompts (one per line)
+        ///
+        /// If provided, each line will be treated as a separate prompt.
+        /// Cannot be used with --prompt.
+        #[arg(short, long, conflicts_with = "prompt")]
+        input: Option<String>,
+
+        /// Output file for responses
+        ///
+        /// If provided, responses will be written to this file.
+        /// If not provided, responses will be printed to stdout.
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Model to use for completion
+        ///
+        /// Use a model alias configured during setup or added with `models add` (default: "text_generator")
+        /// This corresponds to the text generation model set in flock-setup.
+        #[arg(short, long, default_value = "text_generator")]
+        model: String,
+
+        /// Image to include as context (local file or http(s) URL, repeatable)
+        ///
+        /// Local images are base64-encoded; requires a multimodal model
+        /// such as llava or llama3.2-vision.
+        #[arg(long = "image", value_name = "PATH_OR_URL")]
+        images: Vec<String>,
+
+        /// Maximum tokens to generate
+        ///
+        /// Controls the length of the generated response.
+        /// Higher values allow for longer responses.
+        #[arg(long, default_value = "512")]
+        max_tokens: usize,
+
+        /// Temperature for text generation
+        ///
+        /// Controls randomness in generation:
+        /// - 0.0: Deterministic (same input always gives same output)
+        /// - 1.0: Balanced creativity and coherence
+        /// - Higher: More creative but potentially less coherent
+        #[arg(short, long, default_value = "0.7")]
+        temperature: f32,
+
+        /// Estimate token volume and run time without calling the model
+        ///
+        /// Reports input tokens, estimated output tokens, and estimated
+        /// wall-clock time based on the model's measured throughput.
+        #[arg(long)]
+        estimate: bool,
+
+        #[command(flatten)]
+        context: ContextArgs,
+
+        #[command(flatten)]
+        cache: CacheArgs,
+    },
+
+    /// Generate embeddings for text using LLM models via Flock.
+    ///
+    /// This command generates vector embeddings for the provided text,
+    /// which can be used for semantic search, similarity comparison,
+    /// and other vector-based operations.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Generate embedding for single text
+    /// frozen-duckdb embed --text "Python is a programming language"
+    ///
+    /// # Generate embeddings for multiple texts from file
+    /// frozen-duckdb embed --input texts.txt --output embeddings.json
+    /// ```
+    Embed {
+        /// Text to generate embeddings for
+        ///
+        /// The text content to convert into vector embeddings.
+        #[arg(short, long)]
+        text: Option<String>,
+
+        /// Input file containing texts (one per line)
+        ///
+        /// If provided, each line will be treated as separate text for embedding.
+        /// Cannot be used with --text.
+        #[arg(short, long, conflicts_with = "text")]
+        input: Option<String>,
+
+        /// Output file for embeddings
+        ///
+        /// If provided, embeddings will be written to this file in JSON format.
+        /// If not provided, embeddings will be printed to stdout.
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Model to use for embedding generation
+        ///
+        /// Use a model alias configured during setup or added with `models add` (default: "embedder")
+        /// This corresponds to the embedding model set in flock-setup.
+        #[arg(short, long, default_value = "embedder")]
+        model: String,
+
+        /// Normalize embeddings
+        ///
+        /// If set, embeddings will be normalized to unit length.
+        /// Useful for cosine similarity calculations.
+        #[arg(long)]
+        normalize: bool,
+    },
+
+    /// Build a persistent embedding index for a document corpus via Flock.
+    ///
+    /// Documents are embedded in batches and each batch is checkpointed in
+    /// the index database, so an interrupted run over a large corpus can be
+    /// continued with `--resume` instead of starting over.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Index a corpus (one document per line)
+    /// frozen-duckdb index --corpus documents.txt --index embeddings.duckdb
+    ///
+    /// # Continue an interrupted run with larger batches
+    /// frozen-duckdb index --corpus documents.txt --index embeddings.duckdb --batch-size 1024 --resume
+    ///
+    /// # Index a directory of long files as overlapping 256-token chunks
+    /// frozen-duckdb index --corpus docs/ --chunk-size 256 --chunker window --chunk-overlap 32
+    ///
+    /// # Index a documentation set of Markdown, HTML, and PDF files
+    /// # (built with --features loaders), one document per section or page
+    /// frozen-duckdb index --corpus docs/ --index docs.duckdb --chunk-size 256
+    ///
+    /// # Tag every document for filtering with search --filter
+    /// frozen-duckdb index --corpus faq/ --index docs.duckdb --resume --metadata source=faq
+    /// ```
+    Index {
+        /// Corpus file or directory
+        ///
+        /// Text file with one document per line, or a Markdown, HTML, or PDF
+        /// file split into sections, or a directory of such files (searched
+        /// recursively, one document per text file or section).
+        #[arg(short, long)]
+        corpus: String,
+
+        /// Index database path
+        ///
+        /// DuckDB database storing embeddings and indexing checkpoints.
+        #[arg(short, long, default_value = "embeddings.duckdb")]
+        index: String,
+
+        /// Model to use for embedding generation
+        ///
+        /// Use a model alias configured during setup or added with `models add` (default: "embedder")
+        #[arg(short, long, default_value = "embedder")]
+        model: String,
+
+        /// Number of documents embedded and checkpointed per batch
+        #[arg(short, long, default_value = "256")]
+        batch_size: usize,
+
+        /// Resume from the last checkpoint in an existing index
+        #[arg(long)]
+        resume: bool,
+
+        /// Normalize embeddings to unit length
+        #[arg(long)]
+        normalize: bool,
+
+        /// Metadata added to every document as KEY=VALUE (repeatable)
+        ///
+        /// Stored with the automatic metadata (file, format, modified, title,
+        /// heading, page) for search --filter.
+        #[arg(long = "metadata", value_name = "KEY=VALUE", value_parser = parse_metadata)]
+        metadata: Vec<(String, String)>,
+
+        #[command(flatten)]
+        chunking: ChunkArgs,
+    },
+
+    /// Perform semantic search using embeddings and Flock.
+    ///
+    /// This command performs semantic similarity search by comparing
+    /// query embeddings against a corpus of documents. Results are
+    /// ranked by semantic similarity rather than just keyword matching.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Search in a document corpus
+    /// frozen-duckdb search --query "machine learning algorithms" --corpus documents.txt
+    ///
+    /// # Search with specific similarity threshold
+    /// frozen-duckdb search --query "data science" --corpus docs/ --threshold 0.8
+    ///
+    /// # Search an index built with the index command
+    /// frozen-duckdb search --query "data science" --corpus embeddings.duckdb
+    ///
+    /// # Hybrid search: vector and BM25 keyword results fused by rank
+    /// frozen-duckdb search --query "parquet export" --corpus embeddings.duckdb --hybrid
+    ///
+    /// # Also search with 3 paraphrases of the question, fusing all results
+    /// frozen-duckdb search --query "why is my export slow" --corpus docs.duckdb --multi-query 3
+    ///
+    /// # Only documents from the manual modified this year
+    /// frozen-duckdb search --query "export" --corpus docs.duckdb \
+    ///     --filter source=manual --filter "modified>=2024-01-01"
+    /// ```
+    Search {
+        /// Search query text
+        ///
+        /// The text query to search for semantically similar content.
+        #[arg(short, long)]
+        query: String,
+
+        /// Corpus file or directory
+        ///
+        /// File containing documents (one per line), directory containing text files,
+        /// or a `.duckdb` embedding index built with the `index` command.
+        #[arg(short, long)]
+        corpus: String,
+
+        /// Model to use for embedding the query
+        ///
+        /// Must match the model an embedding index was built with.
+        #[arg(short, long, default_value = "embedder")]
+        model: String,
+
+        /// Similarity threshold
         ///
         /// Minimum similarity score (0.0 to 1.0) for results to be included.
         /// Higher values return more similar (but fewer) results.
@@ -387,9 +1613,52 @@ pub enum Commands {
 
         /// Maximum number of results
         ///
-        /// Maximum number of similar documents to return.
-        #[arg(short, long, default_value = "10")]
-        limit: usize,
+        /// Maximum number of similar documents to return.
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
+
+        /// Also search the index by keyword (BM25) and fuse both rankings
+        ///
+        /// Only for indexes built with the index command. Scores are then
+        /// fused scores rather than similarities.
+        #[arg(long)]
+        hybrid: bool,
+
+        /// Fusion method for --hybrid and --multi-query (rrf, combsum, combmnz, combmed, combanz)
+        #[arg(long, default_value = "rrf")]
+        fusion: String,
+
+        /// Also search with N paraphrases of the query and fuse all results
+        ///
+        /// The paraphrases come from the text model (--expansion-model). Only
+        /// for indexes built with the index command; measure the effect with
+        /// eval-retrieval.
+        #[arg(long, value_name = "N", default_value = "0")]
+        multi_query: usize,
+
+        /// Model alias writing the --multi-query paraphrases
+        #[arg(long, default_value = "text_generator")]
+        expansion_model: String,
+
+        /// Only search documents whose metadata matches (repeatable)
+        ///
+        /// KEY=VALUE, or KEY with !=, >, >=, <, or <= and a value; numbers
+        /// compare as numbers. Repeated = filters on one key match any of
+        /// the values, filters on different keys must all match. Only for
+        /// indexes built with the index command.
+        #[arg(long = "filter", value_name = "FILTER", value_parser = parse_filter)]
+        filters: Vec<MetadataFilter>,
+
+        /// Rerank the retrieved results with an LLM (llm_rerank)
+        ///
+        /// The top `--limit` results above the threshold are reordered by the
+        /// text model's judgement of relevance to the query.
+        #[arg(long)]
+        rerank: bool,
+
+        /// Model alias used for reranking
+        #[arg(long, default_value = "text_generator", requires = "rerank")]
+        rerank_model: String,
 
         /// Output format for results
         ///
@@ -400,6 +1669,369 @@ pub enum Commands {
         format: String,
     },
 
+    /// Answer a question from an embedding index with the text model.
+    ///
+    /// The most similar documents are retrieved and passed to the model as
+    /// context. With `--session`, the question and answer are kept as a turn
+    /// of a named conversation in `~/.frozen-duckdb/config.duckdb`, and
+    /// relevant earlier turns are included in the prompt, so follow-up
+    /// questions work.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # One-off question
+    /// frozen-duckdb rag --question "How do I export to Parquet?" --index docs.duckdb
+    ///
+    /// # A conversation: the second question builds on the first
+    /// frozen-duckdb rag -q "How do I export to Parquet?" -i docs.duckdb --session export
+    /// frozen-duckdb rag -q "Can it be compressed?" -i docs.duckdb --session export
+    /// ```
+    Rag {
+        /// Question to answer
+        #[arg(short, long)]
+        question: String,
+
+        /// Index database built with the index command
+        #[arg(short, long, default_value = "embeddings.duckdb")]
+        index: String,
+
+        /// Model to use for embedding the question
+        ///
+        /// Must match the model the index was built with.
+        #[arg(short, long, default_value = "embedder")]
+        model: String,
+
+        /// Model alias writing the answer
+        #[arg(long, default_value = "text_generator")]
+        answer_model: String,
+
+        /// Documents retrieved as context
+        #[arg(short, long, default_value = "5")]
+        limit: usize,
+
+        /// Minimum similarity of retrieved documents
+        #[arg(short, long, default_value = "0.5")]
+        threshold: f32,
+
+        /// Only retrieve documents whose metadata matches (repeatable)
+        ///
+        /// Same syntax as search --filter.
+        #[arg(long = "filter", value_name = "FILTER", value_parser = parse_filter)]
+        filters: Vec<MetadataFilter>,
+
+        /// Conversation to continue and record this turn in
+        ///
+        /// Created on first use; manage with the sessions command.
+        #[arg(short, long)]
+        session: Option<String>,
+
+        /// Earlier turns included in the prompt at most
+        #[arg(long, default_value = "4", requires = "session")]
+        history: usize,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// List, show, and clear rag conversation sessions.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// frozen-duckdb sessions list
+    /// frozen-duckdb sessions show export
+    /// frozen-duckdb sessions clear export
+    /// ```
+    Sessions {
+        #[command(subcommand)]
+        action: SessionAction,
+    },
+
+    /// Compute the most similar items for every item in an embedding index.
+    ///
+    /// Writes an item→neighbors table (`item_neighbors`) into the index and
+    /// optionally to a file, for "more like this" recommendations.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Exact top-10 neighbors of every indexed document
+    /// frozen-duckdb similar-items --index products.duckdb --output neighbors.parquet
+    ///
+    /// # Only items in the catalog, approximate search for large catalogs
+    /// frozen-duckdb similar-items --index products.duckdb --items catalog.csv --id-column sku --hnsw
+    /// ```
+    SimilarItems {
+        /// Index database built with the index command
+        #[arg(short, long)]
+        index: String,
+
+        /// Item table restricting items and neighbors (csv, parquet, or json)
+        #[arg(long)]
+        items: Option<String>,
+
+        /// Column of the item table holding document ids
+        #[arg(long, default_value = "id", requires = "items")]
+        id_column: String,
+
+        /// Neighbors per item
+        #[arg(short = 'n', long, default_value = "10")]
+        top_n: usize,
+
+        /// Drop neighbors with a lower cosine similarity
+        #[arg(long)]
+        min_similarity: Option<f32>,
+
+        /// Use an approximate HNSW index (vss extension) instead of exact search
+        #[arg(long)]
+        hnsw: bool,
+
+        /// Items compared per batch
+        #[arg(long, default_value = "1024")]
+        batch_size: usize,
+
+        /// Also write the neighbors to a file (.parquet, .csv, .json)
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Inspect an embedding index built with the index command.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # 2-D PCA projection as an interactive scatter plot
+    /// frozen-duckdb vss project --index embeddings.duckdb --output points.html
+    ///
+    /// # UMAP coordinates for further analysis (needs the umap feature)
+    /// frozen-duckdb vss project --index embeddings.duckdb --method umap --output points.parquet
+    ///
+    /// # Group documents into 20 clusters named by an LLM
+    /// frozen-duckdb vss cluster --index embeddings.duckdb --k 20 --label --output clusters.parquet
+    /// ```
+    Vss {
+        #[command(subcommand)]
+        action: VssAction,
+    },
+
+    /// Answer a question about a database by generating SQL via Flock.
+    ///
+    /// The model sees a schema summary built from `information_schema`; the
+    /// generated SQL is checked with `EXPLAIN`, shown for confirmation and
+    /// run against a read-only connection.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Review the SQL before it runs
+    /// frozen-duckdb ask --database sales.duckdb "total revenue by region last month"
+    ///
+    /// # Run without confirmation and print JSON
+    /// frozen-duckdb ask --database sales.duckdb --yes --format json "top 5 customers by orders"
+    /// ```
+    Ask {
+        /// Question in natural language
+        question: String,
+
+        /// DuckDB database file to query
+        #[arg(short, long)]
+        database: String,
+
+        /// Model alias used to generate the SQL
+        #[arg(short, long, default_value = "text_generator")]
+        model: String,
+
+        /// Run the generated SQL without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Output format (table, json, csv)
+        #[arg(short, long, default_value = "table")]
+        format: String,
+    },
+
+    /// Generate Markdown documentation for the tables of a database.
+    ///
+    /// Lists every table with its columns, types, row count and sample
+    /// values. The text model adds table summaries and column descriptions;
+    /// with `--no-llm`, or when Flock is unavailable, only the schema
+    /// skeleton is written.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Describe every table with the configured text model
+    /// frozen-duckdb document --database sales.duckdb --output docs.md
+    ///
+    /// # Schema skeleton only
+    /// frozen-duckdb document --database sales.duckdb --no-llm
+    /// ```
+    Document {
+        /// DuckDB database file to document
+        #[arg(short, long)]
+        database: String,
+
+        /// Markdown file to write (prints to stdout when omitted)
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Model alias used for descriptions
+        #[arg(short, long, default_value = "text_generator")]
+        model: String,
+
+        /// Skip the model and emit the schema skeleton
+        #[arg(long)]
+        no_llm: bool,
+
+        /// Distinct sample values shown per column
+        #[arg(long, default_value = "3")]
+        samples: usize,
+    },
+
+    /// Extract typed entities from a text column using an LLM via Flock.
+    ///
+    /// Each row is sent with the same structured prompt; responses are
+    /// validated against a JSON schema built from `--entities` and written
+    /// as typed columns. Rows whose response does not validate keep NULL
+    /// entities and the reason in `extraction_error`.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # People and products as lists, sentiment as a fixed choice
+    /// frozen-duckdb extract --input reviews.parquet --column text \
+    ///     --entities person,product,sentiment --output extracted.parquet
+    ///
+    /// # Explicit kinds: name:list|text|integer|number|boolean or name:a|b|c
+    /// frozen-duckdb extract --input tickets.csv --column body \
+    ///     --entities "order_id:text,refund:boolean,priority:low|medium|high" \
+    ///     --output tickets_out.csv
+    /// ```
+    Extract {
+        /// Input file (csv, parquet, or json)
+        #[arg(short, long)]
+        input: String,
+
+        /// Text column to extract from
+        #[arg(short, long, default_value = "text")]
+        column: String,
+
+        /// Comma-separated entities as name[:kind]
+        #[arg(short, long)]
+        entities: String,
+
+        /// Output file (.parquet, .csv, .json)
+        #[arg(short, long)]
+        output: String,
+
+        /// Model alias used for extraction
+        #[arg(short, long, default_value = "text_generator")]
+        model: String,
+
+        /// Rows sent to the model per Flock call
+        #[arg(long, default_value = "256")]
+        batch_size: usize,
+
+        #[command(flatten)]
+        context: ContextArgs,
+
+        #[command(flatten)]
+        cache: CacheArgs,
+    },
+
+    /// Translate a text column into another language using an LLM via Flock.
+    ///
+    /// Writes the input with a `<column>_<code>` translation column added.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Writes reviews_fr.parquet with a text_fr column
+    /// frozen-duckdb translate --input reviews.parquet --column text --to fr
+    /// ```
+    Translate {
+        /// Input file (csv, parquet, or json)
+        #[arg(short, long)]
+        input: String,
+
+        /// Text column to translate
+        #[arg(short, long, default_value = "text")]
+        column: String,
+
+        /// Target language (ISO 639-1 code or English name)
+        #[arg(long)]
+        to: String,
+
+        /// Output file (default: <input>_<code>.<ext>)
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Name of the translation column (default: <column>_<code>)
+        #[arg(long)]
+        output_column: Option<String>,
+
+        /// Model alias used for translation
+        #[arg(short, long, default_value = "text_generator")]
+        model: String,
+
+        /// Rows sent to the model per Flock call
+        #[arg(long, default_value = "256")]
+        batch_size: usize,
+
+        #[command(flatten)]
+        context: ContextArgs,
+
+        #[command(flatten)]
+        cache: CacheArgs,
+    },
+
+    /// Detect the language of a text column.
+    ///
+    /// Writes the input with `language` (ISO 639-1 code) and
+    /// `language_source` columns added. Uses the text model via Flock and
+    /// falls back to a built-in script and stopword heuristic for rows the
+    /// model cannot answer, or for every row with `--heuristic` or when
+    /// Flock is unavailable.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// frozen-duckdb detect-language --input corpus.csv --column body --output corpus_lang.csv
+    ///
+    /// # Deterministic, no model calls
+    /// frozen-duckdb detect-language --input corpus.csv --column body --heuristic
+    /// ```
+    DetectLanguage {
+        /// Input file (csv, parquet, or json)
+        #[arg(short, long)]
+        input: String,
+
+        /// Text column to inspect
+        #[arg(short, long, default_value = "text")]
+        column: String,
+
+        /// Output file (default: <input>_lang.<ext>)
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Use only the built-in heuristic
+        #[arg(long)]
+        heuristic: bool,
+
+        /// Model alias used for detection
+        #[arg(short, long, default_value = "text_generator")]
+        model: String,
+
+        /// Rows sent to the model per Flock call
+        #[arg(long, default_value = "256")]
+        batch_size: usize,
+
+        #[command(flatten)]
+        cache: CacheArgs,
+    },
+
     /// Filter data using LLM-based classification via Flock.
     ///
     /// This command uses LLM models to classify and filter data based
@@ -414,6 +2046,15 @@ pub enum Commands {
     ///
     /// # Filter with custom prompt
     /// frozen-duckdb filter --prompt "Does this text contain positive sentiment?" --input reviews.txt
+    ///
+    /// # Re-run against the model, ignoring cached responses
+    /// frozen-duckdb filter --criteria "Is this valid Python code?" --input code_samples.csv --no-cache
+    ///
+    /// # Continue an interrupted run over a large input
+    /// frozen-duckdb filter --criteria "Is this spam?" --input emails.txt --output spam.jsonl --resume
+    ///
+    /// # Write results as a Parquet table for analysis in DuckDB
+    /// frozen-duckdb filter --criteria "Is this spam?" --input emails.txt --output spam.parquet
     /// ```
     Filter {
         /// Filtering criteria or prompt
@@ -438,13 +2079,22 @@ pub enum Commands {
 
         /// Output file for filtered results
         ///
-        /// File where matching results will be written.
+        /// Results are streamed to `<output>.partial.jsonl` while the filter
+        /// runs, with progress tracked in `<output>.state` for `--resume`,
+        /// and written here as a table (line, item, matched, model,
+        /// latency_ms) once every line is done.
         #[arg(short, long)]
         output: Option<String>,
 
+        /// Output table format (jsonl, csv, parquet)
+        ///
+        /// Defaults to the output file extension, or jsonl if it names no format.
+        #[arg(long, requires = "output")]
+        output_format: Option<String>,
+
         /// Model to use for filtering
         ///
-        /// Use the model alias configured during setup (default: "text_generator")
+        /// Use a model alias configured during setup or added with `models add` (default: "text_generator")
         /// This corresponds to the text generation model set in flock-setup.
         #[arg(short, long, default_value = "text_generator")]
         model: String,
@@ -455,6 +2105,137 @@ pub enum Commands {
         /// If not set, all items will be included with match scores.
         #[arg(long)]
         positive_only: bool,
+
+        /// Continue an interrupted run, skipping lines already in the output
+        #[arg(long, requires = "output")]
+        resume: bool,
+
+        #[command(flatten)]
+        cache: CacheArgs,
+    },
+
+    /// Measure LLM filter accuracy against a labeled sample.
+    ///
+    /// This command classifies a labeled sample with the same prompt the
+    /// `filter` command uses and reports precision, recall, F1, and a
+    /// confusion matrix, so criteria can be checked before filtering a
+    /// large dataset. Several models can be compared side by side.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Check a criteria against human labels
+    /// frozen-duckdb eval --input labeled.csv --label-column truth --criteria "Is this valid Python code?"
+    ///
+    /// # Compare two models on the first 200 rows, as JSON
+    /// frozen-duckdb eval --input labeled.parquet --label-column truth --criteria "Is this spam?" --models coder,text_generator --limit 200 --format json
+    /// ```
+    Eval {
+        /// Labeled input file (csv, parquet, or json)
+        #[arg(short, long)]
+        input: String,
+
+        /// Column holding the expected classification (true/false, 1/0, yes/no)
+        #[arg(short, long)]
+        label_column: String,
+
+        /// Column holding the text to classify
+        #[arg(short, long, default_value = "text")]
+        text_column: String,
+
+        /// Filtering criteria, as passed to `filter --criteria`
+        #[arg(short, long)]
+        criteria: Option<String>,
+
+        /// Custom prompt, as passed to `filter --prompt`
+        #[arg(short, long, conflicts_with = "criteria")]
+        prompt: Option<String>,
+
+        /// Comma-separated model aliases to evaluate
+        #[arg(short, long, value_delimiter = ',', default_value = "text_generator")]
+        models: Vec<String>,
+
+        /// Evaluate only the first N rows
+        #[arg(long)]
+        limit: Option<usize>,
+
+        /// Output format for the report (human, json)
+        #[arg(short, long, default_value = "human")]
+        format: String,
+
+        #[command(flatten)]
+        cache: CacheArgs,
+    },
+
+    /// Measure search recall against labeled queries, with and without
+    /// multi-query retrieval.
+    ///
+    /// Each labeled query lists the ids of the documents it should find,
+    /// separated by `|` (ids as stored by the `index` command, e.g.
+    /// `guide.md#install`; chunks count as their document). The report gives
+    /// recall@k and hit rate for plain vector search and for
+    /// `--multi-query N` paraphrases fused together.
+    ///
+    /// # Examples
+    ///
+    /// ```bash
+    /// # Does searching with 3 paraphrases find more of the right documents?
+    /// frozen-duckdb eval-retrieval --input queries.csv --index docs.duckdb --multi-query 3
+    ///
+    /// # Top 5 only, fused by CombMNZ, as JSON
+    /// frozen-duckdb eval-retrieval --input queries.parquet --index docs.duckdb \
+    ///     --k 5 --fusion combmnz --format json
+    /// ```
+    EvalRetrieval {
+        /// Labeled queries file (csv, parquet, or json)
+        #[arg(short, long)]
+        input: String,
+
+        /// Index database built with the index command
+        #[arg(long, default_value = "embeddings.duckdb")]
+        index: String,
+
+        /// Column holding the query text
+        #[arg(short, long, default_value = "query")]
+        query_column: String,
+
+        /// Column holding the relevant document ids, separated by `|`
+        #[arg(short, long, default_value = "relevant")]
+        relevant_column: String,
+
+        /// Model to use for embedding the queries
+        ///
+        /// Must match the model the index was built with.
+        #[arg(short, long, default_value = "embedder")]
+        model: String,
+
+        /// Documents retrieved per query
+        #[arg(short, long, default_value = "10")]
+        k: usize,
+
+        /// Minimum similarity of retrieved documents
+        #[arg(short, long, default_value = "0.0")]
+        threshold: f32,
+
+        /// Paraphrases per query for the multi-query run (0 skips it)
+        #[arg(long, value_name = "N", default_value = "3")]
+        multi_query: usize,
+
+        /// Model alias writing the paraphrases
+        #[arg(long, default_value = "text_generator")]
+        expansion_model: String,
+
+        /// Fusion method merging the multi-query results (rrf, combsum, combmnz, combmed, combanz)
+        #[arg(long, default_value = "rrf")]
+        fusion: String,
+
+        /// Evaluate only the first N queries
+        #[arg(long)]
+        limit: Option<usize>,
+
+        /// Output format for the report (human, json)
+        #[arg(short, long, default_value = "human")]
+        format: String,
     },
 
     /// Generate summaries using LLM aggregation via Flock.
@@ -471,6 +2252,15 @@ pub enum Commands {
     ///
     /// # Summarize with custom aggregation strategy
     /// frozen-duckdb summarize --input articles.txt --strategy reduce --max-length 200
+    ///
+    /// # Summarize long documents in 1000-token sentence-aware chunks
+    /// frozen-duckdb summarize --input documents/ --strategy map --chunk-size 1000
+    ///
+    /// # Check token volume and expected run time first
+    /// frozen-duckdb summarize --input documents/ --strategy map --estimate
+    ///
+    /// # Write the summary with its metadata as a CSV row
+    /// frozen-duckdb summarize --input articles.txt --output summary.csv
     /// ```
     Summarize {
         /// Input file or directory containing text to summarize
@@ -485,6 +2275,14 @@ pub enum Commands {
         #[arg(short, long)]
         output: Option<String>,
 
+        /// Output table format (jsonl, csv, parquet)
+        ///
+        /// Writes a one-row table (input, strategy, model, input_count,
+        /// summary, latency_ms) instead of plain text. Defaults to the output
+        /// file extension; other extensions get the plain summary.
+        #[arg(long, requires = "output")]
+        output_format: Option<String>,
+
         /// Summarization strategy
         ///
         /// Available strategies:
@@ -502,10 +2300,26 @@ pub enum Commands {
 
         /// Model to use for summarization
         ///
-        /// Use the model alias configured during setup (default: "text_generator")
+        /// Use a model alias configured during setup or added with `models add` (default: "text_generator")
         /// This corresponds to the text generation model set in flock-setup.
         #[arg(short, long, default_value = "text_generator")]
         model: String,
+
+        /// Estimate token volume and run time without calling the model
+        ///
+        /// Reports input tokens, estimated output tokens, and estimated
+        /// wall-clock time based on the model's measured throughput.
+        #[arg(long)]
+        estimate: bool,
+
+        #[command(flatten)]
+        chunking: ChunkArgs,
+
+        #[command(flatten)]
+        context: ContextArgs,
+
+        #[command(flatten)]
+        cache: CacheArgs,
     },
 
     /// Validate FFI functionality including core DuckDB + Flock LLM extensions.
@@ -529,6 +2343,9 @@ pub enum Commands {
     ///
     /// # Output results in JSON format
     /// frozen-duckdb validate-ffi --format json
+    ///
+    /// # Also check the TPC-H results against the official answers
+    /// frozen-duckdb validate-ffi --skip-llm --verify --scale-factor 0.1
     /// ```
     ///
     /// # Validation Layers
@@ -545,6 +2362,8 @@ pub enum Commands {
     /// - **TPC-H Extension**: Test TPC-H benchmark extension loading
     /// - **TPC-H Data Generation**: Test data generation with different scale factors
     /// - **TPC-H Query Execution**: Test all 22 TPC-H benchmark queries
+    /// - **TPC-H Answer Verification**: Compare the 22 query results with
+    ///   `tpch_answers()` (with `--verify`)
     ValidateFfi {
         /// Skip LLM validation (faster, no Ollama required)
         ///
@@ -554,6 +2373,25 @@ pub enum Commands {
         #[arg(long)]
         skip_llm: bool,
 
+        /// Ollama server used by the LLM layers
+        ///
+        /// Defaults to $FROZEN_DUCKDB_OLLAMA_URL, then http://127.0.0.1:11434.
+        /// Point it at a mock server to run the LLM layers without models.
+        #[arg(long)]
+        ollama_url: Option<String>,
+
+        /// Check the TPC-H query results against the official answers
+        ///
+        /// Generates data at `--scale-factor` in a separate in-memory
+        /// database, runs all 22 queries, and reports every result that
+        /// differs from `tpch_answers()`.
+        #[arg(long)]
+        verify: bool,
+
+        /// Scale factor for `--verify` (answers exist for 0.01, 0.1, and 1)
+        #[arg(long, default_value = "0.01", requires = "verify")]
+        scale_factor: f64,
+
         /// Output format for results
         ///
         /// Choose the format for displaying validation results.
@@ -569,3 +2407,726 @@ pub enum Commands {
         verbose: bool,
     },
 }
+
+/// Actions of the `models` command.
+#[derive(Subcommand)]
+pub enum ModelsAction {
+    /// List configured model aliases
+    List {
+        /// Output format (human, json)
+        #[arg(short, long, default_value = "human")]
+        format: String,
+    },
+
+    /// Add or replace a model alias
+    Add {
+        /// Alias name, e.g. `fast`
+        name: String,
+
+        /// Provider model name, e.g. `llama3.2:1b`
+        model: String,
+
+        /// Flock provider serving the model
+        #[arg(long, default_value = "ollama")]
+        provider: String,
+
+        #[command(flatten)]
+        settings: ModelSettingsArgs,
+    },
+
+    /// Change an existing model alias, keeping options that aren't given
+    Update {
+        /// Alias name to update
+        name: String,
+
+        /// New provider model name
+        #[arg(long)]
+        model: Option<String>,
+
+        /// New Flock provider
+        #[arg(long)]
+        provider: Option<String>,
+
+        #[command(flatten)]
+        settings: ModelSettingsArgs,
+
+        /// Remove a model parameter set with --model-param (repeatable)
+        #[arg(long = "unset-param", value_name = "KEY")]
+        unset_params: Vec<String>,
+    },
+
+    /// Remove a model alias
+    Remove {
+        /// Alias name to remove
+        name: String,
+    },
+}
+
+/// Actions of the `views` command.
+#[derive(Subcommand)]
+pub enum ViewsAction {
+    /// Define or redefine a view and build it
+    Define {
+        /// View (table) name
+        name: String,
+
+        /// SELECT statement the view is built from
+        sql: String,
+    },
+
+    /// Rebuild one view, or every view in dependency order
+    Refresh {
+        /// View to rebuild; all views if omitted
+        name: Option<String>,
+    },
+
+    /// List view definitions
+    List {
+        /// Output format (human, json)
+        #[arg(short, long, default_value = "human")]
+        format: String,
+    },
+
+    /// Remove a view's definition and drop its table
+    Drop {
+        /// View to drop
+        name: String,
+    },
+}
+
+/// Actions of the `jobs` command.
+#[derive(Subcommand)]
+pub enum JobsAction {
+    /// Run jobs as they come due
+    Run {
+        /// Run the jobs that are due now, then exit
+        #[arg(long)]
+        once: bool,
+
+        /// Run only this job, immediately
+        #[arg(long, conflicts_with = "once")]
+        job: Option<String>,
+    },
+
+    /// List jobs with their schedule, last run, and next run
+    List,
+
+    /// Show recorded runs, newest first
+    History {
+        /// Only show runs of this job
+        #[arg(long)]
+        job: Option<String>,
+
+        /// Maximum number of runs to show
+        #[arg(long, default_value = "20")]
+        limit: usize,
+    },
+}
+
+/// Actions of the `reshape` command.
+#[derive(Subcommand)]
+pub enum ReshapeAction {
+    /// Turn distinct values of a column into columns (long to wide)
+    Pivot {
+        /// Input file path (csv, parquet, or json)
+        #[arg(short, long)]
+        input: String,
+
+        /// Output file; defaults to `<input>_pivoted.<ext>`
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Comma-separated columns whose values become columns
+        #[arg(long, value_delimiter = ',', required = true)]
+        on: Vec<String>,
+
+        /// Comma-separated columns to aggregate; rows are counted if omitted
+        #[arg(long, value_delimiter = ',')]
+        values: Vec<String>,
+
+        /// Comma-separated columns identifying an output row; all other
+        /// columns if omitted
+        #[arg(short, long, value_delimiter = ',')]
+        group_by: Vec<String>,
+
+        /// Aggregate function (sum, avg, min, max, count, first, list, ...)
+        #[arg(short, long, default_value = "sum")]
+        aggregate: String,
+    },
+
+    /// Turn columns into name/value rows (wide to long)
+    Unpivot {
+        /// Input file path (csv, parquet, or json)
+        #[arg(short, long)]
+        input: String,
+
+        /// Output file; defaults to `<input>_unpivoted.<ext>`
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Comma-separated columns to fold into rows
+        #[arg(short, long, value_delimiter = ',', required_unless_present = "keep")]
+        columns: Vec<String>,
+
+        /// Comma-separated columns to keep, folding all others
+        #[arg(short, long, value_delimiter = ',', conflicts_with = "columns")]
+        keep: Vec<String>,
+
+        /// Name of the column holding the folded column names
+        #[arg(long, default_value = "name")]
+        name: String,
+
+        /// Name of the column holding the folded values
+        #[arg(long, default_value = "value")]
+        value: String,
+
+        /// Keep rows whose value is NULL
+        #[arg(long)]
+        include_nulls: bool,
+    },
+}
+
+/// Actions of the `vss` command.
+#[derive(Subcommand)]
+pub enum VssAction {
+    /// Project embeddings to two dimensions for visual inspection
+    Project {
+        /// Index database built with the index command
+        #[arg(short, long)]
+        index: String,
+
+        /// Projection method: pca, or umap (with the umap feature)
+        #[arg(short, long, default_value = "pca")]
+        method: String,
+
+        /// Output file: .html for a scatter plot, or .parquet, .csv, .json
+        #[arg(short, long, default_value = "points.parquet")]
+        output: String,
+
+        /// Project only the first N documents (by id)
+        #[arg(short, long)]
+        limit: Option<usize>,
+    },
+
+    /// Group documents with k-means and store the clusters in the index
+    Cluster {
+        /// Index database built with the index command
+        #[arg(short, long)]
+        index: String,
+
+        /// Number of clusters
+        #[arg(short, long, default_value = "8")]
+        k: usize,
+
+        /// Also write assignments to a file (.parquet, .csv, .json)
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Label each cluster with an LLM summary of its members via Flock
+        #[arg(long)]
+        label: bool,
+
+        /// Model used for labels
+        #[arg(short, long, default_value = "text_generator", requires = "label")]
+        model: String,
+
+        /// Seed for centroid initialization
+        #[arg(long, default_value = "42")]
+        seed: u64,
+    },
+}
+
+/// Actions of the `cache` command.
+#[derive(Subcommand)]
+pub enum CacheAction {
+    /// Remove every cached query result
+    Invalidate,
+}
+
+/// Actions of the `catalog` command.
+#[derive(Subcommand)]
+pub enum CatalogAction {
+    /// List registered datasets
+    List {
+        /// Output format (human, json)
+        #[arg(short, long, default_value = "human")]
+        format: String,
+    },
+
+    /// Show a dataset's entry and check its file against it
+    Show {
+        /// Dataset name
+        name: String,
+    },
+
+    /// Remove a dataset from the catalog (its files are kept)
+    Rm {
+        /// Dataset name
+        name: String,
+    },
+}
+
+/// Actions of the `sessions` command.
+#[derive(Subcommand)]
+pub enum SessionAction {
+    /// List sessions, most recently used first
+    List {
+        /// Output format (human, json)
+        #[arg(short, long, default_value = "human")]
+        format: String,
+    },
+
+    /// Show the turns of a session
+    Show {
+        /// Session name
+        name: String,
+
+        /// Output format (human, json)
+        #[arg(short, long, default_value = "human")]
+        format: String,
+    },
+
+    /// Delete a session and its turns
+    Clear {
+        /// Session name
+        name: String,
+    },
+}
+
+/// Actions of the `lineage` command.
+#[derive(Subcommand)]
+pub enum LineageAction {
+    /// Show how a file was produced, back to its source files
+    Show {
+        /// File, or name of a cataloged dataset
+        file: String,
+
+        /// Output format (human, json)
+        #[arg(short, long, default_value = "human")]
+        format: String,
+    },
+}
+
+/// Actions of the `secrets` command.
+#[derive(Subcommand)]
+pub enum SecretsAction {
+    /// Store a secret, replacing one of the same name
+    Add {
+        /// Database type (postgres, mysql)
+        #[arg(long = "type", value_name = "TYPE")]
+        kind: String,
+
+        /// Secret name, used as `secret:NAME`
+        #[arg(long)]
+        name: String,
+
+        /// Server host name
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Server port
+        #[arg(long)]
+        port: Option<u16>,
+
+        /// User name
+        #[arg(long)]
+        user: Option<String>,
+
+        /// Database name
+        #[arg(long)]
+        database: Option<String>,
+    },
+
+    /// List stored secrets (names and types only)
+    List,
+
+    /// Delete a stored secret
+    Rm {
+        /// Secret name
+        name: String,
+    },
+}
+
+/// Actions of the `snapshot` command.
+#[derive(Subcommand)]
+pub enum SnapshotAction {
+    /// Take a snapshot
+    Create {
+        /// DuckDB database file
+        #[arg(short, long)]
+        database: String,
+
+        /// Label appended to the snapshot id, usable to restore it
+        #[arg(short, long)]
+        label: Option<String>,
+
+        /// How to store the snapshot: copy (the file) or export (Parquet
+        /// files, portable across DuckDB versions)
+        #[arg(short, long, default_value = "copy")]
+        method: String,
+
+        #[command(flatten)]
+        retention: RetentionArgs,
+    },
+
+    /// List snapshots, oldest first
+    List {
+        /// DuckDB database file
+        #[arg(short, long)]
+        database: String,
+
+        /// Output format (human, json)
+        #[arg(short, long, default_value = "human")]
+        format: String,
+    },
+
+    /// Replace the database with a snapshot
+    Restore {
+        /// DuckDB database file
+        #[arg(short, long)]
+        database: String,
+
+        /// Snapshot id, label, or unique id prefix
+        snapshot: String,
+    },
+
+    /// Delete snapshots outside the retention limits
+    Prune {
+        /// DuckDB database file
+        #[arg(short, long)]
+        database: String,
+
+        #[command(flatten)]
+        retention: RetentionArgs,
+    },
+}
+
+/// Actions of the `audit` command.
+#[derive(Subcommand)]
+pub enum AuditAction {
+    /// Start recording LLM interactions
+    Enable {
+        /// How much of each prompt to keep (hash, full)
+        #[arg(long, default_value = "hash")]
+        policy: String,
+
+        /// Append records to this JSONL file instead of ~/.frozen-duckdb/audit.duckdb
+        #[arg(long)]
+        jsonl: Option<String>,
+    },
+
+    /// Stop recording LLM interactions (existing records are kept)
+    Disable,
+
+    /// Show the current audit settings
+    Status,
+
+    /// Export audit records to a CSV, Parquet, or JSON file
+    Export {
+        /// Output file; the format is inferred from the extension
+        #[arg(short, long)]
+        output: String,
+
+        /// Only export records newer than this (e.g. 12h, 7d)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only export records of this command (e.g. complete, filter)
+        #[arg(long)]
+        command: Option<String>,
+
+        /// Only export records of this model alias
+        #[arg(short, long)]
+        model: Option<String>,
+    },
+}
+
+/// Parses a `NAME=MODEL` alias definition.
+fn parse_alias(value: &str) -> Result<ModelAlias, String> {
+    match value.split_once('=') {
+        Some((name, model)) if !name.is_empty() && !model.is_empty() => {
+            Ok(ModelAlias::ollama(name, model))
+        }
+        _ => Err(format!("expected NAME=MODEL, got '{}'", value)),
+    }
+}
+
+/// Parses a `KEY=VALUE` model parameter.
+fn parse_model_param(value: &str) -> Result<(String, serde_json::Value), String> {
+    ModelSettings::parse_parameter(value).map_err(|e| e.to_string())
+}
+
+/// Parses a `NAME=VALUE` script variable.
+fn parse_variable(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => Err(format!("expected NAME=VALUE, got '{}'", value)),
+    }
+}
+
+/// Parses a `KEY=VALUE` document metadata entry.
+fn parse_metadata(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(format!("expected KEY=VALUE, got '{}'", value)),
+    }
+}
+
+/// Parses a metadata filter such as `source=manual` or `page<10`.
+fn parse_filter(value: &str) -> Result<MetadataFilter, String> {
+    MetadataFilter::parse(value).map_err(|e| e.to_string())
+}
+
+/// Parses an `ALIAS=PATH` database attachment.
+fn parse_attachment(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((alias, path)) if !alias.is_empty() && !path.is_empty() => {
+            Ok((alias.to_string(), path.to_string()))
+        }
+        _ => Err(format!("expected ALIAS=PATH, got '{}'", value)),
+    }
+}
+
+/// Remote databases attached by the commands that run SQL.
+///
+/// Each value is `CONNECTION AS ALIAS`; see [`crate::cli::federation`].
+#[derive(Args, Debug, Clone, Default)]
+pub struct FederationArgs {
+    /// Attach a Postgres database read-only (repeatable)
+    ///
+    /// CONNECTION is a libpq connection string without a password, or
+    /// `secret:NAME` for credentials stored with `secrets add`.
+    /// Example: --attach-postgres "dbname=shop host=db.internal AS pg"
+    #[arg(long, value_name = "CONNECTION AS ALIAS", value_parser = parse_postgres)]
+    pub attach_postgres: Vec<RemoteAttachment>,
+
+    /// Attach a MySQL database read-only (repeatable)
+    ///
+    /// Example: --attach-mysql "secret:shop AS shop"
+    #[arg(long, value_name = "CONNECTION AS ALIAS", value_parser = parse_mysql)]
+    pub attach_mysql: Vec<RemoteAttachment>,
+}
+
+impl FederationArgs {
+    /// All remote databases to attach, Postgres first.
+    pub fn remotes(&self) -> impl Iterator<Item = &RemoteAttachment> {
+        self.attach_postgres.iter().chain(&self.attach_mysql)
+    }
+}
+
+fn parse_postgres(value: &str) -> Result<RemoteAttachment, String> {
+    RemoteAttachment::parse(RemoteKind::Postgres, value).map_err(|e| e.to_string())
+}
+
+fn parse_mysql(value: &str) -> Result<RemoteAttachment, String> {
+    RemoteAttachment::parse(RemoteKind::Mysql, value).map_err(|e| e.to_string())
+}
+
+/// Text chunking options shared by the LLM commands.
+///
+/// Chunking is disabled unless `--chunk-size` is given. Sizes are in
+/// approximate tokens (see [`crate::text::chunk`]).
+#[derive(Args, Debug, Clone)]
+pub struct ChunkArgs {
+    /// Split documents into chunks of at most this many tokens
+    #[arg(long)]
+    pub chunk_size: Option<usize>,
+
+    /// Chunking strategy
+    ///
+    /// Available strategies:
+    /// - `sentence`: Pack whole sentences up to the chunk size
+    /// - `fixed`: Consecutive fixed-size chunks
+    /// - `window`: Fixed-size chunks overlapping by `--chunk-overlap`
+    #[arg(long, default_value = "sentence")]
+    pub chunker: String,
+
+    /// Overlap in tokens between consecutive chunks (window strategy only)
+    #[arg(long, default_value = "0")]
+    pub chunk_overlap: usize,
+}
+
+impl ChunkArgs {
+    /// Returns the configured chunker, or `None` if chunking is disabled.
+    pub fn chunker(&self) -> anyhow::Result<Option<Chunker>> {
+        self.chunk_size
+            .map(|size| Chunker::parse(&self.chunker, size, self.chunk_overlap, "tokens"))
+            .transpose()
+    }
+}
+
+/// Model options shared by `flock-setup` and `models add`/`update`.
+///
+/// Options that aren't given keep their defaults (or, for `models update`,
+/// their current values).
+#[derive(Args, Debug, Clone)]
+pub struct ModelSettingsArgs {
+    /// Rows sent to the model per request (default: 32)
+    #[arg(long)]
+    pub batch_size: Option<usize>,
+
+    /// Sampling temperature (default: 0.7)
+    #[arg(long)]
+    pub temperature: Option<f64>,
+
+    /// Context window in tokens (Ollama's num_ctx)
+    #[arg(long)]
+    pub num_ctx: Option<usize>,
+
+    /// Provider parameter as KEY=VALUE (repeatable)
+    ///
+    /// Values are parsed as JSON when possible.
+    /// Example: --model-param top_p=0.9 --model-param num_gpu=99
+    #[arg(long = "model-param", value_name = "KEY=VALUE", value_parser = parse_model_param)]
+    pub model_params: Vec<(String, serde_json::Value)>,
+}
+
+impl ModelSettingsArgs {
+    /// Returns the given options as model settings.
+    pub fn settings(&self) -> anyhow::Result<ModelSettings> {
+        if self.batch_size == Some(0) {
+            anyhow::bail!("--batch-size must be at least 1");
+        }
+        if self.num_ctx == Some(0) {
+            anyhow::bail!("--num-ctx must be at least 1");
+        }
+        Ok(ModelSettings {
+            batch_size: self.batch_size,
+            temperature: self.temperature,
+            num_ctx: self.num_ctx,
+            parameters: self.model_params.iter().cloned().collect(),
+        })
+    }
+}
+
+/// Context window options shared by the LLM commands.
+///
+/// Budgeting is disabled unless `--context-window` is given. Token counts
+/// are estimates (see [`crate::text::context`]).
+#[derive(Args, Debug, Clone)]
+pub struct ContextArgs {
+    /// Model context window in tokens; larger inputs are reduced before the call
+    #[arg(long)]
+    pub context_window: Option<usize>,
+
+    /// How to reduce context that exceeds the window
+    ///
+    /// Available strategies:
+    /// - `truncate-tail`: Keep the beginning of the context
+    /// - `truncate-head`: Keep the end of the context
+    /// - `drop-lowest`: Drop the last (lowest-ranked) documents
+    /// - `summarize`: Summarize oversized documents, then drop if still over
+    #[arg(long, default_value = "truncate-tail", requires = "context_window")]
+    pub context_strategy: String,
+
+    /// Tokens of the window kept free for the model's response
+    #[arg(long, default_value = "512", requires = "context_window")]
+    pub reserve_output: usize,
+}
+
+impl ContextArgs {
+    /// Returns the configured budget, or `None` if budgeting is disabled.
+    pub fn budget(&self) -> anyhow::Result<Option<ContextBudget>> {
+        self.context_window
+            .map(|max_tokens| {
+                Ok(ContextBudget {
+                    max_tokens,
+                    reserved_output: self.reserve_output,
+                    strategy: ContextStrategy::parse(&self.context_strategy)?,
+                })
+            })
+            .transpose()
+    }
+}
+
+/// DuckDB tuning options shared by the commands that convert and load data.
+///
+/// See [`PerformanceOptions`] for what each setting does.
+#[derive(Args, Debug, Clone, Default)]
+pub struct PerformanceArgs {
+    /// DuckDB worker threads (default: one per CPU core)
+    #[arg(long, value_name = "N")]
+    pub threads: Option<usize>,
+
+    /// Keep rows in input order; `--preserve-order=false` lets DuckDB write
+    /// large files in parallel with less memory
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub preserve_order: Option<bool>,
+
+    /// Rows per row group in written Parquet files (DuckDB default: 122880)
+    #[arg(long, value_name = "ROWS")]
+    pub row_group_size: Option<usize>,
+}
+
+impl PerformanceArgs {
+    /// Returns the options to apply to the command's connection.
+    pub fn options(&self) -> PerformanceOptions {
+        PerformanceOptions {
+            threads: self.threads,
+            preserve_insertion_order: self.preserve_order,
+            row_group_size: self.row_group_size,
+        }
+    }
+}
+
+/// Snapshot retention limits; without either, every snapshot is kept.
+#[derive(Args, Debug, Clone, Default)]
+pub struct RetentionArgs {
+    /// Keep only the newest N snapshots
+    #[arg(long, value_name = "N")]
+    pub keep: Option<usize>,
+
+    /// Delete snapshots older than this (e.g. 12h, 30d)
+    #[arg(long, value_name = "AGE")]
+    pub max_age: Option<String>,
+}
+
+impl RetentionArgs {
+    /// Returns the retention limits.
+    pub fn retention(&self) -> anyhow::Result<Retention> {
+        Ok(Retention {
+            keep_last: self.keep,
+            max_age: self.max_age.as_deref().map(parse_ttl).transpose()?,
+        })
+    }
+}
+
+/// Lineage options shared by the commands that derive one file from
+/// others.
+#[derive(Args, Debug, Clone, Default)]
+pub struct LineageArgs {
+    /// Also write how the output was produced into its Parquet key-value
+    /// metadata (rewrites the file once)
+    #[arg(long)]
+    pub lineage_metadata: bool,
+}
+
+/// LLM response cache options shared by the LLM commands.
+///
+/// Responses are cached in `~/.frozen-duckdb/config.duckdb`, keyed on the
+/// model, prompt, and parameters.
+#[derive(Args, Debug, Clone)]
+pub struct CacheArgs {
+    /// Always call the model, bypassing the response cache
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Maximum age of cached responses (e.g. 90s, 30m, 12h, 7d)
+    #[arg(long, default_value = "7d")]
+    pub cache_ttl: String,
+}
+
+impl CacheArgs {
+    /// Opens the configured response cache, or `None` if caching is disabled.
+    pub fn open(&self) -> anyhow::Result<Option<ResponseCache>> {
+        if self.no_cache {
+            return Ok(None);
+        }
+        let ttl = parse_ttl(&self.cache_ttl)?;
+        let cache = ResponseCache::open_default()?.with_ttl(Some(ttl));
+        cache.purge_expired()?;
+        Ok(Some(cache))
+    }
+}
@@ -0,0 +1,626 @@
+//! # Persistent CLI Configuration for Frozen DuckDB
+//!
+//! This module reads user settings from `~/.frozen-duckdb/config.json`.
+//! The file is optional and every setting has a default, so the CLI works
+//! without it. Command-line flags override values from the file.
+//!
+//! ## Example
+//!
+//! ```json
+//! {
+//!   "flock": {
+//!     "ollama_url": "http://localhost:11434",
+//!     "requests_per_second": 5.0,
+//!     "burst": 10,
+//!     "max_in_flight": 4
+//!   },
+//!   "models": {
+//!     "fast": { "model": "llama3.2:1b", "provider": "ollama", "temperature": 0.2 },
+//!     "accurate": {
+//!       "model": "llama3.1:70b",
+//!       "provider": "ollama",
+//!       "batch_size": 8,
+//!       "num_ctx": 16384,
+//!       "parameters": { "top_p": 0.9 }
+//!     }
+//!   },
+//!   "audit": {
+//!     "enabled": true,
+//!     "policy": "hash",
+//!     "format": "jsonl",
+//!     "path": "/var/log/frozen-duckdb/audit.jsonl"
+//!   },
+//!   "embedding": {
+//!     "backend": "http",
+//!     "url": "https://api.openai.com/v1",
+//!     "api_key_env": "OPENAI_API_KEY"
+//!   },
+//!   "guardrails": {
+//!     "injection": "block",
+//!     "pii": "redact"
+//!   },
+//!   "serve": {
+//!     "roles": { "analyst": { "statements": ["select"], "schemas": ["reporting"] } },
+//!     "api_keys": [{ "role": "analyst", "key_env": "ANALYST_API_KEY" }]
+//!   }
+//! }
+//! ```
+//!
+//! Model aliases are registered with Flock whenever an LLM command runs,
+//! so any alias can be passed to `--model`. Each alias carries its own
+//! [`ModelSettings`]; unset values fall back to a batch size of
+//! [`DEFAULT_MODEL_BATCH_SIZE`] and a temperature of [`DEFAULT_TEMPERATURE`].
+
+use super::access::AccessControl;
+use super::audit_log::{AuditConfig, AuditPolicy, AuditSink};
+use super::embedding_backends::EmbeddingConfig;
+use super::guardrails::GuardrailConfig;
+use super::rate_limit::RateLimitConfig;
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CONFIG_DIR: &str = ".frozen-duckdb";
+const CONFIG_FILE: &str = "config.json";
+const DEFAULT_PROVIDER: &str = "ollama";
+
+/// Rows Flock sends to a model per request unless the alias overrides it.
+pub const DEFAULT_MODEL_BATCH_SIZE: usize = 32;
+
+/// Sampling temperature used unless the alias overrides it.
+pub const DEFAULT_TEMPERATURE: f64 = 0.7;
+
+/// User configuration loaded from `~/.frozen-duckdb/config.json`.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::config::CliConfig;
+///
+/// let config = CliConfig::load()?;
+/// let limits = config.rate_limits()?;
+/// println!("max in flight: {}", limits.max_in_flight);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CliConfig {
+    /// Parsed top-level JSON object
+    values: Map<String, Value>,
+}
+
+impl CliConfig {
+    /// Returns the default config file path.
+    pub fn default_path() -> Result<PathBuf> {
+        let home = env::var("HOME").context("HOME environment variable not set")?;
+        Ok(Path::new(&home).join(CONFIG_DIR).join(CONFIG_FILE))
+    }
+
+    /// Loads the default config file, or an empty config if it doesn't exist.
+    pub fn load() -> Result<Self> {
+        Self::load_from(Self::default_path()?)
+    }
+
+    /// Loads a config file, or an empty config if it doesn't exist.
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+        let values = match serde_json::from_str(&content)
+            .with_context(|| format!("Invalid JSON in config file: {}", path.display()))?
+        {
+            Value::Object(values) => values,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Config file must contain a JSON object: {}",
+                    path.display()
+                ))
+            }
+        };
+        Ok(Self { values })
+    }
+
+    /// Returns a top-level section, if present.
+    pub fn section(&self, name: &str) -> Option<&Map<String, Value>> {
+        self.values.get(name).and_then(Value::as_object)
+    }
+
+    /// Writes the config to the default config file.
+    pub fn save(&self) -> Result<()> {
+        self.save_to(Self::default_path()?)
+    }
+
+    /// Writes the config to a file, creating its directory if needed.
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&self.values)?)
+            .with_context(|| format!("Failed to write config file: {}", path.display()))
+    }
+
+    /// Returns the Ollama URL saved by `flock-setup`, if any.
+    pub fn ollama_url(&self) -> Option<&str> {
+        self.section("flock")?.get("ollama_url")?.as_str()
+    }
+
+    /// Saves the Ollama URL in the `flock` section.
+    pub fn set_ollama_url(&mut self, url: &str) {
+        self.section_mut("flock")
+            .insert("ollama_url".to_string(), Value::from(url));
+    }
+
+    /// Returns all model aliases from the `models` section, sorted by name.
+    pub fn models(&self) -> Result<Vec<ModelAlias>> {
+        let Some(models) = self.section("models") else {
+            return Ok(Vec::new());
+        };
+
+        let mut aliases = models
+            .iter()
+            .map(|(name, value)| ModelAlias::from_value(name, value))
+            .collect::<Result<Vec<_>>>()?;
+        aliases.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(aliases)
+    }
+
+    /// Returns the model alias named `name`, if configured.
+    pub fn model(&self, name: &str) -> Result<Option<ModelAlias>> {
+        self.section("models")
+            .and_then(|models| models.get(name))
+            .map(|value| ModelAlias::from_value(name, value))
+            .transpose()
+    }
+
+    /// Adds a model alias, replacing any existing alias with the same name.
+    pub fn add_model(&mut self, alias: ModelAlias) {
+        let mut entry = Map::new();
+        entry.insert("model".to_string(), Value::from(alias.model));
+        entry.insert("provider".to_string(), Value::from(alias.provider));
+        let settings = alias.settings;
+        if let Some(batch_size) = settings.batch_size {
+            entry.insert("batch_size".to_string(), Value::from(batch_size));
+        }
+        if let Some(temperature) = settings.temperature {
+            entry.insert("temperature".to_string(), Value::from(temperature));
+        }
+        if let Some(num_ctx) = settings.num_ctx {
+            entry.insert("num_ctx".to_string(), Value::from(num_ctx));
+        }
+        if !settings.parameters.is_empty() {
+            let parameters = settings.parameters.into_iter().collect::<Map<_, _>>();
+            entry.insert("parameters".to_string(), Value::Object(parameters));
+        }
+        self.section_mut("models")
+            .insert(alias.name, Value::Object(entry));
+    }
+
+    /// Removes a model alias, returning whether it existed.
+    pub fn remove_model(&mut self, name: &str) -> bool {
+        self.section_mut("models").remove(name).is_some()
+    }
+
+    /// Returns the stored audit settings, whether or not auditing is enabled.
+    pub fn audit_config(&self) -> Result<Option<AuditConfig>> {
+        let Some(audit) = self.section("audit") else {
+            return Ok(None);
+        };
+        let field = |key: &str| audit.get(key).and_then(Value::as_str);
+
+        let policy = AuditPolicy::parse(field("policy").unwrap_or("hash"))?;
+        let path = field("path").map(PathBuf::from);
+        let sink =
+            match field("format").unwrap_or("duckdb") {
+                "duckdb" => match path {
+                    Some(path) => AuditSink::Database(path),
+                    None => AuditSink::default_database()?,
+                },
+                "jsonl" => AuditSink::Jsonl(path.ok_or_else(|| {
+                    anyhow::anyhow!("audit.path is required for the jsonl format")
+                })?),
+                other => {
+                    return Err(anyhow::anyhow!(
+                        "Unknown audit format: {} (use duckdb or jsonl)",
+                        other
+                    ))
+                }
+            };
+        Ok(Some(AuditConfig { policy, sink }))
+    }
+
+    /// Returns the audit settings if auditing is enabled.
+    pub fn audit(&self) -> Result<Option<AuditConfig>> {
+        let enabled = self
+            .section("audit")
+            .and_then(|audit| audit.get("enabled"))
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        if enabled {
+            self.audit_config()
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Saves audit settings in the `audit` section.
+    pub fn set_audit(&mut self, audit: &AuditConfig, enabled: bool) {
+        let format = match audit.sink {
+            AuditSink::Database(_) => "duckdb",
+            AuditSink::Jsonl(_) => "jsonl",
+        };
+        self.values.insert(
+            "audit".to_string(),
+            serde_json::json!({
+                "enabled": enabled,
+                "policy": audit.policy.as_str(),
+                "format": format,
+                "path": audit.sink.path().to_string_lossy(),
+            }),
+        );
+    }
+
+    /// Returns a top-level section for writing, creating it if needed.
+    fn section_mut(&mut self, name: &str) -> &mut Map<String, Value> {
+        let value = self
+            .values
+            .entry(name)
+            .or_insert_with(|| Value::Object(Map::new()));
+        if !value.is_object() {
+            *value = Value::Object(Map::new());
+        }
+        value.as_object_mut().expect("section is an object")
+    }
+
+    /// Returns the embedding backend settings from the `embedding` section.
+    pub fn embedding(&self) -> Result<EmbeddingConfig> {
+        match self.section("embedding") {
+            Some(embedding) => EmbeddingConfig::from_section(embedding),
+            None => Ok(EmbeddingConfig::default()),
+        }
+    }
+
+    /// Returns the guardrail settings from the `guardrails` section, all
+    /// checks off without one.
+    pub fn guardrails(&self) -> Result<GuardrailConfig> {
+        match self.section("guardrails") {
+            Some(guardrails) => GuardrailConfig::from_section(guardrails),
+            None => Ok(GuardrailConfig::default()),
+        }
+    }
+
+    /// Returns the API keys and roles of `serve` from the `serve` section,
+    /// or `None` if it lists no keys.
+    pub fn access_control(&self) -> Result<Option<AccessControl>> {
+        match self.section("serve") {
+            Some(serve) => AccessControl::from_section(serve),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the Flock rate limits from the `flock` section.
+    pub fn rate_limits(&self) -> Result<RateLimitConfig> {
+        let mut limits = RateLimitConfig::default();
+        let Some(flock) = self.section("flock") else {
+            return Ok(limits);
+        };
+
+        if let Some(value) = flock.get("requests_per_second") {
+            limits.requests_per_second =
+                Some(value.as_f64().ok_or_else(|| {
+                    anyhow::anyhow!("flock.requests_per_second must be a number")
+                })?);
+        }
+        if let Some(value) = flock.get("burst") {
+            limits.burst = as_count(value, "flock.burst")?;
+        }
+        if let Some(value) = flock.get("max_in_flight") {
+            limits.max_in_flight = as_count(value, "flock.max_in_flight")?;
+        }
+        limits.validate()?;
+        Ok(limits)
+    }
+}
+
+/// A named model that can be passed to `--model`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelAlias {
+    /// Alias used on the command line, e.g. `fast`
+    pub name: String,
+    /// Provider model name, e.g. `llama3.2:1b`
+    pub model: String,
+    /// Flock provider serving the model, e.g. `ollama`
+    pub provider: String,
+    /// Batching and generation options sent to Flock
+    pub settings: ModelSettings,
+}
+
+impl ModelAlias {
+    /// Creates an alias served by Ollama.
+    pub fn ollama(name: &str, model: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            model: model.to_string(),
+            provider: DEFAULT_PROVIDER.to_string(),
+            settings: ModelSettings::default(),
+        }
+    }
+
+    fn from_value(name: &str, value: &Value) -> Result<Self> {
+        let field = |key: &str| value.get(key).and_then(Value::as_str);
+        let model = field("model")
+            .ok_or_else(|| anyhow::anyhow!("models.{}.model must be a string", name))?;
+
+        let mut settings = ModelSettings::default();
+        if let Some(batch_size) = value.get("batch_size") {
+            let key = format!("models.{}.batch_size", name);
+            settings.batch_size = Some(as_count(batch_size, &key)?);
+        }
+        if let Some(temperature) = value.get("temperature") {
+            settings.temperature =
+                Some(temperature.as_f64().ok_or_else(|| {
+                    anyhow::anyhow!("models.{}.temperature must be a number", name)
+                })?);
+        }
+        if let Some(num_ctx) = value.get("num_ctx") {
+            let key = format!("models.{}.num_ctx", name);
+            settings.num_ctx = Some(as_count(num_ctx, &key)?);
+        }
+        if let Some(parameters) = value.get("parameters") {
+            let parameters = parameters
+                .as_object()
+                .ok_or_else(|| anyhow::anyhow!("models.{}.parameters must be an object", name))?;
+            settings.parameters = parameters
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            model: model.to_string(),
+            provider: field("provider").unwrap_or(DEFAULT_PROVIDER).to_string(),
+            settings,
+        })
+    }
+}
+
+/// Per-model options applied when an alias is registered with Flock.
+///
+/// Unset values use the defaults, so only overrides are stored in the
+/// config file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ModelSettings {
+    /// Rows sent to the model per request
+    pub batch_size: Option<usize>,
+    /// Sampling temperature
+    pub temperature: Option<f64>,
+    /// Context window in tokens (Ollama's `num_ctx`)
+    pub num_ctx: Option<usize>,
+    /// Any other provider parameters, e.g. `top_p` or `num_gpu`
+    pub parameters: BTreeMap<String, Value>,
+}
+
+impl ModelSettings {
+    /// Rows sent to the model per request.
+    pub fn batch_size(&self) -> usize {
+        self.batch_size.unwrap_or(DEFAULT_MODEL_BATCH_SIZE)
+    }
+
+    /// Provider parameters sent as Flock's `model_parameters`.
+    ///
+    /// `temperature` and `num_ctx` take precedence over entries of the same
+    /// name in [`parameters`](Self::parameters).
+    pub fn model_parameters(&self) -> BTreeMap<String, Value> {
+        let mut parameters = self.parameters.clone();
+        parameters.insert(
+            "temperature".to_string(),
+            Value::from(self.temperature.unwrap_or(DEFAULT_TEMPERATURE)),
+        );
+        if let Some(num_ctx) = self.num_ctx {
+            parameters.insert("num_ctx".to_string(), Value::from(num_ctx));
+        }
+        parameters
+    }
+
+    /// Applies the values set in `changes`, keeping the others.
+    pub fn update(&mut self, changes: ModelSettings) {
+        self.batch_size = changes.batch_size.or(self.batch_size);
+        self.temperature = changes.temperature.or(self.temperature);
+        self.num_ctx = changes.num_ctx.or(self.num_ctx);
+        self.parameters.extend(changes.parameters);
+    }
+
+    /// Parses a `KEY=VALUE` model parameter.
+    ///
+    /// Values are read as JSON when possible (`0.9`, `true`, `[1, 2]`) and
+    /// as plain strings otherwise.
+    pub fn parse_parameter(value: &str) -> Result<(String, Value)> {
+        match value.split_once('=') {
+            Some((key, raw)) if !key.is_empty() && !raw.is_empty() => {
+                let parsed =
+                    serde_json::from_str(raw).unwrap_or_else(|_| Value::from(raw.to_string()));
+                Ok((key.to_string(), parsed))
+            }
+            _ => Err(anyhow::anyhow!("expected KEY=VALUE, got '{}'", value)),
+        }
+    }
+}
+
+fn as_count(value: &Value, key: &str) -> Result<usize> {
+    value
+        .as_u64()
+        .filter(|n| *n > 0)
+        .map(|n| n as usize)
+        .ok_or_else(|| anyhow::anyhow!("{} must be a positive integer", key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::embedding_backends::EmbeddingBackend;
+
+    #[test]
+    fn test_missing_file_uses_defaults() {
+        let temp = tempfile::tempdir().unwrap();
+        let config = CliConfig::load_from(temp.path().join("config.json")).unwrap();
+        assert_eq!(config.rate_limits().unwrap(), RateLimitConfig::default());
+    }
+
+    #[test]
+    fn test_rate_limits_from_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("config.json");
+        fs::write(
+            &path,
+            r#"{"flock": {"requests_per_second": 2.5, "burst": 5, "max_in_flight": 3}}"#,
+        )
+        .unwrap();
+
+        let limits = CliConfig::load_from(&path).unwrap().rate_limits().unwrap();
+        assert_eq!(limits.requests_per_second, Some(2.5));
+        assert_eq!(limits.burst, 5);
+        assert_eq!(limits.max_in_flight, 3);
+    }
+
+    #[test]
+    fn test_invalid_values_are_rejected() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("config.json");
+        fs::write(&path, r#"{"flock": {"max_in_flight": 0}}"#).unwrap();
+        assert!(CliConfig::load_from(&path).unwrap().rate_limits().is_err());
+
+        fs::write(&path, "[1, 2]").unwrap();
+        assert!(CliConfig::load_from(&path).is_err());
+    }
+
+    #[test]
+    fn test_model_aliases_round_trip() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("nested").join("config.json");
+
+        let mut config = CliConfig::load_from(&path).unwrap();
+        config.set_ollama_url("http://gpu-box:11434");
+        config.add_model(ModelAlias::ollama("fast", "llama3.2:1b"));
+        config.add_model(ModelAlias::ollama("accurate", "llama3.1:70b"));
+        config.add_model(ModelAlias::ollama("fast", "qwen2.5:0.5b"));
+        config.save_to(&path).unwrap();
+
+        let mut config = CliConfig::load_from(&path).unwrap();
+        assert_eq!(config.ollama_url(), Some("http://gpu-box:11434"));
+        assert_eq!(
+            config.models().unwrap(),
+            vec![
+                ModelAlias::ollama("accurate", "llama3.1:70b"),
+                ModelAlias::ollama("fast", "qwen2.5:0.5b"),
+            ]
+        );
+
+        assert!(config.remove_model("fast"));
+        assert!(!config.remove_model("fast"));
+        assert_eq!(config.models().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_model_settings_round_trip() {
+        let mut alias = ModelAlias::ollama("accurate", "llama3.1:70b");
+        alias.settings = ModelSettings {
+            batch_size: Some(8),
+            temperature: Some(0.2),
+            num_ctx: Some(16384),
+            parameters: BTreeMap::from([("top_p".to_string(), Value::from(0.9))]),
+        };
+
+        let mut config = CliConfig::default();
+        config.add_model(alias.clone());
+        config.add_model(ModelAlias::ollama("fast", "llama3.2:1b"));
+        assert_eq!(config.model("accurate").unwrap(), Some(alias));
+        assert_eq!(
+            config.model("fast").unwrap().unwrap().settings,
+            ModelSettings::default()
+        );
+        assert_eq!(config.model("missing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_model_settings_defaults_and_updates() {
+        let mut settings = ModelSettings::default();
+        assert_eq!(settings.batch_size(), DEFAULT_MODEL_BATCH_SIZE);
+        assert_eq!(
+            settings.model_parameters(),
+            BTreeMap::from([("temperature".to_string(), Value::from(DEFAULT_TEMPERATURE))])
+        );
+
+        settings.update(ModelSettings {
+            num_ctx: Some(4096),
+            parameters: BTreeMap::from([ModelSettings::parse_parameter("num_gpu=99").unwrap()]),
+            ..Default::default()
+        });
+        settings.update(ModelSettings {
+            batch_size: Some(4),
+            parameters: BTreeMap::from([ModelSettings::parse_parameter("stop=END").unwrap()]),
+            ..Default::default()
+        });
+        assert_eq!(settings.batch_size(), 4);
+        assert_eq!(settings.num_ctx, Some(4096));
+        assert_eq!(settings.parameters["num_gpu"], Value::from(99));
+        assert_eq!(settings.parameters["stop"], Value::from("END"));
+        assert_eq!(settings.model_parameters()["num_ctx"], Value::from(4096));
+
+        assert!(ModelSettings::parse_parameter("top_p").is_err());
+        assert!(ModelSettings::parse_parameter("=1").is_err());
+    }
+
+    #[test]
+    fn test_invalid_model_settings_are_rejected() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("config.json");
+        fs::write(
+            &path,
+            r#"{"models": {"fast": {"model": "llama3.2:1b", "batch_size": 0}}}"#,
+        )
+        .unwrap();
+        assert!(CliConfig::load_from(&path).unwrap().models().is_err());
+    }
+
+    #[test]
+    fn test_audit_settings() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut config = CliConfig::default();
+        assert_eq!(config.audit().unwrap(), None);
+
+        let audit = AuditConfig {
+            policy: AuditPolicy::Full,
+            sink: AuditSink::Jsonl(temp.path().join("audit.jsonl")),
+        };
+        config.set_audit(&audit, false);
+        assert_eq!(config.audit().unwrap(), None);
+        assert_eq!(config.audit_config().unwrap(), Some(audit.clone()));
+        config.set_audit(&audit, true);
+        assert_eq!(config.audit().unwrap(), Some(audit));
+    }
+
+    #[test]
+    fn test_embedding_settings() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("config.json");
+        fs::write(
+            &path,
+            r#"{"embedding": {"backend": "onnx", "cache_dir": "/tmp/models"}}"#,
+        )
+        .unwrap();
+
+        let embedding = CliConfig::load_from(&path).unwrap().embedding().unwrap();
+        assert_eq!(embedding.backend, EmbeddingBackend::Onnx);
+        assert_eq!(embedding.cache_dir, Some(PathBuf::from("/tmp/models")));
+        assert_eq!(
+            CliConfig::default().embedding().unwrap(),
+            EmbeddingConfig::default()
+        );
+    }
+}
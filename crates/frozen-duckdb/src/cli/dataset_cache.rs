@@ -0,0 +1,347 @@
+//! # Dataset Cache for Frozen DuckDB CLI
+//!
+//! This module caches generated datasets under `datasets/` in the
+//! [cache directory](super::cache_dir) so repeated `download` runs don't
+//! regenerate identical data. Entries are
+//! content-addressed by the inputs that determine their contents: dataset
+//! name, output format, scale factor, and compression.
+//!
+//! ## Cache Layout
+//!
+//! ```text
+//! ~/.frozen-duckdb/cache/datasets/
+//! ├── tpch-parquet-sf0.01-zstd-3f2a9c1e5b7d8a04/
+//! │   ├── .complete
+//! │   ├── customer.parquet
+//! │   └── ...
+//! └── chinook-csv-1c0b5e6f2d3a4978/
+//!     ├── .complete
+//!     └── chinook.csv
+//! ```
+//!
+//! An entry is only used once its `.complete` marker exists, so interrupted
+//! generations are regenerated rather than served half-written.
+
+use super::cache_dir::cache_path;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+const DATASETS_DIR: &str = "datasets";
+const COMPLETE_MARKER: &str = ".complete";
+
+/// Identifies a cached dataset by the inputs that determine its contents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatasetKey {
+    /// Dataset name (e.g. "chinook", "tpch")
+    pub dataset: String,
+    /// Output format (e.g. "csv", "parquet", "duckdb")
+    pub format: String,
+    /// Scale factor for generated datasets, `None` for fixed datasets
+    pub scale_factor: Option<f64>,
+    /// Compression codec for formats that support it (e.g. Parquet)
+    pub compression: Option<String>,
+    /// Rows per Parquet row group, `None` for DuckDB's default
+    pub row_group_size: Option<usize>,
+}
+
+impl DatasetKey {
+    /// Creates a new cache key.
+    pub fn new(dataset: &str, format: &str, scale_factor: Option<f64>) -> Self {
+        Self {
+            dataset: dataset.to_string(),
+            format: format.to_string(),
+            scale_factor,
+            compression: None,
+            row_group_size: None,
+        }
+    }
+
+    /// Sets the compression codec, which becomes part of the key.
+    pub fn with_compression(mut self, compression: &str) -> Self {
+        self.compression = Some(compression.to_string());
+        self
+    }
+
+    /// Sets the Parquet row group size, which becomes part of the key.
+    pub fn with_row_group_size(mut self, rows: usize) -> Self {
+        self.row_group_size = Some(rows);
+        self
+    }
+
+    /// Returns the stable directory name for this key.
+    ///
+    /// The name is human-readable and ends with a 64-bit FNV-1a digest of
+    /// the key, which stays stable across Rust versions and platforms.
+    pub fn entry_name(&self) -> String {
+        let mut canonical = format!("{}\0{}", self.dataset, self.format);
+        let mut name = format!("{}-{}", self.dataset, self.format);
+        if let Some(sf) = self.scale_factor {
+            canonical.push_str(&format!("\0sf={}", sf));
+            name.push_str(&format!("-sf{}", sf));
+        }
+        if let Some(compression) = &self.compression {
+            canonical.push_str(&format!("\0compression={}", compression));
+            name.push_str(&format!("-{}", compression));
+        }
+        if let Some(rows) = self.row_group_size {
+            canonical.push_str(&format!("\0row_group_size={}", rows));
+            name.push_str(&format!("-rg{}", rows));
+        }
+
+        format!("{}-{:016x}", name, fnv1a_64(canonical.as_bytes()))
+    }
+}
+
+/// Local cache of generated datasets.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::dataset_cache::{DatasetCache, DatasetKey};
+///
+/// let cache = DatasetCache::new()?;
+/// let key = DatasetKey::new("tpch", "parquet", Some(0.01));
+///
+/// if let Some(entry) = cache.lookup(&key) {
+///     cache.materialize(&entry, "datasets")?;
+/// }
+/// ```
+pub struct DatasetCache {
+    root: PathBuf,
+}
+
+impl DatasetCache {
+    /// Opens the default cache, `datasets/` in the cache directory.
+    pub fn new() -> Result<Self> {
+        Self::with_root(cache_path(DATASETS_DIR)?)
+    }
+
+    /// Opens a cache rooted at a custom directory.
+    pub fn with_root<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root)
+            .with_context(|| format!("Failed to create dataset cache: {}", root.display()))?;
+        Ok(Self { root })
+    }
+
+    /// Returns the cache root directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the directory of a complete cache entry, if one exists.
+    pub fn lookup(&self, key: &DatasetKey) -> Option<PathBuf> {
+        let entry = self.root.join(key.entry_name());
+        if entry.join(COMPLETE_MARKER).exists() {
+            debug!("Dataset cache hit: {}", entry.display());
+            Some(entry)
+        } else {
+            debug!("Dataset cache miss: {}", entry.display());
+            None
+        }
+    }
+
+    /// Generates a cache entry by running `generate` on an empty staging directory.
+    ///
+    /// Any existing entry for `key` is replaced. The staging directory is
+    /// renamed into place only after `generate` succeeds, so failures never
+    /// leave a usable partial entry behind.
+    pub fn store<F>(&self, key: &DatasetKey, generate: F) -> Result<PathBuf>
+    where
+        F: FnOnce(&Path) -> Result<()>,
+    {
+        let entry = self.root.join(key.entry_name());
+        let staging = self
+            .root
+            .join(format!(".{}.tmp-{}", key.entry_name(), std::process::id()));
+
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        fs::create_dir_all(&staging)?;
+
+        if let Err(e) = generate(&staging) {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+        fs::write(staging.join(COMPLETE_MARKER), "")?;
+
+        if entry.exists() {
+            fs::remove_dir_all(&entry).with_context(|| {
+                format!("Failed to remove stale cache entry: {}", entry.display())
+            })?;
+        }
+        fs::rename(&staging, &entry)
+            .with_context(|| format!("Failed to commit cache entry: {}", entry.display()))?;
+
+        info!("📦 Cached dataset at {}", entry.display());
+        Ok(entry)
+    }
+
+    /// Copies every file of a cache entry into `output_dir`.
+    ///
+    /// Files are copied rather than linked, so tools that rewrite the
+    /// output in place never change the cache entry. Existing files with
+    /// the same names in `output_dir` are replaced.
+    pub fn materialize<P: AsRef<Path>>(&self, entry: &Path, output_dir: P) -> Result<()> {
+        let output_dir = output_dir.as_ref();
+        fs::create_dir_all(output_dir)?;
+
+        for file in fs::read_dir(entry)? {
+            let source = file?.path();
+            let Some(name) = source.file_name() else {
+                continue;
+            };
+            if name == COMPLETE_MARKER {
+                continue;
+            }
+
+            let target = output_dir.join(name);
+            if target.symlink_metadata().is_ok() {
+                if target.is_dir() {
+                    fs::remove_dir_all(&target)?;
+                } else {
+                    fs::remove_file(&target)?;
+                }
+            }
+            copy_path(&source, &target)?;
+        }
+
+        info!(
+            "✅ Materialized cached dataset into {}",
+            output_dir.display()
+        );
+        Ok(())
+    }
+}
+
+/// Copies `source` to `target`, recursing into directories.
+fn copy_path(source: &Path, target: &Path) -> Result<()> {
+    if source.is_dir() {
+        fs::create_dir_all(target)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &target.join(entry.file_name()))?;
+        }
+    } else {
+        fs::copy(source, target).with_context(|| {
+            format!(
+                "Failed to copy {} to {}",
+                source.display(),
+                target.display()
+            )
+        })?;
+    }
+    Ok(())
+}
+
+/// 64-bit FNV-1a hash, used for stable cache entry names.
+pub(crate) fn fnv1a_64(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_name_is_stable_and_distinct() {
+        let key = DatasetKey::new("tpch", "parquet", Some(0.01));
+        assert_eq!(key.entry_name(), key.clone().entry_name());
+        assert!(key.entry_name().starts_with("tpch-parquet-sf0.01-"));
+
+        let other_sf = DatasetKey::new("tpch", "parquet", Some(0.1));
+        let other_format = DatasetKey::new("tpch", "csv", Some(0.01));
+        let other_compression = key.clone().with_compression("zstd");
+        assert_ne!(key.entry_name(), other_sf.entry_name());
+        assert_ne!(key.entry_name(), other_format.entry_name());
+        assert_ne!(key.entry_name(), other_compression.entry_name());
+        assert!(other_compression
+            .entry_name()
+            .starts_with("tpch-parquet-sf0.01-zstd-"));
+
+        let other_row_groups = other_compression.clone().with_row_group_size(100_000);
+        assert_ne!(
+            other_compression.entry_name(),
+            other_row_groups.entry_name()
+        );
+        assert!(other_row_groups
+            .entry_name()
+            .starts_with("tpch-parquet-sf0.01-zstd-rg100000-"));
+    }
+
+    #[test]
+    fn test_fnv1a_known_value() {
+        assert_eq!(fnv1a_64(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a_64(b"a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn test_store_lookup_and_materialize() {
+        let temp = tempfile::tempdir().unwrap();
+        let cache = DatasetCache::with_root(temp.path().join("cache")).unwrap();
+        let key = DatasetKey::new("chinook", "csv", None);
+
+        assert!(cache.lookup(&key).is_none());
+
+        let entry = cache
+            .store(&key, |dir| {
+                fs::write(dir.join("chinook.csv"), "ArtistId,Name\n1,AC/DC\n")?;
+                fs::write(dir.join("chinook.duckdb"), "database")?;
+                fs::write(dir.join("tracks.parquet"), "PAR1 tracks")?;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(cache.lookup(&key), Some(entry.clone()));
+
+        let output = temp.path().join("output");
+        cache.materialize(&entry, &output).unwrap();
+        let content = fs::read_to_string(output.join("chinook.csv")).unwrap();
+        assert!(content.contains("AC/DC"));
+        assert!(!output.join(COMPLETE_MARKER).exists());
+
+        // Writing to the output leaves the cache entry intact
+        let database = output.join("chinook.duckdb");
+        assert!(!database
+            .symlink_metadata()
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        fs::write(&database, "modified").unwrap();
+        assert_eq!(
+            fs::read_to_string(entry.join("chinook.duckdb")).unwrap(),
+            "database"
+        );
+
+        // Parquet files too, even when rewritten in place
+        let tracks = output.join("tracks.parquet");
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&tracks)
+            .and_then(|mut file| std::io::Write::write_all(&mut file, b"PAR1 rewritten"))
+            .unwrap();
+        assert_eq!(fs::read_to_string(&tracks).unwrap(), "PAR1 rewritten");
+        assert_eq!(
+            fs::read_to_string(entry.join("tracks.parquet")).unwrap(),
+            "PAR1 tracks"
+        );
+    }
+
+    #[test]
+    fn test_failed_store_leaves_no_entry() {
+        let temp = tempfile::tempdir().unwrap();
+        let cache = DatasetCache::with_root(temp.path()).unwrap();
+        let key = DatasetKey::new("tpch", "csv", Some(0.01));
+
+        let result = cache.store(&key, |_| Err(anyhow::anyhow!("generation failed")));
+        assert!(result.is_err());
+        assert!(cache.lookup(&key).is_none());
+    }
+}
@@ -5,11 +5,31 @@
 //! It maintains an in-memory DuckDB connection for efficient data
 //! processing operations.
 
+use super::catalog::{schema_hash, validate_name, CatalogEntry, DatasetCatalog, CATALOG_SCHEMA};
+use super::dataset_cache::{DatasetCache, DatasetKey};
+use super::dedupe::quote_identifier;
+use super::federation::{self, RemoteAttachment};
+use super::ingest::{ingest, IncrementalOptions, IngestReport};
+use super::lakehouse::{is_remote, parse_table_url, table_format, TableFormat, TABLE_FORMATS};
+use super::lineage::absolute;
+use super::merge::{
+    merge_into, merge_into_file, qualified_name, MergeOptions, MergeReport, WhenMatched,
+    WhenNotMatched,
+};
+use super::metrics::{CacheKind, Metrics};
+use super::parquet_parts::{PartManifest, SplitBy};
+use super::query_cache::{string_literals, QueryCache};
+use super::sql_path::path_literal;
+use super::telemetry::{record_bytes_written, record_rows};
+use crate::capabilities::Capabilities;
+use crate::error::FrozenDuckdbError;
 use anyhow::{Context, Result};
+use duckdb::types::Value;
 use duckdb::Connection;
 use std::fs;
-use std::path::Path;
-use tracing::{info, warn};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tracing::{debug, field, info, instrument, warn};
 
 /// Dataset management utility for frozen DuckDB operations.
 ///
@@ -37,7 +57,7 @@ use tracing::{info, warn};
 /// manager.download_chinook("datasets", "csv")?;
 ///
 /// // Generate TPC-H dataset
-/// manager.download_tpch("data", "parquet")?;
+/// manager.download_tpch("data", "parquet", 0.01)?;
 /// ```
 ///
 /// # Performance Characteristics
@@ -46,9 +66,275 @@ use tracing::{info, warn};
 /// - **Startup time**: <100ms for connection and extension loading
 /// - **Dataset generation**: <10s for small datasets, <60s for large ones
 /// - **Format conversion**: <1s for typical files
+///
+/// # Threads
+///
+/// A manager is `Send` but not `Sync`, like the [`Connection`] it owns: it
+/// can be moved to a worker thread, but each thread needs its own manager.
+/// Managers opened on the same file with [`DatasetManager::open`], or
+/// built from [`Connection::try_clone`] with
+/// [`DatasetManager::with_connection`], share its data.
 pub struct DatasetManager {
     /// In-memory DuckDB connection for data operations
     conn: Connection,
+    /// Compression codec used when writing Parquet files
+    parquet_compression: String,
+    /// Rows per row group when writing Parquet files
+    row_group_size: Option<usize>,
+}
+
+/// Tables produced by the TPC-H generator, largest first so the slowest
+/// export starts earliest when tables are written concurrently.
+const TPCH_TABLES: [&str; 8] = [
+    "lineitem", "orders", "partsupp", "part", "customer", "supplier", "nation", "region",
+];
+
+/// Parquet compression codecs accepted by [`DatasetManager::set_parquet_compression`].
+pub const PARQUET_COMPRESSIONS: [&str; 4] = ["snappy", "zstd", "gzip", "uncompressed"];
+
+/// File formats accepted by [`DatasetManager::convert_dataset`] and
+/// [`DatasetManager::describe_dataset`].
+pub const CONVERT_FORMATS: [&str; 6] = ["csv", "parquet", "xlsx", "jsonl", "geojson", "geoparquet"];
+
+/// Formats with a geometry column, which need the spatial extension and
+/// only convert to each other.
+pub const SPATIAL_FORMATS: [&str; 2] = ["geojson", "geoparquet"];
+
+/// Options for [`DatasetManager::convert_dataset_with`].
+#[derive(Debug, Clone, Default)]
+pub struct ConvertOptions {
+    /// Excel worksheet read (xlsx input) or written (xlsx output); the
+    /// first worksheet is read and `Sheet1` is written by default
+    pub sheet: Option<String>,
+    /// Geometry column of GeoParquet files, `geometry` by default; GeoJSON
+    /// geometries are written to this column
+    pub geometry_column: Option<String>,
+    /// Sniff CSV input with [`DatasetManager::sniff_csv`] and read it with
+    /// the detected dialect, header, and column types, instead of assuming
+    /// a comma-separated (tab-separated for `.tsv`) file with a header row
+    pub auto: bool,
+    /// Write Parquet output as a directory of numbered parts with a
+    /// `manifest.json`, instead of one file (see [`parquet_parts`](super::parquet_parts))
+    pub split: Option<SplitBy>,
+}
+
+/// DuckDB settings for converting and loading large files, applied with
+/// [`DatasetManager::set_performance`].
+///
+/// `None` leaves DuckDB's default: one thread per CPU core, rows kept in
+/// input order, and 122,880 rows per Parquet row group. Turning off
+/// `preserve_insertion_order` lets DuckDB write the output from all
+/// threads at once without buffering rows to restore their order, which
+/// is usually the biggest win for multi-gigabyte files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PerformanceOptions {
+    /// Worker threads (`SET threads`)
+    pub threads: Option<usize>,
+    /// Keep rows in input order (`SET preserve_insertion_order`)
+    pub preserve_insertion_order: Option<bool>,
+    /// Rows per row group in written Parquet files (`ROW_GROUP_SIZE`)
+    pub row_group_size: Option<usize>,
+}
+
+impl PerformanceOptions {
+    /// Applies the thread and insertion order settings to `conn`; the row
+    /// group size is a `COPY` option, see [`PerformanceOptions::parquet_options`].
+    pub fn apply(&self, conn: &Connection) -> Result<()> {
+        if self.threads == Some(0) || self.row_group_size == Some(0) {
+            anyhow::bail!("--threads and --row-group-size must be at least 1");
+        }
+        if let Some(threads) = self.threads {
+            conn.execute_batch(&format!("SET threads = {}", threads))
+                .with_context(|| format!("Failed to use {} threads", threads))?;
+        }
+        if let Some(preserve) = self.preserve_insertion_order {
+            conn.execute_batch(&format!("SET preserve_insertion_order = {}", preserve))?;
+        }
+        Ok(())
+    }
+
+    /// `COPY` options for a Parquet file with `compression` and the row
+    /// group size, e.g. `FORMAT PARQUET, COMPRESSION zstd, ROW_GROUP_SIZE 100000`.
+    pub fn parquet_options(&self, compression: &str) -> String {
+        let mut options = format!("FORMAT PARQUET, COMPRESSION {}", compression);
+        if let Some(rows) = self.row_group_size {
+            options.push_str(&format!(", ROW_GROUP_SIZE {}", rows));
+        }
+        options
+    }
+}
+
+/// Rows of a CSV file included in [`CsvSchema::sample`].
+pub const CSV_SAMPLE_ROWS: usize = 5;
+
+/// A column detected by [`DatasetManager::sniff_csv`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvColumn {
+    /// Column name from the header, or `column0`, `column1`, ... without one
+    pub name: String,
+    /// Detected DuckDB type, e.g. `BIGINT` or `DATE`
+    pub data_type: String,
+}
+
+/// The dialect and schema of a CSV file, as detected by DuckDB's sniffer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvSchema {
+    /// Field delimiter, e.g. `,`, `;`, or `\t`
+    pub delimiter: String,
+    /// Quote character, empty if fields are unquoted
+    pub quote: String,
+    /// Escape character inside quoted fields
+    pub escape: String,
+    /// Whether the first row is a header
+    pub has_header: bool,
+    /// Rows skipped before the header or data
+    pub skip_rows: u64,
+    /// Detected `DATE` format, if any
+    pub date_format: Option<String>,
+    /// Detected `TIMESTAMP` format, if any
+    pub timestamp_format: Option<String>,
+    /// Detected columns, in file order
+    pub columns: Vec<CsvColumn>,
+    /// The first [`CSV_SAMPLE_ROWS`] rows, read with the detected settings
+    pub sample: QueryOutput,
+}
+
+impl CsvSchema {
+    /// Returns a `read_csv` call for `path` that uses exactly the detected
+    /// settings, with auto-detection disabled.
+    ///
+    /// Quoted empty fields are read as empty strings, so only unquoted
+    /// empty fields are `NULL`, matching how DuckDB writes CSV.
+    pub fn read_csv(&self, path: &str) -> String {
+        let literal = |value: &str| format!("'{}'", value.replace('\'', "''"));
+        let mut call = format!(
+            "read_csv({}, auto_detect = false, allow_quoted_nulls = false, delim = {}, quote = {}, escape = {}, header = {}, skip = {}",
+            path_literal(path),
+            literal(&self.delimiter),
+            literal(&self.quote),
+            literal(&self.escape),
+            self.has_header,
+            self.skip_rows
+        );
+        if let Some(format) = &self.date_format {
+            call.push_str(&format!(", dateformat = {}", literal(format)));
+        }
+        if let Some(format) = &self.timestamp_format {
+            call.push_str(&format!(", timestampformat = {}", literal(format)));
+        }
+        let columns: Vec<String> = self
+            .columns
+            .iter()
+            .map(|c| format!("{}: {}", literal(&c.name), literal(&c.data_type)))
+            .collect();
+        call.push_str(&format!(", columns = {{{}}})", columns.join(", ")));
+        call
+    }
+}
+
+/// Guesses a [`CONVERT_FORMATS`] entry from a file extension. GeoParquet
+/// can't be told apart from Parquet and is reported as `parquet`.
+pub fn detect_format(path: &str) -> Option<&'static str> {
+    let lower = path.to_lowercase();
+    let format = if [".csv", ".tsv", ".csv.gz"].iter().any(|ext| lower.ends_with(ext)) {
+        "csv"
+    } else if lower.ends_with(".parquet") {
+        "parquet"
+    } else if lower.ends_with(".xlsx") {
+        "xlsx"
+    } else if jsonl_compression(&lower).is_ok() {
+        "jsonl"
+    } else if lower.ends_with(".geojson") {
+        "geojson"
+    } else {
+        return None;
+    };
+    Some(format)
+}
+
+/// Returns the DuckDB `COMPRESSION` for a JSON Lines file from its
+/// extension: `.jsonl`, `.jsonl.gz`, or `.jsonl.zst`.
+pub fn jsonl_compression(path: &str) -> Result<&'static str> {
+    let lower = path.to_lowercase();
+    if lower.ends_with(".jsonl") {
+        Ok("uncompressed")
+    } else if lower.ends_with(".jsonl.gz") {
+        Ok("gzip")
+    } else if lower.ends_with(".jsonl.zst") {
+        Ok("zstd")
+    } else {
+        Err(anyhow::anyhow!(
+            "Expected a .jsonl, .jsonl.gz, or .jsonl.zst file, got: {}",
+            path
+        ))
+    }
+}
+
+/// Splits a SQL script into statements at top-level semicolons.
+///
+/// Semicolons inside quoted strings, quoted identifiers, and comments
+/// don't split. Empty and comment-only statements are dropped.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::dataset_manager::split_statements;
+///
+/// let statements = split_statements("INSERT INTO t VALUES ('a;b'); -- done;\nSELECT 1;");
+/// assert_eq!(statements, ["INSERT INTO t VALUES ('a;b')", "-- done;\nSELECT 1"]);
+/// ```
+pub fn split_statements(sql: &str) -> Vec<&str> {
+    let bytes = sql.as_bytes();
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut has_code = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'\'' | b'"') => {
+                has_code = true;
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += 1;
+                }
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i < bytes.len() && !(bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/')) {
+                    i += 1;
+                }
+                i += 1;
+            }
+            b';' => {
+                if has_code {
+                    statements.push(sql[start..i].trim());
+                }
+                start = i + 1;
+                has_code = false;
+            }
+            c if !c.is_ascii_whitespace() => has_code = true,
+            _ => {}
+        }
+        i += 1;
+    }
+    if has_code {
+        statements.push(sql[start..].trim());
+    }
+    statements
+}
+
+/// Outcome of [`DatasetManager::execute_in_transactions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchReport {
+    /// Statements executed and committed
+    pub statements: usize,
+    /// Explicit transactions committed (0 in autocommit mode)
+    pub transactions: usize,
 }
 
 impl DatasetManager {
@@ -94,11 +380,541 @@ impl DatasetManager {
     /// - **Total initialization**: <100ms
     pub fn new() -> Result<Self> {
         let conn = Connection::open_in_memory().context("Failed to create DuckDB connection")?;
+        Self::with_connection(conn)
+    }
 
+    /// Creates a new DatasetManager backed by a DuckDB database file.
+    ///
+    /// The file is created if it doesn't exist. The same extensions as
+    /// [`DatasetManager::new`] are installed.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the DuckDB database file
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::DatasetManager;
+    ///
+    /// let manager = DatasetManager::open("datasets/tpch.duckdb")?;
+    /// ```
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open DuckDB database: {}", path))?;
+        Self::with_connection(conn)
+    }
+
+    /// Creates a new DatasetManager around an existing connection, such as
+    /// one opened read-only, installing the same extensions as
+    /// [`DatasetManager::new`].
+    pub fn with_connection(conn: Connection) -> Result<Self> {
         // Install extensions (skip arrow if not available on this platform)
         conn.execute_batch("INSTALL parquet; LOAD parquet; INSTALL tpch; LOAD tpch;")?;
 
-        Ok(Self { conn })
+        Ok(Self {
+            conn,
+            parquet_compression: "snappy".to_string(),
+            row_group_size: None,
+        })
+    }
+
+    /// Returns a manager with the same settings on a new connection to the
+    /// same database.
+    ///
+    /// The new connection starts outside any transaction, with default
+    /// settings and schema, and can't see this one's temporary tables.
+    pub fn new_session(&self) -> Result<Self> {
+        Ok(Self {
+            conn: self.conn.try_clone().context("Failed to open a new connection")?,
+            parquet_compression: self.parquet_compression.clone(),
+            row_group_size: self.row_group_size,
+        })
+    }
+
+    /// Sets the compression codec used for Parquet output.
+    ///
+    /// Applies to dataset downloads and conversions. Defaults to `snappy`,
+    /// DuckDB's default; `zstd` typically produces 20-40% smaller files.
+    ///
+    /// # Arguments
+    ///
+    /// * `compression` - One of `snappy`, `zstd`, `gzip`, `uncompressed`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::DatasetManager;
+    ///
+    /// let mut manager = DatasetManager::new()?;
+    /// manager.set_parquet_compression("zstd")?;
+    /// manager.download_tpch("data", "parquet", 0.01)?;
+    /// ```
+    pub fn set_parquet_compression(&mut self, compression: &str) -> Result<()> {
+        let compression = compression.to_lowercase();
+        if !PARQUET_COMPRESSIONS.contains(&compression.as_str()) {
+            return Err(anyhow::anyhow!(
+                "Unsupported Parquet compression: {} (available: {})",
+                compression,
+                PARQUET_COMPRESSIONS.join(", ")
+            ));
+        }
+        self.parquet_compression = compression;
+        Ok(())
+    }
+
+    /// Tunes DuckDB for large conversions and loads.
+    ///
+    /// Threads and insertion order apply to everything run on this
+    /// manager's connection; the row group size applies to Parquet files
+    /// written by downloads and conversions.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::dataset_manager::{DatasetManager, PerformanceOptions};
+    ///
+    /// let mut manager = DatasetManager::new()?;
+    /// manager.set_performance(&PerformanceOptions {
+    ///     threads: Some(8),
+    ///     preserve_insertion_order: Some(false),
+    ///     row_group_size: Some(500_000),
+    /// })?;
+    /// manager.convert_dataset("events.csv", "events.parquet", "csv", "parquet")?;
+    /// ```
+    pub fn set_performance(&mut self, options: &PerformanceOptions) -> Result<()> {
+        options.apply(&self.conn)?;
+        self.row_group_size = options.row_group_size;
+        Ok(())
+    }
+
+    /// `COPY` options for Parquet output with this manager's compression
+    /// and row group size.
+    fn parquet_options(&self) -> String {
+        self.parquet_tuning().parquet_options(&self.parquet_compression)
+    }
+
+    /// The part of [`PerformanceOptions`] that shapes written Parquet files.
+    fn parquet_tuning(&self) -> PerformanceOptions {
+        PerformanceOptions {
+            row_group_size: self.row_group_size,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the underlying DuckDB connection.
+    ///
+    /// Useful for registering user-defined functions before running queries.
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Attaches another DuckDB database file under `alias`.
+    ///
+    /// Tables of the attached database are queried as `alias.table`, so a
+    /// single query can join across databases, e.g. to compare a backup
+    /// against production or to join reference data.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to an existing DuckDB database file
+    /// * `alias` - Name the database is attached as
+    ///
+    /// # Errors
+    ///
+    /// Fails if `path` doesn't exist, so a typo doesn't silently attach a
+    /// new, empty database, or if the alias is already in use.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::DatasetManager;
+    ///
+    /// let manager = DatasetManager::new()?;
+    /// manager.attach("backup.duckdb", "backup")?;
+    /// manager.attach("prod.duckdb", "prod")?;
+    /// let missing = manager.run_query(
+    ///     "SELECT id FROM prod.orders EXCEPT SELECT id FROM backup.orders",
+    /// )?;
+    /// ```
+    #[instrument(skip_all, fields(input = %path, alias = %alias))]
+    pub fn attach(&self, path: &str, alias: &str) -> Result<(), FrozenDuckdbError> {
+        if alias.trim().is_empty() {
+            return Err(anyhow::anyhow!("Database alias must not be empty: {}", path).into());
+        }
+        if !Path::new(path).exists() {
+            return Err(FrozenDuckdbError::NotFound {
+                what: "Database to attach".to_string(),
+                path: path.into(),
+            });
+        }
+
+        self.conn
+            .execute_batch(&format!(
+                "ATTACH {} AS {};",
+                path_literal(path),
+                quote_identifier(alias)
+            ))
+            .with_context(|| format!("Failed to attach {} as {}", path, alias))?;
+        debug!("Attached {} as {}", path, alias);
+        Ok(())
+    }
+
+    /// Attaches a Postgres or MySQL database read-only, loading its
+    /// extension and the secrets stored by `frozen-duckdb secrets add`.
+    #[instrument(skip_all)]
+    pub fn attach_remote(&self, remote: &RemoteAttachment) -> Result<()> {
+        self.load_extension(remote.kind.as_str())?;
+        federation::use_secret_directory(&self.conn)?;
+        self.conn
+            .execute_batch(&format!("{};", remote.attach_sql()))
+            .with_context(|| {
+                format!(
+                    "Failed to attach {} database {}",
+                    remote.kind.as_str(),
+                    remote.alias
+                )
+            })?;
+        debug!("Attached {} database as {}", remote.kind.as_str(), remote.alias);
+        Ok(())
+    }
+
+    /// Executes a SQL statement and collects all result rows.
+    ///
+    /// # Arguments
+    ///
+    /// * `sql` - SQL statement to execute
+    ///
+    /// # Returns
+    ///
+    /// `Ok(QueryOutput)` containing column names and row values, `Err` if the
+    /// statement fails to prepare or execute.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::DatasetManager;
+    ///
+    /// let manager = DatasetManager::new()?;
+    /// let output = manager.run_query("SELECT 42 AS answer")?;
+    /// assert_eq!(output.columns, vec!["answer"]);
+    /// println!("{}", output.to_table());
+    /// ```
+    pub fn run_query(&self, sql: &str) -> Result<QueryOutput> {
+        self.run_query_limited(sql, None)
+    }
+
+    /// Executes a SQL statement like [`run_query`](Self::run_query), failing
+    /// as soon as the result has more than `max_rows` rows rather than
+    /// collecting all of them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::DatasetManager;
+    ///
+    /// let manager = DatasetManager::new()?;
+    /// assert!(manager.run_query_limited("SELECT * FROM range(10)", Some(10)).is_ok());
+    /// assert!(manager.run_query_limited("SELECT * FROM range(11)", Some(10)).is_err());
+    /// ```
+    #[instrument(name = "run_query", skip_all, fields(rows = field::Empty))]
+    pub fn run_query_limited(&self, sql: &str, max_rows: Option<usize>) -> Result<QueryOutput> {
+        let mut stmt = self
+            .conn
+            .prepare(sql)
+            .with_context(|| format!("Failed to prepare query: {}", sql))?;
+
+        let mut rows = Vec::new();
+        {
+            let mut result = stmt.query([])?;
+            while let Some(row) = result.next()? {
+                if max_rows.is_some_and(|max| rows.len() >= max) {
+                    return Err(anyhow::anyhow!(
+                        "Result exceeds the limit of {} rows",
+                        rows.len()
+                    ));
+                }
+                let column_count = row.as_ref().column_count();
+                let values = (0..column_count)
+                    .map(|i| row.get::<_, Value>(i))
+                    .collect::<Result<Vec<_>, _>>()?;
+                rows.push(values);
+            }
+        }
+        record_rows(rows.len());
+
+        Ok(QueryOutput {
+            columns: stmt.column_names(),
+            rows,
+        })
+    }
+
+    /// Executes a SQL statement, serving the result from `cache` when
+    /// neither the SQL nor its inputs changed since it was cached.
+    ///
+    /// Inputs are the files matched by string literals in `sql` (globs are
+    /// expanded) and the database files of this connection, including
+    /// attached ones. Statements that can't be wrapped in `COPY`, such as
+    /// DDL or several statements, run uncached.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::query_cache::QueryCache;
+    /// use frozen_duckdb::cli::DatasetManager;
+    ///
+    /// let manager = DatasetManager::new()?;
+    /// let cache = QueryCache::new()?;
+    /// let sql = "SELECT region, SUM(amount) FROM 'sales/*.parquet' GROUP BY ALL";
+    /// let first = manager.run_query_cached(sql, &cache)?; // runs the query
+    /// let second = manager.run_query_cached(sql, &cache)?; // reads the cached result
+    /// ```
+    pub fn run_query_cached(&self, sql: &str, cache: &QueryCache) -> Result<QueryOutput> {
+        self.run_query_cached_limited(sql, cache, None)
+    }
+
+    /// Executes a SQL statement like
+    /// [`run_query_cached`](Self::run_query_cached), failing as soon as the
+    /// result has more than `max_rows` rows. Results over the limit are
+    /// never cached.
+    #[instrument(name = "run_query_cached", skip_all)]
+    pub fn run_query_cached_limited(
+        &self,
+        sql: &str,
+        cache: &QueryCache,
+        max_rows: Option<usize>,
+    ) -> Result<QueryOutput> {
+        if split_statements(sql).len() != 1 {
+            return self.run_query_limited(sql, max_rows);
+        }
+        let key = cache.key(sql, &self.query_inputs(sql))?;
+        let cached = cache.lookup(&key);
+        Metrics::global().record_cache_lookup(CacheKind::Query, cached.is_some());
+        let entry = match cached {
+            Some(entry) => entry,
+            None => {
+                let query = sql.trim().trim_end_matches(';');
+                // One row over the limit is enough to reject the result
+                let bounded = match max_rows {
+                    Some(max) => format!("SELECT * FROM ({}) LIMIT {}", query, max + 1),
+                    None => query.to_string(),
+                };
+                let mut written = 0;
+                let stored = cache.store(&key, |path| {
+                    let copy = format!(
+                        "COPY ({}) TO {} (FORMAT PARQUET);",
+                        bounded,
+                        path_literal(path)
+                    );
+                    written = self
+                        .conn
+                        .execute(&copy, [])
+                        .context("Query result can't be cached")?;
+                    match max_rows {
+                        Some(max) if written > max => {
+                            anyhow::bail!("Result exceeds the limit of {} rows", max)
+                        }
+                        _ => Ok(()),
+                    }
+                });
+                match stored {
+                    Ok(entry) => entry,
+                    Err(e) if max_rows.is_some_and(|max| written > max) => return Err(e),
+                    Err(e) => {
+                        debug!("Running query uncached: {:#}", e);
+                        return self.run_query_limited(sql, max_rows);
+                    }
+                }
+            }
+        };
+        self.run_query_limited(
+            &format!("SELECT * FROM read_parquet({})", path_literal(&entry)),
+            max_rows,
+        )
+    }
+
+    /// Files a query reads: matches of its string literals, the files of
+    /// the attached databases, and the files of cataloged datasets.
+    pub fn query_inputs(&self, sql: &str) -> Vec<PathBuf> {
+        let mut literals = string_literals(sql);
+        // Files behind the views added by attach_catalog
+        if let Ok(mut stmt) = self
+            .conn
+            .prepare("SELECT sql FROM duckdb_views() WHERE database_name = ?")
+        {
+            if let Ok(rows) = stmt.query_map([CATALOG_SCHEMA], |row| row.get::<_, String>(0)) {
+                literals.extend(rows.flatten().flat_map(|view| string_literals(&view)));
+            }
+        }
+
+        let mut inputs: Vec<PathBuf> = literals
+            .iter()
+            .filter(|literal| !literal.contains("://"))
+            .flat_map(|literal| {
+                let mut files = Vec::new();
+                if let Ok(mut stmt) = self.conn.prepare("SELECT file FROM glob(?)") {
+                    if let Ok(rows) = stmt.query_map([literal], |row| row.get::<_, String>(0)) {
+                        files.extend(rows.flatten().map(PathBuf::from));
+                    }
+                }
+                files
+            })
+            .collect();
+
+        if let Ok(mut stmt) = self
+            .conn
+            .prepare("SELECT path FROM duckdb_databases() WHERE path IS NOT NULL")
+        {
+            if let Ok(rows) = stmt.query_map([], |row| row.get::<_, String>(0)) {
+                inputs.extend(rows.flatten().map(PathBuf::from));
+            }
+        }
+        inputs
+    }
+
+    /// Executes a SQL statement and writes each row as a JSON object on its
+    /// own line, without collecting the result first.
+    ///
+    /// Rows are written as they are read, so memory use doesn't grow with
+    /// the result size. Returns the number of rows written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::DatasetManager;
+    ///
+    /// let manager = DatasetManager::new()?;
+    /// let rows = manager.write_jsonl("SELECT * FROM range(3)", std::io::stdout().lock())?;
+    /// assert_eq!(rows, 3);
+    /// ```
+    #[instrument(skip_all, fields(rows = field::Empty))]
+    pub fn write_jsonl<W: Write>(&self, sql: &str, writer: W) -> Result<usize> {
+        let mut stmt = self
+            .conn
+            .prepare(sql)
+            .with_context(|| format!("Failed to prepare query: {}", sql))?;
+
+        let mut writer = BufWriter::new(writer);
+        let mut columns: Option<Vec<String>> = None;
+        let mut count = 0;
+        let mut result = stmt.query([])?;
+        while let Some(row) = result.next()? {
+            let columns = columns.get_or_insert_with(|| row.as_ref().column_names());
+            let mut record = serde_json::Map::with_capacity(columns.len());
+            for (i, column) in columns.iter().enumerate() {
+                record.insert(column.clone(), value_to_json(&row.get::<_, Value>(i)?));
+            }
+            serde_json::to_writer(&mut writer, &record)?;
+            writer.write_all(b"\n")?;
+            count += 1;
+        }
+        writer.flush()?;
+        record_rows(count);
+        Ok(count)
+    }
+
+    /// Executes a SQL statement and streams the result into a JSON Lines
+    /// file, compressed according to its extension.
+    ///
+    /// | Extension | Compression |
+    /// |-----------|-------------|
+    /// | `.jsonl` | None |
+    /// | `.jsonl.gz` | gzip |
+    /// | `.jsonl.zst` | zstd |
+    ///
+    /// DuckDB writes and compresses the rows as the query produces them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::DatasetManager;
+    ///
+    /// let manager = DatasetManager::new()?;
+    /// manager.export_jsonl("SELECT * FROM 'events.parquet'", "events.jsonl.zst")?;
+    /// ```
+    #[instrument(skip_all, fields(output = %output, bytes_written = field::Empty))]
+    pub fn export_jsonl(&self, sql: &str, output: &str) -> Result<()> {
+        let compression = jsonl_compression(output)?;
+        self.conn
+            .execute_batch(&format!(
+                "COPY ({}) TO {} (FORMAT JSON, COMPRESSION {});",
+                sql.trim().trim_end_matches(';'),
+                path_literal(output),
+                compression
+            ))
+            .with_context(|| format!("Failed to export query results to {}", output))?;
+        record_bytes_written(output);
+        info!("✅ Exported query results to {}", output);
+        Ok(())
+    }
+
+    /// Executes statements in explicit transactions of `transaction_size`
+    /// statements each.
+    ///
+    /// In autocommit mode (`transaction_size` of 0) every statement commits
+    /// on its own, which makes bulk loads of many small statements slow.
+    /// Batching them commits once per batch instead.
+    ///
+    /// # Errors
+    ///
+    /// If a statement fails, the incomplete batch it belongs to is rolled
+    /// back and the error names the failing statement. Batches committed
+    /// before it stay committed, so a load can be resumed from the first
+    /// statement of the rolled-back batch.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::dataset_manager::split_statements;
+    /// use frozen_duckdb::cli::DatasetManager;
+    ///
+    /// let manager = DatasetManager::new()?;
+    /// let script = std::fs::read_to_string("load.sql")?;
+    /// let report = manager.execute_in_transactions(&split_statements(&script), 1000)?;
+    /// println!("{} statements in {} transactions", report.statements, report.transactions);
+    /// ```
+    #[instrument(skip_all, fields(statements = statements.len()))]
+    pub fn execute_in_transactions(
+        &self,
+        statements: &[&str],
+        transaction_size: usize,
+    ) -> Result<BatchReport> {
+        let mut report = BatchReport {
+            statements: 0,
+            transactions: 0,
+        };
+        if transaction_size == 0 {
+            for (i, statement) in statements.iter().enumerate() {
+                self.conn
+                    .execute_batch(statement)
+                    .with_context(|| format!("Statement {} failed: {}", i + 1, statement))?;
+                report.statements += 1;
+            }
+            return Ok(report);
+        }
+
+        for (batch_index, batch) in statements.chunks(transaction_size).enumerate() {
+            let first = batch_index * transaction_size + 1;
+            self.conn.with_savepoint(|sp| {
+                for (offset, statement) in batch.iter().enumerate() {
+                    sp.execute_batch(statement).with_context(|| {
+                        format!(
+                            "Statement {} failed: {} (rolled back statements {}-{}, {} earlier statements were committed)",
+                            first + offset,
+                            statement,
+                            first,
+                            first + offset,
+                            report.statements
+                        )
+                    })?;
+                }
+                Ok::<_, anyhow::Error>(())
+            })?;
+            report.statements += batch.len();
+            report.transactions += 1;
+            debug!("Committed statements {}-{}", first, first + batch.len() - 1);
+        }
+        Ok(report)
     }
 
     /// Downloads or generates the Chinook music database dataset.
@@ -138,6 +954,7 @@ impl DatasetManager {
     /// - **CSV generation**: <100ms
     /// - **Format conversion**: <500ms for Parquet
     /// - **Total time**: <1s for most formats
+    #[instrument(skip_all, fields(output = %output_dir, format = %format))]
     pub fn download_chinook(&self, output_dir: &str, format: &str) -> Result<()> {
         info!(
             "Downloading Chinook dataset in {} format to {}",
@@ -160,6 +977,73 @@ impl DatasetManager {
         Ok(())
     }
 
+    /// Downloads a dataset through the local dataset cache.
+    ///
+    /// Generated datasets are cached under `~/.frozen-duckdb/cache/datasets/`,
+    /// keyed by dataset, format, and scale factor. On a cache hit the cached
+    /// files are copied into `output_dir` without regenerating anything.
+    ///
+    /// # Arguments
+    ///
+    /// * `dataset` - Dataset name ("chinook" or "tpch")
+    /// * `output_dir` - Directory where the dataset files will be placed
+    /// * `format` - Output format ("csv", "parquet", "duckdb")
+    /// * `scale_factor` - TPC-H scale factor (ignored for Chinook)
+    /// * `force` - Regenerate even if a cached copy exists
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::DatasetManager;
+    ///
+    /// let manager = DatasetManager::new()?;
+    /// // First run generates, later runs are instant
+    /// manager.download_cached("tpch", "datasets", "parquet", 0.01, false)?;
+    /// ```
+    ///
+    /// # Performance
+    ///
+    /// - **Cache hit**: <50ms regardless of dataset size
+    /// - **Cache miss**: Same as direct generation plus one directory rename
+    #[instrument(skip_all, fields(dataset = %dataset, output = %output_dir, format = %format))]
+    pub fn download_cached(
+        &self,
+        dataset: &str,
+        output_dir: &str,
+        format: &str,
+        scale_factor: f64,
+        force: bool,
+    ) -> Result<()> {
+        let mut key = match dataset {
+            "chinook" => DatasetKey::new(dataset, format, None),
+            "tpch" => DatasetKey::new(dataset, format, Some(scale_factor)),
+            _ => return Err(anyhow::anyhow!("Unknown dataset: {}", dataset)),
+        };
+        if format == "parquet" {
+            key = key.with_compression(&self.parquet_compression);
+            if let Some(rows) = self.row_group_size {
+                key = key.with_row_group_size(rows);
+            }
+        }
+        let cache = DatasetCache::new()?;
+
+        if !force {
+            if let Some(entry) = cache.lookup(&key) {
+                info!("⚡ Using cached {} dataset ({})", dataset, entry.display());
+                return cache.materialize(&entry, output_dir);
+            }
+        }
+
+        let entry = cache.store(&key, |staging| {
+            let staging = staging.to_string_lossy();
+            match dataset {
+                "chinook" => self.download_chinook(&staging, format),
+                _ => self.download_tpch(&staging, format, scale_factor),
+            }
+        })?;
+        cache.materialize(&entry, output_dir)
+    }
+
     /// Generates the TPC-H decision support benchmark dataset.
     ///
     /// TPC-H is a standard benchmark for decision support systems that simulates
@@ -170,6 +1054,7 @@ impl DatasetManager {
     ///
     /// * `output_dir` - Directory where the dataset files will be saved
     /// * `format` - Output format ("duckdb", "parquet", "csv")
+    /// * `scale_factor` - TPC-H scale factor (0.01 for a tiny dataset)
     ///
     /// # Returns
     ///
@@ -181,7 +1066,7 @@ impl DatasetManager {
     /// use frozen_duckdb::cli::DatasetManager;
     ///
     /// let manager = DatasetManager::new()?;
-    /// manager.download_tpch("data", "parquet")?;
+    /// manager.download_tpch("data", "parquet", 0.01)?;
     /// ```
     ///
     /// # Dataset Contents
@@ -199,7 +1084,7 @@ impl DatasetManager {
     ///
     /// # Scale Factor
     ///
-    /// Uses scale factor 0.01 (tiny dataset) for fast generation:
+    /// The CLI defaults to scale factor 0.01 (tiny dataset) for fast generation:
     /// - **Total rows**: ~19,000 across all tables
     /// - **Generation time**: <10s
     /// - **File sizes**: 1-5MB per table depending on format
@@ -210,7 +1095,11 @@ impl DatasetManager {
     /// - **DuckDB export**: <1s
     /// - **Parquet export**: <5s
     /// - **CSV export**: <3s
-    pub fn download_tpch(&self, output_dir: &str, format: &str) -> Result<()> {
+    #[instrument(
+        skip_all,
+        fields(output = %output_dir, format = %format, scale_factor = scale_factor)
+    )]
+    pub fn download_tpch(&self, output_dir: &str, format: &str, scale_factor: f64) -> Result<()> {
         info!(
             "Generating TPC-H dataset in {} format to {}",
             format, output_dir
@@ -219,10 +1108,10 @@ impl DatasetManager {
         // Create output directory if it doesn't exist
         fs::create_dir_all(output_dir)?;
 
-        // Generate TPC-H data with scale factor 0.01 (tiny dataset for fast generation)
-        // This creates ~1,500 rows across 8 tables - perfect for testing and development
-        info!("🔄 Generating TPC-H data with scale factor 0.01...");
-        self.conn.execute("CALL dbgen(sf = 0.01)", [])?;
+        // Generate TPC-H data; sf 0.01 creates ~19,000 rows across 8 tables,
+        // which is perfect for testing and development
+        info!("🔄 Generating TPC-H data with scale factor {}...", scale_factor);
+        self.conn.execute(&format!("CALL dbgen(sf = {})", scale_factor), [])?;
 
         // Export to requested format with optimized handling for each type
         match format {
@@ -230,7 +1119,7 @@ impl DatasetManager {
                 // Export as native DuckDB database for maximum performance
                 let db_path = Path::new(output_dir).join("tpch.duckdb");
                 self.conn
-                    .execute(&format!("EXPORT DATABASE '{}'", db_path.display()), [])?;
+                    .execute(&format!("EXPORT DATABASE {}", path_literal(&db_path)), [])?;
                 info!("✅ TPC-H dataset exported to DuckDB: {}", db_path.display());
             }
             "parquet" => {
@@ -248,7 +1137,7 @@ impl DatasetManager {
                 info!("   Defaulting to DuckDB format");
                 let db_path = Path::new(output_dir).join("tpch.duckdb");
                 self.conn
-                    .execute(&format!("EXPORT DATABASE '{}'", db_path.display()), [])?;
+                    .execute(&format!("EXPORT DATABASE {}", path_literal(&db_path)), [])?;
             }
         }
 
@@ -257,47 +1146,71 @@ impl DatasetManager {
     }
 
     fn export_tpch_tables_to_parquet(&self, output_dir: &str) -> Result<()> {
-        let tables = [
-            "customer", "lineitem", "nation", "orders", "part", "partsupp", "region", "supplier",
-        ];
-
-        for table in &tables {
-            let parquet_path = Path::new(output_dir).join(format!("{}.parquet", table));
-            self.conn.execute(
-                &format!(
-                    "COPY {} TO '{}' (FORMAT PARQUET)",
-                    table,
-                    parquet_path.display()
-                ),
-                [],
-            )?;
-        }
+        let options = self.parquet_options();
+        self.export_tables_concurrently(&TPCH_TABLES, output_dir, "parquet", &options)?;
 
-        info!("✅ TPC-H tables exported to Parquet format");
+        info!(
+            "✅ TPC-H tables exported to Parquet format ({} compression)",
+            self.parquet_compression
+        );
         Ok(())
     }
 
     fn export_tpch_tables_to_csv(&self, output_dir: &str) -> Result<()> {
-        let tables = [
-            "customer", "lineitem", "nation", "orders", "part", "partsupp", "region", "supplier",
-        ];
-
-        for table in &tables {
-            let csv_path = Path::new(output_dir).join(format!("{}.csv", table));
-            self.conn.execute(
-                &format!(
-                    "COPY {} TO '{}' (FORMAT CSV, HEADER)",
-                    table,
-                    csv_path.display()
-                ),
-                [],
-            )?;
-        }
+        self.export_tables_concurrently(&TPCH_TABLES, output_dir, "csv", "FORMAT CSV, HEADER")?;
 
         info!("✅ TPC-H tables exported to CSV format");
         Ok(())
     }
 
+    /// Exports each table with `COPY ... TO` on its own connection and thread.
+    ///
+    /// All connections share the same database, so the tables are read
+    /// concurrently instead of waiting behind the largest one.
+    fn export_tables_concurrently(
+        &self,
+        tables: &[&str],
+        output_dir: &str,
+        extension: &str,
+        copy_options: &str,
+    ) -> Result<()> {
+        let connections = tables
+            .iter()
+            .map(|_| self.conn.try_clone())
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to open export connections")?;
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = tables
+                .iter()
+                .zip(connections)
+                .map(|(table, conn)| {
+                    let path = Path::new(output_dir).join(format!("{}.{}", table, extension));
+                    scope.spawn(move || -> Result<()> {
+                        let started = std::time::Instant::now();
+                        let copy = format!(
+                            "COPY {} TO {} ({})",
+                            table,
+                            path_literal(&path),
+                            copy_options
+                        );
+                        conn.execute(&copy, [])
+                            .with_context(|| format!("Failed to export table {}", table))?;
+                        debug!("Exported {} in {:?}", table, started.elapsed());
+                        Ok(())
+                    })
+                })
+                .collect();
+
+            for handle in handles {
+                handle
+                    .join()
+                    .map_err(|_| anyhow::anyhow!("Table export thread panicked"))??;
+            }
+            Ok(())
+        })
+    }
+
     fn create_sample_chinook_data(&self, output_dir: &str) -> Result<()> {
         // Create sample Chinook data in CSV format
         let csv_data = r#"ArtistId,Name
@@ -330,9 +1243,10 @@ TrackId,Name,AlbumId,Composer,Milliseconds,Bytes,UnitPrice
                 let parquet_path = Path::new(output_dir).join("chinook.parquet");
                 self.conn.execute(
                     &format!(
-                        "COPY (SELECT * FROM read_csv('{}', header=true)) TO '{}' (FORMAT PARQUET)",
-                        csv_path.display(),
-                        parquet_path.display()
+                        "COPY (SELECT * FROM read_csv({}, header=true)) TO {} ({})",
+                        path_literal(&csv_path),
+                        path_literal(&parquet_path),
+                        self.parquet_options()
                     ),
                     [],
                 )?;
@@ -353,15 +1267,17 @@ TrackId,Name,AlbumId,Composer,Milliseconds,Bytes,UnitPrice
     /// Convert datasets between different file formats.
     ///
     /// This function provides format conversion capabilities for data files,
-    /// allowing you to convert between CSV, Parquet, JSON, and other formats.
+    /// allowing you to convert between CSV, Parquet, Excel, and other formats.
     /// Conversion is optimized for performance and maintains data integrity.
     ///
     /// # Arguments
     ///
-    /// * `input` - Input file path to convert from
+    /// * `input` - Input file path to convert from, or a Delta Lake or
+    ///   Iceberg table location (see [`lakehouse`](super::lakehouse))
     /// * `output` - Output file path to convert to
-    /// * `input_format` - Input file format ("csv", "parquet", "json")
-    /// * `output_format` - Output file format ("csv", "parquet", "json", "arrow")
+    /// * `input_format` - Input file format (one of [`CONVERT_FORMATS`] or
+    ///   [`TABLE_FORMATS`])
+    /// * `output_format` - Output file format (one of [`CONVERT_FORMATS`])
     ///
     /// # Returns
     ///
@@ -382,6 +1298,12 @@ TrackId,Name,AlbumId,Composer,Milliseconds,Bytes,UnitPrice
     /// |-------|--------|--------|
     /// | CSV | Parquet | ✅ Supported |
     /// | Parquet | CSV | ✅ Supported |
+    /// | CSV, Parquet | Excel | ✅ Supported |
+    /// | Excel | CSV, Parquet | ✅ Supported |
+    /// | Any | JSON Lines (`.jsonl`, `.jsonl.gz`, `.jsonl.zst`) | ✅ Supported |
+    /// | GeoJSON | GeoParquet | ✅ Supported (spatial extension) |
+    /// | GeoParquet | GeoJSON | ✅ Supported (spatial extension) |
+    /// | Delta Lake, Iceberg | Any non-spatial format | ✅ Supported (delta, iceberg extensions) |
     /// | CSV | JSON | ❌ Not implemented |
     /// | JSON | Parquet | ❌ Not implemented |
     ///
@@ -396,35 +1318,571 @@ TrackId,Name,AlbumId,Composer,Milliseconds,Bytes,UnitPrice
         output: &str,
         input_format: &str,
         output_format: &str,
-    ) -> Result<()> {
+    ) -> Result<(), FrozenDuckdbError> {
+        self.convert_dataset_with(
+            input,
+            output,
+            input_format,
+            output_format,
+            &ConvertOptions::default(),
+        )
+    }
+
+    /// Converts a dataset like [`DatasetManager::convert_dataset`], with
+    /// options for Excel worksheets, geometry columns, and split Parquet
+    /// output.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::dataset_manager::{ConvertOptions, DatasetManager};
+    ///
+    /// let manager = DatasetManager::new()?;
+    /// let options = ConvertOptions {
+    ///     sheet: Some("Q3".to_string()),
+    ///     ..Default::default()
+    /// };
+    /// manager.convert_dataset_with("report.xlsx", "q3.parquet", "xlsx", "parquet", &options)?;
+    /// ```
+    #[instrument(
+        skip_all,
+        fields(
+            input = %input,
+            output = %output,
+            format = %output_format,
+            rows = field::Empty,
+            bytes_written = field::Empty,
+        )
+    )]
+    pub fn convert_dataset_with(
+        &self,
+        input: &str,
+        output: &str,
+        input_format: &str,
+        output_format: &str,
+        options: &ConvertOptions,
+    ) -> Result<(), FrozenDuckdbError> {
         info!(
             "Converting {} from {} to {}",
             input, input_format, output_format
         );
 
-        let query = match (input_format, output_format) {
-            ("csv", "parquet") => format!(
-                "COPY (SELECT * FROM read_csv('{}', header=true)) TO '{}' (FORMAT PARQUET)",
-                input, output
-            ),
-            ("parquet", "csv") => format!(
-                "COPY (SELECT * FROM read_parquet('{}')) TO '{}' (FORMAT CSV)",
-                input, output
+        let spatial = [input_format, output_format].map(|f| SPATIAL_FORMATS.contains(&f));
+        if input_format == output_format
+            || !CONVERT_FORMATS.contains(&output_format)
+            || spatial[0] != spatial[1]
+        {
+            return Err(FrozenDuckdbError::UnsupportedConversion {
+                from: input_format.to_string(),
+                to: output_format.to_string(),
+            });
+        }
+        let parquet_output = matches!(output_format, "parquet" | "geoparquet");
+        if options.split.is_some() && !parquet_output {
+            return Err(anyhow::anyhow!(
+                "--max-file-size and --rows-per-file only apply to Parquet output"
+            )
+            .into());
+        }
+        let source = self.read_source(input, input_format, options)?;
+
+        let projection = if spatial[0] {
+            // ST_Read always names the geometry `geom`
+            let geometry = options.geometry_column.as_deref().unwrap_or("geometry");
+            let source_geometry = if input_format == "geojson" {
+                "geom"
+            } else {
+                geometry
+            };
+            format!(
+                "* EXCLUDE ({0}), {0} AS {1}",
+                quote_identifier(source_geometry),
+                quote_identifier(geometry)
+            )
+        } else {
+            "*".to_string()
+        };
+
+        let copy_options = match output_format {
+            "parquet" | "geoparquet" => match options.split {
+                Some(split) => {
+                    split.copy_options(&self.parquet_compression, &self.parquet_tuning())
+                }
+                None => self.parquet_options(),
+            },
+            "xlsx" => format!(
+                "FORMAT XLSX, HEADER true, SHEET '{}'",
+                options
+                    .sheet
+                    .as_deref()
+                    .unwrap_or("Sheet1")
+                    .replace('\'', "''")
             ),
-            _ => {
-                return Err(anyhow::anyhow!(
-                    "Unsupported conversion: {} to {}",
-                    input_format,
-                    output_format
-                ));
-            }
+            "jsonl" => format!("FORMAT JSON, COMPRESSION {}", jsonl_compression(output)?),
+            "geojson" => "FORMAT GDAL, DRIVER 'GeoJSON'".to_string(),
+            _ => "FORMAT CSV".to_string(),
         };
+        let query = format!(
+            "COPY (SELECT {} FROM {}) TO {} ({})",
+            projection,
+            source,
+            path_literal(output),
+            copy_options
+        );
 
-        self.conn.execute(&query, [])?;
+        record_rows(self.conn.execute(&query, [])?);
+        if options.split.is_some() {
+            let manifest = PartManifest::scan(&self.conn, Path::new(output))?;
+            manifest.save(Path::new(output))?;
+            info!(
+                "✅ Converted {} to {} parts ({} rows) in {}",
+                input,
+                manifest.parts.len(),
+                manifest.total_rows,
+                output
+            );
+            return Ok(());
+        }
+        record_bytes_written(output);
         info!("✅ Converted {} to {}", input, output);
         Ok(())
     }
 
+    /// Detects the dialect, header, and column types of a CSV file with
+    /// DuckDB's `sniff_csv`, and reads a few sample rows with them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::DatasetManager;
+    ///
+    /// let manager = DatasetManager::new()?;
+    /// let schema = manager.sniff_csv("export.csv")?;
+    /// println!("delimiter {:?}, header {}", schema.delimiter, schema.has_header);
+    /// for column in &schema.columns {
+    ///     println!("{}: {}", column.name, column.data_type);
+    /// }
+    /// println!("{}", schema.sample.to_table());
+    /// ```
+    #[instrument(skip_all, fields(input = %path))]
+    pub fn sniff_csv(&self, path: &str) -> Result<CsvSchema, FrozenDuckdbError> {
+        if !Path::new(path).exists() {
+            return Err(FrozenDuckdbError::NotFound {
+                what: "CSV file".to_string(),
+                path: path.into(),
+            });
+        }
+        let source = format!("sniff_csv({})", path_literal(path));
+        let non_empty = |value: Option<String>| value.filter(|v| !v.is_empty());
+
+        let mut schema = self
+            .conn
+            .query_row(
+                &format!(
+                    "SELECT Delimiter, Quote, Escape, HasHeader, SkipRows::BIGINT, DateFormat, TimestampFormat FROM {}",
+                    source
+                ),
+                [],
+                |row| {
+                    Ok(CsvSchema {
+                        delimiter: row.get(0)?,
+                        quote: row.get(1)?,
+                        escape: row.get(2)?,
+                        has_header: row.get(3)?,
+                        skip_rows: row.get::<_, i64>(4)? as u64,
+                        date_format: non_empty(row.get(5)?),
+                        timestamp_format: non_empty(row.get(6)?),
+                        columns: Vec::new(),
+                        sample: QueryOutput {
+                            columns: Vec::new(),
+                            rows: Vec::new(),
+                        },
+                    })
+                },
+            )
+            .with_context(|| format!("Failed to sniff CSV file: {}", path))?;
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT c.name, c.type FROM (SELECT unnest(Columns) AS c FROM {})",
+            source
+        ))?;
+        schema.columns = stmt
+            .query_map([], |row| {
+                Ok(CsvColumn {
+                    name: row.get(0)?,
+                    data_type: row.get(1)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        schema.sample = self.run_query(&format!(
+            "SELECT * FROM {} LIMIT {}",
+            schema.read_csv(path),
+            CSV_SAMPLE_ROWS
+        ))?;
+        debug!(
+            "Sniffed {}: delimiter {:?}, header {}, {} columns",
+            path,
+            schema.delimiter,
+            schema.has_header,
+            schema.columns.len()
+        );
+        Ok(schema)
+    }
+
+    /// Loads the spatial extension for geometry types and GeoJSON/GeoParquet.
+    ///
+    /// The extension is loaded from the frozen binary if it was built with
+    /// it, and otherwise installed by DuckDB, which needs network access
+    /// the first time.
+    ///
+    /// # Errors
+    ///
+    /// Fails with an explanation if the extension is neither built in nor
+    /// installable.
+    #[instrument(skip_all)]
+    pub fn enable_spatial(&self) -> Result<(), FrozenDuckdbError> {
+        self.load_extension("spatial")
+    }
+
+    /// Loads the extension reading `format` tables, and `httpfs` if
+    /// `location` is remote (see [`lakehouse`](super::lakehouse)).
+    ///
+    /// # Errors
+    ///
+    /// Fails with an explanation if an extension is neither built in nor
+    /// installable.
+    #[instrument(skip_all, fields(format = format.as_str()))]
+    pub fn enable_table_format(
+        &self,
+        format: TableFormat,
+        location: &str,
+    ) -> Result<(), FrozenDuckdbError> {
+        self.load_extension(format.as_str())?;
+        if is_remote(location) {
+            self.load_extension("httpfs")?;
+        }
+        Ok(())
+    }
+
+    /// Loads `extension` from the frozen binary, or installs it.
+    fn load_extension(&self, extension: &str) -> Result<(), FrozenDuckdbError> {
+        if self.conn.execute_batch(&format!("LOAD {};", extension)).is_ok() {
+            return Ok(());
+        }
+        self.conn
+            .execute_batch(&format!("INSTALL {0}; LOAD {0};", extension))
+            .map_err(|e| FrozenDuckdbError::ExtensionUnavailable {
+                extension: extension.to_string(),
+                reason: format!(
+                    "this DuckDB binary wasn't built with it, and installing it failed ({}). \
+                     Rebuild the frozen binary with {} in BUILD_EXTENSIONS, or run \
+                     once with network access.",
+                    e, extension
+                ),
+            })?;
+        debug!("Installed and loaded the {} extension", extension);
+        Ok(())
+    }
+
+    /// Returns whether the spatial extension is loaded.
+    pub fn has_spatial(&self) -> bool {
+        self.conn
+            .query_row(
+                "SELECT loaded FROM duckdb_extensions() WHERE extension_name = 'spatial'",
+                [],
+                |row| row.get::<_, bool>(0),
+            )
+            .unwrap_or(false)
+    }
+
+    /// Describes the columns of a dataset file.
+    ///
+    /// Returns one row per column with its name and DuckDB type, as
+    /// reported by `DESCRIBE`. For `xlsx` files, `sheet` selects the
+    /// worksheet; the first one is used by default.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::DatasetManager;
+    ///
+    /// let manager = DatasetManager::new()?;
+    /// let schema = manager.describe_dataset("report.xlsx", "xlsx", Some("Q3"))?;
+    /// println!("{}", schema.to_table());
+    /// ```
+    #[instrument(skip_all, fields(input = %input, format = %format))]
+    pub fn describe_dataset(
+        &self,
+        input: &str,
+        format: &str,
+        sheet: Option<&str>,
+    ) -> Result<QueryOutput> {
+        let options = ConvertOptions {
+            sheet: sheet.map(str::to_string),
+            ..Default::default()
+        };
+        let source = self.read_source(input, format, &options)?;
+        self.run_query(&format!(
+            "SELECT column_name, column_type, \"null\" FROM (DESCRIBE SELECT * FROM {})",
+            source
+        ))
+    }
+
+    /// Loads `input` into `table` of this manager's database, replacing
+    /// it, or incrementally above `watermark` (see [`ingest`](super::ingest)).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::ingest::IncrementalOptions;
+    /// use frozen_duckdb::cli::DatasetManager;
+    ///
+    /// let manager = DatasetManager::open("shop.duckdb")?;
+    /// let options = IncrementalOptions {
+    ///     key_columns: vec!["id".to_string()],
+    ///     watermark_column: "updated_at".to_string(),
+    /// };
+    /// let report = manager.ingest("orders.csv", "csv", "orders", Some(&options), None)?;
+    /// println!("Loaded up to {:?}", report.watermark);
+    /// ```
+    #[instrument(skip_all, fields(input = %input, table = %table, rows = field::Empty))]
+    pub fn ingest(
+        &self,
+        input: &str,
+        format: &str,
+        table: &str,
+        incremental: Option<&IncrementalOptions>,
+        watermark: Option<&str>,
+    ) -> Result<IngestReport> {
+        let source = self.read_source(input, format, &ConvertOptions::default())?;
+        let report = ingest(&self.conn, &source, table, incremental, watermark)?;
+        record_rows(report.rows_read);
+        Ok(report)
+    }
+
+    /// Merges `source` into `target_table` on the key columns, with
+    /// `INSERT ... ON CONFLICT` or `MERGE INTO` (see [`merge`](super::merge)).
+    ///
+    /// `source` is a data file, a `delta://` or `iceberg://` table, or the
+    /// name of a table, possibly of an attached database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::merge::{WhenMatched, WhenNotMatched};
+    /// use frozen_duckdb::cli::DatasetManager;
+    ///
+    /// let manager = DatasetManager::open("shop.duckdb")?;
+    /// let report = manager.merge_into(
+    ///     "orders",
+    ///     "updates.parquet",
+    ///     &["id".to_string()],
+    ///     WhenMatched::Update,
+    ///     WhenNotMatched::Insert,
+    /// )?;
+    /// println!("{} inserted, {} updated", report.inserted, report.updated);
+    /// ```
+    #[instrument(skip_all, fields(input = %source, table = %target_table, rows = field::Empty))]
+    pub fn merge_into(
+        &self,
+        target_table: &str,
+        source: &str,
+        key_columns: &[String],
+        when_matched: WhenMatched,
+        when_not_matched: WhenNotMatched,
+    ) -> Result<MergeReport> {
+        let options = MergeOptions {
+            key_columns: key_columns.to_vec(),
+            when_matched,
+            when_not_matched,
+        };
+        let report = merge_into(&self.conn, target_table, &self.merge_source(source)?, &options)?;
+        record_rows(report.source_rows);
+        Ok(report)
+    }
+
+    /// Merges `source` into the CSV, Parquet, or JSON file `target`,
+    /// rewriting it; see [`merge_into`](Self::merge_into).
+    #[instrument(
+        skip_all,
+        fields(input = %source, output = %target, rows = field::Empty, bytes_written = field::Empty)
+    )]
+    pub fn merge_into_file(
+        &self,
+        target: &str,
+        source: &str,
+        key_columns: &[String],
+        when_matched: WhenMatched,
+        when_not_matched: WhenNotMatched,
+    ) -> Result<MergeReport> {
+        let options = MergeOptions {
+            key_columns: key_columns.to_vec(),
+            when_matched,
+            when_not_matched,
+        };
+        let report = merge_into_file(&self.conn, target, &self.merge_source(source)?, &options)?;
+        record_rows(report.source_rows);
+        record_bytes_written(target);
+        Ok(report)
+    }
+
+    /// Returns what to select a merge source from: a reader for files and
+    /// lakehouse tables, the quoted name otherwise.
+    fn merge_source(&self, source: &str) -> Result<String> {
+        let format = table_format(source)
+            .map(|format| format.as_str())
+            .or_else(|| detect_format(source));
+        match format {
+            Some(format) => Ok(self.read_source(source, format, &ConvertOptions::default())?),
+            None => Ok(qualified_name(source)),
+        }
+    }
+
+    /// Describes a dataset for the [`catalog`](super::catalog): its absolute
+    /// path, schema hash, and row count.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::catalog::DatasetCatalog;
+    /// use frozen_duckdb::cli::DatasetManager;
+    ///
+    /// let manager = DatasetManager::new()?;
+    /// let entry = manager.catalog_entry("events", "events/*.parquet", "parquet")?;
+    /// DatasetCatalog::open_default()?.register(&entry)?;
+    /// ```
+    #[instrument(skip_all, fields(dataset = %name, input = %path, rows = field::Empty))]
+    pub fn catalog_entry(&self, name: &str, path: &str, format: &str) -> Result<CatalogEntry> {
+        validate_name(name)?;
+        let path = absolute(path);
+        let source = self.read_source(&path, format, &ConvertOptions::default())?;
+        let schema = self.run_query(&format!(
+            "SELECT column_name, column_type FROM (DESCRIBE SELECT * FROM {})",
+            source
+        ))?;
+        let columns: Vec<(String, String)> = schema
+            .rows
+            .iter()
+            .map(|row| (format_value(&row[0]), format_value(&row[1])))
+            .collect();
+        let row_count: i64 = self
+            .conn
+            .query_row(&format!("SELECT count(*) FROM {}", source), [], |row| row.get(0))
+            .with_context(|| format!("Failed to count rows of {}", path))?;
+        record_rows(row_count as usize);
+
+        Ok(CatalogEntry {
+            name: name.to_string(),
+            path,
+            format: format.to_string(),
+            schema_hash: schema_hash(&columns),
+            row_count,
+            created_at: None,
+        })
+    }
+
+    /// Makes every cataloged dataset queryable as a view in the
+    /// [`CATALOG_SCHEMA`] schema, e.g. `datasets.sales`, and returns how
+    /// many were added.
+    ///
+    /// Datasets whose files can no longer be read are skipped with a
+    /// warning.
+    #[instrument(skip_all)]
+    pub fn attach_catalog(&self, catalog: &DatasetCatalog) -> Result<usize> {
+        self.conn
+            .execute_batch(&format!("ATTACH ':memory:' AS {};", CATALOG_SCHEMA))
+            .context("Failed to attach the dataset catalog")?;
+        let mut attached = 0;
+        for entry in catalog.list()? {
+            let view = self
+                .read_source(&entry.path, &entry.format, &ConvertOptions::default())
+                .and_then(|source| {
+                    Ok(self.conn.execute_batch(&format!(
+                        "CREATE VIEW {}.{} AS SELECT * FROM {};",
+                        CATALOG_SCHEMA,
+                        quote_identifier(&entry.name),
+                        source
+                    ))?)
+                });
+            match view {
+                Ok(()) => attached += 1,
+                Err(e) => warn!("⚠️  Skipping cataloged dataset {}: {}", entry.name, e),
+            }
+        }
+        debug!("Attached {} cataloged datasets as {}", attached, CATALOG_SCHEMA);
+        Ok(attached)
+    }
+
+    /// Returns the table function reading `input` in `format`, loading the
+    /// Excel or spatial extension when needed.
+    fn read_source(
+        &self,
+        input: &str,
+        format: &str,
+        options: &ConvertOptions,
+    ) -> Result<String, FrozenDuckdbError> {
+        let path = path_literal(input);
+        if SPATIAL_FORMATS.contains(&format) {
+            self.enable_spatial()?;
+        }
+        match format {
+            "csv" if options.auto => {
+                let schema = self.sniff_csv(input)?;
+                info!(
+                    "🔎 Detected delimiter {:?}, quote {:?}, header {}, {} columns",
+                    schema.delimiter,
+                    schema.quote,
+                    schema.has_header,
+                    schema.columns.len()
+                );
+                Ok(schema.read_csv(input))
+            }
+            "csv" => {
+                // Pin the dialect DuckDB writes: sniffing it can pick a
+                // character that only appears inside values as the delimiter
+                let lower = input.to_lowercase();
+                let delimiter = if lower.ends_with(".tsv") || lower.ends_with(".tsv.gz") {
+                    "\t"
+                } else {
+                    ","
+                };
+                Ok(format!(
+                    "read_csv({}, header=true, delim='{}', quote='\"', escape='\"', allow_quoted_nulls=false)",
+                    path, delimiter
+                ))
+            }
+            "parquet" | "geoparquet" => Ok(format!("read_parquet({})", path)),
+            "delta" | "iceberg" => {
+                let table = TableFormat::parse(format)?;
+                let location = parse_table_url(input).map_or(input, |(_, location)| location);
+                self.enable_table_format(table, location)?;
+                Ok(table.scan(location))
+            }
+            "geojson" => Ok(format!("ST_Read({})", path)),
+            "jsonl" => Ok(format!("read_json({}, format = 'newline_delimited')", path)),
+            "xlsx" => {
+                self.conn
+                    .execute_batch("INSTALL excel; LOAD excel;")
+                    .context("Failed to load the DuckDB excel extension")?;
+                Ok(match options.sheet.as_deref() {
+                    Some(sheet) => format!(
+                        "read_xlsx({}, header = true, sheet = '{}')",
+                        path,
+                        sheet.replace('\'', "''")
+                    ),
+                    None => format!("read_xlsx({}, header = true)", path),
+                })
+            }
+            other => Err(FrozenDuckdbError::UnsupportedFormat {
+                kind: "input",
+                format: other.to_string(),
+                available: [&CONVERT_FORMATS[..], &TABLE_FORMATS[..]].concat().join(", "),
+            }
+            .into()),
+        }
+    }
+
     /// Show comprehensive information about frozen DuckDB configuration.
     ///
     /// This function displays system information, available extensions,
@@ -452,11 +1910,14 @@ TrackId,Name,AlbumId,Composer,Milliseconds,Bytes,UnitPrice
     /// - **Architecture**: Current system architecture (x86_64/arm64)
     /// - **Target OS**: Operating system information
     /// - **Available Extensions**: List of loaded DuckDB extensions
+    /// - **Capabilities**: Statically linked and installed extensions, ICU
+    ///   collations and time zones (see [`crate::capabilities`])
     ///
     /// # Performance
     ///
     /// - **Query time**: <50ms
     /// - **Memory usage**: <10MB
+    #[instrument(skip_all)]
     pub fn show_info(&self) -> Result<()> {
         info!("🦆 Frozen DuckDB Information");
         info!("  Version: {}", env!("CARGO_PKG_VERSION"));
@@ -465,14 +1926,196 @@ TrackId,Name,AlbumId,Composer,Milliseconds,Bytes,UnitPrice
         info!("  Target: {}", std::env::consts::OS);
 
         // Show available extensions
-        let extensions: Vec<String> = self
-            .conn
-            .prepare("SELECT extension_name FROM duckdb_extensions() ORDER BY extension_name")?
-            .query_map([], |row| row.get(0))?
-            .collect::<Result<Vec<_>, _>>()?;
+        let capabilities = Capabilities::detect(&self.conn)?;
+        let extensions: Vec<&str> = capabilities
+            .extensions
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect();
 
         info!("  Available Extensions: {}", extensions.join(", "));
+        info!(
+            "  Statically Linked: {}",
+            capabilities.statically_linked().join(", ")
+        );
+        info!("  Installed: {}", capabilities.loadable().join(", "));
+        let mark = |supported: bool| if supported { "✅" } else { "❌" };
+        info!(
+            "  ICU: collations {}, time zones {}",
+            mark(capabilities.icu_collations),
+            mark(capabilities.icu_time_zones)
+        );
+        info!(
+            "  Lakehouse: delta {}, iceberg {}",
+            mark(capabilities.is_available("delta")),
+            mark(capabilities.is_available("iceberg"))
+        );
 
         Ok(())
     }
 }
+
+/// Column names and rows collected from a query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryOutput {
+    /// Result column names, in order
+    pub columns: Vec<String>,
+    /// Result rows, one value per column
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl QueryOutput {
+    /// Formats the rows as aligned, human-readable columns.
+    pub fn to_table(&self) -> String {
+        self.to_table_highlighted(|_, _| false)
+    }
+
+    /// Formats the rows like [`QueryOutput::to_table`], rendering the cells
+    /// for which `highlight(row, column)` is true in bold yellow.
+    pub fn to_table_highlighted<F>(&self, highlight: F) -> String
+    where
+        F: Fn(usize, usize) -> bool,
+    {
+        let cells: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| row.iter().map(format_value).collect())
+            .collect();
+
+        let mut widths: Vec<usize> = self.columns.iter().map(|c| c.chars().count()).collect();
+        for row in &cells {
+            for (i, cell) in row.iter().enumerate() {
+                widths[i] = widths[i].max(cell.chars().count());
+            }
+        }
+
+        let format_row = |row: &[String], highlighted: &dyn Fn(usize) -> bool| {
+            row.iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(i, (cell, width))| {
+                    let padded = format!("{:<width$}", cell, width = width);
+                    if highlighted(i) {
+                        format!("\x1b[1;33m{}\x1b[0m", padded)
+                    } else {
+                        padded
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" | ")
+        };
+
+        let mut output = format_row(&self.columns, &|_| false);
+        output.push('\n');
+        output.push_str(
+            &widths
+                .iter()
+                .map(|w| "-".repeat(*w))
+                .collect::<Vec<_>>()
+                .join("-+-"),
+        );
+        for (r, row) in cells.iter().enumerate() {
+            output.push('\n');
+            output.push_str(&format_row(row, &|c| highlight(r, c)));
+        }
+        output.push_str(&format!("\n({} rows)", self.rows.len()));
+        output
+    }
+
+    /// Formats the rows as CSV with a header row.
+    pub fn to_csv(&self) -> String {
+        let mut output = self
+            .columns
+            .iter()
+            .map(|c| escape_csv(c))
+            .collect::<Vec<_>>()
+            .join(",");
+        for row in &self.rows {
+            output.push('\n');
+            output.push_str(
+                &row.iter()
+                    .map(|v| match v {
+                        Value::Null => String::new(),
+                        other => escape_csv(&format_value(other)),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(","),
+            );
+        }
+        output
+    }
+
+    /// Converts the rows to a JSON array of objects keyed by column name.
+    pub fn to_json(&self) -> serde_json::Value {
+        let records = self
+            .rows
+            .iter()
+            .map(|row| {
+                let object = self
+                    .columns
+                    .iter()
+                    .zip(row)
+                    .map(|(column, value)| (column.clone(), value_to_json(value)))
+                    .collect::<serde_json::Map<_, _>>();
+                serde_json::Value::Object(object)
+            })
+            .collect();
+        serde_json::Value::Array(records)
+    }
+}
+
+/// Formats a DuckDB value for display.
+pub fn format_value(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Boolean(v) => v.to_string(),
+        Value::TinyInt(v) => v.to_string(),
+        Value::SmallInt(v) => v.to_string(),
+        Value::Int(v) => v.to_string(),
+        Value::BigInt(v) => v.to_string(),
+        Value::HugeInt(v) => v.to_string(),
+        Value::UTinyInt(v) => v.to_string(),
+        Value::USmallInt(v) => v.to_string(),
+        Value::UInt(v) => v.to_string(),
+        Value::UBigInt(v) => v.to_string(),
+        Value::Float(v) => v.to_string(),
+        Value::Double(v) => v.to_string(),
+        Value::Decimal(v) => v.to_string(),
+        Value::Text(v) | Value::Enum(v) => v.clone(),
+        Value::List(items) | Value::Array(items) => format!(
+            "[{}]",
+            items.iter().map(format_value).collect::<Vec<_>>().join(", ")
+        ),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Converts a DuckDB value to the closest JSON representation.
+fn value_to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Boolean(v) => serde_json::Value::from(*v),
+        Value::TinyInt(v) => serde_json::Value::from(*v),
+        Value::SmallInt(v) => serde_json::Value::from(*v),
+        Value::Int(v) => serde_json::Value::from(*v),
+        Value::BigInt(v) => serde_json::Value::from(*v),
+        Value::UTinyInt(v) => serde_json::Value::from(*v),
+        Value::USmallInt(v) => serde_json::Value::from(*v),
+        Value::UInt(v) => serde_json::Value::from(*v),
+        Value::UBigInt(v) => serde_json::Value::from(*v),
+        Value::Float(v) => serde_json::Value::from(*v),
+        Value::Double(v) => serde_json::Value::from(*v),
+        Value::List(items) | Value::Array(items) => {
+            serde_json::Value::Array(items.iter().map(value_to_json).collect())
+        }
+        other => serde_json::Value::String(format_value(other)),
+    }
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,426 @@
+//! # Deduplication for Frozen DuckDB CLI
+//!
+//! This module removes duplicate rows from CSV, Parquet, and JSON datasets.
+//! Exact duplicates are found with SQL window functions over a set of key
+//! columns; optionally, near-duplicates among the remaining rows are found
+//! by embedding the key columns and comparing cosine similarity.
+//!
+//! Every dropped row is written to a report together with the reason it
+//! was dropped and the row it duplicates, so deduplication can be audited.
+//!
+//! ## Row Identity
+//!
+//! Rows are numbered in input order (`row_id`, starting at 1). The first
+//! occurrence of each duplicate group is kept.
+
+use super::embedding_index::Embedder;
+use super::sql_path::path_literal;
+use anyhow::{Context, Result};
+use duckdb::Connection;
+use std::path::Path;
+use tracing::info;
+
+/// Number of rows embedded per call during semantic deduplication.
+const EMBEDDING_BATCH_SIZE: usize = 256;
+
+/// Options controlling a deduplication run.
+#[derive(Debug, Clone)]
+pub struct DedupeOptions {
+    /// Columns that identify a row; rows with equal keys are duplicates
+    pub key_columns: Vec<String>,
+    /// Similarity threshold (0.0-1.0) for semantic near-duplicates,
+    /// `None` to only remove exact duplicates
+    pub semantic_threshold: Option<f32>,
+}
+
+/// Summary of a deduplication run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DedupeReport {
+    /// Rows in the input dataset
+    pub input_rows: usize,
+    /// Rows dropped as exact key duplicates
+    pub exact_duplicates: usize,
+    /// Rows dropped as semantic near-duplicates
+    pub semantic_duplicates: usize,
+    /// Rows written to the deduplicated output
+    pub output_rows: usize,
+}
+
+/// Removes duplicate rows from `input`, writing the kept rows to `output`
+/// and the dropped rows to `report`.
+///
+/// File formats are inferred from extensions (`.csv`, `.parquet`, `.json`).
+/// `embedder` is only used when `options.semantic_threshold` is set.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::dedupe::{dedupe, DedupeOptions};
+/// use frozen_duckdb::cli::embedding_index::FlockEmbedder;
+/// use frozen_duckdb::cli::{DatasetManager, FlockManager};
+///
+/// let manager = DatasetManager::new()?;
+/// let flock = FlockManager::new()?;
+/// let embedder = FlockEmbedder { manager: &flock, model: "embedder".to_string(), normalize: true };
+///
+/// let options = DedupeOptions {
+///     key_columns: vec!["title".to_string(), "author".to_string()],
+///     semantic_threshold: Some(0.95),
+/// };
+/// let report = dedupe(
+///     manager.connection(),
+///     "books.parquet",
+///     "books_deduped.parquet",
+///     "books_dropped.parquet",
+///     &options,
+///     Some(&embedder),
+/// )?;
+/// println!("Dropped {} rows", report.exact_duplicates + report.semantic_duplicates);
+/// ```
+///
+/// # Performance
+///
+/// - **Exact dedupe**: a single window-function pass, suitable for large files
+/// - **Semantic dedupe**: one embedding per surviving row plus O(n²) pairwise
+///   comparisons, so it is intended for datasets up to roughly 100K rows
+pub fn dedupe<E: Embedder>(
+    conn: &Connection,
+    input: &str,
+    output: &str,
+    report: &str,
+    options: &DedupeOptions,
+    embedder: Option<&E>,
+) -> Result<DedupeReport> {
+    if options.key_columns.is_empty() {
+        return Err(anyhow::anyhow!("At least one key column is required"));
+    }
+    if let Some(threshold) = options.semantic_threshold {
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(anyhow::anyhow!("Threshold must be between 0.0 and 1.0"));
+        }
+    }
+
+    info!(
+        "🧹 Deduplicating {} on ({})",
+        input,
+        options.key_columns.join(", ")
+    );
+
+    let reader = read_function(input)?;
+    let output_format = copy_format(output)?;
+    let report_format = copy_format(report)?;
+    let keys = options
+        .key_columns
+        .iter()
+        .map(|column| quote_identifier(column))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    conn.execute_batch(&format!(
+        "CREATE OR REPLACE TEMP TABLE dedupe_input AS
+             SELECT row_number() OVER () AS row_id, * FROM {reader};
+         CREATE OR REPLACE TEMP TABLE dedupe_dropped (
+             row_id BIGINT, reason VARCHAR, duplicate_of BIGINT, similarity DOUBLE
+         );
+         INSERT INTO dedupe_dropped
+             SELECT row_id, 'exact', kept_row_id, NULL FROM (
+                 SELECT row_id,
+                        row_number() OVER (PARTITION BY {keys} ORDER BY row_id) AS dup_rank,
+                        first_value(row_id) OVER (PARTITION BY {keys} ORDER BY row_id) AS kept_row_id
+                 FROM dedupe_input
+             ) WHERE dup_rank > 1;"
+    ))
+    .with_context(|| format!("Failed to find exact duplicates in {}", input))?;
+
+    let exact_duplicates = count(conn, "SELECT COUNT(*) FROM dedupe_dropped")?;
+    info!("✅ Found {} exact duplicates", exact_duplicates);
+
+    let semantic_duplicates = match (options.semantic_threshold, embedder) {
+        (Some(threshold), Some(embedder)) => {
+            find_semantic_duplicates(conn, &options.key_columns, threshold, embedder)?
+        }
+        (Some(_), None) => {
+            return Err(anyhow::anyhow!(
+                "Semantic deduplication requires an embedder"
+            ));
+        }
+        (None, _) => 0,
+    };
+
+    conn.execute_batch(&format!(
+        "COPY (
+             SELECT * EXCLUDE (row_id) FROM dedupe_input
+             WHERE row_id NOT IN (SELECT row_id FROM dedupe_dropped)
+             ORDER BY row_id
+         ) TO {output} ({output_format});
+         COPY (
+             SELECT d.reason, d.duplicate_of, d.similarity, i.*
+             FROM dedupe_dropped d JOIN dedupe_input i USING (row_id)
+             ORDER BY row_id
+         ) TO {report} ({report_format});",
+        output = path_literal(output),
+        report = path_literal(report),
+    ))
+    .context("Failed to write deduplicated output")?;
+
+    let input_rows = count(conn, "SELECT COUNT(*) FROM dedupe_input")?;
+    let result = DedupeReport {
+        input_rows,
+        exact_duplicates,
+        semantic_duplicates,
+        output_rows: input_rows - exact_duplicates - semantic_duplicates,
+    };
+
+    conn.execute_batch("DROP TABLE dedupe_input; DROP TABLE dedupe_dropped;")?;
+
+    info!(
+        "✅ Kept {} of {} rows, dropped rows reported in {}",
+        result.output_rows, result.input_rows, report
+    );
+    Ok(result)
+}
+
+/// Embeds the key columns of every surviving row and drops rows whose
+/// embedding is at least `threshold` similar to an earlier kept row.
+fn find_semantic_duplicates<E: Embedder>(
+    conn: &Connection,
+    key_columns: &[String],
+    threshold: f32,
+    embedder: &E,
+) -> Result<usize> {
+    let text = key_columns
+        .iter()
+        .map(|column| format!("CAST({} AS VARCHAR)", quote_identifier(column)))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT row_id, concat_ws(' ', {}) FROM dedupe_input
+         WHERE row_id NOT IN (SELECT row_id FROM dedupe_dropped)
+         ORDER BY row_id",
+        text
+    ))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<duckdb::Result<Vec<_>>>()?;
+
+    info!(
+        "🧠 Embedding {} rows for semantic deduplication",
+        rows.len()
+    );
+    let mut embeddings = Vec::with_capacity(rows.len());
+    for chunk in rows.chunks(EMBEDDING_BATCH_SIZE) {
+        let texts = chunk.iter().map(|(_, text)| text.clone()).collect();
+        embeddings.extend(embedder.embed_batch(texts)?);
+    }
+    if embeddings.len() != rows.len() {
+        return Err(anyhow::anyhow!(
+            "Expected {} embeddings but got {}",
+            rows.len(),
+            embeddings.len()
+        ));
+    }
+
+    let mut kept: Vec<(i64, &[f32])> = Vec::new();
+    let mut dropped = Vec::new();
+    for ((row_id, _), embedding) in rows.iter().zip(&embeddings) {
+        let best = kept
+            .iter()
+            .map(|(kept_id, kept_embedding)| {
+                (*kept_id, cosine_similarity(embedding, kept_embedding))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        match best {
+            Some((kept_id, similarity)) if similarity >= threshold => {
+                dropped.push((*row_id, kept_id, similarity));
+            }
+            _ => kept.push((*row_id, embedding.as_slice())),
+        }
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut insert = tx.prepare("INSERT INTO dedupe_dropped VALUES (?, 'semantic', ?, ?)")?;
+        for (row_id, kept_id, similarity) in &dropped {
+            insert.execute([
+                row_id.to_string(),
+                kept_id.to_string(),
+                similarity.to_string(),
+            ])?;
+        }
+    }
+    tx.commit()?;
+
+    info!("✅ Found {} semantic near-duplicates", dropped.len());
+    Ok(dropped.len())
+}
+
+/// Cosine similarity of two vectors, 0.0 if either has zero length.
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+fn count(conn: &Connection, sql: &str) -> Result<usize> {
+    let count: i64 = conn.query_row(sql, [], |row| row.get(0))?;
+    Ok(count as usize)
+}
+
+fn extension(path: &str) -> Result<String> {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .ok_or_else(|| anyhow::anyhow!("Cannot infer file format from: {}", path))
+}
+
+/// Returns the table function reading `path`, e.g. `read_parquet('data.parquet')`.
+pub(crate) fn read_function(path: &str) -> Result<String> {
+    match extension(path)?.as_str() {
+        "csv" => Ok(format!("read_csv({}, header=true)", path_literal(path))),
+        "parquet" => Ok(format!("read_parquet({})", path_literal(path))),
+        "json" => Ok(format!("read_json_auto({})", path_literal(path))),
+        other => Err(anyhow::anyhow!("Unsupported input format: {}", other)),
+    }
+}
+
+/// Returns the `COPY ... TO` options for writing `path`.
+pub(crate) fn copy_format(path: &str) -> Result<&'static str> {
+    match extension(path)?.as_str() {
+        "csv" => Ok("FORMAT CSV, HEADER"),
+        "parquet" => Ok("FORMAT PARQUET"),
+        "json" => Ok("FORMAT JSON"),
+        other => Err(anyhow::anyhow!("Unsupported output format: {}", other)),
+    }
+}
+
+pub(crate) fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.trim().replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Embeds texts by their first letter, so texts sharing it are identical.
+    struct FirstLetterEmbedder;
+
+    impl Embedder for FirstLetterEmbedder {
+        fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    let letter = text.bytes().next().unwrap_or(b'a') - b'a';
+                    let mut embedding = vec![0.0; 26];
+                    embedding[letter as usize % 26] = 1.0;
+                    embedding
+                })
+                .collect())
+        }
+    }
+
+    fn write_input(dir: &Path) -> String {
+        let input = dir.join("books.csv");
+        fs::write(
+            &input,
+            "title,author,year\n\
+             dune,herbert,1965\n\
+             dune,herbert,1984\n\
+             emma,austen,1815\n\
+             dune,lynch,1984\n\
+             eden,lem,1959\n",
+        )
+        .unwrap();
+        input.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_exact_dedupe_keeps_first_occurrence() {
+        let temp = tempfile::tempdir().unwrap();
+        let input = write_input(temp.path());
+        let output = temp.path().join("out.csv");
+        let report = temp.path().join("dropped.csv");
+        let conn = Connection::open_in_memory().unwrap();
+
+        let options = DedupeOptions {
+            key_columns: vec!["title".to_string(), "author".to_string()],
+            semantic_threshold: None,
+        };
+        let result = dedupe::<FirstLetterEmbedder>(
+            &conn,
+            &input,
+            output.to_str().unwrap(),
+            report.to_str().unwrap(),
+            &options,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(result.input_rows, 5);
+        assert_eq!(result.exact_duplicates, 1);
+        assert_eq!(result.output_rows, 4);
+
+        let kept = fs::read_to_string(&output).unwrap();
+        assert!(kept.contains("dune,herbert,1965"));
+        assert!(!kept.contains("dune,herbert,1984"));
+
+        let dropped = fs::read_to_string(&report).unwrap();
+        assert!(dropped.starts_with("reason,duplicate_of,similarity,row_id,title"));
+        assert!(dropped.contains("exact,1,,2,dune,herbert,1984"));
+    }
+
+    #[test]
+    fn test_semantic_dedupe_drops_similar_rows() {
+        let temp = tempfile::tempdir().unwrap();
+        let input = write_input(temp.path());
+        let output = temp.path().join("out.parquet");
+        let report = temp.path().join("dropped.csv");
+        let conn = Connection::open_in_memory().unwrap();
+
+        let options = DedupeOptions {
+            key_columns: vec!["title".to_string()],
+            semantic_threshold: Some(0.95),
+        };
+        let result = dedupe(
+            &conn,
+            &input,
+            output.to_str().unwrap(),
+            report.to_str().unwrap(),
+            &options,
+            Some(&FirstLetterEmbedder),
+        )
+        .unwrap();
+
+        // "dune" x2 are exact duplicates of row 1; "eden" and "emma" share a first letter
+        assert_eq!(result.exact_duplicates, 2);
+        assert_eq!(result.semantic_duplicates, 1);
+        assert_eq!(result.output_rows, 2);
+
+        let dropped = fs::read_to_string(&report).unwrap();
+        assert!(dropped.contains("semantic,3,"));
+        assert!(dropped.contains(",5,eden,lem,1959"));
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[1.0, 0.0]), 1.0);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn test_quote_identifier_escapes_quotes() {
+        assert_eq!(quote_identifier("name"), "\"name\"");
+        assert_eq!(quote_identifier("odd\"col"), "\"odd\"\"col\"");
+    }
+}
@@ -0,0 +1,487 @@
+//! # Embedding Backends for Frozen DuckDB CLI
+//!
+//! The index, search, and dedupe commands only depend on the
+//! [`Embedder`] trait. This module provides the backends behind it and
+//! selects one from the `embedding` section of the CLI configuration, so
+//! embeddings can come from somewhere other than Flock and Ollama.
+//!
+//! | Backend | `--model` names | Requirements |
+//! |---------|-----------------|--------------|
+//! | `flock` (default) | Flock model alias, e.g. `embedder` | `flock-setup` |
+//! | `http` | Provider model, e.g. `text-embedding-3-small` | OpenAI-compatible `/embeddings` API |
+//! | `onnx` | fastembed model code, e.g. `Xenova/all-MiniLM-L6-v2` | Built with the `onnx` feature |
+//!
+//! ## Configuration
+//!
+//! ```json
+//! {
+//!   "embedding": {
+//!     "backend": "http",
+//!     "url": "https://api.openai.com/v1",
+//!     "api_key_env": "OPENAI_API_KEY",
+//!     "timeout_secs": 60
+//!   }
+//! }
+//! ```
+//!
+//! The API key is read from the environment variable named by
+//! `api_key_env` (default [`EMBEDDING_API_KEY_ENV`]) and is never stored in
+//! the config file. The `onnx` backend downloads models on first use into
+//! `cache_dir`, or fastembed's default cache when unset.
+//!
+//! Index metadata records the model name, so an index built with one
+//! backend can only be searched with the same model.
+
+use super::embedding_index::{Embedder, FlockEmbedder};
+use super::flock_manager::FlockManager;
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Environment variable holding the API key for the `http` backend.
+pub const EMBEDDING_API_KEY_ENV: &str = "FROZEN_DUCKDB_EMBEDDING_API_KEY";
+
+/// Default request timeout for the `http` backend.
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+/// Service that produces embeddings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmbeddingBackend {
+    /// Flock's `llm_embedding`, usually served by Ollama
+    #[default]
+    Flock,
+    /// OpenAI-compatible `/embeddings` HTTP API
+    Http,
+    /// Local ONNX model run in-process by fastembed
+    Onnx,
+}
+
+impl EmbeddingBackend {
+    /// Parses a backend name as used in the config file.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "flock" => Ok(Self::Flock),
+            "http" => Ok(Self::Http),
+            "onnx" => Ok(Self::Onnx),
+            other => Err(anyhow::anyhow!(
+                "Unknown embedding backend: {} (use flock, http, or onnx)",
+                other
+            )),
+        }
+    }
+
+    /// Name of the backend as used in the config file.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Flock => "flock",
+            Self::Http => "http",
+            Self::Onnx => "onnx",
+        }
+    }
+}
+
+/// Embedding settings from the `embedding` config section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddingConfig {
+    /// Service producing the embeddings
+    pub backend: EmbeddingBackend,
+    /// Base URL of the `http` backend, e.g. `https://api.openai.com/v1`
+    pub url: Option<String>,
+    /// Environment variable holding the `http` backend's API key
+    pub api_key_env: String,
+    /// Request timeout for the `http` backend
+    pub timeout_secs: u64,
+    /// Model download directory for the `onnx` backend
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl Default for EmbeddingConfig {
+    fn default() -> Self {
+        Self {
+            backend: EmbeddingBackend::default(),
+            url: None,
+            api_key_env: EMBEDDING_API_KEY_ENV.to_string(),
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            cache_dir: None,
+        }
+    }
+}
+
+impl EmbeddingConfig {
+    /// Reads settings from the `embedding` config section.
+    pub fn from_section(section: &Map<String, Value>) -> Result<Self> {
+        let mut config = Self::default();
+        let field = |key: &str| -> Result<Option<&str>> {
+            match section.get(key) {
+                None => Ok(None),
+                Some(value) => value
+                    .as_str()
+                    .map(Some)
+                    .ok_or_else(|| anyhow::anyhow!("embedding.{} must be a string", key)),
+            }
+        };
+
+        if let Some(backend) = field("backend")? {
+            config.backend = EmbeddingBackend::parse(backend)?;
+        }
+        config.url = field("url")?.map(str::to_string);
+        if let Some(api_key_env) = field("api_key_env")? {
+            config.api_key_env = api_key_env.to_string();
+        }
+        if let Some(value) = section.get("timeout_secs") {
+            config.timeout_secs = value.as_u64().filter(|secs| *secs > 0).ok_or_else(|| {
+                anyhow::anyhow!("embedding.timeout_secs must be a positive integer")
+            })?;
+        }
+        config.cache_dir = field("cache_dir")?.map(PathBuf::from);
+
+        if config.backend == EmbeddingBackend::Http && config.url.is_none() {
+            return Err(anyhow::anyhow!(
+                "embedding.url is required for the http backend"
+            ));
+        }
+        Ok(config)
+    }
+
+    /// Creates the configured embedder for `model`.
+    ///
+    /// `flock` is only used by the `flock` backend and must be a ready
+    /// manager when that backend is selected.
+    pub fn embedder<'a>(
+        &self,
+        flock: Option<&'a FlockManager>,
+        model: String,
+        normalize: bool,
+    ) -> Result<Box<dyn Embedder + 'a>> {
+        match self.backend {
+            EmbeddingBackend::Flock => {
+                let manager = flock.ok_or_else(|| {
+                    anyhow::anyhow!("The flock embedding backend requires the Flock extension")
+                })?;
+                Ok(Box::new(FlockEmbedder {
+                    manager,
+                    model,
+                    normalize,
+                }))
+            }
+            EmbeddingBackend::Http => Ok(Box::new(HttpEmbedder {
+                url: self.url.clone().unwrap_or_default(),
+                model,
+                api_key: std::env::var(&self.api_key_env).ok(),
+                normalize,
+                timeout: Duration::from_secs(self.timeout_secs),
+            })),
+            EmbeddingBackend::Onnx => Ok(Box::new(OnnxEmbedder::new(
+                &model,
+                self.cache_dir.clone(),
+                normalize,
+            )?)),
+        }
+    }
+}
+
+/// Embedder calling an OpenAI-compatible `/embeddings` endpoint.
+///
+/// Works with OpenAI, Azure OpenAI deployments behind a compatible proxy,
+/// vLLM, LM Studio, and Ollama's `/v1` API.
+pub struct HttpEmbedder {
+    /// Base URL of the API, with or without the `/embeddings` suffix
+    pub url: String,
+    /// Provider model name sent with every request
+    pub model: String,
+    /// Bearer token, if the API requires one
+    pub api_key: Option<String>,
+    /// Normalize embeddings to unit length
+    pub normalize: bool,
+    /// Timeout for each request
+    pub timeout: Duration,
+}
+
+impl HttpEmbedder {
+    /// Full URL of the embeddings endpoint.
+    pub fn endpoint(&self) -> String {
+        let url = self.url.trim_end_matches('/');
+        if url.ends_with("/embeddings") {
+            url.to_string()
+        } else {
+            format!("{}/embeddings", url)
+        }
+    }
+}
+
+impl Embedder for HttpEmbedder {
+    fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let endpoint = self.endpoint();
+        let body = serde_json::json!({ "model": self.model, "input": texts });
+
+        let agent = ureq::AgentBuilder::new().timeout(self.timeout).build();
+        let mut request = agent
+            .post(&endpoint)
+            .set("Content-Type", "application/json");
+        if let Some(api_key) = &self.api_key {
+            request = request.set("Authorization", &format!("Bearer {}", api_key));
+        }
+        let response = match request.send_string(&body.to_string()) {
+            Ok(response) => response,
+            Err(ureq::Error::Status(code, response)) => {
+                let detail = response.into_string().unwrap_or_default();
+                anyhow::bail!("HTTP error {} from {}: {}", code, endpoint, detail.trim())
+            }
+            Err(e) => return Err(e).with_context(|| format!("Failed to call {}", endpoint)),
+        };
+
+        let body: Value = serde_json::from_str(
+            &response
+                .into_string()
+                .context("Failed to read embeddings response")?,
+        )
+        .with_context(|| format!("Invalid JSON from {}", endpoint))?;
+        let mut embeddings = parse_embeddings_response(&body, texts.len())?;
+        if self.normalize {
+            for embedding in &mut embeddings {
+                normalize(embedding);
+            }
+        }
+        Ok(embeddings)
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    fn normalizes(&self) -> bool {
+        self.normalize
+    }
+}
+
+/// Extracts embeddings from an OpenAI-style response, in input order.
+///
+/// Entries are ordered by their `index` field when present, since the API
+/// does not guarantee that `data` is in request order.
+pub fn parse_embeddings_response(body: &Value, expected: usize) -> Result<Vec<Vec<f32>>> {
+    let data = body
+        .get("data")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow::anyhow!("Embeddings response has no data array"))?;
+    if data.len() != expected {
+        anyhow::bail!(
+            "Embeddings response has {} entries for {} inputs",
+            data.len(),
+            expected
+        );
+    }
+
+    let mut embeddings: Vec<Option<Vec<f32>>> = vec![None; expected];
+    for (position, entry) in data.iter().enumerate() {
+        let index = match entry.get("index") {
+            Some(index) => index
+                .as_u64()
+                .map(|index| index as usize)
+                .filter(|index| *index < expected)
+                .ok_or_else(|| anyhow::anyhow!("Invalid embedding index: {}", index))?,
+            None => position,
+        };
+        let embedding = entry
+            .get("embedding")
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow::anyhow!("Embedding {} is not an array", index))?
+            .iter()
+            .map(|x| {
+                x.as_f64()
+                    .map(|x| x as f32)
+                    .ok_or_else(|| anyhow::anyhow!("Embedding {} has a non-numeric value", index))
+            })
+            .collect::<Result<Vec<f32>>>()?;
+        if embeddings[index].replace(embedding).is_some() {
+            anyhow::bail!("Embeddings response repeats index {}", index);
+        }
+    }
+    Ok(embeddings.into_iter().flatten().collect())
+}
+
+/// Scales `embedding` to unit length, leaving zero vectors unchanged.
+fn normalize(embedding: &mut [f32]) {
+    let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        embedding.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+/// Embedder running a local ONNX model through fastembed.
+#[cfg(feature = "onnx")]
+pub struct OnnxEmbedder {
+    model: String,
+    normalize: bool,
+    embedding: fastembed::TextEmbedding,
+}
+
+#[cfg(feature = "onnx")]
+impl OnnxEmbedder {
+    /// Loads `model`, downloading it into `cache_dir` on first use.
+    pub fn new(model: &str, cache_dir: Option<PathBuf>, normalize: bool) -> Result<Self> {
+        let name: fastembed::EmbeddingModel = model.parse().map_err(|e: String| {
+            anyhow::anyhow!(
+                "{} (e.g. Xenova/all-MiniLM-L6-v2, BAAI/bge-small-en-v1.5)",
+                e
+            )
+        })?;
+        let mut options = fastembed::InitOptions::new(name).with_show_download_progress(false);
+        if let Some(cache_dir) = cache_dir {
+            options = options.with_cache_dir(cache_dir);
+        }
+        let embedding = fastembed::TextEmbedding::try_new(options)
+            .with_context(|| format!("Failed to load ONNX model {}", model))?;
+        Ok(Self {
+            model: model.to_string(),
+            normalize,
+            embedding,
+        })
+    }
+}
+
+#[cfg(feature = "onnx")]
+impl Embedder for OnnxEmbedder {
+    fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let mut embeddings = self.embedding.embed(texts, None)?;
+        if self.normalize {
+            for embedding in &mut embeddings {
+                normalize(embedding);
+            }
+        }
+        Ok(embeddings)
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    fn normalizes(&self) -> bool {
+        self.normalize
+    }
+}
+
+/// Placeholder used when the CLI is built without the `onnx` feature.
+#[cfg(not(feature = "onnx"))]
+pub struct OnnxEmbedder;
+
+#[cfg(not(feature = "onnx"))]
+impl OnnxEmbedder {
+    /// Always fails: ONNX support was not compiled in.
+    pub fn new(model: &str, _cache_dir: Option<PathBuf>, _normalize: bool) -> Result<Self> {
+        anyhow::bail!(
+            "Cannot load ONNX model {}: frozen-duckdb was built without the `onnx` feature",
+            model
+        )
+    }
+}
+
+#[cfg(not(feature = "onnx"))]
+impl Embedder for OnnxEmbedder {
+    fn embed_batch(&self, _texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        anyhow::bail!("frozen-duckdb was built without the `onnx` feature")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(json: &str) -> Map<String, Value> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_config_defaults_to_flock() {
+        let config = EmbeddingConfig::from_section(&Map::new()).unwrap();
+        assert_eq!(config, EmbeddingConfig::default());
+        assert_eq!(config.backend, EmbeddingBackend::Flock);
+        assert_eq!(config.api_key_env, EMBEDDING_API_KEY_ENV);
+    }
+
+    #[test]
+    fn test_config_http_backend() {
+        let config = EmbeddingConfig::from_section(&section(
+            r#"{"backend": "http", "url": "http://localhost:8000/v1",
+                "api_key_env": "OPENAI_API_KEY", "timeout_secs": 5}"#,
+        ))
+        .unwrap();
+        assert_eq!(config.backend, EmbeddingBackend::Http);
+        assert_eq!(config.url.as_deref(), Some("http://localhost:8000/v1"));
+        assert_eq!(config.api_key_env, "OPENAI_API_KEY");
+        assert_eq!(config.timeout_secs, 5);
+
+        assert!(EmbeddingConfig::from_section(&section(r#"{"backend": "http"}"#)).is_err());
+        assert!(EmbeddingConfig::from_section(&section(r#"{"backend": "grpc"}"#)).is_err());
+        assert!(EmbeddingConfig::from_section(&section(r#"{"timeout_secs": 0}"#)).is_err());
+    }
+
+    #[test]
+    fn test_flock_backend_requires_manager() {
+        let config = EmbeddingConfig::default();
+        assert!(config.embedder(None, "embedder".to_string(), true).is_err());
+    }
+
+    #[test]
+    fn test_http_endpoint() {
+        let mut embedder = HttpEmbedder {
+            url: "https://api.openai.com/v1/".to_string(),
+            model: "text-embedding-3-small".to_string(),
+            api_key: None,
+            normalize: false,
+            timeout: Duration::from_secs(1),
+        };
+        assert_eq!(embedder.endpoint(), "https://api.openai.com/v1/embeddings");
+        embedder.url = "http://localhost:11434/v1/embeddings".to_string();
+        assert_eq!(embedder.endpoint(), "http://localhost:11434/v1/embeddings");
+        assert_eq!(embedder.model_name(), "text-embedding-3-small");
+    }
+
+    #[test]
+    fn test_parse_embeddings_response_orders_by_index() {
+        let body = serde_json::json!({
+            "object": "list",
+            "data": [
+                {"object": "embedding", "index": 1, "embedding": [0.0, 1.0]},
+                {"object": "embedding", "index": 0, "embedding": [1.0, 0.5]},
+            ],
+            "model": "text-embedding-3-small"
+        });
+        assert_eq!(
+            parse_embeddings_response(&body, 2).unwrap(),
+            vec![vec![1.0, 0.5], vec![0.0, 1.0]]
+        );
+    }
+
+    #[test]
+    fn test_parse_embeddings_response_rejects_malformed() {
+        let missing = serde_json::json!({"error": {"message": "bad key"}});
+        assert!(parse_embeddings_response(&missing, 1).is_err());
+
+        let short = serde_json::json!({"data": [{"index": 0, "embedding": [1.0]}]});
+        assert!(parse_embeddings_response(&short, 2).is_err());
+
+        let repeated = serde_json::json!({"data": [
+            {"index": 0, "embedding": [1.0]},
+            {"index": 0, "embedding": [2.0]},
+        ]});
+        assert!(parse_embeddings_response(&repeated, 2).is_err());
+
+        let text = serde_json::json!({"data": [{"embedding": ["a"]}]});
+        assert!(parse_embeddings_response(&text, 1).is_err());
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut embedding = vec![3.0, 4.0];
+        normalize(&mut embedding);
+        assert_eq!(embedding, vec![0.6, 0.8]);
+
+        let mut zero = vec![0.0, 0.0];
+        normalize(&mut zero);
+        assert_eq!(zero, vec![0.0, 0.0]);
+    }
+}
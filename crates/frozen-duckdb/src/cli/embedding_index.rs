@@ -0,0 +1,1207 @@
+//! # Embedding Index for Frozen DuckDB CLI
+//!
+//! This module builds a persistent embedding index for a document corpus.
+//! Documents are embedded in batches and each batch is committed together
+//! with a checkpoint of its document ids, so an interrupted run over a large
+//! corpus can be resumed with `--resume` instead of starting over.
+//!
+//! ## Index Schema
+//!
+//! ```text
+//! embeddings       (doc_id VARCHAR PRIMARY KEY, content VARCHAR, embedding FLOAT[])
+//! index_checkpoint (doc_id VARCHAR PRIMARY KEY, batch INTEGER, indexed_at TIMESTAMP)
+//! index_metadata   (model VARCHAR, dimension BIGINT, normalized BOOLEAN, created_at TIMESTAMP)
+//! document_metadata (doc_id VARCHAR, key VARCHAR, value VARCHAR)
+//! ```
+//!
+//! Embeddings and checkpoints are written in the same transaction, so a
+//! checkpointed document always has its embedding stored.
+//!
+//! ## Model Compatibility
+//!
+//! Similarity between embeddings from different models (or with different
+//! normalization) is meaningless, even when the dimensions happen to match.
+//! The first batch records the embedding model, dimension, and
+//! normalization in `index_metadata`; resuming and searching with a
+//! different embedder fails with an error naming both models.
+//!
+//! ## Keyword Search
+//!
+//! [`EmbeddingIndex::keyword_search`] ranks the same documents by BM25
+//! with DuckDB's `fts` extension, for hybrid search fused with the vector
+//! ranking (see [`fusion`](super::fusion)). The full-text index is rebuilt
+//! on each keyword search, so it always covers every indexed document.
+//!
+//! ## Document Metadata
+//!
+//! Each document carries key/value metadata, stored in
+//! `document_metadata` with its embedding. [`load_corpus`] records the
+//! source `file`, its `format` and `modified` date, and the `title`,
+//! `heading`, or `page` of loaded sections; `index --metadata key=value`
+//! adds more. Both searches take [metadata filters](super::metadata_filter)
+//! that restrict them to matching documents.
+
+use super::flock_manager::{embedding_from_value, FlockManager};
+use super::loaders::{load_document, DocumentFormat};
+use super::metadata_filter::{filter_sql, MetadataFilter};
+use crate::text::chunk::Chunker;
+use anyhow::{Context, Result};
+use duckdb::arrow::array::AsArray;
+use duckdb::arrow::datatypes::Float32Type;
+use duckdb::Connection;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// A single document to be embedded.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Document {
+    /// Stable identifier used for checkpointing
+    pub id: String,
+    /// Text content to embed
+    pub content: String,
+    /// Key/value metadata for filtering searches
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Produces embeddings for a batch of texts.
+///
+/// Implemented by [`FlockEmbedder`] and the backends in
+/// [`embedding_backends`](super::embedding_backends).
+pub trait Embedder {
+    /// Returns one embedding per input text, in input order.
+    fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>>;
+
+    /// Name of the model producing the embeddings, recorded in index metadata.
+    fn model_name(&self) -> &str {
+        "unknown"
+    }
+
+    /// Whether embeddings are normalized to unit length.
+    fn normalizes(&self) -> bool {
+        false
+    }
+}
+
+impl<E: Embedder + ?Sized> Embedder for Box<E> {
+    fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        (**self).embed_batch(texts)
+    }
+
+    fn model_name(&self) -> &str {
+        (**self).model_name()
+    }
+
+    fn normalizes(&self) -> bool {
+        (**self).normalizes()
+    }
+}
+
+/// [`FlockManager`] bound to a model, usable as an [`Embedder`].
+pub struct FlockEmbedder<'a> {
+    /// Flock manager used to call `llm_embedding`
+    pub manager: &'a FlockManager,
+    /// Model alias configured during flock-setup
+    pub model: String,
+    /// Normalize embeddings to unit length
+    pub normalize: bool,
+}
+
+impl Embedder for FlockEmbedder<'_> {
+    fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        Ok(self
+            .manager
+            .generate_embeddings(texts, &self.model, self.normalize)?)
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    fn normalizes(&self) -> bool {
+        self.normalize
+    }
+}
+
+/// Embedding model recorded for an index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddingMetadata {
+    /// Model alias the embeddings were generated with
+    pub model: String,
+    /// Number of dimensions of every embedding
+    pub dimension: usize,
+    /// Whether embeddings are normalized to unit length
+    pub normalized: bool,
+}
+
+impl EmbeddingMetadata {
+    /// Checks that embeddings described by `other` can be compared with these.
+    pub fn check_compatible(&self, other: &EmbeddingMetadata) -> Result<()> {
+        if self.model != other.model {
+            return Err(anyhow::anyhow!(
+                "Embedding model mismatch: index was built with '{}' but '{}' was used; \
+                 rerun with --model {}",
+                self.model,
+                other.model,
+                self.model
+            ));
+        }
+        if self.dimension != other.dimension {
+            return Err(anyhow::anyhow!(
+                "Embedding dimension mismatch: index has {} dimensions but model '{}' produced {}",
+                self.dimension,
+                other.model,
+                other.dimension
+            ));
+        }
+        if self.normalized != other.normalized {
+            return Err(anyhow::anyhow!(
+                "Embedding normalization mismatch: index embeddings are {}normalized",
+                if self.normalized { "" } else { "not " }
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Options controlling an indexing run.
+#[derive(Debug, Clone)]
+pub struct IndexOptions {
+    /// Number of documents embedded and committed per batch
+    pub batch_size: usize,
+    /// Continue from the last checkpoint instead of requiring an empty index
+    pub resume: bool,
+}
+
+impl Default for IndexOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 256,
+            resume: false,
+        }
+    }
+}
+
+/// Summary of an indexing run.
+#[derive(Debug, Clone)]
+pub struct IndexReport {
+    /// Documents in the corpus
+    pub total_documents: usize,
+    /// Documents skipped because an earlier run already indexed them
+    pub skipped_documents: usize,
+    /// Documents embedded during this run
+    pub indexed_documents: usize,
+    /// Batches committed during this run
+    pub batches: usize,
+    /// Wall-clock time spent embedding and committing
+    pub elapsed: Duration,
+}
+
+impl IndexReport {
+    /// Embedding throughput of this run in embeddings per second.
+    pub fn embeddings_per_sec(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.indexed_documents as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Persistent, resumable embedding index stored in a DuckDB database.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::embedding_index::{load_corpus, EmbeddingIndex, FlockEmbedder, IndexOptions};
+/// use frozen_duckdb::cli::FlockManager;
+///
+/// let manager = FlockManager::new()?;
+/// let embedder = FlockEmbedder { manager: &manager, model: "embedder".to_string(), normalize: true };
+///
+/// let index = EmbeddingIndex::open("embeddings.duckdb")?;
+/// let documents = load_corpus("documents.txt")?;
+/// let options = IndexOptions { batch_size: 512, resume: true };
+///
+/// let report = index.build(&documents, &embedder, &options)?;
+/// println!("{:.1} embeddings/sec", report.embeddings_per_sec());
+/// ```
+pub struct EmbeddingIndex {
+    /// Connection to the index database
+    conn: Connection,
+}
+
+impl EmbeddingIndex {
+    /// Opens (or creates) an index database at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open index database: {}", path.display()))?;
+        Self::with_connection(conn)
+    }
+
+    /// Creates an index in an in-memory database.
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS embeddings (
+                 doc_id VARCHAR PRIMARY KEY,
+                 content VARCHAR,
+                 embedding FLOAT[]
+             );
+             CREATE TABLE IF NOT EXISTS index_checkpoint (
+                 doc_id VARCHAR PRIMARY KEY,
+                 batch INTEGER,
+                 indexed_at TIMESTAMP DEFAULT current_timestamp
+             );
+             CREATE TABLE IF NOT EXISTS index_metadata (
+                 model VARCHAR,
+                 dimension BIGINT,
+                 normalized BOOLEAN,
+                 created_at TIMESTAMP DEFAULT current_timestamp
+             );
+             CREATE TABLE IF NOT EXISTS document_metadata (
+                 doc_id VARCHAR,
+                 key VARCHAR,
+                 value VARCHAR
+             );",
+        )
+        .context("Failed to create index schema")?;
+
+        Ok(Self { conn })
+    }
+
+    /// Returns the ids of all checkpointed documents.
+    pub fn indexed_ids(&self) -> Result<HashSet<String>> {
+        let mut stmt = self.conn.prepare("SELECT doc_id FROM index_checkpoint")?;
+        let ids = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<duckdb::Result<HashSet<_>>>()?;
+        Ok(ids)
+    }
+
+    /// Returns the number of documents stored in the index.
+    pub fn document_count(&self) -> Result<usize> {
+        let count: i64 =
+            self.conn
+                .query_row("SELECT COUNT(*) FROM index_checkpoint", [], |row| {
+                    row.get(0)
+                })?;
+        Ok(count as usize)
+    }
+
+    /// Returns the embedding model recorded for this index, if any.
+    pub fn metadata(&self) -> Result<Option<EmbeddingMetadata>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT model, dimension, normalized FROM index_metadata LIMIT 1")?;
+        let mut rows = stmt.query([])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(EmbeddingMetadata {
+                model: row.get(0)?,
+                dimension: row.get::<_, i64>(1)? as usize,
+                normalized: row.get(2)?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// Records the embedding model on first use, or checks it matches the recorded one.
+    pub fn ensure_metadata(&self, metadata: &EmbeddingMetadata) -> Result<()> {
+        match self.metadata()? {
+            Some(recorded) => recorded.check_compatible(metadata),
+            None => {
+                self.conn.execute(
+                    "INSERT INTO index_metadata (model, dimension, normalized) VALUES (?, ?, ?)",
+                    [
+                        metadata.model.as_str(),
+                        &metadata.dimension.to_string(),
+                        &metadata.normalized.to_string(),
+                    ],
+                )?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the connection to the index database.
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Returns the distinct metadata keys of indexed documents, sorted.
+    pub fn metadata_keys(&self) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT DISTINCT key FROM document_metadata ORDER BY key")?;
+        let keys = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<duckdb::Result<Vec<_>>>()?;
+        Ok(keys)
+    }
+
+    /// Returns the metadata stored for document `doc_id`.
+    pub fn document_metadata(&self, doc_id: &str) -> Result<BTreeMap<String, String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT key, value FROM document_metadata WHERE doc_id = ?")?;
+        let metadata = stmt
+            .query_map([doc_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<duckdb::Result<BTreeMap<_, _>>>()?;
+        Ok(metadata)
+    }
+
+    /// Warns about filters on keys no indexed document has, which match
+    /// nothing (or everything, for `!=`).
+    fn warn_unknown_keys(&self, filters: &[MetadataFilter]) -> Result<()> {
+        if filters.is_empty() {
+            return Ok(());
+        }
+        let keys = self.metadata_keys()?;
+        for filter in filters {
+            if !keys.contains(&filter.key) {
+                warn!(
+                    "⚠️  No indexed document has metadata '{}' (known keys: {})",
+                    filter.key,
+                    if keys.is_empty() {
+                        "none".to_string()
+                    } else {
+                        keys.join(", ")
+                    }
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns every document id with its embedding, ordered by id.
+    ///
+    /// Vectors are read through Arrow record batches rather than row by
+    /// row, which is considerably faster for large indexes.
+    pub fn vectors(&self) -> Result<(Vec<String>, Vec<Vec<f32>>)> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT doc_id, embedding FROM embeddings ORDER BY doc_id")?;
+        let mut ids = Vec::new();
+        let mut vectors = Vec::new();
+        for batch in stmt.query_arrow([])? {
+            let id_column = batch.column(0).as_string::<i32>();
+            let embedding_column = batch.column(1).as_list::<i32>();
+            for row in 0..batch.num_rows() {
+                ids.push(id_column.value(row).to_string());
+                let values = embedding_column.value(row);
+                vectors.push(values.as_primitive::<Float32Type>().values().to_vec());
+            }
+        }
+        Ok((ids, vectors))
+    }
+
+    /// Returns stored documents with their embeddings, ordered by id.
+    ///
+    /// With `limit`, only the first `limit` documents are read.
+    pub fn embeddings(&self, limit: Option<usize>) -> Result<Vec<(Document, Vec<f32>)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT doc_id, content, embedding FROM embeddings ORDER BY doc_id LIMIT CAST(? AS BIGINT)",
+        )?;
+        let limit = limit.map_or(i64::MAX, |n| n as i64);
+        let rows = stmt
+            .query_map([limit], |row| {
+                Ok((
+                    Document {
+                        id: row.get(0)?,
+                        content: row.get(1)?,
+                        ..Default::default()
+                    },
+                    row.get::<_, duckdb::types::Value>(2)?,
+                ))
+            })?
+            .collect::<duckdb::Result<Vec<_>>>()?;
+        rows.into_iter()
+            .map(|(document, value)| Ok((document, embedding_from_value(value)?)))
+            .collect()
+    }
+
+    /// Returns the documents most similar to `query`, as (content, similarity) pairs.
+    ///
+    /// The query is embedded with `embedder`, which must match the model,
+    /// dimension, and normalization recorded for the index.
+    pub fn search<E: Embedder>(
+        &self,
+        query: &str,
+        embedder: &E,
+        threshold: f32,
+        limit: usize,
+    ) -> Result<Vec<(String, f32)>> {
+        self.search_filtered(query, embedder, threshold, limit, &[])
+    }
+
+    /// Like [`search`](Self::search), ranking only documents whose metadata
+    /// matches every filter.
+    pub fn search_filtered<E: Embedder>(
+        &self,
+        query: &str,
+        embedder: &E,
+        threshold: f32,
+        limit: usize,
+        filters: &[MetadataFilter],
+    ) -> Result<Vec<(String, f32)>> {
+        self.ranked("content", query, embedder, threshold, limit, filters)
+    }
+
+    /// Like [`search_filtered`](Self::search_filtered), returning document
+    /// ids instead of content, e.g. to compare with relevance labels.
+    pub fn search_ids<E: Embedder>(
+        &self,
+        query: &str,
+        embedder: &E,
+        threshold: f32,
+        limit: usize,
+        filters: &[MetadataFilter],
+    ) -> Result<Vec<(String, f32)>> {
+        self.ranked("doc_id", query, embedder, threshold, limit, filters)
+    }
+
+    /// Ranks documents by similarity to `query`, returning `column` and the
+    /// similarity of each.
+    fn ranked<E: Embedder>(
+        &self,
+        column: &str,
+        query: &str,
+        embedder: &E,
+        threshold: f32,
+        limit: usize,
+        filters: &[MetadataFilter],
+    ) -> Result<Vec<(String, f32)>> {
+        let recorded = self.metadata()?.ok_or_else(|| {
+            anyhow::anyhow!("Index is empty; build it with the index command first")
+        })?;
+        if recorded.model != embedder.model_name() || recorded.normalized != embedder.normalizes() {
+            // Fail before calling the model
+            recorded.check_compatible(&EmbeddingMetadata {
+                model: embedder.model_name().to_string(),
+                dimension: recorded.dimension,
+                normalized: embedder.normalizes(),
+            })?;
+        }
+
+        let query_embedding = embedder
+            .embed_batch(vec![query.to_string()])?
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("Embedder returned no embedding for the query"))?;
+        recorded.check_compatible(&metadata_for(embedder, &[query_embedding.clone()])?)?;
+        self.warn_unknown_keys(filters)?;
+
+        let (condition, filter_params) = filter_sql(filters, "e.doc_id");
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {column}, score FROM (
+                 SELECT {column}, list_cosine_similarity(embedding, CAST(? AS FLOAT[])) AS score
+                 FROM embeddings e
+                 WHERE {condition}
+             )
+             WHERE score >= CAST(? AS FLOAT)
+             ORDER BY score DESC
+             LIMIT CAST(? AS BIGINT)"
+        ))?;
+        let mut params = vec![format_embedding(&query_embedding)];
+        params.extend(filter_params);
+        params.extend([threshold.to_string(), limit.to_string()]);
+        let results = stmt
+            .query_map(duckdb::params_from_iter(&params), |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, f32>(1)?))
+            })?
+            .collect::<duckdb::Result<Vec<_>>>()?;
+        Ok(results)
+    }
+
+    /// Returns the documents matching `query` by keyword, as (content, BM25
+    /// score) pairs, best first.
+    ///
+    /// Rebuilds the full-text index (schema `fts_main_embeddings`) first;
+    /// documents without any query term, or whose metadata doesn't match
+    /// every filter, are left out.
+    pub fn keyword_search(
+        &self,
+        query: &str,
+        limit: usize,
+        filters: &[MetadataFilter],
+    ) -> Result<Vec<(String, f32)>> {
+        self.conn
+            .execute_batch(
+                "INSTALL fts; LOAD fts;
+                 PRAGMA create_fts_index('embeddings', 'doc_id', 'content', overwrite = 1);",
+            )
+            .context("Failed to build the full-text index")?;
+        self.warn_unknown_keys(filters)?;
+
+        let (condition, filter_params) = filter_sql(filters, "e.doc_id");
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT content, score FROM (
+                 SELECT content, fts_main_embeddings.match_bm25(doc_id, ?) AS score
+                 FROM embeddings e
+                 WHERE {}
+             )
+             WHERE score IS NOT NULL
+             ORDER BY score DESC
+             LIMIT CAST(? AS BIGINT)",
+            condition
+        ))?;
+        let mut params = vec![query.to_string()];
+        params.extend(filter_params);
+        params.push(limit.to_string());
+        let results = stmt
+            .query_map(duckdb::params_from_iter(&params), |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, f32>(1)?))
+            })?
+            .collect::<duckdb::Result<Vec<_>>>()?;
+        Ok(results)
+    }
+
+    /// Stores a batch of embeddings and metadata and checkpoints its
+    /// document ids atomically.
+    pub fn store_batch(
+        &self,
+        batch: usize,
+        documents: &[Document],
+        embeddings: &[Vec<f32>],
+    ) -> Result<()> {
+        if documents.len() != embeddings.len() {
+            return Err(anyhow::anyhow!(
+                "Batch has {} documents but {} embeddings",
+                documents.len(),
+                embeddings.len()
+            ));
+        }
+
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut insert_embedding =
+                tx.prepare("INSERT OR REPLACE INTO embeddings VALUES (?, ?, CAST(? AS FLOAT[]))")?;
+            let mut insert_checkpoint = tx
+                .prepare("INSERT OR REPLACE INTO index_checkpoint (doc_id, batch) VALUES (?, ?)")?;
+            let mut delete_metadata =
+                tx.prepare("DELETE FROM document_metadata WHERE doc_id = ?")?;
+            let mut insert_metadata =
+                tx.prepare("INSERT INTO document_metadata (doc_id, key, value) VALUES (?, ?, ?)")?;
+
+            let batch = batch.to_string();
+            for (document, embedding) in documents.iter().zip(embeddings) {
+                insert_embedding.execute([
+                    document.id.as_str(),
+                    document.content.as_str(),
+                    &format_embedding(embedding),
+                ])?;
+                delete_metadata.execute([document.id.as_str()])?;
+                for (key, value) in &document.metadata {
+                    insert_metadata.execute([document.id.as_str(), key, value])?;
+                }
+                insert_checkpoint.execute([document.id.as_str(), &batch])?;
+            }
+        }
+        tx.commit().context("Failed to commit index batch")?;
+
+        Ok(())
+    }
+
+    /// Embeds every document not yet in the index, committing after each batch.
+    ///
+    /// Without `options.resume` the index must be empty, so an existing
+    /// index is never silently extended by a mismatched corpus or model.
+    ///
+    /// # Performance
+    ///
+    /// Throughput is dominated by the embedding backend. Larger batches
+    /// amortize per-call overhead but lose more work if a batch fails.
+    pub fn build<E: Embedder>(
+        &self,
+        documents: &[Document],
+        embedder: &E,
+        options: &IndexOptions,
+    ) -> Result<IndexReport> {
+        if options.batch_size == 0 {
+            return Err(anyhow::anyhow!("Batch size must be greater than zero"));
+        }
+
+        // Catch a model change before spending time on embedding
+        if let Some(recorded) = self.metadata()? {
+            if recorded.model != embedder.model_name()
+                || recorded.normalized != embedder.normalizes()
+            {
+                recorded.check_compatible(&EmbeddingMetadata {
+                    model: embedder.model_name().to_string(),
+                    dimension: recorded.dimension,
+                    normalized: embedder.normalizes(),
+                })?;
+            }
+        }
+
+        let indexed = self.indexed_ids()?;
+        if !indexed.is_empty() && !options.resume {
+            return Err(anyhow::anyhow!(
+                "Index already contains {} documents; use --resume to continue indexing",
+                indexed.len()
+            ));
+        }
+
+        let pending: Vec<&Document> = documents
+            .iter()
+            .filter(|document| !indexed.contains(&document.id))
+            .collect();
+        let skipped = documents.len() - pending.len();
+        if skipped > 0 {
+            info!(
+                "⏩ Resuming: {} of {} documents already indexed",
+                skipped,
+                documents.len()
+            );
+        }
+
+        let first_batch = self.next_batch_number()?;
+        let start = Instant::now();
+        let mut indexed_documents = 0;
+        let mut batches = 0;
+
+        for (offset, chunk) in pending.chunks(options.batch_size).enumerate() {
+            let batch_start = Instant::now();
+            let chunk: Vec<Document> = chunk.iter().map(|document| (*document).clone()).collect();
+            let texts = chunk
+                .iter()
+                .map(|document| document.content.clone())
+                .collect();
+
+            let embeddings = embedder.embed_batch(texts).with_context(|| {
+                format!(
+                    "Failed to embed batch {} ({} documents indexed so far; rerun with --resume)",
+                    first_batch + offset,
+                    skipped + indexed_documents
+                )
+            })?;
+            self.ensure_metadata(&metadata_for(embedder, &embeddings)?)?;
+            self.store_batch(first_batch + offset, &chunk, &embeddings)?;
+
+            indexed_documents += chunk.len();
+            batches += 1;
+            info!(
+                "📦 Batch {}: {} documents in {:?} ({}/{} total, {:.1} embeddings/sec)",
+                first_batch + offset,
+                chunk.len(),
+                batch_start.elapsed(),
+                skipped + indexed_documents,
+                documents.len(),
+                indexed_documents as f64 / start.elapsed().as_secs_f64().max(f64::EPSILON)
+            );
+        }
+
+        Ok(IndexReport {
+            total_documents: documents.len(),
+            skipped_documents: skipped,
+            indexed_documents,
+            batches,
+            elapsed: start.elapsed(),
+        })
+    }
+
+    fn next_batch_number(&self) -> Result<usize> {
+        let next: i64 = self.conn.query_row(
+            "SELECT COALESCE(MAX(batch) + 1, 0) FROM index_checkpoint",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(next as usize)
+    }
+}
+
+/// Loads a corpus from a text file (one document per line), a Markdown,
+/// HTML, or PDF file, or a directory of such files.
+///
+/// Line documents are identified by their 1-based line number and file
+/// documents by their path relative to the directory, so ids stay stable
+/// between runs as long as the corpus is unchanged. Markdown, HTML, and
+/// PDF files are split into sections by [`loaders`](super::loaders), each
+/// identified as `<file>#<heading>` or `<file>#page-<n>`. Empty documents,
+/// hidden files, and files that can't be loaded are skipped.
+///
+/// Documents get the metadata `file`, `format`, and `modified` (the
+/// file's modification date, `YYYY-MM-DD`), plus `line` for line
+/// documents and `title`, `heading`, or `page` for loaded sections.
+pub fn load_corpus<P: AsRef<Path>>(path: P) -> Result<Vec<Document>> {
+    let path = path.as_ref();
+
+    if path.is_dir() {
+        let mut files = Vec::new();
+        corpus_files(path, &mut files)?;
+
+        let mut documents = Vec::new();
+        for file in files {
+            let id = file
+                .strip_prefix(path)
+                .unwrap_or(&file)
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            match load_document(&file) {
+                Ok(loaded) => {
+                    let metadata = file_metadata(&file, &id);
+                    documents.extend(loaded.into_documents(&id).into_iter().map(|mut document| {
+                        document.metadata.extend(metadata.clone());
+                        document
+                    }));
+                }
+                Err(e) => warn!("⚠️  Skipping {}: {:#}", file.display(), e),
+            }
+        }
+        Ok(documents)
+    } else if DocumentFormat::for_path(path) != DocumentFormat::Text {
+        let id = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        let metadata = file_metadata(path, &id);
+        let mut documents = load_document(path)?.into_documents(&id);
+        for document in &mut documents {
+            document.metadata.extend(metadata.clone());
+        }
+        Ok(documents)
+    } else {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read corpus file: {}", path.display()))?;
+        let name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        let metadata = file_metadata(path, &name);
+        Ok(content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                let mut metadata = metadata.clone();
+                metadata.insert("line".to_string(), (i + 1).to_string());
+                Document {
+                    id: format!("line-{}", i + 1),
+                    content: line.to_string(),
+                    metadata,
+                }
+            })
+            .collect())
+    }
+}
+
+/// Metadata describing the corpus file `path`, known in the corpus as `name`.
+fn file_metadata(path: &Path, name: &str) -> BTreeMap<String, String> {
+    let mut metadata = BTreeMap::new();
+    metadata.insert("file".to_string(), name.to_string());
+    metadata.insert(
+        "format".to_string(),
+        DocumentFormat::for_path(path).as_str().to_string(),
+    );
+    if let Ok(modified) = fs::metadata(path).and_then(|m| m.modified()) {
+        let modified: chrono::DateTime<chrono::Local> = modified.into();
+        metadata.insert(
+            "modified".to_string(),
+            modified.format("%Y-%m-%d").to_string(),
+        );
+    }
+    metadata
+}
+
+/// Collects the non-hidden files under `dir`, recursively, sorted by path.
+fn corpus_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read corpus directory: {}", dir.display()))?
+        .collect::<std::io::Result<_>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            corpus_files(&path, files)?;
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Splits every document into chunks, identified as `<doc_id>#<n>`.
+///
+/// Chunk ids are stable as long as the document and chunker are unchanged,
+/// so chunked indexing can be resumed like unchunked indexing.
+pub fn chunk_documents(documents: &[Document], chunker: &Chunker) -> Vec<Document> {
+    documents
+        .iter()
+        .flat_map(|document| {
+            chunker
+                .chunk(&document.content)
+                .into_iter()
+                .filter(|chunk| !chunk.text.trim().is_empty())
+                .enumerate()
+                .map(move |(n, chunk)| Document {
+                    id: format!("{}#{}", document.id, n),
+                    content: chunk.text,
+                    metadata: document.metadata.clone(),
+                })
+        })
+        .collect()
+}
+
+/// Describes a batch of embeddings from `embedder`, checking they share one dimension.
+fn metadata_for<E: Embedder>(embedder: &E, embeddings: &[Vec<f32>]) -> Result<EmbeddingMetadata> {
+    let dimension = embeddings.first().map_or(0, Vec::len);
+    if dimension == 0 {
+        return Err(anyhow::anyhow!(
+            "Model '{}' returned empty embeddings",
+            embedder.model_name()
+        ));
+    }
+    if let Some(other) = embeddings.iter().find(|e| e.len() != dimension) {
+        return Err(anyhow::anyhow!(
+            "Model '{}' returned embeddings of mixed dimensions ({} and {})",
+            embedder.model_name(),
+            dimension,
+            other.len()
+        ));
+    }
+    Ok(EmbeddingMetadata {
+        model: embedder.model_name().to_string(),
+        dimension,
+        normalized: embedder.normalizes(),
+    })
+}
+
+/// Formats an embedding as a DuckDB list literal, e.g. `[0.1, 0.2]`.
+fn format_embedding(embedding: &[f32]) -> String {
+    let values: Vec<String> = embedding.iter().map(|v| v.to_string()).collect();
+    format!("[{}]", values.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// Embeds each text as `[len]`, failing on the call numbered `fail_on`.
+    struct FakeEmbedder {
+        calls: Cell<usize>,
+        fail_on: Option<usize>,
+    }
+
+    impl Embedder for FakeEmbedder {
+        fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            let call = self.calls.get();
+            self.calls.set(call + 1);
+            if self.fail_on == Some(call) {
+                return Err(anyhow::anyhow!("embedding backend unavailable"));
+            }
+            Ok(texts.iter().map(|t| vec![t.len() as f32]).collect())
+        }
+    }
+
+    fn corpus(n: usize) -> Vec<Document> {
+        (0..n)
+            .map(|i| Document {
+                id: format!("doc-{}", i),
+                content: "x".repeat(i + 1),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_interrupted_build_resumes_from_checkpoint() {
+        let index = EmbeddingIndex::open_in_memory().unwrap();
+        let documents = corpus(5);
+        let options = IndexOptions {
+            batch_size: 2,
+            resume: false,
+        };
+
+        let failing = FakeEmbedder {
+            calls: Cell::new(0),
+            fail_on: Some(1),
+        };
+        assert!(index.build(&documents, &failing, &options).is_err());
+        assert_eq!(index.document_count().unwrap(), 2);
+
+        // Without --resume a non-empty index is rejected
+        let working = FakeEmbedder {
+            calls: Cell::new(0),
+            fail_on: None,
+        };
+        assert!(index.build(&documents, &working, &options).is_err());
+
+        let resume = IndexOptions {
+            resume: true,
+            ..options
+        };
+        let report = index.build(&documents, &working, &resume).unwrap();
+        assert_eq!(report.skipped_documents, 2);
+        assert_eq!(report.indexed_documents, 3);
+        assert_eq!(report.batches, 2);
+        assert_eq!(index.document_count().unwrap(), 5);
+
+        let embedding: Vec<f32> = index
+            .conn
+            .query_row(
+                "SELECT embedding FROM embeddings WHERE doc_id = 'doc-4'",
+                [],
+                |row| row.get::<_, duckdb::types::Value>(0),
+            )
+            .map(|value| match value {
+                duckdb::types::Value::List(items) => items
+                    .into_iter()
+                    .map(|item| match item {
+                        duckdb::types::Value::Float(f) => f,
+                        other => panic!("unexpected value {:?}", other),
+                    })
+                    .collect(),
+                other => panic!("unexpected value {:?}", other),
+            })
+            .unwrap();
+        assert_eq!(embedding, vec![5.0]);
+    }
+
+    /// Embeds texts as fixed-size vectors under a configurable model name.
+    struct NamedEmbedder {
+        model: &'static str,
+        dimension: usize,
+    }
+
+    impl Embedder for NamedEmbedder {
+        fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            Ok(texts
+                .iter()
+                .map(|t| {
+                    let mut embedding = vec![0.0; self.dimension];
+                    embedding[t.len() % self.dimension] = 1.0;
+                    embedding
+                })
+                .collect())
+        }
+
+        fn model_name(&self) -> &str {
+            self.model
+        }
+    }
+
+    #[test]
+    fn test_metadata_recorded_and_validated() {
+        let index = EmbeddingIndex::open_in_memory().unwrap();
+        let small = NamedEmbedder {
+            model: "small",
+            dimension: 4,
+        };
+        let options = IndexOptions {
+            batch_size: 2,
+            resume: true,
+        };
+        index.build(&corpus(3), &small, &options).unwrap();
+
+        assert_eq!(
+            index.metadata().unwrap(),
+            Some(EmbeddingMetadata {
+                model: "small".to_string(),
+                dimension: 4,
+                normalized: false,
+            })
+        );
+
+        // "x" has length 1, like doc-0
+        let results = index.search("x", &small, 0.5, 10).unwrap();
+        assert_eq!(results, vec![("x".to_string(), 1.0)]);
+
+        let large = NamedEmbedder {
+            model: "large",
+            dimension: 8,
+        };
+        let err = index.search("x", &large, 0.5, 10).unwrap_err();
+        assert!(err.to_string().contains("index was built with 'small'"));
+        assert!(index.build(&corpus(5), &large, &options).is_err());
+
+        // Same name but a different dimension is also rejected
+        let resized = NamedEmbedder {
+            model: "small",
+            dimension: 8,
+        };
+        let err = index.search("x", &resized, 0.5, 10).unwrap_err();
+        assert!(err.to_string().contains("dimension mismatch"));
+    }
+
+    #[test]
+    fn test_keyword_search() {
+        let index = EmbeddingIndex::open_in_memory().unwrap();
+        let documents = [
+            "duckdb parquet export",
+            "rust borrow checker",
+            "duckdb extensions",
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, content)| Document {
+            id: format!("doc-{}", i),
+            content: content.to_string(),
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+        let embedder = NamedEmbedder {
+            model: "small",
+            dimension: 4,
+        };
+        let options = IndexOptions {
+            batch_size: 3,
+            resume: false,
+        };
+        index.build(&documents, &embedder, &options).unwrap();
+
+        let results = index.keyword_search("parquet", 10, &[]).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, "duckdb parquet export");
+
+        let results = index.keyword_search("duckdb", 1, &[]).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(index
+            .keyword_search("postgres", 10, &[])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_metadata_filters() {
+        let index = EmbeddingIndex::open_in_memory().unwrap();
+        let documents = ["a", "bb", "cc", "dd"]
+            .iter()
+            .enumerate()
+            .map(|(i, content)| Document {
+                id: format!("doc-{}", i),
+                content: content.to_string(),
+                metadata: [
+                    (
+                        "source".to_string(),
+                        ["manual", "faq", "manual", "blog"][i].to_string(),
+                    ),
+                    ("page".to_string(), (i * 5).to_string()),
+                ]
+                .into_iter()
+                .collect(),
+            })
+            .collect::<Vec<_>>();
+        let embedder = NamedEmbedder {
+            model: "small",
+            dimension: 4,
+        };
+        let options = IndexOptions {
+            batch_size: 4,
+            resume: false,
+        };
+        index.build(&documents, &embedder, &options).unwrap();
+        assert_eq!(index.metadata_keys().unwrap(), vec!["page", "source"]);
+        assert_eq!(index.document_metadata("doc-1").unwrap()["source"], "faq");
+
+        let search = |filters: &[&str]| {
+            let filters: Vec<MetadataFilter> = filters
+                .iter()
+                .map(|f| MetadataFilter::parse(f).unwrap())
+                .collect();
+            let mut contents: Vec<String> = index
+                .search_filtered("xx", &embedder, 0.5, 10, &filters)
+                .unwrap()
+                .into_iter()
+                .map(|(content, _)| content)
+                .collect();
+            contents.sort();
+            contents
+        };
+        assert_eq!(search(&[]), vec!["bb", "cc", "dd"]);
+        assert_eq!(search(&["source=manual"]), vec!["cc"]);
+        assert_eq!(search(&["source=manual", "source=faq"]), vec!["bb", "cc"]);
+        assert_eq!(search(&["source!=faq"]), vec!["cc", "dd"]);
+        // Numeric comparison: "15" < "5" as text, but not as numbers
+        assert_eq!(search(&["page>=10"]), vec!["cc", "dd"]);
+        assert!(search(&["missing=1"]).is_empty());
+
+        let manual = [MetadataFilter::parse("source=manual").unwrap()];
+        let ids = index.search_ids("xx", &embedder, 0.5, 10, &manual).unwrap();
+        assert_eq!(ids, vec![("doc-2".to_string(), 1.0)]);
+
+        let results = index.keyword_search("bb", 10, &manual).unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_load_corpus_from_file_skips_blank_lines() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("docs.txt");
+        fs::write(&path, "first\n\nthird\n").unwrap();
+
+        let documents = load_corpus(&path).unwrap();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[1].id, "line-3");
+        assert_eq!(documents[1].content, "third");
+        assert_eq!(documents[1].metadata["file"], "docs.txt");
+        assert_eq!(documents[1].metadata["line"], "3");
+    }
+
+    #[test]
+    fn test_load_corpus_from_directory() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(temp.path().join("b.txt"), "second").unwrap();
+        fs::write(temp.path().join("a.txt"), "first").unwrap();
+        fs::write(temp.path().join("empty.txt"), "  \n").unwrap();
+        fs::write(temp.path().join(".hidden"), "skipped").unwrap();
+        fs::create_dir(temp.path().join("guide")).unwrap();
+        fs::write(temp.path().join("guide").join("c.txt"), "nested").unwrap();
+
+        let documents = load_corpus(temp.path()).unwrap();
+        let ids: Vec<&str> = documents.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids, vec!["a.txt", "b.txt", "guide/c.txt"]);
+        assert_eq!(documents[2].content, "nested");
+        assert_eq!(documents[2].metadata["file"], "guide/c.txt");
+        assert_eq!(documents[2].metadata["format"], "text");
+        assert_eq!(documents[2].metadata["modified"].len(), "YYYY-MM-DD".len());
+    }
+
+    #[test]
+    fn test_chunk_documents_assigns_chunk_ids() {
+        let documents = vec![Document {
+            id: "intro.txt".to_string(),
+            content: "First sentence. Second sentence.".to_string(),
+            metadata: [("file".to_string(), "intro.txt".to_string())]
+                .into_iter()
+                .collect(),
+        }];
+        let chunker = Chunker::Sentence {
+            max_size: 16,
+            unit: crate::text::chunk::ChunkUnit::Chars,
+        };
+
+        let chunks = chunk_documents(&documents, &chunker);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].id, "intro.txt#0");
+        assert_eq!(chunks[1].id, "intro.txt#1");
+        assert_eq!(chunks[1].content, "Second sentence.");
+        assert_eq!(chunks[1].metadata["file"], "intro.txt");
+    }
+
+    #[test]
+    fn test_embeddings_per_sec() {
+        let report = IndexReport {
+            total_documents: 100,
+            skipped_documents: 0,
+            indexed_documents: 100,
+            batches: 1,
+            elapsed: Duration::from_secs(4),
+        };
+        assert_eq!(report.embeddings_per_sec(), 25.0);
+    }
+}
@@ -0,0 +1,643 @@
+//! # Classification Evaluation for Frozen DuckDB CLI
+//!
+//! This module measures how well an `llm_filter` criteria matches human
+//! labels before it is run over a large dataset. A labeled sample is
+//! classified with the same prompt the `filter` command uses, and the
+//! predictions are compared to the labels as a confusion matrix with
+//! precision, recall, and F1.
+//!
+//! ## Labels
+//!
+//! The label column may hold booleans or common spellings of them:
+//! `true`/`false`, `1`/`0`, `yes`/`no`, `y`/`n`, `positive`/`negative`.
+//!
+//! ## Retrieval Recall
+//!
+//! [`evaluate_retrieval`] measures search instead of filtering: each
+//! labeled query lists the ids of the documents it should find, separated
+//! by `|`, and the report gives recall@k (the share of those documents in
+//! the top `k`, averaged over queries) and the hit rate (queries with at
+//! least one of them in the top `k`). A retrieved chunk or section
+//! (`<id>#...`) counts as its document. `eval-retrieval` runs it with and
+//! without [multi-query retrieval](super::multi_query) to show whether the
+//! extra model calls pay off.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use frozen_duckdb::cli::eval::{evaluate, load_labeled, FlockClassifier};
+//! use frozen_duckdb::cli::{DatasetManager, FlockManager};
+//!
+//! let datasets = DatasetManager::new()?;
+//! let samples = load_labeled(datasets.connection(), "labeled.csv", "text", "truth", None)?;
+//! let manager = FlockManager::new()?;
+//! let classifier = FlockClassifier {
+//!     manager: &manager,
+//!     criteria: "Is this valid Python code? Answer yes or no: {text}".to_string(),
+//!     model: "coder".to_string(),
+//! };
+//! let report = evaluate(&samples, &classifier)?;
+//! println!("{}", report.format_report());
+//! ```
+
+use super::dedupe::{quote_identifier, read_function};
+use super::embedding_index::{Embedder, EmbeddingIndex};
+use super::multi_query::MultiQuery;
+use super::FlockManager;
+use anyhow::{Context, Result};
+use duckdb::Connection;
+use std::time::{Duration, Instant};
+
+/// Classifies a batch of texts as matching or not matching a criteria.
+///
+/// Implemented by [`FlockClassifier`]; other implementations can be used
+/// to evaluate a different backend.
+pub trait Classifier {
+    /// Returns one match flag per input text, in input order.
+    fn classify_batch(&self, texts: &[String]) -> Result<Vec<bool>>;
+}
+
+/// [`FlockManager`] bound to a criteria and model, usable as a [`Classifier`].
+pub struct FlockClassifier<'a> {
+    /// Flock manager used to call `llm_complete`
+    pub manager: &'a FlockManager,
+    /// Filter criteria, formatted as the `filter` command does
+    pub criteria: String,
+    /// Model alias configured during flock-setup
+    pub model: String,
+}
+
+impl Classifier for FlockClassifier<'_> {
+    /// Fails on the first model call that fails, so an unreachable model
+    /// isn't scored as a run of non-matches.
+    fn classify_batch(&self, texts: &[String]) -> Result<Vec<bool>> {
+        let mut matches = Vec::with_capacity(texts.len());
+        self.manager
+            .classify_each(&self.criteria, texts, &self.model, |_, matched, _| {
+                matches.push(matched);
+                Ok(())
+            })?;
+        Ok(matches)
+    }
+}
+
+/// A text with its expected classification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabeledSample {
+    /// Text passed to the classifier
+    pub text: String,
+    /// Whether the text should match the criteria
+    pub label: bool,
+}
+
+/// Counts of predictions against labels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConfusionMatrix {
+    /// Predicted match, labeled match
+    pub true_positives: usize,
+    /// Predicted match, labeled non-match
+    pub false_positives: usize,
+    /// Predicted non-match, labeled non-match
+    pub true_negatives: usize,
+    /// Predicted non-match, labeled match
+    pub false_negatives: usize,
+}
+
+impl ConfusionMatrix {
+    /// Adds one prediction to the matrix.
+    pub fn record(&mut self, predicted: bool, label: bool) {
+        match (predicted, label) {
+            (true, true) => self.true_positives += 1,
+            (true, false) => self.false_positives += 1,
+            (false, false) => self.true_negatives += 1,
+            (false, true) => self.false_negatives += 1,
+        }
+    }
+
+    /// Total number of predictions.
+    pub fn total(&self) -> usize {
+        self.true_positives + self.false_positives + self.true_negatives + self.false_negatives
+    }
+
+    /// Fraction of predicted matches that are labeled matches.
+    pub fn precision(&self) -> f64 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_positives,
+        )
+    }
+
+    /// Fraction of labeled matches that were predicted.
+    pub fn recall(&self) -> f64 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_negatives,
+        )
+    }
+
+    /// Harmonic mean of precision and recall.
+    pub fn f1(&self) -> f64 {
+        let (precision, recall) = (self.precision(), self.recall());
+        if precision + recall == 0.0 {
+            0.0
+        } else {
+            2.0 * precision * recall / (precision + recall)
+        }
+    }
+
+    /// Fraction of predictions that agree with the label.
+    pub fn accuracy(&self) -> f64 {
+        ratio(self.true_positives + self.true_negatives, self.total())
+    }
+}
+
+/// Result of evaluating one classifier over a labeled sample.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalReport {
+    /// Predictions compared to labels
+    pub matrix: ConfusionMatrix,
+    /// Time spent classifying
+    pub elapsed: Duration,
+}
+
+impl EvalReport {
+    /// Formats the metrics and confusion matrix as a human-readable report.
+    pub fn format_report(&self) -> String {
+        let m = &self.matrix;
+        format!(
+            "Samples:   {}\n\
+             Precision: {:.3}\n\
+             Recall:    {:.3}\n\
+             F1:        {:.3}\n\
+             Accuracy:  {:.3}\n\
+             Elapsed:   {:.1}s\n\
+             \n\
+             {:>16} {:>10} {:>10}\n\
+             {:>16} {:>10} {:>10}\n\
+             {:>16} {:>10} {:>10}",
+            m.total(),
+            m.precision(),
+            m.recall(),
+            m.f1(),
+            m.accuracy(),
+            self.elapsed.as_secs_f64(),
+            "",
+            "pred true",
+            "pred false",
+            "label true",
+            m.true_positives,
+            m.false_negatives,
+            "label false",
+            m.false_positives,
+            m.true_negatives,
+        )
+    }
+
+    /// Returns the report as JSON.
+    pub fn to_json(&self) -> serde_json::Value {
+        let m = &self.matrix;
+        serde_json::json!({
+            "samples": m.total(),
+            "precision": m.precision(),
+            "recall": m.recall(),
+            "f1": m.f1(),
+            "accuracy": m.accuracy(),
+            "elapsed_ms": self.elapsed.as_millis(),
+            "confusion_matrix": {
+                "true_positives": m.true_positives,
+                "false_positives": m.false_positives,
+                "true_negatives": m.true_negatives,
+                "false_negatives": m.false_negatives,
+            }
+        })
+    }
+}
+
+/// Classifies every sample and compares the predictions to their labels.
+pub fn evaluate<C: Classifier>(samples: &[LabeledSample], classifier: &C) -> Result<EvalReport> {
+    let texts: Vec<String> = samples.iter().map(|s| s.text.clone()).collect();
+
+    let start = Instant::now();
+    let predictions = classifier.classify_batch(&texts)?;
+    let elapsed = start.elapsed();
+
+    if predictions.len() != samples.len() {
+        return Err(anyhow::anyhow!(
+            "Classifier returned {} predictions for {} samples",
+            predictions.len(),
+            samples.len()
+        ));
+    }
+
+    let mut matrix = ConfusionMatrix::default();
+    for (sample, predicted) in samples.iter().zip(predictions) {
+        matrix.record(predicted, sample.label);
+    }
+    Ok(EvalReport { matrix, elapsed })
+}
+
+/// Reads labeled samples from a CSV, Parquet, or JSON file.
+///
+/// At most `limit` rows are read, in file order.
+pub fn load_labeled(
+    conn: &Connection,
+    path: &str,
+    text_column: &str,
+    label_column: &str,
+    limit: Option<usize>,
+) -> Result<Vec<LabeledSample>> {
+    let limit = limit.map_or_else(String::new, |n| format!(" LIMIT {}", n));
+    let sql = format!(
+        "SELECT CAST({} AS VARCHAR), CAST({} AS VARCHAR) FROM {}{}",
+        quote_identifier(text_column),
+        quote_identifier(label_column),
+        read_function(path)?,
+        limit
+    );
+
+    let mut stmt = conn
+        .prepare(&sql)
+        .with_context(|| format!("Failed to read labeled data from {}", path))?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, Option<String>>(0)?,
+            row.get::<_, Option<String>>(1)?,
+        ))
+    })?;
+
+    let mut samples = Vec::new();
+    for (i, row) in rows.enumerate() {
+        let (text, label) = row?;
+        let label = label
+            .as_deref()
+            .and_then(parse_label)
+            .ok_or_else(|| anyhow::anyhow!("Row {}: invalid label {:?}", i + 1, label))?;
+        samples.push(LabeledSample {
+            text: text.unwrap_or_default(),
+            label,
+        });
+    }
+    Ok(samples)
+}
+
+/// Retrieves document ids for a query, best first.
+///
+/// Implemented by [`IndexRetriever`]; other implementations can be used
+/// to evaluate a different search backend.
+pub trait Retriever {
+    /// Returns the ids of at most `k` documents for `query`, best first.
+    fn retrieve(&self, query: &str, k: usize) -> Result<Vec<String>>;
+}
+
+/// Vector search of an [`EmbeddingIndex`], usable as a [`Retriever`].
+pub struct IndexRetriever<'a, E: Embedder> {
+    /// Index searched
+    pub index: &'a EmbeddingIndex,
+    /// Embedder for queries, matching the index's model
+    pub embedder: &'a E,
+    /// Minimum similarity of retrieved documents
+    pub threshold: f32,
+    /// Flock manager and options for multi-query retrieval; `None`
+    /// searches with the query only
+    pub multi_query: Option<(&'a FlockManager, MultiQuery)>,
+}
+
+impl<E: Embedder> Retriever for IndexRetriever<'_, E> {
+    fn retrieve(&self, query: &str, k: usize) -> Result<Vec<String>> {
+        let search = |query: &str| {
+            self.index
+                .search_ids(query, self.embedder, self.threshold, k, &[])
+        };
+        let results = match &self.multi_query {
+            None => search(query)?,
+            Some((manager, multi_query)) => {
+                let lists = multi_query
+                    .queries(manager, query)?
+                    .iter()
+                    .map(|query| search(query.as_str()))
+                    .collect::<Result<Vec<_>>>()?;
+                multi_query.fuse(manager, &lists)?
+            }
+        };
+        Ok(results.into_iter().take(k).map(|(id, _)| id).collect())
+    }
+}
+
+/// A query with the documents it should retrieve.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetrievalSample {
+    /// Query passed to the retriever
+    pub query: String,
+    /// Ids of the relevant documents
+    pub relevant: Vec<String>,
+}
+
+/// Result of evaluating one retriever over labeled queries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecallReport {
+    /// Queries evaluated
+    pub samples: usize,
+    /// Documents retrieved per query
+    pub k: usize,
+    /// Mean share of relevant documents among the top `k`
+    pub recall: f64,
+    /// Share of queries with a relevant document among the top `k`
+    pub hit_rate: f64,
+    /// Time spent retrieving
+    pub elapsed: Duration,
+}
+
+impl RecallReport {
+    /// Formats the metrics as a human-readable report.
+    pub fn format_report(&self) -> String {
+        format!(
+            "Queries:   {}\n\
+             Recall@{}: {:.3}\n\
+             Hit rate:  {:.3}\n\
+             Elapsed:   {:.1}s",
+            self.samples,
+            self.k,
+            self.recall,
+            self.hit_rate,
+            self.elapsed.as_secs_f64(),
+        )
+    }
+
+    /// Returns the report as JSON.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "queries": self.samples,
+            "k": self.k,
+            "recall": self.recall,
+            "hit_rate": self.hit_rate,
+            "elapsed_ms": self.elapsed.as_millis(),
+        })
+    }
+}
+
+/// Retrieves the top `k` documents for every sample and measures how many
+/// of its relevant documents were found.
+pub fn evaluate_retrieval<R: Retriever>(
+    samples: &[RetrievalSample],
+    retriever: &R,
+    k: usize,
+) -> Result<RecallReport> {
+    if k == 0 {
+        anyhow::bail!("k must be at least 1");
+    }
+
+    let start = Instant::now();
+    let mut recall = 0.0;
+    let mut hits = 0;
+    for sample in samples {
+        let retrieved = retriever
+            .retrieve(&sample.query, k)
+            .with_context(|| format!("Retrieval failed for query: {}", sample.query))?;
+        let found = recall_at_k(&retrieved, &sample.relevant, k);
+        recall += found;
+        if found > 0.0 {
+            hits += 1;
+        }
+    }
+
+    Ok(RecallReport {
+        samples: samples.len(),
+        k,
+        recall: if samples.is_empty() {
+            0.0
+        } else {
+            recall / samples.len() as f64
+        },
+        hit_rate: ratio(hits, samples.len()),
+        elapsed: start.elapsed(),
+    })
+}
+
+/// Share of `relevant` documents among the first `k` retrieved ids. A
+/// retrieved `<id>#...` chunk or section counts as document `<id>`.
+pub fn recall_at_k(retrieved: &[String], relevant: &[String], k: usize) -> f64 {
+    let top = &retrieved[..retrieved.len().min(k)];
+    let found = relevant
+        .iter()
+        .filter(|relevant| {
+            top.iter().any(|id| {
+                id == *relevant
+                    || id
+                        .strip_prefix(relevant.as_str())
+                        .is_some_and(|rest| rest.starts_with('#'))
+            })
+        })
+        .count();
+    ratio(found, relevant.len())
+}
+
+/// Reads labeled queries from a CSV, Parquet, or JSON file; the relevant
+/// column lists document ids separated by `|`.
+///
+/// At most `limit` rows are read, in file order.
+pub fn load_retrieval_samples(
+    conn: &Connection,
+    path: &str,
+    query_column: &str,
+    relevant_column: &str,
+    limit: Option<usize>,
+) -> Result<Vec<RetrievalSample>> {
+    let limit = limit.map_or_else(String::new, |n| format!(" LIMIT {}", n));
+    let sql = format!(
+        "SELECT CAST({} AS VARCHAR), CAST({} AS VARCHAR) FROM {}{}",
+        quote_identifier(query_column),
+        quote_identifier(relevant_column),
+        read_function(path)?,
+        limit
+    );
+
+    let mut stmt = conn
+        .prepare(&sql)
+        .with_context(|| format!("Failed to read labeled queries from {}", path))?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, Option<String>>(0)?,
+            row.get::<_, Option<String>>(1)?,
+        ))
+    })?;
+
+    let mut samples = Vec::new();
+    for (i, row) in rows.enumerate() {
+        let (query, relevant) = row?;
+        let relevant: Vec<String> = relevant
+            .unwrap_or_default()
+            .split('|')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+            .collect();
+        if relevant.is_empty() {
+            return Err(anyhow::anyhow!("Row {}: no relevant document ids", i + 1));
+        }
+        samples.push(RetrievalSample {
+            query: query.unwrap_or_default(),
+            relevant,
+        });
+    }
+    Ok(samples)
+}
+
+/// Parses a label value such as `true`, `0`, or `yes`.
+pub fn parse_label(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "1" | "yes" | "y" | "positive" => Some(true),
+        "false" | "0" | "no" | "n" | "negative" => Some(false),
+        _ => None,
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Predicts a match for texts containing "def".
+    struct KeywordClassifier;
+
+    impl Classifier for KeywordClassifier {
+        fn classify_batch(&self, texts: &[String]) -> Result<Vec<bool>> {
+            Ok(texts.iter().map(|t| t.contains("def")).collect())
+        }
+    }
+
+    #[test]
+    fn test_confusion_matrix_metrics() {
+        let matrix = ConfusionMatrix {
+            true_positives: 6,
+            false_positives: 2,
+            true_negatives: 10,
+            false_negatives: 2,
+        };
+        assert_eq!(matrix.total(), 20);
+        assert_eq!(matrix.precision(), 0.75);
+        assert_eq!(matrix.recall(), 0.75);
+        assert_eq!(matrix.f1(), 0.75);
+        assert_eq!(matrix.accuracy(), 0.8);
+
+        let empty = ConfusionMatrix::default();
+        assert_eq!(empty.precision(), 0.0);
+        assert_eq!(empty.f1(), 0.0);
+    }
+
+    #[test]
+    fn test_evaluate_labeled_csv() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("labeled.csv");
+        fs::write(
+            &path,
+            "text,truth\n\
+             def f(): pass,yes\n\
+             def g(): return 1,yes\n\
+             print('hi'),yes\n\
+             hello world,no\n\
+             undefined behaviour,no\n",
+        )
+        .unwrap();
+
+        let conn = Connection::open_in_memory().unwrap();
+        let samples = load_labeled(&conn, path.to_str().unwrap(), "text", "truth", None).unwrap();
+        assert_eq!(samples.len(), 5);
+
+        let report = evaluate(&samples, &KeywordClassifier).unwrap();
+        assert_eq!(
+            report.matrix,
+            ConfusionMatrix {
+                true_positives: 2,
+                false_positives: 1,
+                true_negatives: 1,
+                false_negatives: 1,
+            }
+        );
+        assert!(report.format_report().contains("Precision: 0.667"));
+
+        let limited =
+            load_labeled(&conn, path.to_str().unwrap(), "text", "truth", Some(2)).unwrap();
+        assert_eq!(limited.len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_labels_are_rejected() {
+        assert_eq!(parse_label(" Positive "), Some(true));
+        assert_eq!(parse_label("0"), Some(false));
+        assert_eq!(parse_label("maybe"), None);
+
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("labeled.csv");
+        fs::write(&path, "text,truth\nhello,maybe\n").unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        assert!(load_labeled(&conn, path.to_str().unwrap(), "text", "truth", None).is_err());
+    }
+
+    /// Retrieves fixed ids per query.
+    struct FixedRetriever;
+
+    impl Retriever for FixedRetriever {
+        fn retrieve(&self, query: &str, k: usize) -> Result<Vec<String>> {
+            let ids: &[&str] = match query {
+                "parquet export" => &["faq.md#parquet", "guide.md", "other"],
+                _ => &["other"],
+            };
+            Ok(ids.iter().take(k).map(|id| id.to_string()).collect())
+        }
+    }
+
+    #[test]
+    fn test_recall_at_k() {
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        let relevant = ids(&["faq.md", "guide.md"]);
+        assert_eq!(
+            recall_at_k(&ids(&["faq.md#parquet", "guide.md"]), &relevant, 2),
+            1.0
+        );
+        assert_eq!(recall_at_k(&ids(&["x", "guide.md"]), &relevant, 1), 0.0);
+        assert_eq!(
+            recall_at_k(&ids(&["faq.md2", "guide.md#0"]), &relevant, 5),
+            0.5
+        );
+    }
+
+    #[test]
+    fn test_evaluate_retrieval() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("queries.csv");
+        fs::write(
+            &path,
+            "query,relevant\n\
+             parquet export,faq.md|guide.md\n\
+             joins,joins.md\n",
+        )
+        .unwrap();
+
+        let conn = Connection::open_in_memory().unwrap();
+        let samples =
+            load_retrieval_samples(&conn, path.to_str().unwrap(), "query", "relevant", None)
+                .unwrap();
+        assert_eq!(samples[0].relevant, vec!["faq.md", "guide.md"]);
+
+        let report = evaluate_retrieval(&samples, &FixedRetriever, 1).unwrap();
+        assert_eq!((report.recall, report.hit_rate), (0.25, 0.5));
+        let report = evaluate_retrieval(&samples, &FixedRetriever, 3).unwrap();
+        assert_eq!(report.recall, 0.5);
+        assert!(report.format_report().contains("Recall@3: 0.500"));
+        assert!(evaluate_retrieval(&samples, &FixedRetriever, 0).is_err());
+
+        fs::write(&path, "query,relevant\nempty,\n").unwrap();
+        assert!(
+            load_retrieval_samples(&conn, path.to_str().unwrap(), "query", "relevant", None)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,628 @@
+//! # Entity Extraction for Frozen DuckDB CLI
+//!
+//! This module extracts typed fields from a text column with an LLM. Every
+//! row is sent to the model with the same structured prompt, which asks for
+//! a JSON object matching a JSON schema derived from the requested entities.
+//! Responses are validated against that schema and written as typed output
+//! columns; rows whose response cannot be parsed keep NULL entities and an
+//! explanation in the `extraction_error` column.
+//!
+//! ## Entity Specs
+//!
+//! Entities are given as `name[:kind]`, separated by commas:
+//!
+//! | Spec                     | Output column                                    |
+//! |--------------------------|--------------------------------------------------|
+//! | `person`                 | `VARCHAR[]` (default kind `list`)                |
+//! | `sentiment`              | `VARCHAR`, one of positive/negative/neutral/mixed |
+//! | `title:text`             | `VARCHAR`                                        |
+//! | `rating:integer`         | `BIGINT`                                         |
+//! | `price:number`           | `DOUBLE`                                         |
+//! | `refund:boolean`         | `BOOLEAN`                                        |
+//! | `priority:low\|medium\|high` | `VARCHAR`, one of the listed values          |
+//!
+//! ## Examples
+//!
+//! ```bash
+//! frozen-duckdb extract --input reviews.parquet --column text \
+//!     --entities person,product,sentiment --output extracted.parquet
+//! ```
+
+use super::dedupe::{copy_format, quote_identifier, read_function};
+use super::flock_manager::FlockManager;
+use super::sql_path::path_literal;
+use anyhow::{anyhow, Context, Result};
+use duckdb::{params, Connection};
+use serde_json::{json, Map, Value};
+use tracing::info;
+
+/// Output column holding the reason a row could not be extracted.
+pub const ERROR_COLUMN: &str = "extraction_error";
+
+/// Values accepted by the `sentiment` entity when no kind is given.
+const SENTIMENT_VALUES: [&str; 4] = ["positive", "negative", "neutral", "mixed"];
+
+/// Type of an extracted entity.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntityKind {
+    /// Every mention, as a list of strings
+    List,
+    /// A single string
+    Text,
+    /// A whole number
+    Integer,
+    /// Any number
+    Number,
+    /// true or false
+    Boolean,
+    /// One of a fixed set of strings
+    Choice(Vec<String>),
+}
+
+impl EntityKind {
+    /// Parses a kind name (`list`, `text`, `integer`, `number`, `boolean`)
+    /// or `|`-separated choices.
+    pub fn parse(value: &str) -> Result<Self> {
+        if value.contains('|') {
+            let choices: Vec<String> = value
+                .split('|')
+                .map(|choice| choice.trim().to_string())
+                .filter(|choice| !choice.is_empty())
+                .collect();
+            if choices.len() < 2 {
+                return Err(anyhow!("Choice kind needs at least two values: {}", value));
+            }
+            return Ok(Self::Choice(choices));
+        }
+        match value.to_lowercase().as_str() {
+            "list" => Ok(Self::List),
+            "text" => Ok(Self::Text),
+            "integer" => Ok(Self::Integer),
+            "number" => Ok(Self::Number),
+            "boolean" => Ok(Self::Boolean),
+            other => Err(anyhow!(
+                "Unknown entity kind: {} (expected list, text, integer, number, boolean, or a|b|c)",
+                other
+            )),
+        }
+    }
+
+    /// DuckDB type of the output column.
+    pub fn sql_type(&self) -> &'static str {
+        match self {
+            Self::List => "VARCHAR[]",
+            Self::Text | Self::Choice(_) => "VARCHAR",
+            Self::Integer => "BIGINT",
+            Self::Number => "DOUBLE",
+            Self::Boolean => "BOOLEAN",
+        }
+    }
+
+    /// JSON schema of the entity's value; every entity may also be null.
+    fn json_schema(&self) -> Value {
+        match self {
+            Self::List => json!({"type": ["array", "null"], "items": {"type": "string"}}),
+            Self::Text => json!({"type": ["string", "null"]}),
+            Self::Integer => json!({"type": ["integer", "null"]}),
+            Self::Number => json!({"type": ["number", "null"]}),
+            Self::Boolean => json!({"type": ["boolean", "null"]}),
+            Self::Choice(choices) => {
+                let mut values: Vec<Value> = choices.iter().map(|c| json!(c)).collect();
+                values.push(Value::Null);
+                json!({ "enum": values })
+            }
+        }
+    }
+
+    /// Checks `value` against the kind, normalizing it where unambiguous.
+    fn validate(&self, value: &Value) -> std::result::Result<Value, String> {
+        if value.is_null() {
+            return Ok(Value::Null);
+        }
+        match self {
+            Self::List => match value {
+                Value::Array(items) => items
+                    .iter()
+                    .map(|item| match item {
+                        Value::String(_) => Ok(item.clone()),
+                        other => Err(format!("expected strings, got {}", other)),
+                    })
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map(Value::Array),
+                // A lone mention is accepted as a one-element list
+                Value::String(_) => Ok(json!([value])),
+                other => Err(format!("expected an array, got {}", other)),
+            },
+            Self::Text => match value {
+                Value::String(_) => Ok(value.clone()),
+                other => Err(format!("expected a string, got {}", other)),
+            },
+            Self::Integer => match value.as_i64() {
+                Some(n) => Ok(json!(n)),
+                None => match value.as_f64() {
+                    Some(f) if f.fract() == 0.0 => Ok(json!(f as i64)),
+                    _ => Err(format!("expected an integer, got {}", value)),
+                },
+            },
+            Self::Number => match value {
+                Value::Number(_) => Ok(value.clone()),
+                other => Err(format!("expected a number, got {}", other)),
+            },
+            Self::Boolean => match value {
+                Value::Bool(_) => Ok(value.clone()),
+                other => Err(format!("expected true or false, got {}", other)),
+            },
+            Self::Choice(choices) => {
+                let text = value.as_str().unwrap_or_default();
+                choices
+                    .iter()
+                    .find(|choice| choice.eq_ignore_ascii_case(text.trim()))
+                    .map(|choice| json!(choice))
+                    .ok_or_else(|| format!("expected one of {}, got {}", choices.join("|"), value))
+            }
+        }
+    }
+}
+
+/// One entity to extract.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntitySpec {
+    /// Output column and JSON key
+    pub name: String,
+    /// Value type
+    pub kind: EntityKind,
+}
+
+impl EntitySpec {
+    /// Parses `name[:kind]`; `sentiment` defaults to a positive/negative/
+    /// neutral/mixed choice and every other name to a list.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (name, kind) = match spec.split_once(':') {
+            Some((name, kind)) => (name.trim(), Some(kind.trim())),
+            None => (spec.trim(), None),
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(anyhow!(
+                "Invalid entity name: '{}' (use letters, digits, and underscores)",
+                name
+            ));
+        }
+        let kind = match kind {
+            Some(kind) => EntityKind::parse(kind)?,
+            None if name.eq_ignore_ascii_case("sentiment") => {
+                EntityKind::Choice(SENTIMENT_VALUES.iter().map(|v| v.to_string()).collect())
+            }
+            None => EntityKind::List,
+        };
+        Ok(Self {
+            name: name.to_string(),
+            kind,
+        })
+    }
+
+    /// Parses a comma-separated list of specs.
+    pub fn parse_list(specs: &str) -> Result<Vec<Self>> {
+        let entities = specs
+            .split(',')
+            .filter(|spec| !spec.trim().is_empty())
+            .map(Self::parse)
+            .collect::<Result<Vec<_>>>()?;
+        if entities.is_empty() {
+            return Err(anyhow!("At least one entity is required"));
+        }
+        for (i, entity) in entities.iter().enumerate() {
+            if entities[..i]
+                .iter()
+                .any(|e| e.name.eq_ignore_ascii_case(&entity.name))
+            {
+                return Err(anyhow!("Duplicate entity: {}", entity.name));
+            }
+        }
+        Ok(entities)
+    }
+}
+
+/// JSON schema of the object the model must return.
+pub fn json_schema(entities: &[EntitySpec]) -> Value {
+    let properties: Map<String, Value> = entities
+        .iter()
+        .map(|entity| (entity.name.clone(), entity.kind.json_schema()))
+        .collect();
+    let required: Vec<&str> = entities.iter().map(|entity| entity.name.as_str()).collect();
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+/// Prompt sent with every row.
+pub fn extraction_prompt(entities: &[EntitySpec]) -> String {
+    format!(
+        "Extract the following fields from the text: {}. Reply with only a JSON object \
+         matching this JSON schema, using null for fields the text does not mention:\n{}",
+        entities
+            .iter()
+            .map(|entity| entity.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+        json_schema(entities)
+    )
+}
+
+/// Parses and validates one model response against `entities`.
+///
+/// The JSON object may be wrapped in prose or a code fence. Keys match
+/// case-insensitively; missing keys become null and unknown keys are
+/// ignored. The returned object holds exactly the entity keys.
+pub fn parse_response(response: &str, entities: &[EntitySpec]) -> Result<Map<String, Value>> {
+    let start = response
+        .find('{')
+        .ok_or_else(|| anyhow!("response contains no JSON object"))?;
+    let end = response
+        .rfind('}')
+        .filter(|&end| end > start)
+        .ok_or_else(|| anyhow!("response contains no JSON object"))?;
+    let object: Map<String, Value> =
+        serde_json::from_str(&response[start..=end]).context("response is not valid JSON")?;
+
+    let mut extracted = Map::new();
+    for entity in entities {
+        let value = object
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(&entity.name))
+            .map(|(_, value)| value)
+            .unwrap_or(&Value::Null);
+        let value = entity
+            .kind
+            .validate(value)
+            .map_err(|e| anyhow!("{}: {}", entity.name, e))?;
+        extracted.insert(entity.name.clone(), value);
+    }
+    Ok(extracted)
+}
+
+/// Produces one raw model response per text for [`extract_entities`].
+pub trait EntityExtractor {
+    /// Answers `prompt` for each text, in order.
+    fn complete(&self, prompt: &str, texts: &[String]) -> Result<Vec<String>>;
+}
+
+/// [`FlockManager`] bound to a model, usable as an [`EntityExtractor`].
+pub struct FlockExtractor<'a> {
+    /// Flock manager used for completions
+    pub manager: &'a FlockManager,
+    /// Model alias configured during flock-setup
+    pub model: String,
+}
+
+impl EntityExtractor for FlockExtractor<'_> {
+    fn complete(&self, prompt: &str, texts: &[String]) -> Result<Vec<String>> {
+        Ok(self.manager.complete_rows(prompt, texts, &self.model)?)
+    }
+}
+
+/// Options for [`extract_entities`].
+#[derive(Debug, Clone)]
+pub struct ExtractOptions {
+    /// Text column to extract from
+    pub column: String,
+    /// Rows sent to the extractor per call
+    pub batch_size: usize,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            column: "text".to_string(),
+            batch_size: 256,
+        }
+    }
+}
+
+/// Summary of an extraction run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractReport {
+    /// Rows written
+    pub rows: usize,
+    /// Rows sent to the model
+    pub extracted: usize,
+    /// Rows whose response failed validation
+    pub failures: usize,
+}
+
+/// Extracts `entities` from a text column of `input` and writes `output`.
+///
+/// The output keeps every input column, followed by one typed column per
+/// entity and [`ERROR_COLUMN`]. Rows with a NULL or empty text are not sent
+/// to the model.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::extraction::{extract_entities, EntitySpec, ExtractOptions, FlockExtractor};
+/// use frozen_duckdb::cli::{DatasetManager, FlockManager};
+///
+/// let manager = DatasetManager::new()?;
+/// let flock = FlockManager::new()?;
+/// let extractor = FlockExtractor { manager: &flock, model: "text_generator".to_string() };
+/// let entities = EntitySpec::parse_list("person,product,sentiment")?;
+///
+/// let report = extract_entities(
+///     manager.connection(),
+///     "reviews.parquet",
+///     &entities,
+///     &extractor,
+///     "extracted.parquet",
+///     &ExtractOptions::default(),
+/// )?;
+/// println!("{} of {} rows failed", report.failures, report.extracted);
+/// ```
+pub fn extract_entities(
+    conn: &Connection,
+    input: &str,
+    entities: &[EntitySpec],
+    extractor: &dyn EntityExtractor,
+    output: &str,
+    options: &ExtractOptions,
+) -> Result<ExtractReport> {
+    if entities.is_empty() {
+        return Err(anyhow!("At least one entity is required"));
+    }
+    let format = copy_format(output)?;
+
+    conn.execute_batch(&format!(
+        "CREATE OR REPLACE TEMP TABLE extract_input AS
+         SELECT row_number() OVER () AS __row, * FROM {};
+         CREATE OR REPLACE TEMP TABLE extract_results
+             (__row BIGINT, extracted VARCHAR, {} VARCHAR);",
+        read_function(input)?,
+        ERROR_COLUMN
+    ))
+    .context("Failed to read extraction input")?;
+
+    let mut stmt = conn.prepare("SELECT column_name FROM (DESCRIBE extract_input)")?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    if !columns.contains(&options.column) {
+        return Err(anyhow!("Column {} not found in {}", options.column, input));
+    }
+    for name in entities
+        .iter()
+        .map(|e| e.name.as_str())
+        .chain([ERROR_COLUMN])
+    {
+        if columns
+            .iter()
+            .any(|column| column.eq_ignore_ascii_case(name))
+        {
+            return Err(anyhow!("Input already has a column named {}", name));
+        }
+    }
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT __row, CAST({} AS VARCHAR) FROM extract_input
+         WHERE nullif(trim(CAST({0} AS VARCHAR)), '') IS NOT NULL ORDER BY __row",
+        quote_identifier(&options.column)
+    ))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let total: i64 = conn.query_row("SELECT count(*) FROM extract_input", [], |row| row.get(0))?;
+
+    let prompt = extraction_prompt(entities);
+    let mut failures = 0;
+    for (batch_number, batch) in rows.chunks(options.batch_size.max(1)).enumerate() {
+        let texts: Vec<String> = batch.iter().map(|(_, text)| text.clone()).collect();
+        let responses = extractor.complete(&prompt, &texts)?;
+        if responses.len() != texts.len() {
+            return Err(anyhow!(
+                "Expected {} responses but the model returned {}",
+                texts.len(),
+                responses.len()
+            ));
+        }
+
+        let tx = conn.unchecked_transaction()?;
+        let mut insert = tx.prepare("INSERT INTO extract_results VALUES ($1, $2, $3)")?;
+        for ((row, _), response) in batch.iter().zip(&responses) {
+            match parse_response(response, entities) {
+                Ok(extracted) => {
+                    let json = Value::Object(extracted).to_string();
+                    insert.execute(params![row, Some(json), None::<String>])?;
+                }
+                Err(e) => {
+                    failures += 1;
+                    insert.execute(params![row, None::<String>, Some(format!("{:#}", e))])?;
+                }
+            }
+        }
+        drop(insert);
+        tx.commit()?;
+        info!(
+            "🔎 Extracted batch {} ({} rows, {} failures so far)",
+            batch_number + 1,
+            batch.len(),
+            failures
+        );
+    }
+
+    let typed_columns = entities
+        .iter()
+        .map(|entity| {
+            let path = format!("'$.\"{}\"'", entity.name);
+            let expression = match entity.kind {
+                EntityKind::List => {
+                    format!("CAST(json_extract(r.extracted, {}) AS VARCHAR[])", path)
+                }
+                _ => format!(
+                    "CAST(json_extract_string(r.extracted, {}) AS {})",
+                    path,
+                    entity.kind.sql_type()
+                ),
+            };
+            format!("{} AS {}", expression, quote_identifier(&entity.name))
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    conn.execute_batch(&format!(
+        "COPY (
+             SELECT i.* EXCLUDE (__row), {}, r.{}
+             FROM extract_input i LEFT JOIN extract_results r USING (__row)
+             ORDER BY i.__row
+         ) TO {} ({});
+         DROP TABLE extract_input;
+         DROP TABLE extract_results;",
+        typed_columns,
+        ERROR_COLUMN,
+        path_literal(output),
+        format
+    ))
+    .with_context(|| format!("Failed to write {}", output))?;
+
+    Ok(ExtractReport {
+        rows: total as usize,
+        extracted: rows.len(),
+        failures,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Answers with a canned response per text.
+    struct CannedExtractor;
+
+    impl EntityExtractor for CannedExtractor {
+        fn complete(&self, _prompt: &str, texts: &[String]) -> Result<Vec<String>> {
+            Ok(texts
+                .iter()
+                .map(|text| match text.as_str() {
+                    "Alice loves the X1 phone" => "```json\n{\"person\": [\"Alice\"], \
+                         \"product\": \"X1\", \"Sentiment\": \"Positive\", \"stars\": 5.0}\n```"
+                        .to_string(),
+                    "Bob hates it" => "{\"person\": [\"Bob\"], \"product\": null, \
+                         \"sentiment\": \"angry\", \"stars\": 1}"
+                        .to_string(),
+                    _ => "I cannot answer that.".to_string(),
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_entity_spec_parse() -> Result<()> {
+        let entities = EntitySpec::parse_list("person, sentiment,stars:integer,priority:low|high")?;
+        assert_eq!(entities[0].kind, EntityKind::List);
+        assert_eq!(entities[1].kind.sql_type(), "VARCHAR");
+        assert!(matches!(&entities[1].kind, EntityKind::Choice(c) if c.len() == 4));
+        assert_eq!(entities[2].kind, EntityKind::Integer);
+        assert_eq!(
+            entities[3].kind,
+            EntityKind::Choice(vec!["low".to_string(), "high".to_string()])
+        );
+
+        assert!(EntitySpec::parse("bad name").is_err());
+        assert!(EntitySpec::parse("x:decimal").is_err());
+        assert!(EntitySpec::parse_list("a,A").is_err());
+        assert!(EntitySpec::parse_list(" , ").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_schema_and_validation() -> Result<()> {
+        let entities = EntitySpec::parse_list("person,sentiment,stars:integer,refund:boolean")?;
+        let schema = json_schema(&entities);
+        assert_eq!(schema["required"].as_array().unwrap().len(), 4);
+        assert_eq!(
+            schema["properties"]["stars"]["type"],
+            json!(["integer", "null"])
+        );
+
+        let extracted = parse_response(
+            "Sure! {\"person\": \"Ann\", \"SENTIMENT\": \"Neutral\", \"stars\": 4.0, \"extra\": 1}",
+            &entities,
+        )?;
+        assert_eq!(extracted["person"], json!(["Ann"]));
+        assert_eq!(extracted["sentiment"], json!("neutral"));
+        assert_eq!(extracted["stars"], json!(4));
+        assert_eq!(extracted["refund"], Value::Null);
+        assert_eq!(extracted.len(), 4);
+
+        assert!(parse_response("{\"stars\": 4.5}", &entities).is_err());
+        assert!(parse_response("{\"refund\": \"yes\"}", &entities).is_err());
+        assert!(parse_response("no json here", &entities).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_entities_writes_typed_columns() -> Result<()> {
+        let dir = TempDir::new()?;
+        let input = dir.path().join("reviews.csv");
+        let output = dir.path().join("extracted.parquet");
+        std::fs::write(
+            &input,
+            "id,text\n1,Alice loves the X1 phone\n2,Bob hates it\n3,???\n4,\n",
+        )?;
+
+        let conn = Connection::open_in_memory()?;
+        let entities = EntitySpec::parse_list("person,product:text,sentiment,stars:integer")?;
+        let report = extract_entities(
+            &conn,
+            input.to_str().unwrap(),
+            &entities,
+            &CannedExtractor,
+            output.to_str().unwrap(),
+            &ExtractOptions {
+                batch_size: 2,
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(
+            report,
+            ExtractReport {
+                rows: 4,
+                extracted: 3,
+                failures: 2
+            }
+        );
+
+        let (person, product, sentiment, stars): (String, String, String, i64) = conn.query_row(
+            &format!(
+                "SELECT CAST(person AS VARCHAR), product, sentiment, stars
+                 FROM read_parquet('{}') WHERE id = 1",
+                output.display()
+            ),
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+        assert_eq!(person, "[Alice]");
+        assert_eq!(product, "X1");
+        assert_eq!(sentiment, "positive");
+        assert_eq!(stars, 5);
+
+        let errors: Vec<Option<String>> = conn
+            .prepare(&format!(
+                "SELECT {} FROM read_parquet('{}') ORDER BY id",
+                ERROR_COLUMN,
+                output.display()
+            ))?
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<_, _>>()?;
+        assert_eq!(errors[0], None);
+        assert!(errors[1]
+            .as_deref()
+            .unwrap()
+            .starts_with("sentiment: expected one of"));
+        assert!(errors[2].as_deref().unwrap().contains("no JSON object"));
+        assert_eq!(errors[3], None);
+        Ok(())
+    }
+}
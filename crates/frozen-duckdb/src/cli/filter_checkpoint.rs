@@ -0,0 +1,301 @@
+//! # Resumable Output for the Filter Command
+//!
+//! This module streams `filter` results to a partial results file as each
+//! line is classified and records progress in a sidecar state file, so an
+//! interrupted run over a large input can be continued with `--resume`
+//! instead of starting over. When every line is done, the partial results
+//! are written to the output as a table (see [`super::result_table`]).
+//!
+//! ## Files
+//!
+//! - **Partial results** (`<output>.partial.jsonl`): one
+//!   `{"line", "item", "matched", "model", "latency_ms"}` object per result,
+//!   in completion order. With `--positive-only` only matching lines are
+//!   written.
+//! - **State** (`<output>.state`): a JSON header identifying the run
+//!   (input, criteria, model), followed by one processed line number per line.
+//!
+//! Each result is written to the partial file before its line number is
+//! added to the state file. On resume, line numbers found in either file
+//! are skipped, so no line is classified or written twice.
+
+use super::result_table::{export_jsonl, OutputFormat};
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Columns of the filter results table, in output order.
+pub const RESULT_COLUMNS: &[(&str, &str)] = &[
+    ("line", "BIGINT"),
+    ("item", "VARCHAR"),
+    ("matched", "BOOLEAN"),
+    ("model", "VARCHAR"),
+    ("latency_ms", "BIGINT"),
+];
+
+/// Streaming, resumable writer for filter results.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::filter_checkpoint::FilterCheckpoint;
+/// use frozen_duckdb::cli::result_table::OutputFormat;
+/// use std::time::Duration;
+///
+/// let mut checkpoint = FilterCheckpoint::open("matches.parquet", "items.txt", "Is this spam?", "fast", true)?;
+/// if !checkpoint.is_done(42) {
+///     checkpoint.record(42, "Buy now!!!", true, Duration::from_millis(850), false)?;
+/// }
+/// checkpoint.finish(OutputFormat::Parquet)?;
+/// ```
+pub struct FilterCheckpoint {
+    output: String,
+    model: String,
+    partial: File,
+    state: File,
+    done: HashSet<usize>,
+}
+
+impl FilterCheckpoint {
+    /// Opens the partial results and state files for a run writing to `output`.
+    ///
+    /// Without `resume`, an existing state file is an error so a previous
+    /// run's progress is never silently discarded. With `resume`, the state
+    /// must belong to the same input, criteria, and model.
+    pub fn open(
+        output: &str,
+        input: &str,
+        criteria: &str,
+        model: &str,
+        resume: bool,
+    ) -> Result<Self> {
+        let state_path = state_path(output);
+        let partial_path = partial_path(output);
+        let header = serde_json::json!({
+            "input": input,
+            "criteria": criteria,
+            "model": model,
+        });
+
+        let mut done = HashSet::new();
+        if state_path.exists() {
+            if !resume {
+                return Err(anyhow::anyhow!(
+                    "Found progress from a previous run in {}; use --resume to continue or delete it to start over",
+                    state_path.display()
+                ));
+            }
+            done = read_state(&state_path, &header)?;
+            done.extend(read_result_lines(&partial_path)?);
+        } else {
+            // A fresh run replaces any earlier partial results
+            fs::write(&state_path, format!("{}\n", header)).with_context(|| {
+                format!("Failed to create state file: {}", state_path.display())
+            })?;
+            File::create(&partial_path).with_context(|| {
+                format!("Failed to create results file: {}", partial_path.display())
+            })?;
+        }
+
+        let append = |path: &Path| {
+            OpenOptions::new()
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open {}", path.display()))
+        };
+        Ok(Self {
+            output: output.to_string(),
+            model: model.to_string(),
+            partial: append(&partial_path)?,
+            state: append(&state_path)?,
+            done,
+        })
+    }
+
+    /// Returns whether `line` was processed by an earlier run.
+    pub fn is_done(&self, line: usize) -> bool {
+        self.done.contains(&line)
+    }
+
+    /// Number of lines processed by earlier runs.
+    pub fn done_count(&self) -> usize {
+        self.done.len()
+    }
+
+    /// Records the result for `line`, writing it to the partial results
+    /// unless `positive_only` is set and the line doesn't match.
+    pub fn record(
+        &mut self,
+        line: usize,
+        item: &str,
+        matched: bool,
+        latency: Duration,
+        positive_only: bool,
+    ) -> Result<()> {
+        if matched || !positive_only {
+            let entry = serde_json::json!({
+                "line": line,
+                "item": item,
+                "matched": matched,
+                "model": self.model,
+                "latency_ms": latency.as_millis() as u64,
+            });
+            writeln!(self.partial, "{}", entry)?;
+            self.partial.flush()?;
+        }
+        writeln!(self.state, "{}", line)?;
+        self.state.flush()?;
+        self.done.insert(line);
+        Ok(())
+    }
+
+    /// Writes the results, ordered by line, to the output in `format` and
+    /// removes the partial results and state files. Returns the row count.
+    pub fn finish(self, format: OutputFormat) -> Result<usize> {
+        let partial_path = partial_path(&self.output);
+        let rows = export_jsonl(
+            &partial_path.to_string_lossy(),
+            RESULT_COLUMNS,
+            Some("line"),
+            &self.output,
+            format,
+        )?;
+
+        drop(self.partial);
+        drop(self.state);
+        fs::remove_file(&partial_path)?;
+        fs::remove_file(state_path(&self.output))?;
+        Ok(rows)
+    }
+}
+
+/// Returns the sidecar state file for `output`, i.e. `<output>.state`.
+pub fn state_path(output: &str) -> PathBuf {
+    PathBuf::from(format!("{}.state", output))
+}
+
+/// Returns the partial results file for `output`, i.e. `<output>.partial.jsonl`.
+pub fn partial_path(output: &str) -> PathBuf {
+    PathBuf::from(format!("{}.partial.jsonl", output))
+}
+
+fn read_state(path: &Path, expected_header: &Value) -> Result<HashSet<usize>> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to read state file: {}", path.display()))?;
+    let mut lines = BufReader::new(file).lines();
+
+    let header: Value = match lines.next() {
+        Some(line) => serde_json::from_str(&line?).context("Corrupt filter state header")?,
+        None => Value::Null,
+    };
+    if &header != expected_header {
+        return Err(anyhow::anyhow!(
+            "State file {} belongs to a different run ({}); delete it to start over",
+            path.display(),
+            header
+        ));
+    }
+
+    let mut done = HashSet::new();
+    for line in lines {
+        // A torn final line from an interrupted write is ignored
+        if let Ok(number) = line?.trim().parse() {
+            done.insert(number);
+        }
+    }
+    Ok(done)
+}
+
+fn read_result_lines(path: &Path) -> Result<HashSet<usize>> {
+    let Ok(file) = File::open(path) else {
+        return Ok(HashSet::new());
+    };
+
+    let mut done = HashSet::new();
+    for line in BufReader::new(file).lines() {
+        let number = serde_json::from_str::<Value>(&line?)
+            .ok()
+            .and_then(|entry| entry.get("line").and_then(Value::as_u64));
+        if let Some(number) = number {
+            done.insert(number as usize);
+        }
+    }
+    Ok(done)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_skips_recorded_lines() {
+        let temp = tempfile::tempdir().unwrap();
+        let output = temp.path().join("out.jsonl");
+        let output = output.to_str().unwrap();
+        let latency = Duration::from_millis(120);
+
+        let mut first = FilterCheckpoint::open(output, "in.txt", "spam?", "fast", false).unwrap();
+        first.record(1, "buy now", true, latency, true).unwrap();
+        first.record(2, "hello", false, latency, true).unwrap();
+        drop(first);
+
+        // Interrupted after writing line 3's result but before its state
+        fs::OpenOptions::new()
+            .append(true)
+            .open(partial_path(output))
+            .unwrap()
+            .write_all(b"{\"line\":3,\"item\":\"win\",\"matched\":true,\"model\":\"fast\",\"latency_ms\":90}\n")
+            .unwrap();
+
+        assert!(FilterCheckpoint::open(output, "in.txt", "spam?", "fast", false).is_err());
+        assert!(FilterCheckpoint::open(output, "in.txt", "spam?", "slow", true).is_err());
+
+        let mut resumed = FilterCheckpoint::open(output, "in.txt", "spam?", "fast", true).unwrap();
+        assert_eq!(resumed.done_count(), 3);
+        assert!(resumed.is_done(2) && resumed.is_done(3) && !resumed.is_done(4));
+        resumed
+            .record(4, "free money", true, latency, true)
+            .unwrap();
+
+        let written: Vec<Value> = fs::read_to_string(partial_path(output))
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let lines: Vec<u64> = written
+            .iter()
+            .map(|e| e["line"].as_u64().unwrap())
+            .collect();
+        assert_eq!(lines, vec![1, 3, 4]);
+        assert_eq!(written[2]["model"], "fast");
+        assert_eq!(written[2]["latency_ms"], 120);
+    }
+
+    #[test]
+    fn test_finish_writes_ordered_table() {
+        let temp = tempfile::tempdir().unwrap();
+        let output = temp.path().join("out.csv");
+        let output = output.to_str().unwrap();
+
+        let mut checkpoint =
+            FilterCheckpoint::open(output, "in.txt", "spam?", "fast", false).unwrap();
+        checkpoint
+            .record(2, "hello", false, Duration::from_millis(40), false)
+            .unwrap();
+        checkpoint
+            .record(1, "buy now", true, Duration::from_millis(55), false)
+            .unwrap();
+        assert_eq!(checkpoint.finish(OutputFormat::Csv).unwrap(), 2);
+
+        assert_eq!(
+            fs::read_to_string(output).unwrap(),
+            "line,item,matched,model,latency_ms\n1,buy now,true,fast,55\n2,hello,false,fast,40\n"
+        );
+        assert!(!partial_path(output).exists());
+        assert!(!state_path(output).exists());
+    }
+}
@@ -6,8 +6,26 @@
 
 use anyhow::{Context, Result};
 use chrono;
-use duckdb::Connection;
-use tracing::info;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use super::audit_log::AuditLog;
+use super::config::{ModelAlias, ModelSettings, DEFAULT_MODEL_BATCH_SIZE};
+use super::dataset_manager::split_statements;
+use super::fusion::{self, FusionMethod};
+use super::image_input::ImageSource;
+use super::llm_recording::ResponseRecorder;
+use super::metrics::{CacheKind, LlmSource, Metrics};
+use super::rate_limit::{RateLimitConfig, RateLimiter};
+use super::response_cache::ResponseCache;
+use super::tpch_answers;
+use duckdb::types::Value;
+use duckdb::{params, Connection};
+use crate::error::FrozenDuckdbError;
+use crate::ingest::{BulkInsert, DEFAULT_FLUSH_INTERVAL};
+use crate::text::context::ContextBudget;
+use crate::text::tokens::{count_tokens, words_to_tokens, TokenEstimate, TOKENS_PER_WORD};
+use tracing::{debug, info, instrument, warn};
 
 /// Flock LLM Manager for handling LLM operations via DuckDB Flock extension.
 ///
@@ -43,11 +61,40 @@ use tracing::info;
 /// // Generate embeddings for semantic search
 /// let embeddings = manager.generate_embeddings(vec!["Python programming", "Machine learning"])?;
 /// ```
+///
+/// # Threads
+///
+/// `Send` but not `Sync`: the connection, response cache, and counters
+/// are not synchronized, so give each worker thread its own manager.
 pub struct FlockManager {
     /// DuckDB connection with Flock extension loaded
     conn: Connection,
+    /// Optional cache of LLM responses
+    cache: Option<ResponseCache>,
+    /// Number of responses served from the cache
+    cache_hits: Cell<usize>,
+    /// Rate limiter applied to every model request
+    limiter: RateLimiter,
+    /// Optional audit log of every prompt and response
+    audit: Option<AuditLog>,
+    /// Optional context window budget applied before model calls
+    context_budget: Option<ContextBudget>,
+    /// Batch size of every registered model alias
+    batch_sizes: RefCell<HashMap<String, usize>>,
+    /// Optional record/replay store for deterministic runs
+    recorder: Option<ResponseRecorder>,
 }
 
+/// Environment variable naming the Ollama server `validate-ffi` targets,
+/// e.g. a mock server in CI.
+pub const OLLAMA_URL_ENV: &str = "FROZEN_DUCKDB_OLLAMA_URL";
+
+/// Ollama server `validate-ffi` targets by default.
+pub const DEFAULT_OLLAMA_URL: &str = "http://127.0.0.1:11434";
+
+/// Model calls [`FlockManager::nl_to_sql`] makes before giving up on invalid SQL.
+const NL_TO_SQL_ATTEMPTS: usize = 2;
+
 impl FlockManager {
     /// Creates a new FlockManager with Flock extension loaded.
     ///
@@ -94,7 +141,195 @@ impl FlockManager {
         conn.execute_batch("INSTALL flock FROM community; LOAD flock;")
             .context("Failed to load Flock extension")?;
 
-        Ok(Self { conn })
+        Ok(Self {
+            conn,
+            cache: None,
+            cache_hits: Cell::new(0),
+            limiter: RateLimiter::default(),
+            audit: None,
+            context_budget: None,
+            batch_sizes: RefCell::new(HashMap::new()),
+            recorder: None,
+        })
+    }
+
+    /// Applies request rate and concurrency limits to all LLM operations.
+    ///
+    /// Operations over many rows query one model batch at a time, so every
+    /// request Flock sends waits for its own permit.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::rate_limit::RateLimitConfig;
+    /// use frozen_duckdb::cli::FlockManager;
+    ///
+    /// let limits = RateLimitConfig { requests_per_second: Some(2.0), burst: 4, max_in_flight: 2 };
+    /// let manager = FlockManager::new()?.with_rate_limits(limits)?;
+    /// ```
+    pub fn with_rate_limits(mut self, config: RateLimitConfig) -> Result<Self> {
+        config.validate()?;
+        self.limiter = RateLimiter::new(config);
+        Ok(self)
+    }
+
+    /// Enables caching of LLM responses for completion, filtering, and summarization.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::response_cache::ResponseCache;
+    /// use frozen_duckdb::cli::FlockManager;
+    ///
+    /// let manager = FlockManager::new()?.with_response_cache(ResponseCache::open_default()?);
+    /// ```
+    pub fn with_response_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Returns how many responses were served from the cache so far.
+    pub fn cache_hits(&self) -> usize {
+        self.cache_hits.get()
+    }
+
+    /// Records every prompt and response of this manager in `audit`.
+    pub fn with_audit_log(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Returns the audit log of this manager, if auditing is enabled.
+    pub fn audit_log(&self) -> Option<&AuditLog> {
+        self.audit.as_ref()
+    }
+
+    /// Records model responses to, or replays them from, `recorder`.
+    ///
+    /// Replayed responses bypass both the response cache and the model;
+    /// a prompt missing from the store is an error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::llm_recording::{LlmMode, ResponseRecorder};
+    /// use frozen_duckdb::cli::FlockManager;
+    ///
+    /// let recorder = ResponseRecorder::open(LlmMode::Replay, "tests/fixtures/llm.jsonl")?;
+    /// let manager = FlockManager::new()?.with_recorder(recorder);
+    /// ```
+    pub fn with_recorder(mut self, recorder: ResponseRecorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Returns the recorded response for `prompt` when replaying.
+    fn replayed(&self, model: &str, prompt: &str, params: &str) -> Result<Option<String>> {
+        match &self.recorder {
+            Some(recorder) => recorder.lookup(model, prompt, params),
+            None => Ok(None),
+        }
+    }
+
+    /// Stores `response` for `prompt` when recording.
+    fn record(&self, model: &str, prompt: &str, params: &str, response: &str) -> Result<()> {
+        match &self.recorder {
+            Some(recorder) => recorder.record(model, prompt, params, response),
+            None => Ok(()),
+        }
+    }
+
+    /// Fits context to `budget` before completion, summarization, and
+    /// per-row calls, instead of letting oversized inputs fail in the model.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::FlockManager;
+    /// use frozen_duckdb::text::context::{ContextBudget, ContextStrategy};
+    ///
+    /// let budget = ContextBudget {
+    ///     max_tokens: 4096,
+    ///     strategy: ContextStrategy::Summarize,
+    ///     ..Default::default()
+    /// };
+    /// let manager = FlockManager::new()?.with_context_budget(budget);
+    /// ```
+    pub fn with_context_budget(mut self, budget: ContextBudget) -> Self {
+        self.context_budget = Some(budget);
+        self
+    }
+
+    /// Applies the context budget, if one is configured, to `documents`
+    /// sent with `prompt`, logging every truncation, drop, and summary.
+    fn fit_context(&self, prompt: &str, documents: Vec<String>, model: &str) -> Result<Vec<String>> {
+        let Some(budget) = &self.context_budget else {
+            return Ok(documents);
+        };
+        let fitted = budget.fit(prompt, documents, |text, max_tokens| {
+            let max_words = ((max_tokens as f64 / TOKENS_PER_WORD) as usize).max(1);
+            self.generate_summary(&[text.to_string()], "concat", max_words, model)
+        })?;
+        for action in &fitted.actions {
+            warn!("✂️  Context exceeds the {}-token window: {}", budget.max_tokens, action);
+        }
+        Ok(fitted.documents)
+    }
+
+    /// Runs `generate` through the response cache, if one is configured,
+    /// and records the interaction in the audit log and metrics.
+    ///
+    /// In replay mode the recorded response is returned instead; in record
+    /// mode every response, cached or not, is added to the store.
+    fn cached_response<F>(
+        &self,
+        model: &str,
+        prompt: &str,
+        params: &str,
+        generate: F,
+    ) -> Result<String>
+    where
+        F: FnOnce() -> Result<String>,
+    {
+        let started = Instant::now();
+        let metrics = Metrics::global();
+        if let Some(response) = self.replayed(model, prompt, params)? {
+            metrics.record_llm_call(model, LlmSource::Replay);
+            if let Some(audit) = &self.audit {
+                audit.record(model, prompt, &response, started.elapsed(), true)?;
+            }
+            return Ok(response);
+        }
+        let cached = match &self.cache {
+            Some(cache) => {
+                let cached = cache.get(model, prompt, params)?;
+                metrics.record_cache_lookup(CacheKind::Response, cached.is_some());
+                cached
+            }
+            None => None,
+        };
+
+        let response = match cached {
+            Some(ref response) => {
+                self.cache_hits.set(self.cache_hits.get() + 1);
+                metrics.record_llm_call(model, LlmSource::Cache);
+                response.clone()
+            }
+            None => {
+                let response = generate()?;
+                metrics.record_llm_call(model, LlmSource::Model);
+                if let Some(cache) = &self.cache {
+                    cache.put(model, prompt, params, &response)?;
+                }
+                response
+            }
+        };
+        self.record(model, prompt, params, &response)?;
+
+        if let Some(audit) = &self.audit {
+            audit.record(model, prompt, &response, started.elapsed(), cached.is_some())?;
+        }
+        Ok(response)
     }
 
     /// Setup Ollama models and secrets for Flock LLM operations.
@@ -108,6 +343,7 @@ impl FlockManager {
     /// * `ollama_url` - URL where Ollama server is running
     /// * `text_model` - Model name for text generation (e.g., "llama3.1:8b")
     /// * `embedding_model` - Model name for embedding generation (e.g., "mxbai-embed-large")
+    /// * `settings` - Batch size and generation options for both models
     /// * `skip_verification` - Skip checking if models are available
     ///
     /// # Returns
@@ -117,10 +353,17 @@ impl FlockManager {
     /// # Examples
     ///
     /// ```rust
+    /// use frozen_duckdb::cli::config::ModelSettings;
     /// use frozen_duckdb::cli::FlockManager;
     ///
     /// let manager = FlockManager::new()?;
-    /// manager.setup_ollama("http://localhost:11434", "llama3.1:8b", "mxbai-embed-large", false)?;
+    /// manager.setup_ollama(
+    ///     "http://localhost:11434",
+    ///     "llama3.1:8b",
+    ///     "mxbai-embed-large",
+    ///     &ModelSettings::default(),
+    ///     false,
+    /// )?;
     /// ```
     ///
     /// # Models Configured
@@ -132,11 +375,13 @@ impl FlockManager {
     ///
     /// - **Setup time**: <1s (excluding model downloads)
     /// - **Model verification**: <5s per model
+    #[instrument(skip_all, fields(model = %text_model))]
     pub fn setup_ollama(
         &self,
         ollama_url: &str,
         text_model: &str,
         embedding_model: &str,
+        settings: &ModelSettings,
         skip_verification: bool,
     ) -> Result<()> {
         info!("🔧 Setting up Ollama integration for Flock LLM operations");
@@ -144,7 +389,32 @@ impl FlockManager {
         info!("   Text model: {}", text_model);
         info!("   Embedding model: {}", embedding_model);
 
-        // Create Ollama secret
+        self.create_ollama_secret(ollama_url);
+
+        // Create models with user-specified names and proper Ollama configuration
+        for (model_alias, model_spec) in [("text_generator", text_model), ("embedder", embedding_model)] {
+            let mut alias = ModelAlias::ollama(model_alias, model_spec);
+            alias.settings = settings.clone();
+            self.register_model(&alias)?;
+            info!("✅ Created model: {} ({})", model_alias, model_spec);
+        }
+
+        if !skip_verification {
+            info!("🔍 Verifying model availability...");
+            // Note: Model verification would require actual API calls to Ollama
+            // For now, we assume models are available if setup succeeds
+            info!("✅ Model verification completed");
+        }
+
+        info!("🎉 Ollama setup complete! Ready for LLM operations.");
+        Ok(())
+    }
+
+    /// Registers the Ollama secret pointing Flock at `ollama_url`.
+    ///
+    /// An existing secret is left in place.
+    #[instrument(skip_all)]
+    pub fn create_ollama_secret(&self, ollama_url: &str) {
         let secret_result = self.conn.execute(
             "CREATE SECRET ollama_secret (TYPE OLLAMA, API_URL ?)",
             [&ollama_url],
@@ -155,35 +425,65 @@ impl FlockManager {
         } else {
             info!("✅ Created Ollama secret");
         }
+    }
 
-        // Create models with user-specified names and proper Ollama configuration
-        let models = [
-            ("text_generator", text_model),
-            ("embedder", embedding_model),
-        ];
+    /// Registers a model alias with Flock, updating it if it already exists.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::config::ModelAlias;
+    /// use frozen_duckdb::cli::FlockManager;
+    ///
+    /// let manager = FlockManager::new()?;
+    /// manager.register_model(&ModelAlias::ollama("fast", "llama3.2:1b"))?;
+    /// let response = manager.complete_text("Explain recursion in programming", "fast")?;
+    /// ```
+    #[instrument(skip_all, fields(model = %alias.name))]
+    pub fn register_model(&self, alias: &ModelAlias) -> Result<()> {
+        let options = model_options(&alias.settings);
+        let params = [&alias.name, &alias.model, &alias.provider];
+
+        if let Err(e) = self
+            .conn
+            .execute(&format!("CREATE MODEL(?, ?, ?, {})", options), params)
+        {
+            debug!("Model '{}' already exists, updating: {}", alias.name, e);
+            self.conn
+                .execute(&format!("UPDATE MODEL(?, ?, ?, {})", options), params)
+                .with_context(|| format!("Failed to register model '{}'", alias.name))?;
+        }
 
-        for (model_alias, model_spec) in &models {
-            let model_result = self.conn.execute(
-                "CREATE MODEL(?, ?, 'ollama', {'tuple_format': 'json', 'batch_size': 32, 'model_parameters': {'temperature': 0.7}})",
-                [&model_alias, &model_spec],
-            );
+        self.batch_sizes
+            .borrow_mut()
+            .insert(alias.name.clone(), alias.settings.batch_size());
+        debug!("Registered model: {} ({}) with {}", alias.name, alias.model, options);
+        Ok(())
+    }
 
-            if let Err(e) = model_result {
-                info!("ℹ️  Model '{}' might already exist: {}", model_alias, e);
-            } else {
-                info!("✅ Created model: {} ({})", model_alias, model_spec);
-            }
-        }
+    /// Rows Flock sends to `model` per request.
+    fn batch_size(&self, model: &str) -> usize {
+        self.batch_sizes
+            .borrow()
+            .get(model)
+            .copied()
+            .unwrap_or(DEFAULT_MODEL_BATCH_SIZE)
+    }
 
-        if !skip_verification {
-            info!("🔍 Verifying model availability...");
-            // Note: Model verification would require actual API calls to Ollama
-            // For now, we assume models are available if setup succeeds
-            info!("✅ Model verification completed");
+    /// Registers every alias in `aliases` so they can be passed as `model`.
+    pub fn with_model_aliases(self, aliases: &[ModelAlias]) -> Result<Self> {
+        for alias in aliases {
+            self.register_model(alias)?;
         }
+        Ok(self)
+    }
 
-        info!("🎉 Ollama setup complete! Ready for LLM operations.");
-        Ok(())
+    /// Removes a model alias from Flock.
+    #[instrument(skip_all, fields(model = %name))]
+    pub fn delete_model(&self, name: &str) -> Result<()> {
+        self.conn
+            .execute_batch(&format!("DELETE MODEL '{}';", name.replace('\'', "''")))
+            .with_context(|| format!("Failed to delete model '{}'", name))
     }
 
     /// Generate text completions using LLM models.
@@ -222,30 +522,91 @@ impl FlockManager {
         &self,
         prompt: &str,
         model: &str,
-    ) -> Result<String> {
+    ) -> Result<String, FrozenDuckdbError> {
+        self.complete_with_images(prompt, &[], model)
+    }
+
+    /// Generate a text completion with images as additional context.
+    ///
+    /// Images are passed through Flock's `image` context column type, so
+    /// `model` must be a multimodal model (e.g. `llava` or `llama3.2-vision`).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::image_input::ImageSource;
+    /// use frozen_duckdb::cli::FlockManager;
+    ///
+    /// let manager = FlockManager::new()?;
+    /// let images = vec![ImageSource::parse("chart.png")?];
+    /// let response = manager.complete_with_images("Describe this chart", &images, "vision")?;
+    /// ```
+    #[instrument(skip_all, fields(model = %model, images = images.len()))]
+    pub fn complete_with_images(
+        &self,
+        prompt: &str,
+        images: &[ImageSource],
+        model: &str,
+    ) -> Result<String, FrozenDuckdbError> {
         info!("🤖 Generating text completion for prompt: {} using model: {}", prompt, model);
 
         // Verify Flock is ready before proceeding
         if !self.is_flock_ready()? {
-            return Err(anyhow::anyhow!("Flock extension not available. Run setup first."));
+            return Err(FrozenDuckdbError::FlockNotReady);
         }
 
-        // Create a temporary prompt for this completion
-        let prompt_name = format!("temp_prompt_{}", chrono::Utc::now().timestamp());
+        let prompt = self
+            .fit_context(&completion_prompt(""), vec![prompt.to_string()], model)?
+            .concat();
+        let prompt_content = completion_prompt(&prompt);
+        let image_data = images
+            .iter()
+            .map(|image| image.context_data(&self.conn))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut cache_key = prompt_content.clone();
+        for data in &image_data {
+            cache_key.push('\n');
+            cache_key.push_str(data);
+        }
 
-        let prompt_content = format!("Complete this text: {}", prompt);
-        self.conn.execute(
-            "CREATE PROMPT(?, ?)",
-            [&prompt_name, &prompt_content],
-        )?;
+        let result = self.cached_response(model, &cache_key, "{\"op\":\"complete\"}", || {
+            // Create a temporary prompt for this completion
+            let prompt_name = format!("temp_prompt_{}", chrono::Utc::now().timestamp());
+            self.conn.execute(
+                "CREATE PROMPT(?, ?)",
+                [&prompt_name, &prompt_content],
+            )?;
 
-        // Generate completion using the specified model
-        let result: String = self.conn.query_row(
-            "SELECT llm_complete({'model_name': ?}, {'prompt_name': ?})",
-            [model, &prompt_name],
-            |row| row.get(0),
-        )
-        .context("Failed to generate text completion - check if Ollama is running and models are available")?;
+            // Generate completion using the specified model
+            let _permit = self.limiter.acquire();
+            if image_data.is_empty() {
+                let result: String = self.conn.query_row(
+                    "SELECT llm_complete({'model_name': ?}, {'prompt_name': ?})",
+                    [model, &prompt_name],
+                    |row| row.get(0),
+                )
+                .context("Failed to generate text completion - check if Ollama is running and models are available")?;
+                return Ok(result);
+            }
+
+            let columns = vec!["{'data': ?, 'type': 'image'}"; image_data.len()].join(", ");
+            let sql = format!(
+                "SELECT llm_complete({{'model_name': ?}}, {{'prompt_name': ?, 'context_columns': [{}]}})",
+                columns
+            );
+            let params = [model, prompt_name.as_str()]
+                .into_iter()
+                .chain(image_data.iter().map(String::as_str));
+            let result: String = self.conn
+                .query_row(&sql, duckdb::params_from_iter(params), |row| row.get(0))
+                .with_context(|| format!(
+                    "Failed to generate completion with {} image(s) - make sure model '{}' is multimodal (e.g. llava, llama3.2-vision)",
+                    image_data.len(),
+                    model
+                ))?;
+            Ok(result)
+        })?;
 
         info!("✅ Text completion generated ({} chars)", result.len());
         Ok(result)
@@ -290,17 +651,36 @@ impl FlockManager {
     /// - Ollama embedder model is not configured
     /// - Network connection to Ollama fails
     /// - Embedding generation returns invalid data
+    #[instrument(skip_all, fields(model = %model, rows = texts.len()))]
     pub fn generate_embeddings(
         &self,
         texts: Vec<String>,
         model: &str,
         normalize: bool,
-    ) -> Result<Vec<Vec<f32>>> {
+    ) -> Result<Vec<Vec<f32>>, FrozenDuckdbError> {
         info!("🧠 Generating embeddings for {} texts using model: {}", texts.len(), model);
 
+        let op_params = format!("{{\"op\":\"embedding\",\"normalize\":{}}}", normalize);
+        if self.recorder.is_some() {
+            let replayed = texts
+                .iter()
+                .map(|text| self.replayed(model, text, &op_params))
+                .collect::<Result<Option<Vec<_>>>>()?;
+            if let Some(replayed) = replayed {
+                let embeddings = replayed
+                    .iter()
+                    .map(|response| {
+                        serde_json::from_str(response)
+                            .context("Recorded embedding is not an array of numbers")
+                    })
+                    .collect::<Result<_>>()?;
+                return Ok(embeddings);
+            }
+        }
+
         // Verify Flock is ready before proceeding
         if !self.is_flock_ready()? {
-            return Err(anyhow::anyhow!("Flock extension not available. Run setup first."));
+            return Err(FrozenDuckdbError::FlockNotReady);
         }
 
         // Create temporary table for texts
@@ -311,46 +691,71 @@ impl FlockManager {
             [],
         )?;
 
-        // Insert texts - fix type conversion issue
+        // Insert texts
+        let mut insert = BulkInsert::new(&self.conn, &table_name, DEFAULT_FLUSH_INTERVAL)?;
         for (i, text) in texts.iter().enumerate() {
-            self.conn.execute(
-                &format!("INSERT INTO {} VALUES (?, ?)", table_name),
-                [&(i as i32).to_string(), text],
-            )?;
+            insert.append(params![i as i32, text])?;
         }
+        insert.finish()?;
 
         // Generate embeddings using Flock
-        let embedding_table = format!("{}_embeddings", table_name);
         let normalize_clause = if normalize { "true" } else { "false" };
+        let sql = format!(
+            "SELECT llm_embedding({{'model_name': '{}'}}, {{'context_columns': [{{'data': content}}]}}, {})::FLOAT[]
+             FROM {} WHERE id >= ? AND id < ? ORDER BY id",
+            model, normalize_clause, table_name
+        );
 
-        self.conn.execute(
-            &format!(
-                "CREATE TABLE {} AS
-                 SELECT id, content,
-                        llm_embedding({{'model_name': '{}'}}, {{'context_columns': [{{'data': content}}]}}, {}) as embedding
-                 FROM {}",
-                embedding_table, model, normalize_clause, table_name
-            ),
-            [],
-        ).context("Failed to generate embeddings - check if embedder model is available in Ollama")?;
-
-        // Extract embeddings - real implementation would parse the actual embedding arrays
-        // For now, return error indicating this needs proper implementation
-        let _stmt = self.conn.prepare(&format!(
-            "SELECT embedding FROM {} ORDER BY id",
-            embedding_table
-        ))?;
+        let started = Instant::now();
+
+        // Flock sends one request per batch of rows, so query one batch at
+        // a time for each request to wait for its own permit
+        let batch_size = self.batch_size(model);
+        let embed_batch = |start: usize| -> Result<Vec<Vec<f32>>> {
+            let _permit = self.limiter.acquire();
+            let mut stmt = self.conn.prepare(&sql)?;
+            let end = start + batch_size;
+            let rows = stmt.query_map(params![start as i32, end as i32], |row| {
+                row.get::<_, Value>(0)
+            })?;
+            rows.map(|value| value.map_err(anyhow::Error::from).and_then(embedding_from_value))
+                .collect()
+        };
+        let embeddings = (0..texts.len())
+            .step_by(batch_size)
+            .map(|start| {
+                embed_batch(start)
+                    .context("Failed to generate embeddings - check if embedder model is available in Ollama")
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(|batches| batches.concat());
+
+        // Clean up temporary table
+        self.conn.execute(&format!("DROP TABLE IF EXISTS {}", table_name), [])?;
 
-        // TODO: Implement proper embedding extraction from DuckDB array type
-        // This would involve parsing the embedding column which contains float arrays
-        let embeddings = Vec::new(); // Placeholder
+        let embeddings = embeddings?;
+        if embeddings.len() != texts.len() {
+            return Err(anyhow::anyhow!(
+                "Expected {} embeddings but Flock returned {}",
+                texts.len(),
+                embeddings.len()
+            )
+            .into());
+        }
 
-        // Clean up temporary tables
-        self.conn.execute(&format!("DROP TABLE IF EXISTS {}", table_name), [])?;
-        self.conn.execute(&format!("DROP TABLE IF EXISTS {}", embedding_table), [])?;
+        for (text, embedding) in texts.iter().zip(&embeddings) {
+            let response =
+                serde_json::to_string(embedding).context("Failed to serialize embedding")?;
+            self.record(model, text, &op_params, &response)?;
+        }
 
-        if embeddings.is_empty() {
-            return Err(anyhow::anyhow!("Embedding generation not fully implemented - requires parsing of DuckDB array columns"));
+        if let Some(audit) = &self.audit {
+            // Per-text latency is not observable within a batch; log the batch average
+            let latency = started.elapsed() / texts.len().max(1) as u32;
+            for (text, embedding) in texts.iter().zip(&embeddings) {
+                let response = format!("[embedding: {} dims]", embedding.len());
+                audit.record(model, text, &response, latency, false)?;
+            }
         }
 
         info!("✅ Generated {} embeddings", embeddings.len());
@@ -400,18 +805,19 @@ impl FlockManager {
     /// - Flock extension is not available
     /// - Corpus doesn't have pre-computed embeddings
     /// - Embedding comparison fails
+    #[instrument(skip_all)]
     pub fn semantic_search(
         &self,
         query: &str,
         _corpus: &str,
         _threshold: f32,
         _limit: usize,
-    ) -> Result<Vec<(String, f32)>> {
+    ) -> Result<Vec<(String, f32)>, FrozenDuckdbError> {
         info!("🔍 Performing semantic search for: {}", query);
 
         // Verify Flock is ready before proceeding
         if !self.is_flock_ready()? {
-            return Err(anyhow::anyhow!("Flock extension not available. Run setup first."));
+            return Err(FrozenDuckdbError::FlockNotReady);
         }
 
         // For now, return error indicating this needs proper implementation with embeddings
@@ -423,7 +829,8 @@ impl FlockManager {
         Err(anyhow::anyhow!(
             "Semantic search not implemented - requires pre-computed embeddings and similarity comparison. \
              Use generate_embeddings() first to create embeddings for your corpus."
-        ))
+        )
+        .into())
     }
 
     /// Filter data using LLM-based classification.
@@ -506,73 +913,495 @@ impl FlockManager {
     /// - Ollama model is not configured
     /// - Input file cannot be read
     /// - Classification fails
+    #[instrument(skip_all, fields(model = %model, input = %input_file))]
     pub fn llm_filter(
         &self,
         criteria: &str,
         input_file: &str,
         model: &str,
         positive_only: bool,
-    ) -> Result<Vec<(String, bool)>> {
+    ) -> Result<Vec<(String, bool)>, FrozenDuckdbError> {
         info!("🎯 Filtering data with criteria: {} using model: {}", criteria, model);
 
         // Verify Flock is ready before proceeding
         if !self.is_flock_ready()? {
-            return Err(anyhow::anyhow!("Flock extension not available. Run setup first."));
+            return Err(FrozenDuckdbError::FlockNotReady);
         }
 
         // Read input file
         let content = std::fs::read_to_string(input_file)
             .context("Failed to read input file for filtering")?;
 
-        let items: Vec<&str> = content.lines().collect();
-        let mut results = Vec::new();
+        let items: Vec<String> = content.lines().map(str::to_string).collect();
+        let matches = self.classify_texts(criteria, &items, model)?;
 
-        // Create a temporary table for filtering
-        let table_name = format!("temp_filter_{}", chrono::Utc::now().timestamp());
-        
-        self.conn.execute(
-            &format!("CREATE TABLE {} (id INTEGER, content TEXT)", table_name),
-            [],
-        )?;
+        let results: Vec<(String, bool)> = items
+            .iter()
+            .cloned()
+            .zip(matches)
+            .filter(|(_, matches)| !positive_only || *matches)
+            .collect();
 
-        // Insert items to filter
-        for (i, item) in items.iter().enumerate() {
-            self.conn.execute(
-                &format!("INSERT INTO {} VALUES (?, ?)", table_name),
-                [&(i as i32).to_string(), &item.to_string()],
-            )?;
-        }
+        info!("✅ Filtered {} items, {} matches found", items.len(), results.len());
+        Ok(results)
+    }
 
-        // Create filter prompt
+    /// Classifies each text against `criteria`, returning one match flag per text.
+    ///
+    /// This is the per-item classification behind [`FlockManager::llm_filter`];
+    /// items whose model call fails are treated as non-matching.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::FlockManager;
+    ///
+    /// let manager = FlockManager::new()?;
+    /// let texts = vec!["def f(): pass".to_string(), "hello world".to_string()];
+    /// let matches = manager.classify_texts("Is this valid Python code?", &texts, "coder")?;
+    /// ```
+    #[instrument(skip_all, fields(model = %model, rows = texts.len()))]
+    pub fn classify_texts(&self, criteria: &str, texts: &[String], model: &str) -> Result<Vec<bool>> {
+        let prompt = self.create_filter_prompt(criteria)?;
+
+        // Classify each item using the specified model
+        let matches = texts
+            .iter()
+            .map(|item| self.classify_one(&prompt, item, model).unwrap_or(false))
+            .collect();
+
+        let _ = self.conn.execute("DROP PROMPT IF EXISTS ?", [&prompt.0]);
+        Ok(matches)
+    }
+
+    /// Classifies texts one at a time, calling `on_result` with each text's
+    /// index, match flag, and model latency as soon as it is known.
+    ///
+    /// Unlike [`FlockManager::classify_texts`], a failed model call stops
+    /// the run with an error, so callers that checkpoint results never
+    /// record a failure as a non-match.
+    #[instrument(skip_all, fields(model = %model, rows = texts.len()))]
+    pub fn classify_each<F>(
+        &self,
+        criteria: &str,
+        texts: &[String],
+        model: &str,
+        mut on_result: F,
+    ) -> Result<()>
+    where
+        F: FnMut(usize, bool, Duration) -> Result<()>,
+    {
+        let prompt = self.create_filter_prompt(criteria)?;
+
+        let result = texts.iter().enumerate().try_for_each(|(i, item)| {
+            let started = Instant::now();
+            let matches = self
+                .classify_one(&prompt, item, model)
+                .with_context(|| format!("Failed to classify item {}", i + 1))?;
+            on_result(i, matches, started.elapsed())
+        });
+
+        let _ = self.conn.execute("DROP PROMPT IF EXISTS ?", [&prompt.0]);
+        result
+    }
+
+    /// Creates the Flock prompt used for filtering, returning its (name, content).
+    fn create_filter_prompt(&self, criteria: &str) -> Result<(String, String)> {
         let prompt_name = format!("filter_prompt_{}", chrono::Utc::now().timestamp());
         let prompt_content = format!("Classify this text based on the criteria: {}. Return only 'true' or 'false'.", criteria);
-        
+
         self.conn.execute(
             "CREATE PROMPT(?, ?)",
             [&prompt_name, &prompt_content],
         )?;
+        Ok((prompt_name, prompt_content))
+    }
 
-        // Filter each item using the specified model
-        for (_i, item) in items.iter().enumerate() {
+    fn classify_one(&self, prompt: &(String, String), item: &str, model: &str) -> Result<bool> {
+        let (prompt_name, prompt_content) = prompt;
+        let cache_key = format!("{}\n{}", prompt_content, item);
+        let result = self.cached_response(model, &cache_key, "{\"op\":\"filter\"}", || {
+            let _permit = self.limiter.acquire();
             let result: String = self.conn.query_row(
                 "SELECT llm_complete({'model_name': ?}, {'prompt_name': ?, 'context_columns': [{'data': ?}]})",
-                [model, &prompt_name, &item.to_string()],
+                [model, prompt_name.as_str(), item],
                 |row| row.get(0),
-            ).unwrap_or_else(|_| "false".to_string());
+            )?;
+            Ok(result)
+        })?;
+        Ok(result.to_lowercase().contains("true"))
+    }
 
-            let matches = result.to_lowercase().contains("true");
-            
-            if !positive_only || matches {
-                results.push((item.to_string(), matches));
+    /// Completes `instructions` once per text in a single set-based Flock call.
+    ///
+    /// Each text is passed to the model as context for the same prompt, and
+    /// responses are returned in input order. Cached responses are served
+    /// first; only the remaining texts are sent to the model.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::FlockManager;
+    ///
+    /// let manager = FlockManager::new()?;
+    /// let reviews = vec!["Great phone".to_string(), "Battery died".to_string()];
+    /// let moods = manager.complete_rows("Reply with the mood of this review.", &reviews, "text_generator")?;
+    /// assert_eq!(moods.len(), 2);
+    /// ```
+    #[instrument(skip_all, fields(model = %model, rows = texts.len()))]
+    pub fn complete_rows(
+        &self,
+        instructions: &str,
+        texts: &[String],
+        model: &str,
+    ) -> Result<Vec<String>, FrozenDuckdbError> {
+        info!("🤖 Completing {} rows using model: {}", texts.len(), model);
+
+        if !self.is_flock_ready()? {
+            return Err(FrozenDuckdbError::FlockNotReady);
+        }
+
+        let texts = texts
+            .iter()
+            .map(|text| Ok(self.fit_context(instructions, vec![text.clone()], model)?.concat()))
+            .collect::<Result<Vec<_>>>()?;
+        let op_params = "{\"op\":\"complete_rows\"}";
+        let cache_key = |text: &str| format!("{}\n{}", instructions, text);
+        let metrics = Metrics::global();
+        let mut responses: Vec<Option<String>> = Vec::with_capacity(texts.len());
+        for text in &texts {
+            if let Some(response) = self.replayed(model, &cache_key(text), op_params)? {
+                metrics.record_llm_call(model, LlmSource::Replay);
+                responses.push(Some(response));
+                continue;
+            }
+            let cached = match &self.cache {
+                Some(cache) => {
+                    let cached = cache.get(model, &cache_key(text), op_params)?;
+                    metrics.record_cache_lookup(CacheKind::Response, cached.is_some());
+                    cached
+                }
+                None => None,
+            };
+            if let Some(response) = &cached {
+                self.record(model, &cache_key(text), op_params, response)?;
+            }
+            if let Some(response) = &cached {
+                self.cache_hits.set(self.cache_hits.get() + 1);
+                metrics.record_llm_call(model, LlmSource::Cache);
+                if let Some(audit) = &self.audit {
+                    audit.record(model, &cache_key(text), response, Duration::ZERO, true)?;
+                }
             }
+            responses.push(cached);
         }
 
-        // Clean up temporary tables
-        let _ = self.conn.execute(&format!("DROP TABLE IF EXISTS {}", table_name), []);
-        let _ = self.conn.execute("DROP PROMPT IF EXISTS ?", [&prompt_name]);
+        let missing: Vec<usize> = (0..texts.len()).filter(|&i| responses[i].is_none()).collect();
+        if !missing.is_empty() {
+            let stamp = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default();
+            let table_name = format!("temp_rows_{}", stamp);
+            let prompt_name = format!("rows_prompt_{}", stamp);
 
-        info!("✅ Filtered {} items, {} matches found", items.len(), results.len());
-        Ok(results)
+            self.conn.execute(
+                &format!("CREATE TABLE {} (id INTEGER, content TEXT)", table_name),
+                [],
+            )?;
+            let mut insert = BulkInsert::new(&self.conn, &table_name, DEFAULT_FLUSH_INTERVAL)?;
+            for &i in &missing {
+                insert.append(params![i as i32, texts[i]])?;
+            }
+            insert.finish()?;
+            self.conn.execute("CREATE PROMPT(?, ?)", [&prompt_name, instructions])?;
+
+            let sql = format!(
+                "SELECT id, llm_complete({{'model_name': ?}}, {{'prompt_name': ?, 'context_columns': [{{'data': content}}]}})
+                 FROM {} WHERE id BETWEEN ? AND ? ORDER BY id",
+                table_name
+            );
+            let started = Instant::now();
+
+            // Flock sends one request per batch of rows, so query one batch
+            // at a time for each request to wait for its own permit
+            let generated = missing
+                .chunks(self.batch_size(model))
+                .map(|batch| {
+                    let _permit = self.limiter.acquire();
+                    let (first, last) = (batch[0] as i32, batch[batch.len() - 1] as i32);
+                    let mut stmt = self.conn.prepare(&sql)?;
+                    let rows = stmt.query_map(params![model, prompt_name, first, last], |row| {
+                        Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?))
+                    })?;
+                    rows.collect::<duckdb::Result<Vec<_>>>()
+                })
+                .collect::<duckdb::Result<Vec<_>>>()
+                .map(|batches| batches.concat())
+                .context("Failed to complete rows - check if Ollama is running and models are available");
+
+            let _ = self.conn.execute(&format!("DROP TABLE IF EXISTS {}", table_name), []);
+            let _ = self.conn.execute("DROP PROMPT IF EXISTS ?", [&prompt_name]);
+
+            let generated = generated?;
+            // Per-row latency is not observable within a batch; log the batch average
+            let latency = started.elapsed() / missing.len() as u32;
+            for (id, response) in generated {
+                let i = id as usize;
+                metrics.record_llm_call(model, LlmSource::Model);
+                if let Some(cache) = &self.cache {
+                    cache.put(model, &cache_key(&texts[i]), op_params, &response)?;
+                }
+                self.record(model, &cache_key(&texts[i]), op_params, &response)?;
+                if let Some(audit) = &self.audit {
+                    audit.record(model, &cache_key(&texts[i]), &response, latency, false)?;
+                }
+                responses[i] = Some(response);
+            }
+        }
+
+        let responses = responses
+            .into_iter()
+            .enumerate()
+            .map(|(i, response)| {
+                response.ok_or_else(|| anyhow::anyhow!("Flock returned no response for row {}", i + 1))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        info!("✅ Completed {} rows", responses.len());
+        Ok(responses)
+    }
+
+    /// Rerank documents by relevance to `query` using Flock's `llm_rerank`.
+    ///
+    /// Returns every document with its 1-based rank, best first. Documents
+    /// the model leaves out of its ranking keep their input order after the
+    /// ranked ones.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::FlockManager;
+    ///
+    /// let manager = FlockManager::new()?;
+    /// let documents = vec![
+    ///     "Rust ownership rules".to_string(),
+    ///     "Baking sourdough bread".to_string(),
+    /// ];
+    /// let ranked = manager.rerank("memory safety", documents, "text_generator")?;
+    /// assert_eq!(ranked[0].1, 1);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if Flock is not available or the model call fails.
+    #[instrument(skip_all, fields(model = %model, rows = documents.len()))]
+    pub fn rerank(
+        &self,
+        query: &str,
+        documents: Vec<String>,
+        model: &str,
+    ) -> Result<Vec<(String, usize)>, FrozenDuckdbError> {
+        info!("🏅 Reranking {} documents for query: {} using model: {}", documents.len(), query, model);
+        if documents.len() < 2 {
+            return Ok(documents.into_iter().map(|document| (document, 1)).collect());
+        }
+
+        if !self.is_flock_ready()? {
+            return Err(FrozenDuckdbError::FlockNotReady);
+        }
+
+        let prompt = rerank_prompt(query);
+        let cache_key = std::iter::once(prompt.as_str())
+            .chain(documents.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let response = self.cached_response(model, &cache_key, "{\"op\":\"rerank\"}", || {
+            let table_name = format!(
+                "temp_rerank_{}",
+                chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+            );
+            self.conn.execute(
+                &format!("CREATE TABLE {} (id INTEGER, content TEXT)", table_name),
+                [],
+            )?;
+            let mut insert = BulkInsert::new(&self.conn, &table_name, DEFAULT_FLUSH_INTERVAL)?;
+            for (i, document) in documents.iter().enumerate() {
+                insert.append(params![i as i32, document])?;
+            }
+            insert.finish()?;
+
+            let _permit = self.limiter.acquire();
+            let result = self.conn.query_row(
+                &format!(
+                    "SELECT llm_rerank({{'model_name': ?}}, {{'prompt': ?, 'context_columns': [{{'name': 'id', 'data': id::VARCHAR}}, {{'name': 'content', 'data': content}}]}})::VARCHAR
+                     FROM {}",
+                    table_name
+                ),
+                [model, prompt.as_str()],
+                |row| row.get::<_, String>(0),
+            )
+            .context("Failed to rerank documents - check if Ollama is running and models are available");
+            let _ = self.conn.execute(&format!("DROP TABLE IF EXISTS {}", table_name), []);
+            result
+        })?;
+
+        let ranked = rerank_order(&response, &documents)
+            .into_iter()
+            .enumerate()
+            .map(|(rank, i)| (documents[i].clone(), rank + 1))
+            .collect::<Vec<_>>();
+        info!("✅ Reranked {} documents", ranked.len());
+        Ok(ranked)
+    }
+
+    /// Fuse one document's ranks or scores from several retrievers with
+    /// Flock's fusion functions, `None` marking a retriever that didn't
+    /// return it. See [`fusion`] for the methods and their inputs.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use frozen_duckdb::cli::fusion::FusionMethod;
+    /// use frozen_duckdb::cli::FlockManager;
+    ///
+    /// let manager = FlockManager::new()?;
+    /// // 2nd by vector similarity, 5th by keywords
+    /// let score = manager.fuse(FusionMethod::Rrf, &[Some(2.0), Some(5.0)])?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    #[instrument(skip_all, fields(method = method.as_str()))]
+    pub fn fuse(&self, method: FusionMethod, inputs: &[Option<f64>]) -> Result<f64> {
+        fusion::fuse(&self.conn, method, inputs)
+    }
+
+    /// Merge result lists of (document, score) pairs, each best first, into
+    /// one list ordered by fused score, as hybrid search does with its
+    /// vector and keyword results.
+    #[instrument(
+        skip_all,
+        fields(method = method.as_str(), rows = lists.iter().map(Vec::len).sum::<usize>())
+    )]
+    pub fn fuse_rankings(
+        &self,
+        method: FusionMethod,
+        lists: &[Vec<(String, f32)>],
+    ) -> Result<Vec<(String, f64)>> {
+        let fused = fusion::fuse_rankings(&self.conn, method, lists)?;
+        debug!(
+            "Fused {} result lists into {} documents with {}",
+            lists.len(),
+            fused.len(),
+            method.as_str()
+        );
+        Ok(fused)
+    }
+
+    /// Pick the candidate that best satisfies `prompt` using Flock's `llm_first`.
+    ///
+    /// A second call asks the model for a one-sentence rationale for the
+    /// choice, e.g. to show why one generated title beat its variants.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::FlockManager;
+    ///
+    /// let manager = FlockManager::new()?;
+    /// let titles = vec![
+    ///     "Fast Rust builds with prebuilt DuckDB".to_string(),
+    ///     "A DuckDB thing".to_string(),
+    /// ];
+    /// let pick = manager.pick_best("the clearest blog post title", titles, "text_generator")?;
+    /// println!("{} ({})", pick.candidate, pick.rationale);
+    /// ```
+    #[instrument(skip_all, fields(model = %model, rows = candidates.len()))]
+    pub fn pick_best(
+        &self,
+        prompt: &str,
+        candidates: Vec<String>,
+        model: &str,
+    ) -> Result<CandidatePick, FrozenDuckdbError> {
+        self.pick("llm_first", "best", prompt, candidates, model)
+    }
+
+    /// Pick the candidate that least satisfies `prompt` using Flock's `llm_last`.
+    ///
+    /// The counterpart of [`FlockManager::pick_best`].
+    #[instrument(skip_all, fields(model = %model, rows = candidates.len()))]
+    pub fn pick_worst(
+        &self,
+        prompt: &str,
+        candidates: Vec<String>,
+        model: &str,
+    ) -> Result<CandidatePick, FrozenDuckdbError> {
+        self.pick("llm_last", "worst", prompt, candidates, model)
+    }
+
+    fn pick(
+        &self,
+        function: &str,
+        direction: &str,
+        prompt: &str,
+        candidates: Vec<String>,
+        model: &str,
+    ) -> Result<CandidatePick, FrozenDuckdbError> {
+        info!("🏅 Picking the {} of {} candidates using model: {}", direction, candidates.len(), model);
+        if candidates.is_empty() {
+            return Err(anyhow::anyhow!("No candidates to pick from").into());
+        }
+
+        if !self.is_flock_ready()? {
+            return Err(FrozenDuckdbError::FlockNotReady);
+        }
+
+        let cache_key = std::iter::once(prompt)
+            .chain(candidates.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let op_params = format!("{{\"op\":\"{}\"}}", function);
+        let response = self.cached_response(model, &cache_key, &op_params, || {
+            let table_name = format!(
+                "temp_candidates_{}",
+                chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+            );
+            self.conn.execute(
+                &format!("CREATE TABLE {} (id INTEGER, content TEXT)", table_name),
+                [],
+            )?;
+            let mut insert = BulkInsert::new(&self.conn, &table_name, DEFAULT_FLUSH_INTERVAL)?;
+            for (i, candidate) in candidates.iter().enumerate() {
+                insert.append(params![i as i32, candidate])?;
+            }
+            insert.finish()?;
+
+            let _permit = self.limiter.acquire();
+            let result = self.conn.query_row(
+                &format!(
+                    "SELECT {}({{'model_name': ?}}, {{'prompt': ?, 'context_columns': [{{'name': 'id', 'data': id::VARCHAR}}, {{'name': 'content', 'data': content}}]}})::VARCHAR
+                     FROM {}",
+                    function, table_name
+                ),
+                [model, prompt],
+                |row| row.get::<_, String>(0),
+            )
+            .with_context(|| format!("Failed to run {} - check if Ollama is running and models are available", function));
+            let _ = self.conn.execute(&format!("DROP TABLE IF EXISTS {}", table_name), []);
+            result
+        })?;
+
+        let index = picked_index(&response, &candidates).ok_or_else(|| {
+            anyhow::anyhow!("Could not match the {} response to a candidate: {}", function, response)
+        })?;
+        let candidate = candidates[index].clone();
+        let rationale = self
+            .complete_text(&pick_rationale_prompt(prompt, direction, &candidate, &candidates), model)?
+            .trim()
+            .to_string();
+
+        info!("✅ Picked candidate {}", index + 1);
+        Ok(CandidatePick {
+            index,
+            candidate,
+            rationale,
+        })
     }
 
     /// Generate summaries using LLM aggregation.
@@ -615,24 +1444,54 @@ impl FlockManager {
     /// - Ollama model is not configured
     /// - Text collection is empty
     /// - Summarization fails
+    #[instrument(skip_all, fields(model = %model, strategy = %strategy, rows = texts.len()))]
     pub fn summarize_texts(
         &self,
         texts: Vec<String>,
         strategy: &str,
         max_length: usize,
         model: &str,
-    ) -> Result<String> {
+    ) -> Result<String, FrozenDuckdbError> {
         info!("📝 Generating summary using {} strategy with model: {}", strategy, model);
 
         // Verify Flock is ready before proceeding
         if !self.is_flock_ready()? {
-            return Err(anyhow::anyhow!("Flock extension not available. Run setup first."));
+            return Err(FrozenDuckdbError::FlockNotReady);
         }
 
         if texts.is_empty() {
-            return Err(anyhow::anyhow!("Cannot summarize empty text collection"));
+            return Err(anyhow::anyhow!("Cannot summarize empty text collection").into());
         }
 
+        // map and reduce send texts to the model one at a time; the default
+        // strategy sends them all in a single call
+        let prompt = summary_prompt(max_length);
+        let texts = match strategy {
+            "map" | "reduce" => texts
+                .into_iter()
+                .map(|text| Ok(self.fit_context(&prompt, vec![text], model)?.concat()))
+                .collect::<Result<Vec<_>>>()?,
+            _ => self.fit_context(&prompt, texts, model)?,
+        };
+
+        let cache_key = format!("{}\n{}", summary_prompt(max_length), texts.join("\n\u{1e}\n"));
+        let params = format!("{{\"op\":\"summarize\",\"strategy\":\"{}\"}}", strategy);
+        let summary = self.cached_response(model, &cache_key, &params, || {
+            self.generate_summary(&texts, strategy, max_length, model)
+        })?;
+
+        info!("✅ Generated summary ({} chars)", summary.len());
+        Ok(summary)
+    }
+
+    /// Runs the summarization strategy against the model (uncached).
+    fn generate_summary(
+        &self,
+        texts: &[String],
+        strategy: &str,
+        max_length: usize,
+        model: &str,
+    ) -> Result<String> {
         // Create a temporary table for texts
         let table_name = format!("temp_summary_{}", chrono::Utc::now().timestamp());
         
@@ -642,16 +1501,15 @@ impl FlockManager {
         )?;
 
         // Insert texts to summarize
+        let mut insert = BulkInsert::new(&self.conn, &table_name, DEFAULT_FLUSH_INTERVAL)?;
         for (i, text) in texts.iter().enumerate() {
-            self.conn.execute(
-                &format!("INSERT INTO {} VALUES (?, ?)", table_name),
-                [&(i as i32).to_string(), text],
-            )?;
+            insert.append(params![i as i32, text])?;
         }
+        insert.finish()?;
 
         // Create summary prompt
         let prompt_name = format!("summary_prompt_{}", chrono::Utc::now().timestamp());
-        let prompt_content = format!("Summarize the following text in {} words or less. Focus on the key points and main ideas.", max_length);
+        let prompt_content = summary_prompt(max_length);
         
         self.conn.execute(
             "CREATE PROMPT(?, ?)",
@@ -661,6 +1519,7 @@ impl FlockManager {
         let summary = match strategy {
             "reduce" => {
                 // Use llm_reduce for hierarchical summarization
+                let _permit = self.limiter.acquire();
                 let result: String = self.conn.query_row(
                     "SELECT llm_reduce({'model_name': ?}, {'prompt_name': ?, 'context_columns': [{'data': content}]}) FROM ?",
                     [model, &prompt_name, &table_name],
@@ -671,7 +1530,8 @@ impl FlockManager {
             "map" => {
                 // Generate individual summaries then combine
                 let mut summaries = Vec::new();
-                for text in &texts {
+                for text in texts {
+                    let _permit = self.limiter.acquire();
                     let summary: String = self.conn.query_row(
                         "SELECT llm_complete({'model_name': ?}, {'prompt_name': ?, 'context_columns': [{'data': ?}]})",
                         [model, &prompt_name, text.as_str()],
@@ -684,6 +1544,7 @@ impl FlockManager {
             _ => {
                 // Default to simple concatenation and summary
                 let combined_text = texts.join(" ");
+                let _permit = self.limiter.acquire();
                 let result: String = self.conn.query_row(
                     "SELECT llm_complete({'model_name': ?}, {'prompt_name': ?, 'context_columns': [{'data': ?}]})",
                     [model, &prompt_name, combined_text.as_str()],
@@ -697,10 +1558,98 @@ impl FlockManager {
         let _ = self.conn.execute(&format!("DROP TABLE IF EXISTS {}", table_name), []);
         let _ = self.conn.execute("DROP PROMPT IF EXISTS ?", [&prompt_name]);
 
-        info!("✅ Generated summary ({} chars)", summary.len());
         Ok(summary)
     }
 
+    /// Translate a natural-language question into SQL for `database`.
+    ///
+    /// Builds a schema summary from `information_schema` (see
+    /// [`schema_context`]), asks `model` for a single query and validates the
+    /// answer with `EXPLAIN` against `database`. When validation fails the
+    /// model gets one more attempt with the error message in its prompt.
+    ///
+    /// The returned SQL has not been executed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::cli::FlockManager;
+    /// use duckdb::Connection;
+    ///
+    /// let manager = FlockManager::new()?;
+    /// let database = Connection::open("sales.duckdb")?;
+    /// let sql = manager.nl_to_sql("total revenue by region last month", &database, "text_generator")?;
+    /// println!("{}", sql);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database has no tables, the model call fails or
+    /// the generated SQL still does not pass `EXPLAIN` after the retry.
+    #[instrument(skip_all, fields(model = %model))]
+    pub fn nl_to_sql(
+        &self,
+        question: &str,
+        database: &Connection,
+        model: &str,
+    ) -> Result<String, FrozenDuckdbError> {
+        info!("🤖 Generating SQL for question: {} using model: {}", question, model);
+
+        if !self.is_flock_ready()? {
+            return Err(FrozenDuckdbError::FlockNotReady);
+        }
+
+        let schema = schema_context(database)?;
+        let mut previous: Option<(String, String)> = None;
+
+        for attempt in 1..=NL_TO_SQL_ATTEMPTS {
+            let prompt_content = nl_to_sql_prompt(
+                &schema,
+                question,
+                previous.as_ref().map(|(sql, error)| (sql.as_str(), error.as_str())),
+            );
+            let response = self.cached_response(model, &prompt_content, "{\"op\":\"nl_to_sql\"}", || {
+                let prompt_name = format!(
+                    "temp_sql_prompt_{}",
+                    chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+                );
+                self.conn.execute("CREATE PROMPT(?, ?)", [&prompt_name, &prompt_content])?;
+
+                let _permit = self.limiter.acquire();
+                let result: String = self.conn.query_row(
+                    "SELECT llm_complete({'model_name': ?}, {'prompt_name': ?})",
+                    [model, &prompt_name],
+                    |row| row.get(0),
+                )
+                .context("Failed to generate SQL - check if Ollama is running and models are available")?;
+                let _ = self.conn.execute("DROP PROMPT IF EXISTS ?", [&prompt_name]);
+                Ok(result)
+            })?;
+
+            let sql = extract_sql(&response);
+            match validate_sql(database, &sql) {
+                Ok(()) => {
+                    info!("✅ Generated SQL passed validation (attempt {})", attempt);
+                    return Ok(sql);
+                }
+                Err(e) => {
+                    debug!("Generated SQL failed validation (attempt {}): {:#}", attempt, e);
+                    previous = Some((sql, format!("{:#}", e)));
+                }
+            }
+        }
+
+        let (sql, error) = previous.unwrap_or_default();
+        Err(anyhow::anyhow!(
+            "Model '{}' did not produce valid SQL after {} attempts: {}\n{}",
+            model,
+            NL_TO_SQL_ATTEMPTS,
+            error,
+            sql
+        )
+        .into())
+    }
+
     /// Check if Flock extension is available and working.
     ///
     /// This function verifies that the Flock extension is properly loaded
@@ -728,6 +1677,7 @@ impl FlockManager {
     ///
     /// - **Check time**: <100ms
     /// - **Memory usage**: <10MB
+    #[instrument(skip_all)]
     pub fn is_flock_ready(&self) -> Result<bool> {
         // Check if Flock extension is loaded
         let extensions: Vec<String> = self.conn.prepare(
@@ -756,6 +1706,10 @@ impl FlockManager {
     ///
     /// This function performs comprehensive FFI validation to ensure that
     /// the frozen-duckdb library properly exposes all required functionality.
+    /// The LLM layers call the Ollama server at `ollama_url`, which can be a
+    /// mock server (see the `frozen-duckdb-test-support` crate). With
+    /// `verify_scale_factor`, a last layer checks the TPC-H query results
+    /// against the official answers at that scale factor.
     ///
     /// # Returns
     ///
@@ -768,7 +1722,7 @@ impl FlockManager {
     /// use frozen_duckdb::cli::FlockManager;
     ///
     /// let manager = FlockManager::new()?;
-    /// let result = manager.validate_ffi()?;
+    /// let result = manager.validate_ffi("http://127.0.0.1:11434", Some(0.01))?;
     /// println!("FFI validation: {} passed, {} failed", result.passed_count, result.failed_count);
     /// ```
     ///
@@ -779,12 +1733,19 @@ impl FlockManager {
     /// - **Core Functionality**: Test basic DuckDB operations
     /// - **Extension Validation**: Test Flock LLM functions
     /// - **Integration Validation**: Test end-to-end workflows
+    /// - **TPC-H Answer Verification**: Compare query results with
+    ///   `tpch_answers()` (only with `verify_scale_factor`)
     ///
     /// # Performance
     ///
     /// - **Total validation time**: < 5s
     /// - **Individual layer time**: < 1s per layer
-    pub fn validate_ffi(&self) -> Result<FFIValidationResult> {
+    #[instrument(skip_all)]
+    pub fn validate_ffi(
+        &self,
+        ollama_url: &str,
+        verify_scale_factor: Option<f64>,
+    ) -> Result<FFIValidationResult> {
         info!("🦆 Starting comprehensive FFI validation for frozen-duckdb");
         
         let mut results = Vec::new();
@@ -803,10 +1764,10 @@ impl FlockManager {
         results.push(self.validate_extension_layer()?);
         
         // Layer 5: Integration Validation
-        results.push(self.validate_integration_layer()?);
+        results.push(self.validate_integration_layer(ollama_url)?);
         
         // Layer 6: Comprehensive Flock Functions Validation
-        results.push(self.validate_flock_scalar_functions()?);
+        results.push(self.validate_flock_scalar_functions(ollama_url)?);
         
         // Layer 7: Flock Aggregate Functions Validation
         results.push(self.validate_flock_aggregate_functions()?);
@@ -826,6 +1787,11 @@ impl FlockManager {
         // Layer 12: TPC-H Query Execution Validation
         results.push(self.validate_tpch_queries()?);
 
+        // Layer 13: TPC-H Answer Verification
+        if let Some(scale_factor) = verify_scale_factor {
+            results.push(self.verify_tpch_answers(scale_factor)?);
+        }
+
         let total_duration = start_time.elapsed();
         let passed_count = results.iter().filter(|r| r.passed).count();
         let failed_count = results.len() - passed_count;
@@ -1009,13 +1975,14 @@ impl FlockManager {
     }
 
     /// Validate integration with actual LLM operations.
-    fn validate_integration_layer(&self) -> Result<ValidationLayerResult> {
+    fn validate_integration_layer(&self, ollama_url: &str) -> Result<ValidationLayerResult> {
         let start_time = std::time::Instant::now();
         
         info!("🔍 Layer 5: Integration Validation");
         
         // Try to setup Ollama and test actual LLM functionality
-        match self.setup_ollama("http://127.0.0.1:11434", "llama3.2", "mxbai-embed-large", true) {
+        let settings = ModelSettings::default();
+        match self.setup_ollama(ollama_url, "llama3.2", "mxbai-embed-large", &settings, true) {
             Ok(_) => {
                 info!("✅ Ollama setup successful");
             }
@@ -1058,13 +2025,14 @@ impl FlockManager {
     }
 
     /// Validate Flock scalar functions (llm_complete, llm_filter, llm_embedding).
-    fn validate_flock_scalar_functions(&self) -> Result<ValidationLayerResult> {
+    fn validate_flock_scalar_functions(&self, ollama_url: &str) -> Result<ValidationLayerResult> {
         let start_time = std::time::Instant::now();
         
         info!("🔍 Layer 6: Flock Scalar Functions Validation");
         
         // Setup Ollama for testing
-        let _ = self.setup_ollama("http://127.0.0.1:11434", "llama3.2", "mxbai-embed-large", true);
+        let settings = ModelSettings::default();
+        let _ = self.setup_ollama(ollama_url, "llama3.2", "mxbai-embed-large", &settings, true);
         
         // Test llm_complete with context_columns API
         let complete_result = self.conn.query_row(
@@ -1643,6 +2611,362 @@ impl FlockManager {
             error: if all_passed { None } else { Some("Some TPC-H queries failed".to_string()) },
         })
     }
+
+    /// Verify TPC-H query results against the official answers.
+    ///
+    /// Runs on a separate in-memory database, so the data generated at
+    /// `scale_factor` doesn't replace the SF 0.01 tables of layer 11.
+    fn verify_tpch_answers(&self, scale_factor: f64) -> Result<ValidationLayerResult> {
+        let start_time = std::time::Instant::now();
+
+        info!("🔍 Layer 13: TPC-H Answer Verification (SF {})", scale_factor);
+
+        let checks = match Connection::open_in_memory()
+            .map_err(anyhow::Error::from)
+            .and_then(|conn| tpch_answers::verify(&conn, scale_factor))
+        {
+            Ok(checks) => checks,
+            Err(e) => {
+                return Ok(ValidationLayerResult {
+                    layer: "TPC-H Answer Verification".to_string(),
+                    passed: false,
+                    duration: start_time.elapsed(),
+                    details: Some(format!("Could not verify at SF {}", scale_factor)),
+                    error: Some(format!("{:#}", e)),
+                });
+            }
+        };
+
+        let mut mismatches = Vec::new();
+        for check in &checks {
+            if check.passed() {
+                info!("✅ TPC-H Query {} matches ({} rows)", check.query_nr, check.actual_rows);
+            } else {
+                warn!("❌ TPC-H Query {} differs from the answer:", check.query_nr);
+                for mismatch in &check.mismatches {
+                    warn!("   {}", mismatch);
+                    mismatches.push(format!("Q{}: {}", check.query_nr, mismatch));
+                }
+            }
+        }
+
+        if checks.is_empty() {
+            mismatches.push(format!("No TPC-H answers at SF {}", scale_factor));
+        }
+        let matching = checks.iter().filter(|check| check.passed()).count();
+        let all_passed = mismatches.is_empty();
+        let duration = start_time.elapsed();
+        info!("✅ TPC-H answer verification completed in {:?}", duration);
+
+        Ok(ValidationLayerResult {
+            layer: "TPC-H Answer Verification".to_string(),
+            passed: all_passed,
+            duration,
+            details: Some(format!(
+                "Matching answers: {}/{} at SF {}",
+                matching,
+                checks.len(),
+                scale_factor
+            )),
+            error: if all_passed { None } else { Some(mismatches.join("; ")) },
+        })
+    }
+}
+
+/// Flock model options for `CREATE MODEL` and `UPDATE MODEL`.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::config::ModelSettings;
+/// use frozen_duckdb::cli::flock_manager::model_options;
+///
+/// assert_eq!(
+///     model_options(&ModelSettings::default()),
+///     "{'tuple_format': 'json', 'batch_size': 32, 'model_parameters': {'temperature': 0.7}}"
+/// );
+/// ```
+pub fn model_options(settings: &ModelSettings) -> String {
+    let parameters = serde_json::Value::Object(settings.model_parameters().into_iter().collect());
+    format!(
+        "{{'tuple_format': 'json', 'batch_size': {}, 'model_parameters': {}}}",
+        settings.batch_size(),
+        sql_literal(&parameters)
+    )
+}
+
+/// Renders a JSON value as a DuckDB literal, with objects as structs.
+fn sql_literal(value: &serde_json::Value) -> String {
+    let quote = |text: &str| format!("'{}'", text.replace('\'', "''"));
+    match value {
+        serde_json::Value::Null => "NULL".to_string(),
+        serde_json::Value::Bool(b) => b.to_string(),
+        serde_json::Value::Number(n) => n.to_string(),
+        serde_json::Value::String(s) => quote(s),
+        serde_json::Value::Array(items) => {
+            format!("[{}]", items.iter().map(sql_literal).collect::<Vec<_>>().join(", "))
+        }
+        serde_json::Value::Object(fields) => format!(
+            "{{{}}}",
+            fields
+                .iter()
+                .map(|(key, value)| format!("{}: {}", quote(key), sql_literal(value)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Prompt template used by [`FlockManager::complete_text`].
+fn completion_prompt(prompt: &str) -> String {
+    format!("Complete this text: {}", prompt)
+}
+
+/// Prompt template used by [`FlockManager::summarize_texts`].
+fn summary_prompt(max_length: usize) -> String {
+    format!(
+        "Summarize the following text in {} words or less. Focus on the key points and main ideas.",
+        max_length
+    )
+}
+
+/// Estimates the token volume of [`FlockManager::complete_text`] without calling the model.
+///
+/// Assumes the model generates the full `max_tokens`, so the estimate is an
+/// upper bound on output.
+pub fn estimate_completion(prompt: &str, max_tokens: usize, tokens_per_sec: f64) -> TokenEstimate {
+    TokenEstimate::new(1, count_tokens(&completion_prompt(prompt)), max_tokens, tokens_per_sec)
+}
+
+/// Estimates the token volume of [`FlockManager::summarize_texts`] without calling the model.
+///
+/// The `map` strategy makes one call per text; other strategies send all
+/// texts in a single call. Each call is assumed to produce `max_length` words.
+pub fn estimate_summary(
+    texts: &[String],
+    strategy: &str,
+    max_length: usize,
+    tokens_per_sec: f64,
+) -> TokenEstimate {
+    let prompt_tokens = count_tokens(&summary_prompt(max_length));
+    let text_tokens: usize = texts.iter().map(|text| count_tokens(text)).sum();
+    let requests = match strategy {
+        "map" => texts.len(),
+        _ => 1,
+    };
+
+    TokenEstimate::new(
+        requests,
+        text_tokens + prompt_tokens * requests,
+        words_to_tokens(max_length) * requests,
+        tokens_per_sec,
+    )
+}
+
+/// Converts a DuckDB list/array value into an embedding vector.
+pub(crate) fn embedding_from_value(value: Value) -> Result<Vec<f32>> {
+    let items = match value {
+        Value::List(items) | Value::Array(items) => items,
+        other => return Err(anyhow::anyhow!("Expected embedding array, got {:?}", other)),
+    };
+
+    items
+        .into_iter()
+        .map(|item| match item {
+            Value::Float(f) => Ok(f),
+            Value::Double(d) => Ok(d as f32),
+            other => Err(anyhow::anyhow!("Expected float in embedding, got {:?}", other)),
+        })
+        .collect()
+}
+
+/// Prompt asking why `candidate` was picked by [`FlockManager::pick_best`]
+/// or [`FlockManager::pick_worst`].
+fn pick_rationale_prompt(prompt: &str, direction: &str, candidate: &str, candidates: &[String]) -> String {
+    let listed = candidates
+        .iter()
+        .map(|c| format!("- {}", c))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "Task: {}\nCandidates:\n{}\nIn one sentence, explain why this candidate is the {}: {}",
+        prompt, listed, direction, candidate
+    )
+}
+
+/// Prompt template used by [`FlockManager::rerank`].
+fn rerank_prompt(query: &str) -> String {
+    format!("Rank the documents by how relevant they are to this query: {}", query)
+}
+
+/// Reads the document order from an `llm_rerank` response.
+///
+/// The response is a JSON array of the ranked rows; each entry is matched
+/// to a document by its `id` field, or by content when the id is missing.
+/// Documents the response does not mention follow in input order, so the
+/// result is always a permutation of `0..documents.len()`.
+pub fn rerank_order(response: &str, documents: &[String]) -> Vec<usize> {
+    let entries = match serde_json::from_str::<serde_json::Value>(response) {
+        Ok(serde_json::Value::Array(entries)) => entries,
+        _ => Vec::new(),
+    };
+
+    let mut order = Vec::with_capacity(documents.len());
+    let mut seen = vec![false; documents.len()];
+    for entry in &entries {
+        if let Some(i) = match_document(entry, documents) {
+            if !seen[i] {
+                seen[i] = true;
+                order.push(i);
+            }
+        }
+    }
+    order.extend((0..documents.len()).filter(|&i| !seen[i]));
+    order
+}
+
+/// Reads the candidate chosen by an `llm_first`/`llm_last` response.
+///
+/// The response is the selected row as a JSON object (or a one-element
+/// array), matched like [`rerank_order`] entries. A bare response equal to
+/// a candidate is accepted too.
+pub fn picked_index(response: &str, candidates: &[String]) -> Option<usize> {
+    match serde_json::from_str::<serde_json::Value>(response) {
+        Ok(serde_json::Value::Array(entries)) => {
+            entries.first().and_then(|entry| match_document(entry, candidates))
+        }
+        Ok(entry) => match_document(&entry, candidates),
+        Err(_) => candidates.iter().position(|c| c.trim() == response.trim()),
+    }
+}
+
+/// Matches one row returned by a Flock aggregate to its document, by `id`
+/// field first and content second.
+fn match_document(entry: &serde_json::Value, documents: &[String]) -> Option<usize> {
+    let id = match entry.get("id") {
+        Some(serde_json::Value::Number(n)) => n.as_u64().map(|n| n as usize),
+        Some(serde_json::Value::String(s)) => s.trim().parse().ok(),
+        _ => None,
+    };
+    let content = entry
+        .get("content")
+        .or_else(|| entry.get("data"))
+        .unwrap_or(entry)
+        .as_str();
+    id.filter(|&i| i < documents.len())
+        .or_else(|| content.and_then(|c| documents.iter().position(|d| d == c)))
+}
+
+/// Prompt template used by [`FlockManager::nl_to_sql`].
+///
+/// `retry` carries the previous SQL and the error it produced.
+fn nl_to_sql_prompt(schema: &str, question: &str, retry: Option<(&str, &str)>) -> String {
+    let mut prompt = format!(
+        "You translate questions into DuckDB SQL. Use only the tables and columns below.\n\n\
+         {}\n\n\
+         Answer with a single read-only SELECT statement and nothing else.\n\n\
+         Question: {}",
+        schema, question
+    );
+    if let Some((sql, error)) = retry {
+        prompt.push_str(&format!(
+            "\n\nYour previous answer was:\n{}\nIt failed with: {}\nReturn a corrected query.",
+            sql, error
+        ));
+    }
+    prompt
+}
+
+/// Describes every user table in `conn` as one `CREATE TABLE` line per table.
+///
+/// Columns come from `information_schema.columns` in declaration order;
+/// tables outside the `main` schema keep their schema qualifier.
+pub fn schema_context(conn: &Connection) -> Result<String> {
+    let mut stmt = conn.prepare(
+        "SELECT table_schema, table_name, column_name, data_type
+         FROM information_schema.columns
+         WHERE table_catalog NOT IN ('system', 'temp')
+           AND table_schema NOT IN ('information_schema', 'pg_catalog')
+         ORDER BY table_schema, table_name, ordinal_position",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+        ))
+    })?;
+
+    let mut tables: Vec<(String, Vec<String>)> = Vec::new();
+    for row in rows {
+        let (schema, table, column, data_type) = row?;
+        let name = if schema == "main" {
+            table
+        } else {
+            format!("{}.{}", schema, table)
+        };
+        let column = format!("{} {}", column, data_type);
+        match tables.last_mut() {
+            Some((last, columns)) if *last == name => columns.push(column),
+            _ => tables.push((name, vec![column])),
+        }
+    }
+
+    if tables.is_empty() {
+        return Err(anyhow::anyhow!("Database has no tables to query"));
+    }
+
+    Ok(tables
+        .into_iter()
+        .map(|(name, columns)| format!("CREATE TABLE {} ({});", name, columns.join(", ")))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Pulls the SQL statement out of a model response.
+///
+/// Prefers the first fenced code block, drops any text after the first
+/// statement and strips the trailing semicolon.
+pub fn extract_sql(response: &str) -> String {
+    let body = match response.find("```") {
+        Some(start) => {
+            let fenced = &response[start + 3..];
+            // Skip the info string (```sql) up to the end of the line
+            let fenced = fenced.find('\n').map_or(fenced, |newline| &fenced[newline + 1..]);
+            fenced.find("```").map_or(fenced, |end| &fenced[..end])
+        }
+        None => response,
+    };
+
+    split_statements(body)
+        .first()
+        .map_or_else(String::new, |sql| sql.to_string())
+}
+
+/// Checks that `sql` is a single statement DuckDB can plan against `conn`.
+pub fn validate_sql(conn: &Connection, sql: &str) -> Result<()> {
+    if sql.is_empty() {
+        return Err(anyhow::anyhow!("Model returned no SQL"));
+    }
+    if split_statements(sql).len() != 1 {
+        return Err(anyhow::anyhow!("Expected exactly one SQL statement"));
+    }
+
+    let mut stmt = conn.prepare(&format!("EXPLAIN {}", sql))?;
+    stmt.query([])?.next()?;
+    Ok(())
+}
+
+/// Candidate chosen by [`FlockManager::pick_best`] or [`FlockManager::pick_worst`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandidatePick {
+    /// Position of the candidate in the input
+    pub index: usize,
+    /// The chosen candidate
+    pub candidate: String,
+    /// The model's one-sentence explanation of the choice
+    pub rationale: String,
 }
 
 /// Result of a single validation layer.
@@ -0,0 +1,313 @@
+//! # Score Fusion
+//!
+//! Hybrid search runs several retrievers, e.g. vector similarity and BM25
+//! keyword search, and merges their rankings. Flock provides the standard
+//! fusion methods as SQL functions; this module wraps them in a typed API
+//! that checks the inputs first, which the SQL functions leave to the
+//! caller.
+//!
+//! | Method | Flock function | Inputs | Fused score |
+//! |--------|----------------|--------|-------------|
+//! | [`FusionMethod::Rrf`] | `fusion_rrf` | 1-based ranks | Reciprocal rank fusion: sum of `1 / (60 + rank)` |
+//! | [`FusionMethod::CombSum`] | `fusion_combsum` | Scores in 0..1 | Sum of the scores |
+//! | [`FusionMethod::CombMnz`] | `fusion_combmnz` | Scores in 0..1 | Sum times the number of non-zero scores |
+//! | [`FusionMethod::CombMed`] | `fusion_combmed` | Scores in 0..1 | Median score |
+//! | [`FusionMethod::CombAnz`] | `fusion_combanz` | Scores in 0..1 | Average of the non-zero scores |
+//!
+//! ## Missing Inputs
+//!
+//! A document one retriever didn't return has no rank or score there,
+//! passed as `None`. Ranks of `None` are left out, so the document only
+//! collects reciprocal ranks from the lists it is in; scores of `None`
+//! count as 0, the score of a document that wasn't retrieved.
+//!
+//! [`fuse_rankings`] does this for whole result lists: it ranks or
+//! min-max normalizes each list, fuses every document, and sorts by the
+//! fused score.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use frozen_duckdb::cli::fusion;
+//! use duckdb::Connection;
+//!
+//! let conn = Connection::open_in_memory()?;
+//! conn.execute_batch("LOAD flock;")?;
+//!
+//! // Ranked 1st by vector search, not returned by keyword search
+//! let score = fusion::rrf(&conn, &[Some(1.0), None])?;
+//! assert!(fusion::rrf(&conn, &[Some(0.0)]).is_err()); // ranks start at 1
+//! let combined = fusion::combmnz(&conn, &[Some(0.8), Some(0.4)])?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{Context, Result};
+use duckdb::Connection;
+use std::collections::HashMap;
+
+/// A Flock score fusion method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FusionMethod {
+    /// Reciprocal rank fusion of ranks
+    #[default]
+    Rrf,
+    /// Sum of normalized scores
+    CombSum,
+    /// Sum of normalized scores, times the number of non-zero scores
+    CombMnz,
+    /// Median of normalized scores
+    CombMed,
+    /// Average of the non-zero normalized scores
+    CombAnz,
+}
+
+impl FusionMethod {
+    /// Parses `rrf`, `combsum`, `combmnz`, `combmed`, or `combanz`.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "rrf" => Ok(Self::Rrf),
+            "combsum" => Ok(Self::CombSum),
+            "combmnz" => Ok(Self::CombMnz),
+            "combmed" => Ok(Self::CombMed),
+            "combanz" => Ok(Self::CombAnz),
+            other => Err(anyhow::anyhow!(
+                "Unknown fusion method: {} (use rrf, combsum, combmnz, combmed, or combanz)",
+                other
+            )),
+        }
+    }
+
+    /// Returns the method name accepted by [`parse`](Self::parse).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Rrf => "rrf",
+            Self::CombSum => "combsum",
+            Self::CombMnz => "combmnz",
+            Self::CombMed => "combmed",
+            Self::CombAnz => "combanz",
+        }
+    }
+
+    /// Returns the Flock SQL function implementing the method.
+    pub fn sql_function(&self) -> &'static str {
+        match self {
+            Self::Rrf => "fusion_rrf",
+            Self::CombSum => "fusion_combsum",
+            Self::CombMnz => "fusion_combmnz",
+            Self::CombMed => "fusion_combmed",
+            Self::CombAnz => "fusion_combanz",
+        }
+    }
+
+    /// Whether the method takes ranks rather than scores.
+    pub fn uses_ranks(&self) -> bool {
+        matches!(self, Self::Rrf)
+    }
+}
+
+/// Checks the inputs of `method` and returns the values to pass to its
+/// SQL function, with missing ranks left out and missing scores as 0.
+fn prepare(method: FusionMethod, inputs: &[Option<f64>]) -> Result<Vec<f64>> {
+    if inputs.is_empty() {
+        anyhow::bail!(
+            "Nothing to fuse: {} needs at least one input",
+            method.as_str()
+        );
+    }
+    let mut values = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        match (input, method.uses_ranks()) {
+            (None, true) => {}
+            (None, false) => values.push(0.0),
+            (Some(value), _) if !value.is_finite() => {
+                anyhow::bail!("{} can't fuse {}", method.as_str(), value)
+            }
+            (Some(rank), true) => {
+                if *rank < 1.0 || rank.fract() != 0.0 {
+                    anyhow::bail!("RRF ranks are whole numbers starting at 1, got {}", rank);
+                }
+                values.push(*rank);
+            }
+            (Some(score), false) => {
+                if !(0.0..=1.0).contains(score) {
+                    anyhow::bail!(
+                        "{} scores must be normalized to 0..1, got {}",
+                        method.as_str(),
+                        score
+                    );
+                }
+                values.push(*score);
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// Fuses one document's ranks or scores with `method`, `None` marking a
+/// list the document isn't in. Needs Flock loaded in `conn`.
+pub fn fuse(conn: &Connection, method: FusionMethod, inputs: &[Option<f64>]) -> Result<f64> {
+    let values = prepare(method, inputs)?;
+    if values.is_empty() {
+        // In none of the lists
+        return Ok(0.0);
+    }
+    let placeholders = vec!["CAST(? AS DOUBLE)"; values.len()].join(", ");
+    let sql = format!(
+        "SELECT CAST({}({}) AS DOUBLE)",
+        method.sql_function(),
+        placeholders
+    );
+    conn.query_row(&sql, duckdb::params_from_iter(&values), |row| row.get(0))
+        .with_context(|| {
+            format!(
+                "{} failed; is the Flock extension loaded?",
+                method.sql_function()
+            )
+        })
+}
+
+/// Reciprocal rank fusion of 1-based ranks, with `fusion_rrf`.
+pub fn rrf(conn: &Connection, ranks: &[Option<f64>]) -> Result<f64> {
+    fuse(conn, FusionMethod::Rrf, ranks)
+}
+
+/// Sum of normalized scores, with `fusion_combsum`.
+pub fn combsum(conn: &Connection, scores: &[Option<f64>]) -> Result<f64> {
+    fuse(conn, FusionMethod::CombSum, scores)
+}
+
+/// Sum of normalized scores times the number of non-zero scores, with
+/// `fusion_combmnz`.
+pub fn combmnz(conn: &Connection, scores: &[Option<f64>]) -> Result<f64> {
+    fuse(conn, FusionMethod::CombMnz, scores)
+}
+
+/// Median of normalized scores, with `fusion_combmed`.
+pub fn combmed(conn: &Connection, scores: &[Option<f64>]) -> Result<f64> {
+    fuse(conn, FusionMethod::CombMed, scores)
+}
+
+/// Average of the non-zero normalized scores, with `fusion_combanz`.
+pub fn combanz(conn: &Connection, scores: &[Option<f64>]) -> Result<f64> {
+    fuse(conn, FusionMethod::CombAnz, scores)
+}
+
+/// Per-document fusion inputs for result lists of (document, score)
+/// pairs, best first: 1-based ranks for RRF, scores min-max normalized
+/// within each list otherwise. Documents are in order of first
+/// appearance.
+fn fusion_inputs(
+    method: FusionMethod,
+    lists: &[Vec<(String, f32)>],
+) -> Vec<(String, Vec<Option<f64>>)> {
+    let mut documents: Vec<(String, Vec<Option<f64>>)> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for (list_index, list) in lists.iter().enumerate() {
+        let min = list.iter().map(|(_, s)| *s).fold(f32::INFINITY, f32::min);
+        let max = list
+            .iter()
+            .map(|(_, s)| *s)
+            .fold(f32::NEG_INFINITY, f32::max);
+        for (rank, (document, score)) in list.iter().enumerate() {
+            let position = *positions.entry(document.clone()).or_insert_with(|| {
+                documents.push((document.clone(), vec![None; lists.len()]));
+                documents.len() - 1
+            });
+            let input = &mut documents[position].1[list_index];
+            if input.is_some() {
+                // Listed twice; the first, better entry counts
+                continue;
+            }
+            *input = Some(if method.uses_ranks() {
+                (rank + 1) as f64
+            } else if max > min {
+                f64::from(score - min) / f64::from(max - min)
+            } else {
+                1.0
+            });
+        }
+    }
+    documents
+}
+
+/// Merges result lists of (document, score) pairs, each best first, into
+/// one list by fused score, best first. Needs Flock loaded in `conn`.
+pub fn fuse_rankings(
+    conn: &Connection,
+    method: FusionMethod,
+    lists: &[Vec<(String, f32)>],
+) -> Result<Vec<(String, f64)>> {
+    let mut fused = fusion_inputs(method, lists)
+        .into_iter()
+        .map(|(document, inputs)| Ok((document, fuse(conn, method, &inputs)?)))
+        .collect::<Result<Vec<_>>>()?;
+    // Stable, so ties keep their order of first appearance
+    fused.sort_by(|a, b| b.1.total_cmp(&a.1));
+    Ok(fused)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_methods() {
+        for name in ["rrf", "combsum", "combmnz", "combmed", "combanz"] {
+            let method = FusionMethod::parse(name).unwrap();
+            assert_eq!(method.as_str(), name);
+            assert_eq!(method.sql_function(), format!("fusion_{}", name));
+        }
+        assert!(FusionMethod::parse("borda").is_err());
+        assert_eq!(FusionMethod::default(), FusionMethod::Rrf);
+    }
+
+    #[test]
+    fn test_prepare_ranks() {
+        assert_eq!(
+            prepare(FusionMethod::Rrf, &[Some(2.0), None, Some(1.0)]).unwrap(),
+            vec![2.0, 1.0]
+        );
+        assert!(prepare(FusionMethod::Rrf, &[None]).unwrap().is_empty());
+        assert!(prepare(FusionMethod::Rrf, &[Some(0.0)]).is_err());
+        assert!(prepare(FusionMethod::Rrf, &[Some(1.5)]).is_err());
+        assert!(prepare(FusionMethod::Rrf, &[]).is_err());
+    }
+
+    #[test]
+    fn test_prepare_scores() {
+        assert_eq!(
+            prepare(FusionMethod::CombMnz, &[Some(0.5), None]).unwrap(),
+            vec![0.5, 0.0]
+        );
+        assert!(prepare(FusionMethod::CombSum, &[Some(1.2)]).is_err());
+        assert!(prepare(FusionMethod::CombSum, &[Some(-0.1)]).is_err());
+        assert!(prepare(FusionMethod::CombMed, &[Some(f64::NAN)]).is_err());
+    }
+
+    #[test]
+    fn test_fusion_inputs() {
+        let vector = vec![("a".to_string(), 0.9), ("b".to_string(), 0.5)];
+        let keyword = vec![
+            ("c".to_string(), 7.0),
+            ("a".to_string(), 3.0),
+            ("d".to_string(), 2.0),
+        ];
+        let lists = [vector, keyword];
+
+        let ranks = fusion_inputs(FusionMethod::Rrf, &lists);
+        assert_eq!(
+            ranks,
+            vec![
+                ("a".to_string(), vec![Some(1.0), Some(2.0)]),
+                ("b".to_string(), vec![Some(2.0), None]),
+                ("c".to_string(), vec![None, Some(1.0)]),
+                ("d".to_string(), vec![None, Some(3.0)]),
+            ]
+        );
+
+        let scores = fusion_inputs(FusionMethod::CombSum, &lists);
+        assert_eq!(scores[0].1, vec![Some(1.0), Some(0.2)]);
+        assert_eq!(scores[1].1, vec![Some(0.0), None]);
+        assert_eq!(scores[3].1, vec![None, Some(0.0)]);
+    }
+}
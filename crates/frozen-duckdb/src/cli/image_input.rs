@@ -0,0 +1,132 @@
+//! # Image Inputs for Multimodal Completions
+//!
+//! This module prepares images for Flock's `image` context column type.
+//! Remote images (`http://`, `https://`) are passed to Flock as URLs, and
+//! local files are read and base64-encoded by DuckDB (`read_blob` and
+//! `to_base64`), so no image is decoded or resized by the CLI itself.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use frozen_duckdb::cli::image_input::ImageSource;
+//! use frozen_duckdb::cli::FlockManager;
+//!
+//! let images = vec![
+//!     ImageSource::parse("diagram.png")?,
+//!     ImageSource::parse("https://example.com/photo.jpg")?,
+//! ];
+//! let manager = FlockManager::new()?;
+//! let response = manager.complete_with_images("Describe these images", &images, "vision")?;
+//! ```
+
+use anyhow::{Context, Result};
+use duckdb::Connection;
+use std::path::{Path, PathBuf};
+
+/// Image file extensions accepted for local images.
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "bmp"];
+
+/// Largest local image accepted, since it is sent inline with the request.
+pub const MAX_IMAGE_BYTES: u64 = 20 * 1024 * 1024;
+
+/// An image passed as context to a completion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageSource {
+    /// Remote image fetched by Flock
+    Url(String),
+    /// Local image file, sent base64-encoded
+    File(PathBuf),
+}
+
+impl ImageSource {
+    /// Parses a `--image` argument as a URL or an existing local image file.
+    pub fn parse(value: &str) -> Result<Self> {
+        if value.starts_with("http://") || value.starts_with("https://") {
+            return Ok(Self::Url(value.to_string()));
+        }
+
+        let path = Path::new(value);
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase())
+            .unwrap_or_default();
+        if !IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+            return Err(anyhow::anyhow!(
+                "Unsupported image type: {} (expected one of: {})",
+                value,
+                IMAGE_EXTENSIONS.join(", ")
+            ));
+        }
+
+        let size = path
+            .metadata()
+            .with_context(|| format!("Image not found: {}", value))?
+            .len();
+        if size > MAX_IMAGE_BYTES {
+            return Err(anyhow::anyhow!(
+                "Image too large: {} ({} MB, limit {} MB)",
+                value,
+                size / (1024 * 1024),
+                MAX_IMAGE_BYTES / (1024 * 1024)
+            ));
+        }
+        Ok(Self::File(path.to_path_buf()))
+    }
+
+    /// Returns the value for Flock's image context column: the URL, or the
+    /// base64-encoded file contents.
+    pub fn context_data(&self, conn: &Connection) -> Result<String> {
+        match self {
+            Self::Url(url) => Ok(url.clone()),
+            Self::File(path) => {
+                let path_str = path.to_string_lossy();
+                conn.query_row(
+                    "SELECT to_base64(content) FROM read_blob(?)",
+                    [path_str.as_ref()],
+                    |row| row.get(0),
+                )
+                .with_context(|| format!("Failed to read image: {}", path.display()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_parse_urls_and_files() {
+        assert_eq!(
+            ImageSource::parse("https://example.com/cat.jpg").unwrap(),
+            ImageSource::Url("https://example.com/cat.jpg".to_string())
+        );
+
+        let temp = tempfile::tempdir().unwrap();
+        let image = temp.path().join("pixel.PNG");
+        fs::write(&image, b"\x89PNG").unwrap();
+        assert_eq!(
+            ImageSource::parse(image.to_str().unwrap()).unwrap(),
+            ImageSource::File(image.clone())
+        );
+
+        assert!(ImageSource::parse(temp.path().join("missing.png").to_str().unwrap()).is_err());
+        assert!(ImageSource::parse("notes.txt").is_err());
+    }
+
+    #[test]
+    fn test_local_image_is_base64_encoded() {
+        let temp = tempfile::tempdir().unwrap();
+        let image = temp.path().join("tiny.gif");
+        fs::write(&image, b"GIF89a").unwrap();
+
+        let conn = Connection::open_in_memory().unwrap();
+        let data = ImageSource::parse(image.to_str().unwrap())
+            .unwrap()
+            .context_data(&conn)
+            .unwrap();
+        assert_eq!(data, "R0lGODlh");
+    }
+}
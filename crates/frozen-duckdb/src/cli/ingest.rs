@@ -0,0 +1,408 @@
+//! # Incremental Ingest
+//!
+//! `ingest` loads a file or table into a table of a DuckDB database. By
+//! default the table is replaced with the source's rows. Sources that only
+//! grow or change, such as a nightly export of an operational table, can
+//! be loaded incrementally instead, change-data-capture style:
+//!
+//! ```bash
+//! frozen-duckdb ingest --input orders.parquet --database shop.duckdb --table orders \
+//!   --incremental --key id --watermark-column updated_at
+//! ```
+//!
+//! ## Incremental Loads
+//!
+//! | Run | Rows read | Written as |
+//! |-----|-----------|------------|
+//! | First | All rows | A new table with a `PRIMARY KEY` on the keys |
+//! | Later | Rows whose watermark column is above the last run's maximum | Upserts: new keys are inserted, existing keys updated |
+//!
+//! The upsert is a [`merge_into`] on the key columns: `INSERT ... ON
+//! CONFLICT` when the table has a primary key or unique constraint on them,
+//! as tables created by `ingest` do, and `MERGE INTO` otherwise. When a
+//! batch has several rows for one key, the one with the highest watermark
+//! wins.
+//!
+//! The watermark of each source and target pair is kept in the dataset
+//! [`catalog`](super::catalog). Rows are only picked up when their
+//! watermark is strictly greater than the recorded one, so a row changed
+//! later but stamped with the same value as the last loaded row is missed,
+//! as are rows with a `NULL` watermark after the first run. Deleted rows
+//! aren't propagated.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use duckdb::Connection;
+//! use frozen_duckdb::cli::ingest::{ingest, IncrementalOptions};
+//!
+//! let conn = Connection::open("shop.duckdb")?;
+//! let options = IncrementalOptions {
+//!     key_columns: vec!["id".to_string()],
+//!     watermark_column: "updated_at".to_string(),
+//! };
+//! let report = ingest(
+//!     &conn,
+//!     "read_parquet('orders.parquet')",
+//!     "orders",
+//!     Some(&options),
+//!     Some("2024-01-01 00:00:00"),
+//! )?;
+//! println!("{} inserted, {} updated", report.inserted, report.updated);
+//! ```
+
+use super::catalog::validate_name;
+use super::dedupe::quote_identifier;
+use super::merge::{merge_into, MergeOptions};
+use anyhow::{Context, Result};
+use duckdb::Connection;
+use tracing::info;
+
+/// Settings of an incremental load.
+#[derive(Debug, Clone, Default)]
+pub struct IncrementalOptions {
+    /// Columns identifying a row; the primary key of a table `ingest`
+    /// creates
+    pub key_columns: Vec<String>,
+    /// Column that increases whenever a row is added or changed, such as
+    /// `updated_at`
+    pub watermark_column: String,
+}
+
+/// Outcome of an [`ingest`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IngestReport {
+    /// Rows read from the source, after filtering by the watermark
+    pub rows_read: usize,
+    /// Rows added to the table
+    pub inserted: usize,
+    /// Existing rows replaced by a newer version
+    pub updated: usize,
+    /// Highest watermark loaded so far, as text; the previous one if no
+    /// rows were new
+    pub watermark: Option<String>,
+    /// Whether the table was created (or replaced, for full loads)
+    pub created: bool,
+}
+
+/// Loads the rows of `source`, a table function such as
+/// `read_parquet('orders.parquet')`, into `table`.
+///
+/// Without `incremental`, `table` is replaced. With it, only rows whose
+/// watermark column is above `watermark` are read, and they are upserted
+/// on the key columns.
+pub fn ingest(
+    conn: &Connection,
+    source: &str,
+    table: &str,
+    incremental: Option<&IncrementalOptions>,
+    watermark: Option<&str>,
+) -> Result<IngestReport> {
+    validate_name(table)?;
+    let Some(options) = incremental else {
+        conn.execute_batch(&format!(
+            "CREATE OR REPLACE TABLE {} AS SELECT * FROM {};",
+            quote_identifier(table),
+            source
+        ))
+        .with_context(|| format!("Failed to load {} into {}", source, table))?;
+        let rows = count_rows(conn, &quote_identifier(table))?;
+        info!("📥 Loaded {} rows into {}", rows, table);
+        return Ok(IngestReport {
+            rows_read: rows,
+            inserted: rows,
+            created: true,
+            ..Default::default()
+        });
+    };
+
+    if options.key_columns.is_empty() {
+        return Err(anyhow::anyhow!("At least one key column is required"));
+    }
+    let columns = describe(conn, source)?;
+    let watermark_type = column_type(&columns, &options.watermark_column, source)?;
+    for key in &options.key_columns {
+        column_type(&columns, key, source)?;
+    }
+
+    let created = !table_exists(conn, table)?;
+    if created {
+        conn.execute_batch(&create_table_sql(table, &columns, &options.key_columns))
+            .with_context(|| format!("Failed to create table {}", table))?;
+    }
+
+    conn.execute_batch(&batch_sql(source, options, watermark_type, watermark))
+        .with_context(|| format!("Failed to read new rows from {}", source))?;
+    let merge = MergeOptions {
+        key_columns: options.key_columns.clone(),
+        ..Default::default()
+    };
+    let merged = merge_into(conn, table, "ingest_batch", &merge)?;
+
+    let latest: Option<String> = conn.query_row(
+        &format!(
+            "SELECT CAST(max({}) AS VARCHAR) FROM ingest_batch",
+            quote_identifier(&options.watermark_column)
+        ),
+        [],
+        |row| row.get(0),
+    )?;
+    conn.execute_batch("DROP TABLE ingest_batch;")?;
+
+    info!(
+        "📥 Merged {} new or changed rows into {} ({} inserted, {} updated)",
+        merged.source_rows, table, merged.inserted, merged.updated
+    );
+    Ok(IngestReport {
+        rows_read: merged.source_rows,
+        inserted: merged.inserted,
+        updated: merged.updated,
+        watermark: latest.or_else(|| watermark.map(str::to_string)),
+        created,
+    })
+}
+
+/// Column names and types of `source`.
+fn describe(conn: &Connection, source: &str) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT column_name, column_type FROM (DESCRIBE SELECT * FROM {})",
+            source
+        ))
+        .with_context(|| format!("Failed to read the schema of {}", source))?;
+    let columns = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<duckdb::Result<Vec<_>>>()?;
+    Ok(columns)
+}
+
+fn column_type<'a>(columns: &'a [(String, String)], name: &str, source: &str) -> Result<&'a str> {
+    columns
+        .iter()
+        .find(|(column, _)| column == name)
+        .map(|(_, data_type)| data_type.as_str())
+        .with_context(|| {
+            let names: Vec<&str> = columns.iter().map(|(column, _)| column.as_str()).collect();
+            format!(
+                "Column {} not found in {} (columns: {})",
+                name,
+                source,
+                names.join(", ")
+            )
+        })
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT count(*) FROM duckdb_tables() \
+         WHERE database_name = current_database() AND schema_name = current_schema() \
+         AND table_name = ?",
+        [table],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+fn count_rows(conn: &Connection, table: &str) -> Result<usize> {
+    let count: i64 = conn.query_row(&format!("SELECT count(*) FROM {}", table), [], |row| {
+        row.get(0)
+    })?;
+    Ok(count as usize)
+}
+
+fn key_list(keys: &[String]) -> String {
+    keys.iter()
+        .map(|key| quote_identifier(key))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `CREATE TABLE` with the source's columns and a primary key on `keys`.
+fn create_table_sql(table: &str, columns: &[(String, String)], keys: &[String]) -> String {
+    let definitions: Vec<String> = columns
+        .iter()
+        .map(|(name, data_type)| format!("{} {}", quote_identifier(name), data_type))
+        .collect();
+    format!(
+        "CREATE TABLE {} ({}, PRIMARY KEY ({}));",
+        quote_identifier(table),
+        definitions.join(", "),
+        key_list(keys)
+    )
+}
+
+/// Stages the rows above `watermark`, one per key, as `ingest_batch`.
+fn batch_sql(
+    source: &str,
+    options: &IncrementalOptions,
+    watermark_type: &str,
+    watermark: Option<&str>,
+) -> String {
+    let column = quote_identifier(&options.watermark_column);
+    let filter = match watermark {
+        Some(value) => format!(
+            " WHERE {} > CAST('{}' AS {})",
+            column,
+            value.replace('\'', "''"),
+            watermark_type
+        ),
+        None => String::new(),
+    };
+    format!(
+        "CREATE OR REPLACE TEMP TABLE ingest_batch AS SELECT * FROM {}{} \
+         QUALIFY row_number() OVER (PARTITION BY {} ORDER BY {} DESC NULLS LAST) = 1;",
+        source,
+        filter,
+        key_list(&options.key_columns),
+        column
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn columns() -> Vec<(String, String)> {
+        [
+            ("id", "BIGINT"),
+            ("status", "VARCHAR"),
+            ("updated_at", "TIMESTAMP"),
+        ]
+        .iter()
+        .map(|(name, data_type)| (name.to_string(), data_type.to_string()))
+        .collect()
+    }
+
+    fn options() -> IncrementalOptions {
+        IncrementalOptions {
+            key_columns: vec!["id".to_string()],
+            watermark_column: "updated_at".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_create_table_sql() {
+        assert_eq!(
+            create_table_sql("orders", &columns(), &["id".to_string()]),
+            "CREATE TABLE \"orders\" (\"id\" BIGINT, \"status\" VARCHAR, \
+             \"updated_at\" TIMESTAMP, PRIMARY KEY (\"id\"));"
+        );
+    }
+
+    #[test]
+    fn test_batch_sql() {
+        assert_eq!(
+            batch_sql("read_parquet('o.parquet')", &options(), "TIMESTAMP", None),
+            "CREATE OR REPLACE TEMP TABLE ingest_batch AS SELECT * FROM read_parquet('o.parquet') \
+             QUALIFY row_number() OVER (PARTITION BY \"id\" ORDER BY \"updated_at\" DESC NULLS LAST) = 1;"
+        );
+        assert!(
+            batch_sql("t", &options(), "TIMESTAMP", Some("2024-01-01 00:00:00"))
+                .contains(" WHERE \"updated_at\" > CAST('2024-01-01 00:00:00' AS TIMESTAMP) ")
+        );
+    }
+
+    #[test]
+    fn test_incremental_ingest() {
+        let dir = tempfile::tempdir().unwrap();
+        let csv = dir.path().join("orders.csv");
+        let source = format!("read_csv('{}')", csv.display());
+        let conn = Connection::open_in_memory().unwrap();
+
+        fs::write(
+            &csv,
+            "id,status,updated_at\n1,new,2024-01-01 10:00:00\n2,new,2024-01-01 11:00:00\n",
+        )
+        .unwrap();
+        let first = ingest(&conn, &source, "orders", Some(&options()), None).unwrap();
+        assert!(first.created);
+        assert_eq!((first.inserted, first.updated), (2, 0));
+        assert_eq!(first.watermark.as_deref(), Some("2024-01-01 11:00:00"));
+
+        // Order 2 shipped and order 3 arrived; order 1 is unchanged
+        fs::write(
+            &csv,
+            "id,status,updated_at\n1,new,2024-01-01 10:00:00\n2,shipped,2024-01-02 09:00:00\n\
+             3,new,2024-01-02 10:00:00\n",
+        )
+        .unwrap();
+        let second = ingest(
+            &conn,
+            &source,
+            "orders",
+            Some(&options()),
+            first.watermark.as_deref(),
+        )
+        .unwrap();
+        assert!(!second.created);
+        assert_eq!(
+            (second.rows_read, second.inserted, second.updated),
+            (2, 1, 1)
+        );
+        assert_eq!(second.watermark.as_deref(), Some("2024-01-02 10:00:00"));
+
+        let status: String = conn
+            .query_row("SELECT status FROM orders WHERE id = 2", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(status, "shipped");
+
+        // Nothing new keeps the watermark
+        let third = ingest(
+            &conn,
+            &source,
+            "orders",
+            Some(&options()),
+            second.watermark.as_deref(),
+        )
+        .unwrap();
+        assert_eq!(third.rows_read, 0);
+        assert_eq!(third.watermark, second.watermark);
+
+        let error = ingest(
+            &conn,
+            &source,
+            "orders",
+            Some(&IncrementalOptions {
+                key_columns: vec!["order_id".to_string()],
+                watermark_column: "updated_at".to_string(),
+            }),
+            None,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("order_id"), "{}", error);
+    }
+
+    #[test]
+    fn test_incremental_ingest_without_primary_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let csv = dir.path().join("orders.csv");
+        let source = format!("read_csv('{}')", csv.display());
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE orders (id BIGINT, status VARCHAR, updated_at TIMESTAMP);
+             INSERT INTO orders VALUES (1, 'new', '2024-01-01 10:00:00');",
+        )
+        .unwrap();
+
+        fs::write(
+            &csv,
+            "id,status,updated_at\n1,shipped,2024-01-02 09:00:00\n2,new,2024-01-02 10:00:00\n",
+        )
+        .unwrap();
+        let report = ingest(&conn, &source, "orders", Some(&options()), None).unwrap();
+        assert!(!report.created);
+        assert_eq!((report.inserted, report.updated), (1, 1));
+
+        let statuses: String = conn
+            .query_row(
+                "SELECT string_agg(status, ',' ORDER BY id) FROM orders",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(statuses, "shipped,new");
+    }
+}
@@ -0,0 +1,905 @@
+//! # Scheduled Jobs
+//!
+//! `frozen-duckdb jobs` runs recurring dataset tasks defined in a TOML
+//! file: SQL scripts, format conversions, embedding index refreshes, and
+//! database backups. Run due jobs once with `jobs run --once` (for example
+//! from system cron), or keep `jobs run` running as a daemon.
+//!
+//! ## Job File
+//!
+//! ```toml
+//! # Where run history is recorded (default: jobs.duckdb in the cache directory)
+//! history = "jobs.duckdb"
+//!
+//! [[job]]
+//! name = "nightly-load"
+//! schedule = "0 2 * * *"
+//! type = "sql"
+//! database = "analytics.duckdb"
+//! script = "load.sql"            # or: sql = "INSERT INTO ..."
+//!
+//! [[job]]
+//! name = "events-to-parquet"
+//! schedule = "@every 15m"
+//! type = "convert"
+//! input = "events.csv"
+//! output = "events.parquet"       # formats are detected from extensions
+//!
+//! [[job]]
+//! name = "docs-index"
+//! schedule = "@daily"
+//! type = "index"
+//! corpus = "docs/"
+//! index = "docs.duckdb"
+//! model = "embedder"
+//!
+//! [[job]]
+//! name = "weekly-backup"
+//! schedule = "30 3 * * 0"
+//! type = "backup"
+//! database = "analytics.duckdb"
+//! output = "backups/"
+//! ```
+//!
+//! ## Schedules
+//!
+//! | Schedule | Meaning |
+//! |----------|---------|
+//! | `m h dom mon dow` | Standard five-field cron, in local time |
+//! | `@hourly`, `@daily`, `@weekly`, `@monthly`, `@yearly` | Cron shorthands |
+//! | `@every 15m` | Fixed interval after the previous run |
+//!
+//! Cron fields accept `*`, numbers, ranges (`1-5`), steps (`*/10`), and
+//! lists (`1,15`). A job that has never run is due immediately; after that
+//! it's due at the first scheduled time after its previous run, so a run
+//! missed while the daemon was stopped happens once when it restarts.
+//!
+//! ## Run History
+//!
+//! Every run is recorded in the `frozen_duckdb_job_runs` table of the
+//! history database with its start and finish time, status, and a short
+//! message, so it can be queried like any other data:
+//!
+//! ```sql
+//! SELECT job, status, started_at FROM frozen_duckdb_job_runs ORDER BY started_at DESC;
+//! ```
+//!
+//! Paths in the job file are relative to the working directory.
+
+use super::cache_dir::cache_path;
+use super::config::CliConfig;
+use super::dataset_manager::{detect_format, ConvertOptions, DatasetManager};
+use super::embedding_backends::EmbeddingBackend;
+use super::embedding_index::{load_corpus, EmbeddingIndex, IndexOptions};
+use super::flock_manager::FlockManager;
+use super::response_cache::parse_ttl;
+use super::sql_path::path_literal;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use duckdb::{params, Connection};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{error, info};
+
+const HISTORY_FILE: &str = "jobs.duckdb";
+
+/// Table holding the run history.
+pub const JOB_RUNS_TABLE: &str = "frozen_duckdb_job_runs";
+
+/// Timestamp format used to store and read run times.
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.6f";
+
+/// Longest the daemon sleeps before re-reading the history, so runs
+/// recorded by `jobs run --once` in another process are noticed.
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+/// Five-field cron schedule, with each field stored as a bit set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Day of month is `*`
+    any_day: bool,
+    /// Day of week is `*`
+    any_weekday: bool,
+}
+
+impl CronSchedule {
+    /// Parses `minute hour day-of-month month day-of-week`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let fields: Vec<&str> = spec.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            bail!(
+                "Cron schedule needs 5 fields, got {}: {}",
+                fields.len(),
+                spec
+            );
+        };
+        let (days, any_day) = parse_field(day, 1, 31)?;
+        let (weekdays, any_weekday) = parse_field(weekday, 0, 7)?;
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59)?.0,
+            hours: parse_field(hour, 0, 23)?.0,
+            days,
+            months: parse_field(month, 1, 12)?.0,
+            // 7 is Sunday, like 0
+            weekdays: (weekdays | (weekdays >> 7)) & 0x7f,
+            any_day,
+            any_weekday,
+        })
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        let day_matches = match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (false, true) => day,
+            (true, false) => weekday,
+            // When both are restricted, cron runs on either
+            (false, false) => day || weekday,
+        };
+        day_matches && self.months & (1 << date.month()) != 0
+    }
+
+    /// Whether the schedule fires at the minute containing `t`.
+    pub fn matches(&self, t: NaiveDateTime) -> bool {
+        self.matches_date(t.date())
+            && self.hours & (1 << t.hour()) != 0
+            && self.minutes & (1 << t.minute()) != 0
+    }
+
+    /// Returns the first minute strictly after `t` the schedule fires at,
+    /// searching up to five years ahead.
+    pub fn next_after(&self, t: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = t.date().and_hms_opt(t.hour(), t.minute(), 0)? + chrono::Duration::minutes(1);
+        let limit = start + chrono::Duration::days(5 * 366);
+
+        let mut date = start.date();
+        let mut from = start.time();
+        while date.and_time(from) < limit {
+            if self.matches_date(date) {
+                for hour in from.hour()..24 {
+                    if self.hours & (1 << hour) == 0 {
+                        continue;
+                    }
+                    let first_minute = if hour == from.hour() {
+                        from.minute()
+                    } else {
+                        0
+                    };
+                    if let Some(minute) = (first_minute..60).find(|m| self.minutes & (1 << m) != 0)
+                    {
+                        return date.and_hms_opt(hour, minute, 0);
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+            from = NaiveTime::MIN;
+        }
+        None
+    }
+}
+
+/// Parses one cron field into a bit set of allowed values, and whether it
+/// was `*`.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<(u64, bool)> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| anyhow!("Invalid step in cron field: {}", field))?,
+            ),
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (start.parse()?, end.parse()?)
+        } else {
+            let value: u32 = range
+                .parse()
+                .with_context(|| format!("Invalid cron field: {}", field))?;
+            // "5/15" means every 15 starting at 5
+            (value, if step > 1 { max } else { value })
+        };
+        if start < min || end > max || start > end {
+            bail!("Cron field out of range {}-{}: {}", min, max, field);
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok((bits, field == "*"))
+}
+
+/// When a job runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// Cron expression or shorthand
+    Cron(CronSchedule),
+    /// Fixed interval after the previous run
+    Every(Duration),
+}
+
+impl Schedule {
+    /// Parses a cron expression, a shorthand like `@daily`, or
+    /// `@every <interval>`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        if let Some(interval) = spec.strip_prefix("@every") {
+            let interval = parse_ttl(interval.trim())
+                .with_context(|| format!("Invalid interval in schedule: {}", spec))?;
+            if interval < Duration::from_secs(1) {
+                bail!("Interval must be at least 1s: {}", spec);
+            }
+            return Ok(Self::Every(interval));
+        }
+        let cron = match spec {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        Ok(Self::Cron(CronSchedule::parse(cron)?))
+    }
+
+    /// Returns the first time after `t` the schedule fires.
+    pub fn next_after(&self, t: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self {
+            Self::Cron(cron) => cron.next_after(t),
+            Self::Every(interval) => Some(t + chrono::Duration::from_std(*interval).ok()?),
+        }
+    }
+}
+
+/// What a job does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobKind {
+    /// Runs a SQL script against a database
+    Sql {
+        /// Database file; in-memory if not set
+        database: Option<String>,
+        /// Script file, read on every run
+        script: Option<String>,
+        /// Inline SQL, used when no script is set
+        sql: Option<String>,
+    },
+    /// Converts a file between formats
+    Convert {
+        /// Input file
+        input: String,
+        /// Output file
+        output: String,
+        /// Input format; detected from the extension if not set
+        input_format: Option<String>,
+        /// Output format; detected from the extension if not set
+        output_format: Option<String>,
+    },
+    /// Embeds documents added to a corpus since the last run
+    Index {
+        /// Corpus file or directory
+        corpus: String,
+        /// Index database
+        index: String,
+        /// Embedding model alias
+        model: String,
+        /// Documents embedded per batch
+        batch_size: usize,
+    },
+    /// Exports a database as Parquet files into a timestamped directory
+    Backup {
+        /// Database file to back up
+        database: String,
+        /// Directory the backups are written under
+        output: String,
+    },
+}
+
+/// A named, scheduled task from the job file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Job {
+    /// Unique job name
+    pub name: String,
+    /// Schedule as written in the job file
+    pub schedule_spec: String,
+    /// Parsed schedule
+    pub schedule: Schedule,
+    /// What the job does
+    pub kind: JobKind,
+}
+
+impl Job {
+    /// Short name of the job type, as written in the job file.
+    pub fn kind_name(&self) -> &'static str {
+        match self.kind {
+            JobKind::Sql { .. } => "sql",
+            JobKind::Convert { .. } => "convert",
+            JobKind::Index { .. } => "index",
+            JobKind::Backup { .. } => "backup",
+        }
+    }
+
+    fn from_table(table: &toml::Table) -> Result<Self> {
+        let string = |key: &str| -> Result<Option<String>> {
+            match table.get(key) {
+                None => Ok(None),
+                Some(toml::Value::String(s)) => Ok(Some(s.clone())),
+                Some(other) => bail!("'{}' must be a string, got {}", key, other),
+            }
+        };
+        let required = |key: &str| -> Result<String> {
+            string(key)?.ok_or_else(|| anyhow!("'{}' is required", key))
+        };
+
+        let name = required("name")?;
+        let schedule_spec = required("schedule")?;
+        let schedule = Schedule::parse(&schedule_spec)
+            .with_context(|| format!("Invalid schedule for job {}", name))?;
+        let kind = match required("type")?.as_str() {
+            "sql" => {
+                let (script, sql) = (string("script")?, string("sql")?);
+                if script.is_none() == sql.is_none() {
+                    bail!("Job {} needs exactly one of 'script' or 'sql'", name);
+                }
+                JobKind::Sql {
+                    database: string("database")?,
+                    script,
+                    sql,
+                }
+            }
+            "convert" => JobKind::Convert {
+                input: required("input")?,
+                output: required("output")?,
+                input_format: string("input_format")?,
+                output_format: string("output_format")?,
+            },
+            "index" => JobKind::Index {
+                corpus: required("corpus")?,
+                index: required("index")?,
+                model: string("model")?.unwrap_or_else(|| "embedder".to_string()),
+                batch_size: match table.get("batch_size") {
+                    None => IndexOptions::default().batch_size,
+                    Some(toml::Value::Integer(n)) if *n > 0 => *n as usize,
+                    Some(other) => bail!("'batch_size' must be a positive integer, got {}", other),
+                },
+            },
+            "backup" => JobKind::Backup {
+                database: required("database")?,
+                output: required("output")?,
+            },
+            other => bail!(
+                "Unknown job type for {}: {} (use sql, convert, index, or backup)",
+                name,
+                other
+            ),
+        };
+        Ok(Self {
+            name,
+            schedule_spec,
+            schedule,
+            kind,
+        })
+    }
+}
+
+/// Jobs and settings loaded from a TOML job file.
+#[derive(Debug, Clone)]
+pub struct JobFile {
+    /// History database path
+    pub history: PathBuf,
+    /// Jobs in file order
+    pub jobs: Vec<Job>,
+}
+
+impl JobFile {
+    /// Loads and validates a job file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read job file: {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid job file: {}", path.display()))
+    }
+
+    /// Parses and validates job file contents.
+    pub fn parse(content: &str) -> Result<Self> {
+        let table: toml::Table = content.parse()?;
+        let history = match table.get("history") {
+            Some(toml::Value::String(path)) => PathBuf::from(path),
+            Some(other) => bail!("'history' must be a string, got {}", other),
+            None => cache_path(HISTORY_FILE)?,
+        };
+
+        let mut jobs: Vec<Job> = Vec::new();
+        if let Some(entries) = table.get("job") {
+            let entries = entries
+                .as_array()
+                .ok_or_else(|| anyhow!("'job' must be an array of tables ([[job]])"))?;
+            for (i, entry) in entries.iter().enumerate() {
+                let entry = entry
+                    .as_table()
+                    .ok_or_else(|| anyhow!("Job {} must be a table", i + 1))?;
+                let job =
+                    Job::from_table(entry).with_context(|| format!("Invalid job {}", i + 1))?;
+                if jobs.iter().any(|j| j.name == job.name) {
+                    bail!("Duplicate job name: {}", job.name);
+                }
+                jobs.push(job);
+            }
+        }
+        Ok(Self { history, jobs })
+    }
+
+    /// Returns the job named `name`.
+    pub fn get(&self, name: &str) -> Result<&Job> {
+        self.jobs
+            .iter()
+            .find(|j| j.name == name)
+            .ok_or_else(|| anyhow!("No job named {}", name))
+    }
+}
+
+/// One recorded job run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobRun {
+    /// Job name
+    pub job: String,
+    /// Start time, local
+    pub started_at: String,
+    /// Finish time, local
+    pub finished_at: String,
+    /// `success` or `failed`
+    pub status: String,
+    /// Summary on success, error on failure
+    pub message: String,
+}
+
+/// Run history stored in a DuckDB table.
+pub struct JobHistory {
+    conn: Connection,
+}
+
+impl JobHistory {
+    /// Opens (creating if needed) the history database at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open job history: {}", path.display()))?;
+        Self::with_connection(conn)
+    }
+
+    /// Uses an existing connection, creating the history table if needed.
+    pub fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                job VARCHAR NOT NULL,
+                started_at TIMESTAMP NOT NULL,
+                finished_at TIMESTAMP NOT NULL,
+                status VARCHAR NOT NULL,
+                message VARCHAR
+            )",
+            JOB_RUNS_TABLE
+        ))?;
+        Ok(Self { conn })
+    }
+
+    /// Returns when `job` last started, if it ever ran.
+    pub fn last_run(&self, job: &str) -> Result<Option<NaiveDateTime>> {
+        let last: Option<String> = self.conn.query_row(
+            &format!(
+                "SELECT strftime(MAX(started_at), '%Y-%m-%d %H:%M:%S.%f') FROM {} WHERE job = ?",
+                JOB_RUNS_TABLE
+            ),
+            params![job],
+            |row| row.get(0),
+        )?;
+        last.map(|t| NaiveDateTime::parse_from_str(&t, TIMESTAMP_FORMAT))
+            .transpose()
+            .context("Invalid timestamp in job history")
+    }
+
+    /// Records a finished run.
+    pub fn record(
+        &self,
+        job: &str,
+        started_at: NaiveDateTime,
+        finished_at: NaiveDateTime,
+        result: &Result<String>,
+    ) -> Result<()> {
+        let (status, message) = match result {
+            Ok(summary) => ("success", summary.clone()),
+            Err(e) => ("failed", format!("{:#}", e)),
+        };
+        self.conn.execute(
+            &format!(
+                "INSERT INTO {} VALUES (?, CAST(? AS TIMESTAMP), CAST(? AS TIMESTAMP), ?, ?)",
+                JOB_RUNS_TABLE
+            ),
+            params![
+                job,
+                started_at.format(TIMESTAMP_FORMAT).to_string(),
+                finished_at.format(TIMESTAMP_FORMAT).to_string(),
+                status,
+                message
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Returns the most recent runs, newest first, optionally of one job.
+    pub fn runs(&self, job: Option<&str>, limit: usize) -> Result<Vec<JobRun>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT job, strftime(started_at, '%Y-%m-%d %H:%M:%S'),
+                    strftime(finished_at, '%Y-%m-%d %H:%M:%S'), status, COALESCE(message, '')
+             FROM {} WHERE job = COALESCE(?, job) ORDER BY started_at DESC LIMIT ?",
+            JOB_RUNS_TABLE
+        ))?;
+        let runs = stmt
+            .query_map(params![job, limit as i64], |row| {
+                Ok(JobRun {
+                    job: row.get(0)?,
+                    started_at: row.get(1)?,
+                    finished_at: row.get(2)?,
+                    status: row.get(3)?,
+                    message: row.get(4)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(runs)
+    }
+
+    /// Returns when `job` is next due: now if it never ran, otherwise the
+    /// first scheduled time after its last run.
+    pub fn next_run(&self, job: &Job, now: NaiveDateTime) -> Result<Option<NaiveDateTime>> {
+        Ok(match self.last_run(&job.name)? {
+            None => Some(now),
+            Some(last) => job.schedule.next_after(last),
+        })
+    }
+
+    /// Returns the jobs due at `now`.
+    pub fn due_jobs<'j>(&self, jobs: &'j [Job], now: NaiveDateTime) -> Result<Vec<&'j Job>> {
+        let mut due = Vec::new();
+        for job in jobs {
+            if self.next_run(job, now)?.is_some_and(|next| next <= now) {
+                due.push(job);
+            }
+        }
+        Ok(due)
+    }
+
+    /// Runs `job` with `execute` and records the outcome, returning whether
+    /// it succeeded.
+    pub fn run<F>(&self, job: &Job, execute: F) -> Result<bool>
+    where
+        F: FnOnce(&Job) -> Result<String>,
+    {
+        info!("▶️  Running job {} ({})", job.name, job.kind_name());
+        let started_at = Local::now().naive_local();
+        let result = execute(job);
+        self.record(&job.name, started_at, Local::now().naive_local(), &result)?;
+        match &result {
+            Ok(summary) => info!("✅ Job {} succeeded: {}", job.name, summary),
+            Err(e) => error!("❌ Job {} failed: {:#}", job.name, e),
+        }
+        Ok(result.is_ok())
+    }
+
+    /// Runs every due job once, returning `(succeeded, failed)` counts.
+    pub fn run_due<F>(&self, jobs: &[Job], mut execute: F) -> Result<(usize, usize)>
+    where
+        F: FnMut(&Job) -> Result<String>,
+    {
+        let (mut succeeded, mut failed) = (0, 0);
+        for job in self.due_jobs(jobs, Local::now().naive_local())? {
+            if self.run(job, &mut execute)? {
+                succeeded += 1;
+            } else {
+                failed += 1;
+            }
+        }
+        Ok((succeeded, failed))
+    }
+
+    /// Runs jobs as they come due until the process is interrupted.
+    ///
+    /// A failing job is logged and recorded, and retried at its next
+    /// scheduled time.
+    pub fn run_daemon<F>(&self, jobs: &[Job], mut execute: F) -> Result<()>
+    where
+        F: FnMut(&Job) -> Result<String>,
+    {
+        info!("🕒 Scheduler started with {} jobs", jobs.len());
+        loop {
+            self.run_due(jobs, &mut execute)?;
+
+            let now = Local::now().naive_local();
+            let mut sleep = MAX_SLEEP;
+            for job in jobs {
+                if let Some(next) = self.next_run(job, now)? {
+                    let until = (next - now).to_std().unwrap_or_default();
+                    sleep = sleep.min(until.max(Duration::from_secs(1)));
+                }
+            }
+            std::thread::sleep(sleep);
+        }
+    }
+}
+
+/// Runs a job's task, returning a one-line summary.
+///
+/// `open_flock` is only called for index jobs using the flock embedding
+/// backend.
+pub fn execute_job(job: &Job, open_flock: &dyn Fn() -> Result<FlockManager>) -> Result<String> {
+    match &job.kind {
+        JobKind::Sql {
+            database,
+            script,
+            sql,
+        } => {
+            let sql = match (script, sql) {
+                (Some(script), _) => fs::read_to_string(script)
+                    .with_context(|| format!("Failed to read SQL script: {}", script))?,
+                (None, Some(sql)) => sql.clone(),
+                (None, None) => bail!("Job {} has no SQL", job.name),
+            };
+            let manager = match database {
+                Some(path) => DatasetManager::open(path)?,
+                None => DatasetManager::new()?,
+            };
+            manager.connection().execute_batch(&sql)?;
+            Ok(format!("ran {}", script.as_deref().unwrap_or("inline SQL")))
+        }
+        JobKind::Convert {
+            input,
+            output,
+            input_format,
+            output_format,
+        } => {
+            let input_format = match input_format {
+                Some(format) => format.clone(),
+                None => detect_format(input)
+                    .with_context(|| format!("Can't detect the format of {}", input))?
+                    .to_string(),
+            };
+            let output_format = match output_format {
+                Some(format) => format.clone(),
+                None => detect_format(output)
+                    .with_context(|| format!("Can't detect the format of {}", output))?
+                    .to_string(),
+            };
+            DatasetManager::new()?.convert_dataset_with(
+                input,
+                output,
+                &input_format,
+                &output_format,
+                &ConvertOptions {
+                    auto: true,
+                    ..Default::default()
+                },
+            )?;
+            Ok(format!("converted {} to {}", input, output))
+        }
+        JobKind::Index {
+            corpus,
+            index,
+            model,
+            batch_size,
+        } => {
+            let embedding = CliConfig::load()?.embedding()?;
+            let manager = match embedding.backend {
+                EmbeddingBackend::Flock => {
+                    let manager = open_flock()?;
+                    if !manager.is_flock_ready()? {
+                        bail!("Flock extension not available; run 'frozen-duckdb flock-setup'");
+                    }
+                    Some(manager)
+                }
+                _ => None,
+            };
+            let documents = load_corpus(corpus)?;
+            let report = EmbeddingIndex::open(index)?.build(
+                &documents,
+                &embedding.embedder(manager.as_ref(), model.clone(), false)?,
+                &IndexOptions {
+                    batch_size: *batch_size,
+                    resume: true,
+                },
+            )?;
+            Ok(format!(
+                "indexed {} new documents ({} already indexed)",
+                report.indexed_documents, report.skipped_documents
+            ))
+        }
+        JobKind::Backup { database, output } => {
+            let stem = Path::new(database)
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "database".to_string());
+            let target = Path::new(output).join(format!(
+                "{}-{}",
+                stem,
+                Local::now().format("%Y%m%d-%H%M%S")
+            ));
+            fs::create_dir_all(output)?;
+            let conn = Connection::open(database)
+                .with_context(|| format!("Failed to open database: {}", database))?;
+            conn.execute_batch(&format!(
+                "EXPORT DATABASE {} (FORMAT parquet)",
+                path_literal(&target)
+            ))?;
+            Ok(format!("exported to {}", target.display()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        let daily = Schedule::parse("0 2 * * *").unwrap();
+        assert_eq!(
+            daily.next_after(at("2024-03-10 01:59")),
+            Some(at("2024-03-10 02:00"))
+        );
+        assert_eq!(
+            daily.next_after(at("2024-03-10 02:00")),
+            Some(at("2024-03-11 02:00"))
+        );
+
+        let every_ten = Schedule::parse("*/10 9-17 * * 1-5").unwrap();
+        // Friday evening -> Monday morning
+        assert_eq!(
+            every_ten.next_after(at("2024-03-08 17:55")),
+            Some(at("2024-03-11 09:00"))
+        );
+
+        // Sunday written as 7
+        let sunday = Schedule::parse("30 3 * * 7").unwrap();
+        assert_eq!(
+            sunday.next_after(at("2024-03-11 00:00")),
+            Some(at("2024-03-17 03:30"))
+        );
+
+        let leap = Schedule::parse("0 0 29 2 *").unwrap();
+        assert_eq!(
+            leap.next_after(at("2024-03-01 00:00")),
+            Some(at("2028-02-29 00:00"))
+        );
+
+        assert_eq!(
+            Schedule::parse("@every 15m")
+                .unwrap()
+                .next_after(at("2024-03-10 01:50")),
+            Some(at("2024-03-10 02:05"))
+        );
+    }
+
+    #[test]
+    fn test_invalid_schedules() {
+        for spec in [
+            "0 2 * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "@every 0s",
+            "@often",
+        ] {
+            assert!(
+                Schedule::parse(spec).is_err(),
+                "{} should be rejected",
+                spec
+            );
+        }
+    }
+
+    #[test]
+    fn test_job_file_parse() {
+        let file = JobFile::parse(
+            r#"
+            history = "history.duckdb"
+
+            [[job]]
+            name = "load"
+            schedule = "@daily"
+            type = "sql"
+            sql = "SELECT 1"
+
+            [[job]]
+            name = "backup"
+            schedule = "30 3 * * 0"
+            type = "backup"
+            database = "app.duckdb"
+            output = "backups"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(file.history, PathBuf::from("history.duckdb"));
+        assert_eq!(file.jobs.len(), 2);
+        assert_eq!(file.get("backup").unwrap().kind_name(), "backup");
+
+        let missing =
+            JobFile::parse("[[job]]\nname = \"x\"\nschedule = \"@daily\"\ntype = \"sql\"");
+        assert!(format!("{:#}", missing.unwrap_err()).contains("'script' or 'sql'"));
+        let duplicate =
+            "[[job]]\nname = \"x\"\nschedule = \"@daily\"\ntype = \"sql\"\nsql = \"SELECT 1\"\n";
+        assert!(JobFile::parse(&format!("{}{}", duplicate, duplicate)).is_err());
+    }
+
+    #[test]
+    fn test_history_and_due_jobs() -> Result<()> {
+        let history = JobHistory::with_connection(Connection::open_in_memory()?)?;
+        let file = JobFile::parse(
+            "history = \"h.duckdb\"\n[[job]]\nname = \"load\"\nschedule = \"@every 1h\"\ntype = \"sql\"\nsql = \"SELECT 1\"\n",
+        )?;
+        let now = Local::now().naive_local();
+        assert_eq!(history.due_jobs(&file.jobs, now)?.len(), 1);
+
+        assert!(history.run(&file.jobs[0], |_| Ok("done".to_string()))?);
+        assert!(history.due_jobs(&file.jobs, now)?.is_empty());
+        assert!(!history.run(&file.jobs[0], |_| Err(anyhow!("boom")))?);
+
+        let runs = history.runs(Some("load"), 10)?;
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].status, "failed");
+        assert_eq!(runs[0].message, "boom");
+        assert!(history.runs(Some("other"), 10)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_sql_and_backup() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let database = temp
+            .path()
+            .join("app.duckdb")
+            .to_string_lossy()
+            .into_owned();
+        let no_flock = || -> Result<FlockManager> { Err(anyhow!("not needed")) };
+
+        let load = Job {
+            name: "load".to_string(),
+            schedule_spec: "@daily".to_string(),
+            schedule: Schedule::parse("@daily")?,
+            kind: JobKind::Sql {
+                database: Some(database.clone()),
+                script: None,
+                sql: Some("CREATE TABLE t AS SELECT range AS x FROM range(5)".to_string()),
+            },
+        };
+        execute_job(&load, &no_flock)?;
+
+        let backups = temp.path().join("backups");
+        let backup = Job {
+            kind: JobKind::Backup {
+                database,
+                output: backups.to_string_lossy().into_owned(),
+            },
+            ..load
+        };
+        let summary = execute_job(&backup, &no_flock)?;
+        assert!(summary.starts_with("exported to"));
+        let exported = fs::read_dir(&backups)?.next().unwrap()?.path();
+        assert!(exported.join("schema.sql").exists());
+        Ok(())
+    }
+}
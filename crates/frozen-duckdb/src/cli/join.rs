@@ -0,0 +1,445 @@
+//! # File Joins for Frozen DuckDB CLI
+//!
+//! This module joins two CSV, Parquet, or JSON files on key columns
+//! without writing SQL, and reports how well the keys matched.
+//!
+//! ## Join Types
+//!
+//! | `how` | Rows kept |
+//! |-------|-----------|
+//! | `inner` | Rows whose keys match on both sides |
+//! | `left` | Every left row, with right columns where matched |
+//! | `right` | Every right row, with left columns where matched |
+//! | `full` | Every row from either side |
+//! | `semi` | Left rows with a match (left columns only) |
+//! | `anti` | Left rows without a match (left columns only) |
+//!
+//! ## Output Columns
+//!
+//! Keys with the same name on both sides appear once. Other right columns
+//! whose names clash with a left column get a `_right` suffix.
+//!
+//! ## Key Diagnostics
+//!
+//! Every join reports, for each side, how many rows found a match and a
+//! sample of unmatched keys. Keys whose types differ between the files
+//! (say `BIGINT` in a CSV and `VARCHAR` in a Parquet file) are compared as
+//! text and listed in the report, since that is a common cause of
+//! unexpected misses.
+
+use super::dedupe::{copy_format, quote_identifier, read_function};
+use super::sql_path::path_literal;
+use anyhow::{anyhow, Context, Result};
+use duckdb::Connection;
+use tracing::info;
+
+/// Unmatched keys sampled from each side.
+const UNMATCHED_SAMPLE: usize = 5;
+
+/// Kind of join.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinHow {
+    /// Matching rows only
+    Inner,
+    /// All left rows
+    Left,
+    /// All right rows
+    Right,
+    /// All rows from both sides
+    Full,
+    /// Left rows with a match
+    Semi,
+    /// Left rows without a match
+    Anti,
+}
+
+impl JoinHow {
+    /// Parses a join type name.
+    pub fn parse(how: &str) -> Result<Self> {
+        match how.to_lowercase().as_str() {
+            "inner" => Ok(Self::Inner),
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            "full" | "outer" => Ok(Self::Full),
+            "semi" => Ok(Self::Semi),
+            "anti" => Ok(Self::Anti),
+            other => Err(anyhow!(
+                "Unknown join type: {} (use inner, left, right, full, semi, or anti)",
+                other
+            )),
+        }
+    }
+
+    fn keyword(self) -> &'static str {
+        match self {
+            Self::Inner => "INNER",
+            Self::Left => "LEFT",
+            Self::Right => "RIGHT",
+            Self::Full => "FULL OUTER",
+            Self::Semi => "SEMI",
+            Self::Anti => "ANTI",
+        }
+    }
+}
+
+/// A key column pair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoinKey {
+    /// Column in the left file
+    pub left: String,
+    /// Column in the right file
+    pub right: String,
+}
+
+impl JoinKey {
+    /// Parses `id` (same name on both sides) or `left_id=right_id`.
+    pub fn parse(key: &str) -> Result<Self> {
+        let (left, right) = key.split_once('=').unwrap_or((key, key));
+        let (left, right) = (left.trim(), right.trim());
+        if left.is_empty() || right.is_empty() {
+            return Err(anyhow!("Invalid join key: {}", key));
+        }
+        Ok(Self {
+            left: left.to_string(),
+            right: right.to_string(),
+        })
+    }
+}
+
+/// Match statistics of a join.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoinReport {
+    /// Rows in the left file
+    pub left_rows: usize,
+    /// Rows in the right file
+    pub right_rows: usize,
+    /// Left rows with at least one matching right row
+    pub left_matched: usize,
+    /// Right rows with at least one matching left row
+    pub right_matched: usize,
+    /// Rows written
+    pub output_rows: usize,
+    /// Unmatched left keys, up to a few
+    pub left_unmatched_sample: Vec<String>,
+    /// Unmatched right keys, up to a few
+    pub right_unmatched_sample: Vec<String>,
+    /// Keys compared as text because their types differ, as
+    /// `left (TYPE) = right (TYPE)`
+    pub cast_keys: Vec<String>,
+}
+
+impl JoinReport {
+    /// Left rows without a match.
+    pub fn left_unmatched(&self) -> usize {
+        self.left_rows - self.left_matched
+    }
+
+    /// Right rows without a match.
+    pub fn right_unmatched(&self) -> usize {
+        self.right_rows - self.right_matched
+    }
+}
+
+/// Joins `left` and `right` on `keys`, writing the result to `output`.
+///
+/// File formats are inferred from extensions (`.csv`, `.parquet`, `.json`).
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::join::{join_files, JoinHow, JoinKey};
+/// use frozen_duckdb::cli::DatasetManager;
+///
+/// let manager = DatasetManager::new()?;
+/// let keys = vec![JoinKey::parse("customer_id=id")?];
+/// let report = join_files(
+///     manager.connection(),
+///     "orders.csv",
+///     "customers.parquet",
+///     &keys,
+///     JoinHow::Left,
+///     "orders_with_customers.parquet",
+/// )?;
+/// println!("{} orders have no customer", report.left_unmatched());
+/// ```
+pub fn join_files(
+    conn: &Connection,
+    left: &str,
+    right: &str,
+    keys: &[JoinKey],
+    how: JoinHow,
+    output: &str,
+) -> Result<JoinReport> {
+    if keys.is_empty() {
+        return Err(anyhow!("At least one join key is required"));
+    }
+    let format = copy_format(output)?;
+
+    info!("🔗 Joining {} and {} ({})", left, right, how.keyword());
+    conn.execute_batch(&format!(
+        "CREATE OR REPLACE TEMP TABLE join_left AS SELECT * FROM {};
+         CREATE OR REPLACE TEMP TABLE join_right AS SELECT * FROM {};",
+        read_function(left)?,
+        read_function(right)?
+    ))
+    .context("Failed to read join inputs")?;
+
+    let left_columns = describe(conn, "join_left")?;
+    let right_columns = describe(conn, "join_right")?;
+    let column_type = |columns: &[(String, String)], name: &str, file: &str| {
+        columns
+            .iter()
+            .find(|(column, _)| column == name)
+            .map(|(_, data_type)| data_type.clone())
+            .ok_or_else(|| anyhow!("Key column {} not found in {}", name, file))
+    };
+
+    let mut conditions = Vec::new();
+    let mut key_expressions = Vec::new();
+    let mut cast_keys = Vec::new();
+    for key in keys {
+        let left_type = column_type(&left_columns, &key.left, left)?;
+        let right_type = column_type(&right_columns, &key.right, right)?;
+        let (mut l, mut r) = (
+            format!("l.{}", quote_identifier(&key.left)),
+            format!("r.{}", quote_identifier(&key.right)),
+        );
+        if left_type != right_type {
+            cast_keys.push(format!(
+                "{} ({}) = {} ({})",
+                key.left, left_type, key.right, right_type
+            ));
+            l = format!("CAST({} AS VARCHAR)", l);
+            r = format!("CAST({} AS VARCHAR)", r);
+        }
+        conditions.push(format!("{} = {}", l, r));
+        key_expressions.push((l, r));
+    }
+    let condition = conditions.join(" AND ");
+
+    let count = |sql: String| -> Result<usize> {
+        let n: i64 = conn.query_row(&sql, [], |row| row.get(0))?;
+        Ok(n as usize)
+    };
+    let sample = |side: &str, other: &str, expressions: Vec<&String>| -> Result<Vec<String>> {
+        let key = if expressions.len() == 1 {
+            format!("CAST({} AS VARCHAR)", expressions[0])
+        } else {
+            format!(
+                "concat_ws(', ', {})",
+                expressions
+                    .iter()
+                    .map(|e| format!("CAST({} AS VARCHAR)", e))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+        let mut stmt = conn.prepare(&format!(
+            "SELECT DISTINCT {} AS key FROM {} ANTI JOIN {} ON {} ORDER BY key LIMIT {}",
+            key, side, other, condition, UNMATCHED_SAMPLE
+        ))?;
+        let keys = stmt
+            .query_map([], |row| row.get::<_, Option<String>>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(keys
+            .into_iter()
+            .map(|k| k.unwrap_or_else(|| "NULL".to_string()))
+            .collect())
+    };
+
+    let report_without_output = JoinReport {
+        left_rows: count("SELECT COUNT(*) FROM join_left".to_string())?,
+        right_rows: count("SELECT COUNT(*) FROM join_right".to_string())?,
+        left_matched: count(format!(
+            "SELECT COUNT(*) FROM join_left l SEMI JOIN join_right r ON {}",
+            condition
+        ))?,
+        right_matched: count(format!(
+            "SELECT COUNT(*) FROM join_right r SEMI JOIN join_left l ON {}",
+            condition
+        ))?,
+        output_rows: 0,
+        left_unmatched_sample: sample(
+            "join_left l",
+            "join_right r",
+            key_expressions.iter().map(|(l, _)| l).collect(),
+        )?,
+        right_unmatched_sample: sample(
+            "join_right r",
+            "join_left l",
+            key_expressions.iter().map(|(_, r)| r).collect(),
+        )?,
+        cast_keys,
+    };
+
+    let projection = projection(&left_columns, &right_columns, keys, how);
+    let output_rows = conn.execute(
+        &format!(
+            "COPY (SELECT {} FROM join_left l {} JOIN join_right r ON {}) TO {} ({})",
+            projection,
+            how.keyword(),
+            condition,
+            path_literal(output),
+            format
+        ),
+        [],
+    )?;
+    conn.execute_batch("DROP TABLE join_left; DROP TABLE join_right;")?;
+
+    Ok(JoinReport {
+        output_rows,
+        ..report_without_output
+    })
+}
+
+/// Selects left columns, then right columns; same-named keys appear once
+/// and clashing right columns get a `_right` suffix.
+fn projection(
+    left_columns: &[(String, String)],
+    right_columns: &[(String, String)],
+    keys: &[JoinKey],
+    how: JoinHow,
+) -> String {
+    let shared_key = |name: &str| keys.iter().any(|k| k.left == name && k.right == name);
+    let mut columns: Vec<String> = left_columns
+        .iter()
+        .map(|(name, _)| {
+            let quoted = quote_identifier(name);
+            if shared_key(name) && matches!(how, JoinHow::Right | JoinHow::Full) {
+                format!("COALESCE(l.{0}, r.{0}) AS {0}", quoted)
+            } else {
+                format!("l.{}", quoted)
+            }
+        })
+        .collect();
+    if !matches!(how, JoinHow::Semi | JoinHow::Anti) {
+        for (name, _) in right_columns {
+            if shared_key(name) {
+                continue;
+            }
+            let quoted = quote_identifier(name);
+            if left_columns.iter().any(|(left, _)| left == name) {
+                columns.push(format!(
+                    "r.{} AS {}",
+                    quoted,
+                    quote_identifier(&format!("{}_right", name))
+                ));
+            } else {
+                columns.push(format!("r.{}", quoted));
+            }
+        }
+    }
+    columns.join(", ")
+}
+
+fn describe(conn: &Connection, table: &str) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(&format!("DESCRIBE {}", table))?;
+    let columns = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    fn write_inputs(dir: &Path, conn: &Connection) -> (String, String) {
+        let orders = dir.join("orders.csv");
+        fs::write(
+            &orders,
+            "order_id,customer_id,total\n1,10,5\n2,11,7\n3,99,1\n",
+        )
+        .unwrap();
+        let customers = dir.join("customers.parquet");
+        conn.execute_batch(&format!(
+            "COPY (SELECT * FROM (VALUES ('10', 'ada', 3), ('11', 'alan', 4), ('12', 'grace', 5)) t(id, name, total))
+             TO '{}' (FORMAT PARQUET)",
+            customers.display()
+        ))
+        .unwrap();
+        (
+            orders.to_str().unwrap().to_string(),
+            customers.to_str().unwrap().to_string(),
+        )
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            JoinKey::parse("customer_id=id").unwrap(),
+            JoinKey {
+                left: "customer_id".to_string(),
+                right: "id".to_string()
+            }
+        );
+        assert_eq!(JoinKey::parse("id").unwrap().right, "id");
+        assert!(JoinKey::parse("=id").is_err());
+        assert_eq!(JoinHow::parse("OUTER").unwrap(), JoinHow::Full);
+        assert!(JoinHow::parse("cross").is_err());
+    }
+
+    #[test]
+    fn test_left_join_with_diagnostics() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let conn = Connection::open_in_memory()?;
+        let (orders, customers) = write_inputs(temp.path(), &conn);
+        let output = temp.path().join("joined.csv");
+
+        let keys = [JoinKey::parse("customer_id=id")?];
+        let report = join_files(
+            &conn,
+            &orders,
+            &customers,
+            &keys,
+            JoinHow::Left,
+            output.to_str().unwrap(),
+        )?;
+
+        assert_eq!((report.left_rows, report.left_matched), (3, 2));
+        assert_eq!((report.right_rows, report.right_unmatched()), (3, 1));
+        assert_eq!(report.left_unmatched_sample, ["99"]);
+        assert_eq!(report.right_unmatched_sample, ["12"]);
+        assert_eq!(report.cast_keys, ["customer_id (BIGINT) = id (VARCHAR)"]);
+        assert_eq!(report.output_rows, 3);
+
+        let joined = fs::read_to_string(&output)?;
+        assert!(joined.starts_with("order_id,customer_id,total,id,name,total_right\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_anti_join_keeps_left_columns() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let conn = Connection::open_in_memory()?;
+        let (orders, customers) = write_inputs(temp.path(), &conn);
+        let output = temp.path().join("orphans.csv");
+
+        let keys = [JoinKey::parse("customer_id=id")?];
+        let report = join_files(
+            &conn,
+            &orders,
+            &customers,
+            &keys,
+            JoinHow::Anti,
+            output.to_str().unwrap(),
+        )?;
+        assert_eq!(report.output_rows, 1);
+        assert_eq!(
+            fs::read_to_string(&output)?,
+            "order_id,customer_id,total\n3,99,1\n"
+        );
+        assert!(join_files(
+            &conn,
+            &orders,
+            &customers,
+            &[JoinKey::parse("nope")?],
+            JoinHow::Inner,
+            output.to_str().unwrap()
+        )
+        .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,265 @@
+//! # Delta Lake and Iceberg Tables
+//!
+//! Lakehouse tables are directories of Parquet files plus a transaction log
+//! that says which files make up the current version. DuckDB reads them
+//! through its `delta` and `iceberg` extensions; this module finds table
+//! references and turns them into the extensions' scan functions.
+//!
+//! | Format | Reference | Read with | Detected from |
+//! |--------|-----------|-----------|---------------|
+//! | Delta Lake | `delta://<location>` | `delta_scan` | A `_delta_log/` directory |
+//! | Iceberg | `iceberg://<location>` | `iceberg_scan` | A `metadata/` directory of `*.metadata.json` files |
+//!
+//! The location is a local directory or a remote URL such as
+//! `s3://bucket/events`; remote tables also load `httpfs`, and S3
+//! credentials come from DuckDB secrets or the usual `AWS_*` environment
+//! variables.
+//!
+//! In SQL, a quoted reference is rewritten into a scan, so both of these
+//! read the same table:
+//!
+//! ```sql
+//! SELECT count(*) FROM 'delta://s3://lake/events';
+//! SELECT count(*) FROM delta_scan('s3://lake/events');
+//! ```
+//!
+//! Any quoted string starting with `delta://` or `iceberg://` is taken as
+//! a table reference. The tables are read-only: they can be converted
+//! from, but not to.
+//!
+//! The extensions are loaded from the frozen binary if it was built with
+//! them (add `delta` or `iceberg` to `BUILD_EXTENSIONS`), and otherwise
+//! installed by DuckDB on first use, which needs network access.
+
+use super::sql_path::path_literal;
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+/// Read-only table formats accepted as `convert`, `schema`, and `query`
+/// input.
+pub const TABLE_FORMATS: [&str; 2] = ["delta", "iceberg"];
+
+/// A lakehouse table format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TableFormat {
+    /// Delta Lake, read with the `delta` extension
+    Delta,
+    /// Apache Iceberg, read with the `iceberg` extension
+    Iceberg,
+}
+
+impl TableFormat {
+    /// Parses `delta` or `iceberg`.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "delta" => Ok(Self::Delta),
+            "iceberg" => Ok(Self::Iceberg),
+            other => Err(anyhow::anyhow!(
+                "Unknown table format: {} (use delta or iceberg)",
+                other
+            )),
+        }
+    }
+
+    /// Returns the format name, which is also its DuckDB extension.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Delta => "delta",
+            Self::Iceberg => "iceberg",
+        }
+    }
+
+    /// URL scheme of table references, e.g. `delta://`.
+    pub fn scheme(&self) -> &'static str {
+        match self {
+            Self::Delta => "delta://",
+            Self::Iceberg => "iceberg://",
+        }
+    }
+
+    /// Table function reading the table at `location`.
+    pub fn scan(&self, location: &str) -> String {
+        let location = path_literal(location);
+        match self {
+            Self::Delta => format!("delta_scan({})", location),
+            // Tables copied or moved since they were written keep their old
+            // absolute paths in the metadata
+            Self::Iceberg => format!("iceberg_scan({}, allow_moved_paths = true)", location),
+        }
+    }
+}
+
+/// Splits a `delta://` or `iceberg://` reference into its format and
+/// location.
+pub fn parse_table_url(reference: &str) -> Option<(TableFormat, &str)> {
+    [TableFormat::Delta, TableFormat::Iceberg]
+        .into_iter()
+        .find_map(|format| {
+            reference
+                .strip_prefix(format.scheme())
+                .map(|location| (format, location))
+        })
+}
+
+/// The table format of `input`: from its `delta://` or `iceberg://`
+/// scheme, or from the log directory of a local table.
+pub fn table_format(input: &str) -> Option<TableFormat> {
+    if let Some((format, _)) = parse_table_url(input) {
+        return Some(format);
+    }
+    let path = Path::new(input);
+    if path.join("_delta_log").is_dir() {
+        return Some(TableFormat::Delta);
+    }
+    let has_iceberg_metadata = fs::read_dir(path.join("metadata")).is_ok_and(|entries| {
+        entries.flatten().any(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .ends_with(".metadata.json")
+        })
+    });
+    has_iceberg_metadata.then_some(TableFormat::Iceberg)
+}
+
+/// Whether `location` is read over the network, so `httpfs` is needed.
+pub fn is_remote(location: &str) -> bool {
+    [
+        "s3://", "s3a://", "gs://", "gcs://", "r2://", "http://", "https://",
+    ]
+    .iter()
+    .any(|scheme| location.starts_with(scheme))
+}
+
+/// Rewrites quoted `'delta://...'` and `'iceberg://...'` references in
+/// `sql` into scans, and returns the rewritten SQL with the referenced
+/// formats and locations, in order.
+pub fn rewrite_table_urls(sql: &str) -> (String, Vec<(TableFormat, String)>) {
+    let mut rewritten = String::with_capacity(sql.len());
+    let mut tables = Vec::new();
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\'' {
+            rewritten.push(c);
+            continue;
+        }
+        // Read the literal, with '' standing for a quote
+        let mut literal = String::new();
+        let mut closed = false;
+        while let Some(c) = chars.next() {
+            match c {
+                '\'' if chars.peek() == Some(&'\'') => {
+                    chars.next();
+                    literal.push('\'');
+                }
+                '\'' => {
+                    closed = true;
+                    break;
+                }
+                c => literal.push(c),
+            }
+        }
+        match parse_table_url(&literal).filter(|_| closed) {
+            Some((format, location)) => {
+                rewritten.push_str(&format.scan(location));
+                tables.push((format, location.to_string()));
+            }
+            None => {
+                rewritten.push('\'');
+                rewritten.push_str(&literal.replace('\'', "''"));
+                if closed {
+                    rewritten.push('\'');
+                }
+            }
+        }
+    }
+    (rewritten, tables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_table_url() {
+        assert_eq!(
+            parse_table_url("delta://s3://lake/events"),
+            Some((TableFormat::Delta, "s3://lake/events"))
+        );
+        assert_eq!(
+            parse_table_url("iceberg:///data/orders"),
+            Some((TableFormat::Iceberg, "/data/orders"))
+        );
+        assert_eq!(parse_table_url("events.parquet"), None);
+        assert_eq!(TableFormat::parse("delta").unwrap(), TableFormat::Delta);
+        assert!(TableFormat::parse("hudi").is_err());
+    }
+
+    #[test]
+    fn test_scan() {
+        assert_eq!(
+            TableFormat::Delta.scan("s3://lake/events"),
+            "delta_scan('s3://lake/events')"
+        );
+        assert_eq!(
+            TableFormat::Iceberg.scan("/data/o'brien"),
+            "iceberg_scan('/data/o''brien', allow_moved_paths = true)"
+        );
+        assert_eq!(
+            TableFormat::Delta.scan(r"\\?\C:\lake\events"),
+            r"delta_scan('C:\lake\events')"
+        );
+        assert!(is_remote("s3://lake/events"));
+        assert!(!is_remote("/data/events"));
+    }
+
+    #[test]
+    fn test_rewrite_table_urls() {
+        let (sql, tables) = rewrite_table_urls(
+            "SELECT e.*, 'it''s' AS note FROM 'delta://s3://lake/events' e \
+             JOIN 'iceberg://warehouse/users' u USING (id)",
+        );
+        assert_eq!(
+            sql,
+            "SELECT e.*, 'it''s' AS note FROM delta_scan('s3://lake/events') e \
+             JOIN iceberg_scan('warehouse/users', allow_moved_paths = true) u USING (id)"
+        );
+        assert_eq!(tables.len(), 2);
+        assert_eq!(
+            tables[1],
+            (TableFormat::Iceberg, "warehouse/users".to_string())
+        );
+
+        for plain in [
+            "SELECT * FROM 'events.parquet'",
+            "SELECT 'delta://unterminated",
+        ] {
+            assert_eq!(rewrite_table_urls(plain), (plain.to_string(), Vec::new()));
+        }
+    }
+
+    #[test]
+    fn test_table_format_from_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let delta = dir.path().join("events");
+        fs::create_dir_all(delta.join("_delta_log")).unwrap();
+        let iceberg = dir.path().join("orders");
+        fs::create_dir_all(iceberg.join("metadata")).unwrap();
+        fs::write(iceberg.join("metadata/v1.metadata.json"), "{}").unwrap();
+
+        assert_eq!(
+            table_format(delta.to_str().unwrap()),
+            Some(TableFormat::Delta)
+        );
+        assert_eq!(
+            table_format(iceberg.to_str().unwrap()),
+            Some(TableFormat::Iceberg)
+        );
+        assert_eq!(table_format(dir.path().to_str().unwrap()), None);
+        assert_eq!(
+            table_format("iceberg://s3://lake/t"),
+            Some(TableFormat::Iceberg)
+        );
+    }
+}
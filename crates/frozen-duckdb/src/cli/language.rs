@@ -0,0 +1,665 @@
+//! # Language Detection and Translation for Frozen DuckDB CLI
+//!
+//! This module adds language columns to a dataset: `detect-language` writes
+//! the ISO 639-1 code of each row's text, and `translate` writes a
+//! translation into a target language. Both send rows to the text model in
+//! set-based Flock calls (see [`FlockManager::complete_rows`]).
+//!
+//! ## Detection Fallback
+//!
+//! Detection also works without a model. [`detect_heuristic`] recognizes
+//! non-Latin scripts by code point range and Latin-script languages by
+//! their most frequent function words. It is used for every row when no
+//! model is given, and for rows whose model reply is not a language code.
+//!
+//! | Output column       | Content                                  |
+//! |---------------------|------------------------------------------|
+//! | `language`          | ISO 639-1 code, NULL if undetermined     |
+//! | `language_source`   | `llm` or `heuristic`                     |
+//! | `<column>_<code>`   | Translation (`translate`)                |
+
+use super::dedupe::{copy_format, quote_identifier, read_function};
+use super::flock_manager::FlockManager;
+use super::sql_path::path_literal;
+use anyhow::{anyhow, Context, Result};
+use duckdb::types::Value;
+use duckdb::{params_from_iter, Connection};
+use tracing::info;
+
+/// Languages known by name, as (ISO 639-1 code, English name).
+pub const LANGUAGES: [(&str, &str); 24] = [
+    ("ar", "Arabic"),
+    ("cs", "Czech"),
+    ("da", "Danish"),
+    ("de", "German"),
+    ("el", "Greek"),
+    ("en", "English"),
+    ("es", "Spanish"),
+    ("fi", "Finnish"),
+    ("fr", "French"),
+    ("he", "Hebrew"),
+    ("hi", "Hindi"),
+    ("id", "Indonesian"),
+    ("it", "Italian"),
+    ("ja", "Japanese"),
+    ("ko", "Korean"),
+    ("nl", "Dutch"),
+    ("pl", "Polish"),
+    ("pt", "Portuguese"),
+    ("ru", "Russian"),
+    ("sv", "Swedish"),
+    ("th", "Thai"),
+    ("tr", "Turkish"),
+    ("uk", "Ukrainian"),
+    ("zh", "Chinese"),
+];
+
+/// Frequent function words of Latin-script languages used by [`detect_heuristic`].
+const STOPWORDS: [(&str, &[&str]); 8] = [
+    (
+        "en",
+        &[
+            "the", "and", "is", "of", "to", "in", "it", "that", "was", "for", "with", "this",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "le", "la", "les", "et", "est", "des", "une", "un", "du", "que", "pas", "pour",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "der", "die", "das", "und", "ist", "nicht", "ein", "eine", "ich", "mit", "zu", "auf",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "el", "los", "las", "y", "es", "que", "por", "una", "con", "para", "muy", "pero",
+        ],
+    ),
+    (
+        "it",
+        &[
+            "il", "che", "di", "è", "non", "per", "una", "sono", "gli", "della", "molto", "ma",
+        ],
+    ),
+    (
+        "pt",
+        &[
+            "o", "os", "que", "não", "uma", "com", "para", "é", "muito", "mas", "do", "da",
+        ],
+    ),
+    (
+        "nl",
+        &[
+            "de", "het", "een", "en", "is", "niet", "van", "dat", "ik", "met", "voor", "zijn",
+        ],
+    ),
+    (
+        "sv",
+        &[
+            "och", "att", "det", "är", "som", "en", "på", "inte", "för", "med", "jag", "av",
+        ],
+    ),
+];
+
+/// A target language for [`translate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Language {
+    /// ISO 639-1 code, used in the output column name
+    pub code: String,
+    /// Name given to the model
+    pub name: String,
+}
+
+impl Language {
+    /// Parses an ISO 639-1 code or English language name.
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        LANGUAGES
+            .iter()
+            .find(|(code, name)| {
+                code.eq_ignore_ascii_case(value) || name.eq_ignore_ascii_case(value)
+            })
+            .map(|(code, name)| Self {
+                code: code.to_string(),
+                name: name.to_string(),
+            })
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown language: {} (expected one of {})",
+                    value,
+                    LANGUAGES
+                        .iter()
+                        .map(|(code, _)| *code)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+/// Answers one prompt per text for detection and translation.
+pub trait LanguageModel {
+    /// Answers `prompt` for each text, in order.
+    fn complete(&self, prompt: &str, texts: &[String]) -> Result<Vec<String>>;
+}
+
+/// [`FlockManager`] bound to a model, usable as a [`LanguageModel`].
+pub struct FlockLanguageModel<'a> {
+    /// Flock manager used for completions
+    pub manager: &'a FlockManager,
+    /// Model alias configured during flock-setup
+    pub model: String,
+}
+
+impl LanguageModel for FlockLanguageModel<'_> {
+    fn complete(&self, prompt: &str, texts: &[String]) -> Result<Vec<String>> {
+        Ok(self.manager.complete_rows(prompt, texts, &self.model)?)
+    }
+}
+
+/// Options for [`detect_languages`] and [`translate`].
+#[derive(Debug, Clone)]
+pub struct LanguageOptions {
+    /// Text column to read
+    pub column: String,
+    /// Rows sent to the model per call
+    pub batch_size: usize,
+    /// Name of the translation column (default `<column>_<code>`)
+    pub output_column: Option<String>,
+}
+
+impl Default for LanguageOptions {
+    fn default() -> Self {
+        Self {
+            column: "text".to_string(),
+            batch_size: 256,
+            output_column: None,
+        }
+    }
+}
+
+/// Summary of a detection or translation run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LanguageReport {
+    /// Rows written
+    pub rows: usize,
+    /// Rows with a non-empty text
+    pub processed: usize,
+    /// Rows answered by the heuristic instead of the model (detection only)
+    pub fallbacks: usize,
+}
+
+/// Detects the language of `text` without a model.
+///
+/// Returns the ISO 639-1 code, or `None` when the text has no letters or
+/// no known function words.
+pub fn detect_heuristic(text: &str) -> Option<&'static str> {
+    // Letters per script; index 9 is Latin and anything else unrecognized
+    let mut counts = [0usize; 10];
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        let script = match c as u32 {
+            0x3040..=0x30FF => 0, // Hiragana, Katakana
+            0xAC00..=0xD7AF | 0x1100..=0x11FF => 1,
+            0x4E00..=0x9FFF => 2,
+            0x0400..=0x04FF => 3,
+            0x0370..=0x03FF => 4,
+            0x0600..=0x06FF => 5,
+            0x0590..=0x05FF => 6,
+            0x0900..=0x097F => 7,
+            0x0E00..=0x0E7F => 8,
+            _ => 9,
+        };
+        counts[script] += 1;
+    }
+
+    let letters: usize = counts.iter().sum();
+    if letters == 0 {
+        return None;
+    }
+    if counts[9] * 2 < letters {
+        // Kana marks Japanese even when most characters are Han
+        if counts[0] > 0 {
+            return Some("ja");
+        }
+        let script = (1..9).max_by_key(|&i| counts[i])?;
+        return Some(match script {
+            1 => "ko",
+            2 => "zh",
+            3 if text.chars().any(|c| "іїєґ".contains(c)) => "uk",
+            3 => "ru",
+            4 => "el",
+            5 => "ar",
+            6 => "he",
+            7 => "hi",
+            _ => "th",
+        });
+    }
+
+    let lowercase = text.to_lowercase();
+    let words: Vec<&str> = lowercase
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .collect();
+    STOPWORDS
+        .iter()
+        .map(|(code, stopwords)| {
+            let hits = words.iter().filter(|word| stopwords.contains(word)).count();
+            (*code, hits)
+        })
+        .filter(|(_, hits)| *hits > 0)
+        // Earlier entries win ties, so English is preferred for short texts
+        .fold(None, |best: Option<(&str, usize)>, candidate| match best {
+            Some(best) if best.1 >= candidate.1 => Some(best),
+            _ => Some(candidate),
+        })
+        .map(|(code, _)| code)
+}
+
+/// Reads a language code from a model reply such as `fr`, `"FR".` or `French`.
+pub fn parse_language_reply(reply: &str) -> Option<String> {
+    let reply = reply
+        .trim()
+        .trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase();
+    if reply.len() == 2 && reply.chars().all(|c| c.is_ascii_lowercase()) {
+        return Some(reply);
+    }
+    Language::parse(&reply).ok().map(|language| language.code)
+}
+
+/// Prompt used for language detection.
+fn detection_prompt() -> String {
+    "Identify the language of the text. Reply with only its two-letter ISO 639-1 code.".to_string()
+}
+
+/// Prompt used for translation into `language`.
+fn translation_prompt(language: &Language) -> String {
+    format!(
+        "Translate the text into {}. Reply with only the translation, keeping the \
+         original formatting.",
+        language.name
+    )
+}
+
+/// Writes the language of each row of `input` to `output`.
+///
+/// With a `model`, rows are detected by the model and fall back to
+/// [`detect_heuristic`] when its reply is not a language code; without
+/// one, only the heuristic is used.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::language::{detect_languages, LanguageOptions};
+/// use frozen_duckdb::cli::DatasetManager;
+///
+/// let manager = DatasetManager::new()?;
+/// let report = detect_languages(
+///     manager.connection(),
+///     "reviews.parquet",
+///     "reviews_lang.parquet",
+///     None,
+///     &LanguageOptions::default(),
+/// )?;
+/// ```
+pub fn detect_languages(
+    conn: &Connection,
+    input: &str,
+    output: &str,
+    model: Option<&dyn LanguageModel>,
+    options: &LanguageOptions,
+) -> Result<LanguageReport> {
+    let prompt = detection_prompt();
+    let mut fallbacks = 0;
+    let (rows, processed) = annotate(
+        conn,
+        input,
+        output,
+        &["language", "language_source"],
+        options,
+        |texts| {
+            let replies = match model {
+                Some(model) => model.complete(&prompt, texts)?,
+                None => Vec::new(),
+            };
+            Ok(texts
+                .iter()
+                .enumerate()
+                .map(|(i, text)| {
+                    match replies.get(i).and_then(|reply| parse_language_reply(reply)) {
+                        Some(code) => vec![Some(code), Some("llm".to_string())],
+                        None => {
+                            fallbacks += 1;
+                            vec![
+                                detect_heuristic(text).map(str::to_string),
+                                Some("heuristic".to_string()),
+                            ]
+                        }
+                    }
+                })
+                .collect())
+        },
+    )?;
+
+    Ok(LanguageReport {
+        rows,
+        processed,
+        fallbacks,
+    })
+}
+
+/// Writes a translation of each row of `input` into `language` to `output`.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::language::{translate, FlockLanguageModel, Language, LanguageOptions};
+/// use frozen_duckdb::cli::{DatasetManager, FlockManager};
+///
+/// let manager = DatasetManager::new()?;
+/// let flock = FlockManager::new()?;
+/// let model = FlockLanguageModel { manager: &flock, model: "text_generator".to_string() };
+///
+/// translate(
+///     manager.connection(),
+///     "reviews.parquet",
+///     "reviews_fr.parquet",
+///     &Language::parse("fr")?,
+///     &model,
+///     &LanguageOptions::default(),
+/// )?;
+/// ```
+pub fn translate(
+    conn: &Connection,
+    input: &str,
+    output: &str,
+    language: &Language,
+    model: &dyn LanguageModel,
+    options: &LanguageOptions,
+) -> Result<LanguageReport> {
+    let column = options
+        .output_column
+        .clone()
+        .unwrap_or_else(|| format!("{}_{}", options.column, language.code));
+    let prompt = translation_prompt(language);
+    let (rows, processed) = annotate(conn, input, output, &[&column], options, |texts| {
+        Ok(model
+            .complete(&prompt, texts)?
+            .into_iter()
+            .map(|translation| vec![Some(translation.trim().to_string())])
+            .collect())
+    })?;
+
+    Ok(LanguageReport {
+        rows,
+        processed,
+        fallbacks: 0,
+    })
+}
+
+/// Copies `input` to `output` with `columns` appended, filled by `annotate_batch`
+/// from the non-empty texts of the input column.
+///
+/// Returns the number of rows written and the number of texts annotated.
+fn annotate<F>(
+    conn: &Connection,
+    input: &str,
+    output: &str,
+    columns: &[&str],
+    options: &LanguageOptions,
+    mut annotate_batch: F,
+) -> Result<(usize, usize)>
+where
+    F: FnMut(&[String]) -> Result<Vec<Vec<Option<String>>>>,
+{
+    let format = copy_format(output)?;
+    let result_columns = columns
+        .iter()
+        .map(|column| format!("{} VARCHAR", quote_identifier(column)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    conn.execute_batch(&format!(
+        "CREATE OR REPLACE TEMP TABLE language_input AS
+         SELECT row_number() OVER () AS __row, * FROM {};
+         CREATE OR REPLACE TEMP TABLE language_results (__row BIGINT, {});",
+        read_function(input)?,
+        result_columns
+    ))
+    .context("Failed to read input")?;
+
+    let mut stmt = conn.prepare("SELECT column_name FROM (DESCRIBE language_input)")?;
+    let existing = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    if !existing.contains(&options.column) {
+        return Err(anyhow!("Column {} not found in {}", options.column, input));
+    }
+    if let Some(column) = columns
+        .iter()
+        .find(|column| existing.iter().any(|e| e.eq_ignore_ascii_case(column)))
+    {
+        return Err(anyhow!("Input already has a column named {}", column));
+    }
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT __row, CAST({0} AS VARCHAR) FROM language_input
+         WHERE nullif(trim(CAST({0} AS VARCHAR)), '') IS NOT NULL ORDER BY __row",
+        quote_identifier(&options.column)
+    ))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let total: i64 = conn.query_row("SELECT count(*) FROM language_input", [], |row| row.get(0))?;
+
+    let placeholders = (1..=columns.len() + 1)
+        .map(|i| format!("${}", i))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut done = 0;
+    for batch in rows.chunks(options.batch_size.max(1)) {
+        let texts: Vec<String> = batch.iter().map(|(_, text)| text.clone()).collect();
+        let values = annotate_batch(&texts)?;
+        if values.len() != texts.len() {
+            return Err(anyhow!(
+                "Expected {} responses but the model returned {}",
+                texts.len(),
+                values.len()
+            ));
+        }
+
+        let tx = conn.unchecked_transaction()?;
+        let mut insert = tx.prepare(&format!(
+            "INSERT INTO language_results VALUES ({})",
+            placeholders
+        ))?;
+        for ((row, _), values) in batch.iter().zip(values) {
+            let values = values
+                .into_iter()
+                .map(|value| value.map_or(Value::Null, Value::Text));
+            insert.execute(params_from_iter(
+                std::iter::once(Value::BigInt(*row)).chain(values),
+            ))?;
+        }
+        drop(insert);
+        tx.commit()?;
+
+        done += batch.len();
+        info!("🌐 Processed {}/{} rows", done, rows.len());
+    }
+
+    let selected = columns
+        .iter()
+        .map(|column| format!("r.{}", quote_identifier(column)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    conn.execute_batch(&format!(
+        "COPY (
+             SELECT i.* EXCLUDE (__row), {}
+             FROM language_input i LEFT JOIN language_results r USING (__row)
+             ORDER BY i.__row
+         ) TO {} ({});
+         DROP TABLE language_input;
+         DROP TABLE language_results;",
+        selected,
+        path_literal(output),
+        format
+    ))
+    .with_context(|| format!("Failed to write {}", output))?;
+
+    Ok((total as usize, rows.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Replies "fr" to detection and upper-cases translations.
+    struct UpperModel;
+
+    impl LanguageModel for UpperModel {
+        fn complete(&self, prompt: &str, texts: &[String]) -> Result<Vec<String>> {
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    if prompt.starts_with("Identify") {
+                        if text.contains("chat") {
+                            " FR.".to_string()
+                        } else {
+                            "I am not sure".to_string()
+                        }
+                    } else {
+                        format!(" {} ", text.to_uppercase())
+                    }
+                })
+                .collect())
+        }
+    }
+
+    fn write_input(dir: &TempDir) -> Result<String> {
+        let input = dir.path().join("texts.csv");
+        std::fs::write(
+            &input,
+            "id,text\n1,le chat est sur la table\n2,the dog and the cat\n3,\n4,Привет мир\n",
+        )?;
+        Ok(input.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn test_detect_heuristic() {
+        assert_eq!(detect_heuristic("The weather is nice and warm"), Some("en"));
+        assert_eq!(detect_heuristic("Le chat est sur la table"), Some("fr"));
+        assert_eq!(detect_heuristic("Der Hund und die Katze"), Some("de"));
+        assert_eq!(detect_heuristic("El perro es muy grande pero"), Some("es"));
+        assert_eq!(detect_heuristic("Привет, как дела?"), Some("ru"));
+        assert_eq!(detect_heuristic("Привіт, як справи? Їжак"), Some("uk"));
+        assert_eq!(detect_heuristic("東京は日本の首都です"), Some("ja"));
+        assert_eq!(detect_heuristic("北京是中国的首都"), Some("zh"));
+        assert_eq!(detect_heuristic("안녕하세요"), Some("ko"));
+        assert_eq!(detect_heuristic("Καλημέρα"), Some("el"));
+        assert_eq!(detect_heuristic("12345 !!"), None);
+        assert_eq!(detect_heuristic("xyzzy"), None);
+    }
+
+    #[test]
+    fn test_language_parse_and_replies() -> Result<()> {
+        assert_eq!(Language::parse("FR")?.name, "French");
+        assert_eq!(Language::parse("german")?.code, "de");
+        assert!(Language::parse("klingon").is_err());
+
+        assert_eq!(parse_language_reply("\"FR\"."), Some("fr".to_string()));
+        assert_eq!(parse_language_reply("Spanish"), Some("es".to_string()));
+        assert_eq!(parse_language_reply("I think it is French"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_languages_with_fallback() -> Result<()> {
+        let dir = TempDir::new()?;
+        let input = write_input(&dir)?;
+        let output = dir.path().join("detected.csv");
+        let conn = Connection::open_in_memory()?;
+
+        let report = detect_languages(
+            &conn,
+            &input,
+            output.to_str().unwrap(),
+            Some(&UpperModel),
+            &LanguageOptions::default(),
+        )?;
+        assert_eq!(
+            report,
+            LanguageReport {
+                rows: 4,
+                processed: 3,
+                fallbacks: 2
+            }
+        );
+
+        let detected: Vec<(Option<String>, Option<String>)> = conn
+            .prepare(&format!(
+                "SELECT language, language_source FROM read_csv('{}', header=true) ORDER BY id",
+                output.display()
+            ))?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<_, _>>()?;
+        assert_eq!(detected[0], (Some("fr".into()), Some("llm".into())));
+        assert_eq!(detected[1], (Some("en".into()), Some("heuristic".into())));
+        assert_eq!(detected[2], (None, None));
+        assert_eq!(detected[3], (Some("ru".into()), Some("heuristic".into())));
+        Ok(())
+    }
+
+    #[test]
+    fn test_translate_adds_column() -> Result<()> {
+        let dir = TempDir::new()?;
+        let input = write_input(&dir)?;
+        let output = dir.path().join("translated.parquet");
+        let conn = Connection::open_in_memory()?;
+
+        let report = translate(
+            &conn,
+            &input,
+            output.to_str().unwrap(),
+            &Language::parse("de")?,
+            &UpperModel,
+            &LanguageOptions {
+                batch_size: 2,
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(report.processed, 3);
+
+        let translated: Option<String> = conn.query_row(
+            &format!(
+                "SELECT text_de FROM read_parquet('{}') WHERE id = 2",
+                output.display()
+            ),
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(translated.as_deref(), Some("THE DOG AND THE CAT"));
+
+        let err = translate(
+            &conn,
+            &input,
+            output.to_str().unwrap(),
+            &Language::parse("de")?,
+            &UpperModel,
+            &LanguageOptions {
+                output_column: Some("id".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("already has a column"));
+        Ok(())
+    }
+}
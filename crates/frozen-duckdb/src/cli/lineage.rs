@@ -0,0 +1,354 @@
+//! # Dataset Lineage
+//!
+//! A derived file says nothing about how it was made. When `convert`,
+//! `join`, `dedupe`, or `mask` writes a file, the command records its
+//! provenance in the [dataset catalog](super::catalog), keyed by the
+//! output's absolute path:
+//!
+//! | Field | Example |
+//! |-------|---------|
+//! | `command` | `dedupe` |
+//! | `inputs` | `["/data/customers.csv"]` |
+//! | `parameters` | `{"key_columns": ["email"], "semantic": false}` |
+//! | `git_sha` | Commit of the working directory's repository, if any |
+//! | `created_at` | `2026-10-18 09:30:12` |
+//!
+//! With `--lineage-metadata`, Parquet outputs also carry the record as
+//! JSON in their key-value metadata under [`LINEAGE_METADATA_KEY`], so it
+//! travels with the file to machines without the catalog.
+//!
+//! `frozen-duckdb lineage show <file>` follows the inputs back to files
+//! with no recorded lineage:
+//!
+//! ```text
+//! /data/customers_masked.parquet ← mask (2026-10-18 09:31:40, git 1a2b3c4)
+//!   parameters: {"config":"mask.toml"}
+//!   └─ /data/customers_deduped.parquet ← dedupe (2026-10-18 09:30:12, git 1a2b3c4)
+//!        parameters: {"key_columns":["email"],"semantic":false}
+//!        └─ /data/customers.csv (source)
+//! ```
+//!
+//! Secrets such as the masking salt are never recorded.
+
+use super::catalog::DatasetCatalog;
+use super::sql_path::path_literal;
+use anyhow::{Context, Result};
+use duckdb::Connection;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Parquet key-value metadata key holding the lineage record
+pub const LINEAGE_METADATA_KEY: &str = "frozen_duckdb.lineage";
+
+/// How a file was produced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lineage {
+    /// Absolute path of the produced file
+    pub output: String,
+    /// CLI command that produced it, e.g. `convert`
+    pub command: String,
+    /// Absolute paths of the files it was produced from
+    pub inputs: Vec<String>,
+    /// Command options that affect the output
+    pub parameters: Value,
+    /// `HEAD` of the git repository the command ran in, if any
+    pub git_sha: Option<String>,
+    /// When the file was produced (`YYYY-MM-DD HH:MM:SS`); `None` until
+    /// the record is stored
+    pub created_at: Option<String>,
+}
+
+impl Lineage {
+    /// Describes `output` as produced by `command` from `inputs`, with the
+    /// git commit of the current directory.
+    pub fn new(command: &str, output: &str, inputs: &[&str], parameters: Value) -> Self {
+        Self {
+            output: absolute(output),
+            command: command.to_string(),
+            inputs: inputs.iter().map(|input| absolute(input)).collect(),
+            parameters,
+            git_sha: git_sha(),
+            created_at: None,
+        }
+    }
+
+    /// Serializes the record, as stored in Parquet metadata.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "output": self.output,
+            "command": self.command,
+            "inputs": self.inputs,
+            "parameters": self.parameters,
+            "git_sha": self.git_sha,
+            "created_at": self.created_at,
+        })
+    }
+
+    /// Parses a record written by [`Lineage::to_json`].
+    pub fn from_json(value: &Value) -> Result<Self> {
+        let text = |key: &str| value[key].as_str().map(str::to_string);
+        Ok(Self {
+            output: text("output").context("Lineage record has no output")?,
+            command: text("command").context("Lineage record has no command")?,
+            inputs: value["inputs"]
+                .as_array()
+                .context("Lineage record has no inputs")?
+                .iter()
+                .filter_map(|input| input.as_str().map(str::to_string))
+                .collect(),
+            parameters: value["parameters"].clone(),
+            git_sha: text("git_sha"),
+            created_at: text("created_at"),
+        })
+    }
+
+    /// One-line summary, e.g. `dedupe (2026-10-18 09:30:12, git 1a2b3c4)`.
+    pub fn format_summary(&self) -> String {
+        let mut details = Vec::new();
+        if let Some(created_at) = &self.created_at {
+            details.push(created_at.clone());
+        }
+        if let Some(sha) = &self.git_sha {
+            details.push(format!("git {}", &sha[..sha.len().min(7)]));
+        }
+        if details.is_empty() {
+            self.command.clone()
+        } else {
+            format!("{} ({})", self.command, details.join(", "))
+        }
+    }
+}
+
+/// Returns `path` made absolute, without resolving symlinks, so files that
+/// don't exist yet (or anymore) keep a stable key. URLs such as
+/// `s3://...` or `delta://...` are returned unchanged.
+pub fn absolute(path: &str) -> String {
+    if path.contains("://") {
+        return path.to_string();
+    }
+    std::path::absolute(path)
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+/// `HEAD` of the git repository of the current directory, if there is one.
+pub fn git_sha() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let sha = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (output.status.success() && !sha.is_empty()).then_some(sha)
+}
+
+/// `KV_METADATA` option of a Parquet `COPY` carrying `lineage`.
+pub fn kv_metadata_option(lineage: &Lineage) -> String {
+    format!(
+        "KV_METADATA {{'{}': '{}'}}",
+        LINEAGE_METADATA_KEY,
+        lineage.to_json().to_string().replace('\'', "''")
+    )
+}
+
+/// Rewrites the Parquet file at `lineage.output` with the record in its
+/// key-value metadata. Returns `false` without writing for other files.
+pub fn embed_in_parquet(conn: &Connection, lineage: &Lineage) -> Result<bool> {
+    let path = Path::new(&lineage.output);
+    let is_parquet = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("parquet"));
+    if !is_parquet || !path.is_file() {
+        return Ok(false);
+    }
+
+    let staging = path.with_extension("parquet.lineage");
+    conn.execute_batch(&format!(
+        "COPY (SELECT * FROM read_parquet({})) TO {} (FORMAT PARQUET, {});",
+        path_literal(path),
+        path_literal(&staging),
+        kv_metadata_option(lineage)
+    ))
+    .with_context(|| format!("Failed to write lineage metadata to {}", path.display()))?;
+    fs::rename(&staging, path)?;
+    Ok(true)
+}
+
+/// Reads the lineage record from a Parquet file's key-value metadata.
+pub fn read_from_parquet(conn: &Connection, path: &str) -> Result<Option<Lineage>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT decode(value) FROM parquet_kv_metadata({}) WHERE decode(key) = ?",
+        path_literal(path)
+    ))?;
+    let mut rows = stmt.query([LINEAGE_METADATA_KEY])?;
+    match rows.next()? {
+        Some(row) => {
+            let text: String = row.get(0)?;
+            Ok(Some(Lineage::from_json(&serde_json::from_str(&text)?)?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// A file and, recursively, the files it was produced from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineageNode {
+    /// Absolute path of the file
+    pub path: String,
+    /// How it was produced; `None` for source files
+    pub lineage: Option<Lineage>,
+    /// Nodes of its inputs
+    pub inputs: Vec<LineageNode>,
+}
+
+impl LineageNode {
+    /// Serializes the tree, each node with its lineage record (`null` for
+    /// sources) and inputs.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "path": self.path,
+            "lineage": self.lineage.as_ref().map(Lineage::to_json),
+            "inputs": self.inputs.iter().map(LineageNode::to_json).collect::<Vec<_>>(),
+        })
+    }
+
+    /// Formats the tree with one line per file, inputs indented below.
+    pub fn format_tree(&self) -> String {
+        let mut out = String::new();
+        self.write_tree(&mut out, "", "");
+        out
+    }
+
+    fn write_tree(&self, out: &mut String, first: &str, rest: &str) {
+        match &self.lineage {
+            Some(lineage) => {
+                out.push_str(&format!(
+                    "{}{} ← {}\n",
+                    first,
+                    self.path,
+                    lineage.format_summary()
+                ));
+                out.push_str(&format!("{}  parameters: {}\n", rest, lineage.parameters));
+            }
+            None => out.push_str(&format!("{}{} (source)\n", first, self.path)),
+        }
+        for input in &self.inputs {
+            input.write_tree(out, &format!("{}  └─ ", rest), &format!("{}     ", rest));
+        }
+    }
+}
+
+/// Traces `path` back through recorded lineage, looking records up with
+/// `lookup`. A file reached twice on one branch is shown as a source, so
+/// cycles end.
+pub fn trace<F>(path: &str, lookup: &F) -> Result<LineageNode>
+where
+    F: Fn(&str) -> Result<Option<Lineage>>,
+{
+    trace_from(path, lookup, &mut HashSet::new())
+}
+
+fn trace_from<F>(path: &str, lookup: &F, seen: &mut HashSet<String>) -> Result<LineageNode>
+where
+    F: Fn(&str) -> Result<Option<Lineage>>,
+{
+    let lineage = if seen.insert(path.to_string()) {
+        lookup(path)?
+    } else {
+        None
+    };
+    let inputs = match &lineage {
+        Some(lineage) => lineage
+            .inputs
+            .iter()
+            .map(|input| trace_from(input, lookup, seen))
+            .collect::<Result<Vec<_>>>()?,
+        None => Vec::new(),
+    };
+    seen.remove(path);
+    Ok(LineageNode {
+        path: path.to_string(),
+        lineage,
+        inputs,
+    })
+}
+
+/// Looks up a file's lineage in the catalog, falling back to the Parquet
+/// metadata of the file itself.
+pub fn lookup(catalog: &DatasetCatalog, conn: &Connection, path: &str) -> Result<Option<Lineage>> {
+    if let Some(lineage) = catalog.lineage(path)? {
+        return Ok(Some(lineage));
+    }
+    if Path::new(path).is_file() && path.to_lowercase().ends_with(".parquet") {
+        return read_from_parquet(conn, path);
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(command: &str, output: &str, inputs: &[&str]) -> Lineage {
+        Lineage {
+            output: output.to_string(),
+            command: command.to_string(),
+            inputs: inputs.iter().map(|i| i.to_string()).collect(),
+            parameters: json!({"step": command}),
+            git_sha: Some("1a2b3c4d5e6f".to_string()),
+            created_at: Some("2026-10-18 09:30:12".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let lineage = step("join", "/d/out.parquet", &["/d/a.csv", "/d/b.csv"]);
+        assert_eq!(Lineage::from_json(&lineage.to_json()).unwrap(), lineage);
+        assert!(Lineage::from_json(&json!({"command": "join"})).is_err());
+        assert_eq!(
+            lineage.format_summary(),
+            "join (2026-10-18 09:30:12, git 1a2b3c4)"
+        );
+    }
+
+    #[test]
+    fn test_kv_metadata_escapes_quotes() {
+        let mut lineage = step("convert", "/d/o'brien.parquet", &[]);
+        lineage.git_sha = None;
+        let option = kv_metadata_option(&lineage);
+        assert!(option.starts_with("KV_METADATA {'frozen_duckdb.lineage': '{"));
+        assert!(option.contains("o''brien"));
+    }
+
+    #[test]
+    fn test_trace_tree() {
+        let records = [
+            step("mask", "/d/masked.parquet", &["/d/deduped.parquet"]),
+            step("dedupe", "/d/deduped.parquet", &["/d/raw.csv"]),
+        ];
+        let lookup = |path: &str| Ok(records.iter().find(|r| r.output == path).cloned());
+        let tree = trace("/d/masked.parquet", &lookup).unwrap();
+        assert_eq!(tree.inputs[0].inputs[0].path, "/d/raw.csv");
+        assert!(tree.inputs[0].inputs[0].lineage.is_none());
+        assert_eq!(
+            tree.format_tree(),
+            "/d/masked.parquet ← mask (2026-10-18 09:30:12, git 1a2b3c4)\n\
+             \x20 parameters: {\"step\":\"mask\"}\n\
+             \x20 └─ /d/deduped.parquet ← dedupe (2026-10-18 09:30:12, git 1a2b3c4)\n\
+             \x20      parameters: {\"step\":\"dedupe\"}\n\
+             \x20      └─ /d/raw.csv (source)\n"
+        );
+    }
+
+    #[test]
+    fn test_trace_stops_at_cycles() {
+        let records = [step("convert", "/d/a.parquet", &["/d/a.parquet"])];
+        let lookup = |path: &str| Ok(records.iter().find(|r| r.output == path).cloned());
+        let tree = trace("/d/a.parquet", &lookup).unwrap();
+        assert!(tree.lineage.is_some());
+        assert!(tree.inputs[0].lineage.is_none());
+    }
+}
@@ -0,0 +1,334 @@
+//! # Deterministic LLM Record/Replay for Frozen DuckDB CLI
+//!
+//! LLM output varies between runs, models, and machines, which makes
+//! snapshot tests of commands like `filter`, `summarize`, or `search`
+//! impossible to reproduce. This module records model responses to a file
+//! and serves them back later without calling the model.
+//!
+//! | `FROZEN_DUCKDB_LLM_MODE` | Behavior |
+//! |--------------------------|----------|
+//! | `live` (default) | Call the model as usual |
+//! | `record` | Call the model and store every response |
+//! | `replay` | Serve stored responses; a missing response is an error |
+//!
+//! Responses are stored in `FROZEN_DUCKDB_LLM_STORE` (default
+//! [`DEFAULT_STORE_PATH`], relative to the working directory) as JSON lines
+//! keyed on the model, the SHA-256 hash of the full prompt, and the
+//! operation parameters, so the file can be committed next to the tests
+//! that replay it:
+//!
+//! ```json
+//! {"model":"text_generator","prompt_hash":"9f86d0…","params":"{\"op\":\"complete\"}","response":"…"}
+//! ```
+//!
+//! Embeddings are recorded as JSON arrays. Replay still loads the Flock
+//! extension, but never sends a request to the model provider.
+//!
+//! ## Example
+//!
+//! ```bash
+//! # Record once against a real model
+//! FROZEN_DUCKDB_LLM_MODE=record frozen-duckdb filter --input reviews.csv --criteria "Is it positive?"
+//!
+//! # Replay anywhere, without Ollama
+//! FROZEN_DUCKDB_LLM_MODE=replay frozen-duckdb filter --input reviews.csv --criteria "Is it positive?"
+//! ```
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Environment variable selecting the [`LlmMode`].
+pub const LLM_MODE_ENV: &str = "FROZEN_DUCKDB_LLM_MODE";
+
+/// Environment variable naming the response store file.
+pub const LLM_STORE_ENV: &str = "FROZEN_DUCKDB_LLM_STORE";
+
+/// Response store used when [`LLM_STORE_ENV`] is not set.
+pub const DEFAULT_STORE_PATH: &str = ".frozen-duckdb/llm_responses.jsonl";
+
+/// Whether LLM calls go to the model, are recorded, or are replayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LlmMode {
+    /// Call the model without recording
+    #[default]
+    Live,
+    /// Call the model and store every response
+    Record,
+    /// Serve stored responses without calling the model
+    Replay,
+}
+
+impl LlmMode {
+    /// Parses `live`, `record`, or `replay`.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "live" => Ok(Self::Live),
+            "record" => Ok(Self::Record),
+            "replay" => Ok(Self::Replay),
+            other => Err(anyhow::anyhow!(
+                "Unknown LLM mode: {} (use live, record, or replay)",
+                other
+            )),
+        }
+    }
+
+    /// Name of the mode as accepted by [`LlmMode::parse`].
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Live => "live",
+            Self::Record => "record",
+            Self::Replay => "replay",
+        }
+    }
+}
+
+/// Key of a stored response: model, prompt hash, and parameters.
+type ResponseKey = (String, String, String);
+
+/// Records or replays LLM responses in a JSON lines file.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::llm_recording::{LlmMode, ResponseRecorder};
+///
+/// let recorder = ResponseRecorder::open(LlmMode::Record, "llm_responses.jsonl")?;
+/// recorder.record("coder", "Say hi", "{}", "Hi!")?;
+///
+/// let replay = ResponseRecorder::open(LlmMode::Replay, "llm_responses.jsonl")?;
+/// assert_eq!(replay.lookup("coder", "Say hi", "{}")?, Some("Hi!".to_string()));
+/// ```
+pub struct ResponseRecorder {
+    /// Record or replay
+    mode: LlmMode,
+    /// JSON lines file holding the responses
+    path: PathBuf,
+    /// Responses loaded from or added to the file
+    responses: RefCell<HashMap<ResponseKey, String>>,
+}
+
+impl ResponseRecorder {
+    /// Opens the store at `path`, loading any responses already in it.
+    ///
+    /// Replay requires the file to exist; record creates it on first use.
+    /// Later lines win when a key appears more than once.
+    pub fn open<P: AsRef<Path>>(mode: LlmMode, path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut responses = HashMap::new();
+
+        if path.exists() {
+            let content = fs::read_to_string(&path).with_context(|| {
+                format!("Failed to read LLM response store: {}", path.display())
+            })?;
+            for (number, line) in content.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let entry: Value = serde_json::from_str(line).with_context(|| {
+                    format!("Invalid entry on line {} of {}", number + 1, path.display())
+                })?;
+                let field = |key: &str| {
+                    entry
+                        .get(key)
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "Line {} of {} has no '{}' field",
+                                number + 1,
+                                path.display(),
+                                key
+                            )
+                        })
+                };
+                responses.insert(
+                    (field("model")?, field("prompt_hash")?, field("params")?),
+                    field("response")?,
+                );
+            }
+        } else if mode == LlmMode::Replay {
+            return Err(anyhow::anyhow!(
+                "LLM response store not found: {} (record it with {}=record)",
+                path.display(),
+                LLM_MODE_ENV
+            ));
+        }
+
+        debug!(
+            "Loaded {} recorded LLM responses from {}",
+            responses.len(),
+            path.display()
+        );
+        Ok(Self {
+            mode,
+            path,
+            responses: RefCell::new(responses),
+        })
+    }
+
+    /// Record or replay.
+    pub fn mode(&self) -> LlmMode {
+        self.mode
+    }
+
+    /// Path of the response store.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of stored responses.
+    pub fn len(&self) -> usize {
+        self.responses.borrow().len()
+    }
+
+    /// Whether no responses are stored.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the stored response when replaying.
+    ///
+    /// Recording always returns `None` so the model is called. Replaying
+    /// fails if the response was never recorded, rather than silently
+    /// falling back to the model.
+    pub fn lookup(&self, model: &str, prompt: &str, params: &str) -> Result<Option<String>> {
+        if self.mode != LlmMode::Replay {
+            return Ok(None);
+        }
+        let key = response_key(model, prompt, params);
+        match self.responses.borrow().get(&key) {
+            Some(response) => Ok(Some(response.clone())),
+            None => Err(anyhow::anyhow!(
+                "No recorded response for model '{}' (prompt hash {}, params {}) in {}; \
+                 re-record with {}=record",
+                model,
+                key.1,
+                params,
+                self.path.display(),
+                LLM_MODE_ENV
+            )),
+        }
+    }
+
+    /// Stores a model response when recording.
+    ///
+    /// Responses identical to the stored one are not written again, so
+    /// re-recording an unchanged run leaves the file untouched.
+    pub fn record(&self, model: &str, prompt: &str, params: &str, response: &str) -> Result<()> {
+        if self.mode != LlmMode::Record {
+            return Ok(());
+        }
+        let key = response_key(model, prompt, params);
+        if self.responses.borrow().get(&key).map(String::as_str) == Some(response) {
+            return Ok(());
+        }
+
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| {
+                format!("Failed to open LLM response store: {}", self.path.display())
+            })?;
+        let entry = serde_json::json!({
+            "model": key.0,
+            "prompt_hash": key.1,
+            "params": key.2,
+            "response": response,
+        });
+        writeln!(file, "{}", entry)?;
+
+        self.responses
+            .borrow_mut()
+            .insert(key, response.to_string());
+        Ok(())
+    }
+}
+
+/// SHA-256 hash of a prompt, as lowercase hex.
+pub fn prompt_hash(prompt: &str) -> String {
+    format!("{:x}", Sha256::digest(prompt.as_bytes()))
+}
+
+fn response_key(model: &str, prompt: &str, params: &str) -> ResponseKey {
+    (model.to_string(), prompt_hash(prompt), params.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_parse() {
+        assert_eq!(LlmMode::parse("record").unwrap(), LlmMode::Record);
+        assert_eq!(LlmMode::parse("replay").unwrap(), LlmMode::Replay);
+        assert_eq!(LlmMode::parse("live").unwrap(), LlmMode::Live);
+        assert!(LlmMode::parse("mock").is_err());
+        assert_eq!(LlmMode::Replay.as_str(), "replay");
+    }
+
+    #[test]
+    fn test_prompt_hash() {
+        assert_eq!(
+            prompt_hash("test"),
+            "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+        );
+    }
+
+    #[test]
+    fn test_record_then_replay() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("fixtures").join("llm.jsonl");
+
+        let recorder = ResponseRecorder::open(LlmMode::Record, &path).unwrap();
+        assert_eq!(recorder.lookup("coder", "Say hi", "{}").unwrap(), None);
+        recorder.record("coder", "Say hi", "{}", "Hi!").unwrap();
+        recorder.record("coder", "Say hi", "{}", "Hi!").unwrap();
+        recorder.record("coder", "Say bye", "{}", "Bye!").unwrap();
+        recorder
+            .record("coder", "Say bye", "{}", "Goodbye!")
+            .unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+
+        let replay = ResponseRecorder::open(LlmMode::Replay, &path).unwrap();
+        assert_eq!(replay.len(), 2);
+        assert_eq!(
+            replay.lookup("coder", "Say hi", "{}").unwrap(),
+            Some("Hi!".to_string())
+        );
+        assert_eq!(
+            replay.lookup("coder", "Say bye", "{}").unwrap(),
+            Some("Goodbye!".to_string())
+        );
+        assert!(replay
+            .lookup("coder", "Say hi", "{\"op\":\"filter\"}")
+            .is_err());
+        assert!(replay.lookup("other", "Say hi", "{}").is_err());
+
+        // Replay never writes
+        replay
+            .record("coder", "New prompt", "{}", "ignored")
+            .unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+    }
+
+    #[test]
+    fn test_replay_requires_store() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("missing.jsonl");
+        assert!(ResponseRecorder::open(LlmMode::Replay, &path).is_err());
+
+        fs::write(&path, "not json\n").unwrap();
+        assert!(ResponseRecorder::open(LlmMode::Replay, &path).is_err());
+    }
+}
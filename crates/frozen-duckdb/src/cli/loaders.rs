@@ -0,0 +1,690 @@
+//! # Document Loaders
+//!
+//! `index` embeds plain text, but documentation sets are Markdown, HTML,
+//! and PDF. Loaders extract the text of such files, with the metadata that
+//! tells the sections apart, before the text is chunked and embedded:
+//!
+//! | Extension | Format | Feature | Sections | Title |
+//! |-----------|--------|---------|----------|-------|
+//! | anything else | Text | (always) | The whole file | None |
+//! | `.md`, `.markdown` | Markdown | `markdown` | One per `#`, `##`, or `###` heading | Front matter `title:`, else the first `#` heading |
+//! | `.html`, `.htm` | HTML | `html` | One per `<h1>` to `<h3>` | `<title>`, else the first `<h1>` |
+//! | `.pdf` | PDF | `pdf` | One per page | The document info `Title` |
+//!
+//! The `loaders` feature enables all three. Without a format's feature,
+//! its files are skipped with a warning when indexing a directory.
+//!
+//! Each section becomes one document, identified as `<file>#<heading>`
+//! (slugified) or `<file>#page-<n>`, and its text is headed by the title
+//! and heading so the embedding carries that context. A file with a single
+//! untitled section, like a text file, keeps the file name as its id and
+//! its text unchanged.
+//!
+//! HTML is reduced to its readable text: `<script>`, `<style>`, `<nav>`,
+//! `<footer>`, and the `<head>` are left out.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use frozen_duckdb::cli::loaders::load_document;
+//! use std::path::Path;
+//!
+//! let loaded = load_document(Path::new("docs/install.md"))?;
+//! for document in loaded.into_documents("install.md") {
+//!     println!("{}: {} bytes", document.id, document.content.len());
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use super::embedding_index::Document;
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// File format of a corpus document, chosen by extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentFormat {
+    /// Plain text, the whole file as one document
+    Text,
+    /// Markdown, split at headings
+    Markdown,
+    /// HTML, split at headings
+    Html,
+    /// PDF, split into pages
+    Pdf,
+}
+
+impl DocumentFormat {
+    /// Returns the format of `path` by its extension; unknown extensions
+    /// are read as text.
+    pub fn for_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("md" | "markdown") => Self::Markdown,
+            Some("html" | "htm") => Self::Html,
+            Some("pdf") => Self::Pdf,
+            _ => Self::Text,
+        }
+    }
+
+    /// Returns the format name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Markdown => "markdown",
+            Self::Html => "html",
+            Self::Pdf => "pdf",
+        }
+    }
+}
+
+/// A part of a loaded document: the text under one heading, or one page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// Heading the section starts with
+    pub heading: Option<String>,
+    /// 1-based page number
+    pub page: Option<usize>,
+    /// Extracted text
+    pub text: String,
+}
+
+/// Text and metadata extracted from one file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedDocument {
+    /// Document title
+    pub title: Option<String>,
+    /// Sections in document order
+    pub sections: Vec<Section>,
+}
+
+impl LoadedDocument {
+    /// Converts the non-empty sections to index documents, identified by
+    /// `id` followed by the section's heading or page, which are also
+    /// recorded as `heading` and `page` metadata along with the `title`.
+    pub fn into_documents(self, id: &str) -> Vec<Document> {
+        let sections: Vec<Section> = self
+            .sections
+            .into_iter()
+            .filter(|section| !section.text.trim().is_empty())
+            .collect();
+        let untitled = |section: &Section| section.heading.is_none() && section.page.is_none();
+        if sections.len() == 1 && untitled(&sections[0]) && self.title.is_none() {
+            let text = sections
+                .into_iter()
+                .next()
+                .map(|s| s.text)
+                .unwrap_or_default();
+            return vec![Document {
+                id: id.to_string(),
+                content: text,
+                ..Default::default()
+            }];
+        }
+
+        let mut used: HashMap<String, usize> = HashMap::new();
+        sections
+            .into_iter()
+            .map(|section| {
+                let anchor = match (&section.heading, section.page) {
+                    (_, Some(page)) => format!("page-{}", page),
+                    (Some(heading), None) => slugify(heading),
+                    (None, None) => "start".to_string(),
+                };
+                let count = used.entry(anchor.clone()).or_insert(0);
+                *count += 1;
+                let anchor = match *count {
+                    1 => anchor,
+                    n => format!("{}-{}", anchor, n),
+                };
+
+                let mut context: Vec<String> = self.title.iter().cloned().collect();
+                match (&section.heading, section.page) {
+                    (_, Some(page)) => context.push(format!("page {}", page)),
+                    (Some(heading), None) if self.title.as_ref() != Some(heading) => {
+                        context.push(heading.clone())
+                    }
+                    _ => {}
+                }
+                let content = if context.is_empty() {
+                    section.text
+                } else {
+                    format!("{}\n\n{}", context.join(" — "), section.text)
+                };
+
+                let mut metadata = BTreeMap::new();
+                if let Some(title) = &self.title {
+                    metadata.insert("title".to_string(), title.clone());
+                }
+                if let Some(heading) = section.heading {
+                    metadata.insert("heading".to_string(), heading);
+                }
+                if let Some(page) = section.page {
+                    metadata.insert("page".to_string(), page.to_string());
+                }
+                Document {
+                    id: format!("{}#{}", id, anchor),
+                    content,
+                    metadata,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Extracts the text of one document format.
+///
+/// Implemented for each [`DocumentFormat`] whose feature is enabled; see
+/// [`loader_for`].
+pub trait DocumentLoader {
+    /// Reads `path` and returns its text and metadata.
+    fn load(&self, path: &Path) -> Result<LoadedDocument>;
+}
+
+/// Loads a file as a single untitled section of text.
+pub struct TextLoader;
+
+impl DocumentLoader for TextLoader {
+    fn load(&self, path: &Path) -> Result<LoadedDocument> {
+        let text = fs::read_to_string(path)?;
+        Ok(LoadedDocument {
+            title: None,
+            sections: vec![Section {
+                heading: None,
+                page: None,
+                text,
+            }],
+        })
+    }
+}
+
+/// Loads Markdown with `pulldown-cmark`.
+#[cfg(feature = "markdown")]
+pub struct MarkdownLoader;
+
+#[cfg(feature = "markdown")]
+impl DocumentLoader for MarkdownLoader {
+    fn load(&self, path: &Path) -> Result<LoadedDocument> {
+        Ok(parse_markdown(&fs::read_to_string(path)?))
+    }
+}
+
+/// Loads HTML with `scraper`.
+#[cfg(feature = "html")]
+pub struct HtmlLoader;
+
+#[cfg(feature = "html")]
+impl DocumentLoader for HtmlLoader {
+    fn load(&self, path: &Path) -> Result<LoadedDocument> {
+        Ok(parse_html(&fs::read_to_string(path)?))
+    }
+}
+
+/// Loads PDF pages with `pdf-extract`.
+#[cfg(feature = "pdf")]
+pub struct PdfLoader;
+
+#[cfg(feature = "pdf")]
+impl DocumentLoader for PdfLoader {
+    fn load(&self, path: &Path) -> Result<LoadedDocument> {
+        let bytes = fs::read(path)?;
+        let pages = pdf_extract::extract_text_from_mem_by_pages(&bytes)?;
+        Ok(LoadedDocument {
+            title: pdf_title(&bytes),
+            sections: pages
+                .iter()
+                .enumerate()
+                .map(|(i, page)| Section {
+                    heading: None,
+                    page: Some(i + 1),
+                    text: tidy(page),
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Returns the loader for `format`, or an error naming the feature it
+/// needs.
+pub fn loader_for(format: DocumentFormat) -> Result<Box<dyn DocumentLoader>> {
+    match format {
+        DocumentFormat::Text => Ok(Box::new(TextLoader)),
+        #[cfg(feature = "markdown")]
+        DocumentFormat::Markdown => Ok(Box::new(MarkdownLoader)),
+        #[cfg(feature = "html")]
+        DocumentFormat::Html => Ok(Box::new(HtmlLoader)),
+        #[cfg(feature = "pdf")]
+        DocumentFormat::Pdf => Ok(Box::new(PdfLoader)),
+        #[allow(unreachable_patterns)]
+        other => Err(anyhow::anyhow!(
+            "Loading {} files needs the `{}` feature; rebuild with --features {}",
+            other.as_str(),
+            other.as_str(),
+            other.as_str()
+        )),
+    }
+}
+
+/// Loads a file with the loader for its extension.
+pub fn load_document(path: &Path) -> Result<LoadedDocument> {
+    loader_for(DocumentFormat::for_path(path))?
+        .load(path)
+        .with_context(|| format!("Failed to load {}", path.display()))
+}
+
+/// Extracts the title and heading sections of a Markdown document.
+#[cfg(feature = "markdown")]
+pub fn parse_markdown(text: &str) -> LoadedDocument {
+    use pulldown_cmark::{Event, HeadingLevel, MetadataBlockKind, Options, Parser, Tag, TagEnd};
+
+    let mut title = None;
+    let mut sections = vec![Section {
+        heading: None,
+        page: None,
+        text: String::new(),
+    }];
+    // Text of the heading being read, if it starts a section
+    let mut heading: Option<String> = None;
+    let mut in_metadata = false;
+
+    let parser = Parser::new_ext(text, Options::ENABLE_YAML_STYLE_METADATA_BLOCKS);
+    for event in parser {
+        let text = &mut sections.last_mut().expect("sections start non-empty").text;
+        match event {
+            Event::Start(Tag::MetadataBlock(MetadataBlockKind::YamlStyle)) => in_metadata = true,
+            Event::End(TagEnd::MetadataBlock(_)) => in_metadata = false,
+            Event::Text(value) if in_metadata => {
+                title = title.or_else(|| front_matter_title(&value));
+            }
+            Event::Start(Tag::Heading { level, .. }) if level <= HeadingLevel::H3 => {
+                heading = Some(String::new());
+            }
+            Event::End(TagEnd::Heading(level)) if level <= HeadingLevel::H3 => {
+                let name = heading.take().unwrap_or_default().trim().to_string();
+                if level == HeadingLevel::H1 && title.is_none() {
+                    title = Some(name.clone());
+                }
+                sections.push(Section {
+                    heading: Some(name),
+                    page: None,
+                    text: String::new(),
+                });
+            }
+            Event::Text(value) | Event::Code(value) => match heading.as_mut() {
+                Some(heading) => heading.push_str(&value),
+                None => text.push_str(&value),
+            },
+            Event::SoftBreak | Event::HardBreak => match heading.as_mut() {
+                Some(heading) => heading.push(' '),
+                None => text.push('\n'),
+            },
+            Event::End(
+                TagEnd::Paragraph
+                | TagEnd::Heading(_)
+                | TagEnd::Item
+                | TagEnd::CodeBlock
+                | TagEnd::TableRow
+                | TagEnd::TableHead,
+            ) => text.push('\n'),
+            Event::End(TagEnd::TableCell) => text.push(' '),
+            _ => {}
+        }
+    }
+
+    for section in &mut sections {
+        section.text = tidy(&section.text);
+    }
+    LoadedDocument { title, sections }
+}
+
+/// Returns the `title:` of YAML front matter.
+#[cfg(feature = "markdown")]
+fn front_matter_title(metadata: &str) -> Option<String> {
+    metadata.lines().find_map(|line| {
+        let value = line.strip_prefix("title:")?.trim();
+        let value = value.trim_matches(|c| c == '"' || c == '\'').trim();
+        (!value.is_empty()).then(|| value.to_string())
+    })
+}
+
+/// Extracts the title and heading sections of an HTML document.
+#[cfg(feature = "html")]
+pub fn parse_html(text: &str) -> LoadedDocument {
+    use scraper::node::Node;
+    use scraper::{ElementRef, Html, Selector};
+
+    const SKIPPED: &[&str] = &[
+        "head", "script", "style", "noscript", "template", "svg", "nav", "footer",
+    ];
+    const BLOCKS: &[&str] = &[
+        "p",
+        "div",
+        "li",
+        "tr",
+        "pre",
+        "blockquote",
+        "section",
+        "article",
+        "table",
+        "ul",
+        "ol",
+        "h4",
+        "h5",
+        "h6",
+        "dd",
+        "dt",
+    ];
+    const HEADINGS: &[&str] = &["h1", "h2", "h3"];
+
+    let document = Html::parse_document(text);
+    let element_text = |selector: &str| {
+        let selector = Selector::parse(selector).expect("valid selector");
+        document
+            .select(&selector)
+            .next()
+            .map(|element| collapse_whitespace(&element.text().collect::<String>()))
+            .filter(|text| !text.is_empty())
+    };
+    let title = element_text("title").or_else(|| element_text("h1"));
+
+    let mut sections = vec![Section {
+        heading: None,
+        page: None,
+        text: String::new(),
+    }];
+    let mut skipping = None;
+    let mut heading: Option<(ego_tree::NodeId, String)> = None;
+    let mut preformatted = 0;
+
+    for edge in document.tree.root().traverse() {
+        match edge {
+            ego_tree::iter::Edge::Open(node) => {
+                if skipping.is_some() {
+                    continue;
+                }
+                match node.value() {
+                    Node::Element(element) => {
+                        let name = element.name();
+                        if SKIPPED.contains(&name) {
+                            skipping = Some(node.id());
+                        } else if HEADINGS.contains(&name) {
+                            heading = Some((node.id(), String::new()));
+                        } else if name == "pre" {
+                            preformatted += 1;
+                        } else if name == "br" {
+                            sections.last_mut().expect("non-empty").text.push('\n');
+                        }
+                    }
+                    Node::Text(value) => {
+                        let value: &str = value;
+                        match heading.as_mut() {
+                            Some((_, heading)) => heading.push_str(value),
+                            None if preformatted > 0 => {
+                                sections.last_mut().expect("non-empty").text.push_str(value)
+                            }
+                            None => {
+                                let section = sections.last_mut().expect("non-empty");
+                                // Whitespace between inline elements separates words
+                                if value.starts_with(char::is_whitespace) {
+                                    section.text.push(' ');
+                                }
+                                section.text.push_str(&collapse_whitespace(value));
+                                if value.ends_with(char::is_whitespace) {
+                                    section.text.push(' ');
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            ego_tree::iter::Edge::Close(node) => {
+                if skipping == Some(node.id()) {
+                    skipping = None;
+                    continue;
+                }
+                if skipping.is_some() {
+                    continue;
+                }
+                if heading.as_ref().is_some_and(|(id, _)| *id == node.id()) {
+                    let (_, name) = heading.take().expect("checked above");
+                    sections.push(Section {
+                        heading: Some(collapse_whitespace(&name)),
+                        page: None,
+                        text: String::new(),
+                    });
+                    continue;
+                }
+                if let Some(element) = ElementRef::wrap(node) {
+                    let name = element.value().name();
+                    if name == "pre" {
+                        preformatted -= 1;
+                    }
+                    if BLOCKS.contains(&name) {
+                        sections.last_mut().expect("non-empty").text.push('\n');
+                    }
+                }
+            }
+        }
+    }
+
+    for section in &mut sections {
+        section.text = tidy(&section.text);
+    }
+    LoadedDocument { title, sections }
+}
+
+/// Reads the `Title` of a PDF's document information dictionary.
+#[cfg(feature = "pdf")]
+fn pdf_title(bytes: &[u8]) -> Option<String> {
+    use pdf_extract::Object;
+
+    let document = pdf_extract::Document::load_mem(bytes).ok()?;
+    let info = match document.trailer.get(b"Info").ok()? {
+        Object::Reference(id) => document.get_dictionary(*id).ok()?,
+        Object::Dictionary(info) => info,
+        _ => return None,
+    };
+    let title = pdf_extract::decode_text_string(info.get(b"Title").ok()?).ok()?;
+    let title = title.trim();
+    (!title.is_empty()).then(|| title.to_string())
+}
+
+/// Replaces runs of whitespace with single spaces and trims the ends.
+#[cfg_attr(not(feature = "html"), allow(dead_code))]
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Trims every line and collapses runs of blank lines into one.
+#[cfg_attr(
+    not(any(feature = "markdown", feature = "html", feature = "pdf")),
+    allow(dead_code)
+)]
+fn tidy(text: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim_end) {
+        let line = if line.trim().is_empty() { "" } else { line };
+        if line.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last() == Some(&"") {
+        lines.pop();
+    }
+    lines
+        .iter()
+        .map(|line| line.trim_start_matches(' '))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Lowercases a heading and joins its words with hyphens, for ids.
+fn slugify(heading: &str) -> String {
+    let slug = heading
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(heading: Option<&str>, page: Option<usize>, text: &str) -> Section {
+        Section {
+            heading: heading.map(str::to_string),
+            page,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_format_for_path() {
+        assert_eq!(
+            DocumentFormat::for_path(Path::new("a/README.md")),
+            DocumentFormat::Markdown
+        );
+        assert_eq!(
+            DocumentFormat::for_path(Path::new("index.HTM")),
+            DocumentFormat::Html
+        );
+        assert_eq!(
+            DocumentFormat::for_path(Path::new("manual.pdf")),
+            DocumentFormat::Pdf
+        );
+        assert_eq!(
+            DocumentFormat::for_path(Path::new("notes.txt")),
+            DocumentFormat::Text
+        );
+        assert_eq!(
+            DocumentFormat::for_path(Path::new("LICENSE")),
+            DocumentFormat::Text
+        );
+    }
+
+    #[test]
+    fn test_untitled_text_keeps_id_and_content() {
+        let loaded = LoadedDocument {
+            title: None,
+            sections: vec![section(None, None, "  plain text\n")],
+        };
+        let documents = loaded.into_documents("notes.txt");
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].id, "notes.txt");
+        assert_eq!(documents[0].content, "  plain text\n");
+    }
+
+    #[test]
+    fn test_sections_become_documents() {
+        let loaded = LoadedDocument {
+            title: Some("Guide".to_string()),
+            sections: vec![
+                section(None, None, "Preamble"),
+                section(Some("Guide"), None, "Welcome"),
+                section(Some("Install on Linux"), None, "apt install"),
+                section(Some("Install on Linux"), None, "again"),
+                section(Some("Empty"), None, "  "),
+            ],
+        };
+        let documents = loaded.into_documents("guide.md");
+        let ids: Vec<&str> = documents.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "guide.md#start",
+                "guide.md#guide",
+                "guide.md#install-on-linux",
+                "guide.md#install-on-linux-2"
+            ]
+        );
+        assert_eq!(documents[1].content, "Guide\n\nWelcome");
+        assert_eq!(
+            documents[2].content,
+            "Guide — Install on Linux\n\napt install"
+        );
+        assert_eq!(documents[2].metadata["title"], "Guide");
+        assert_eq!(documents[2].metadata["heading"], "Install on Linux");
+
+        let pages = LoadedDocument {
+            title: None,
+            sections: vec![section(None, Some(1), "one"), section(None, Some(2), "two")],
+        };
+        let documents = pages.into_documents("manual.pdf");
+        assert_eq!(documents[1].id, "manual.pdf#page-2");
+        assert_eq!(documents[1].content, "page 2\n\ntwo");
+        assert_eq!(documents[1].metadata["page"], "2");
+    }
+
+    #[test]
+    fn test_tidy_and_slugify() {
+        assert_eq!(tidy("\n  a  \n\n\n b\n\n"), "a\n\nb");
+        assert_eq!(slugify("What's new in 1.4?"), "what-s-new-in-1-4");
+        assert_eq!(slugify("!!!"), "section");
+    }
+
+    #[test]
+    fn test_missing_feature_is_reported() {
+        if cfg!(feature = "pdf") {
+            return;
+        }
+        let error = loader_for(DocumentFormat::Pdf).err().unwrap();
+        assert!(error.to_string().contains("--features pdf"));
+    }
+
+    #[cfg(feature = "markdown")]
+    #[test]
+    fn test_parse_markdown() {
+        let loaded = parse_markdown(
+            "---\ntitle: \"User Guide\"\n---\nIntro text.\n\n# Setup\n\nRun `make`.\n\n\
+             - one\n- two\n\n## Linux\n\nUse apt.\n\n#### Details\n\nStays in Linux.\n",
+        );
+        assert_eq!(loaded.title.as_deref(), Some("User Guide"));
+        let headings: Vec<Option<&str>> = loaded
+            .sections
+            .iter()
+            .map(|s| s.heading.as_deref())
+            .collect();
+        assert_eq!(headings, vec![None, Some("Setup"), Some("Linux")]);
+        assert_eq!(loaded.sections[0].text, "Intro text.");
+        assert_eq!(loaded.sections[1].text, "Run make.\none\ntwo");
+        assert_eq!(
+            loaded.sections[2].text,
+            "Use apt.\nDetails\nStays in Linux."
+        );
+
+        let untitled = parse_markdown("# Heading Title\n\nBody");
+        assert_eq!(untitled.title.as_deref(), Some("Heading Title"));
+    }
+
+    #[cfg(feature = "html")]
+    #[test]
+    fn test_parse_html() {
+        let loaded = parse_html(
+            "<html><head><title> API   Docs </title><style>p {}</style></head><body>\
+             <nav>Home | About</nav><p>Overview of the <b>API</b>.</p>\
+             <h2>Auth<span>entication</span></h2><p>Use a token.</p>\
+             <script>track()</script><pre>curl -H  'x'\n  /v1</pre>\
+             <footer>© 2024</footer></body></html>",
+        );
+        assert_eq!(loaded.title.as_deref(), Some("API Docs"));
+        assert_eq!(loaded.sections.len(), 2);
+        assert_eq!(loaded.sections[0].text, "Overview of the API.");
+        assert_eq!(
+            loaded.sections[1].heading.as_deref(),
+            Some("Authentication")
+        );
+        assert_eq!(loaded.sections[1].text, "Use a token.\ncurl -H  'x'\n/v1");
+    }
+}
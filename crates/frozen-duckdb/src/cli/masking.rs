@@ -0,0 +1,548 @@
+//! # Column Masking for Frozen DuckDB CLI
+//!
+//! This module anonymizes sensitive columns before a dataset is shared.
+//! Each masked column is rewritten by a SQL expression in a single `COPY`
+//! pass; other columns are copied unchanged and row order is preserved.
+//!
+//! ## Mask File
+//!
+//! ```yaml
+//! columns:
+//!   email: hash
+//!   customer_id:
+//!     method: tokenize
+//!     prefix: cust_
+//!   city: shuffle
+//!   ssn:
+//!     method: redact
+//!     keep_last: 4
+//!   full_name:
+//!     method: fake
+//!     kind: name
+//! ```
+//!
+//! | Method | Result | Options |
+//! |--------|--------|---------|
+//! | `hash` | SHA-256 hex digest of salt + value | |
+//! | `tokenize` | Short stable token such as `tok_3f2a9c01d4e5b6a7` | `prefix` (default `tok_`) |
+//! | `shuffle` | Values permuted across rows | |
+//! | `redact` | Value with its middle replaced, such as `*****6789` | `keep_first`, `keep_last` (default 0), `mask_char` (default `*`) |
+//! | `fake` | Made-up replacement derived from the value | `kind`: `first_name`, `last_name`, `name`, `email`, `phone`, `city` |
+//!
+//! NULLs stay NULL under every method.
+//!
+//! ## Salt and Referential Integrity
+//!
+//! `hash`, `tokenize`, `fake`, and `shuffle` are keyed by a salt. With the
+//! same salt, a value masks to the same output in every column and file,
+//! so masked tables can still be joined. The salt comes from `--salt`, then
+//! `FROZEN_DUCKDB_MASK_SALT`, then a `salt:` entry in the mask file. Keep
+//! it secret: without a salt, hashed values such as emails can be recovered
+//! by hashing guesses.
+
+use super::dedupe::{copy_format, quote_identifier, read_function};
+use super::sql_path::path_literal;
+use anyhow::{anyhow, bail, Context, Result};
+use duckdb::Connection;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// Environment variable holding the masking salt.
+pub const MASK_SALT_ENV: &str = "FROZEN_DUCKDB_MASK_SALT";
+
+/// Kind of made-up value produced by the `fake` method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FakeKind {
+    /// A first name
+    FirstName,
+    /// A last name
+    LastName,
+    /// First and last name
+    Name,
+    /// An address at example.com
+    Email,
+    /// A fictional 555-01xx phone number
+    Phone,
+    /// A city name
+    City,
+}
+
+impl FakeKind {
+    /// Parses a fake kind name.
+    pub fn parse(kind: &str) -> Result<Self> {
+        match kind {
+            "first_name" => Ok(Self::FirstName),
+            "last_name" => Ok(Self::LastName),
+            "name" => Ok(Self::Name),
+            "email" => Ok(Self::Email),
+            "phone" => Ok(Self::Phone),
+            "city" => Ok(Self::City),
+            other => Err(anyhow!(
+                "Unknown fake kind: {} (use first_name, last_name, name, email, phone, or city)",
+                other
+            )),
+        }
+    }
+}
+
+const FIRST_NAMES: [&str; 16] = [
+    "Alex", "Blake", "Casey", "Dana", "Eli", "Frances", "Gale", "Harper", "Indra", "Jordan", "Kai",
+    "Lee", "Morgan", "Noa", "Quinn", "Robin",
+];
+
+const LAST_NAMES: [&str; 16] = [
+    "Adler", "Brooks", "Chen", "Diaz", "Evans", "Fischer", "Garcia", "Hughes", "Ito", "Jensen",
+    "Kowalski", "Lopez", "Moreau", "Novak", "Okafor", "Patel",
+];
+
+const CITIES: [&str; 12] = [
+    "Ashford",
+    "Brookvale",
+    "Cedar Falls",
+    "Dunmore",
+    "Eastwick",
+    "Fairhaven",
+    "Glenrock",
+    "Harborview",
+    "Irongate",
+    "Juniper",
+    "Kingsbridge",
+    "Lakemont",
+];
+
+/// How a column is masked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MaskMethod {
+    /// SHA-256 hex digest of salt + value
+    Hash,
+    /// `prefix` followed by the first 16 hex digits of the hash
+    Tokenize {
+        /// Token prefix
+        prefix: String,
+    },
+    /// Values permuted across rows
+    Shuffle,
+    /// Characters between the kept prefix and suffix replaced
+    Redact {
+        /// Leading characters kept
+        keep_first: usize,
+        /// Trailing characters kept
+        keep_last: usize,
+        /// Replacement character
+        mask_char: char,
+    },
+    /// Made-up value chosen by the hash of the original
+    Fake(FakeKind),
+}
+
+/// A column and how it is masked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMask {
+    /// Column name
+    pub column: String,
+    /// Masking method
+    pub method: MaskMethod,
+}
+
+/// Masks loaded from a mask file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MaskConfig {
+    /// Salt from the file, used when none is given on the command line
+    pub salt: Option<String>,
+    /// Masked columns, in file order
+    pub columns: Vec<ColumnMask>,
+}
+
+impl MaskConfig {
+    /// Loads a YAML mask file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read mask file: {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid mask file: {}", path.display()))
+    }
+
+    /// Parses a YAML mask file.
+    pub fn parse(yaml: &str) -> Result<Self> {
+        let documents = YamlLoader::load_from_str(yaml)?;
+        let root = documents
+            .first()
+            .ok_or_else(|| anyhow!("Mask file is empty"))?;
+        let salt = match &root["salt"] {
+            Yaml::BadValue => None,
+            value => Some(
+                value
+                    .as_str()
+                    .ok_or_else(|| anyhow!("'salt' must be a string"))?
+                    .to_string(),
+            ),
+        };
+        let columns = root["columns"]
+            .as_hash()
+            .ok_or_else(|| anyhow!("'columns' must map column names to methods"))?
+            .iter()
+            .map(|(column, spec)| {
+                let column = column
+                    .as_str()
+                    .ok_or_else(|| anyhow!("Column names must be strings"))?;
+                Ok(ColumnMask {
+                    column: column.to_string(),
+                    method: parse_method(spec).with_context(|| format!("Column {}", column))?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if columns.is_empty() {
+            bail!("No columns to mask");
+        }
+        Ok(Self { salt, columns })
+    }
+}
+
+fn parse_method(spec: &Yaml) -> Result<MaskMethod> {
+    // `email: hash` is shorthand for `email: {method: hash}`
+    let (method, options) = match spec {
+        Yaml::String(method) => (method.as_str(), None),
+        Yaml::Hash(_) => (
+            spec["method"]
+                .as_str()
+                .ok_or_else(|| anyhow!("'method' is required"))?,
+            Some(spec),
+        ),
+        _ => bail!("Expected a method name or a map with 'method'"),
+    };
+    let option = |key: &str| options.map(|spec| &spec[key]).unwrap_or(&Yaml::BadValue);
+    let count = |key: &str| -> Result<usize> {
+        match option(key) {
+            Yaml::BadValue => Ok(0),
+            Yaml::Integer(n) if *n >= 0 => Ok(*n as usize),
+            _ => bail!("'{}' must be a non-negative integer", key),
+        }
+    };
+
+    match method {
+        "hash" => Ok(MaskMethod::Hash),
+        "tokenize" => Ok(MaskMethod::Tokenize {
+            prefix: option("prefix").as_str().unwrap_or("tok_").to_string(),
+        }),
+        "shuffle" => Ok(MaskMethod::Shuffle),
+        "redact" => {
+            let mask_char = option("mask_char").as_str().unwrap_or("*");
+            let mut chars = mask_char.chars();
+            let (Some(mask_char), None) = (chars.next(), chars.next()) else {
+                bail!("'mask_char' must be a single character");
+            };
+            Ok(MaskMethod::Redact {
+                keep_first: count("keep_first")?,
+                keep_last: count("keep_last")?,
+                mask_char,
+            })
+        }
+        "fake" => Ok(MaskMethod::Fake(FakeKind::parse(
+            option("kind")
+                .as_str()
+                .ok_or_else(|| anyhow!("'kind' is required for fake"))?,
+        )?)),
+        other => bail!(
+            "Unknown method: {} (use hash, tokenize, shuffle, redact, or fake)",
+            other
+        ),
+    }
+}
+
+/// Summary of a masking run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaskReport {
+    /// Rows written
+    pub rows: usize,
+    /// Columns masked
+    pub masked_columns: usize,
+    /// Columns copied unchanged
+    pub unchanged_columns: usize,
+}
+
+/// Masks the columns named in `config`, writing `input` to `output`.
+///
+/// File formats are inferred from extensions (`.csv`, `.parquet`, `.json`).
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::masking::{mask, MaskConfig};
+/// use frozen_duckdb::cli::DatasetManager;
+///
+/// let manager = DatasetManager::new()?;
+/// let config = MaskConfig::load("mask.yaml")?;
+/// let report = mask(manager.connection(), "users.parquet", "users_masked.parquet", &config, "s3cret")?;
+/// println!("Masked {} columns in {} rows", report.masked_columns, report.rows);
+/// ```
+pub fn mask(
+    conn: &Connection,
+    input: &str,
+    output: &str,
+    config: &MaskConfig,
+    salt: &str,
+) -> Result<MaskReport> {
+    let reader = read_function(input)?;
+    let format = copy_format(output)?;
+
+    let mut stmt = conn.prepare(&format!("DESCRIBE SELECT * FROM {}", reader))?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    for mask in &config.columns {
+        if !columns.contains(&mask.column) {
+            bail!("Column {} not found in {}", mask.column, input);
+        }
+    }
+
+    info!(
+        "🎭 Masking {} of {} columns in {}",
+        config.columns.len(),
+        columns.len(),
+        input
+    );
+    let query = masking_query(&reader, &columns, &config.columns, salt);
+    let rows = conn
+        .execute(
+            &format!("COPY ({}) TO {} ({})", query, path_literal(output), format),
+            [],
+        )
+        .with_context(|| format!("Failed to mask {}", input))?;
+
+    Ok(MaskReport {
+        rows,
+        masked_columns: config.columns.len(),
+        unchanged_columns: columns.len() - config.columns.len(),
+    })
+}
+
+/// Builds the query selecting `columns` of `source` with `masks` applied.
+pub fn masking_query(source: &str, columns: &[String], masks: &[ColumnMask], salt: &str) -> String {
+    let salt = format!("'{}'", salt.replace('\'', "''"));
+    let mut ctes = vec![format!(
+        "mask_source AS MATERIALIZED (SELECT row_number() OVER () AS mask_row, * FROM {})",
+        source
+    )];
+    let mut joins = String::new();
+
+    let projection = columns
+        .iter()
+        .map(|column| {
+            let quoted = quote_identifier(column);
+            let value = format!("mask_source.{}", quoted);
+            let expression = match masks.iter().find(|m| &m.column == column) {
+                None => value,
+                Some(ColumnMask {
+                    method: MaskMethod::Shuffle,
+                    ..
+                }) => {
+                    // Renumber the rows in salted-hash order and join the
+                    // value back by the new number
+                    let alias = format!("mask_shuffle_{}", ctes.len());
+                    ctes.push(format!(
+                        "{alias} AS (SELECT row_number() OVER (ORDER BY md5({salt} || mask_row), mask_row) AS mask_row, {quoted} AS value FROM mask_source)"
+                    ));
+                    joins.push_str(&format!(
+                        " LEFT JOIN {alias} ON {alias}.mask_row = mask_source.mask_row"
+                    ));
+                    format!("{}.value", alias)
+                }
+                Some(mask) => mask_expression(&mask.method, &value, &salt),
+            };
+            format!("{} AS {}", expression, quoted)
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        "WITH {} SELECT {} FROM mask_source{} ORDER BY mask_source.mask_row",
+        ctes.join(", "),
+        projection,
+        joins
+    )
+}
+
+/// SQL expression masking `value`; `salt` is a quoted SQL literal.
+fn mask_expression(method: &MaskMethod, value: &str, salt: &str) -> String {
+    let text = format!("CAST({} AS VARCHAR)", value);
+    let digest = format!("sha256({} || {})", salt, text);
+    // Non-negative number derived from the digest; NULL for NULL values
+    let number = format!("md5_number({} || {})", salt, text);
+    let pick = |list: &[&str], n: &str| {
+        format!(
+            "[{}][CAST({} % {} AS INTEGER) + 1]",
+            list.iter()
+                .map(|s| format!("'{}'", s))
+                .collect::<Vec<_>>()
+                .join(", "),
+            n,
+            list.len()
+        )
+    };
+
+    match method {
+        MaskMethod::Hash => digest,
+        MaskMethod::Tokenize { prefix } => format!(
+            "'{}' || substr({}, 1, 16)",
+            prefix.replace('\'', "''"),
+            digest
+        ),
+        MaskMethod::Redact {
+            keep_first,
+            keep_last,
+            mask_char,
+        } => {
+            let mask_char = mask_char.to_string().replace('\'', "''");
+            format!(
+                "CASE WHEN length({text}) <= {kept} THEN repeat('{mask_char}', length({text})) \
+                 ELSE left({text}, {keep_first}) || repeat('{mask_char}', length({text}) - {kept}) || right({text}, {keep_last}) END",
+                kept = keep_first + keep_last,
+            )
+        }
+        MaskMethod::Fake(kind) => {
+            let first = pick(&FIRST_NAMES, &number);
+            let last = pick(
+                &LAST_NAMES,
+                &format!("({} // {})", number, FIRST_NAMES.len()),
+            );
+            match kind {
+                FakeKind::FirstName => first,
+                FakeKind::LastName => last,
+                FakeKind::Name => format!("{} || ' ' || {}", first, last),
+                FakeKind::Email => format!(
+                    "lower({} || '.' || {}) || CAST({} % 1000 AS VARCHAR) || '@example.com'",
+                    first, last, number
+                ),
+                FakeKind::Phone => format!(
+                    "'555-01' || lpad(CAST({} % 100 AS VARCHAR), 2, '0')",
+                    number
+                ),
+                FakeKind::City => pick(&CITIES, &number),
+            }
+        }
+        MaskMethod::Shuffle => unreachable!("shuffled columns are joined, not computed"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MASKS: &str = r#"
+columns:
+  email: hash
+  customer_id:
+    method: tokenize
+    prefix: cust_
+  city: shuffle
+  ssn:
+    method: redact
+    keep_last: 4
+  name:
+    method: fake
+    kind: name
+"#;
+
+    fn write_input(dir: &Path) -> String {
+        let input = dir.join("customers.csv");
+        fs::write(
+            &input,
+            "customer_id,name,email,ssn,city,score\n\
+             1,Ada Lovelace,ada@example.org,123-45-6789,London,10\n\
+             2,Alan Turing,alan@example.org,987-65-4321,Wilmslow,20\n\
+             3,Grace Hopper,,555-12-3456,Arlington,30\n",
+        )
+        .unwrap();
+        input.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = MaskConfig::parse(MASKS).unwrap();
+        assert_eq!(config.salt, None);
+        assert_eq!(config.columns.len(), 5);
+        assert_eq!(
+            config.columns[1].method,
+            MaskMethod::Tokenize {
+                prefix: "cust_".to_string()
+            }
+        );
+        assert_eq!(
+            config.columns[3].method,
+            MaskMethod::Redact {
+                keep_first: 0,
+                keep_last: 4,
+                mask_char: '*'
+            }
+        );
+
+        assert!(MaskConfig::parse("columns:\n  a: scramble\n").is_err());
+        assert!(MaskConfig::parse("columns:\n  a: fake\n").is_err());
+        assert!(MaskConfig::parse("columns:\n  a: {method: redact, mask_char: ab}\n").is_err());
+        assert!(MaskConfig::parse("salt: x\n").is_err());
+    }
+
+    #[test]
+    fn test_mask_columns() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let input = write_input(temp.path());
+        let output = temp.path().join("masked.csv");
+        let output = output.to_str().unwrap();
+        let conn = Connection::open_in_memory()?;
+
+        let config = MaskConfig::parse(MASKS)?;
+        let report = mask(&conn, &input, output, &config, "pepper")?;
+        assert_eq!(report.rows, 3);
+        assert_eq!(report.unchanged_columns, 1);
+
+        let rows = conn
+            .prepare(&format!(
+                "SELECT customer_id, name, email, ssn, city, score FROM read_csv('{}', header=true, all_varchar=true)",
+                output
+            ))?
+            .query_map([], |row| {
+                (0..6)
+                    .map(|i| row.get::<_, Option<String>>(i))
+                    .collect::<std::result::Result<Vec<_>, _>>()
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        // Order and unmasked columns are preserved
+        let scores: Vec<_> = rows.iter().map(|r| r[5].as_deref()).collect();
+        assert_eq!(scores, [Some("10"), Some("20"), Some("30")]);
+
+        let token = rows[0][0].as_deref().unwrap();
+        assert!(token.starts_with("cust_") && token.len() == 21);
+        assert_eq!(rows[0][2].as_deref().map(str::len), Some(64));
+        assert_eq!(rows[2][2], None);
+        assert_eq!(rows[0][3].as_deref(), Some("*******6789"));
+        assert!(!rows[0][1].as_deref().unwrap().contains("Ada"));
+
+        let mut cities: Vec<_> = rows.iter().map(|r| r[4].clone().unwrap()).collect();
+        cities.sort();
+        assert_eq!(cities, ["Arlington", "London", "Wilmslow"]);
+
+        // The same salt masks values identically, so joins survive
+        let again = temp.path().join("again.csv");
+        mask(&conn, &input, again.to_str().unwrap(), &config, "pepper")?;
+        assert_eq!(fs::read_to_string(output)?, fs::read_to_string(&again)?);
+        mask(&conn, &input, again.to_str().unwrap(), &config, "salt")?;
+        assert_ne!(fs::read_to_string(output)?, fs::read_to_string(&again)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_column() {
+        let temp = tempfile::tempdir().unwrap();
+        let input = write_input(temp.path());
+        let conn = Connection::open_in_memory().unwrap();
+        let config = MaskConfig::parse("columns:\n  phone: hash\n").unwrap();
+        let output = temp.path().join("out.csv");
+        let error = mask(&conn, &input, output.to_str().unwrap(), &config, "")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("phone"));
+    }
+}
@@ -0,0 +1,384 @@
+//! # Materialized Views
+//!
+//! DuckDB views are recomputed on every query. A materialized view is a
+//! table built with `CREATE TABLE ... AS` from a stored SQL definition and
+//! rebuilt on demand with `frozen-duckdb views refresh`, so dashboards read
+//! a precomputed result.
+//!
+//! ## Metadata Schema
+//!
+//! Definitions live in the database next to the tables they produce:
+//!
+//! ```text
+//! frozen_duckdb_materialized_views (name VARCHAR PRIMARY KEY, sql VARCHAR,
+//!                                   defined_at TIMESTAMP, refreshed_at TIMESTAMP,
+//!                                   row_count BIGINT)
+//! ```
+//!
+//! ## Dependencies
+//!
+//! A view depends on every other materialized view whose name appears as
+//! an identifier in its SQL. [`ViewRegistry::refresh_all`] refreshes views
+//! after the views they read, and defining a view that would create a cycle
+//! fails.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use frozen_duckdb::cli::materialized_views::ViewRegistry;
+//! use frozen_duckdb::Connection;
+//!
+//! let conn = Connection::open("analytics.duckdb")?;
+//! let views = ViewRegistry::new(&conn)?;
+//! views.define("daily_sales", "SELECT day, SUM(amount) AS total FROM 'sales/*.parquet' GROUP BY day")?;
+//! views.define("best_day", "SELECT * FROM daily_sales ORDER BY total DESC LIMIT 1")?;
+//!
+//! // Later, after new sales files arrive
+//! for (name, rows) in views.refresh_all()? {
+//!     println!("{}: {} rows", name, rows);
+//! }
+//! ```
+
+use super::dataset_manager::split_statements;
+use super::dedupe::quote_identifier;
+use anyhow::{Context, Result};
+use duckdb::{params, Connection};
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::{debug, info};
+
+/// Table holding the view definitions.
+pub const VIEWS_TABLE: &str = "frozen_duckdb_materialized_views";
+
+/// A stored materialized view definition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaterializedView {
+    /// Name of the table the view is materialized into
+    pub name: String,
+    /// `SELECT` statement the table is built from
+    pub sql: String,
+    /// When the view was last refreshed (`YYYY-MM-DD HH:MM:SS`), if ever
+    pub refreshed_at: Option<String>,
+    /// Rows in the table after the last refresh
+    pub row_count: Option<i64>,
+    /// Other materialized views the definition reads
+    pub depends_on: Vec<String>,
+}
+
+/// Materialized view definitions stored in one database.
+pub struct ViewRegistry<'conn> {
+    conn: &'conn Connection,
+}
+
+impl<'conn> ViewRegistry<'conn> {
+    /// Opens the registry of `conn`'s database, creating its metadata table
+    /// if needed.
+    pub fn new(conn: &'conn Connection) -> Result<Self> {
+        conn.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                name VARCHAR PRIMARY KEY,
+                sql VARCHAR NOT NULL,
+                defined_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+                refreshed_at TIMESTAMP,
+                row_count BIGINT
+            )",
+            VIEWS_TABLE
+        ))
+        .context("Failed to create materialized view metadata table")?;
+        Ok(Self { conn })
+    }
+
+    /// Defines (or redefines) a view and materializes it.
+    ///
+    /// Nothing is stored if the SQL fails or would create a dependency
+    /// cycle.
+    pub fn define(&self, name: &str, sql: &str) -> Result<usize> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("Materialized view name must not be empty"));
+        }
+        let sql = sql.trim().trim_end_matches(';').trim();
+        if split_statements(sql).len() != 1 {
+            return Err(anyhow::anyhow!(
+                "Materialized view {} must be defined by a single SELECT statement",
+                name
+            ));
+        }
+
+        self.conn.with_savepoint(|sp| {
+            sp.execute(
+                &format!(
+                    "INSERT OR REPLACE INTO {} (name, sql, defined_at) VALUES (?, ?, current_timestamp)",
+                    VIEWS_TABLE
+                ),
+                [name, sql],
+            )?;
+            // Fails on a cycle before anything is materialized
+            self.refresh_order()?;
+            let rows = materialize(sp, name, sql)?;
+            info!("✅ Defined materialized view {} ({} rows)", name, rows);
+            Ok(rows)
+        })
+    }
+
+    /// Removes a view's definition and drops its table.
+    pub fn drop_view(&self, name: &str) -> Result<()> {
+        let dependents: Vec<String> = self
+            .list()?
+            .into_iter()
+            .filter(|view| view.depends_on.iter().any(|d| d == name))
+            .map(|view| view.name)
+            .collect();
+        if !dependents.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Materialized view {} is read by {}; drop those first",
+                name,
+                dependents.join(", ")
+            ));
+        }
+
+        self.conn.with_savepoint(|sp| {
+            let removed = sp.execute(
+                &format!("DELETE FROM {} WHERE name = ?", VIEWS_TABLE),
+                [name],
+            )?;
+            if removed == 0 {
+                return Err(anyhow::anyhow!("No materialized view named {}", name));
+            }
+            sp.execute_batch(&format!("DROP TABLE IF EXISTS {}", quote_identifier(name)))?;
+            Ok(())
+        })
+    }
+
+    /// Returns all definitions, sorted by name.
+    pub fn list(&self) -> Result<Vec<MaterializedView>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT name, sql, strftime(refreshed_at, '%Y-%m-%d %H:%M:%S'), row_count
+             FROM {} ORDER BY name",
+            VIEWS_TABLE
+        ))?;
+        let mut views = stmt
+            .query_map([], |row| {
+                Ok(MaterializedView {
+                    name: row.get(0)?,
+                    sql: row.get(1)?,
+                    refreshed_at: row.get(2)?,
+                    row_count: row.get(3)?,
+                    depends_on: Vec::new(),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let names: Vec<String> = views.iter().map(|view| view.name.clone()).collect();
+        for view in &mut views {
+            view.depends_on = dependencies(&view.sql, &names, &view.name);
+        }
+        Ok(views)
+    }
+
+    /// Returns the definition of `name`.
+    pub fn get(&self, name: &str) -> Result<MaterializedView> {
+        self.list()?
+            .into_iter()
+            .find(|view| view.name == name)
+            .ok_or_else(|| anyhow::anyhow!("No materialized view named {}", name))
+    }
+
+    /// Returns view names in refresh order: every view after the views it
+    /// reads, ties broken by name.
+    pub fn refresh_order(&self) -> Result<Vec<String>> {
+        let mut pending: BTreeMap<String, BTreeSet<String>> = self
+            .list()?
+            .into_iter()
+            .map(|view| (view.name, view.depends_on.into_iter().collect()))
+            .collect();
+
+        let mut order = Vec::with_capacity(pending.len());
+        while !pending.is_empty() {
+            let ready: Vec<String> = pending
+                .iter()
+                .filter(|(_, deps)| deps.is_empty())
+                .map(|(name, _)| name.clone())
+                .collect();
+            if ready.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Materialized views depend on each other in a cycle: {}",
+                    pending.keys().cloned().collect::<Vec<_>>().join(", ")
+                ));
+            }
+            for name in &ready {
+                pending.remove(name);
+            }
+            for deps in pending.values_mut() {
+                deps.retain(|dep| !ready.contains(dep));
+            }
+            order.extend(ready);
+        }
+        Ok(order)
+    }
+
+    /// Rebuilds one view from its definition and returns its row count.
+    ///
+    /// Views it reads are not refreshed first; use
+    /// [`ViewRegistry::refresh_all`] for that.
+    pub fn refresh(&self, name: &str) -> Result<usize> {
+        let view = self.get(name)?;
+        self.conn
+            .with_savepoint(|sp| materialize(sp, &view.name, &view.sql))
+    }
+
+    /// Rebuilds every view in dependency order, in one transaction, and
+    /// returns each view's row count.
+    ///
+    /// If any view fails, none of the refreshes are kept, so readers never
+    /// see a view built from a half-refreshed dependency.
+    pub fn refresh_all(&self) -> Result<Vec<(String, usize)>> {
+        let order = self.refresh_order()?;
+        let views: BTreeMap<String, MaterializedView> = self
+            .list()?
+            .into_iter()
+            .map(|view| (view.name.clone(), view))
+            .collect();
+
+        self.conn.with_savepoint(|sp| {
+            order
+                .iter()
+                .map(|name| {
+                    let rows = materialize(sp, name, &views[name].sql)?;
+                    Ok((name.clone(), rows))
+                })
+                .collect()
+        })
+    }
+}
+
+/// Replaces the view's table with the result of `sql` and records the
+/// refresh.
+fn materialize(conn: &Connection, name: &str, sql: &str) -> Result<usize> {
+    conn.execute_batch(&format!(
+        "CREATE OR REPLACE TABLE {} AS {}",
+        quote_identifier(name),
+        sql
+    ))
+    .with_context(|| format!("Failed to refresh materialized view {}", name))?;
+
+    let rows: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM {}", quote_identifier(name)),
+        [],
+        |row| row.get(0),
+    )?;
+    conn.execute(
+        &format!(
+            "UPDATE {} SET refreshed_at = current_timestamp, row_count = ? WHERE name = ?",
+            VIEWS_TABLE
+        ),
+        params![rows, name],
+    )?;
+    debug!("Refreshed materialized view {} ({} rows)", name, rows);
+    Ok(rows as usize)
+}
+
+/// Names from `views` (other than `own`) that appear as identifiers in `sql`.
+fn dependencies(sql: &str, views: &[String], own: &str) -> Vec<String> {
+    let identifiers: BTreeSet<String> = sql
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    views
+        .iter()
+        .filter(|view| view.as_str() != own && identifiers.contains(&view.to_lowercase()))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(conn: &Connection) -> Result<()> {
+        conn.execute_batch(
+            "CREATE TABLE sales (day DATE, amount INTEGER);
+             INSERT INTO sales VALUES ('2024-01-01', 10), ('2024-01-01', 5), ('2024-01-02', 7);",
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_define_list_and_refresh() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        setup(&conn)?;
+        let views = ViewRegistry::new(&conn)?;
+
+        let rows = views.define(
+            "daily",
+            "SELECT day, SUM(amount) AS total FROM sales GROUP BY day;",
+        )?;
+        assert_eq!(rows, 2);
+        views.define(
+            "best_day",
+            "SELECT * FROM daily ORDER BY total DESC LIMIT 1",
+        )?;
+
+        let listed = views.list()?;
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].name, "best_day");
+        assert_eq!(listed[0].depends_on, ["daily"]);
+        assert!(listed[1].refreshed_at.is_some());
+        assert_eq!(views.refresh_order()?, ["daily", "best_day"]);
+
+        conn.execute_batch("INSERT INTO sales VALUES ('2024-01-03', 100)")?;
+        let refreshed = views.refresh_all()?;
+        assert_eq!(
+            refreshed,
+            [("daily".to_string(), 3), ("best_day".to_string(), 1)]
+        );
+        let best: i64 = conn.query_row("SELECT total FROM best_day", [], |row| row.get(0))?;
+        assert_eq!(best, 100);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_definitions_are_not_stored() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        setup(&conn)?;
+        let views = ViewRegistry::new(&conn)?;
+
+        assert!(views
+            .define("broken", "SELECT * FROM missing_table")
+            .is_err());
+        assert!(views.define("two", "SELECT 1; SELECT 2").is_err());
+        assert!(views.list()?.is_empty());
+
+        views.define("a", "SELECT 1 AS x")?;
+        views.define("b", "SELECT * FROM a")?;
+        assert!(views.define("a", "SELECT * FROM b").is_err());
+        assert_eq!(views.get("a")?.sql, "SELECT 1 AS x");
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_view_checks_dependents() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        let views = ViewRegistry::new(&conn)?;
+        views.define("a", "SELECT 1 AS x")?;
+        views.define("b", "SELECT * FROM a")?;
+
+        assert!(views.drop_view("a").is_err());
+        views.drop_view("b")?;
+        views.drop_view("a")?;
+        assert!(views.list()?.is_empty());
+        assert!(views.drop_view("a").is_err());
+        assert!(conn.execute_batch("SELECT * FROM a").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dependencies_match_whole_identifiers() {
+        let names = vec!["daily".to_string(), "day".to_string(), "weekly".to_string()];
+        assert_eq!(
+            dependencies("SELECT d.day FROM \"Daily\" d", &names, "weekly"),
+            ["daily", "day"]
+        );
+        assert!(dependencies("SELECT * FROM daily_sales", &names, "weekly").is_empty());
+    }
+}
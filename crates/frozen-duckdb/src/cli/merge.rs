@@ -0,0 +1,510 @@
+//! # Upserts and Merges
+//!
+//! Writing a correct upsert by hand means listing every column twice,
+//! getting the conflict target right, and remembering which tables have a
+//! key constraint. [`merge_into`] generates the statement from the key
+//! columns and what to do with matched and unmatched rows:
+//!
+//! | `when_matched` | Rows of the target whose keys appear in the source |
+//! |----------------|-----------------------------------------------------|
+//! | `update` | Updated with the source's values (default) |
+//! | `ignore` | Left as they are |
+//! | `delete` | Deleted |
+//!
+//! | `when_not_matched` | Source rows whose keys aren't in the target |
+//! |--------------------|---------------------------------------------|
+//! | `insert` | Inserted (default) |
+//! | `ignore` | Skipped |
+//!
+//! ## Generated SQL
+//!
+//! Upserts (`update` or `ignore` with `insert`) into a table with a
+//! `PRIMARY KEY` or `UNIQUE` constraint on exactly the key columns use
+//! `INSERT ... ON CONFLICT`, which uses the constraint's index. Every
+//! other combination, and tables without such a constraint, use
+//! `MERGE INTO`.
+//!
+//! The source is read once into a temporary table, and must not contain a
+//! key twice: which of the duplicates wins would be arbitrary.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use duckdb::Connection;
+//! use frozen_duckdb::cli::merge::{merge_into, MergeOptions};
+//!
+//! let conn = Connection::open("shop.duckdb")?;
+//! let options = MergeOptions {
+//!     key_columns: vec!["id".to_string()],
+//!     ..Default::default()
+//! };
+//! let report = merge_into(&conn, "orders", "read_csv('updates.csv')", &options)?;
+//! println!("{} inserted, {} updated", report.inserted, report.updated);
+//! ```
+
+use super::dedupe::{copy_format, quote_identifier, read_function};
+use super::sql_path::path_literal;
+use anyhow::{Context, Result};
+use duckdb::Connection;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+/// What to do with target rows whose keys appear in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WhenMatched {
+    /// Overwrite them with the source's values
+    #[default]
+    Update,
+    /// Keep them unchanged
+    Ignore,
+    /// Delete them
+    Delete,
+}
+
+impl WhenMatched {
+    /// Parses `update`, `ignore`, or `delete`.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "update" => Ok(Self::Update),
+            "ignore" | "nothing" => Ok(Self::Ignore),
+            "delete" => Ok(Self::Delete),
+            other => Err(anyhow::anyhow!(
+                "Unknown --when-matched action: {} (use update, ignore, or delete)",
+                other
+            )),
+        }
+    }
+
+    /// Returns the action name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Update => "update",
+            Self::Ignore => "ignore",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+/// What to do with source rows whose keys aren't in the target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WhenNotMatched {
+    /// Insert them
+    #[default]
+    Insert,
+    /// Skip them
+    Ignore,
+}
+
+impl WhenNotMatched {
+    /// Parses `insert` or `ignore`.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "insert" => Ok(Self::Insert),
+            "ignore" | "nothing" => Ok(Self::Ignore),
+            other => Err(anyhow::anyhow!(
+                "Unknown --when-not-matched action: {} (use insert or ignore)",
+                other
+            )),
+        }
+    }
+
+    /// Returns the action name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Insert => "insert",
+            Self::Ignore => "ignore",
+        }
+    }
+}
+
+/// Options of a [`merge_into`].
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptions {
+    /// Columns matching source rows to target rows
+    pub key_columns: Vec<String>,
+    /// Action for matched rows
+    pub when_matched: WhenMatched,
+    /// Action for unmatched source rows
+    pub when_not_matched: WhenNotMatched,
+}
+
+/// Outcome of a [`merge_into`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    /// Rows in the source
+    pub source_rows: usize,
+    /// Rows inserted into the target
+    pub inserted: usize,
+    /// Target rows updated
+    pub updated: usize,
+    /// Target rows deleted
+    pub deleted: usize,
+    /// Whether `INSERT ... ON CONFLICT` was used rather than `MERGE INTO`
+    pub on_conflict: bool,
+}
+
+/// Merges the rows of `source` into the table `target` on the key columns.
+///
+/// `source` is anything that can follow `FROM`: a table name or a table
+/// function such as `read_parquet('updates.parquet')`. `target` may be
+/// qualified, e.g. `warehouse.main.orders`.
+pub fn merge_into(
+    conn: &Connection,
+    target: &str,
+    source: &str,
+    options: &MergeOptions,
+) -> Result<MergeReport> {
+    if options.key_columns.is_empty() {
+        return Err(anyhow::anyhow!("At least one key column is required"));
+    }
+    let target_name = qualified_name(target);
+    let keys = column_list(&options.key_columns);
+
+    conn.execute_batch(&format!(
+        "CREATE OR REPLACE TEMP TABLE merge_source AS SELECT * FROM {};",
+        source
+    ))
+    .with_context(|| format!("Failed to read merge source {}", source))?;
+    let columns = source_columns(conn)?;
+    for key in &options.key_columns {
+        if !columns.contains(key) {
+            return Err(anyhow::anyhow!(
+                "Key column {} not found in {} (columns: {})",
+                key,
+                source,
+                columns.join(", ")
+            ));
+        }
+    }
+    let duplicates = count(
+        conn,
+        &format!(
+            "SELECT count(*) FROM (SELECT {0} FROM merge_source GROUP BY {0} HAVING count(*) > 1)",
+            keys
+        ),
+    )?;
+    if duplicates > 0 {
+        return Err(anyhow::anyhow!(
+            "{} keys appear more than once in {}; deduplicate it first (frozen-duckdb dedupe)",
+            duplicates,
+            source
+        ));
+    }
+
+    let source_rows = count(conn, "SELECT count(*) FROM merge_source")?;
+    let matched = count(
+        conn,
+        &format!(
+            "SELECT count(*) FROM merge_source source SEMI JOIN {} target ON {}",
+            target_name,
+            key_condition(&options.key_columns)
+        ),
+    )
+    .with_context(|| format!("Failed to read merge target {}", target))?;
+
+    let on_conflict = matches!(
+        options.when_matched,
+        WhenMatched::Update | WhenMatched::Ignore
+    ) && options.when_not_matched == WhenNotMatched::Insert
+        && has_key_constraint(conn, target, &options.key_columns)?;
+    let sql = if on_conflict {
+        upsert_sql(
+            &target_name,
+            "merge_source",
+            &columns,
+            &options.key_columns,
+            options.when_matched == WhenMatched::Update,
+        )
+    } else {
+        merge_sql(&target_name, &columns, options)
+    };
+    conn.execute_batch(&sql)
+        .with_context(|| format!("Failed to merge {} into {}", source, target))?;
+    conn.execute_batch("DROP TABLE merge_source;")?;
+
+    let report = MergeReport {
+        source_rows,
+        inserted: match options.when_not_matched {
+            WhenNotMatched::Insert => source_rows - matched,
+            WhenNotMatched::Ignore => 0,
+        },
+        updated: if options.when_matched == WhenMatched::Update {
+            matched
+        } else {
+            0
+        },
+        deleted: if options.when_matched == WhenMatched::Delete {
+            matched
+        } else {
+            0
+        },
+        on_conflict,
+    };
+    info!(
+        "🔀 Merged {} rows into {}: {} inserted, {} updated, {} deleted",
+        report.source_rows, target, report.inserted, report.updated, report.deleted
+    );
+    Ok(report)
+}
+
+/// Merges `source` into the CSV, Parquet, or JSON file `target`, which is
+/// rewritten in place.
+pub fn merge_into_file(
+    conn: &Connection,
+    target: &str,
+    source: &str,
+    options: &MergeOptions,
+) -> Result<MergeReport> {
+    let format = copy_format(target)?;
+    conn.execute_batch(&format!(
+        "CREATE OR REPLACE TEMP TABLE merge_target AS SELECT * FROM {};",
+        read_function(target)?
+    ))
+    .with_context(|| format!("Failed to read merge target {}", target))?;
+    let report = merge_into(conn, "merge_target", source, options)?;
+
+    // Write next to the target and rename, so a failed write keeps it intact
+    let staging = format!("{}.merge", target);
+    conn.execute_batch(&format!(
+        "COPY merge_target TO {} ({}); DROP TABLE merge_target;",
+        path_literal(&staging),
+        format
+    ))
+    .with_context(|| format!("Failed to write {}", target))?;
+    fs::rename(&staging, target)
+        .with_context(|| format!("Failed to replace {}", Path::new(target).display()))?;
+    Ok(report)
+}
+
+/// Whether `target` names a file [`merge_into_file`] can rewrite rather
+/// than a table.
+pub fn is_file_target(target: &str) -> bool {
+    copy_format(target).is_ok()
+}
+
+/// Quotes each part of a possibly qualified table name.
+pub(crate) fn qualified_name(name: &str) -> String {
+    name.split('.')
+        .map(quote_identifier)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// `INSERT ... ON CONFLICT` of all of `source`'s `columns` into `target`,
+/// updating or keeping rows whose keys exist. `target` and `source` are
+/// used as given.
+fn upsert_sql(
+    target: &str,
+    source: &str,
+    columns: &[String],
+    keys: &[String],
+    update: bool,
+) -> String {
+    let updates: Vec<String> = columns
+        .iter()
+        .filter(|name| !keys.contains(name))
+        .map(|name| format!("{0} = EXCLUDED.{0}", quote_identifier(name)))
+        .collect();
+    let action = if update && !updates.is_empty() {
+        format!("UPDATE SET {}", updates.join(", "))
+    } else {
+        "NOTHING".to_string()
+    };
+    format!(
+        "INSERT INTO {0} ({1}) SELECT {1} FROM {2} ON CONFLICT ({3}) DO {4};",
+        target,
+        column_list(columns),
+        source,
+        column_list(keys),
+        action
+    )
+}
+
+/// `MERGE INTO` of `merge_source` into `target`.
+fn merge_sql(target: &str, columns: &[String], options: &MergeOptions) -> String {
+    let mut sql = format!(
+        "MERGE INTO {} AS target USING merge_source AS source ON ({})",
+        target,
+        key_condition(&options.key_columns)
+    );
+    let updates: Vec<String> = columns
+        .iter()
+        .filter(|name| !options.key_columns.contains(name))
+        .map(|name| format!("{0} = source.{0}", quote_identifier(name)))
+        .collect();
+    match options.when_matched {
+        WhenMatched::Update if !updates.is_empty() => {
+            sql.push_str(&format!(
+                " WHEN MATCHED THEN UPDATE SET {}",
+                updates.join(", ")
+            ));
+        }
+        WhenMatched::Update | WhenMatched::Ignore => {}
+        WhenMatched::Delete => sql.push_str(" WHEN MATCHED THEN DELETE"),
+    }
+    if options.when_not_matched == WhenNotMatched::Insert {
+        let values: Vec<String> = columns
+            .iter()
+            .map(|name| format!("source.{}", quote_identifier(name)))
+            .collect();
+        sql.push_str(&format!(
+            " WHEN NOT MATCHED THEN INSERT ({}) VALUES ({})",
+            column_list(columns),
+            values.join(", ")
+        ));
+    }
+    sql.push(';');
+    sql
+}
+
+fn column_list(columns: &[String]) -> String {
+    columns
+        .iter()
+        .map(|name| quote_identifier(name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn key_condition(keys: &[String]) -> String {
+    keys.iter()
+        .map(|key| format!("target.{0} = source.{0}", quote_identifier(key)))
+        .collect::<Vec<_>>()
+        .join(" AND ")
+}
+
+fn source_columns(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT column_name FROM (DESCRIBE merge_source)")?;
+    let columns = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<duckdb::Result<Vec<String>>>()?;
+    Ok(columns)
+}
+
+fn count(conn: &Connection, sql: &str) -> Result<usize> {
+    let count: i64 = conn.query_row(sql, [], |row| row.get(0))?;
+    Ok(count as usize)
+}
+
+/// Whether `target` has a `PRIMARY KEY` or `UNIQUE` constraint on exactly
+/// `keys`, which `ON CONFLICT` needs.
+fn has_key_constraint(conn: &Connection, target: &str, keys: &[String]) -> Result<bool> {
+    let table = target.rsplit('.').next().unwrap_or(target);
+    let mut stmt = conn.prepare(
+        "SELECT array_to_string(list_sort(constraint_column_names), ',') \
+         FROM duckdb_constraints() \
+         WHERE table_name = ? AND constraint_type IN ('PRIMARY KEY', 'UNIQUE')",
+    )?;
+    let constraints = stmt
+        .query_map([table], |row| row.get(0))?
+        .collect::<duckdb::Result<Vec<String>>>()?;
+    let mut sorted = keys.to_vec();
+    sorted.sort();
+    Ok(constraints.contains(&sorted.join(",")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns() -> Vec<String> {
+        ["id", "status"].iter().map(|c| c.to_string()).collect()
+    }
+
+    fn options(when_matched: WhenMatched, when_not_matched: WhenNotMatched) -> MergeOptions {
+        MergeOptions {
+            key_columns: vec!["id".to_string()],
+            when_matched,
+            when_not_matched,
+        }
+    }
+
+    #[test]
+    fn test_upsert_sql() {
+        let keys = vec!["id".to_string()];
+        assert_eq!(
+            upsert_sql("\"orders\"", "merge_source", &columns(), &keys, true),
+            "INSERT INTO \"orders\" (\"id\", \"status\") SELECT \"id\", \"status\" FROM merge_source \
+             ON CONFLICT (\"id\") DO UPDATE SET \"status\" = EXCLUDED.\"status\";"
+        );
+        assert!(upsert_sql("t", "s", &columns(), &keys, false).ends_with("DO NOTHING;"));
+    }
+
+    #[test]
+    fn test_merge_sql() {
+        let target = qualified_name("shop.orders");
+        assert_eq!(target, "\"shop\".\"orders\"");
+        assert_eq!(
+            merge_sql(
+                &target,
+                &columns(),
+                &options(WhenMatched::Update, WhenNotMatched::Insert)
+            ),
+            "MERGE INTO \"shop\".\"orders\" AS target USING merge_source AS source \
+             ON (target.\"id\" = source.\"id\") \
+             WHEN MATCHED THEN UPDATE SET \"status\" = source.\"status\" \
+             WHEN NOT MATCHED THEN INSERT (\"id\", \"status\") VALUES (source.\"id\", source.\"status\");"
+        );
+        assert_eq!(
+            merge_sql(
+                "t",
+                &columns(),
+                &options(WhenMatched::Delete, WhenNotMatched::Ignore)
+            ),
+            "MERGE INTO t AS target USING merge_source AS source \
+             ON (target.\"id\" = source.\"id\") WHEN MATCHED THEN DELETE;"
+        );
+    }
+
+    #[test]
+    fn test_parse_actions() {
+        assert_eq!(WhenMatched::parse("delete").unwrap(), WhenMatched::Delete);
+        assert_eq!(
+            WhenNotMatched::parse("ignore").unwrap(),
+            WhenNotMatched::Ignore
+        );
+        assert!(WhenMatched::parse("upsert").is_err());
+        assert!(is_file_target("orders.parquet"));
+        assert!(!is_file_target("shop.orders"));
+    }
+
+    #[test]
+    fn test_merge_into() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE keyed (id INTEGER PRIMARY KEY, status VARCHAR);
+             CREATE TABLE plain (id INTEGER, status VARCHAR);
+             INSERT INTO keyed VALUES (1, 'new'), (2, 'new');
+             INSERT INTO plain VALUES (1, 'new'), (2, 'new');
+             CREATE TABLE updates AS SELECT * FROM (VALUES (2, 'shipped'), (3, 'new')) t(id, status);",
+        )
+        .unwrap();
+
+        let upsert = options(WhenMatched::Update, WhenNotMatched::Insert);
+        let keyed = merge_into(&conn, "keyed", "updates", &upsert).unwrap();
+        assert!(keyed.on_conflict);
+        let plain = merge_into(&conn, "plain", "updates", &upsert).unwrap();
+        assert!(!plain.on_conflict);
+        for (report, table) in [(keyed, "keyed"), (plain, "plain")] {
+            assert_eq!((report.inserted, report.updated), (1, 1));
+            let shipped: String = conn
+                .query_row(
+                    &format!("SELECT string_agg(status, ',' ORDER BY id) FROM {}", table),
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(shipped, "new,shipped,new");
+        }
+
+        let delete = options(WhenMatched::Delete, WhenNotMatched::Ignore);
+        let report = merge_into(&conn, "keyed", "updates", &delete).unwrap();
+        assert_eq!(report.deleted, 2);
+        assert_eq!(count(&conn, "SELECT count(*) FROM keyed").unwrap(), 1);
+
+        conn.execute_batch("INSERT INTO updates VALUES (3, 'cancelled')")
+            .unwrap();
+        let error = merge_into(&conn, "plain", "updates", &upsert).unwrap_err();
+        assert!(error.to_string().contains("more than once"), "{}", error);
+    }
+}
@@ -0,0 +1,208 @@
+//! # Metadata Filters for Semantic Search
+//!
+//! Every indexed document carries key/value metadata in the index's
+//! `document_metadata` table: where it came from (`file`, `format`,
+//! `modified`), what the [loaders](super::loaders) found (`title`,
+//! `heading`, `page`), and whatever `index --metadata key=value` added.
+//! `search --filter` restricts the ranking to documents whose metadata
+//! matches:
+//!
+//! | Filter | Matches documents |
+//! |--------|-------------------|
+//! | `source=manual` | With `source` equal to `manual`; repeating `=` for one key matches any of the values |
+//! | `source!=faq` | Without `source` equal to `faq`, including those without a `source` |
+//! | `modified>=2024-01-01` | With `modified` on or after 2024-01-01 (also `>`, `<`, `<=`) |
+//! | `page<10` | With `page` below 10, compared as numbers since `10` is one |
+//!
+//! Filters on different keys must all match. They become `WHERE` clauses
+//! of the similarity query, so the limit applies to matching documents.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use frozen_duckdb::cli::metadata_filter::{MetadataFilter, FilterOp};
+//!
+//! let filter = MetadataFilter::parse("modified>=2024-01-01")?;
+//! assert_eq!(filter.key, "modified");
+//! assert_eq!(filter.op, FilterOp::Ge);
+//! assert_eq!(filter.value, "2024-01-01");
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// Comparison of a metadata filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOp {
+    /// `=`
+    Eq,
+    /// `!=`
+    Ne,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+}
+
+impl FilterOp {
+    /// Operators by how they are written, two-character ones first so
+    /// `>=` isn't read as `>`.
+    const ALL: [(&'static str, FilterOp); 6] = [
+        ("!=", FilterOp::Ne),
+        (">=", FilterOp::Ge),
+        ("<=", FilterOp::Le),
+        ("=", FilterOp::Eq),
+        (">", FilterOp::Gt),
+        ("<", FilterOp::Lt),
+    ];
+
+    /// Returns the operator as written in filters and SQL.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Eq => "=",
+            Self::Ne => "!=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+        }
+    }
+}
+
+/// A condition on one metadata key, e.g. `source=manual`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataFilter {
+    /// Metadata key
+    pub key: String,
+    /// Comparison
+    pub op: FilterOp,
+    /// Value compared with, as a number when it is one
+    pub value: String,
+}
+
+impl MetadataFilter {
+    /// Parses `KEY=VALUE`, or `KEY` followed by `!=`, `>`, `>=`, `<`, or
+    /// `<=` and a value.
+    pub fn parse(value: &str) -> Result<Self> {
+        let (position, text, op) = FilterOp::ALL
+            .iter()
+            .filter_map(|(text, op)| value.find(text).map(|position| (position, *text, *op)))
+            // The first operator in the filter; at a tie, the longer one
+            .min_by_key(|(position, text, _)| (*position, usize::MAX - text.len()))
+            .ok_or_else(|| {
+                anyhow::anyhow!("Expected KEY=VALUE (or !=, >, >=, <, <=), got '{}'", value)
+            })?;
+        let key = value[..position].trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            anyhow::bail!("Invalid metadata key '{}' in filter '{}'", key, value);
+        }
+        Ok(Self {
+            key: key.to_string(),
+            op,
+            value: value[position + text.len()..].trim().to_string(),
+        })
+    }
+}
+
+/// Builds the `WHERE` condition matching `filters` for documents whose id
+/// is `doc_id_column`, with its parameters in order. `TRUE` without
+/// filters.
+pub fn filter_sql(filters: &[MetadataFilter], doc_id_column: &str) -> (String, Vec<String>) {
+    let mut conditions = Vec::new();
+    let mut params = Vec::new();
+    let exists = |condition: &str| {
+        format!(
+            "EXISTS (SELECT 1 FROM document_metadata m WHERE m.doc_id = {} AND m.key = ? AND {})",
+            doc_id_column, condition
+        )
+    };
+
+    // Equality filters on one key are alternatives
+    let mut equal: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for filter in filters.iter().filter(|f| f.op == FilterOp::Eq) {
+        equal.entry(&filter.key).or_default().push(&filter.value);
+    }
+    for (key, values) in equal {
+        let placeholders = vec!["?"; values.len()].join(", ");
+        conditions.push(exists(&format!("m.value IN ({})", placeholders)));
+        params.push(key.to_string());
+        params.extend(values.iter().map(|v| v.to_string()));
+    }
+
+    for filter in filters.iter().filter(|f| f.op != FilterOp::Eq) {
+        let condition = match filter.op {
+            FilterOp::Ne => format!("NOT {}", exists("m.value = ?")),
+            op if filter.value.parse::<f64>().is_ok() => exists(&format!(
+                "TRY_CAST(m.value AS DOUBLE) {} CAST(? AS DOUBLE)",
+                op.as_str()
+            )),
+            op => exists(&format!("m.value {} ?", op.as_str())),
+        };
+        conditions.push(condition);
+        params.push(filter.key.clone());
+        params.push(filter.value.clone());
+    }
+
+    if conditions.is_empty() {
+        ("TRUE".to_string(), params)
+    } else {
+        (conditions.join(" AND "), params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_filters() {
+        let filter = MetadataFilter::parse("source=manual").unwrap();
+        assert_eq!((filter.key.as_str(), filter.op), ("source", FilterOp::Eq));
+        assert_eq!(filter.value, "manual");
+
+        assert_eq!(MetadataFilter::parse("a!=b").unwrap().op, FilterOp::Ne);
+        assert_eq!(MetadataFilter::parse("page <= 3").unwrap().op, FilterOp::Le);
+        assert_eq!(MetadataFilter::parse("page>3").unwrap().op, FilterOp::Gt);
+
+        // Only the first operator counts; the value may contain more
+        let filter = MetadataFilter::parse("title=a=b").unwrap();
+        assert_eq!(filter.value, "a=b");
+
+        assert!(MetadataFilter::parse("source").is_err());
+        assert!(MetadataFilter::parse("=manual").is_err());
+        assert!(MetadataFilter::parse("my key=x").is_err());
+    }
+
+    #[test]
+    fn test_filter_sql() {
+        assert_eq!(filter_sql(&[], "e.doc_id"), ("TRUE".to_string(), vec![]));
+
+        let filters: Vec<MetadataFilter> =
+            ["source=manual", "source=faq", "page<10", "year>=2024x"]
+                .iter()
+                .map(|f| MetadataFilter::parse(f).unwrap())
+                .collect();
+        let (sql, params) = filter_sql(&filters, "e.doc_id");
+        assert_eq!(
+            sql,
+            "EXISTS (SELECT 1 FROM document_metadata m WHERE m.doc_id = e.doc_id AND m.key = ? \
+             AND m.value IN (?, ?)) AND \
+             EXISTS (SELECT 1 FROM document_metadata m WHERE m.doc_id = e.doc_id AND m.key = ? \
+             AND TRY_CAST(m.value AS DOUBLE) < CAST(? AS DOUBLE)) AND \
+             EXISTS (SELECT 1 FROM document_metadata m WHERE m.doc_id = e.doc_id AND m.key = ? \
+             AND m.value >= ?)"
+        );
+        assert_eq!(
+            params,
+            vec!["source", "manual", "faq", "page", "10", "year", "2024x"]
+        );
+
+        let (sql, _) = filter_sql(&[MetadataFilter::parse("source!=faq").unwrap()], "doc_id");
+        assert!(sql.starts_with("NOT EXISTS"));
+    }
+}
@@ -0,0 +1,329 @@
+//! # Prometheus Metrics
+//!
+//! `frozen-duckdb serve` answers `GET /metrics` with the counters below in
+//! the Prometheus text format, so the server can be scraped like any other
+//! service. `--metrics-listen` serves them on a separate address as well,
+//! which is the only way to scrape `--protocol postgres`.
+//!
+//! | Metric | Type | Labels | Description |
+//! |--------|------|--------|-------------|
+//! | `frozen_duckdb_queries_total` | counter | `protocol`, `status` | Queries run, by outcome (`ok`, `error`) |
+//! | `frozen_duckdb_query_duration_seconds` | histogram | `protocol` | Time to run a query and build its result |
+//! | `frozen_duckdb_active_connections` | gauge | `protocol` | Open client connections |
+//! | `frozen_duckdb_cache_lookups_total` | counter | `cache`, `result` | Query and LLM response cache lookups (`hit`, `miss`) |
+//! | `frozen_duckdb_cache_hit_ratio` | gauge | `cache` | Hits divided by lookups since startup |
+//! | `frozen_duckdb_llm_calls_total` | counter | `model`, `source` | LLM responses by where they came from (`model`, `cache`, `replay`) |
+//!
+//! Metrics are process-wide: cache and LLM counters are recorded wherever
+//! [`DatasetManager`](super::DatasetManager) and
+//! [`FlockManager`](super::FlockManager) run, so an application embedding
+//! the server alongside its own model calls exports both. Labels never
+//! contain SQL or data, which is why `/metrics`, like `/healthz`, doesn't
+//! require the bearer token.
+//!
+//! ## Usage Examples
+//!
+//! ```bash
+//! frozen-duckdb serve --database analytics.duckdb --cache
+//! curl http://127.0.0.1:8080/metrics
+//!
+//! # Scrape a PostgreSQL-protocol server on its own port
+//! frozen-duckdb serve --database analytics.duckdb --protocol postgres --metrics-listen 127.0.0.1:9187
+//! ```
+//!
+//! # Examples
+//!
+//! ```rust
+//! use frozen_duckdb::cli::metrics::{CacheKind, Metrics};
+//! use std::time::Duration;
+//!
+//! let metrics = Metrics::new()?;
+//! metrics.observe_query("http", Duration::from_millis(12), true);
+//! metrics.record_cache_lookup(CacheKind::Query, true);
+//!
+//! let text = metrics.encode()?;
+//! assert!(text.contains(r#"frozen_duckdb_queries_total{protocol="http",status="ok"} 1"#));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{anyhow, Context, Result};
+use prometheus::{
+    Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Header, Response, Server};
+use tracing::{info, warn};
+
+/// `Content-Type` of the Prometheus text format.
+pub const METRICS_CONTENT_TYPE: &str = prometheus::TEXT_FORMAT;
+
+/// Upper bounds of the query latency buckets, in seconds.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// A cache whose lookups are counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheKind {
+    /// The query result cache of `query --cache` and `serve --cache`
+    Query,
+    /// The LLM response cache
+    Response,
+}
+
+impl CacheKind {
+    /// Returns the `cache` label value.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Query => "query",
+            Self::Response => "response",
+        }
+    }
+}
+
+/// Where an LLM response came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LlmSource {
+    /// Generated by the model
+    Model,
+    /// Served from the response cache
+    Cache,
+    /// Replayed from a recording
+    Replay,
+}
+
+impl LlmSource {
+    /// Returns the `source` label value.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Model => "model",
+            Self::Cache => "cache",
+            Self::Replay => "replay",
+        }
+    }
+}
+
+/// The metrics registry of a process.
+pub struct Metrics {
+    registry: Registry,
+    queries: IntCounterVec,
+    query_duration: HistogramVec,
+    active_connections: IntGaugeVec,
+    cache_lookups: IntCounterVec,
+    cache_hit_ratio: GaugeVec,
+    llm_calls: IntCounterVec,
+}
+
+impl Metrics {
+    /// Creates an empty registry with every metric registered.
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new();
+        let queries = IntCounterVec::new(
+            Opts::new("frozen_duckdb_queries_total", "Queries run, by outcome"),
+            &["protocol", "status"],
+        )?;
+        let query_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "frozen_duckdb_query_duration_seconds",
+                "Time to run a query and build its result",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+            &["protocol"],
+        )?;
+        let active_connections = IntGaugeVec::new(
+            Opts::new(
+                "frozen_duckdb_active_connections",
+                "Open client connections",
+            ),
+            &["protocol"],
+        )?;
+        let cache_lookups = IntCounterVec::new(
+            Opts::new(
+                "frozen_duckdb_cache_lookups_total",
+                "Cache lookups, by result",
+            ),
+            &["cache", "result"],
+        )?;
+        let cache_hit_ratio = GaugeVec::new(
+            Opts::new(
+                "frozen_duckdb_cache_hit_ratio",
+                "Cache hits divided by lookups since startup",
+            ),
+            &["cache"],
+        )?;
+        let llm_calls = IntCounterVec::new(
+            Opts::new(
+                "frozen_duckdb_llm_calls_total",
+                "LLM responses, by where they came from",
+            ),
+            &["model", "source"],
+        )?;
+
+        registry.register(Box::new(queries.clone()))?;
+        registry.register(Box::new(query_duration.clone()))?;
+        registry.register(Box::new(active_connections.clone()))?;
+        registry.register(Box::new(cache_lookups.clone()))?;
+        registry.register(Box::new(cache_hit_ratio.clone()))?;
+        registry.register(Box::new(llm_calls.clone()))?;
+
+        Ok(Self {
+            registry,
+            queries,
+            query_duration,
+            active_connections,
+            cache_lookups,
+            cache_hit_ratio,
+            llm_calls,
+        })
+    }
+
+    /// Returns the registry shared by the whole process.
+    pub fn global() -> &'static Metrics {
+        static GLOBAL: OnceLock<Metrics> = OnceLock::new();
+        GLOBAL.get_or_init(|| Metrics::new().expect("metric definitions are valid"))
+    }
+
+    /// Counts a query served over `protocol` and observes its latency.
+    pub fn observe_query(&self, protocol: &str, elapsed: Duration, ok: bool) {
+        let status = if ok { "ok" } else { "error" };
+        self.queries.with_label_values(&[protocol, status]).inc();
+        self.query_duration
+            .with_label_values(&[protocol])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Counts an active connection over `protocol` until the returned
+    /// guard is dropped.
+    pub fn connection(&self, protocol: &str) -> ConnectionGuard {
+        let gauge = self.active_connections.with_label_values(&[protocol]);
+        gauge.inc();
+        ConnectionGuard { gauge }
+    }
+
+    /// Counts a lookup in `cache`.
+    pub fn record_cache_lookup(&self, cache: CacheKind, hit: bool) {
+        let result = if hit { "hit" } else { "miss" };
+        self.cache_lookups
+            .with_label_values(&[cache.as_str(), result])
+            .inc();
+    }
+
+    /// Counts an LLM response for `model`.
+    pub fn record_llm_call(&self, model: &str, source: LlmSource) {
+        self.llm_calls
+            .with_label_values(&[model, source.as_str()])
+            .inc();
+    }
+
+    /// Renders every metric in the Prometheus text format.
+    pub fn encode(&self) -> Result<String> {
+        for cache in [CacheKind::Query, CacheKind::Response] {
+            let count = |result: &str| {
+                self.cache_lookups
+                    .get_metric_with_label_values(&[cache.as_str(), result])
+                    .map(|counter| counter.get())
+                    .unwrap_or_default()
+            };
+            let (hits, misses) = (count("hit"), count("miss"));
+            if hits + misses > 0 {
+                self.cache_hit_ratio
+                    .with_label_values(&[cache.as_str()])
+                    .set(hits as f64 / (hits + misses) as f64);
+            }
+        }
+
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .context("Failed to encode metrics")?;
+        String::from_utf8(buffer).context("Metrics are not UTF-8")
+    }
+}
+
+/// Decrements the active connections gauge when dropped.
+pub struct ConnectionGuard {
+    gauge: prometheus::IntGauge,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.gauge.dec();
+    }
+}
+
+/// Serves `GET /metrics` from [`Metrics::global`] on `listen` in a
+/// background thread.
+pub fn spawn_listener(listen: &str) -> Result<()> {
+    let http = Server::http(listen)
+        .map_err(|e| anyhow!("Failed to listen for metrics on {}: {}", listen, e))?;
+    let content_type = Header::from_bytes(&b"Content-Type"[..], METRICS_CONTENT_TYPE.as_bytes())
+        .map_err(|_| anyhow!("Invalid content type: {}", METRICS_CONTENT_TYPE))?;
+    info!("📈 Serving metrics on http://{}/metrics", listen);
+
+    thread::spawn(move || {
+        for request in http.incoming_requests() {
+            let path = request.url().split('?').next().unwrap_or_default();
+            let response = if path != "/metrics" {
+                Response::from_string("Not found").with_status_code(404)
+            } else {
+                match Metrics::global().encode() {
+                    Ok(text) => Response::from_string(text).with_header(content_type.clone()),
+                    Err(e) => Response::from_string(format!("{:#}", e)).with_status_code(500),
+                }
+            };
+            if let Err(e) = request.respond(response) {
+                warn!("Failed to send metrics: {}", e);
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queries_and_connections() -> Result<()> {
+        let metrics = Metrics::new()?;
+        metrics.observe_query("http", Duration::from_millis(3), true);
+        metrics.observe_query("http", Duration::from_secs(2), false);
+        let connection = metrics.connection("postgres");
+
+        let text = metrics.encode()?;
+        assert!(text.contains(r#"frozen_duckdb_queries_total{protocol="http",status="ok"} 1"#));
+        assert!(text.contains(r#"frozen_duckdb_queries_total{protocol="http",status="error"} 1"#));
+        assert!(text.contains(r#"frozen_duckdb_query_duration_seconds_count{protocol="http"} 2"#));
+        assert!(text.contains(
+            r#"frozen_duckdb_query_duration_seconds_bucket{protocol="http",le="0.005"} 1"#
+        ));
+        assert!(text.contains(r#"frozen_duckdb_active_connections{protocol="postgres"} 1"#));
+
+        drop(connection);
+        let text = metrics.encode()?;
+        assert!(text.contains(r#"frozen_duckdb_active_connections{protocol="postgres"} 0"#));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_hit_ratio_and_llm_calls() -> Result<()> {
+        let metrics = Metrics::new()?;
+        assert!(!metrics.encode()?.contains("frozen_duckdb_cache_hit_ratio"));
+
+        metrics.record_cache_lookup(CacheKind::Response, true);
+        metrics.record_cache_lookup(CacheKind::Response, true);
+        metrics.record_cache_lookup(CacheKind::Response, true);
+        metrics.record_cache_lookup(CacheKind::Response, false);
+        metrics.record_llm_call("coder", LlmSource::Cache);
+        metrics.record_llm_call("coder", LlmSource::Model);
+
+        let text = metrics.encode()?;
+        assert!(text.contains(r#"frozen_duckdb_cache_hit_ratio{cache="response"} 0.75"#));
+        assert!(!text.contains(r#"frozen_duckdb_cache_hit_ratio{cache="query"}"#));
+        assert!(text.contains(r#"frozen_duckdb_llm_calls_total{model="coder",source="cache"} 1"#));
+        Ok(())
+    }
+}
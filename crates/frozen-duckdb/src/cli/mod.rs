@@ -3,9 +3,65 @@
 //! This module contains the command-line interface implementation,
 //! organized into logical sub-modules for better maintainability.
 
+pub mod access;
+pub mod audit_log;
+pub mod build_stats;
+pub mod cache_dir;
+pub mod catalog;
+pub mod clustering;
 pub mod commands;
+pub mod config;
+pub mod dataset_cache;
 pub mod dataset_manager;
+pub mod dedupe;
+pub mod embedding_backends;
+pub mod embedding_index;
+pub mod eval;
+pub mod extraction;
+pub mod federation;
+pub mod filter_checkpoint;
 pub mod flock_manager;
+pub mod fusion;
+pub mod guardrails;
+pub mod image_input;
+pub mod ingest;
+pub mod jobs;
+pub mod join;
+pub mod lakehouse;
+pub mod language;
+pub mod lineage;
+pub mod llm_recording;
+pub mod loaders;
+pub mod masking;
+pub mod materialized_views;
+pub mod merge;
+pub mod metadata_filter;
+pub mod metrics;
+pub mod multi_query;
+pub mod output;
+pub mod parquet_parts;
+pub mod pgwire;
+pub mod pool;
+pub mod progress;
+pub mod projection;
+pub mod query_cache;
+pub mod rag;
+pub mod rate_limit;
+pub mod reshape;
+pub mod response_cache;
+pub mod result_table;
+pub mod schema_docs;
+pub mod script;
+pub mod server;
+pub mod sharing;
+pub mod similar_items;
+pub mod snapshot;
+pub mod sql_models;
+pub mod sql_path;
+pub mod telemetry;
+pub mod throughput;
+pub mod tpch_answers;
+pub mod watch;
 
 pub use commands::*;
 pub use dataset_manager::*;
@@ -0,0 +1,204 @@
+//! # Multi-Query Retrieval
+//!
+//! A single query embedding misses documents that answer the question in
+//! other words. Multi-query retrieval asks the text model for several
+//! paraphrases of the question, searches with the question and every
+//! paraphrase, and merges the result lists with a Flock fusion function
+//! (see [`fusion`](super::fusion)). Documents found by several phrasings
+//! rise to the top, and those only one phrasing found are still included.
+//!
+//! | Step | Calls |
+//! |------|-------|
+//! | Expand | One `llm_complete` call returning `N` paraphrases, one per line |
+//! | Search | `N + 1` searches, or `2 (N + 1)` with `search --hybrid` |
+//! | Fuse | One fusion per distinct document, `rrf` by default |
+//!
+//! Paraphrases are parsed leniently: list markers, numbering, and quotes
+//! are stripped, and lines repeating the question or an earlier paraphrase
+//! are dropped. When the model returns nothing usable, only the original
+//! question is searched.
+//!
+//! `eval-retrieval` measures whether this pays off for a corpus: it reports
+//! recall@k with and without multi-query retrieval over labeled queries.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use frozen_duckdb::cli::multi_query::MultiQuery;
+//! use frozen_duckdb::cli::FlockManager;
+//!
+//! let manager = FlockManager::new()?;
+//! let multi_query = MultiQuery { paraphrases: 3, ..Default::default() };
+//! let queries = multi_query.queries(&manager, "How do I export to Parquet?")?;
+//! // The question itself comes first
+//! assert_eq!(queries[0], "How do I export to Parquet?");
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use super::fusion::FusionMethod;
+use super::FlockManager;
+use anyhow::Result;
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+/// Options for multi-query retrieval.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiQuery {
+    /// Paraphrases generated in addition to the question
+    pub paraphrases: usize,
+    /// Model alias generating the paraphrases
+    pub model: String,
+    /// Fusion method merging the result lists
+    pub fusion: FusionMethod,
+}
+
+impl Default for MultiQuery {
+    fn default() -> Self {
+        Self {
+            paraphrases: 3,
+            model: "text_generator".to_string(),
+            fusion: FusionMethod::Rrf,
+        }
+    }
+}
+
+impl MultiQuery {
+    /// Returns `question` followed by up to `paraphrases` paraphrases from
+    /// the text model.
+    pub fn queries(&self, manager: &FlockManager, question: &str) -> Result<Vec<String>> {
+        let mut queries = vec![question.to_string()];
+        if self.paraphrases == 0 {
+            return Ok(queries);
+        }
+
+        let response =
+            manager.complete_text(&expansion_prompt(question, self.paraphrases), &self.model)?;
+        let paraphrases = parse_paraphrases(&response, question, self.paraphrases);
+        if paraphrases.is_empty() {
+            warn!(
+                "⚠️  Model '{}' returned no paraphrases; searching with the question only",
+                self.model
+            );
+        } else {
+            info!(
+                "🔁 Searching with {} paraphrases: {}",
+                paraphrases.len(),
+                paraphrases.join(" | ")
+            );
+        }
+        queries.extend(paraphrases);
+        Ok(queries)
+    }
+
+    /// Merges the result lists of all queries, each best first, into one
+    /// list by fused score, best first.
+    pub fn fuse(
+        &self,
+        manager: &FlockManager,
+        lists: &[Vec<(String, f32)>],
+    ) -> Result<Vec<(String, f32)>> {
+        Ok(manager
+            .fuse_rankings(self.fusion, lists)?
+            .into_iter()
+            .map(|(document, score)| (document, score as f32))
+            .collect())
+    }
+}
+
+/// Prompt asking the text model for `n` paraphrases of `question`.
+pub fn expansion_prompt(question: &str, n: usize) -> String {
+    format!(
+        "Write {} different rephrasings of the search question below. Each must ask for the \
+         same information in different words, using synonyms and related terms. Answer with \
+         one rephrasing per line and nothing else.\n\nQuestion: {}",
+        n, question
+    )
+}
+
+/// Reads up to `n` paraphrases from a model response: one per line, or a
+/// JSON array of strings. List markers and quotes are stripped; blank
+/// lines, introductions ending in `:`, and repeats of `question` or an
+/// earlier paraphrase are skipped.
+pub fn parse_paraphrases(response: &str, question: &str, n: usize) -> Vec<String> {
+    let candidates: Vec<String> = match serde_json::from_str::<Vec<String>>(response.trim()) {
+        Ok(items) => items,
+        Err(_) => response.lines().map(str::to_string).collect(),
+    };
+
+    let mut seen: HashSet<String> = HashSet::new();
+    seen.insert(normalize(question));
+    let mut paraphrases = Vec::new();
+    for candidate in candidates {
+        let text = strip_marker(candidate.trim());
+        if text.is_empty() || text.ends_with(':') {
+            continue;
+        }
+        if seen.insert(normalize(text)) {
+            paraphrases.push(text.to_string());
+            if paraphrases.len() == n {
+                break;
+            }
+        }
+    }
+    paraphrases
+}
+
+/// Strips a leading list marker (`-`, `*`, `•`, `1.`, `2)`) and
+/// surrounding quotes.
+fn strip_marker(line: &str) -> &str {
+    let line = line.trim_start_matches(['-', '*', '•']).trim_start();
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let line = match line[digits..].chars().next() {
+        Some('.' | ')') if digits > 0 => line[digits + 1..].trim_start(),
+        _ => line,
+    };
+    line.trim_matches(['"', '\'', '“', '”']).trim()
+}
+
+/// Lowercases and drops trailing punctuation, for spotting repeats.
+fn normalize(text: &str) -> String {
+    text.trim_end_matches(['?', '.', '!']).trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_paraphrases_from_lines() {
+        let response = "Here are three rephrasings:\n\
+                        1. How can I write a table to Parquet?\n\
+                        2) \"Exporting data as Parquet files\"\n\
+                        - how do i export to parquet\n\
+                        \n\
+                        * Saving query results in Parquet format\n\
+                        - One too many";
+        let paraphrases = parse_paraphrases(response, "How do I export to Parquet?", 3);
+        assert_eq!(
+            paraphrases,
+            vec![
+                "How can I write a table to Parquet?",
+                "Exporting data as Parquet files",
+                "Saving query results in Parquet format"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_paraphrases_from_json() {
+        let response = r#"["Parquet export", "parquet export.", "Write Parquet"]"#;
+        assert_eq!(
+            parse_paraphrases(response, "export parquet", 5),
+            vec!["Parquet export", "Write Parquet"]
+        );
+        assert!(parse_paraphrases("", "q", 3).is_empty());
+    }
+
+    #[test]
+    fn test_strip_marker() {
+        assert_eq!(strip_marker("10. Ten"), "Ten");
+        assert_eq!(strip_marker("• “Quoted”"), "Quoted");
+        // Numbers that aren't list markers are kept
+        assert_eq!(strip_marker("2024 release notes"), "2024 release notes");
+    }
+}
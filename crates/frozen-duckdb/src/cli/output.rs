@@ -0,0 +1,246 @@
+//! # Output Modes for Frozen DuckDB CLI
+//!
+//! Command results (query rows, JSON reports, generated SQL) are the only
+//! thing written to stdout, so they can be piped into other tools. Logs,
+//! progress bars, and hints go to stderr, and can be toned down:
+//!
+//! | Setting | Effect |
+//! |---------|--------|
+//! | `-v`, `-vv`, `-vvv` | More detailed logs |
+//! | `--quiet` | Only errors are logged; no progress bars |
+//! | `--no-emoji` | Emoji removed from logs, and status marks printed as text |
+//! | `NO_COLOR` (any non-empty value) | No ANSI colors in logs |
+//!
+//! Colors are also off when stderr is not a terminal. From `-vv` on, the
+//! log also shows every finished [telemetry](super::telemetry) span with its
+//! duration.
+//!
+//! ## Example
+//!
+//! ```bash
+//! # Only the query result, no logs
+//! frozen-duckdb --quiet query --input sales.parquet --sql "SELECT count(*) FROM data" > count.txt
+//!
+//! # Plain logs for CI
+//! NO_COLOR=1 frozen-duckdb --no-emoji convert --input data.csv --output data.parquet
+//! ```
+
+use super::telemetry::OtlpExporter;
+use std::borrow::Cow;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::Level;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::prelude::*;
+
+/// Environment variable that turns off colors (see <https://no-color.org>).
+pub const NO_COLOR_ENV: &str = "NO_COLOR";
+
+static EMOJI: AtomicBool = AtomicBool::new(true);
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// How the CLI writes diagnostics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputOptions {
+    /// Log errors only, and hide progress bars
+    pub quiet: bool,
+    /// Keep emoji in logs and status marks
+    pub emoji: bool,
+    /// Color log levels with ANSI escapes
+    pub color: bool,
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self {
+            quiet: false,
+            emoji: true,
+            color: true,
+        }
+    }
+}
+
+impl OutputOptions {
+    /// Options from the `--quiet` and `--no-emoji` flags and [`NO_COLOR_ENV`].
+    pub fn from_flags(quiet: bool, no_emoji: bool) -> Self {
+        let no_color = std::env::var(NO_COLOR_ENV).is_ok_and(|value| !value.is_empty());
+        Self {
+            quiet,
+            emoji: !no_emoji,
+            color: !no_color,
+        }
+    }
+
+    /// Most detailed log level shown for `verbose` (the `-v` count).
+    pub fn max_level(&self, verbose: u8) -> Level {
+        match (self.quiet, verbose) {
+            (true, _) => Level::ERROR,
+            (false, 0) => Level::WARN,
+            (false, 1) => Level::INFO,
+            (false, 2) => Level::DEBUG,
+            (false, _) => Level::TRACE,
+        }
+    }
+
+    /// Applies the options and installs the stderr log subscriber.
+    ///
+    /// Spans also go to `exporter`, when given, at any `verbose` level.
+    pub fn init(&self, verbose: u8, exporter: Option<&OtlpExporter>) {
+        EMOJI.store(self.emoji, Ordering::Relaxed);
+        QUIET.store(self.quiet, Ordering::Relaxed);
+
+        let level = self.max_level(verbose);
+        let span_events = if level >= Level::DEBUG {
+            FmtSpan::CLOSE
+        } else {
+            FmtSpan::NONE
+        };
+        let log = tracing_subscriber::fmt::layer()
+            .with_ansi(self.color && io::stderr().is_terminal())
+            .with_writer(StderrWriter { emoji: self.emoji })
+            .with_span_events(span_events)
+            .with_filter(LevelFilter::from_level(level));
+        let subscriber = tracing_subscriber::registry()
+            .with(log)
+            .with(exporter.map(|exporter| exporter.layer().with_filter(LevelFilter::INFO)));
+        tracing::subscriber::set_global_default(subscriber)
+            .expect("Failed to set tracing subscriber");
+    }
+}
+
+/// Whether emoji are shown (no `--no-emoji`).
+pub fn emoji_enabled() -> bool {
+    EMOJI.load(Ordering::Relaxed)
+}
+
+/// Whether `--quiet` was given.
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Returns `emoji` normally, or `text` with `--no-emoji`.
+///
+/// Use for status marks in command output, where dropping the emoji would
+/// lose information, e.g. `mark("✅", "ok")`.
+pub fn mark(emoji: &'static str, text: &'static str) -> &'static str {
+    if emoji_enabled() {
+        emoji
+    } else {
+        text
+    }
+}
+
+/// Returns `text` with emoji removed when `--no-emoji` was given.
+pub fn plain(text: &str) -> Cow<'_, str> {
+    if emoji_enabled() {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(strip_emoji(text))
+    }
+}
+
+/// Removes emoji, and the spaces that follow them, from `text`.
+pub fn strip_emoji(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut after_emoji = false;
+    for c in text.chars() {
+        if is_emoji(c) {
+            after_emoji = true;
+        } else if !(after_emoji && c == ' ') {
+            after_emoji = false;
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+/// Emoji blocks: pictographs, miscellaneous symbols and dingbats (✅ ❌ ⚠),
+/// media controls (⏩ ⏭), stars, ℹ, ▶, and the joiners and selectors that
+/// combine them. Arrows and box drawing characters are kept.
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF
+            | 0x2600..=0x27BF
+            | 0x23E9..=0x23FA
+            | 0x2B50..=0x2B55
+            | 0x2139
+            | 0x25B6
+            | 0xFE0F
+            | 0x200D
+    )
+}
+
+/// Log writer for stderr that drops emoji when they are turned off.
+struct StderrWriter {
+    emoji: bool,
+}
+
+impl<'a> MakeWriter<'a> for StderrWriter {
+    type Writer = EmojiFilter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        EmojiFilter {
+            emoji: self.emoji,
+            inner: io::stderr(),
+        }
+    }
+}
+
+/// Writes formatted log events to stderr, stripping emoji if needed.
+struct EmojiFilter {
+    emoji: bool,
+    inner: io::Stderr,
+}
+
+impl Write for EmojiFilter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match std::str::from_utf8(buf) {
+            Ok(text) if !self.emoji => {
+                self.inner.write_all(strip_emoji(text).as_bytes())?;
+                Ok(buf.len())
+            }
+            _ => self.inner.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_emoji() {
+        assert_eq!(
+            strip_emoji("❌ Flock extension not available"),
+            "Flock extension not available"
+        );
+        assert_eq!(strip_emoji("⚠️  Key types differ"), "Key types differ");
+        assert_eq!(
+            strip_emoji("INFO 🏗️  Building 3 models"),
+            "INFO Building 3 models"
+        );
+        assert_eq!(strip_emoji("Zoë → 数据 ok"), "Zoë → 数据 ok");
+        assert_eq!(strip_emoji("┌─┬─┐"), "┌─┬─┐");
+    }
+
+    #[test]
+    fn test_max_level() {
+        let options = OutputOptions::default();
+        assert_eq!(options.max_level(0), Level::WARN);
+        assert_eq!(options.max_level(2), Level::DEBUG);
+        assert_eq!(options.max_level(9), Level::TRACE);
+
+        let quiet = OutputOptions {
+            quiet: true,
+            ..OutputOptions::default()
+        };
+        assert_eq!(quiet.max_level(3), Level::ERROR);
+    }
+}
@@ -0,0 +1,258 @@
+//! # Split Parquet Exports for Frozen DuckDB CLI
+//!
+//! One huge Parquet file is hard to upload, copy, and read in parallel
+//! downstream. `convert --max-file-size` and `--rows-per-file` write the
+//! output as a directory of numbered parts instead, using DuckDB's `COPY`
+//! options, and list them in a `manifest.json`:
+//!
+//! | Option | `COPY` options | Parts hold |
+//! |--------|----------------|------------|
+//! | `--max-file-size 512MB` | `FILE_SIZE_BYTES` | About 512 MB each |
+//! | `--rows-per-file 1000000` | `ROW_GROUP_SIZE`, `ROW_GROUPS_PER_FILE 1` | About a million rows each |
+//!
+//! DuckDB closes a part after the row group that crosses the limit, so a
+//! part can be slightly larger than asked for.
+//!
+//! ```text
+//! events/
+//! ├── manifest.json
+//! ├── part_0.parquet
+//! ├── part_1.parquet
+//! └── part_2.parquet
+//! ```
+//!
+//! The manifest names each part relative to the directory, with its row
+//! count and size:
+//!
+//! ```json
+//! {
+//!   "format": "parquet",
+//!   "total_rows": 2500000,
+//!   "parts": [
+//!     { "path": "part_0.parquet", "rows": 1000000, "bytes": 41234567 },
+//!     { "path": "part_1.parquet", "rows": 1000000, "bytes": 41198765 },
+//!     { "path": "part_2.parquet", "rows": 500000, "bytes": 20634012 }
+//!   ]
+//! }
+//! ```
+
+use super::dataset_manager::PerformanceOptions;
+use super::sql_path::path_literal;
+use anyhow::{Context, Result};
+use duckdb::Connection;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+/// Name of the manifest written next to the parts
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// How to split a Parquet export into parts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitBy {
+    /// Parts of about this many bytes
+    FileSize(u64),
+    /// Parts of about this many rows
+    Rows(u64),
+}
+
+impl SplitBy {
+    /// The split for `--max-file-size` or `--rows-per-file`, if either was
+    /// given; giving both is an error.
+    pub fn from_options(
+        max_file_size: Option<u64>,
+        rows_per_file: Option<u64>,
+    ) -> Result<Option<Self>> {
+        match (max_file_size, rows_per_file) {
+            (Some(_), Some(_)) => {
+                anyhow::bail!("Use either --max-file-size or --rows-per-file, not both")
+            }
+            (Some(0), _) | (_, Some(0)) => {
+                anyhow::bail!("Parts must hold at least one byte and row")
+            }
+            (Some(bytes), None) => Ok(Some(Self::FileSize(bytes))),
+            (None, Some(rows)) => Ok(Some(Self::Rows(rows))),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// `COPY` options writing numbered parts, given the export's
+    /// compression and tuning.
+    pub fn copy_options(&self, compression: &str, performance: &PerformanceOptions) -> String {
+        match *self {
+            Self::FileSize(bytes) => format!(
+                "{}, FILE_SIZE_BYTES {}, FILENAME_PATTERN 'part_{{i}}'",
+                performance.parquet_options(compression),
+                bytes
+            ),
+            // One row group per file, so the row group size is the part size
+            Self::Rows(rows) => format!(
+                "{}, ROW_GROUPS_PER_FILE 1, FILENAME_PATTERN 'part_{{i}}'",
+                PerformanceOptions {
+                    row_group_size: Some(rows as usize),
+                    ..performance.clone()
+                }
+                .parquet_options(compression)
+            ),
+        }
+    }
+}
+
+/// One file of a split export.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportPart {
+    /// File name, relative to the export directory
+    pub path: String,
+    /// Rows in the file
+    pub rows: u64,
+    /// File size in bytes
+    pub bytes: u64,
+}
+
+/// The parts of a split export, as listed in its `manifest.json`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartManifest {
+    /// File format of the parts
+    pub format: String,
+    /// Rows across all parts
+    pub total_rows: u64,
+    /// Parts in order
+    pub parts: Vec<ExportPart>,
+}
+
+impl PartManifest {
+    /// Reads the row counts of the Parquet parts in `dir` from their
+    /// footers, in part order.
+    pub fn scan(conn: &Connection, dir: &Path) -> Result<Self> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT file_name, sum(row_group_num_rows)::BIGINT FROM \
+             (SELECT DISTINCT file_name, row_group_id, row_group_num_rows FROM parquet_metadata({})) \
+             GROUP BY file_name",
+            path_literal(dir.join("*.parquet"))
+        ))?;
+        let mut parts = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })?
+            .map(|row| {
+                let (file, rows) = row?;
+                let file = Path::new(&file);
+                let name = file
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned();
+                let bytes = fs::metadata(dir.join(&name))
+                    .with_context(|| format!("Failed to read export part: {}", file.display()))?
+                    .len();
+                Ok(ExportPart {
+                    path: name,
+                    rows: rows as u64,
+                    bytes,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        parts.sort_by_key(|part| part_number(&part.path));
+
+        Ok(Self {
+            format: "parquet".to_string(),
+            total_rows: parts.iter().map(|part| part.rows).sum(),
+            parts,
+        })
+    }
+
+    /// Serializes the manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "format": self.format,
+            "total_rows": self.total_rows,
+            "parts": self.parts.iter().map(|part| json!({
+                "path": part.path,
+                "rows": part.rows,
+                "bytes": part.bytes,
+            })).collect::<Vec<_>>(),
+        })
+    }
+
+    /// Writes the manifest to `dir/manifest.json`.
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(MANIFEST_FILE);
+        fs::write(&path, serde_json::to_string_pretty(&self.to_json())?)
+            .with_context(|| format!("Failed to write manifest: {}", path.display()))
+    }
+}
+
+/// The `N` of `part_N.parquet`, so `part_10` sorts after `part_9`.
+fn part_number(name: &str) -> (u64, String) {
+    let number = name
+        .strip_prefix("part_")
+        .and_then(|rest| rest.split('.').next())
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(u64::MAX);
+    (number, name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_options() {
+        assert_eq!(SplitBy::from_options(None, None).unwrap(), None);
+        assert_eq!(
+            SplitBy::from_options(Some(512_000_000), None).unwrap(),
+            Some(SplitBy::FileSize(512_000_000))
+        );
+        assert!(SplitBy::from_options(Some(1), Some(1)).is_err());
+        assert!(SplitBy::from_options(None, Some(0)).is_err());
+
+        let tuning = PerformanceOptions {
+            row_group_size: Some(50_000),
+            ..Default::default()
+        };
+        assert_eq!(
+            SplitBy::FileSize(1_000).copy_options("zstd", &tuning),
+            "FORMAT PARQUET, COMPRESSION zstd, ROW_GROUP_SIZE 50000, \
+             FILE_SIZE_BYTES 1000, FILENAME_PATTERN 'part_{i}'"
+        );
+        assert_eq!(
+            SplitBy::Rows(200).copy_options("snappy", &tuning),
+            "FORMAT PARQUET, COMPRESSION snappy, ROW_GROUP_SIZE 200, \
+             ROW_GROUPS_PER_FILE 1, FILENAME_PATTERN 'part_{i}'"
+        );
+    }
+
+    #[test]
+    fn test_parts_sort_numerically() {
+        let mut names = vec!["part_10.parquet", "part_2.parquet", "part_0.parquet"];
+        names.sort_by_key(|name| part_number(name));
+        assert_eq!(
+            names,
+            ["part_0.parquet", "part_2.parquet", "part_10.parquet"]
+        );
+    }
+
+    #[test]
+    fn test_manifest_json() {
+        let manifest = PartManifest {
+            format: "parquet".to_string(),
+            total_rows: 3,
+            parts: vec![
+                ExportPart {
+                    path: "part_0.parquet".to_string(),
+                    rows: 2,
+                    bytes: 100,
+                },
+                ExportPart {
+                    path: "part_1.parquet".to_string(),
+                    rows: 1,
+                    bytes: 80,
+                },
+            ],
+        };
+        let json = manifest.to_json();
+        assert_eq!(json["total_rows"], 3);
+        assert_eq!(json["parts"][1]["path"], "part_1.parquet");
+        assert_eq!(json["parts"][0]["rows"], 2);
+    }
+}
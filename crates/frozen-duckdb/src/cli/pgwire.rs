@@ -0,0 +1,926 @@
+//! # PostgreSQL Wire Protocol (Experimental)
+//!
+//! `frozen-duckdb serve --protocol postgres` accepts PostgreSQL client
+//! connections, so `psql`, BI tools, and PostgreSQL drivers can query a
+//! DuckDB database. Queries run on DuckDB unchanged: the SQL dialect is
+//! DuckDB's, which covers most of what these tools send.
+//!
+//! ## Supported Subset
+//!
+//! | Feature | Support |
+//! |---------|---------|
+//! | Simple query protocol | ✅ Multiple statements per query |
+//! | Result format | Text only; types mapped to the closest PostgreSQL OID |
+//! | Authentication | None, or cleartext password equal to `--token` |
+//! | Extended query protocol | ❌ Answered with an error (prepared statements) |
+//! | TLS | ❌ `SSLRequest` is declined; clients fall back to plain TCP |
+//! | `COPY`, cancel requests | ❌ |
+//!
+//! JDBC-based tools such as Metabase use the extended protocol by default;
+//! add `preferQueryMode=simple` to the connection URL. The password is sent
+//! in cleartext, so keep the listener on localhost or behind a tunnel.
+//!
+//! Clients share the `--max-connections` connections of a
+//! [`ConnectionPool`], opened read-only unless `--read-write` is given.
+//! After authenticating, a client holds one connection until it
+//! disconnects, so its transactions and `SET` options stay on it; an open
+//! transaction is rolled back when the client leaves. Clients waiting
+//! longer than `--queue-timeout` for a connection, and those beyond
+//! `--max-connections` plus `--max-queue`, are refused with
+//! `too_many_connections`. Statements running longer than `--timeout`, or
+//! whose client disconnects, are canceled with `query_canceled`, and
+//! results larger than `--max-rows` fail. A client has 30 seconds to
+//! finish the startup and password exchange, and is disconnected after an
+//! hour without sending anything.
+//!
+//! ## Usage Examples
+//!
+//! ```bash
+//! frozen-duckdb serve --protocol postgres --database analytics.duckdb --listen 127.0.0.1:5432
+//!
+//! psql "host=127.0.0.1 port=5432 user=duckdb sslmode=disable" -c "SELECT 42"
+//! ```
+
+use super::dataset_manager::{format_value, split_statements, DatasetManager};
+use super::metrics::Metrics;
+use super::pool::{Cancellation, ConnectionPool, PoolOptions, Watchdog};
+use super::server::{constant_time_eq, open_database, Protocol, QueryLimits, ServeOptions};
+use anyhow::{anyhow, Context, Result};
+use duckdb::types::Value;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// Startup code of protocol version 3.0.
+const PROTOCOL_VERSION_3: i32 = 196_608;
+/// Startup code of an `SSLRequest`.
+const SSL_REQUEST_CODE: i32 = 80_877_103;
+/// Startup code of a `GSSENCRequest`.
+const GSSENC_REQUEST_CODE: i32 = 80_877_104;
+/// Startup code of a `CancelRequest`.
+const CANCEL_REQUEST_CODE: i32 = 80_877_102;
+
+/// Largest accepted message, to bound memory on malformed input.
+const MAX_MESSAGE_BYTES: usize = 64 << 20;
+
+/// Largest startup or password message, read before the client is
+/// authenticated.
+const MAX_STARTUP_BYTES: usize = 8 << 10;
+
+/// Longest a client may take to finish the startup phase.
+const STARTUP_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest an authenticated client may stay silent before its connection
+/// is closed, so vanished clients don't hold a slot forever.
+const IDLE_READ_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Server version reported to clients. Tools gate features on it, so it
+/// names a PostgreSQL release rather than the DuckDB version.
+const SERVER_VERSION: &str = "14.0 (frozen-duckdb)";
+
+/// PostgreSQL type OIDs of result columns.
+mod oid {
+    pub const BOOL: i32 = 16;
+    pub const BYTEA: i32 = 17;
+    pub const INT8: i32 = 20;
+    pub const INT2: i32 = 21;
+    pub const INT4: i32 = 23;
+    pub const TEXT: i32 = 25;
+    pub const FLOAT4: i32 = 700;
+    pub const FLOAT8: i32 = 701;
+    pub const DATE: i32 = 1082;
+    pub const TIME: i32 = 1083;
+    pub const TIMESTAMP: i32 = 1114;
+    pub const NUMERIC: i32 = 1700;
+}
+
+/// Returns the PostgreSQL type OID that best describes `value`.
+pub fn type_oid(value: &Value) -> i32 {
+    match value {
+        Value::Boolean(_) => oid::BOOL,
+        Value::TinyInt(_) | Value::SmallInt(_) | Value::UTinyInt(_) => oid::INT2,
+        Value::Int(_) | Value::USmallInt(_) => oid::INT4,
+        Value::BigInt(_) | Value::UInt(_) => oid::INT8,
+        Value::HugeInt(_) | Value::UBigInt(_) | Value::Decimal(_) => oid::NUMERIC,
+        Value::Float(_) => oid::FLOAT4,
+        Value::Double(_) => oid::FLOAT8,
+        Value::Blob(_) => oid::BYTEA,
+        Value::Date32(_) => oid::DATE,
+        Value::Time64(..) => oid::TIME,
+        Value::Timestamp(..) => oid::TIMESTAMP,
+        _ => oid::TEXT,
+    }
+}
+
+/// Formats `value` in PostgreSQL's text format, or `None` for NULL.
+pub fn text_value(value: &Value) -> Option<String> {
+    Some(match value {
+        Value::Null => return None,
+        Value::Boolean(true) => "t".to_string(),
+        Value::Boolean(false) => "f".to_string(),
+        Value::Blob(bytes) => {
+            let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            format!("\\x{}", hex)
+        }
+        Value::Date32(days) => {
+            // NaiveDate::default() is the Unix epoch
+            (chrono::NaiveDate::default() + chrono::Duration::days(i64::from(*days))).to_string()
+        }
+        Value::Time64(unit, v) => {
+            let micros = unit.to_micros(*v);
+            chrono::NaiveTime::from_num_seconds_from_midnight_opt(
+                (micros / 1_000_000) as u32,
+                (micros % 1_000_000) as u32 * 1000,
+            )
+            .map(|t| t.format("%H:%M:%S%.f").to_string())
+            .unwrap_or_else(|| format_value(value))
+        }
+        Value::Timestamp(unit, v) => chrono::DateTime::from_timestamp_micros(unit.to_micros(*v))
+            .map(|t| t.naive_utc().format("%Y-%m-%d %H:%M:%S%.f").to_string())
+            .unwrap_or_else(|| format_value(value)),
+        other => format_value(other),
+    })
+}
+
+/// Returns the command tag PostgreSQL reports for a statement that
+/// returns no rows, such as `CREATE TABLE` or `BEGIN`.
+pub fn command_tag(statement: &str) -> String {
+    let words = leading_keywords(statement);
+    match words.first().map(String::as_str) {
+        Some("CREATE" | "DROP" | "ALTER") => words.join(" "),
+        Some(keyword) => keyword.to_string(),
+        None => String::new(),
+    }
+}
+
+/// Returns the first two keywords of a statement in upper case, skipping
+/// leading comments.
+fn leading_keywords(statement: &str) -> Vec<String> {
+    let mut rest = statement.trim_start();
+    loop {
+        if let Some(comment) = rest.strip_prefix("--") {
+            rest = comment.split_once('\n').map_or("", |(_, r)| r).trim_start();
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            rest = comment.split_once("*/").map_or("", |(_, r)| r).trim_start();
+        } else {
+            break;
+        }
+    }
+    rest.split(|c: char| !c.is_ascii_alphabetic())
+        .filter(|w| !w.is_empty())
+        .take(2)
+        .map(str::to_uppercase)
+        .collect()
+}
+
+/// Appends a backend message with its tag and length to `out`.
+fn message(out: &mut Vec<u8>, tag: u8, body: &[u8]) {
+    out.push(tag);
+    out.extend_from_slice(&(body.len() as i32 + 4).to_be_bytes());
+    out.extend_from_slice(body);
+}
+
+fn put_cstr(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(s.as_bytes());
+    buf.push(0);
+}
+
+/// Appends an `ErrorResponse` with the given severity and SQLSTATE.
+fn error_response(out: &mut Vec<u8>, severity: &str, code: &str, text: &str) {
+    let mut body = Vec::new();
+    for (field, value) in [
+        (b'S', severity),
+        (b'V', severity),
+        (b'C', code),
+        (b'M', text),
+    ] {
+        body.push(field);
+        put_cstr(&mut body, value);
+    }
+    body.push(0);
+    message(out, b'E', &body);
+}
+
+/// Reads an `i32` length followed by that many bytes minus the length,
+/// refusing messages longer than `max_bytes`.
+fn read_body<S: Read>(stream: &mut S, max_bytes: usize) -> Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len)?;
+    let len = i32::from_be_bytes(len);
+    if !(4..=max_bytes as i32).contains(&len) {
+        return Err(anyhow!("Invalid message length: {}", len));
+    }
+    let mut body = vec![0u8; len as usize - 4];
+    stream.read_exact(&mut body)?;
+    Ok(body)
+}
+
+/// Returns the NUL-terminated string at the start of `body`.
+fn read_cstr(body: &[u8]) -> Result<&str> {
+    let end = body.iter().position(|&b| b == 0).unwrap_or(body.len());
+    std::str::from_utf8(&body[..end]).context("Message is not valid UTF-8")
+}
+
+/// One client connection speaking the PostgreSQL protocol.
+pub struct PgSession {
+    pool: Arc<ConnectionPool>,
+    token: Option<String>,
+    limits: QueryLimits,
+    /// Socket of a TCP client, watched for disconnects while a statement
+    /// runs
+    client: Option<TcpStream>,
+    /// Transaction status reported in `ReadyForQuery`: idle, in a
+    /// transaction, or in a failed transaction
+    status: u8,
+}
+
+impl PgSession {
+    /// Creates a session on `conn`, requiring `token` as the password if
+    /// given.
+    pub fn new(conn: duckdb::Connection, token: Option<String>) -> Result<Self> {
+        let options = PoolOptions {
+            size: 1,
+            ..Default::default()
+        };
+        Ok(Self::with_pool(
+            Arc::new(ConnectionPool::new(conn, options)?),
+            token,
+        ))
+    }
+
+    /// Creates a session borrowing a connection of `pool` once the client
+    /// is authenticated, requiring `token` as the password if given.
+    pub fn with_pool(pool: Arc<ConnectionPool>, token: Option<String>) -> Self {
+        Self {
+            pool,
+            token: token.filter(|t| !t.is_empty()),
+            limits: QueryLimits::default(),
+            client: None,
+            status: b'I',
+        }
+    }
+
+    /// Applies `limits` to every statement.
+    pub fn with_limits(mut self, limits: QueryLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Runs the session until the client disconnects.
+    pub fn run<S: Read + Write>(&mut self, mut stream: S) -> Result<()> {
+        if !self.startup(&mut stream)? {
+            return Ok(());
+        }
+        self.idle()?;
+
+        let pool = Arc::clone(&self.pool);
+        let manager = match pool.get() {
+            Ok(manager) => manager,
+            Err(e) => {
+                let mut out = Vec::new();
+                error_response(&mut out, "FATAL", "53300", &format!("{:#}", e));
+                stream.write_all(&out)?;
+                return Ok(());
+            }
+        };
+        let result = self.serve_queries(&manager, stream);
+        if self.status != b'I' {
+            // The pool replaces the connection with a new session either
+            // way, so a failed rollback never reaches the next client
+            if let Err(e) = manager.connection().execute_batch("ROLLBACK") {
+                warn!(
+                    "Failed to roll back the transaction of a closed session: {:#}",
+                    e
+                );
+            }
+            self.status = b'I';
+        }
+        result
+    }
+
+    /// Runs the session on a client socket, with a short read timeout
+    /// during startup and a long one once the client is authenticated.
+    /// Statements are canceled when the client disconnects.
+    pub fn run_tcp(&mut self, stream: TcpStream) -> Result<()> {
+        stream.set_read_timeout(Some(STARTUP_READ_TIMEOUT))?;
+        self.client = Some(stream.try_clone()?);
+        self.run(stream)
+    }
+
+    /// Restores the idle read timeout of a TCP client, which a [`Watchdog`]
+    /// shortens while it watches the socket.
+    fn idle(&self) -> Result<()> {
+        if let Some(client) = &self.client {
+            client.set_read_timeout(Some(IDLE_READ_TIMEOUT))?;
+        }
+        Ok(())
+    }
+
+    /// Answers queries on `manager` until the client disconnects.
+    fn serve_queries<S: Read + Write>(
+        &mut self,
+        manager: &DatasetManager,
+        mut stream: S,
+    ) -> Result<()> {
+        // After an error in the extended protocol, the client's messages
+        // are discarded until it sends Sync
+        let mut discarding = false;
+        loop {
+            let mut tag = [0u8; 1];
+            match stream.read_exact(&mut tag) {
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+                result => result?,
+            }
+            let body = read_body(&mut stream, MAX_MESSAGE_BYTES)?;
+
+            let mut out = Vec::new();
+            match tag[0] {
+                b'Q' => {
+                    self.simple_query(manager, &mut out, read_cstr(&body)?);
+                    self.idle()?;
+                    self.ready_for_query(&mut out);
+                }
+                b'S' => {
+                    discarding = false;
+                    self.ready_for_query(&mut out);
+                }
+                b'X' => return Ok(()),
+                b'P' | b'B' | b'D' | b'E' | b'C' | b'H' => {
+                    if !discarding {
+                        error_response(
+                            &mut out,
+                            "ERROR",
+                            "0A000",
+                            "Extended query protocol is not supported; use the simple query protocol",
+                        );
+                        discarding = true;
+                    }
+                }
+                other => {
+                    error_response(
+                        &mut out,
+                        "FATAL",
+                        "08P01",
+                        &format!("Unsupported message type: {}", other as char),
+                    );
+                    stream.write_all(&out)?;
+                    return Ok(());
+                }
+            }
+            stream.write_all(&out)?;
+            stream.flush()?;
+        }
+    }
+
+    /// Negotiates the startup phase, returning whether the client is ready
+    /// to send queries.
+    fn startup<S: Read + Write>(&self, stream: &mut S) -> Result<bool> {
+        let params = loop {
+            let body = read_body(stream, MAX_STARTUP_BYTES)?;
+            let code = body
+                .get(..4)
+                .map(|b| i32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+                .unwrap_or_default();
+            match code {
+                SSL_REQUEST_CODE | GSSENC_REQUEST_CODE => stream.write_all(b"N")?,
+                CANCEL_REQUEST_CODE => return Ok(false),
+                PROTOCOL_VERSION_3 => break body[4..].to_vec(),
+                _ => {
+                    let mut out = Vec::new();
+                    error_response(
+                        &mut out,
+                        "FATAL",
+                        "0A000",
+                        &format!("Unsupported protocol version: {}", code),
+                    );
+                    stream.write_all(&out)?;
+                    return Ok(false);
+                }
+            }
+        };
+
+        let fields: Vec<&str> = params
+            .split(|&b| b == 0)
+            .filter_map(|f| std::str::from_utf8(f).ok())
+            .collect();
+        let user = fields
+            .chunks(2)
+            .find(|pair| pair[0] == "user")
+            .and_then(|pair| pair.get(1))
+            .copied()
+            .unwrap_or_default();
+        debug!("PostgreSQL client connected as {:?}", user);
+
+        let mut out = Vec::new();
+        if let Some(token) = &self.token {
+            // AuthenticationCleartextPassword
+            message(&mut out, b'R', &3i32.to_be_bytes());
+            stream.write_all(&out)?;
+            stream.flush()?;
+            out.clear();
+
+            let mut tag = [0u8; 1];
+            stream.read_exact(&mut tag)?;
+            let body = read_body(stream, MAX_STARTUP_BYTES)?;
+            let password = read_cstr(&body)?;
+            if tag[0] != b'p' || !constant_time_eq(password.as_bytes(), token.as_bytes()) {
+                error_response(
+                    &mut out,
+                    "FATAL",
+                    "28P01",
+                    &format!("password authentication failed for user \"{}\"", user),
+                );
+                stream.write_all(&out)?;
+                return Ok(false);
+            }
+        }
+
+        // AuthenticationOk
+        message(&mut out, b'R', &0i32.to_be_bytes());
+        for (name, value) in [
+            ("server_version", SERVER_VERSION),
+            ("server_encoding", "UTF8"),
+            ("client_encoding", "UTF8"),
+            ("DateStyle", "ISO, MDY"),
+            ("integer_datetimes", "on"),
+            ("standard_conforming_strings", "on"),
+        ] {
+            let mut body = Vec::new();
+            put_cstr(&mut body, name);
+            put_cstr(&mut body, value);
+            message(&mut out, b'S', &body);
+        }
+        // BackendKeyData; cancel requests are ignored, so the key is unused
+        let mut key = std::process::id().to_be_bytes().to_vec();
+        key.extend_from_slice(&0i32.to_be_bytes());
+        message(&mut out, b'K', &key);
+        self.ready_for_query(&mut out);
+        stream.write_all(&out)?;
+        stream.flush()?;
+        Ok(true)
+    }
+
+    fn ready_for_query(&self, out: &mut Vec<u8>) {
+        message(out, b'Z', &[self.status]);
+    }
+
+    /// Runs each statement of a simple query, stopping at the first error.
+    fn simple_query(&mut self, manager: &DatasetManager, out: &mut Vec<u8>, sql: &str) {
+        let statements = split_statements(sql);
+        if statements.is_empty() {
+            message(out, b'I', &[]);
+            return;
+        }
+        for statement in statements {
+            let started = Instant::now();
+            let interrupt = manager.connection().interrupt_handle();
+            let watchdog = Watchdog::start(interrupt, self.limits.timeout, self.client.as_ref());
+            let result = self.execute(manager, out, statement);
+            let cancellation = watchdog.stop();
+            Metrics::global().observe_query(
+                Protocol::Postgres.as_str(),
+                started.elapsed(),
+                result.is_ok() && cancellation.is_none(),
+            );
+            if let Err(e) = result {
+                match cancellation {
+                    Some(Cancellation::TimedOut(_)) => {
+                        let text = "canceling statement due to statement timeout";
+                        error_response(out, "ERROR", "57014", text);
+                    }
+                    Some(Cancellation::Disconnected) => {
+                        let text = "canceling statement due to client disconnect";
+                        error_response(out, "ERROR", "57014", text);
+                    }
+                    None => error_response(out, "ERROR", "42000", &format!("{:#}", e)),
+                }
+                if self.status == b'T' {
+                    self.status = b'E';
+                }
+                return;
+            }
+        }
+    }
+
+    /// Runs one statement, appending its rows and `CommandComplete`.
+    fn execute(
+        &mut self,
+        manager: &DatasetManager,
+        out: &mut Vec<u8>,
+        statement: &str,
+    ) -> Result<()> {
+        let keywords = leading_keywords(statement);
+        let keyword = keywords.first().map(String::as_str).unwrap_or_default();
+
+        if self.status == b'E' && !matches!(keyword, "ROLLBACK" | "ABORT" | "COMMIT" | "END") {
+            return Err(anyhow!(
+                "current transaction is aborted, commands ignored until end of transaction block"
+            ));
+        }
+
+        let tag = match keyword {
+            "INSERT" | "UPDATE" | "DELETE" if !statement.to_uppercase().contains("RETURNING") => {
+                let changed = manager.connection().execute(statement, [])?;
+                if keyword == "INSERT" {
+                    format!("INSERT 0 {}", changed)
+                } else {
+                    format!("{} {}", keyword, changed)
+                }
+            }
+            "SELECT" | "WITH" | "VALUES" | "FROM" | "TABLE" | "SHOW" | "DESCRIBE" | "EXPLAIN"
+            | "SUMMARIZE" | "PRAGMA" | "CALL" | "INSERT" | "UPDATE" | "DELETE" => {
+                let output = manager.run_query_limited(statement, self.limits.max_rows)?;
+                self.row_description(out, &output.columns, &output.rows);
+                for row in &output.rows {
+                    let mut body = (row.len() as i16).to_be_bytes().to_vec();
+                    for value in row {
+                        match text_value(value) {
+                            Some(text) => {
+                                body.extend_from_slice(&(text.len() as i32).to_be_bytes());
+                                body.extend_from_slice(text.as_bytes());
+                            }
+                            None => body.extend_from_slice(&(-1i32).to_be_bytes()),
+                        }
+                    }
+                    message(out, b'D', &body);
+                }
+                format!("SELECT {}", output.rows.len())
+            }
+            _ => {
+                if self.status == b'E' {
+                    // Any end of a failed transaction rolls it back
+                    manager.connection().execute_batch("ROLLBACK")?;
+                    self.status = b'I';
+                    "ROLLBACK".to_string()
+                } else {
+                    manager.connection().execute_batch(statement)?;
+                    match keyword {
+                        "BEGIN" | "START" => self.status = b'T',
+                        "COMMIT" | "END" | "ROLLBACK" | "ABORT" => self.status = b'I',
+                        _ => {}
+                    }
+                    command_tag(statement)
+                }
+            }
+        };
+
+        let mut body = Vec::new();
+        put_cstr(&mut body, &tag);
+        message(out, b'C', &body);
+        Ok(())
+    }
+
+    /// Appends a `RowDescription`, typing each column by its first
+    /// non-NULL value.
+    fn row_description(&self, out: &mut Vec<u8>, columns: &[String], rows: &[Vec<Value>]) {
+        let mut body = (columns.len() as i16).to_be_bytes().to_vec();
+        for (i, column) in columns.iter().enumerate() {
+            let type_oid = rows
+                .iter()
+                .filter_map(|row| row.get(i))
+                .find(|v| !matches!(v, Value::Null))
+                .map(type_oid)
+                .unwrap_or(oid::TEXT);
+            put_cstr(&mut body, column);
+            body.extend_from_slice(&0i32.to_be_bytes()); // table OID
+            body.extend_from_slice(&0i16.to_be_bytes()); // column number
+            body.extend_from_slice(&type_oid.to_be_bytes());
+            body.extend_from_slice(&(-1i16).to_be_bytes()); // type size
+            body.extend_from_slice(&(-1i32).to_be_bytes()); // type modifier
+            body.extend_from_slice(&0i16.to_be_bytes()); // text format
+        }
+        message(out, b'T', &body);
+    }
+}
+
+/// Accepts PostgreSQL clients until the process is interrupted, serving
+/// each on its own thread with a connection of the pool.
+pub fn serve_postgres(options: &ServeOptions) -> Result<()> {
+    let pool = Arc::new(ConnectionPool::new(
+        open_database(options)?,
+        options.pool.clone(),
+    )?);
+    let listener = TcpListener::bind(&options.listen)
+        .with_context(|| format!("Failed to listen on {}", options.listen))?;
+
+    info!(
+        "🐘 Serving {} over the PostgreSQL protocol on {} ({}{}, {} connections, experimental)",
+        options.database,
+        options.listen,
+        if options.read_write {
+            "read-write"
+        } else {
+            "read-only"
+        },
+        if options.token.is_some() {
+            ", password required"
+        } else {
+            ""
+        },
+        pool.size()
+    );
+
+    let max_clients = pool.size() + options.pool.max_waiting;
+    let clients = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        let peer = stream
+            .peer_addr()
+            .map(|a| a.to_string())
+            .unwrap_or_default();
+        if clients.load(Ordering::Acquire) >= max_clients {
+            warn!(
+                "Refusing client {}: {} clients connected",
+                peer, max_clients
+            );
+            let mut out = Vec::new();
+            error_response(
+                &mut out,
+                "FATAL",
+                "53300",
+                "sorry, too many clients already",
+            );
+            let _ = stream.write_all(&out);
+            continue;
+        }
+
+        let mut session = PgSession::with_pool(Arc::clone(&pool), options.token.clone())
+            .with_limits(options.limits);
+        clients.fetch_add(1, Ordering::AcqRel);
+        let clients = Arc::clone(&clients);
+        thread::spawn(move || {
+            let _connection = Metrics::global().connection(Protocol::Postgres.as_str());
+            info!("Client {} connected", peer);
+            if let Err(e) = session.run_tcp(stream) {
+                debug!("Client {} disconnected: {:#}", peer, e);
+            }
+            clients.fetch_sub(1, Ordering::AcqRel);
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A client script on one side and the server's replies on the other.
+    struct Duplex {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Duplex {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Duplex {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn startup(user: &str) -> Vec<u8> {
+        let mut body = PROTOCOL_VERSION_3.to_be_bytes().to_vec();
+        put_cstr(&mut body, "user");
+        put_cstr(&mut body, user);
+        body.push(0);
+        let mut out = (body.len() as i32 + 4).to_be_bytes().to_vec();
+        out.extend_from_slice(&body);
+        out
+    }
+
+    fn frontend(tag: u8, text: &str) -> Vec<u8> {
+        let mut body = Vec::new();
+        put_cstr(&mut body, text);
+        let mut out = Vec::new();
+        message(&mut out, tag, &body);
+        out
+    }
+
+    /// Splits server output into `(tag, body)` messages.
+    fn messages(mut output: &[u8]) -> Vec<(u8, Vec<u8>)> {
+        let mut messages = Vec::new();
+        while !output.is_empty() {
+            let len = i32::from_be_bytes(output[1..5].try_into().unwrap()) as usize;
+            messages.push((output[0], output[5..1 + len].to_vec()));
+            output = &output[1 + len..];
+        }
+        messages
+    }
+
+    fn run(token: Option<&str>, script: Vec<u8>) -> Vec<(u8, Vec<u8>)> {
+        run_limited(token, QueryLimits::default(), script)
+    }
+
+    fn run_limited(
+        token: Option<&str>,
+        limits: QueryLimits,
+        script: Vec<u8>,
+    ) -> Vec<(u8, Vec<u8>)> {
+        let conn = duckdb::Connection::open_in_memory().unwrap();
+        let mut session = PgSession::new(conn, token.map(String::from))
+            .unwrap()
+            .with_limits(limits);
+        converse(&mut session, script)
+    }
+
+    fn converse(session: &mut PgSession, script: Vec<u8>) -> Vec<(u8, Vec<u8>)> {
+        let mut duplex = Duplex {
+            input: Cursor::new(script),
+            output: Vec::new(),
+        };
+        session.run(&mut duplex).unwrap();
+        messages(&duplex.output)
+    }
+
+    #[test]
+    fn test_simple_query() {
+        let mut script = startup("duckdb");
+        script.extend(frontend(
+            b'Q',
+            "CREATE TABLE t (id INTEGER, name TEXT); INSERT INTO t VALUES (1, 'a'), (2, NULL); SELECT * FROM t ORDER BY id",
+        ));
+        script.extend(frontend(b'X', ""));
+        let replies = run(None, script);
+
+        let tags: Vec<u8> = replies.iter().map(|(tag, _)| *tag).collect();
+        assert_eq!(
+            tags,
+            b"RSSSSSSKZCCTDDCZ".to_vec(),
+            "unexpected message sequence"
+        );
+        let completions: Vec<&[u8]> = replies
+            .iter()
+            .filter(|(tag, _)| *tag == b'C')
+            .map(|(_, body)| &body[..body.len() - 1])
+            .collect();
+        assert_eq!(
+            completions,
+            [&b"CREATE TABLE"[..], b"INSERT 0 2", b"SELECT 2"]
+        );
+
+        // Second row: id "2", name NULL
+        let (_, row) = &replies[13];
+        assert_eq!(&row[..2], &2i16.to_be_bytes());
+        assert_eq!(&row[2..7], &[0, 0, 0, 1, b'2']);
+        assert_eq!(&row[7..], &(-1i32).to_be_bytes());
+    }
+
+    #[test]
+    fn test_errors_and_extended_protocol() {
+        let mut script = startup("duckdb");
+        script.extend(frontend(b'Q', "SELECT * FROM missing"));
+        script.extend(frontend(b'P', "SELECT 1"));
+        script.extend(frontend(b'B', ""));
+        script.extend(frontend(b'S', ""));
+        let replies = run(None, script);
+
+        let tags: Vec<u8> = replies.iter().skip(9).map(|(tag, _)| *tag).collect();
+        assert_eq!(tags, b"EZEZ".to_vec());
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits = QueryLimits {
+            max_rows: Some(5),
+            timeout: Some(std::time::Duration::from_millis(100)),
+        };
+        let mut script = startup("duckdb");
+        script.extend(frontend(b'Q', "SELECT * FROM range(10)"));
+        script.extend(frontend(
+            b'Q',
+            "SELECT COUNT(*) FROM range(10000000000) a, range(10000000000) b",
+        ));
+        script.extend(frontend(b'Q', "SELECT * FROM range(5)"));
+        let replies = run_limited(None, limits, script);
+
+        let errors: Vec<&Vec<u8>> = replies
+            .iter()
+            .filter(|(tag, _)| *tag == b'E')
+            .map(|(_, body)| body)
+            .collect();
+        assert_eq!(errors.len(), 2);
+        assert!(String::from_utf8_lossy(errors[0]).contains("limit of 5 rows"));
+        assert!(String::from_utf8_lossy(errors[1]).contains("57014"));
+        let rows = replies.iter().filter(|(tag, _)| *tag == b'D').count();
+        assert_eq!(rows, 5);
+    }
+
+    #[test]
+    fn test_clients_share_the_pool() -> Result<()> {
+        let options = PoolOptions {
+            size: 1,
+            max_waiting: 0,
+            wait_timeout: Duration::ZERO,
+        };
+        let conn = duckdb::Connection::open_in_memory()?;
+        let pool = Arc::new(ConnectionPool::new(conn, options)?);
+
+        // A transaction the client leaves open is rolled back
+        let mut script = startup("duckdb");
+        script.extend(frontend(b'Q', "BEGIN; CREATE TABLE t AS SELECT 1 AS x"));
+        let mut session = PgSession::with_pool(Arc::clone(&pool), None);
+        let replies = converse(&mut session, script);
+        assert_eq!(replies.last(), Some(&(b'Z', b"T".to_vec())));
+
+        let mut script = startup("duckdb");
+        script.extend(frontend(b'Q', "SELECT * FROM t"));
+        let mut session = PgSession::with_pool(Arc::clone(&pool), None);
+        let tags: Vec<u8> = converse(&mut session, script)
+            .iter()
+            .skip(9)
+            .map(|(tag, _)| *tag)
+            .collect();
+        assert_eq!(tags, b"EZ".to_vec());
+
+        // Without a free connection, the client is refused
+        let _held = pool.get()?;
+        let mut session = PgSession::with_pool(Arc::clone(&pool), None);
+        let replies = converse(&mut session, startup("duckdb"));
+        let (tag, body) = replies.last().unwrap();
+        assert_eq!(*tag, b'E');
+        assert!(String::from_utf8_lossy(body).contains("53300"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_password_authentication() {
+        let mut script = startup("duckdb");
+        script.extend(frontend(b'p', "wrong"));
+        let replies = run(Some("s3cret"), script);
+        assert_eq!(replies[0], (b'R', 3i32.to_be_bytes().to_vec()));
+        assert_eq!(replies[1].0, b'E');
+        assert_eq!(replies.len(), 2);
+
+        let mut script = startup("duckdb");
+        script.extend(frontend(b'p', "s3cret"));
+        let replies = run(Some("s3cret"), script);
+        assert_eq!(replies[1], (b'R', 0i32.to_be_bytes().to_vec()));
+    }
+
+    #[test]
+    fn test_oversized_startup_is_refused() {
+        let conn = duckdb::Connection::open_in_memory().unwrap();
+        let mut session = PgSession::new(conn, Some("s3cret".to_string())).unwrap();
+        let mut script = ((MAX_STARTUP_BYTES + 1) as i32).to_be_bytes().to_vec();
+        script.extend(PROTOCOL_VERSION_3.to_be_bytes());
+        let mut duplex = Duplex {
+            input: Cursor::new(script),
+            output: Vec::new(),
+        };
+        let err = session.run(&mut duplex).unwrap_err();
+        assert!(
+            err.to_string().contains("Invalid message length"),
+            "{}",
+            err
+        );
+
+        // A password can't be larger either
+        let mut script = startup("duckdb");
+        script.push(b'p');
+        script.extend(((MAX_STARTUP_BYTES + 1) as i32).to_be_bytes());
+        let mut duplex = Duplex {
+            input: Cursor::new(script),
+            output: Vec::new(),
+        };
+        assert!(session.run(&mut duplex).is_err());
+    }
+
+    #[test]
+    fn test_text_values_and_tags() {
+        assert_eq!(text_value(&Value::Boolean(true)).as_deref(), Some("t"));
+        assert_eq!(
+            text_value(&Value::Blob(vec![0xde, 0xad])).as_deref(),
+            Some("\\xdead")
+        );
+        assert_eq!(
+            text_value(&Value::Date32(19_000)).as_deref(),
+            Some("2022-01-08")
+        );
+        assert_eq!(text_value(&Value::Null), None);
+        assert_eq!(type_oid(&Value::BigInt(1)), oid::INT8);
+        assert_eq!(
+            command_tag("-- note\ncreate view v as select 1"),
+            "CREATE VIEW"
+        );
+        assert_eq!(command_tag("begin transaction"), "BEGIN");
+    }
+}
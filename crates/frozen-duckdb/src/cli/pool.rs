@@ -0,0 +1,447 @@
+//! # Connection Pool for the Query Server
+//!
+//! `frozen-duckdb serve` handles every HTTP client on its own thread, and
+//! each query borrows one of `--max-connections` connections to the served
+//! database for as long as it runs. DuckDB already spreads a single query
+//! across all cores, so a few connections are usually enough; they let
+//! short queries proceed while a long one is running. PostgreSQL clients
+//! (`--protocol postgres`) instead hold a connection from login until they
+//! disconnect; see [`super::pgwire`].
+//!
+//! A connection goes back to the pool as a new session on the same
+//! database, so an open transaction, temporary tables, `SET` and `USE` of
+//! one client never reach the next. Closing the old session rolls back its
+//! transaction.
+//!
+//! ## Backpressure
+//!
+//! When every connection is busy, requests wait in a queue of at most
+//! `--max-queue` requests for up to `--queue-timeout`. A request arriving
+//! at a full queue, or still waiting when the timeout expires, is answered
+//! with `503 Service Unavailable` and `Retry-After`, so clients and load
+//! balancers back off instead of piling up.
+//!
+//! ## Cancellation
+//!
+//! A [`Watchdog`] interrupts the running query when it exceeds the
+//! per-request `--timeout`, or when the client disconnects before the
+//! result is ready, so abandoned queries stop using the database.
+//!
+//! | Setting | Default | Effect |
+//! |---------|---------|--------|
+//! | `--max-connections` | 4 | Queries running at once |
+//! | `--max-queue` | 64 | Requests waiting for a connection |
+//! | `--queue-timeout` | 30s | Longest wait for a connection |
+//! | `--timeout` | none | Longest a query may run |
+//! | `--max-rows` | none | Largest result returned |
+//!
+//! # Examples
+//!
+//! ```rust
+//! use duckdb::Connection;
+//! use frozen_duckdb::cli::pool::{ConnectionPool, PoolOptions};
+//!
+//! let pool = ConnectionPool::new(Connection::open_in_memory()?, PoolOptions::default())?;
+//! let manager = pool.get()?;
+//! let output = manager.run_query("SELECT 42 AS answer")?;
+//! assert_eq!(output.rows.len(), 1);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use super::dataset_manager::DatasetManager;
+use anyhow::{anyhow, Result};
+use duckdb::{Connection, InterruptHandle};
+use std::io::ErrorKind;
+use std::net::TcpStream;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// How often a [`Watchdog`] checks its deadline and client.
+const WATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Size and queueing limits of a [`ConnectionPool`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolOptions {
+    /// Number of connections, and so of queries running at once
+    pub size: usize,
+    /// Requests allowed to wait for a connection
+    pub max_waiting: usize,
+    /// Longest a request waits for a connection
+    pub wait_timeout: Duration,
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        Self {
+            size: 4,
+            max_waiting: 64,
+            wait_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+struct PoolState {
+    idle: Vec<DatasetManager>,
+    waiting: usize,
+}
+
+/// A fixed set of connections to one database, lent out one query at a
+/// time.
+pub struct ConnectionPool {
+    state: Mutex<PoolState>,
+    returned: Condvar,
+    options: PoolOptions,
+}
+
+impl ConnectionPool {
+    /// Creates a pool of `options.size` connections sharing the database of
+    /// `conn`.
+    pub fn new(conn: Connection, options: PoolOptions) -> Result<Self> {
+        let size = options.size.max(1);
+        let mut idle = Vec::with_capacity(size);
+        for _ in 1..size {
+            idle.push(DatasetManager::with_connection(conn.try_clone()?)?);
+        }
+        idle.push(DatasetManager::with_connection(conn)?);
+
+        Ok(Self {
+            state: Mutex::new(PoolState { idle, waiting: 0 }),
+            returned: Condvar::new(),
+            options: PoolOptions { size, ..options },
+        })
+    }
+
+    /// Returns the number of connections in the pool.
+    pub fn size(&self) -> usize {
+        self.options.size
+    }
+
+    /// Borrows a connection, waiting in the queue while all are busy.
+    ///
+    /// Fails at once when the queue is full, and when no connection was
+    /// returned within the wait timeout.
+    pub fn get(&self) -> Result<PooledConnection<'_>> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| anyhow!("Connection pool poisoned"))?;
+        if let Some(manager) = state.idle.pop() {
+            return Ok(PooledConnection::new(self, manager));
+        }
+        if state.waiting >= self.options.max_waiting {
+            return Err(anyhow!(
+                "Server busy: {} queries running and {} waiting",
+                self.options.size,
+                state.waiting
+            ));
+        }
+
+        state.waiting += 1;
+        let deadline = Instant::now() + self.options.wait_timeout;
+        let manager = loop {
+            if let Some(manager) = state.idle.pop() {
+                break Some(manager);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break None;
+            }
+            state = match self.returned.wait_timeout(state, remaining) {
+                Ok((state, _)) => state,
+                Err(_) => return Err(anyhow!("Connection pool poisoned")),
+            };
+        };
+        state.waiting -= 1;
+
+        manager
+            .map(|manager| PooledConnection::new(self, manager))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Server busy: no connection became free within {:?}",
+                    self.options.wait_timeout
+                )
+            })
+    }
+
+    /// Returns the slot of `manager` on a new session, or gives it up if no
+    /// new session can be opened.
+    fn put(&self, manager: DatasetManager) {
+        let manager = match manager.new_session() {
+            Ok(fresh) => fresh,
+            Err(e) => {
+                warn!(
+                    "Closing a pooled connection that couldn't be reset: {:#}",
+                    e
+                );
+                return;
+            }
+        };
+        if let Ok(mut state) = self.state.lock() {
+            state.idle.push(manager);
+            self.returned.notify_one();
+        }
+    }
+}
+
+/// A connection borrowed from a [`ConnectionPool`], returned as a new
+/// session when dropped.
+pub struct PooledConnection<'a> {
+    pool: &'a ConnectionPool,
+    manager: Option<DatasetManager>,
+}
+
+impl<'a> PooledConnection<'a> {
+    fn new(pool: &'a ConnectionPool, manager: DatasetManager) -> Self {
+        Self {
+            pool,
+            manager: Some(manager),
+        }
+    }
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = DatasetManager;
+
+    fn deref(&self) -> &DatasetManager {
+        self.manager
+            .as_ref()
+            .expect("connection is held until drop")
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        if let Some(manager) = self.manager.take() {
+            self.pool.put(manager);
+        }
+    }
+}
+
+/// Why a [`Watchdog`] interrupted a query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cancellation {
+    /// The query ran longer than the time limit
+    TimedOut(Duration),
+    /// The client closed its connection
+    Disconnected,
+}
+
+/// Interrupts a running query on timeout or client disconnect until
+/// [`stop`](Self::stop) is called.
+///
+/// # Examples
+///
+/// ```rust
+/// use duckdb::Connection;
+/// use frozen_duckdb::cli::pool::{Cancellation, Watchdog};
+/// use std::time::Duration;
+///
+/// let conn = Connection::open_in_memory()?;
+/// let watchdog = Watchdog::start(conn.interrupt_handle(), Some(Duration::from_millis(100)), None);
+/// let result = conn.execute_batch("SELECT COUNT(*) FROM range(10000000000)");
+/// assert!(result.is_err());
+/// assert!(matches!(watchdog.stop(), Some(Cancellation::TimedOut(_))));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct Watchdog {
+    done: Arc<AtomicBool>,
+    thread: Option<JoinHandle<Option<Cancellation>>>,
+}
+
+impl Watchdog {
+    /// Starts watching the query about to run on the connection of
+    /// `interrupt`. Without a `timeout` or `client` there is nothing to
+    /// watch and no thread is started.
+    pub fn start(
+        interrupt: Arc<InterruptHandle>,
+        timeout: Option<Duration>,
+        client: Option<&TcpStream>,
+    ) -> Self {
+        let client = client.and_then(|stream| stream.try_clone().ok());
+        if timeout.is_none() && client.is_none() {
+            return Self {
+                done: Arc::new(AtomicBool::new(true)),
+                thread: None,
+            };
+        }
+
+        let done = Arc::new(AtomicBool::new(false));
+        let watching = Arc::clone(&done);
+        let started = Instant::now();
+        let thread = thread::spawn(move || {
+            if let Some(stream) = &client {
+                // Bounds each peek, so the loop wakes up to check the flags
+                let _ = stream.set_read_timeout(Some(WATCH_INTERVAL));
+            }
+            let cancellation = loop {
+                if watching.load(Ordering::Acquire) {
+                    return None;
+                }
+                if let Some(limit) = timeout.filter(|limit| started.elapsed() >= *limit) {
+                    break Cancellation::TimedOut(limit);
+                }
+                match &client {
+                    Some(stream) if client_disconnected(stream) => {
+                        break Cancellation::Disconnected;
+                    }
+                    Some(_) => {}
+                    None => thread::sleep(WATCH_INTERVAL),
+                }
+            };
+            // An interrupt arriving before the query starts is lost, so
+            // repeat it until the query has returned
+            while !watching.load(Ordering::Acquire) {
+                interrupt.interrupt();
+                thread::sleep(WATCH_INTERVAL);
+            }
+            Some(cancellation)
+        });
+
+        Self {
+            done,
+            thread: Some(thread),
+        }
+    }
+
+    /// Stops watching and returns why the query was interrupted, if it was.
+    pub fn stop(mut self) -> Option<Cancellation> {
+        self.done.store(true, Ordering::Release);
+        self.thread
+            .take()
+            .and_then(|thread| thread.join().ok().flatten())
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.done.store(true, Ordering::Release);
+    }
+}
+
+/// Whether the peer of `stream` closed the connection, waiting at most the
+/// stream's read timeout to find out.
+fn client_disconnected(stream: &TcpStream) -> bool {
+    let mut byte = [0u8; 1];
+    match stream.peek(&mut byte) {
+        Ok(0) => true,
+        Ok(_) => {
+            // The client sent more data; it is still there
+            thread::sleep(WATCH_INTERVAL);
+            false
+        }
+        Err(e) => !matches!(
+            e.kind(),
+            ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    fn pool(size: usize, max_waiting: usize, wait_timeout: Duration) -> ConnectionPool {
+        let options = PoolOptions {
+            size,
+            max_waiting,
+            wait_timeout,
+        };
+        ConnectionPool::new(Connection::open_in_memory().unwrap(), options).unwrap()
+    }
+
+    #[test]
+    fn test_connections_share_the_database() -> Result<()> {
+        let pool = pool(2, 0, Duration::ZERO);
+        let first = pool.get()?;
+        let second = pool.get()?;
+        first
+            .connection()
+            .execute_batch("CREATE TABLE t AS SELECT 1 AS x")?;
+        assert_eq!(second.run_query("SELECT x FROM t")?.rows.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_full_queue_is_rejected() -> Result<()> {
+        let pool = pool(1, 0, Duration::from_secs(5));
+        let held = pool.get()?;
+        let error = pool.get().err().unwrap();
+        assert!(error.to_string().contains("Server busy"));
+
+        drop(held);
+        assert!(pool.get().is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_waiting_request_gets_returned_connection() -> Result<()> {
+        let pool = pool(1, 1, Duration::from_secs(5));
+        thread::scope(|scope| -> Result<()> {
+            let held = pool.get()?;
+            let waiter = scope.spawn(|| pool.get().map(|c| c.run_query("SELECT 1").is_ok()));
+            thread::sleep(Duration::from_millis(100));
+            drop(held);
+            assert!(waiter.join().unwrap()?);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_wait_times_out() -> Result<()> {
+        let pool = pool(1, 1, Duration::from_millis(50));
+        let _held = pool.get()?;
+        let error = pool.get().err().unwrap();
+        assert!(error.to_string().contains("within"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_watchdog_interrupts_slow_query() {
+        let conn = Connection::open_in_memory().unwrap();
+        let watchdog = Watchdog::start(
+            conn.interrupt_handle(),
+            Some(Duration::from_millis(100)),
+            None,
+        );
+        let result =
+            conn.execute_batch("SELECT COUNT(*) FROM range(10000000000) a, range(10000000000) b");
+        assert!(result.is_err());
+        assert_eq!(
+            watchdog.stop(),
+            Some(Cancellation::TimedOut(Duration::from_millis(100)))
+        );
+    }
+
+    #[test]
+    fn test_watchdog_detects_disconnect() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let client = TcpStream::connect(listener.local_addr()?)?;
+        let (server_side, _) = listener.accept()?;
+
+        let conn = Connection::open_in_memory()?;
+        let watchdog = Watchdog::start(conn.interrupt_handle(), None, Some(&server_side));
+        let disconnect = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            drop(client);
+        });
+        let result =
+            conn.execute_batch("SELECT COUNT(*) FROM range(10000000000) a, range(10000000000) b");
+        disconnect.join().unwrap();
+        assert!(result.is_err());
+        assert_eq!(watchdog.stop(), Some(Cancellation::Disconnected));
+        Ok(())
+    }
+
+    #[test]
+    fn test_watchdog_without_limits_reports_nothing() {
+        let conn = Connection::open_in_memory().unwrap();
+        let watchdog = Watchdog::start(conn.interrupt_handle(), None, None);
+        conn.execute_batch("SELECT 1").unwrap();
+        assert_eq!(watchdog.stop(), None);
+    }
+}
@@ -0,0 +1,178 @@
+//! # Progress Reporting for Long-Running CLI Commands
+//!
+//! This module draws a single-line progress bar with throughput and ETA on
+//! stderr, e.g.
+//!
+//! ```text
+//! [=========>                    ] 31250/100000 (31.2%) 12.4/s ETA 1h 32m
+//! ```
+//!
+//! The bar is only drawn when stderr is a terminal, so piped output and
+//! log files stay clean. Redraws are throttled to a few per second.
+
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+const BAR_WIDTH: usize = 30;
+const REDRAW_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Progress bar for a known number of items.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::progress::ProgressBar;
+///
+/// let mut progress = ProgressBar::new(1000);
+/// for _ in 0..1000 {
+///     // process one item
+///     progress.inc(1);
+/// }
+/// progress.finish();
+/// ```
+pub struct ProgressBar {
+    total: usize,
+    position: usize,
+    /// Position when timing started; items before it don't count toward throughput
+    start_position: usize,
+    started: Instant,
+    last_draw: Option<Instant>,
+    enabled: bool,
+}
+
+impl ProgressBar {
+    /// Creates a progress bar for `total` items.
+    pub fn new(total: usize) -> Self {
+        Self {
+            total,
+            position: 0,
+            start_position: 0,
+            started: Instant::now(),
+            last_draw: None,
+            enabled: io::stderr().is_terminal() && !super::output::quiet(),
+        }
+    }
+
+    /// Starts from `position` items already done, e.g. when resuming.
+    pub fn with_position(mut self, position: usize) -> Self {
+        self.position = position;
+        self.start_position = position;
+        self
+    }
+
+    /// Advances the bar by `n` items.
+    pub fn inc(&mut self, n: usize) {
+        self.position += n;
+        let due = self
+            .last_draw
+            .is_none_or(|last| last.elapsed() >= REDRAW_INTERVAL);
+        if due || self.position >= self.total {
+            self.draw();
+        }
+    }
+
+    /// Draws the final state and ends the line.
+    pub fn finish(&mut self) {
+        if self.enabled {
+            self.draw();
+            eprintln!();
+        }
+    }
+
+    /// Items processed per second since the bar was created.
+    pub fn rate(&self) -> f64 {
+        let secs = self.started.elapsed().as_secs_f64();
+        if secs > 0.0 {
+            (self.position - self.start_position) as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    /// Estimated time until all items are processed, if the rate is known.
+    pub fn eta(&self) -> Option<Duration> {
+        let rate = self.rate();
+        (rate > 0.0).then(|| {
+            let remaining = self.total.saturating_sub(self.position);
+            Duration::from_secs_f64(remaining as f64 / rate)
+        })
+    }
+
+    fn draw(&mut self) {
+        self.last_draw = Some(Instant::now());
+        if !self.enabled {
+            return;
+        }
+        let mut stderr = io::stderr();
+        let _ = write!(stderr, "\r{}", self.render());
+        let _ = stderr.flush();
+    }
+
+    /// Renders the bar as a single line.
+    pub fn render(&self) -> String {
+        let fraction = if self.total == 0 {
+            1.0
+        } else {
+            (self.position as f64 / self.total as f64).min(1.0)
+        };
+        let filled = (fraction * BAR_WIDTH as f64) as usize;
+        let bar = if filled >= BAR_WIDTH {
+            "=".repeat(BAR_WIDTH)
+        } else {
+            format!(
+                "{}>{}",
+                "=".repeat(filled),
+                " ".repeat(BAR_WIDTH - filled - 1)
+            )
+        };
+        let eta = self.eta().map_or_else(|| "--".to_string(), format_eta);
+
+        format!(
+            "[{}] {}/{} ({:.1}%) {:.1}/s ETA {}",
+            bar,
+            self.position,
+            self.total,
+            fraction * 100.0,
+            self.rate(),
+            eta
+        )
+    }
+}
+
+fn format_eta(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, (secs % 3600) / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_bar_and_counts() {
+        let mut progress = ProgressBar::new(200).with_position(50);
+        progress.enabled = false;
+        progress.inc(50);
+
+        let line = progress.render();
+        assert!(line.starts_with(&format!("[{}>", "=".repeat(15))));
+        assert!(line.contains("100/200 (50.0%)"));
+
+        progress.inc(100);
+        assert!(progress
+            .render()
+            .starts_with(&format!("[{}]", "=".repeat(BAR_WIDTH))));
+        assert_eq!(progress.eta(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_format_eta() {
+        assert_eq!(format_eta(Duration::from_secs(42)), "42s");
+        assert_eq!(format_eta(Duration::from_secs(192)), "3m 12s");
+        assert_eq!(format_eta(Duration::from_secs(5520)), "1h 32m");
+    }
+}
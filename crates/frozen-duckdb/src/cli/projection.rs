@@ -0,0 +1,679 @@
+//! # Embedding Projection for Frozen DuckDB CLI
+//!
+//! This module projects the embeddings of an index built with the `index`
+//! command down to two dimensions, so clusters can be inspected visually.
+//!
+//! ## Methods
+//!
+//! | Method | Notes |
+//! |--------|-------|
+//! | `pca` | First two principal components, by power iteration. Fast and deterministic; preserves global structure |
+//! | `umap` | Requires the `umap` feature. Neighbor-graph layout that separates local clusters better; exact neighbors, so intended for up to tens of thousands of points |
+//!
+//! ## Output
+//!
+//! Points are written as `doc_id, content, x, y` rows in the format of the
+//! output extension (`.parquet`, `.csv`, `.json`), or as a self-contained
+//! HTML scatter plot (`.html`) that shows each document on hover.
+
+use super::dedupe::copy_format;
+use super::embedding_index::EmbeddingIndex;
+use super::sql_path::path_literal;
+use anyhow::{anyhow, Context, Result};
+use duckdb::{params, Connection};
+use std::fs;
+use tracing::info;
+
+/// Power iterations per principal component.
+const POWER_ITERATIONS: usize = 100;
+
+/// Characters of document content shown in HTML tooltips.
+const TOOLTIP_CHARS: usize = 280;
+
+/// Projection algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectionMethod {
+    /// Principal component analysis
+    Pca,
+    /// Uniform manifold approximation and projection
+    #[cfg(feature = "umap")]
+    Umap,
+}
+
+impl ProjectionMethod {
+    /// Parses a method name.
+    pub fn parse(method: &str) -> Result<Self> {
+        match method {
+            "pca" => Ok(Self::Pca),
+            #[cfg(feature = "umap")]
+            "umap" => Ok(Self::Umap),
+            #[cfg(not(feature = "umap"))]
+            "umap" => Err(anyhow!(
+                "UMAP support is not compiled in; rebuild with --features umap"
+            )),
+            other => Err(anyhow!(
+                "Unknown projection method: {} (use pca or umap)",
+                other
+            )),
+        }
+    }
+}
+
+/// A document placed in two dimensions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectedPoint {
+    /// Document id
+    pub id: String,
+    /// Document content
+    pub content: String,
+    /// Horizontal coordinate
+    pub x: f32,
+    /// Vertical coordinate
+    pub y: f32,
+}
+
+/// Projects the documents of `index` to two dimensions.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::embedding_index::EmbeddingIndex;
+/// use frozen_duckdb::cli::projection::{project_index, write_points, ProjectionMethod};
+/// use frozen_duckdb::cli::DatasetManager;
+///
+/// let index = EmbeddingIndex::open("embeddings.duckdb")?;
+/// let points = project_index(&index, ProjectionMethod::Pca, None)?;
+///
+/// let manager = DatasetManager::new()?;
+/// write_points(manager.connection(), &points, "points.html")?;
+/// ```
+pub fn project_index(
+    index: &EmbeddingIndex,
+    method: ProjectionMethod,
+    limit: Option<usize>,
+) -> Result<Vec<ProjectedPoint>> {
+    let (documents, embeddings): (Vec<_>, Vec<_>) = index.embeddings(limit)?.into_iter().unzip();
+    if documents.is_empty() {
+        return Err(anyhow!(
+            "Index is empty; build it with the index command first"
+        ));
+    }
+
+    info!(
+        "📉 Projecting {} embeddings with {:?}",
+        documents.len(),
+        method
+    );
+    let coordinates = match method {
+        ProjectionMethod::Pca => pca(&embeddings)?,
+        #[cfg(feature = "umap")]
+        ProjectionMethod::Umap => umap::umap(&embeddings, &umap::UmapOptions::default())?,
+    };
+
+    Ok(documents
+        .into_iter()
+        .zip(coordinates)
+        .map(|(document, [x, y])| ProjectedPoint {
+            id: document.id,
+            content: document.content,
+            x,
+            y,
+        })
+        .collect())
+}
+
+/// Projects `embeddings` onto their first two principal components.
+///
+/// Components are found by power iteration on the covariance without
+/// forming it, so memory stays O(n·d). Each component's sign is fixed so
+/// its largest coordinate is positive, making the output deterministic.
+pub fn pca(embeddings: &[Vec<f32>]) -> Result<Vec<[f32; 2]>> {
+    let Some(first) = embeddings.first() else {
+        return Ok(Vec::new());
+    };
+    let dimension = first.len();
+    if embeddings.iter().any(|e| e.len() != dimension) {
+        return Err(anyhow!("Embeddings have different dimensions"));
+    }
+
+    let n = embeddings.len() as f64;
+    let mut mean = vec![0.0f64; dimension];
+    for embedding in embeddings {
+        for (m, &v) in mean.iter_mut().zip(embedding) {
+            *m += v as f64 / n;
+        }
+    }
+    let centered: Vec<Vec<f64>> = embeddings
+        .iter()
+        .map(|e| e.iter().zip(&mean).map(|(&v, m)| v as f64 - m).collect())
+        .collect();
+
+    let mut components: Vec<Vec<f64>> = Vec::new();
+    for _ in 0..2.min(dimension) {
+        // Deterministic start that is unlikely to be orthogonal to the
+        // leading component
+        let mut v: Vec<f64> = (0..dimension).map(|i| 1.0 / (i + 1) as f64).collect();
+        normalize(&mut v);
+        for _ in 0..POWER_ITERATIONS {
+            let mut w = vec![0.0; dimension];
+            for row in &centered {
+                let s = dot(row, &v);
+                for (wi, ri) in w.iter_mut().zip(row) {
+                    *wi += s * ri;
+                }
+            }
+            for u in &components {
+                let p = dot(&w, u);
+                for (wi, ui) in w.iter_mut().zip(u) {
+                    *wi -= p * ui;
+                }
+            }
+            if !normalize(&mut w) {
+                // No variance left in this direction
+                break;
+            }
+            v = w;
+        }
+        let largest = v
+            .iter()
+            .copied()
+            .max_by(|a, b| a.abs().total_cmp(&b.abs()))
+            .unwrap_or(0.0);
+        if largest < 0.0 {
+            v.iter_mut().for_each(|x| *x = -*x);
+        }
+        components.push(v);
+    }
+
+    Ok(centered
+        .iter()
+        .map(|row| {
+            let mut point = [0.0f32; 2];
+            for (coordinate, component) in point.iter_mut().zip(&components) {
+                *coordinate = dot(row, component) as f32;
+            }
+            point
+        })
+        .collect())
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Scales `v` to unit length; returns false if it is (nearly) zero.
+fn normalize(v: &mut [f64]) -> bool {
+    let norm = dot(v, v).sqrt();
+    if norm < 1e-12 {
+        return false;
+    }
+    v.iter_mut().for_each(|x| *x /= norm);
+    true
+}
+
+/// Writes `points` to `output`: an HTML scatter plot for `.html`, rows of
+/// `doc_id, content, x, y` otherwise.
+pub fn write_points(conn: &Connection, points: &[ProjectedPoint], output: &str) -> Result<()> {
+    if output.to_lowercase().ends_with(".html") {
+        fs::write(output, render_html(points, "Embedding projection"))
+            .with_context(|| format!("Failed to write {}", output))?;
+        return Ok(());
+    }
+
+    let format = copy_format(output)?;
+    conn.execute_batch(
+        "CREATE OR REPLACE TEMP TABLE projection_points (
+             doc_id VARCHAR, content VARCHAR, x FLOAT, y FLOAT
+         )",
+    )?;
+    let tx = conn.unchecked_transaction()?;
+    let mut insert = tx.prepare("INSERT INTO projection_points VALUES (?, ?, ?, ?)")?;
+    for point in points {
+        insert.execute(params![point.id, point.content, point.x, point.y])?;
+    }
+    drop(insert);
+    tx.commit()?;
+    conn.execute(
+        &format!(
+            "COPY projection_points TO {} ({})",
+            path_literal(output),
+            format
+        ),
+        [],
+    )?;
+    conn.execute_batch("DROP TABLE projection_points")?;
+    Ok(())
+}
+
+/// Renders a self-contained HTML scatter plot of `points`.
+pub fn render_html(points: &[ProjectedPoint], title: &str) -> String {
+    let data = serde_json::Value::Array(
+        points
+            .iter()
+            .map(|p| {
+                let mut text: String = p.content.chars().take(TOOLTIP_CHARS).collect();
+                if text.len() < p.content.len() {
+                    text.push('…');
+                }
+                serde_json::json!({ "id": p.id, "text": text, "x": p.x, "y": p.y })
+            })
+            .collect(),
+    );
+    // Keep `</script>` in document text from ending the data block
+    let data = data.to_string().replace("</", "<\\/");
+    let title = title
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    HTML_TEMPLATE
+        .replace("__TITLE__", &title)
+        .replace("__POINTS__", &data)
+}
+
+const HTML_TEMPLATE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>__TITLE__</title>
+<style>
+  body { margin: 0; font: 14px sans-serif; }
+  header { padding: 8px 12px; border-bottom: 1px solid #ddd; }
+  canvas { display: block; width: 100vw; height: calc(100vh - 40px); }
+  #tip { position: fixed; display: none; max-width: 360px; padding: 6px 8px;
+         background: #fff; border: 1px solid #999; box-shadow: 0 2px 6px #0003;
+         white-space: pre-wrap; pointer-events: none; }
+</style>
+</head>
+<body>
+<header>__TITLE__ — <span id="count"></span> documents</header>
+<canvas id="plot"></canvas>
+<div id="tip"></div>
+<script id="points" type="application/json">__POINTS__</script>
+<script>
+const points = JSON.parse(document.getElementById("points").textContent);
+const canvas = document.getElementById("plot");
+const tip = document.getElementById("tip");
+const ctx = canvas.getContext("2d");
+document.getElementById("count").textContent = points.length;
+
+const xs = points.map(p => p.x), ys = points.map(p => p.y);
+const minX = Math.min(...xs), maxX = Math.max(...xs);
+const minY = Math.min(...ys), maxY = Math.max(...ys);
+let screen = [];
+
+function draw() {
+  const ratio = window.devicePixelRatio || 1;
+  canvas.width = canvas.clientWidth * ratio;
+  canvas.height = canvas.clientHeight * ratio;
+  ctx.setTransform(ratio, 0, 0, ratio, 0, 0);
+  const w = canvas.clientWidth, h = canvas.clientHeight, pad = 20;
+  const sx = (maxX - minX) || 1, sy = (maxY - minY) || 1;
+  screen = points.map(p => [
+    pad + (p.x - minX) / sx * (w - 2 * pad),
+    h - pad - (p.y - minY) / sy * (h - 2 * pad),
+  ]);
+  ctx.fillStyle = "#3367d6aa";
+  for (const [x, y] of screen) {
+    ctx.beginPath();
+    ctx.arc(x, y, 3, 0, 2 * Math.PI);
+    ctx.fill();
+  }
+}
+
+canvas.addEventListener("mousemove", event => {
+  const rect = canvas.getBoundingClientRect();
+  const mx = event.clientX - rect.left, my = event.clientY - rect.top;
+  let best = -1, bestDistance = 64;
+  screen.forEach(([x, y], i) => {
+    const d = (x - mx) ** 2 + (y - my) ** 2;
+    if (d < bestDistance) { best = i; bestDistance = d; }
+  });
+  if (best < 0) { tip.style.display = "none"; return; }
+  tip.textContent = points[best].id + "\n\n" + points[best].text;
+  tip.style.left = event.clientX + 12 + "px";
+  tip.style.top = event.clientY + 12 + "px";
+  tip.style.display = "block";
+});
+canvas.addEventListener("mouseleave", () => { tip.style.display = "none"; });
+window.addEventListener("resize", draw);
+draw();
+</script>
+</body>
+</html>
+"##;
+
+/// UMAP layout, compiled with the `umap` feature.
+#[cfg(feature = "umap")]
+pub mod umap {
+    use super::pca;
+    use anyhow::{anyhow, Result};
+    use std::collections::HashMap;
+
+    /// Negative samples drawn per positive edge sample.
+    const NEGATIVE_SAMPLES: usize = 5;
+
+    /// UMAP parameters.
+    #[derive(Debug, Clone)]
+    pub struct UmapOptions {
+        /// Neighbors per point in the graph; larger values favour global
+        /// structure
+        pub neighbors: usize,
+        /// Minimum distance between points in the layout (0.0-1.0)
+        pub min_dist: f32,
+        /// Optimization epochs
+        pub epochs: usize,
+        /// Random seed, for reproducible layouts
+        pub seed: u64,
+    }
+
+    impl Default for UmapOptions {
+        fn default() -> Self {
+            Self {
+                neighbors: 15,
+                min_dist: 0.1,
+                epochs: 200,
+                seed: 42,
+            }
+        }
+    }
+
+    /// Lays out `embeddings` in two dimensions with UMAP.
+    ///
+    /// Uses exact cosine neighbors, a PCA initialization, and the standard
+    /// attractive/repulsive SGD with negative sampling.
+    pub fn umap(embeddings: &[Vec<f32>], options: &UmapOptions) -> Result<Vec<[f32; 2]>> {
+        let n = embeddings.len();
+        if options.neighbors < 2 {
+            return Err(anyhow!("UMAP needs at least 2 neighbors"));
+        }
+        if n <= options.neighbors {
+            // Too few points for a neighbor graph
+            return pca(embeddings);
+        }
+        let k = options.neighbors;
+
+        // Fuzzy neighbor graph
+        let norms: Vec<f32> = embeddings
+            .iter()
+            .map(|e| {
+                e.iter()
+                    .map(|v| v * v)
+                    .sum::<f32>()
+                    .sqrt()
+                    .max(f32::EPSILON)
+            })
+            .collect();
+        let mut graph: HashMap<(usize, usize), f32> = HashMap::new();
+        for i in 0..n {
+            let mut distances: Vec<(usize, f32)> = (0..n)
+                .filter(|&j| j != i)
+                .map(|j| {
+                    let dot: f32 = embeddings[i]
+                        .iter()
+                        .zip(&embeddings[j])
+                        .map(|(a, b)| a * b)
+                        .sum();
+                    (j, (1.0 - dot / (norms[i] * norms[j])).max(0.0))
+                })
+                .collect();
+            distances.select_nth_unstable_by(k - 1, |a, b| a.1.total_cmp(&b.1));
+            distances.truncate(k);
+
+            let rho = distances
+                .iter()
+                .map(|d| d.1)
+                .filter(|&d| d > 0.0)
+                .fold(f32::INFINITY, f32::min);
+            let rho = if rho.is_finite() { rho } else { 0.0 };
+            let sigma = smooth_sigma(&distances, rho, (k as f32).log2());
+            for &(j, d) in &distances {
+                let weight = (-((d - rho).max(0.0)) / sigma).exp();
+                let key = (i.min(j), i.max(j));
+                // Fuzzy union of the two directed memberships
+                let entry = graph.entry(key).or_insert(0.0);
+                *entry = *entry + weight - *entry * weight;
+            }
+        }
+        let mut edges: Vec<(usize, usize, f32)> =
+            graph.into_iter().map(|((i, j), w)| (i, j, w)).collect();
+        edges.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+        let max_weight = edges.iter().map(|e| e.2).fold(0.0, f32::max);
+
+        // Initialize from PCA, scaled to [-10, 10]
+        let mut layout = pca(embeddings)?;
+        let extent = layout
+            .iter()
+            .flat_map(|p| p.iter().map(|v| v.abs()))
+            .fold(f32::EPSILON, f32::max);
+        layout
+            .iter_mut()
+            .for_each(|p| p.iter_mut().for_each(|v| *v *= 10.0 / extent));
+
+        let (a, b) = fit_curve(options.min_dist);
+        let mut rng = XorShift(options.seed.max(1));
+        for epoch in 0..options.epochs {
+            let rate = 1.0 - epoch as f32 / options.epochs as f32;
+            for &(i, j, weight) in &edges {
+                if rng.unit() > weight / max_weight {
+                    continue;
+                }
+                let d2 = distance2(&layout[i], &layout[j]);
+                if d2 > 0.0 {
+                    let coefficient = -2.0 * a * b * d2.powf(b - 1.0) / (1.0 + a * d2.powf(b));
+                    move_points(&mut layout, i, j, coefficient, rate, true);
+                }
+                for _ in 0..NEGATIVE_SAMPLES {
+                    let other = rng.below(n);
+                    if other == i {
+                        continue;
+                    }
+                    let d2 = distance2(&layout[i], &layout[other]);
+                    let coefficient = 2.0 * b / ((0.001 + d2) * (1.0 + a * d2.powf(b)));
+                    move_points(&mut layout, i, other, coefficient, rate, false);
+                }
+            }
+        }
+        Ok(layout)
+    }
+
+    /// Finds sigma so the memberships of the neighbors sum to `target`.
+    fn smooth_sigma(distances: &[(usize, f32)], rho: f32, target: f32) -> f32 {
+        let (mut low, mut high, mut sigma) = (0.0f32, f32::INFINITY, 1.0f32);
+        for _ in 0..64 {
+            let sum: f32 = distances
+                .iter()
+                .map(|&(_, d)| (-((d - rho).max(0.0)) / sigma).exp())
+                .sum();
+            if (sum - target).abs() < 1e-5 {
+                break;
+            }
+            if sum > target {
+                high = sigma;
+                sigma = (low + high) / 2.0;
+            } else {
+                low = sigma;
+                sigma = if high.is_finite() {
+                    (low + high) / 2.0
+                } else {
+                    sigma * 2.0
+                };
+            }
+        }
+        sigma.max(1e-3)
+    }
+
+    /// Fits `1 / (1 + a·d^(2b))` to the UMAP target curve for `min_dist`
+    /// by grid search.
+    fn fit_curve(min_dist: f32) -> (f32, f32) {
+        let samples: Vec<(f32, f32)> = (1..=300)
+            .map(|i| {
+                let d = i as f32 * 0.01;
+                let target = if d < min_dist {
+                    1.0
+                } else {
+                    (-(d - min_dist)).exp()
+                };
+                (d, target)
+            })
+            .collect();
+        let mut best = (1.577, 0.895, f32::INFINITY);
+        for ai in 1..=100 {
+            for bi in 1..=40 {
+                let (a, b) = (ai as f32 * 0.05, bi as f32 * 0.05);
+                let error: f32 = samples
+                    .iter()
+                    .map(|&(d, target)| (1.0 / (1.0 + a * d.powf(2.0 * b)) - target).powi(2))
+                    .sum();
+                if error < best.2 {
+                    best = (a, b, error);
+                }
+            }
+        }
+        (best.0, best.1)
+    }
+
+    fn distance2(p: &[f32; 2], q: &[f32; 2]) -> f32 {
+        (p[0] - q[0]).powi(2) + (p[1] - q[1]).powi(2)
+    }
+
+    /// Moves `i` along the gradient towards or away from `j`; attractive
+    /// moves also move `j`.
+    fn move_points(
+        layout: &mut [[f32; 2]],
+        i: usize,
+        j: usize,
+        coefficient: f32,
+        rate: f32,
+        move_other: bool,
+    ) {
+        for axis in 0..2 {
+            let gradient =
+                (coefficient * (layout[i][axis] - layout[j][axis])).clamp(-4.0, 4.0) * rate;
+            layout[i][axis] += gradient;
+            if move_other {
+                layout[j][axis] -= gradient;
+            }
+        }
+    }
+
+    /// Small deterministic generator for sampling.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn unit(&mut self) -> f32 {
+            (self.next() >> 40) as f32 / (1u64 << 24) as f32
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_umap_separates_clusters() {
+            // Two tight clusters of 20 points on different axes
+            let embeddings: Vec<Vec<f32>> = (0..40)
+                .map(|i| {
+                    let jitter = (i % 20) as f32 * 0.001;
+                    if i < 20 {
+                        vec![1.0, jitter, 0.0]
+                    } else {
+                        vec![0.0, jitter, 1.0]
+                    }
+                })
+                .collect();
+            let options = UmapOptions {
+                neighbors: 5,
+                epochs: 100,
+                ..Default::default()
+            };
+            let layout = umap(&embeddings, &options).unwrap();
+            let centroid = |range: std::ops::Range<usize>| {
+                let n = range.len() as f32;
+                range.fold([0.0, 0.0], |c, i| {
+                    [c[0] + layout[i][0] / n, c[1] + layout[i][1] / n]
+                })
+            };
+            let (a, b) = (centroid(0..20), centroid(20..40));
+            let spread = (0..20)
+                .map(|i| distance2(&layout[i], &a))
+                .fold(0.0, f32::max);
+            assert!(distance2(&a, &b) > spread);
+            assert_eq!(layout, umap(&embeddings, &options).unwrap());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::embedding_index::Document;
+
+    #[test]
+    fn test_pca_finds_main_axis() {
+        // Points spread along (1, 1, 0) with a little noise on z
+        let embeddings: Vec<Vec<f32>> = (0..10)
+            .map(|i| {
+                let t = i as f32;
+                vec![t, t, (i % 2) as f32 * 0.1]
+            })
+            .collect();
+        let points = pca(&embeddings).unwrap();
+        let xs: Vec<f32> = points.iter().map(|p| p[0]).collect();
+        assert!(xs.windows(2).all(|w| w[1] > w[0]));
+        assert!(points.iter().all(|p| p[1].abs() < 0.1));
+        assert_eq!(points, pca(&embeddings).unwrap());
+
+        assert!(pca(&[vec![1.0], vec![1.0, 2.0]]).is_err());
+        assert!(ProjectionMethod::parse("tsne").is_err());
+    }
+
+    #[test]
+    fn test_project_and_write() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let index = EmbeddingIndex::open_in_memory()?;
+        let documents: Vec<Document> = (0..4)
+            .map(|i| Document {
+                id: format!("doc-{}", i),
+                content: format!("document </script> {}", i),
+                ..Default::default()
+            })
+            .collect();
+        let embeddings: Vec<Vec<f32>> = (0..4).map(|i| vec![i as f32, 1.0, 0.0]).collect();
+        index.store_batch(0, &documents, &embeddings)?;
+
+        let points = project_index(&index, ProjectionMethod::Pca, None)?;
+        assert_eq!(points.len(), 4);
+        assert_eq!(points[0].id, "doc-0");
+
+        let conn = Connection::open_in_memory()?;
+        let csv = temp.path().join("points.csv");
+        write_points(&conn, &points, csv.to_str().unwrap())?;
+        assert!(fs::read_to_string(&csv)?.starts_with("doc_id,content,x,y\n"));
+
+        let html = temp.path().join("points.html");
+        write_points(&conn, &points, html.to_str().unwrap())?;
+        let page = fs::read_to_string(&html)?;
+        assert!(page.contains("doc-3"));
+        assert!(!page.contains("document </script>"));
+
+        assert!(project_index(
+            &EmbeddingIndex::open_in_memory()?,
+            ProjectionMethod::Pca,
+            None
+        )
+        .is_err());
+        Ok(())
+    }
+}
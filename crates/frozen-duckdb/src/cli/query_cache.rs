@@ -0,0 +1,246 @@
+//! # Query Result Cache for Frozen DuckDB CLI
+//!
+//! Dashboards often re-run the same expensive aggregate over Parquet files
+//! that haven't changed. With `frozen-duckdb query --cache`, results are
+//! written to `queries/` in the [cache directory](super::cache_dir) as
+//! Parquet files and served from there until the SQL or one of its inputs
+//! changes.
+//!
+//! An entry is keyed on the SQL text and the path, size, and modification
+//! time of every input: files named in string literals (globs included) and
+//! the database files the query can see. Touching an input gives the query
+//! a new key, so stale results are never served; `frozen-duckdb cache
+//! invalidate` removes every entry.
+//!
+//! | Setting | Effect |
+//! |---------|--------|
+//! | `--cache` | Serve and store results for this query |
+//! | `--no-cache` | Bypass the cache, even if enabled by the environment |
+//! | `FROZEN_DUCKDB_QUERY_CACHE=1` | Cache every `query` by default |
+//!
+//! Results round-trip through Parquet, so types Parquet can't store (such as
+//! `HUGEINT`) come back in their closest Parquet representation.
+
+use super::cache_dir::cache_path;
+use super::dataset_cache::fnv1a_64;
+use anyhow::{Context, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tracing::{debug, info};
+
+const QUERIES_DIR: &str = "queries";
+
+/// Environment variable that enables the query cache by default.
+pub const QUERY_CACHE_ENV: &str = "FROZEN_DUCKDB_QUERY_CACHE";
+
+/// Whether a query should use the cache, given its `--cache` and
+/// `--no-cache` flags and [`QUERY_CACHE_ENV`].
+pub fn cache_enabled(cache: bool, no_cache: bool) -> bool {
+    if no_cache {
+        return false;
+    }
+    cache || env::var(QUERY_CACHE_ENV).is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+/// Returns the string literals of a SQL statement, which may name input
+/// files or glob patterns.
+pub fn string_literals(sql: &str) -> Vec<String> {
+    let mut literals = Vec::new();
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\'' {
+            continue;
+        }
+        let mut literal = String::new();
+        while let Some(c) = chars.next() {
+            if c == '\'' {
+                if chars.peek() == Some(&'\'') {
+                    chars.next();
+                    literal.push('\'');
+                    continue;
+                }
+                break;
+            }
+            literal.push(c);
+        }
+        literals.push(literal);
+    }
+    literals
+}
+
+/// Local cache of query results stored as Parquet files.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::query_cache::QueryCache;
+/// use std::path::PathBuf;
+///
+/// let cache = QueryCache::new()?;
+/// let sql = "SELECT region, SUM(amount) FROM 'sales.parquet' GROUP BY region";
+/// let key = cache.key(sql, &[PathBuf::from("sales.parquet")])?;
+/// if let Some(entry) = cache.lookup(&key) {
+///     println!("Cached result at {}", entry.display());
+/// }
+/// ```
+pub struct QueryCache {
+    root: PathBuf,
+}
+
+impl QueryCache {
+    /// Opens the default cache, `queries/` in the cache directory.
+    pub fn new() -> Result<Self> {
+        Self::with_root(cache_path(QUERIES_DIR)?)
+    }
+
+    /// Opens a cache rooted at a custom directory.
+    pub fn with_root<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root)
+            .with_context(|| format!("Failed to create query cache: {}", root.display()))?;
+        Ok(Self { root })
+    }
+
+    /// Returns the cache root directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Computes the cache key of `sql` over `inputs`.
+    ///
+    /// Inputs that no longer exist are part of the key as missing, so
+    /// deleting an input also changes the key.
+    pub fn key(&self, sql: &str, inputs: &[PathBuf]) -> Result<String> {
+        let mut inputs = inputs.to_vec();
+        inputs.sort();
+        inputs.dedup();
+
+        let mut canonical = sql.trim().to_string();
+        for input in &inputs {
+            canonical.push_str(&format!("\0{}", input.display()));
+            match fs::metadata(input) {
+                Ok(metadata) => {
+                    let modified = metadata
+                        .modified()?
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_nanos();
+                    canonical.push_str(&format!("\0{}\0{}", metadata.len(), modified));
+                }
+                Err(_) => canonical.push_str("\0missing"),
+            }
+        }
+        Ok(format!("{:016x}", fnv1a_64(canonical.as_bytes())))
+    }
+
+    /// Returns the Parquet file path of the entry for `key`.
+    pub fn entry_path(&self, key: &str) -> PathBuf {
+        self.root.join(format!("{}.parquet", key))
+    }
+
+    /// Returns the result file of a cached entry, if one exists.
+    pub fn lookup(&self, key: &str) -> Option<PathBuf> {
+        let entry = self.entry_path(key);
+        if entry.is_file() {
+            debug!("Query cache hit: {}", entry.display());
+            Some(entry)
+        } else {
+            debug!("Query cache miss: {}", entry.display());
+            None
+        }
+    }
+
+    /// Stores an entry by running `write` on a staging path.
+    ///
+    /// The staging file is renamed into place only after `write` succeeds,
+    /// so a failed or interrupted query never leaves a usable entry.
+    pub fn store<F>(&self, key: &str, write: F) -> Result<PathBuf>
+    where
+        F: FnOnce(&Path) -> Result<()>,
+    {
+        let entry = self.entry_path(key);
+        let staging = self
+            .root
+            .join(format!(".{}.tmp-{}.parquet", key, std::process::id()));
+
+        if let Err(e) = write(&staging) {
+            let _ = fs::remove_file(&staging);
+            return Err(e);
+        }
+        fs::rename(&staging, &entry)
+            .with_context(|| format!("Failed to commit query cache entry: {}", entry.display()))?;
+        debug!("Cached query result at {}", entry.display());
+        Ok(entry)
+    }
+
+    /// Removes every cached result and returns how many were removed.
+    pub fn invalidate(&self) -> Result<usize> {
+        let mut removed = 0;
+        for file in fs::read_dir(&self.root)? {
+            let path = file?.path();
+            if path.extension().is_some_and(|ext| ext == "parquet") {
+                fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+                removed += 1;
+            }
+        }
+        info!("🗑️  Removed {} cached query results", removed);
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_literals() {
+        assert_eq!(
+            string_literals("SELECT * FROM 'a.parquet' WHERE name = 'O''Brien' AND x = 1"),
+            ["a.parquet", "O'Brien"]
+        );
+        assert!(string_literals("SELECT 1").is_empty());
+    }
+
+    #[test]
+    fn test_key_changes_with_sql_and_inputs() {
+        let temp = tempfile::tempdir().unwrap();
+        let cache = QueryCache::with_root(temp.path().join("queries")).unwrap();
+        let input = temp.path().join("data.csv");
+        fs::write(&input, "a\n1\n").unwrap();
+        let inputs = [input.clone()];
+
+        let key = cache.key("SELECT * FROM 'data.csv'", &inputs).unwrap();
+        assert_eq!(
+            key,
+            cache.key("SELECT * FROM 'data.csv' ", &inputs).unwrap()
+        );
+        assert_ne!(key, cache.key("SELECT a FROM 'data.csv'", &inputs).unwrap());
+
+        fs::write(&input, "a\n1\n2\n").unwrap();
+        assert_ne!(key, cache.key("SELECT * FROM 'data.csv'", &inputs).unwrap());
+        fs::remove_file(&input).unwrap();
+        assert_ne!(key, cache.key("SELECT * FROM 'data.csv'", &inputs).unwrap());
+    }
+
+    #[test]
+    fn test_store_lookup_and_invalidate() {
+        let temp = tempfile::tempdir().unwrap();
+        let cache = QueryCache::with_root(temp.path()).unwrap();
+
+        assert!(cache.lookup("abc").is_none());
+        assert!(cache
+            .store("abc", |_| Err(anyhow::anyhow!("query failed")))
+            .is_err());
+        assert!(cache.lookup("abc").is_none());
+
+        let entry = cache
+            .store("abc", |path| Ok(fs::write(path, "PAR1")?))
+            .unwrap();
+        assert_eq!(cache.lookup("abc"), Some(entry));
+        assert_eq!(cache.invalidate().unwrap(), 1);
+        assert!(cache.lookup("abc").is_none());
+    }
+}
@@ -0,0 +1,341 @@
+//! # Retrieval-Augmented Answers with Conversation Sessions
+//!
+//! `rag` answers a question from an embedding index: the most similar
+//! documents are retrieved and passed to the text model as context. With
+//! `--session NAME`, every question and answer is kept as a turn of a named
+//! conversation in the `rag_session_turns` table of the config database
+//! (`~/.frozen-duckdb/config.duckdb`), so follow-up questions can build on
+//! earlier ones:
+//!
+//! | Step | With a session |
+//! |------|----------------|
+//! | Pick history | The latest turn, plus earlier turns sharing words with the question, up to `--history` |
+//! | Retrieve | The question is searched together with the latest question, so "what about its size?" finds the same topic |
+//! | Prompt | Picked turns precede the retrieved documents as "Conversation so far" |
+//! | Record | The question and answer are appended as the next turn |
+//!
+//! `sessions list`, `sessions show NAME`, and `sessions clear NAME` manage
+//! the stored conversations.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use frozen_duckdb::cli::rag::{relevant_turns, SessionStore};
+//!
+//! let store = SessionStore::open_in_memory()?;
+//! store.append("docs", "How do I export to Parquet?", "Use COPY ... TO 'out.parquet'.")?;
+//! store.append("docs", "Which joins are supported?", "Inner, left, right, full, and asof.")?;
+//!
+//! let turns = store.turns("docs")?;
+//! let history = relevant_turns(&turns, "Can the Parquet export be compressed?", 2);
+//! assert_eq!(history.len(), 2); // the Parquet turn and the latest turn
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{Context, Result};
+use duckdb::Connection;
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const CONFIG_DIR: &str = ".frozen-duckdb";
+const CONFIG_DATABASE: &str = "config.duckdb";
+
+/// One question and answer of a session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Turn {
+    /// 1-based position in the session
+    pub turn: usize,
+    /// Question asked
+    pub question: String,
+    /// Answer given
+    pub answer: String,
+    /// When the turn was recorded (`YYYY-MM-DD HH:MM:SS`)
+    pub created_at: String,
+}
+
+/// A stored session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionSummary {
+    /// Session name
+    pub name: String,
+    /// Number of turns
+    pub turns: usize,
+    /// When the first turn was recorded
+    pub started_at: String,
+    /// When the latest turn was recorded
+    pub updated_at: String,
+}
+
+/// Conversation turns of `rag` sessions, stored in the config database.
+pub struct SessionStore {
+    /// Connection to the config database
+    conn: Connection,
+}
+
+impl SessionStore {
+    /// Opens the store in the config database at `~/.frozen-duckdb/config.duckdb`.
+    pub fn open_default() -> Result<Self> {
+        let home = env::var("HOME").context("HOME environment variable not set")?;
+        let dir = Path::new(&home).join(CONFIG_DIR);
+        fs::create_dir_all(&dir)?;
+        Self::open(dir.join(CONFIG_DATABASE))
+    }
+
+    /// Opens the store in a specific database file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open config database: {}", path.display()))?;
+        Self::with_connection(conn)
+    }
+
+    /// Opens a store that lives only for the current process.
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS rag_session_turns (
+                 session VARCHAR,
+                 turn INTEGER,
+                 question VARCHAR,
+                 answer VARCHAR,
+                 created_at TIMESTAMP DEFAULT current_timestamp,
+                 PRIMARY KEY (session, turn)
+             );",
+        )
+        .context("Failed to create session table")?;
+        Ok(Self { conn })
+    }
+
+    /// Appends a turn to `session`, creating the session if needed, and
+    /// returns its turn number.
+    pub fn append(&self, session: &str, question: &str, answer: &str) -> Result<usize> {
+        validate_session_name(session)?;
+        let turn: i64 = self.conn.query_row(
+            "INSERT INTO rag_session_turns (session, turn, question, answer)
+             SELECT CAST(? AS VARCHAR), COALESCE(MAX(turn), 0) + 1, CAST(? AS VARCHAR),
+                    CAST(? AS VARCHAR)
+             FROM rag_session_turns WHERE session = ?
+             RETURNING turn",
+            [session, question, answer, session],
+            |row| row.get(0),
+        )?;
+        Ok(turn as usize)
+    }
+
+    /// Returns the turns of `session` in order; empty for an unknown session.
+    pub fn turns(&self, session: &str) -> Result<Vec<Turn>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT turn, question, answer, strftime(created_at, '%Y-%m-%d %H:%M:%S')
+             FROM rag_session_turns WHERE session = ? ORDER BY turn",
+        )?;
+        let turns = stmt
+            .query_map([session], |row| {
+                Ok(Turn {
+                    turn: row.get::<_, i64>(0)? as usize,
+                    question: row.get(1)?,
+                    answer: row.get(2)?,
+                    created_at: row.get(3)?,
+                })
+            })?
+            .collect::<duckdb::Result<Vec<_>>>()?;
+        Ok(turns)
+    }
+
+    /// Returns every session, most recently updated first.
+    pub fn list(&self) -> Result<Vec<SessionSummary>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT session, COUNT(*),
+                    strftime(MIN(created_at), '%Y-%m-%d %H:%M:%S'),
+                    strftime(MAX(created_at), '%Y-%m-%d %H:%M:%S')
+             FROM rag_session_turns GROUP BY session ORDER BY MAX(created_at) DESC, session",
+        )?;
+        let sessions = stmt
+            .query_map([], |row| {
+                Ok(SessionSummary {
+                    name: row.get(0)?,
+                    turns: row.get::<_, i64>(1)? as usize,
+                    started_at: row.get(2)?,
+                    updated_at: row.get(3)?,
+                })
+            })?
+            .collect::<duckdb::Result<Vec<_>>>()?;
+        Ok(sessions)
+    }
+
+    /// Deletes `session`, returning how many turns it had.
+    pub fn clear(&self, session: &str) -> Result<usize> {
+        let removed = self
+            .conn
+            .execute("DELETE FROM rag_session_turns WHERE session = ?", [session])?;
+        Ok(removed)
+    }
+}
+
+/// Checks that a session name is non-empty and made of letters, digits,
+/// `-`, `_`, and `.`.
+fn validate_session_name(name: &str) -> Result<()> {
+    let valid = name
+        .chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if name.is_empty() || !valid {
+        anyhow::bail!(
+            "Invalid session name '{}': use letters, digits, '-', '_', and '.'",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Picks up to `max` prior turns to include with `question`: the latest
+/// turn, which a follow-up usually refers to, and the earlier turns sharing
+/// the most words with the question. Returned in session order.
+pub fn relevant_turns<'a>(turns: &'a [Turn], question: &str, max: usize) -> Vec<&'a Turn> {
+    let Some((latest, earlier)) = turns.split_last() else {
+        return Vec::new();
+    };
+    if max == 0 {
+        return Vec::new();
+    }
+
+    let question_words = words(question);
+    let mut scored: Vec<(usize, &Turn)> = earlier
+        .iter()
+        .map(|turn| {
+            let turn_words = words(&format!("{} {}", turn.question, turn.answer));
+            (question_words.intersection(&turn_words).count(), turn)
+        })
+        .filter(|(score, _)| *score > 0)
+        .collect();
+    // Most shared words first, the more recent turn at a tie
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.turn.cmp(&a.1.turn)));
+
+    let mut picked: Vec<&Turn> = scored
+        .into_iter()
+        .take(max - 1)
+        .map(|(_, turn)| turn)
+        .collect();
+    picked.push(latest);
+    picked.sort_by_key(|turn| turn.turn);
+    picked
+}
+
+/// Lowercased words of at least three characters, for comparing texts.
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Text searched for `question`: with history, the latest question comes
+/// first, so a follow-up retrieves documents on the same topic.
+pub fn retrieval_query(question: &str, history: &[&Turn]) -> String {
+    match history.last() {
+        Some(latest) => format!("{} {}", latest.question, question),
+        None => question.to_string(),
+    }
+}
+
+/// Prompt answering `question` from the retrieved `documents`, after the
+/// conversation `history`.
+pub fn rag_prompt(question: &str, documents: &[String], history: &[&Turn]) -> String {
+    let mut prompt = String::from(
+        "Answer the question using only the context documents below. If they don't \
+         contain the answer, say so.\n",
+    );
+    if !history.is_empty() {
+        prompt.push_str("\nConversation so far:\n");
+        for turn in history {
+            prompt.push_str(&format!("Q: {}\nA: {}\n", turn.question, turn.answer));
+        }
+    }
+    prompt.push_str("\nContext:\n");
+    for (i, document) in documents.iter().enumerate() {
+        prompt.push_str(&format!("[{}] {}\n", i + 1, document));
+    }
+    prompt.push_str(&format!("\nQuestion: {}", question));
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(n: usize, question: &str, answer: &str) -> Turn {
+        Turn {
+            turn: n,
+            question: question.to_string(),
+            answer: answer.to_string(),
+            created_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_session_store() {
+        let store = SessionStore::open_in_memory().unwrap();
+        assert_eq!(store.append("docs", "q1", "a1").unwrap(), 1);
+        assert_eq!(store.append("docs", "q2", "a2").unwrap(), 2);
+        assert_eq!(store.append("other", "q", "a").unwrap(), 1);
+
+        let turns = store.turns("docs").unwrap();
+        assert_eq!(turns.len(), 2);
+        assert_eq!((turns[1].turn, turns[1].answer.as_str()), (2, "a2"));
+        assert_eq!(turns[1].created_at.len(), "YYYY-MM-DD HH:MM:SS".len());
+
+        let sessions = store.list().unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions.iter().find(|s| s.name == "docs").unwrap().turns, 2);
+
+        assert_eq!(store.clear("docs").unwrap(), 2);
+        assert!(store.turns("docs").unwrap().is_empty());
+        assert_eq!(store.clear("docs").unwrap(), 0);
+        assert!(store.append("my session", "q", "a").is_err());
+    }
+
+    #[test]
+    fn test_relevant_turns() {
+        let turns = vec![
+            turn(1, "How do I export to Parquet?", "Use COPY TO."),
+            turn(2, "Which joins exist?", "Inner and outer joins."),
+            turn(3, "What about CSV headers?", "Set HEADER true."),
+            turn(4, "Is ZSTD supported?", "Yes, for Parquet exports."),
+        ];
+        let picked = |question: &str, max: usize| -> Vec<usize> {
+            relevant_turns(&turns, question, max)
+                .iter()
+                .map(|t| t.turn)
+                .collect()
+        };
+        assert_eq!(picked("Can the Parquet export use snappy?", 2), vec![1, 4]);
+        assert_eq!(picked("Which join is fastest?", 3), vec![2, 4]);
+        assert_eq!(picked("Thanks!", 4), vec![4]);
+        assert!(picked("Parquet", 0).is_empty());
+        assert!(relevant_turns(&[], "Parquet", 3).is_empty());
+    }
+
+    #[test]
+    fn test_rag_prompt() {
+        let history = [turn(1, "What is ZSTD?", "A compression codec.")];
+        let history: Vec<&Turn> = history.iter().collect();
+        let prompt = rag_prompt(
+            "Is it faster than gzip?",
+            &["ZSTD compresses fast.".to_string()],
+            &history,
+        );
+        assert!(prompt.contains("Q: What is ZSTD?\nA: A compression codec.\n\nContext:"));
+        assert!(prompt.contains("Context:\n[1] ZSTD compresses fast.\n"));
+        assert!(prompt.ends_with("Question: Is it faster than gzip?"));
+        assert!(!rag_prompt("q", &[], &[]).contains("Conversation"));
+
+        assert_eq!(
+            retrieval_query("Is it faster?", &history),
+            "What is ZSTD? Is it faster?"
+        );
+        assert_eq!(retrieval_query("Is it faster?", &[]), "Is it faster?");
+    }
+}
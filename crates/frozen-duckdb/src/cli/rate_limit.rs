@@ -0,0 +1,251 @@
+//! # Rate Limiting for Flock LLM Operations
+//!
+//! This module keeps batch LLM operations from overloading a shared Ollama
+//! server. A [`RateLimiter`] combines a token bucket (sustained requests
+//! per second with a burst allowance) with a cap on concurrent requests.
+//!
+//! Limits come from the `flock` section of the config file
+//! (see [`super::config`]) and can be overridden with `--rate-limit` and
+//! `--max-in-flight`.
+
+use anyhow::Result;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Limits applied to LLM requests.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitConfig {
+    /// Sustained request rate, `None` for no rate limit
+    pub requests_per_second: Option<f64>,
+    /// Requests that may be sent back-to-back before the rate applies
+    pub burst: usize,
+    /// Maximum number of requests in flight at once
+    pub max_in_flight: usize,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: None,
+            burst: 1,
+            max_in_flight: thread::available_parallelism().map_or(4, |n| n.get()),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Applies command-line overrides on top of these limits.
+    pub fn with_overrides(
+        mut self,
+        requests_per_second: Option<f64>,
+        max_in_flight: Option<usize>,
+    ) -> Result<Self> {
+        if requests_per_second.is_some() {
+            self.requests_per_second = requests_per_second;
+        }
+        if let Some(max_in_flight) = max_in_flight {
+            self.max_in_flight = max_in_flight;
+        }
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Checks that all limits are positive.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(rate) = self.requests_per_second {
+            if !(rate > 0.0 && rate.is_finite()) {
+                return Err(anyhow::anyhow!("Rate limit must be a positive number"));
+            }
+        }
+        if self.burst == 0 || self.max_in_flight == 0 {
+            return Err(anyhow::anyhow!(
+                "Burst and max in-flight must be at least 1"
+            ));
+        }
+        Ok(())
+    }
+}
+
+struct LimiterState {
+    /// Tokens currently available in the bucket
+    tokens: f64,
+    /// When the bucket was last refilled
+    last_refill: Instant,
+    /// Requests currently holding a permit
+    in_flight: usize,
+}
+
+/// Token-bucket rate limiter with a concurrency cap.
+///
+/// Share it between threads with `Arc` to limit requests across workers.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::rate_limit::{RateLimitConfig, RateLimiter};
+///
+/// let limiter = RateLimiter::new(RateLimitConfig {
+///     requests_per_second: Some(5.0),
+///     burst: 10,
+///     max_in_flight: 2,
+/// });
+///
+/// for prompt in ["a", "b", "c"] {
+///     let _permit = limiter.acquire();
+///     // call the model with `prompt`; the permit is released on drop
+/// }
+/// ```
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    state: Mutex<LimiterState>,
+    released: Condvar,
+}
+
+impl RateLimiter {
+    /// Creates a limiter; the token bucket starts full.
+    pub fn new(config: RateLimitConfig) -> Self {
+        let state = LimiterState {
+            tokens: config.burst as f64,
+            last_refill: Instant::now(),
+            in_flight: 0,
+        };
+        Self {
+            config,
+            state: Mutex::new(state),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Returns the limits this limiter enforces.
+    pub fn config(&self) -> &RateLimitConfig {
+        &self.config
+    }
+
+    /// Blocks until a request may be sent, returning a permit held for its duration.
+    pub fn acquire(&self) -> Permit<'_> {
+        let mut state = self.lock();
+        loop {
+            while state.in_flight >= self.config.max_in_flight {
+                state = self
+                    .released
+                    .wait(state)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+            }
+
+            let Some(rate) = self.config.requests_per_second else {
+                break;
+            };
+            let now = Instant::now();
+            let refill = now.duration_since(state.last_refill).as_secs_f64() * rate;
+            state.tokens = (state.tokens + refill).min(self.config.burst as f64);
+            state.last_refill = now;
+
+            if state.tokens >= 1.0 {
+                state.tokens -= 1.0;
+                break;
+            }
+
+            // Sleep without holding the lock until the next token is due
+            let wait = Duration::from_secs_f64((1.0 - state.tokens) / rate);
+            drop(state);
+            thread::sleep(wait);
+            state = self.lock();
+        }
+
+        state.in_flight += 1;
+        Permit { limiter: self }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LimiterState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(RateLimitConfig::default())
+    }
+}
+
+/// Permission to send one request; releases its in-flight slot on drop.
+pub struct Permit<'a> {
+    limiter: &'a RateLimiter,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.limiter.lock().in_flight -= 1;
+        self.limiter.released.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_rate_limit_spaces_requests() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            requests_per_second: Some(20.0),
+            burst: 2,
+            max_in_flight: 1,
+        });
+
+        let start = Instant::now();
+        for _ in 0..6 {
+            drop(limiter.acquire());
+        }
+        // 2 burst requests are free, the other 4 wait 50ms each
+        assert!(start.elapsed() >= Duration::from_millis(190));
+    }
+
+    #[test]
+    fn test_max_in_flight_caps_concurrency() {
+        let limiter = Arc::new(RateLimiter::new(RateLimitConfig {
+            requests_per_second: None,
+            burst: 1,
+            max_in_flight: 2,
+        }));
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let workers: Vec<_> = (0..8)
+            .map(|_| {
+                let (limiter, active, peak) = (limiter.clone(), active.clone(), peak.clone());
+                thread::spawn(move || {
+                    let _permit = limiter.acquire();
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(10));
+                    active.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[test]
+    fn test_overrides_and_validation() {
+        let config = RateLimitConfig::default()
+            .with_overrides(Some(3.0), Some(1))
+            .unwrap();
+        assert_eq!(config.requests_per_second, Some(3.0));
+        assert_eq!(config.max_in_flight, 1);
+
+        assert!(RateLimitConfig::default()
+            .with_overrides(Some(0.0), None)
+            .is_err());
+        assert!(RateLimitConfig::default()
+            .with_overrides(None, Some(0))
+            .is_err());
+    }
+}
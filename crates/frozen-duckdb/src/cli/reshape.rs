@@ -0,0 +1,298 @@
+//! # Pivot and Unpivot for Frozen DuckDB CLI
+//!
+//! This module reshapes CSV, Parquet, and JSON datasets between long and
+//! wide layouts with DuckDB's `PIVOT` and `UNPIVOT` statements.
+//!
+//! ## Pivot (long to wide)
+//!
+//! | customer | month | amount |      | customer | jan | feb |
+//! |----------|-------|--------|  ->  |----------|-----|-----|
+//! | ada      | jan   | 10     |      | ada      | 10  | 5   |
+//! | ada      | feb   | 5      |
+//!
+//! Each distinct value of the `on` columns becomes a column holding the
+//! aggregate of the `values` columns for each `group_by` combination.
+//!
+//! ## Unpivot (wide to long)
+//!
+//! The reverse: the listed columns (or every column except the `keep`
+//! ones) are folded into a name column and a value column.
+//!
+//! Output rows are written in the format of the output file's extension.
+
+use super::dedupe::{copy_format, quote_identifier, read_function};
+use super::sql_path::path_literal;
+use anyhow::{anyhow, Context, Result};
+use duckdb::Connection;
+use tracing::info;
+
+/// Options for [`pivot`].
+#[derive(Debug, Clone)]
+pub struct PivotOptions {
+    /// Columns whose distinct values become new columns
+    pub on: Vec<String>,
+    /// Columns aggregated into the new columns; rows are counted if empty
+    pub values: Vec<String>,
+    /// Columns identifying an output row; all others if empty
+    pub group_by: Vec<String>,
+    /// Aggregate function applied to `values`
+    pub aggregate: String,
+}
+
+impl Default for PivotOptions {
+    fn default() -> Self {
+        Self {
+            on: Vec::new(),
+            values: Vec::new(),
+            group_by: Vec::new(),
+            aggregate: "sum".to_string(),
+        }
+    }
+}
+
+/// Options for [`unpivot`].
+#[derive(Debug, Clone)]
+pub struct UnpivotOptions {
+    /// Columns folded into rows
+    pub columns: Vec<String>,
+    /// Columns kept as they are, folding every other column; used when
+    /// `columns` is empty
+    pub keep: Vec<String>,
+    /// Name of the column holding the folded column names
+    pub name_column: String,
+    /// Name of the column holding the folded values
+    pub value_column: String,
+    /// Keep rows whose value is NULL
+    pub include_nulls: bool,
+}
+
+impl Default for UnpivotOptions {
+    fn default() -> Self {
+        Self {
+            columns: Vec::new(),
+            keep: Vec::new(),
+            name_column: "name".to_string(),
+            value_column: "value".to_string(),
+            include_nulls: false,
+        }
+    }
+}
+
+/// Builds the `PIVOT` statement for `source`.
+pub fn pivot_statement(source: &str, options: &PivotOptions) -> Result<String> {
+    if options.on.is_empty() {
+        return Err(anyhow!("At least one --on column is required"));
+    }
+    let aggregate = options.aggregate.to_lowercase();
+    if aggregate.is_empty()
+        || !aggregate
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err(anyhow!("Invalid aggregate function: {}", options.aggregate));
+    }
+
+    let mut statement = format!("PIVOT {} ON {}", source, identifiers(&options.on));
+    if !options.values.is_empty() {
+        let using = options
+            .values
+            .iter()
+            .map(|column| {
+                let quoted = quote_identifier(column);
+                // Name the columns `jan_amount` etc. when there are several
+                if options.values.len() > 1 {
+                    format!("{}({}) AS {}", aggregate, quoted, quoted)
+                } else {
+                    format!("{}({})", aggregate, quoted)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        statement.push_str(&format!(" USING {}", using));
+    }
+    if !options.group_by.is_empty() {
+        statement.push_str(&format!(" GROUP BY {}", identifiers(&options.group_by)));
+    }
+    Ok(statement)
+}
+
+/// Builds the `UNPIVOT` statement for `source`.
+pub fn unpivot_statement(source: &str, options: &UnpivotOptions) -> Result<String> {
+    let on = match (options.columns.is_empty(), options.keep.is_empty()) {
+        (false, true) => identifiers(&options.columns),
+        (true, false) => format!("COLUMNS(* EXCLUDE ({}))", identifiers(&options.keep)),
+        _ => return Err(anyhow!("Specify either --columns or --keep")),
+    };
+    Ok(format!(
+        "UNPIVOT {} {}ON {} INTO NAME {} VALUE {}",
+        source,
+        if options.include_nulls {
+            "INCLUDE NULLS "
+        } else {
+            ""
+        },
+        on,
+        quote_identifier(&options.name_column),
+        quote_identifier(&options.value_column)
+    ))
+}
+
+/// Pivots `input` from long to wide, writing `output`. Returns the number
+/// of rows written.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::reshape::{pivot, PivotOptions};
+/// use frozen_duckdb::cli::DatasetManager;
+///
+/// let manager = DatasetManager::new()?;
+/// let options = PivotOptions {
+///     on: vec!["month".to_string()],
+///     values: vec!["amount".to_string()],
+///     group_by: vec!["customer".to_string()],
+///     ..Default::default()
+/// };
+/// pivot(manager.connection(), "sales.csv", "sales_by_month.csv", &options)?;
+/// ```
+pub fn pivot(
+    conn: &Connection,
+    input: &str,
+    output: &str,
+    options: &PivotOptions,
+) -> Result<usize> {
+    info!("🔄 Pivoting {} on {}", input, options.on.join(", "));
+    let statement = pivot_statement(&read_function(input)?, options)?;
+    write_reshaped(conn, &statement, output)
+}
+
+/// Unpivots `input` from wide to long, writing `output`. Returns the
+/// number of rows written.
+pub fn unpivot(
+    conn: &Connection,
+    input: &str,
+    output: &str,
+    options: &UnpivotOptions,
+) -> Result<usize> {
+    info!("🔄 Unpivoting {}", input);
+    let statement = unpivot_statement(&read_function(input)?, options)?;
+    write_reshaped(conn, &statement, output)
+}
+
+fn write_reshaped(conn: &Connection, statement: &str, output: &str) -> Result<usize> {
+    let format = copy_format(output)?;
+    // PIVOT with columns taken from the data can't run inside COPY, so the
+    // result is staged in a temporary table first
+    conn.execute_batch(&format!(
+        "CREATE OR REPLACE TEMP TABLE reshape_result AS {}",
+        statement
+    ))
+    .context("Failed to reshape data")?;
+    let rows = conn.execute(
+        &format!(
+            "COPY reshape_result TO {} ({})",
+            path_literal(output),
+            format
+        ),
+        [],
+    )?;
+    conn.execute_batch("DROP TABLE reshape_result")?;
+    Ok(rows)
+}
+
+fn identifiers(columns: &[String]) -> String {
+    columns
+        .iter()
+        .map(|column| quote_identifier(column))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_statements() {
+        let options = PivotOptions {
+            on: strings(&["month"]),
+            values: strings(&["amount"]),
+            group_by: strings(&["customer"]),
+            ..Default::default()
+        };
+        assert_eq!(
+            pivot_statement("sales", &options).unwrap(),
+            r#"PIVOT sales ON "month" USING sum("amount") GROUP BY "customer""#
+        );
+        let options = PivotOptions {
+            on: strings(&["month"]),
+            aggregate: "sum(x); DROP".to_string(),
+            ..Default::default()
+        };
+        assert!(pivot_statement("sales", &options).is_err());
+
+        let options = UnpivotOptions {
+            keep: strings(&["customer"]),
+            name_column: "month".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            unpivot_statement("wide", &options).unwrap(),
+            r#"UNPIVOT wide ON COLUMNS(* EXCLUDE ("customer")) INTO NAME "month" VALUE "value""#
+        );
+        assert!(unpivot_statement("wide", &UnpivotOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_pivot_round_trip() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let long = temp.path().join("sales.csv");
+        fs::write(
+            &long,
+            "customer,month,amount\nada,jan,10\nada,feb,5\nada,jan,1\nalan,feb,7\n",
+        )?;
+        let wide = temp.path().join("wide.csv");
+        let back = temp.path().join("long.csv");
+        let conn = Connection::open_in_memory()?;
+
+        let options = PivotOptions {
+            on: strings(&["month"]),
+            values: strings(&["amount"]),
+            group_by: strings(&["customer"]),
+            ..Default::default()
+        };
+        let rows = pivot(
+            &conn,
+            long.to_str().unwrap(),
+            wide.to_str().unwrap(),
+            &options,
+        )?;
+        assert_eq!(rows, 2);
+        let pivoted = fs::read_to_string(&wide)?;
+        let mut lines: Vec<&str> = pivoted.lines().collect();
+        lines[1..].sort();
+        assert_eq!(lines, ["customer,feb,jan", "ada,5,11", "alan,7,"]);
+
+        let options = UnpivotOptions {
+            keep: strings(&["customer"]),
+            name_column: "month".to_string(),
+            value_column: "amount".to_string(),
+            ..Default::default()
+        };
+        let rows = unpivot(
+            &conn,
+            wide.to_str().unwrap(),
+            back.to_str().unwrap(),
+            &options,
+        )?;
+        // alan has no January sales, which is dropped without INCLUDE NULLS
+        assert_eq!(rows, 3);
+        assert!(fs::read_to_string(&back)?.starts_with("customer,month,amount\n"));
+        Ok(())
+    }
+}
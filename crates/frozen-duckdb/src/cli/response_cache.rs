@@ -0,0 +1,243 @@
+//! # LLM Response Cache for Frozen DuckDB CLI
+//!
+//! This module caches LLM responses so repeated identical prompts (for
+//! example re-running a filter while iterating on a pipeline) are answered
+//! locally instead of hitting Ollama again.
+//!
+//! Responses are keyed on the model, the SHA-256 hash of the full prompt,
+//! and the generation parameters, and are stored in the
+//! `llm_response_cache` table of the config database at
+//! `~/.frozen-duckdb/config.duckdb`. Entries older than the configured TTL
+//! are ignored and eventually purged.
+
+use anyhow::{Context, Result};
+use duckdb::Connection;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tracing::debug;
+
+const CONFIG_DIR: &str = ".frozen-duckdb";
+const CONFIG_DATABASE: &str = "config.duckdb";
+
+/// Persistent cache of LLM responses.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::response_cache::ResponseCache;
+/// use std::time::Duration;
+///
+/// let cache = ResponseCache::open_default()?.with_ttl(Some(Duration::from_secs(3600)));
+/// let response = cache.get_or_insert_with("coder", "Explain recursion", "{}", || {
+///     Ok("Recursion is when a function calls itself.".to_string())
+/// })?;
+/// ```
+pub struct ResponseCache {
+    /// Connection to the config database
+    conn: Connection,
+    /// Maximum age of a usable entry, `None` for no expiry
+    ttl: Option<Duration>,
+}
+
+impl ResponseCache {
+    /// Opens the cache in the config database at `~/.frozen-duckdb/config.duckdb`.
+    pub fn open_default() -> Result<Self> {
+        let home = env::var("HOME").context("HOME environment variable not set")?;
+        let dir = Path::new(&home).join(CONFIG_DIR);
+        fs::create_dir_all(&dir)?;
+        Self::open(dir.join(CONFIG_DATABASE))
+    }
+
+    /// Opens the cache in a specific database file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open config database: {}", path.display()))?;
+        Self::with_connection(conn)
+    }
+
+    /// Opens a cache that lives only for the current process.
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS llm_response_cache (
+                 model VARCHAR,
+                 prompt_hash VARCHAR,
+                 params VARCHAR,
+                 response VARCHAR,
+                 created_at TIMESTAMP DEFAULT current_timestamp,
+                 PRIMARY KEY (model, prompt_hash, params)
+             );",
+        )
+        .context("Failed to create response cache table")?;
+
+        Ok(Self { conn, ttl: None })
+    }
+
+    /// Sets the maximum age of cached responses; `None` disables expiry.
+    pub fn with_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Returns the cached response for a prompt, if present and not expired.
+    pub fn get(&self, model: &str, prompt: &str, params: &str) -> Result<Option<String>> {
+        let max_age = self
+            .ttl
+            .map_or(i64::MAX, |ttl| ttl.as_secs() as i64)
+            .to_string();
+        let mut stmt = self.conn.prepare(
+            "SELECT response FROM llm_response_cache
+             WHERE model = ? AND prompt_hash = sha256(?) AND params = ?
+               AND epoch(current_timestamp::TIMESTAMP) - epoch(created_at) <= CAST(? AS BIGINT)",
+        )?;
+        let mut rows = stmt.query([model, prompt, params, max_age.as_str()])?;
+
+        match rows.next()? {
+            Some(row) => {
+                debug!("LLM response cache hit for model {}", model);
+                Ok(Some(row.get(0)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Stores a response, replacing any previous entry for the same key.
+    pub fn put(&self, model: &str, prompt: &str, params: &str, response: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO llm_response_cache (model, prompt_hash, params, response)
+             VALUES (?, sha256(?), ?, ?)",
+            [model, prompt, params, response],
+        )?;
+        Ok(())
+    }
+
+    /// Returns the cached response or calls `generate` and caches its result.
+    ///
+    /// Errors from `generate` are returned and never cached.
+    pub fn get_or_insert_with<F>(
+        &self,
+        model: &str,
+        prompt: &str,
+        params: &str,
+        generate: F,
+    ) -> Result<String>
+    where
+        F: FnOnce() -> Result<String>,
+    {
+        if let Some(response) = self.get(model, prompt, params)? {
+            return Ok(response);
+        }
+
+        let response = generate()?;
+        self.put(model, prompt, params, &response)?;
+        Ok(response)
+    }
+
+    /// Deletes entries older than the TTL, returning how many were removed.
+    pub fn purge_expired(&self) -> Result<usize> {
+        let Some(ttl) = self.ttl else {
+            return Ok(0);
+        };
+        let removed = self.conn.execute(
+            "DELETE FROM llm_response_cache
+             WHERE epoch(current_timestamp::TIMESTAMP) - epoch(created_at) > CAST(? AS BIGINT)",
+            [ttl.as_secs().to_string()],
+        )?;
+        Ok(removed)
+    }
+}
+
+/// Parses a duration such as `90s`, `30m`, `12h`, or `7d` (bare numbers are seconds).
+pub fn parse_ttl(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .with_context(|| format!("Invalid cache TTL: {}", value))?;
+
+    let seconds = match unit {
+        "" | "s" => number,
+        "m" => number * 60,
+        "h" => number * 3600,
+        "d" => number * 86400,
+        other => {
+            return Err(anyhow::anyhow!(
+                "Invalid cache TTL unit: {} (use s, m, h, or d)",
+                other
+            ))
+        }
+    };
+    Ok(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_hit_skips_generation() {
+        let cache = ResponseCache::open_in_memory().unwrap();
+        let first = cache
+            .get_or_insert_with("coder", "Is this Python?", "{}", || Ok("true".to_string()))
+            .unwrap();
+        let second = cache
+            .get_or_insert_with("coder", "Is this Python?", "{}", || {
+                panic!("cached response should be used")
+            })
+            .unwrap();
+        assert_eq!(first, "true");
+        assert_eq!(second, "true");
+    }
+
+    #[test]
+    fn test_key_includes_model_and_params() {
+        let cache = ResponseCache::open_in_memory().unwrap();
+        cache.put("coder", "prompt", "{}", "a").unwrap();
+
+        assert_eq!(
+            cache.get("coder", "prompt", "{}").unwrap(),
+            Some("a".to_string())
+        );
+        assert_eq!(cache.get("text_generator", "prompt", "{}").unwrap(), None);
+        assert_eq!(
+            cache.get("coder", "prompt", "{\"max_length\":50}").unwrap(),
+            None
+        );
+        assert_eq!(cache.get("coder", "other prompt", "{}").unwrap(), None);
+    }
+
+    #[test]
+    fn test_expired_entries_are_ignored() {
+        let cache = ResponseCache::open_in_memory()
+            .unwrap()
+            .with_ttl(Some(Duration::from_secs(60)));
+        cache.put("coder", "prompt", "{}", "stale").unwrap();
+        cache
+            .conn
+            .execute_batch(
+                "UPDATE llm_response_cache SET created_at = created_at - INTERVAL 2 MINUTE",
+            )
+            .unwrap();
+
+        assert_eq!(cache.get("coder", "prompt", "{}").unwrap(), None);
+        assert_eq!(cache.purge_expired().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_parse_ttl() {
+        assert_eq!(parse_ttl("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_ttl("30m").unwrap(), Duration::from_secs(1800));
+        assert_eq!(parse_ttl("7d").unwrap(), Duration::from_secs(604800));
+        assert!(parse_ttl("soon").is_err());
+        assert!(parse_ttl("5w").is_err());
+    }
+}
@@ -0,0 +1,250 @@
+//! # Tabular Output for LLM Command Results
+//!
+//! This module writes LLM command results (filter decisions, summaries) as
+//! proper tables in JSON Lines, CSV, or Parquet, so they can be analyzed
+//! with DuckDB or any other tool. Results are first collected as JSON Lines
+//! and then converted with DuckDB `COPY` using an explicit column schema,
+//! so every format has the same columns and types.
+
+use super::sql_path::path_literal;
+use anyhow::{Context, Result};
+use duckdb::Connection;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// Columns of the summarize results table, in output order.
+pub const SUMMARY_COLUMNS: &[(&str, &str)] = &[
+    ("input", "VARCHAR"),
+    ("strategy", "VARCHAR"),
+    ("model", "VARCHAR"),
+    ("input_count", "BIGINT"),
+    ("summary", "VARCHAR"),
+    ("latency_ms", "BIGINT"),
+];
+
+/// Table format for command results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Newline-delimited JSON objects
+    Jsonl,
+    /// CSV with a header row
+    Csv,
+    /// Parquet
+    Parquet,
+}
+
+impl OutputFormat {
+    /// Parses `jsonl`, `csv`, or `parquet`.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "jsonl" | "json" => Ok(Self::Jsonl),
+            "csv" => Ok(Self::Csv),
+            "parquet" => Ok(Self::Parquet),
+            other => Err(anyhow::anyhow!(
+                "Unsupported output format: {} (use jsonl, csv, or parquet)",
+                other
+            )),
+        }
+    }
+
+    /// Infers the format from a file extension, if it names one.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?;
+        Self::parse(extension).ok()
+    }
+
+    /// Resolves `--output-format`, falling back to the output file extension.
+    pub fn resolve(format: Option<&str>, output: &str) -> Result<Option<Self>> {
+        match format {
+            Some(format) => Self::parse(format).map(Some),
+            None => Ok(Self::from_path(output)),
+        }
+    }
+
+    fn copy_options(&self) -> &'static str {
+        match self {
+            Self::Jsonl => "FORMAT JSON",
+            Self::Csv => "FORMAT CSV, HEADER",
+            Self::Parquet => "FORMAT PARQUET",
+        }
+    }
+}
+
+/// Converts a JSON Lines file to `output`, returning the number of rows written.
+///
+/// `columns` gives each column's name and DuckDB type, in output order;
+/// missing fields become NULL.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::result_table::{export_jsonl, OutputFormat};
+///
+/// let rows = export_jsonl(
+///     "results.partial.jsonl",
+///     &[("item", "VARCHAR"), ("matched", "BOOLEAN")],
+///     Some("item"),
+///     "results.parquet",
+///     OutputFormat::Parquet,
+/// )?;
+/// ```
+pub fn export_jsonl(
+    source: &str,
+    columns: &[(&str, &str)],
+    order_by: Option<&str>,
+    output: &str,
+    format: OutputFormat,
+) -> Result<usize> {
+    let conn = Connection::open_in_memory()?;
+    let schema: Vec<String> = columns
+        .iter()
+        .map(|(name, sql_type)| format!("{}: '{}'", name, sql_type))
+        .collect();
+    let names: Vec<&str> = columns.iter().map(|(name, _)| *name).collect();
+    let order = order_by.map_or_else(String::new, |column| format!(" ORDER BY {}", column));
+
+    conn.execute_batch(&format!(
+        "CREATE TEMP TABLE results AS
+         SELECT {} FROM read_json({}, format = 'newline_delimited', columns = {{{}}}){};",
+        names.join(", "),
+        path_literal(source),
+        schema.join(", "),
+        order
+    ))
+    .with_context(|| format!("Failed to read results from {}", source))?;
+
+    let rows: i64 = conn.query_row("SELECT COUNT(*) FROM results", [], |row| row.get(0))?;
+    conn.execute_batch(&format!(
+        "COPY results TO {} ({});",
+        path_literal(output),
+        format.copy_options()
+    ))
+    .with_context(|| format!("Failed to write results to {}", output))?;
+
+    Ok(rows as usize)
+}
+
+/// Writes `rows` (JSON objects) to `output` as a table with `columns`.
+///
+/// The rows are staged in `<output>.partial.jsonl`, which is removed
+/// afterwards.
+pub fn export_rows(
+    rows: &[Value],
+    columns: &[(&str, &str)],
+    output: &str,
+    format: OutputFormat,
+) -> Result<usize> {
+    let staging = format!("{}.partial.jsonl", output);
+    let lines: String = rows.iter().map(|row| format!("{}\n", row)).collect();
+    fs::write(&staging, lines)
+        .with_context(|| format!("Failed to write results to {}", staging))?;
+
+    let result = export_jsonl(&staging, columns, None, output, format);
+    let _ = fs::remove_file(&staging);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_infer_format() {
+        assert_eq!(
+            OutputFormat::parse("Parquet").unwrap(),
+            OutputFormat::Parquet
+        );
+        assert!(OutputFormat::parse("xlsx").is_err());
+        assert_eq!(
+            OutputFormat::from_path("out/results.csv"),
+            Some(OutputFormat::Csv)
+        );
+        assert_eq!(OutputFormat::from_path("summary.txt"), None);
+        assert_eq!(
+            OutputFormat::resolve(Some("jsonl"), "results.csv").unwrap(),
+            Some(OutputFormat::Jsonl)
+        );
+    }
+
+    #[test]
+    fn test_export_jsonl_to_csv_and_parquet() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("rows.jsonl");
+        fs::write(
+            &source,
+            "{\"line\":2,\"item\":\"b\",\"matched\":false}\n{\"line\":1,\"item\":\"a, c\",\"matched\":true}\n",
+        )
+        .unwrap();
+        let columns = [
+            ("line", "BIGINT"),
+            ("item", "VARCHAR"),
+            ("matched", "BOOLEAN"),
+        ];
+
+        let csv = temp.path().join("rows.csv");
+        let rows = export_jsonl(
+            source.to_str().unwrap(),
+            &columns,
+            Some("line"),
+            csv.to_str().unwrap(),
+            OutputFormat::Csv,
+        )
+        .unwrap();
+        assert_eq!(rows, 2);
+        assert_eq!(
+            fs::read_to_string(&csv).unwrap(),
+            "line,item,matched\n1,\"a, c\",true\n2,b,false\n"
+        );
+
+        let parquet = temp.path().join("rows.parquet");
+        export_jsonl(
+            source.to_str().unwrap(),
+            &columns,
+            None,
+            parquet.to_str().unwrap(),
+            OutputFormat::Parquet,
+        )
+        .unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        let matched: i64 = conn
+            .query_row(
+                &format!(
+                    "SELECT COUNT(*) FROM read_parquet('{}') WHERE matched",
+                    parquet.display()
+                ),
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(matched, 1);
+    }
+
+    #[test]
+    fn test_export_rows_removes_staging_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let output = temp.path().join("summary.jsonl");
+        let output = output.to_str().unwrap();
+        let rows = [serde_json::json!({ "summary": "short", "latency_ms": 12 })];
+
+        let written = export_rows(
+            &rows,
+            &[
+                ("summary", "VARCHAR"),
+                ("model", "VARCHAR"),
+                ("latency_ms", "BIGINT"),
+            ],
+            output,
+            OutputFormat::Jsonl,
+        )
+        .unwrap();
+        assert_eq!(written, 1);
+
+        let row: Value = serde_json::from_str(fs::read_to_string(output).unwrap().trim()).unwrap();
+        assert_eq!(
+            row,
+            serde_json::json!({ "summary": "short", "model": null, "latency_ms": 12 })
+        );
+        assert!(!Path::new(&format!("{}.partial.jsonl", output)).exists());
+    }
+}
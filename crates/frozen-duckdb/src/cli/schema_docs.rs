@@ -0,0 +1,451 @@
+//! # Schema Documentation for Frozen DuckDB CLI
+//!
+//! This module introspects the tables of a DuckDB database (columns, types,
+//! row counts and sample values) and renders them as Markdown. With a
+//! [`SchemaDescriber`], each table also gets a summary and per-column
+//! descriptions written by an LLM; without one, the output is the raw schema
+//! skeleton.
+//!
+//! ## Output
+//!
+//! ```text
+//! # sales.duckdb
+//!
+//! ## orders
+//!
+//! One row per customer order.
+//!
+//! Rows: 1200
+//!
+//! | Column | Type    | Nullable | Description        | Sample values |
+//! |--------|---------|----------|--------------------|---------------|
+//! | id     | INTEGER | no       | Order identifier   | `1`, `2`      |
+//! ```
+//!
+//! A table whose description fails (for example a malformed model reply)
+//! keeps its skeleton; the rest of the document is unaffected.
+
+use super::dedupe::quote_identifier;
+use super::flock_manager::FlockManager;
+use anyhow::{Context, Result};
+use duckdb::Connection;
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+/// Characters kept of each sample value.
+const SAMPLE_VALUE_CHARS: usize = 60;
+
+/// Options for [`document_database`].
+#[derive(Debug, Clone)]
+pub struct DocumentOptions {
+    /// Distinct non-null sample values collected per column
+    pub sample_values: usize,
+}
+
+impl Default for DocumentOptions {
+    fn default() -> Self {
+        Self { sample_values: 3 }
+    }
+}
+
+/// One column of a documented table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnDoc {
+    /// Column name
+    pub name: String,
+    /// DuckDB data type
+    pub data_type: String,
+    /// Whether the column accepts NULL
+    pub nullable: bool,
+    /// Distinct sample values, rendered as text
+    pub samples: Vec<String>,
+    /// Description, if one was generated
+    pub description: Option<String>,
+}
+
+/// One documented table or view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableDoc {
+    /// Schema name
+    pub schema: String,
+    /// Table name
+    pub name: String,
+    /// Row count
+    pub rows: u64,
+    /// Columns in declaration order
+    pub columns: Vec<ColumnDoc>,
+    /// Summary, if one was generated
+    pub summary: Option<String>,
+}
+
+impl TableDoc {
+    /// Name as it would be written in a query; `main` tables stay unqualified.
+    pub fn qualified_name(&self) -> String {
+        if self.schema == "main" {
+            self.name.clone()
+        } else {
+            format!("{}.{}", self.schema, self.name)
+        }
+    }
+}
+
+/// Summary and column descriptions for one table.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableDescription {
+    /// One or two sentence table summary
+    pub summary: Option<String>,
+    /// Descriptions keyed by column name
+    pub columns: HashMap<String, String>,
+}
+
+/// Writes descriptions for [`document_database`].
+pub trait SchemaDescriber {
+    /// Describes a table from its columns and sample values.
+    fn describe(&self, table: &TableDoc) -> Result<TableDescription>;
+}
+
+/// [`FlockManager`] bound to a model, usable as a [`SchemaDescriber`].
+pub struct FlockDescriber<'a> {
+    /// Flock manager used for completions
+    pub manager: &'a FlockManager,
+    /// Model alias configured during flock-setup
+    pub model: String,
+}
+
+impl SchemaDescriber for FlockDescriber<'_> {
+    fn describe(&self, table: &TableDoc) -> Result<TableDescription> {
+        let mut prompt = format!(
+            "Document the database table `{}` ({} rows). Reply with one line \
+             `TABLE: <summary in one or two sentences>` followed by one line \
+             `<column>: <short description>` per column, and nothing else.\n\nColumns:",
+            table.qualified_name(),
+            table.rows
+        );
+        for column in &table.columns {
+            prompt.push_str(&format!("\n- {} {}", column.name, column.data_type));
+            if !column.samples.is_empty() {
+                prompt.push_str(&format!(" (e.g. {})", column.samples.join(", ")));
+            }
+        }
+        let response = self.manager.complete_text(&prompt, &self.model)?;
+        parse_description(&response, table)
+    }
+}
+
+/// A documented database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaDoc {
+    /// Document heading, usually the database path
+    pub title: String,
+    /// Tables in schema and name order
+    pub tables: Vec<TableDoc>,
+}
+
+impl SchemaDoc {
+    /// Renders the document as Markdown.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n", self.title);
+        for table in &self.tables {
+            out.push_str(&format!("\n## {}\n\n", table.qualified_name()));
+            if let Some(summary) = &table.summary {
+                out.push_str(&format!("{}\n\n", summary));
+            }
+            out.push_str(&format!("Rows: {}\n\n", table.rows));
+            out.push_str("| Column | Type | Nullable | Description | Sample values |\n");
+            out.push_str("|--------|------|----------|-------------|---------------|\n");
+            for column in &table.columns {
+                let samples = column
+                    .samples
+                    .iter()
+                    .map(|sample| format!("`{}`", markdown_cell(sample).replace('`', "'")))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                out.push_str(&format!(
+                    "| {} | {} | {} | {} | {} |\n",
+                    markdown_cell(&column.name),
+                    markdown_cell(&column.data_type),
+                    if column.nullable { "yes" } else { "no" },
+                    markdown_cell(column.description.as_deref().unwrap_or("")),
+                    samples
+                ));
+            }
+        }
+        out
+    }
+}
+
+/// Reads tables, columns, row counts and sample values from `conn`.
+pub fn introspect(conn: &Connection, options: &DocumentOptions) -> Result<Vec<TableDoc>> {
+    let mut stmt = conn.prepare(
+        "SELECT table_schema, table_name, column_name, data_type, is_nullable = 'YES'
+         FROM information_schema.columns
+         WHERE table_catalog NOT IN ('system', 'temp')
+           AND table_schema NOT IN ('information_schema', 'pg_catalog')
+         ORDER BY table_schema, table_name, ordinal_position",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            ColumnDoc {
+                name: row.get(2)?,
+                data_type: row.get(3)?,
+                nullable: row.get(4)?,
+                samples: Vec::new(),
+                description: None,
+            },
+        ))
+    })?;
+
+    let mut tables: Vec<TableDoc> = Vec::new();
+    for row in rows {
+        let (schema, name, column) = row?;
+        match tables.last_mut() {
+            Some(table) if table.schema == schema && table.name == name => {
+                table.columns.push(column)
+            }
+            _ => tables.push(TableDoc {
+                schema,
+                name,
+                rows: 0,
+                columns: vec![column],
+                summary: None,
+            }),
+        }
+    }
+
+    for table in &mut tables {
+        let relation = format!(
+            "{}.{}",
+            quote_identifier(&table.schema),
+            quote_identifier(&table.name)
+        );
+        table.rows = conn
+            .query_row(&format!("SELECT count(*) FROM {}", relation), [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .with_context(|| format!("Failed to count rows of {}", table.qualified_name()))?
+            as u64;
+
+        if options.sample_values == 0 {
+            continue;
+        }
+        for column in &mut table.columns {
+            let column_name = quote_identifier(&column.name);
+            let mut stmt = conn.prepare(&format!(
+                "SELECT DISTINCT CAST({0} AS VARCHAR) FROM {1} WHERE {0} IS NOT NULL LIMIT {2}",
+                column_name, relation, options.sample_values
+            ))?;
+            column.samples = stmt
+                .query_map([], |row| row.get::<_, String>(0))?
+                .map(|sample| sample.map(|s| s.chars().take(SAMPLE_VALUE_CHARS).collect()))
+                .collect::<std::result::Result<_, _>>()?;
+        }
+    }
+
+    Ok(tables)
+}
+
+/// Documents every table of `conn`.
+///
+/// With a `describer`, each table gets a summary and column descriptions;
+/// a table whose description fails is logged and keeps its skeleton.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::schema_docs::{document_database, DocumentOptions, FlockDescriber};
+/// use frozen_duckdb::cli::FlockManager;
+/// use duckdb::Connection;
+///
+/// let conn = Connection::open("sales.duckdb")?;
+/// let flock = FlockManager::new()?;
+/// let describer = FlockDescriber { manager: &flock, model: "text_generator".to_string() };
+///
+/// let doc = document_database(&conn, "sales.duckdb", Some(&describer), &DocumentOptions::default())?;
+/// std::fs::write("docs.md", doc.to_markdown())?;
+/// ```
+pub fn document_database(
+    conn: &Connection,
+    title: &str,
+    describer: Option<&dyn SchemaDescriber>,
+    options: &DocumentOptions,
+) -> Result<SchemaDoc> {
+    let mut tables = introspect(conn, options)?;
+    info!("📋 Found {} tables", tables.len());
+
+    if let Some(describer) = describer {
+        for table in &mut tables {
+            match describer.describe(table) {
+                Ok(description) => apply_description(table, description),
+                Err(e) => warn!(
+                    "⚠️  Could not describe {}, keeping the schema skeleton: {:#}",
+                    table.qualified_name(),
+                    e
+                ),
+            }
+        }
+    }
+
+    Ok(SchemaDoc {
+        title: title.to_string(),
+        tables,
+    })
+}
+
+/// Parses a `TABLE: ...` / `<column>: ...` model reply for `table`.
+///
+/// Lines may carry list markers or backticks; column names match
+/// case-insensitively and unknown names are ignored.
+pub fn parse_description(response: &str, table: &TableDoc) -> Result<TableDescription> {
+    let mut description = TableDescription::default();
+    for line in response.lines() {
+        let line = line.trim().trim_start_matches(['-', '*']).trim();
+        let Some((key, text)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().trim_matches(|c| c == '`' || c == '*').trim();
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        if key.eq_ignore_ascii_case("table") {
+            description.summary = Some(text.to_string());
+        } else if let Some(column) = table
+            .columns
+            .iter()
+            .find(|column| column.name.eq_ignore_ascii_case(key))
+        {
+            description
+                .columns
+                .insert(column.name.clone(), text.to_string());
+        }
+    }
+
+    if description.summary.is_none() && description.columns.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Model reply did not contain a table summary or column descriptions"
+        ));
+    }
+    Ok(description)
+}
+
+fn apply_description(table: &mut TableDoc, mut description: TableDescription) {
+    table.summary = description.summary;
+    for column in &mut table.columns {
+        column.description = description.columns.remove(&column.name);
+    }
+}
+
+/// Escapes a value for a Markdown table cell.
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Describes every column as "the <name>".
+    struct NameDescriber;
+
+    impl SchemaDescriber for NameDescriber {
+        fn describe(&self, table: &TableDoc) -> Result<TableDescription> {
+            if table.name == "broken" {
+                return Err(anyhow::anyhow!("no reply"));
+            }
+            Ok(TableDescription {
+                summary: Some(format!("All about {}.", table.name)),
+                columns: table
+                    .columns
+                    .iter()
+                    .map(|c| (c.name.clone(), format!("the {}", c.name)))
+                    .collect(),
+            })
+        }
+    }
+
+    fn sample_database() -> Result<Connection> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute_batch(
+            "CREATE TABLE orders (id INTEGER NOT NULL, region VARCHAR);
+             INSERT INTO orders VALUES (1, 'north|east'), (2, NULL), (3, 'south');
+             CREATE SCHEMA crm;
+             CREATE TABLE crm.broken (x INTEGER);",
+        )?;
+        Ok(conn)
+    }
+
+    #[test]
+    fn test_introspect_reads_columns_and_samples() -> Result<()> {
+        let conn = sample_database()?;
+        let tables = introspect(&conn, &DocumentOptions { sample_values: 2 })?;
+
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].qualified_name(), "crm.broken");
+        let orders = &tables[1];
+        assert_eq!(orders.rows, 3);
+        assert_eq!(orders.columns[0].name, "id");
+        assert_eq!(orders.columns[0].data_type, "INTEGER");
+        assert!(!orders.columns[0].nullable);
+        assert!(orders.columns[1].nullable);
+        assert_eq!(orders.columns[1].samples.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_skeleton_without_describer() -> Result<()> {
+        let conn = sample_database()?;
+        let doc = document_database(&conn, "test.duckdb", None, &DocumentOptions::default())?;
+        let markdown = doc.to_markdown();
+
+        assert!(markdown.starts_with("# test.duckdb\n"));
+        assert!(markdown.contains("## orders\n\nRows: 3\n"));
+        assert!(markdown.contains("| id | INTEGER | no |  | "));
+        assert!(markdown.contains("`north\\|east`"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_describer_failures_keep_skeleton() -> Result<()> {
+        let conn = sample_database()?;
+        let doc = document_database(
+            &conn,
+            "test.duckdb",
+            Some(&NameDescriber),
+            &DocumentOptions::default(),
+        )?;
+
+        assert_eq!(doc.tables[0].summary, None);
+        assert_eq!(doc.tables[1].summary.as_deref(), Some("All about orders."));
+        assert_eq!(
+            doc.tables[1].columns[1].description.as_deref(),
+            Some("the region")
+        );
+        assert!(doc
+            .to_markdown()
+            .contains("| region | VARCHAR | yes | the region |"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_description() -> Result<()> {
+        let conn = sample_database()?;
+        let tables = introspect(&conn, &DocumentOptions::default())?;
+        let response = "TABLE: Customer orders.\n\
+                        - `ID`: Order identifier\n\
+                        * region: Sales region: north or south\n\
+                        unknown: ignored";
+        let description = parse_description(response, &tables[1])?;
+
+        assert_eq!(description.summary.as_deref(), Some("Customer orders."));
+        assert_eq!(description.columns["id"], "Order identifier");
+        assert_eq!(
+            description.columns["region"],
+            "Sales region: north or south"
+        );
+        assert_eq!(description.columns.len(), 2);
+        assert!(parse_description("I cannot help with that.", &tables[1]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,391 @@
+//! # SQL Script Runner
+//!
+//! `frozen-duckdb run script.sql` executes a multi-statement SQL file, the
+//! usual glue of an ETL pipeline. Statements run in order with their timing
+//! printed as they finish.
+//!
+//! ## Variables
+//!
+//! `${name}` is replaced by the value given with `--var name=value` before
+//! the script runs, and `${name:-default}` falls back to `default` when the
+//! variable isn't given. An undefined variable without a default is an
+//! error, so a typo never silently runs with an empty value. Write `$${` for
+//! a literal `${`. Values are substituted as-is; quote them in the script
+//! where a string literal is needed:
+//!
+//! ```sql
+//! COPY (SELECT * FROM events WHERE day = '${date}') TO '${out:-events}.parquet';
+//! ```
+//!
+//! ## Error and Transaction Policies
+//!
+//! | Option | Behavior |
+//! |--------|----------|
+//! | `--on-error stop` (default) | Stop at the first failing statement |
+//! | `--on-error continue` | Report the failure and run the remaining statements |
+//! | `--transaction none` (default) | Each statement commits on its own |
+//! | `--transaction script` | All statements commit together or not at all |
+//! | `--transaction N` | Commit every N statements; a failure rolls back its batch |
+//!
+//! DuckDB aborts a transaction when a statement in it fails, so
+//! `--on-error continue` requires `--transaction none`.
+
+use super::dataset_manager::split_statements;
+use anyhow::{anyhow, bail, Result};
+use duckdb::Connection;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// What to do when a statement fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnError {
+    /// Stop at the first failure
+    #[default]
+    Stop,
+    /// Run the remaining statements
+    Continue,
+}
+
+impl OnError {
+    /// Parses `stop` or `continue`.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "stop" => Ok(Self::Stop),
+            "continue" => Ok(Self::Continue),
+            other => Err(anyhow!(
+                "Unsupported error policy: {} (use stop or continue)",
+                other
+            )),
+        }
+    }
+}
+
+/// How statements are grouped into transactions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransactionMode {
+    /// Every statement commits on its own
+    #[default]
+    Autocommit,
+    /// The whole script is one transaction
+    Script,
+    /// A transaction per this many statements
+    Batches(usize),
+}
+
+impl TransactionMode {
+    /// Parses `none`, `script`, or a batch size.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "none" | "0" => Ok(Self::Autocommit),
+            "script" => Ok(Self::Script),
+            other => other.parse().map(Self::Batches).map_err(|_| {
+                anyhow!(
+                    "Unsupported transaction mode: {} (use none, script, or a statement count)",
+                    other
+                )
+            }),
+        }
+    }
+}
+
+/// Options for [`run_script`].
+#[derive(Debug, Clone, Default)]
+pub struct ScriptOptions {
+    /// Values substituted for `${name}`
+    pub variables: HashMap<String, String>,
+    /// What to do when a statement fails
+    pub on_error: OnError,
+    /// How statements are grouped into transactions
+    pub transaction: TransactionMode,
+}
+
+/// Result of one statement.
+#[derive(Debug, Clone)]
+pub struct StatementOutcome {
+    /// 1-based position in the script
+    pub index: usize,
+    /// Statement text after substitution
+    pub sql: String,
+    /// Execution time
+    pub elapsed: Duration,
+    /// Error message if the statement failed
+    pub error: Option<String>,
+}
+
+impl StatementOutcome {
+    /// First line of the statement, shortened for progress output.
+    pub fn summary(&self) -> String {
+        let first_line = self
+            .sql
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty() && !l.starts_with("--"))
+            .unwrap_or_default();
+        if first_line.chars().count() > 60 {
+            format!("{}...", first_line.chars().take(57).collect::<String>())
+        } else {
+            first_line.to_string()
+        }
+    }
+}
+
+/// Summary of a script run.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptReport {
+    /// Statements in the script
+    pub total: usize,
+    /// Outcomes of the statements that ran, in order
+    pub outcomes: Vec<StatementOutcome>,
+    /// Statements that succeeded but were rolled back with a failed
+    /// transaction
+    pub rolled_back: usize,
+    /// Total run time
+    pub elapsed: Duration,
+}
+
+impl ScriptReport {
+    /// Number of statements that failed.
+    pub fn failed(&self) -> usize {
+        self.outcomes.iter().filter(|o| o.error.is_some()).count()
+    }
+
+    /// Number of statements that ran and stayed committed.
+    pub fn committed(&self) -> usize {
+        self.outcomes.len() - self.failed() - self.rolled_back
+    }
+
+    /// Number of statements that never ran because the script stopped.
+    pub fn skipped(&self) -> usize {
+        self.total - self.outcomes.len()
+    }
+}
+
+/// Replaces `${name}` and `${name:-default}` with variable values.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::script::substitute_variables;
+/// use std::collections::HashMap;
+///
+/// let vars = HashMap::from([("date".to_string(), "2024-01-01".to_string())]);
+/// let sql = substitute_variables("SELECT '${date}', '${limit:-10}'", &vars)?;
+/// assert_eq!(sql, "SELECT '2024-01-01', '10'");
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn substitute_variables(sql: &str, variables: &HashMap<String, String>) -> Result<String> {
+    let mut result = String::with_capacity(sql.len());
+    let mut rest = sql;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            // "$${" is a literal "${"
+            result.push_str(&rest[..start - 1]);
+            result.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        result.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("Unterminated variable reference: {}", &rest[start..]))?;
+        let reference = &rest[start + 2..start + end];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+        match (variables.get(name), default) {
+            (Some(value), _) => result.push_str(value),
+            (None, Some(default)) => result.push_str(default),
+            (None, None) => bail!("Undefined variable: {} (pass --var {}=...)", name, name),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Runs each statement of `script`, calling `progress` as each finishes.
+///
+/// Statement failures are reported in the returned [`ScriptReport`] rather
+/// than as an error; `Err` means the script couldn't start, for example
+/// because a variable is undefined.
+pub fn run_script<F>(
+    conn: &Connection,
+    script: &str,
+    options: &ScriptOptions,
+    mut progress: F,
+) -> Result<ScriptReport>
+where
+    F: FnMut(&StatementOutcome),
+{
+    if options.on_error == OnError::Continue && options.transaction != TransactionMode::Autocommit {
+        bail!("--on-error continue requires --transaction none");
+    }
+    let sql = substitute_variables(script, &options.variables)?;
+    let statements = split_statements(&sql);
+    let started = Instant::now();
+    let mut report = ScriptReport {
+        total: statements.len(),
+        ..Default::default()
+    };
+
+    let batch_size = match options.transaction {
+        TransactionMode::Autocommit => 1,
+        TransactionMode::Script => statements.len().max(1),
+        TransactionMode::Batches(n) => n.max(1),
+    };
+    for (batch_index, batch) in statements.chunks(batch_size).enumerate() {
+        let first = batch_index * batch_size + 1;
+        let mut run_batch = |conn: &Connection, report: &mut ScriptReport| -> bool {
+            for (offset, statement) in batch.iter().enumerate() {
+                let timer = Instant::now();
+                let result = conn.execute_batch(statement);
+                let outcome = StatementOutcome {
+                    index: first + offset,
+                    sql: statement.to_string(),
+                    elapsed: timer.elapsed(),
+                    error: result.err().map(|e| e.to_string()),
+                };
+                progress(&outcome);
+                let failed = outcome.error.is_some();
+                report.outcomes.push(outcome);
+                if failed && options.on_error == OnError::Stop {
+                    return false;
+                }
+            }
+            true
+        };
+
+        let completed = if options.transaction == TransactionMode::Autocommit {
+            run_batch(conn, &mut report)
+        } else {
+            let before = report.outcomes.len();
+            let committed = conn.with_savepoint(|sp| {
+                if run_batch(&**sp, &mut report) {
+                    Ok(())
+                } else {
+                    Err(anyhow!("statement failed"))
+                }
+            });
+            if committed.is_err() {
+                // The failed statement is counted as failed, the rest of its
+                // batch as rolled back
+                report.rolled_back += report.outcomes.len() - before - 1;
+            }
+            committed.is_ok()
+        };
+        if !completed {
+            break;
+        }
+    }
+
+    report.elapsed = started.elapsed();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn count(conn: &Connection) -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM t", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_substitute_variables() {
+        let vars = vars(&[("date", "2024-01-01"), ("table", "events")]);
+        assert_eq!(
+            substitute_variables("SELECT * FROM ${table} WHERE day = '${date}'", &vars).unwrap(),
+            "SELECT * FROM events WHERE day = '2024-01-01'"
+        );
+        assert_eq!(
+            substitute_variables("LIMIT ${n:-10}; SELECT '$${literal}'", &vars).unwrap(),
+            "LIMIT 10; SELECT '${literal}'"
+        );
+        let error = substitute_variables("SELECT ${missing}", &vars).unwrap_err();
+        assert!(error.to_string().contains("missing"));
+        assert!(substitute_variables("SELECT ${open", &vars).is_err());
+    }
+
+    #[test]
+    fn test_on_error_policies() -> Result<()> {
+        let script =
+            "CREATE TABLE t (x INTEGER); INSERT INTO missing VALUES (1); INSERT INTO t VALUES (2);";
+
+        let conn = Connection::open_in_memory()?;
+        let report = run_script(&conn, script, &ScriptOptions::default(), |_| {})?;
+        assert_eq!((report.failed(), report.skipped()), (1, 1));
+        assert_eq!(count(&conn), 0);
+
+        let conn = Connection::open_in_memory()?;
+        let options = ScriptOptions {
+            on_error: OnError::Continue,
+            ..Default::default()
+        };
+        let mut seen = Vec::new();
+        let report = run_script(&conn, script, &options, |o| seen.push(o.index))?;
+        assert_eq!(seen, [1, 2, 3]);
+        assert_eq!((report.failed(), report.committed()), (1, 2));
+        assert_eq!(count(&conn), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_transaction_modes() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute_batch("CREATE TABLE t (x INTEGER)")?;
+        let script =
+            "INSERT INTO t VALUES (1); INSERT INTO t VALUES (2); INSERT INTO t VALUES ('x');";
+
+        let options = ScriptOptions {
+            transaction: TransactionMode::Script,
+            ..Default::default()
+        };
+        let report = run_script(&conn, script, &options, |_| {})?;
+        assert_eq!((report.failed(), report.rolled_back), (1, 2));
+        assert_eq!(count(&conn), 0);
+
+        let options = ScriptOptions {
+            transaction: TransactionMode::Batches(2),
+            ..Default::default()
+        };
+        let report = run_script(&conn, script, &options, |_| {})?;
+        assert_eq!((report.committed(), report.failed()), (2, 1));
+        assert_eq!(count(&conn), 2);
+
+        let invalid = ScriptOptions {
+            on_error: OnError::Continue,
+            transaction: TransactionMode::Script,
+            ..Default::default()
+        };
+        assert!(run_script(&conn, script, &invalid, |_| {}).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_policies() {
+        assert_eq!(OnError::parse("Continue").unwrap(), OnError::Continue);
+        assert!(OnError::parse("retry").is_err());
+        assert_eq!(
+            TransactionMode::parse("none").unwrap(),
+            TransactionMode::Autocommit
+        );
+        assert_eq!(
+            TransactionMode::parse("script").unwrap(),
+            TransactionMode::Script
+        );
+        assert_eq!(
+            TransactionMode::parse("500").unwrap(),
+            TransactionMode::Batches(500)
+        );
+        assert!(TransactionMode::parse("sometimes").is_err());
+    }
+}
@@ -0,0 +1,1154 @@
+//! # HTTP Query Server
+//!
+//! `frozen-duckdb serve` exposes a database over a small HTTP API, so
+//! dashboards and scripts in other languages can query it without linking
+//! DuckDB themselves.
+//!
+//! | Route | Description |
+//! |-------|-------------|
+//! | `POST /query` | Runs the SQL in the body and returns the result |
+//! | `GET /healthz` | Returns `{"status":"ok"}` while the database is usable |
+//! | `GET /metrics` | Prometheus metrics; see [`super::metrics`] |
+//!
+//! The body of `POST /query` is either the SQL text or a JSON object
+//! `{"sql": "..."}`. Results are a JSON array of objects keyed by column
+//! name, or an Arrow IPC stream when the request sends
+//! `Accept: application/vnd.apache.arrow.stream`. Failing queries return
+//! `400` with `{"error": "..."}`.
+//!
+//! With `--cache`, JSON results are served from the query cache of
+//! `frozen-duckdb query --cache` until the SQL or one of its input files
+//! changes. The cache relies on the database file not changing under it,
+//! so it can't be combined with `--read-write`.
+//!
+//! ## Security
+//!
+//! The database is opened read-only unless `--read-write` is given, so
+//! clients can't modify it. Queries can't reach anything but the database
+//! either: reading or writing other files (`read_csv`, `COPY ... TO`),
+//! `ATTACH`, and `INSTALL`/`LOAD` are refused, and the configuration is
+//! locked so `SET` can't lift this. `--allow-file-access` opts out, for
+//! servers whose clients are trusted with the server's files.
+//!
+//! With `--token` (or `FROZEN_DUCKDB_SERVE_TOKEN`) every `/query` request
+//! must send `Authorization: Bearer <token>`; `/healthz` and `/metrics`
+//! stay open for load balancers and scrapers. API keys mapped to roles in
+//! the config file narrow what each client may run, down to `SELECT` on
+//! specific schemas; see [`super::access`].
+//!
+//! The server speaks plain HTTP and listens on `127.0.0.1` by default; put
+//! it behind a TLS proxy before exposing it to a network.
+//!
+//! ## Concurrency and Limits
+//!
+//! Every client is served on its own thread, and each query runs on one of
+//! `--max-connections` pooled connections. Requests beyond the pool queue
+//! up to `--max-queue`; past that the server answers `503` with
+//! `Retry-After`. `--timeout` interrupts queries that run too long
+//! (`504`), `--max-rows` rejects larger results (`400`), and queries are
+//! interrupted when their client disconnects. See [`super::pool`].
+//!
+//! Responses close the connection, and request bodies must be sent with
+//! `Content-Length`.
+//!
+//! `--protocol postgres` serves the same database over the PostgreSQL wire
+//! protocol instead; see [`super::pgwire`].
+//!
+//! ## Usage Examples
+//!
+//! ```bash
+//! frozen-duckdb serve --database analytics.duckdb --listen 127.0.0.1:8080 --token s3cret
+//!
+//! curl -H "Authorization: Bearer s3cret" \
+//!      -d "SELECT region, SUM(amount) FROM sales GROUP BY region" \
+//!      http://127.0.0.1:8080/query
+//!
+//! curl http://127.0.0.1:8080/metrics
+//! ```
+
+use super::access::{AccessControl, Role, Verdict};
+use super::dataset_manager::DatasetManager;
+use super::metrics::{self, Metrics, METRICS_CONTENT_TYPE};
+use super::pgwire::serve_postgres;
+use super::pool::{Cancellation, ConnectionPool, PoolOptions, Watchdog};
+use super::query_cache::QueryCache;
+use anyhow::{anyhow, bail, Context, Result};
+use duckdb::arrow::ipc::writer::StreamWriter;
+use duckdb::{AccessMode, Config, Connection};
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// Environment variable holding the bearer token if `--token` isn't given.
+pub const SERVE_TOKEN_ENV: &str = "FROZEN_DUCKDB_SERVE_TOKEN";
+
+/// Media type of Arrow IPC stream responses.
+pub const ARROW_STREAM_MEDIA_TYPE: &str = "application/vnd.apache.arrow.stream";
+
+/// Largest accepted request body.
+const MAX_BODY_BYTES: usize = 1 << 20;
+
+/// Largest accepted request line and headers.
+const MAX_HEAD_BYTES: usize = 64 << 10;
+
+/// Longest a client may take to send its request.
+const REQUEST_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Status of requests whose client disconnected before the reply, as
+/// logged by nginx. Never sent, since nobody is left to receive it.
+const CLIENT_CLOSED_REQUEST: u16 = 499;
+
+/// Protocol spoken by [`serve`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    /// The JSON / Arrow HTTP API
+    Http,
+    /// The PostgreSQL wire protocol (experimental)
+    Postgres,
+}
+
+impl Protocol {
+    /// Parses `http` or `postgres`.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "http" => Ok(Self::Http),
+            "postgres" | "postgresql" | "pg" => Ok(Self::Postgres),
+            other => Err(anyhow!(
+                "Unsupported protocol: {} (use http or postgres)",
+                other
+            )),
+        }
+    }
+
+    /// Returns the `protocol` label of this protocol's metrics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Http => "http",
+            Self::Postgres => "postgres",
+        }
+    }
+}
+
+/// Options for [`serve`].
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    /// DuckDB database file to serve
+    pub database: String,
+    /// Address to listen on
+    pub listen: String,
+    /// Protocol to serve
+    pub protocol: Protocol,
+    /// Bearer token required on `/query`, if any
+    pub token: Option<String>,
+    /// API keys accepted on `/query` and the roles they grant, if any
+    pub access: Option<AccessControl>,
+    /// Open the database read-write instead of read-only
+    pub read_write: bool,
+    /// Let queries read and write other files, attach databases, and load
+    /// extensions
+    pub allow_file_access: bool,
+    /// Serve `/query` JSON results from the query cache
+    pub cache: bool,
+    /// Additional address serving only `/metrics`, if any
+    pub metrics_listen: Option<String>,
+    /// Connection pool size and request queueing
+    pub pool: PoolOptions,
+    /// Limits applied to every query
+    pub limits: QueryLimits,
+}
+
+impl Default for ServeOptions {
+    fn default() -> Self {
+        Self {
+            database: String::new(),
+            listen: "127.0.0.1:8080".to_string(),
+            protocol: Protocol::Http,
+            token: None,
+            access: None,
+            read_write: false,
+            allow_file_access: false,
+            cache: false,
+            metrics_listen: None,
+            pool: PoolOptions::default(),
+            limits: QueryLimits::default(),
+        }
+    }
+}
+
+/// Limits applied to every query a server runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryLimits {
+    /// Largest number of result rows
+    pub max_rows: Option<usize>,
+    /// Longest a query may run
+    pub timeout: Option<Duration>,
+}
+
+/// A response produced by [`QueryServer::respond`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reply {
+    /// HTTP status code
+    pub status: u16,
+    /// Value of the `Content-Type` header
+    pub content_type: &'static str,
+    /// Response body
+    pub body: Vec<u8>,
+}
+
+impl Reply {
+    fn json(status: u16, value: serde_json::Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::json(status, serde_json::json!({ "error": message.into() }))
+    }
+}
+
+/// Answers API requests against a pool of connections to one database.
+pub struct QueryServer {
+    pool: ConnectionPool,
+    token: Option<String>,
+    access: Option<AccessControl>,
+    cache: Option<QueryCache>,
+    limits: QueryLimits,
+}
+
+/// What an authorized `/query` request may run.
+enum Grant<'a> {
+    /// Anything, with the bearer token or without authentication
+    All,
+    /// What the role of its API key allows
+    Role(&'a Role),
+}
+
+impl QueryServer {
+    /// Opens the database named in `options`, read-only unless
+    /// `read_write` is set, with a pool and limits as configured.
+    pub fn open(options: &ServeOptions) -> Result<Self> {
+        let pool = ConnectionPool::new(open_database(options)?, options.pool.clone())?;
+        let mut server = Self::with_pool(pool, options.token.clone()).with_limits(options.limits);
+        if let Some(access) = &options.access {
+            server = server.with_access(access.clone());
+        }
+        if options.cache {
+            return Ok(server.with_cache(QueryCache::new()?));
+        }
+        Ok(server)
+    }
+
+    /// Serves an existing connection, requiring `token` if given.
+    pub fn with_connection(conn: Connection, token: Option<String>) -> Result<Self> {
+        let options = PoolOptions {
+            size: 1,
+            ..Default::default()
+        };
+        Ok(Self::with_pool(ConnectionPool::new(conn, options)?, token))
+    }
+
+    /// Serves the connections of `pool`, requiring `token` if given.
+    pub fn with_pool(pool: ConnectionPool, token: Option<String>) -> Self {
+        Self {
+            pool,
+            token: token.filter(|t| !t.is_empty()),
+            access: None,
+            cache: None,
+            limits: QueryLimits::default(),
+        }
+    }
+
+    /// Applies `limits` to every query.
+    pub fn with_limits(mut self, limits: QueryLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Accepts the API keys of `access`, limiting each to what its role
+    /// allows. Without a token, requests must then send one of the keys.
+    pub fn with_access(mut self, access: AccessControl) -> Self {
+        self.access = Some(access);
+        self
+    }
+
+    /// Serves JSON query results from `cache` while the SQL and its inputs
+    /// are unchanged.
+    pub fn with_cache(mut self, cache: QueryCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Answers the one request of a client connection, then closes it.
+    pub fn handle(&self, mut stream: TcpStream) -> Result<()> {
+        let _connection = Metrics::global().connection(Protocol::Http.as_str());
+        stream.set_read_timeout(Some(REQUEST_READ_TIMEOUT))?;
+
+        let (request_line, reply) = match read_request(&mut stream) {
+            Ok(request) => {
+                let reply = self.respond_to(
+                    &request.method,
+                    &request.path,
+                    request.header("Authorization"),
+                    request.header("Accept"),
+                    &request.body,
+                    Some(&stream),
+                );
+                (format!("{} {}", request.method, request.path), reply)
+            }
+            Err(reply) => ("Invalid request".to_string(), reply),
+        };
+        if reply.status == CLIENT_CLOSED_REQUEST {
+            info!("{} -> client disconnected, query interrupted", request_line);
+            return Ok(());
+        }
+        info!("{} -> {}", request_line, reply.status);
+        write_reply(&mut stream, &reply).context("Failed to send response")
+    }
+
+    /// Routes one request.
+    ///
+    /// `authorization` and `accept` are the values of the corresponding
+    /// request headers.
+    pub fn respond(
+        &self,
+        method: &str,
+        path: &str,
+        authorization: Option<&str>,
+        accept: Option<&str>,
+        body: &str,
+    ) -> Reply {
+        self.respond_to(method, path, authorization, accept, body, None)
+    }
+
+    /// Routes one request from `client`, whose queries are interrupted if
+    /// it disconnects.
+    fn respond_to(
+        &self,
+        method: &str,
+        path: &str,
+        authorization: Option<&str>,
+        accept: Option<&str>,
+        body: &str,
+        client: Option<&TcpStream>,
+    ) -> Reply {
+        let path = path.split('?').next().unwrap_or_default();
+        match (method, path) {
+            ("GET", "/healthz") => match self.pool.get().and_then(|m| m.run_query("SELECT 1")) {
+                Ok(_) => Reply::json(200, serde_json::json!({ "status": "ok" })),
+                Err(e) => Reply::error(503, format!("{:#}", e)),
+            },
+            ("GET", "/metrics") => match Metrics::global().encode() {
+                Ok(text) => Reply {
+                    status: 200,
+                    content_type: METRICS_CONTENT_TYPE,
+                    body: text.into_bytes(),
+                },
+                Err(e) => Reply::error(500, format!("{:#}", e)),
+            },
+            ("POST", "/query") => {
+                let Some(grant) = self.authorize(authorization) else {
+                    return Reply::error(401, "Missing or invalid bearer token");
+                };
+                let sql = match query_sql(body) {
+                    Some(sql) => sql,
+                    None => return Reply::error(400, "Request body must contain SQL"),
+                };
+                let arrow = accept.is_some_and(|a| a.contains(ARROW_STREAM_MEDIA_TYPE));
+                let manager = match self.pool.get() {
+                    Ok(manager) => manager,
+                    Err(e) => return Reply::error(503, format!("{:#}", e)),
+                };
+                if let Grant::Role(role) = grant {
+                    match role.check(manager.connection(), &sql) {
+                        Ok(Verdict::Allowed) => {}
+                        Ok(Verdict::Denied(reason)) => {
+                            warn!("🚫 {}", reason);
+                            return Reply::error(403, reason);
+                        }
+                        Err(e) => return Reply::error(400, format!("{:#}", e)),
+                    }
+                }
+
+                let started = Instant::now();
+                let interrupt = manager.connection().interrupt_handle();
+                let watchdog = Watchdog::start(interrupt, self.limits.timeout, client);
+                let result = if arrow {
+                    self.query_arrow(&manager, &sql).map(|body| Reply {
+                        status: 200,
+                        content_type: ARROW_STREAM_MEDIA_TYPE,
+                        body,
+                    })
+                } else {
+                    self.query_json(&manager, &sql)
+                };
+                let cancellation = watchdog.stop();
+                Metrics::global().observe_query(
+                    Protocol::Http.as_str(),
+                    started.elapsed(),
+                    result.is_ok() && cancellation.is_none(),
+                );
+
+                match cancellation {
+                    Some(Cancellation::TimedOut(limit)) => {
+                        Reply::error(504, format!("Query exceeded the time limit of {:?}", limit))
+                    }
+                    Some(Cancellation::Disconnected) => {
+                        Reply::error(CLIENT_CLOSED_REQUEST, "Client disconnected")
+                    }
+                    None => result.unwrap_or_else(|e| Reply::error(400, format!("{:#}", e))),
+                }
+            }
+            (_, "/healthz") | (_, "/metrics") | (_, "/query") => {
+                Reply::error(405, "Method not allowed")
+            }
+            _ => Reply::error(404, "Not found"),
+        }
+    }
+
+    fn authorize(&self, authorization: Option<&str>) -> Option<Grant<'_>> {
+        if self.token.is_none() && self.access.is_none() {
+            return Some(Grant::All);
+        }
+        let given = authorization
+            .and_then(|h| h.strip_prefix("Bearer "))?
+            .trim();
+        if let Some(token) = &self.token {
+            if constant_time_eq(given.as_bytes(), token.as_bytes()) {
+                return Some(Grant::All);
+            }
+        }
+        let role = self.access.as_ref()?.role(given)?;
+        Some(Grant::Role(role))
+    }
+
+    fn query_json(&self, manager: &DatasetManager, sql: &str) -> Result<Reply> {
+        let max_rows = self.limits.max_rows;
+        let output = match &self.cache {
+            Some(cache) => manager.run_query_cached_limited(sql, cache, max_rows)?,
+            None => manager.run_query_limited(sql, max_rows)?,
+        };
+        Ok(Reply::json(200, output.to_json()))
+    }
+
+    fn query_arrow(&self, manager: &DatasetManager, sql: &str) -> Result<Vec<u8>> {
+        let mut stmt = manager
+            .connection()
+            .prepare(sql)
+            .with_context(|| format!("Failed to prepare query: {}", sql))?;
+        let batches = stmt.query_arrow([])?;
+        let schema = batches.get_schema();
+
+        let mut body = Vec::new();
+        let mut writer = StreamWriter::try_new(&mut body, &schema)?;
+        let mut rows = 0;
+        for batch in batches {
+            rows += batch.num_rows();
+            if let Some(max) = self.limits.max_rows.filter(|max| rows > *max) {
+                bail!("Result exceeds the limit of {} rows", max);
+            }
+            writer.write(&batch)?;
+        }
+        writer.finish()?;
+        drop(writer);
+        Ok(body)
+    }
+}
+
+/// Opens the database named in `options`, read-only unless `read_write`
+/// is set.
+///
+/// Unless `allow_file_access` is set, external access is disabled and the
+/// configuration locked for every connection to the database, so queries
+/// only reach the database file and, with `cache`, the query cache.
+pub fn open_database(options: &ServeOptions) -> Result<Connection> {
+    let mode = if options.read_write {
+        AccessMode::ReadWrite
+    } else {
+        AccessMode::ReadOnly
+    };
+    let mut config = Config::default().access_mode(mode)?;
+    if !options.allow_file_access {
+        if options.cache {
+            let root = QueryCache::new()?
+                .root()
+                .to_string_lossy()
+                .replace('\'', "''");
+            config = config.with("allowed_directories", format!("['{}']", root))?;
+        }
+        config = config
+            .enable_external_access(false)?
+            .with("lock_configuration", "true")?;
+    }
+    Connection::open_with_flags(&options.database, config)
+        .with_context(|| format!("Failed to open DuckDB database: {}", options.database))
+}
+
+/// Extracts the SQL from a `/query` body: either `{"sql": "..."}` or the
+/// SQL text itself.
+fn query_sql(body: &str) -> Option<String> {
+    let sql = match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Object(object)) => object.get("sql")?.as_str()?.to_string(),
+        _ => body.to_string(),
+    };
+    let sql = sql.trim();
+    (!sql.is_empty()).then(|| sql.to_string())
+}
+
+/// Compares two byte strings in time independent of where they differ.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Serves the database until the process is interrupted.
+pub fn serve(options: &ServeOptions) -> Result<()> {
+    if options.cache && options.read_write {
+        bail!("--cache can't be combined with --read-write: writes don't invalidate the cache");
+    }
+    if options.access.is_some() && options.protocol == Protocol::Postgres {
+        bail!("API key roles (serve.api_keys) only apply to --protocol http");
+    }
+    if options.cache && options.protocol == Protocol::Postgres {
+        warn!("⚠️  --cache only applies to the HTTP API; PostgreSQL queries run uncached");
+    }
+    if let Some(listen) = &options.metrics_listen {
+        metrics::spawn_listener(listen)?;
+    }
+    match options.protocol {
+        Protocol::Http => serve_http(options),
+        Protocol::Postgres => serve_postgres(options),
+    }
+}
+
+fn serve_http(options: &ServeOptions) -> Result<()> {
+    let server = Arc::new(QueryServer::open(options)?);
+    let listener = TcpListener::bind(&options.listen)
+        .with_context(|| format!("Failed to listen on {}", options.listen))?;
+
+    info!(
+        "🌐 Serving {} on http://{} ({}{}, {} connections{})",
+        options.database,
+        options.listen,
+        if options.read_write {
+            "read-write"
+        } else {
+            "read-only"
+        },
+        if options.allow_file_access {
+            ", file access allowed"
+        } else {
+            ""
+        },
+        server.pool.size(),
+        if server.access.is_some() {
+            ", API key required"
+        } else if server.token.is_some() {
+            ", bearer token required"
+        } else {
+            ""
+        }
+    );
+    if let Some(access) = &server.access {
+        for role in access.roles() {
+            let statements: Vec<_> = role.statements.iter().map(|t| t.as_str()).collect();
+            let schemas = match &role.schemas {
+                Some(schemas) => schemas.join(", "),
+                None => "any".to_string(),
+            };
+            info!(
+                "🔑 Role {}: {} in schemas {}",
+                role.name,
+                statements.join(", "),
+                schemas
+            );
+        }
+    }
+    if server.token.is_none() && server.access.is_none() && !options.listen.starts_with("127.0.0.1")
+    {
+        warn!("⚠️  Listening beyond localhost without a token; anyone who can connect can query");
+    }
+
+    // Clients beyond what the pool and its queue can take are turned away
+    // before a thread is spent on them
+    let max_clients = server.pool.size() + options.pool.max_waiting;
+    let clients = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        if clients.load(Ordering::Acquire) >= max_clients {
+            warn!(
+                "Server busy: rejecting client beyond {} open requests",
+                max_clients
+            );
+            let _ = write_reply(&mut stream, &Reply::error(503, "Server busy"));
+            continue;
+        }
+
+        clients.fetch_add(1, Ordering::AcqRel);
+        let server = Arc::clone(&server);
+        let clients = Arc::clone(&clients);
+        thread::spawn(move || {
+            if let Err(e) = server.handle(stream) {
+                debug!("Failed to answer client: {:#}", e);
+            }
+            clients.fetch_sub(1, Ordering::AcqRel);
+        });
+    }
+    Ok(())
+}
+
+/// A request read by [`read_request`].
+#[derive(Debug)]
+struct HttpRequest {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl HttpRequest {
+    /// Returns the value of the header `name`, ignoring case.
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Reads one HTTP/1.1 request from `stream`. When the request can't be
+/// read, the error is the reply to send instead.
+fn read_request<S: Read + Write>(stream: &mut S) -> std::result::Result<HttpRequest, Reply> {
+    let read_error = |e: io::Error| match e.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => Reply::error(408, "Request timed out"),
+        _ => Reply::error(400, format!("Failed to read request: {}", e)),
+    };
+    let mut reader = BufReader::new(stream);
+    let mut head_bytes = 0;
+    let mut read_line = |reader: &mut BufReader<&mut S>| {
+        let mut line = String::new();
+        let limit = (MAX_HEAD_BYTES - head_bytes) as u64;
+        let read = reader
+            .take(limit)
+            .read_line(&mut line)
+            .map_err(read_error)?;
+        head_bytes += read;
+        if !line.ends_with('\n') {
+            return Err(if head_bytes >= MAX_HEAD_BYTES {
+                Reply::error(431, "Request headers too large")
+            } else {
+                Reply::error(400, "Incomplete request")
+            });
+        }
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    };
+
+    let request_line = read_line(&mut reader)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(path), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(Reply::error(400, "Malformed request line"));
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(Reply::error(505, "Only HTTP/1.x is supported"));
+    }
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(&mut reader)?;
+        if line.is_empty() {
+            break;
+        }
+        let Some((field, value)) = line.split_once(':') else {
+            return Err(Reply::error(400, "Malformed header"));
+        };
+        headers.push((field.trim().to_string(), value.trim().to_string()));
+    }
+    let mut request = HttpRequest {
+        method: method.to_string(),
+        path: path.to_string(),
+        headers,
+        body: String::new(),
+    };
+
+    if request.header("Transfer-Encoding").is_some() {
+        return Err(Reply::error(
+            411,
+            "Send the request body with Content-Length",
+        ));
+    }
+    let length = match request.header("Content-Length") {
+        Some(value) => value
+            .parse::<usize>()
+            .map_err(|_| Reply::error(400, "Invalid Content-Length"))?,
+        None => 0,
+    };
+    if length > MAX_BODY_BYTES {
+        return Err(Reply::error(413, "Request body too large"));
+    }
+    if length > 0 {
+        if request
+            .header("Expect")
+            .is_some_and(|expect| expect.eq_ignore_ascii_case("100-continue"))
+        {
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+                .map_err(read_error)?;
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).map_err(read_error)?;
+        request.body =
+            String::from_utf8(body).map_err(|_| Reply::error(400, "Request body must be UTF-8"))?;
+    }
+    Ok(request)
+}
+
+/// Writes `reply` as a response that closes the connection.
+fn write_reply<W: Write>(writer: &mut W, reply: &Reply) -> io::Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        reply.status,
+        reason_phrase(reply.status),
+        reply.content_type,
+        reply.body.len()
+    );
+    if reply.status == 503 {
+        head.push_str("Retry-After: 1\r\n");
+    }
+    head.push_str("\r\n");
+    writer.write_all(head.as_bytes())?;
+    writer.write_all(&reply.body)?;
+    writer.flush()
+}
+
+/// Returns the reason phrase of the statuses the server sends.
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        505 => "HTTP Version Not Supported",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::sql_path::path_literal;
+    use duckdb::StatementType;
+    use std::io::Cursor;
+
+    /// A request on one side and what the server wrote on the other.
+    struct Duplex {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Duplex {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Duplex {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn parse(request: &str) -> (std::result::Result<HttpRequest, Reply>, Vec<u8>) {
+        let mut duplex = Duplex {
+            input: Cursor::new(request.as_bytes().to_vec()),
+            output: Vec::new(),
+        };
+        (read_request(&mut duplex), duplex.output)
+    }
+
+    fn server(token: Option<&str>) -> QueryServer {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE t AS SELECT range AS x FROM range(3)")
+            .unwrap();
+        QueryServer::with_connection(conn, token.map(String::from)).unwrap()
+    }
+
+    #[test]
+    fn test_protocol_parse() {
+        assert_eq!(Protocol::parse("HTTP").unwrap(), Protocol::Http);
+        assert_eq!(Protocol::parse("postgres").unwrap(), Protocol::Postgres);
+        assert!(Protocol::parse("mysql").is_err());
+    }
+
+    #[test]
+    fn test_healthz_and_routing() {
+        let server = server(Some("s3cret"));
+        let health = server.respond("GET", "/healthz", None, None, "");
+        assert_eq!(health.status, 200);
+        assert_eq!(health.body, br#"{"status":"ok"}"#);
+        assert_eq!(server.respond("GET", "/query", None, None, "").status, 405);
+        assert_eq!(
+            server.respond("GET", "/missing", None, None, "").status,
+            404
+        );
+    }
+
+    #[test]
+    fn test_query_json_and_auth() {
+        let server = server(Some("s3cret"));
+        let sql = r#"{"sql": "SELECT COUNT(*) AS n FROM t"}"#;
+
+        assert_eq!(
+            server.respond("POST", "/query", None, None, sql).status,
+            401
+        );
+        let wrong = server.respond("POST", "/query", Some("Bearer nope"), None, sql);
+        assert_eq!(wrong.status, 401);
+
+        let reply = server.respond("POST", "/query", Some("Bearer s3cret"), None, sql);
+        assert_eq!(reply.status, 200);
+        let json: serde_json::Value = serde_json::from_slice(&reply.body).unwrap();
+        assert_eq!(json, serde_json::json!([{ "n": 3 }]));
+
+        let error = server.respond("POST", "/query", Some("Bearer s3cret"), None, "SELEC 1");
+        assert_eq!(error.status, 400);
+        assert_eq!(
+            server
+                .respond("POST", "/query", Some("Bearer s3cret"), None, " ")
+                .status,
+            400
+        );
+    }
+
+    #[test]
+    fn test_api_key_roles() -> Result<()> {
+        let access = AccessControl::default()
+            .with_role(Role::new("reader", vec![StatementType::Select]))
+            .with_key("r-key", "reader")?;
+        let server = server(Some("admin")).with_access(access);
+        let query = |key: Option<&str>, sql: &str| {
+            let header = key.map(|k| format!("Bearer {}", k));
+            server.respond("POST", "/query", header.as_deref(), None, sql)
+        };
+
+        assert_eq!(
+            query(Some("r-key"), "SELECT COUNT(*) AS n FROM t").status,
+            200
+        );
+        let denied = query(Some("r-key"), "SELECT 1; DROP TABLE t");
+        assert_eq!(denied.status, 403);
+        assert!(String::from_utf8(denied.body)?.contains("may not run DROP statements"));
+        assert_eq!(query(Some("r-key"), "SELEC 1").status, 400);
+        assert_eq!(query(Some("other"), "SELECT 1").status, 401);
+        assert_eq!(query(None, "SELECT 1").status, 401);
+
+        // The token still allows everything
+        assert_eq!(
+            query(Some("admin"), "CREATE TEMP TABLE u (y INTEGER)").status,
+            200
+        );
+        assert_eq!(query(Some("r-key"), "SELECT COUNT(*) FROM t").status, 200);
+        Ok(())
+    }
+
+    #[test]
+    fn test_roles_need_http() {
+        let options = ServeOptions {
+            protocol: Protocol::Postgres,
+            access: Some(AccessControl::default()),
+            ..Default::default()
+        };
+        assert!(serve(&options).is_err());
+    }
+
+    #[test]
+    fn test_query_arrow_stream() {
+        let server = server(None);
+        let reply = server.respond(
+            "POST",
+            "/query",
+            None,
+            Some(ARROW_STREAM_MEDIA_TYPE),
+            "SELECT x FROM t",
+        );
+        assert_eq!(reply.status, 200);
+        assert_eq!(reply.content_type, ARROW_STREAM_MEDIA_TYPE);
+        // IPC stream messages start with the continuation marker
+        assert_eq!(&reply.body[..4], &[0xFF; 4]);
+    }
+
+    #[test]
+    fn test_metrics_route() {
+        let server = server(None);
+        server.respond("POST", "/query", None, None, "SELECT COUNT(*) FROM t");
+
+        let reply = server.respond("GET", "/metrics", None, None, "");
+        assert_eq!(reply.status, 200);
+        assert_eq!(reply.content_type, METRICS_CONTENT_TYPE);
+        let text = String::from_utf8(reply.body).unwrap();
+        assert!(text.contains(r#"frozen_duckdb_queries_total{protocol="http",status="ok"}"#));
+        assert!(text.contains(r#"frozen_duckdb_query_duration_seconds_count{protocol="http"}"#));
+        assert_eq!(
+            server.respond("POST", "/metrics", None, None, "").status,
+            405
+        );
+    }
+
+    #[test]
+    fn test_query_cache() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let server = server(None).with_cache(QueryCache::with_root(temp.path())?);
+        let sql = "SELECT SUM(x) AS total FROM t";
+
+        let first = server.respond("POST", "/query", None, None, sql);
+        let second = server.respond("POST", "/query", None, None, sql);
+        assert_eq!(first.status, 200);
+        assert_eq!(first.body, second.body);
+        assert_eq!(std::fs::read_dir(temp.path())?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_cache_row_limit() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let server = server(None)
+            .with_cache(QueryCache::with_root(temp.path())?)
+            .with_limits(QueryLimits {
+                max_rows: Some(2),
+                ..Default::default()
+            });
+
+        let over = server.respond("POST", "/query", None, None, "SELECT x FROM t");
+        assert_eq!(over.status, 400);
+        assert!(String::from_utf8_lossy(&over.body).contains("limit of 2 rows"));
+        assert_eq!(std::fs::read_dir(temp.path())?.count(), 0);
+
+        let sql = "SELECT x FROM t ORDER BY x LIMIT 2";
+        assert_eq!(
+            server.respond("POST", "/query", None, None, sql).status,
+            200
+        );
+        assert_eq!(std::fs::read_dir(temp.path())?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_needs_read_only() {
+        let error = serve(&ServeOptions {
+            read_write: true,
+            cache: true,
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("--read-write"));
+    }
+
+    #[test]
+    fn test_read_request() {
+        let (request, written) = parse(
+            "POST /query?x=1 HTTP/1.1\r\nauthorization: Bearer s3cret\r\n\
+             Content-Length: 8\r\nExpect: 100-continue\r\n\r\nSELECT 1",
+        );
+        let request = request.unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/query?x=1");
+        assert_eq!(request.header("Authorization"), Some("Bearer s3cret"));
+        assert_eq!(request.body, "SELECT 1");
+        assert_eq!(written, b"HTTP/1.1 100 Continue\r\n\r\n");
+
+        let (request, written) = parse("GET /healthz HTTP/1.0\r\n\r\n");
+        assert_eq!(request.unwrap().body, "");
+        assert!(written.is_empty());
+    }
+
+    #[test]
+    fn test_read_request_errors() {
+        let status = |request: &str| parse(request).0.unwrap_err().status;
+        assert_eq!(status("GET /healthz HTTP/1.1\r\n"), 400);
+        assert_eq!(status("GARBAGE\r\n\r\n"), 400);
+        assert_eq!(status("GET / HTTP/2\r\n\r\n"), 505);
+        assert_eq!(
+            status("POST /query HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n"),
+            411
+        );
+        assert_eq!(
+            status("POST /query HTTP/1.1\r\nContent-Length: 99999999\r\n\r\n"),
+            413
+        );
+        let huge = format!(
+            "GET / HTTP/1.1\r\nX: {}\r\n\r\n",
+            "a".repeat(MAX_HEAD_BYTES)
+        );
+        assert_eq!(status(&huge), 431);
+    }
+
+    #[test]
+    fn test_write_reply() {
+        let mut out = Vec::new();
+        write_reply(&mut out, &Reply::error(503, "Server busy")).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+        assert!(text.contains("Retry-After: 1\r\n"));
+        assert!(text.contains("Connection: close\r\n"));
+        assert!(text.ends_with("\r\n\r\n{\"error\":\"Server busy\"}"));
+    }
+
+    #[test]
+    fn test_handle_over_tcp() -> Result<()> {
+        let server = server(None);
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let mut client = TcpStream::connect(listener.local_addr()?)?;
+        let sql = "SELECT COUNT(*) AS n FROM t";
+        write!(
+            client,
+            "POST /query HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+            sql.len(),
+            sql
+        )?;
+
+        let (stream, _) = listener.accept()?;
+        server.handle(stream)?;
+        let mut response = String::new();
+        client.read_to_string(&mut response)?;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(r#"[{"n":3}]"#));
+        Ok(())
+    }
+
+    #[test]
+    fn test_row_limit() {
+        let server = server(None).with_limits(QueryLimits {
+            max_rows: Some(2),
+            ..Default::default()
+        });
+        let over = server.respond("POST", "/query", None, None, "SELECT x FROM t");
+        assert_eq!(over.status, 400);
+        assert!(String::from_utf8_lossy(&over.body).contains("limit of 2 rows"));
+        let arrow = Some(ARROW_STREAM_MEDIA_TYPE);
+        assert_eq!(
+            server
+                .respond("POST", "/query", None, arrow, "SELECT x FROM t")
+                .status,
+            400
+        );
+        assert_eq!(
+            server
+                .respond("POST", "/query", None, None, "SELECT x FROM t LIMIT 2")
+                .status,
+            200
+        );
+    }
+
+    #[test]
+    fn test_requests_dont_share_sessions() {
+        // One pooled connection, so the second request reuses its slot
+        let server = server(None);
+        let post = |sql: &str| server.respond("POST", "/query", None, None, sql);
+        let sql = "CREATE TEMP TABLE scratch AS SELECT 1 AS y; \
+                   BEGIN; INSERT INTO t VALUES (10); SELECT COUNT(*) AS n FROM t";
+        assert_eq!(post(sql).body, br#"[{"n":4}]"#);
+
+        let reply = post("SELECT COUNT(*) AS n FROM t");
+        assert_eq!(reply.body, br#"[{"n":3}]"#);
+        assert_eq!(post("SELECT * FROM scratch").status, 400);
+        assert_eq!(post("BEGIN; SELECT 1 AS x").status, 200);
+    }
+
+    #[test]
+    fn test_query_timeout() {
+        let server = server(None).with_limits(QueryLimits {
+            timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        });
+        let sql = "SELECT COUNT(*) FROM range(10000000000) a, range(10000000000) b";
+        let reply = server.respond("POST", "/query", None, None, sql);
+        assert_eq!(reply.status, 504);
+        assert_eq!(
+            server
+                .respond("POST", "/query", None, None, "SELECT 1")
+                .status,
+            200
+        );
+    }
+
+    #[test]
+    fn test_file_access_is_refused_by_default() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let path = temp.path().join("served.duckdb");
+        Connection::open(&path)?.execute_batch("CREATE TABLE t AS SELECT 1 AS x")?;
+        let csv = temp.path().join("secret.csv");
+        std::fs::write(&csv, "x\n1\n")?;
+        let stolen = temp.path().join("stolen.csv");
+        let read_csv = format!("SELECT * FROM read_csv({})", path_literal(&csv));
+        let copy_to = format!("COPY (SELECT * FROM t) TO {}", path_literal(&stolen));
+
+        let options = ServeOptions {
+            database: path.to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+        let server = QueryServer::open(&options)?;
+        let post = |sql: &str| server.respond("POST", "/query", None, None, sql).status;
+        assert_eq!(post(&read_csv), 400);
+        assert_eq!(post(&copy_to), 400);
+        assert!(!stolen.exists());
+        assert_eq!(post("SET enable_external_access = true"), 400);
+        assert_eq!(post("SELECT * FROM t"), 200);
+        drop(server);
+
+        let trusted = QueryServer::open(&ServeOptions {
+            allow_file_access: true,
+            ..options
+        })?;
+        assert_eq!(
+            trusted
+                .respond("POST", "/query", None, None, &read_csv)
+                .status,
+            200
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only_by_default() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let path = temp.path().join("served.duckdb");
+        Connection::open(&path)?.execute_batch("CREATE TABLE t (x INTEGER)")?;
+
+        let server = QueryServer::open(&ServeOptions {
+            database: path.to_string_lossy().into_owned(),
+            ..Default::default()
+        })?;
+        let reply = server.respond("POST", "/query", None, None, "INSERT INTO t VALUES (1)");
+        assert_eq!(reply.status, 400);
+        assert_eq!(
+            server
+                .respond("POST", "/query", None, None, "SELECT * FROM t")
+                .status,
+            200
+        );
+        Ok(())
+    }
+}
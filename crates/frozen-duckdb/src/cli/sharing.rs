@@ -0,0 +1,529 @@
+//! # Per-Consumer Subsets of a Database
+//!
+//! A multi-tenant DuckDB file can't be handed to a partner as is. A share
+//! policy says which rows and columns one consumer may see; `share` turns
+//! it into filtered views in the database, or exports the subset as a
+//! separate database file the consumer attaches read-only.
+//!
+//! ## Policy File
+//!
+//! ```yaml
+//! consumer: acme
+//! where:
+//!   tenant_id: 42
+//! drop_columns: [cost_price, internal_notes]
+//! tables:
+//!   orders:
+//!     where: "status <> 'draft'"
+//!     drop_columns: [margin]
+//!   currencies: {}
+//! ```
+//!
+//! | Key | Meaning |
+//! |-----|---------|
+//! | `consumer` | Name of the consumer; the schema the views are created in |
+//! | `where` | Column values every row must have, in every table with that column; a list matches any of its values |
+//! | `drop_columns` | Columns removed from every table |
+//! | `tables` | Optional allowlist; each table may add a SQL `where` predicate and its own `drop_columns` |
+//!
+//! ## What Gets Shared
+//!
+//! A table is shared when it has at least one of the `where` columns, or
+//! when it is listed under `tables`. A table with no filter column is left
+//! out unless it is listed, so a new table never leaks all of its rows by
+//! default. When `tables` is given, only the listed tables are shared.
+//! Naming a dropped column a table doesn't have is an error, to catch
+//! typos in the policy.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use frozen_duckdb::cli::sharing::{export, plan, source_tables, SharePolicy};
+//! use frozen_duckdb::cli::DatasetManager;
+//!
+//! let manager = DatasetManager::open("saas.duckdb")?;
+//! let policy = SharePolicy::load("acme.yaml")?;
+//! let plan = plan(&policy, &source_tables(manager.connection())?)?;
+//! export(manager.connection(), &plan, "acme.duckdb")?;
+//! ```
+
+use super::catalog::validate_name;
+use super::dedupe::quote_identifier;
+use super::sql_path::path_literal;
+use anyhow::{anyhow, bail, Context, Result};
+use duckdb::Connection;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// Table-specific rules of a [`SharePolicy`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TablePolicy {
+    /// Table name
+    pub name: String,
+    /// Extra SQL predicate rows must satisfy
+    pub predicate: Option<String>,
+    /// Columns removed from this table
+    pub drop_columns: Vec<String>,
+}
+
+/// Which rows and columns a consumer may see.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SharePolicy {
+    /// Consumer name, used as the schema of the views
+    pub consumer: String,
+    /// Column and SQL literals of allowed values, applied to every table
+    /// with the column
+    pub filters: Vec<(String, Vec<String>)>,
+    /// Columns removed from every table
+    pub drop_columns: Vec<String>,
+    /// Allowlist of tables with their own rules; `None` shares every table
+    /// with a filter column
+    pub tables: Option<Vec<TablePolicy>>,
+}
+
+impl SharePolicy {
+    /// Loads a YAML policy file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read share policy: {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid share policy: {}", path.display()))
+    }
+
+    /// Parses a YAML policy.
+    pub fn parse(yaml: &str) -> Result<Self> {
+        let documents = YamlLoader::load_from_str(yaml)?;
+        let root = documents
+            .first()
+            .ok_or_else(|| anyhow!("Share policy is empty"))?;
+        let consumer = root["consumer"]
+            .as_str()
+            .ok_or_else(|| anyhow!("'consumer' is required"))?
+            .to_string();
+        validate_name(&consumer)?;
+
+        let filters = match &root["where"] {
+            Yaml::BadValue => Vec::new(),
+            Yaml::Hash(filters) => filters
+                .iter()
+                .map(|(column, values)| {
+                    let column = column
+                        .as_str()
+                        .ok_or_else(|| anyhow!("'where' columns must be strings"))?;
+                    let values = match values {
+                        Yaml::Array(values) => values.iter().map(sql_literal).collect(),
+                        value => sql_literal(value).map(|literal| vec![literal]),
+                    }
+                    .with_context(|| format!("'where' value of {}", column))?;
+                    Ok((column.to_string(), values))
+                })
+                .collect::<Result<Vec<_>>>()?,
+            _ => bail!("'where' must map column names to values"),
+        };
+        let drop_columns = string_list(&root["drop_columns"], "drop_columns")?;
+
+        let tables = match &root["tables"] {
+            Yaml::BadValue => None,
+            Yaml::Hash(tables) => Some(
+                tables
+                    .iter()
+                    .map(|(name, spec)| {
+                        let name = name
+                            .as_str()
+                            .ok_or_else(|| anyhow!("Table names must be strings"))?;
+                        let predicate = match &spec["where"] {
+                            Yaml::BadValue => None,
+                            Yaml::String(predicate) => Some(predicate.clone()),
+                            _ => bail!("Table {}: 'where' must be a SQL predicate", name),
+                        };
+                        Ok(TablePolicy {
+                            name: name.to_string(),
+                            predicate,
+                            drop_columns: string_list(&spec["drop_columns"], "drop_columns")
+                                .with_context(|| format!("Table {}", name))?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            _ => bail!("'tables' must map table names to rules"),
+        };
+
+        if filters.is_empty() && tables.is_none() {
+            bail!("The policy shares nothing: give 'where' filters or list 'tables'");
+        }
+        Ok(Self {
+            consumer,
+            filters,
+            drop_columns,
+            tables,
+        })
+    }
+
+    fn table(&self, name: &str) -> Option<&TablePolicy> {
+        self.tables
+            .as_ref()
+            .and_then(|tables| tables.iter().find(|table| table.name == name))
+    }
+}
+
+fn sql_literal(value: &Yaml) -> Result<String> {
+    match value {
+        Yaml::Integer(n) => Ok(n.to_string()),
+        Yaml::Real(n) => Ok(n.clone()),
+        Yaml::Boolean(b) => Ok(b.to_string()),
+        Yaml::String(s) => Ok(format!("'{}'", s.replace('\'', "''"))),
+        _ => bail!("Expected a number, boolean, or string"),
+    }
+}
+
+fn string_list(value: &Yaml, key: &str) -> Result<Vec<String>> {
+    match value {
+        Yaml::BadValue => Ok(Vec::new()),
+        Yaml::Array(items) => items
+            .iter()
+            .map(|item| {
+                item.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| anyhow!("'{}' must list column names", key))
+            })
+            .collect(),
+        _ => bail!("'{}' must be a list of column names", key),
+    }
+}
+
+/// A table as shared with the consumer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedTable {
+    /// Table name
+    pub name: String,
+    /// Query selecting the rows and columns the consumer may see
+    pub select: String,
+}
+
+/// Tables to share and the ones left out, with why.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SharePlan {
+    /// Consumer name, the schema of the views
+    pub consumer: String,
+    /// Shared tables, by name
+    pub shared: Vec<SharedTable>,
+    /// Tables left out and the reason
+    pub skipped: Vec<(String, String)>,
+}
+
+impl SharePlan {
+    /// The statements creating the consumer's views in the source database.
+    pub fn view_sql(&self) -> String {
+        let schema = quote_identifier(&self.consumer);
+        let mut sql = format!("CREATE SCHEMA IF NOT EXISTS {};\n", schema);
+        for table in &self.shared {
+            sql.push_str(&format!(
+                "CREATE OR REPLACE VIEW {}.{} AS {};\n",
+                schema,
+                quote_identifier(&table.name),
+                table.select
+            ));
+        }
+        sql
+    }
+}
+
+/// Tables (not views) of the `main` schema of `conn`'s database with
+/// their columns, by name.
+pub fn source_tables(conn: &Connection) -> Result<Vec<(String, Vec<String>)>> {
+    let mut stmt = conn.prepare(
+        "SELECT table_name, column_name FROM duckdb_columns() \
+         WHERE database_name = current_database() AND schema_name = 'main' \
+         AND table_oid IN (SELECT table_oid FROM duckdb_tables() WHERE NOT internal) \
+         ORDER BY table_name, column_index",
+    )?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))?
+        .collect::<duckdb::Result<Vec<_>>>()?;
+    let mut tables: Vec<(String, Vec<String>)> = Vec::new();
+    for (table, column) in rows {
+        match tables.last_mut() {
+            Some((name, columns)) if *name == table => columns.push(column),
+            _ => tables.push((table, vec![column])),
+        }
+    }
+    Ok(tables)
+}
+
+/// Applies `policy` to `tables` (names with their columns).
+pub fn plan(policy: &SharePolicy, tables: &[(String, Vec<String>)]) -> Result<SharePlan> {
+    if let Some(listed) = &policy.tables {
+        for table in listed {
+            if !tables.iter().any(|(name, _)| *name == table.name) {
+                bail!("Table {} of the policy doesn't exist", table.name);
+            }
+        }
+    }
+
+    let mut plan = SharePlan {
+        consumer: policy.consumer.clone(),
+        ..Default::default()
+    };
+    for (name, columns) in tables {
+        let listed = policy.table(name);
+        if policy.tables.is_some() && listed.is_none() {
+            plan.skipped
+                .push((name.clone(), "not listed in tables".to_string()));
+            continue;
+        }
+
+        let mut predicates: Vec<String> = policy
+            .filters
+            .iter()
+            .filter(|(column, _)| columns.contains(column))
+            .map(|(column, values)| match values.as_slice() {
+                [value] => format!("{} = {}", quote_identifier(column), value),
+                values => format!("{} IN ({})", quote_identifier(column), values.join(", ")),
+            })
+            .collect();
+        if predicates.is_empty() && listed.is_none() {
+            let columns: Vec<&str> = policy.filters.iter().map(|(c, _)| c.as_str()).collect();
+            plan.skipped.push((
+                name.clone(),
+                format!("no filter column ({})", columns.join(", ")),
+            ));
+            continue;
+        }
+        if let Some(predicate) = listed.and_then(|table| table.predicate.as_ref()) {
+            predicates.push(format!("({})", predicate));
+        }
+
+        let table_drops = listed
+            .map(|table| table.drop_columns.as_slice())
+            .unwrap_or(&[]);
+        if let Some(missing) = table_drops.iter().find(|column| !columns.contains(column)) {
+            bail!("Table {} has no column {} to drop", name, missing);
+        }
+        let kept: Vec<String> = columns
+            .iter()
+            .filter(|column| !policy.drop_columns.contains(column) && !table_drops.contains(column))
+            .map(|column| quote_identifier(column))
+            .collect();
+        if kept.is_empty() {
+            plan.skipped
+                .push((name.clone(), "every column dropped".to_string()));
+            continue;
+        }
+
+        let mut select = format!(
+            "SELECT {} FROM main.{}",
+            kept.join(", "),
+            quote_identifier(name)
+        );
+        if !predicates.is_empty() {
+            select.push_str(&format!(" WHERE {}", predicates.join(" AND ")));
+        }
+        plan.shared.push(SharedTable {
+            name: name.clone(),
+            select,
+        });
+    }
+    Ok(plan)
+}
+
+/// Creates the plan's views in the consumer's schema of `conn`'s database.
+pub fn create_views(conn: &Connection, plan: &SharePlan) -> Result<()> {
+    conn.execute_batch(&plan.view_sql())
+        .with_context(|| format!("Failed to create the views of {}", plan.consumer))?;
+    info!(
+        "👁️  Created {} views in schema {}",
+        plan.shared.len(),
+        plan.consumer
+    );
+    Ok(())
+}
+
+/// Writes the shared rows and columns as tables of a new database file,
+/// which must not exist yet. Returns the rows written.
+pub fn export(conn: &Connection, plan: &SharePlan, output: &str) -> Result<usize> {
+    if Path::new(output).exists() {
+        bail!(
+            "{} already exists; remove it or choose another --output",
+            output
+        );
+    }
+    conn.execute_batch(&format!("ATTACH {} AS share_export;", path_literal(output)))
+        .with_context(|| format!("Failed to create {}", output))?;
+    let copied = plan.shared.iter().try_fold(0, |rows, table| {
+        conn.execute_batch(&format!(
+            "CREATE TABLE share_export.{} AS {};",
+            quote_identifier(&table.name),
+            table.select
+        ))
+        .with_context(|| format!("Failed to export table {}", table.name))?;
+        let count: i64 = conn.query_row(
+            &format!(
+                "SELECT count(*) FROM share_export.{}",
+                quote_identifier(&table.name)
+            ),
+            [],
+            |row| row.get(0),
+        )?;
+        Ok::<_, anyhow::Error>(rows + count as usize)
+    });
+    conn.execute_batch("DETACH share_export;")?;
+    let rows = copied?;
+    info!(
+        "📦 Exported {} tables ({} rows) for {} to {}",
+        plan.shared.len(),
+        rows,
+        plan.consumer,
+        output
+    );
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = "
+consumer: acme
+where:
+  tenant_id: 42
+  region: [eu, uk]
+drop_columns: [cost_price]
+";
+
+    fn tables() -> Vec<(String, Vec<String>)> {
+        [
+            ("currencies", vec!["code", "name"]),
+            (
+                "orders",
+                vec!["id", "tenant_id", "cost_price", "margin", "status"],
+            ),
+            ("users", vec!["id", "tenant_id", "region", "email"]),
+        ]
+        .iter()
+        .map(|(name, columns)| {
+            (
+                name.to_string(),
+                columns.iter().map(|c| c.to_string()).collect(),
+            )
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        let policy = SharePolicy::parse(POLICY).unwrap();
+        assert_eq!(policy.consumer, "acme");
+        assert_eq!(
+            policy.filters,
+            vec![
+                ("tenant_id".to_string(), vec!["42".to_string()]),
+                (
+                    "region".to_string(),
+                    vec!["'eu'".to_string(), "'uk'".to_string()]
+                ),
+            ]
+        );
+        assert!(policy.tables.is_none());
+
+        assert!(SharePolicy::parse("consumer: acme").is_err());
+        assert!(SharePolicy::parse("consumer: a-b\nwhere: {tenant_id: 1}").is_err());
+    }
+
+    #[test]
+    fn test_plan_filters_every_tenant_table() {
+        let plan = plan(&SharePolicy::parse(POLICY).unwrap(), &tables()).unwrap();
+        assert_eq!(
+            plan.shared,
+            vec![
+                SharedTable {
+                    name: "orders".to_string(),
+                    select:
+                        "SELECT \"id\", \"tenant_id\", \"margin\", \"status\" FROM main.\"orders\" \
+                             WHERE \"tenant_id\" = 42"
+                            .to_string(),
+                },
+                SharedTable {
+                    name: "users".to_string(),
+                    select:
+                        "SELECT \"id\", \"tenant_id\", \"region\", \"email\" FROM main.\"users\" \
+                             WHERE \"tenant_id\" = 42 AND \"region\" IN ('eu', 'uk')"
+                            .to_string(),
+                },
+            ]
+        );
+        // Without a tenant column, a table would leak every row
+        assert_eq!(plan.skipped.len(), 1);
+        assert_eq!(plan.skipped[0].0, "currencies");
+        assert!(plan
+            .view_sql()
+            .contains("CREATE OR REPLACE VIEW \"acme\".\"orders\" AS SELECT"));
+    }
+
+    #[test]
+    fn test_plan_with_table_allowlist() {
+        let policy = SharePolicy::parse(&format!(
+            "{}tables:\n  orders:\n    where: \"status <> 'draft'\"\n    drop_columns: [margin]\n  currencies: {{}}\n",
+            POLICY
+        ))
+        .unwrap();
+        let plan = plan(&policy, &tables()).unwrap();
+        let names: Vec<&str> = plan.shared.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["currencies", "orders"]);
+        assert_eq!(
+            plan.shared[0].select,
+            "SELECT \"code\", \"name\" FROM main.\"currencies\""
+        );
+        assert_eq!(
+            plan.shared[1].select,
+            "SELECT \"id\", \"tenant_id\", \"status\" FROM main.\"orders\" \
+             WHERE \"tenant_id\" = 42 AND (status <> 'draft')"
+        );
+        assert_eq!(
+            plan.skipped,
+            vec![("users".to_string(), "not listed in tables".to_string())]
+        );
+
+        let typo = SharePolicy::parse(&format!(
+            "{}tables:\n  orders:\n    drop_columns: [marginn]\n",
+            POLICY
+        ))
+        .unwrap();
+        assert!(super::plan(&typo, &tables()).is_err());
+    }
+
+    #[test]
+    fn test_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE orders (id INTEGER, tenant_id INTEGER, cost_price DOUBLE);
+             INSERT INTO orders VALUES (1, 42, 9.5), (2, 7, 3.0), (3, 42, 1.0);",
+        )
+        .unwrap();
+        let policy = SharePolicy::parse(POLICY).unwrap();
+        let plan = plan(&policy, &source_tables(&conn).unwrap()).unwrap();
+
+        let output = dir.path().join("acme.duckdb");
+        let output = output.to_str().unwrap();
+        assert_eq!(export(&conn, &plan, output).unwrap(), 2);
+        assert!(export(&conn, &plan, output).is_err());
+
+        let shared = Connection::open(output).unwrap();
+        let columns = source_tables(&shared).unwrap();
+        assert_eq!(
+            columns,
+            vec![(
+                "orders".to_string(),
+                vec!["id".to_string(), "tenant_id".to_string()]
+            )]
+        );
+
+        create_views(&conn, &plan).unwrap();
+        let visible: i64 = conn
+            .query_row("SELECT count(*) FROM acme.orders", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(visible, 2);
+    }
+}
@@ -0,0 +1,391 @@
+//! # Similar Items for Frozen DuckDB CLI
+//!
+//! This module computes, for every document of an embedding index, its
+//! top-N most similar documents, the usual building block for "more like
+//! this" recommendations.
+//!
+//! ## Search Modes
+//!
+//! | Mode | Notes |
+//! |------|-------|
+//! | Exact (default) | Batched brute-force cosine similarity in parallel with rayon. O(n²·d), practical up to a few hundred thousand items |
+//! | HNSW (`hnsw: true`) | Approximate neighbors from an HNSW index built with DuckDB's `vss` extension. Much faster for large catalogs; may miss a few true neighbors |
+//!
+//! ## Item Table
+//!
+//! An optional item table (CSV, Parquet, or JSON) restricts both the items
+//! and their candidate neighbors to the ids in one of its columns, for
+//! example to recommend only products that are still in stock.
+//!
+//! ## Output
+//!
+//! Results are stored in the index and can also be exported to a file:
+//!
+//! ```text
+//! item_neighbors (item_id VARCHAR, neighbor_id VARCHAR, rank INTEGER, similarity FLOAT)
+//! ```
+
+use super::dedupe::{copy_format, quote_identifier, read_function};
+use super::embedding_index::EmbeddingIndex;
+use super::sql_path::path_literal;
+use anyhow::{anyhow, Context, Result};
+use duckdb::{params, Connection};
+use rayon::prelude::*;
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+/// Options for [`similar_items`].
+#[derive(Debug, Clone)]
+pub struct SimilarItemsOptions {
+    /// Neighbors kept per item
+    pub top_n: usize,
+    /// Items compared per batch
+    pub batch_size: usize,
+    /// Drop neighbors less similar than this
+    pub min_similarity: Option<f32>,
+    /// Use an approximate HNSW index instead of exact search
+    pub hnsw: bool,
+}
+
+impl Default for SimilarItemsOptions {
+    fn default() -> Self {
+        Self {
+            top_n: 10,
+            batch_size: 1024,
+            min_similarity: None,
+            hnsw: false,
+        }
+    }
+}
+
+/// One item→neighbor pair.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Neighbor {
+    /// Item the neighbor was found for
+    pub item_id: String,
+    /// Similar item
+    pub neighbor_id: String,
+    /// 1 for the most similar neighbor
+    pub rank: usize,
+    /// Cosine similarity
+    pub similarity: f32,
+}
+
+/// Reads the distinct ids in `id_column` of an item table file.
+pub fn load_item_ids(conn: &Connection, items: &str, id_column: &str) -> Result<HashSet<String>> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT DISTINCT CAST({} AS VARCHAR) FROM {} WHERE {} IS NOT NULL",
+            quote_identifier(id_column),
+            read_function(items)?,
+            quote_identifier(id_column)
+        ))
+        .with_context(|| format!("Failed to read item ids from {}", items))?;
+    let ids = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<duckdb::Result<HashSet<_>>>()?;
+    Ok(ids)
+}
+
+/// Finds the top-N neighbors of every item in `index`, optionally
+/// restricted to `items`, and stores them in the index.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::embedding_index::EmbeddingIndex;
+/// use frozen_duckdb::cli::similar_items::{similar_items, SimilarItemsOptions};
+///
+/// let index = EmbeddingIndex::open("products.duckdb")?;
+/// let options = SimilarItemsOptions { top_n: 5, ..Default::default() };
+/// let neighbors = similar_items(&index, None, &options)?;
+/// println!("{} recommendations", neighbors.len());
+/// ```
+pub fn similar_items(
+    index: &EmbeddingIndex,
+    items: Option<&HashSet<String>>,
+    options: &SimilarItemsOptions,
+) -> Result<Vec<Neighbor>> {
+    if options.top_n == 0 || options.batch_size == 0 {
+        return Err(anyhow!("top_n and batch_size must be greater than zero"));
+    }
+    let (mut ids, mut vectors) = index.vectors()?;
+    if let Some(items) = items {
+        let (kept_ids, kept_vectors): (Vec<_>, Vec<_>) = ids
+            .into_iter()
+            .zip(vectors)
+            .filter(|(id, _)| items.contains(id))
+            .unzip();
+        if kept_ids.len() < items.len() {
+            warn!(
+                "⚠️  {} items have no embedding in the index",
+                items.len() - kept_ids.len()
+            );
+        }
+        (ids, vectors) = (kept_ids, kept_vectors);
+    }
+    if ids.is_empty() {
+        return Err(anyhow!("No items to compare"));
+    }
+    let dimension = vectors[0].len();
+    if vectors.iter().any(|v| v.len() != dimension) {
+        return Err(anyhow!("Embeddings have different dimensions"));
+    }
+
+    info!(
+        "🔎 Finding {} neighbors for {} items ({})",
+        options.top_n,
+        ids.len(),
+        if options.hnsw { "HNSW" } else { "exact" }
+    );
+    let mut neighbors = if options.hnsw {
+        hnsw_neighbors(&ids, &vectors, options)?
+    } else {
+        exact_neighbors(&ids, &vectors, options)
+    };
+    if let Some(min) = options.min_similarity {
+        neighbors.retain(|n| n.similarity >= min);
+    }
+
+    store_neighbors(index.connection(), &neighbors)?;
+    Ok(neighbors)
+}
+
+/// Brute-force cosine neighbors, one batch of items at a time.
+fn exact_neighbors(
+    ids: &[String],
+    vectors: &[Vec<f32>],
+    options: &SimilarItemsOptions,
+) -> Vec<Neighbor> {
+    let unit: Vec<Vec<f32>> = vectors.iter().map(|v| normalized(v)).collect();
+    let mut neighbors = Vec::with_capacity(ids.len() * options.top_n);
+
+    for (batch, start) in (0..ids.len()).step_by(options.batch_size).enumerate() {
+        let end = (start + options.batch_size).min(ids.len());
+        let found: Vec<Vec<(usize, f32)>> = (start..end)
+            .into_par_iter()
+            .map(|item| {
+                let mut scores: Vec<(usize, f32)> = unit
+                    .iter()
+                    .enumerate()
+                    .filter(|&(candidate, _)| candidate != item)
+                    .map(|(candidate, v)| (candidate, dot(&unit[item], v)))
+                    .collect();
+                let n = options.top_n.min(scores.len());
+                if n > 0 && n < scores.len() {
+                    scores.select_nth_unstable_by(n - 1, |a, b| b.1.total_cmp(&a.1));
+                }
+                scores.truncate(n);
+                scores.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+                scores
+            })
+            .collect();
+
+        for (item, scores) in (start..end).zip(found) {
+            neighbors.extend(scores.into_iter().enumerate().map(
+                |(rank, (candidate, similarity))| Neighbor {
+                    item_id: ids[item].clone(),
+                    neighbor_id: ids[candidate].clone(),
+                    rank: rank + 1,
+                    similarity,
+                },
+            ));
+        }
+        info!(
+            "📦 Batch {}: items {}-{} of {}",
+            batch,
+            start + 1,
+            end,
+            ids.len()
+        );
+    }
+    neighbors
+}
+
+/// Approximate neighbors from an HNSW index in a scratch in-memory database.
+fn hnsw_neighbors(
+    ids: &[String],
+    vectors: &[Vec<f32>],
+    options: &SimilarItemsOptions,
+) -> Result<Vec<Neighbor>> {
+    let dimension = vectors[0].len();
+    let conn = Connection::open_in_memory()?;
+    conn.execute_batch("INSTALL vss; LOAD vss;")
+        .context("The vss extension is required for --hnsw")?;
+    conn.execute_batch(&format!(
+        "CREATE TABLE items (item_id VARCHAR, embedding FLOAT[{}])",
+        dimension
+    ))?;
+
+    let tx = conn.unchecked_transaction()?;
+    let mut insert = tx.prepare(&format!(
+        "INSERT INTO items VALUES (?, CAST(? AS FLOAT[{}]))",
+        dimension
+    ))?;
+    for (id, vector) in ids.iter().zip(vectors) {
+        insert.execute(params![id, format_vector(vector)])?;
+    }
+    drop(insert);
+    tx.commit()?;
+    conn.execute_batch(
+        "CREATE INDEX items_hnsw ON items USING HNSW (embedding) WITH (metric = 'cosine')",
+    )?;
+
+    // One extra result, since an item finds itself
+    let mut search = conn.prepare(&format!(
+        "SELECT item_id, array_cosine_similarity(embedding, CAST($1 AS FLOAT[{0}])) AS similarity
+         FROM items
+         ORDER BY array_cosine_distance(embedding, CAST($1 AS FLOAT[{0}]))
+         LIMIT {1}",
+        dimension,
+        options.top_n + 1
+    ))?;
+    let mut neighbors = Vec::with_capacity(ids.len() * options.top_n);
+    for (position, (id, vector)) in ids.iter().zip(vectors).enumerate() {
+        let found = search
+            .query_map([format_vector(vector)], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, f32>(1)?))
+            })?
+            .collect::<duckdb::Result<Vec<_>>>()?;
+        neighbors.extend(
+            found
+                .into_iter()
+                .filter(|(neighbor, _)| neighbor != id)
+                .take(options.top_n)
+                .enumerate()
+                .map(|(rank, (neighbor_id, similarity))| Neighbor {
+                    item_id: id.clone(),
+                    neighbor_id,
+                    rank: rank + 1,
+                    similarity,
+                }),
+        );
+        if (position + 1) % options.batch_size == 0 {
+            info!("📦 {} of {} items searched", position + 1, ids.len());
+        }
+    }
+    Ok(neighbors)
+}
+
+/// Replaces the `item_neighbors` table of the index with `neighbors`.
+fn store_neighbors(conn: &Connection, neighbors: &[Neighbor]) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute_batch(
+        "CREATE OR REPLACE TABLE item_neighbors (
+             item_id VARCHAR,
+             neighbor_id VARCHAR,
+             rank INTEGER,
+             similarity FLOAT
+         )",
+    )?;
+    let mut insert = tx.prepare("INSERT INTO item_neighbors VALUES (?, ?, ?, ?)")?;
+    for neighbor in neighbors {
+        insert.execute(params![
+            neighbor.item_id,
+            neighbor.neighbor_id,
+            neighbor.rank as i32,
+            neighbor.similarity
+        ])?;
+    }
+    drop(insert);
+    tx.commit().context("Failed to store neighbors")?;
+    Ok(())
+}
+
+/// Writes the stored `item_neighbors` table to `output`, in the format of
+/// its extension.
+pub fn export_neighbors(index: &EmbeddingIndex, output: &str) -> Result<usize> {
+    let rows = index.connection().execute(
+        &format!(
+            "COPY (SELECT * FROM item_neighbors ORDER BY item_id, rank) TO {} ({})",
+            path_literal(output),
+            copy_format(output)?
+        ),
+        [],
+    )?;
+    Ok(rows)
+}
+
+fn normalized(v: &[f32]) -> Vec<f32> {
+    let norm = dot(v, v).sqrt().max(f32::EPSILON);
+    v.iter().map(|x| x / norm).collect()
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn format_vector(vector: &[f32]) -> String {
+    let values: Vec<String> = vector.iter().map(|v| v.to_string()).collect();
+    format!("[{}]", values.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::embedding_index::Document;
+
+    fn build_index() -> EmbeddingIndex {
+        let index = EmbeddingIndex::open_in_memory().unwrap();
+        let documents: Vec<Document> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|id| Document {
+                id: id.to_string(),
+                content: format!("item {}", id),
+                ..Default::default()
+            })
+            .collect();
+        let vectors = vec![
+            vec![1.0, 0.0],
+            vec![0.9, 0.1],
+            vec![0.0, 1.0],
+            vec![0.1, 0.9],
+        ];
+        index.store_batch(0, &documents, &vectors).unwrap();
+        index
+    }
+
+    #[test]
+    fn test_exact_neighbors() -> Result<()> {
+        let index = build_index();
+        let options = SimilarItemsOptions {
+            top_n: 2,
+            batch_size: 3,
+            ..Default::default()
+        };
+        let neighbors = similar_items(&index, None, &options)?;
+        assert_eq!(neighbors.len(), 8);
+        let first: Vec<(&str, &str)> = neighbors
+            .iter()
+            .filter(|n| n.rank == 1)
+            .map(|n| (n.item_id.as_str(), n.neighbor_id.as_str()))
+            .collect();
+        assert_eq!(first, [("a", "b"), ("b", "a"), ("c", "d"), ("d", "c")]);
+        assert!(neighbors.iter().all(|n| n.item_id != n.neighbor_id));
+
+        let stored: i64 =
+            index
+                .connection()
+                .query_row("SELECT COUNT(*) FROM item_neighbors", [], |row| row.get(0))?;
+        assert_eq!(stored, 8);
+        Ok(())
+    }
+
+    #[test]
+    fn test_item_restriction_and_threshold() -> Result<()> {
+        let index = build_index();
+        let items: HashSet<String> = ["a", "c", "d", "z"].iter().map(|s| s.to_string()).collect();
+        let options = SimilarItemsOptions {
+            min_similarity: Some(0.5),
+            ..Default::default()
+        };
+        let neighbors = similar_items(&index, Some(&items), &options)?;
+        // "b" is excluded, so "a" has no sufficiently similar neighbor
+        let pairs: Vec<(&str, &str)> = neighbors
+            .iter()
+            .map(|n| (n.item_id.as_str(), n.neighbor_id.as_str()))
+            .collect();
+        assert_eq!(pairs, [("c", "d"), ("d", "c")]);
+        Ok(())
+    }
+}
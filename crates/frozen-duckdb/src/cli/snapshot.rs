@@ -0,0 +1,518 @@
+//! # Database Snapshots
+//!
+//! Analytical database files are usually rebuilt or appended to in place,
+//! so a bad load is hard to undo. Snapshots are point-in-time copies kept
+//! next to the database, in `<database>.snapshots/`, giving cheap time
+//! travel: take one before a risky step, and restore it if the step goes
+//! wrong.
+//!
+//! ## Methods
+//!
+//! | Method | Stored as | Notes |
+//! |--------|-----------|-------|
+//! | `copy` | `<id>.duckdb`, a copy of the file | Fast; restorable with the same DuckDB version |
+//! | `export` | `<id>/`, an `EXPORT DATABASE` directory of Parquet files | Portable across DuckDB versions, usually smaller |
+//!
+//! Both are consistent: the database is opened, which locks out other
+//! writers, and checkpointed so the file holds every committed change
+//! before it is copied or exported.
+//!
+//! Snapshot ids are their UTC creation time, e.g. `20240105T093000Z`,
+//! optionally followed by a label: `20240105T093000Z_before_backfill`.
+//! Restoring first snapshots the current state with the label
+//! `pre_restore`, so a restore can be undone too.
+//!
+//! ## Retention
+//!
+//! [`Retention`] keeps the newest `keep_last` snapshots and drops those
+//! older than `max_age`; a snapshot must satisfy both to be kept.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use frozen_duckdb::cli::snapshot::{Retention, SnapshotMethod, SnapshotStore};
+//! use std::path::Path;
+//!
+//! let store = SnapshotStore::for_database(Path::new("warehouse.duckdb"));
+//! let snapshot = store.create(Some("before_backfill"), SnapshotMethod::Copy)?;
+//! // ... a load goes wrong ...
+//! store.restore(&snapshot.id)?;
+//! store.prune(&Retention { keep_last: Some(10), max_age: None })?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use super::catalog::validate_name;
+use super::sql_path::path_literal;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{NaiveDateTime, Utc};
+use duckdb::Connection;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::info;
+
+const ID_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// How a snapshot stores the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotMethod {
+    /// A copy of the checkpointed database file
+    Copy,
+    /// An `EXPORT DATABASE` directory of Parquet files
+    Export,
+}
+
+impl SnapshotMethod {
+    /// Parses `copy` or `export`.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "copy" => Ok(Self::Copy),
+            "export" => Ok(Self::Export),
+            other => Err(anyhow!(
+                "Unknown snapshot method: {} (use copy or export)",
+                other
+            )),
+        }
+    }
+
+    /// Returns the method name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Copy => "copy",
+            Self::Export => "export",
+        }
+    }
+}
+
+/// A stored snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Creation time and label, e.g. `20240105T093000Z_before_backfill`
+    pub id: String,
+    /// Label given when the snapshot was created
+    pub label: Option<String>,
+    /// Creation time, UTC
+    pub created_at: NaiveDateTime,
+    /// How the snapshot is stored
+    pub method: SnapshotMethod,
+    /// File or directory holding the snapshot
+    pub path: PathBuf,
+    /// Size on disk in bytes
+    pub size_bytes: u64,
+}
+
+impl Snapshot {
+    /// Returns the snapshot as JSON.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "label": self.label,
+            "created_at": self.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            "method": self.method.as_str(),
+            "path": self.path.display().to_string(),
+            "size_bytes": self.size_bytes,
+        })
+    }
+
+    /// Sort key: creation time, then the sequence number of snapshots
+    /// taken within the same second (`-2`, `-3`, ...).
+    fn order(&self) -> (NaiveDateTime, u32) {
+        let sequence = self
+            .id
+            .split('_')
+            .next()
+            .and_then(|stamp| stamp.split_once('-'))
+            .and_then(|(_, n)| n.parse().ok())
+            .unwrap_or(1);
+        (self.created_at, sequence)
+    }
+
+    /// Parses a snapshot entry name, `<id>.duckdb` or `<id>`.
+    fn from_entry(path: PathBuf) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        let (id, method) = match name.strip_suffix(".duckdb") {
+            Some(id) => (id, SnapshotMethod::Copy),
+            None if path.is_dir() => (name, SnapshotMethod::Export),
+            None => return None,
+        };
+        let (time, label) = match id.split_once('_') {
+            Some((time, label)) => (time, Some(label.to_string())),
+            None => (id, None),
+        };
+        // A "-2" suffix separates snapshots taken within the same second
+        let time = time.split('-').next()?;
+        let created_at = NaiveDateTime::parse_from_str(time, ID_FORMAT).ok()?;
+        Some(Self {
+            id: id.to_string(),
+            label,
+            created_at,
+            method,
+            size_bytes: disk_size(&path),
+            path,
+        })
+    }
+}
+
+/// Which snapshots to keep. Unset limits keep everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Retention {
+    /// Keep at most this many snapshots, the newest
+    pub keep_last: Option<usize>,
+    /// Drop snapshots older than this
+    pub max_age: Option<Duration>,
+}
+
+impl Retention {
+    /// Whether any limit is set.
+    pub fn is_set(&self) -> bool {
+        self.keep_last.is_some() || self.max_age.is_some()
+    }
+
+    /// Returns the snapshots to drop from `snapshots`, oldest first, as of
+    /// `now`.
+    pub fn expired<'a>(&self, snapshots: &'a [Snapshot], now: NaiveDateTime) -> Vec<&'a Snapshot> {
+        let mut newest_first: Vec<&Snapshot> = snapshots.iter().collect();
+        newest_first.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.order()));
+        let mut expired: Vec<&Snapshot> = newest_first
+            .into_iter()
+            .enumerate()
+            .filter(|(index, snapshot)| {
+                let too_many = self.keep_last.is_some_and(|keep| *index >= keep);
+                let too_old = self.max_age.is_some_and(|max_age| {
+                    (now - snapshot.created_at)
+                        .to_std()
+                        .is_ok_and(|age| age > max_age)
+                });
+                too_many || too_old
+            })
+            .map(|(_, snapshot)| snapshot)
+            .collect();
+        expired.reverse();
+        expired
+    }
+}
+
+/// The snapshots of one database file.
+#[derive(Debug, Clone)]
+pub struct SnapshotStore {
+    database: PathBuf,
+    dir: PathBuf,
+}
+
+impl SnapshotStore {
+    /// Returns the store of `database`, `<database>.snapshots/` next to it.
+    pub fn for_database(database: &Path) -> Self {
+        let mut dir = database.as_os_str().to_owned();
+        dir.push(".snapshots");
+        Self {
+            database: database.to_path_buf(),
+            dir: PathBuf::from(dir),
+        }
+    }
+
+    /// Directory holding the snapshots.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Takes a consistent snapshot of the database.
+    pub fn create(&self, label: Option<&str>, method: SnapshotMethod) -> Result<Snapshot> {
+        if let Some(label) = label {
+            validate_name(label).context("Invalid snapshot label")?;
+        }
+        if !self.database.exists() {
+            bail!("Database not found: {}", self.database.display());
+        }
+        fs::create_dir_all(&self.dir)?;
+
+        // Holding the connection keeps other writers out while copying
+        let conn = Connection::open(&self.database)
+            .with_context(|| format!("Failed to open {}", self.database.display()))?;
+        conn.execute_batch("CHECKPOINT;")
+            .with_context(|| format!("Failed to checkpoint {}", self.database.display()))?;
+
+        let id = self.next_id(label)?;
+        let path = match method {
+            SnapshotMethod::Copy => {
+                let path = self.dir.join(format!("{}.duckdb", id));
+                let staging = self.dir.join(format!(".{}.partial", id));
+                fs::copy(&self.database, &staging)
+                    .with_context(|| format!("Failed to copy {}", self.database.display()))?;
+                fs::rename(&staging, &path)?;
+                path
+            }
+            SnapshotMethod::Export => {
+                let path = self.dir.join(&id);
+                conn.execute_batch(&format!(
+                    "EXPORT DATABASE {} (FORMAT parquet);",
+                    path_literal(&path)
+                ))
+                .with_context(|| format!("Failed to export {}", self.database.display()))?;
+                path
+            }
+        };
+        let snapshot = Snapshot::from_entry(path)
+            .ok_or_else(|| anyhow!("Snapshot {} was written but can't be read back", id))?;
+        info!(
+            "📸 Snapshot {} of {} ({} bytes)",
+            snapshot.id,
+            self.database.display(),
+            snapshot.size_bytes
+        );
+        Ok(snapshot)
+    }
+
+    /// Returns the snapshots, oldest first.
+    pub fn list(&self) -> Result<Vec<Snapshot>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut snapshots: Vec<Snapshot> = fs::read_dir(&self.dir)?
+            .flatten()
+            .filter_map(|entry| Snapshot::from_entry(entry.path()))
+            .collect();
+        snapshots.sort_by_key(Snapshot::order);
+        Ok(snapshots)
+    }
+
+    /// Finds a snapshot by id, label, or unique id prefix.
+    pub fn get(&self, reference: &str) -> Result<Snapshot> {
+        let snapshots = self.list()?;
+        if let Some(snapshot) = snapshots.iter().find(|s| s.id == reference) {
+            return Ok(snapshot.clone());
+        }
+        // The newest snapshot with a label wins, labels being reusable
+        if let Some(snapshot) = snapshots
+            .iter()
+            .rev()
+            .find(|s| s.label.as_deref() == Some(reference))
+        {
+            return Ok(snapshot.clone());
+        }
+        let matches: Vec<&Snapshot> = snapshots
+            .iter()
+            .filter(|s| s.id.starts_with(reference))
+            .collect();
+        match matches.as_slice() {
+            [snapshot] => Ok((*snapshot).clone()),
+            [] => bail!(
+                "No snapshot {} of {} (see snapshot list)",
+                reference,
+                self.database.display()
+            ),
+            _ => bail!(
+                "{} snapshots start with {}; give more of the id",
+                matches.len(),
+                reference
+            ),
+        }
+    }
+
+    /// Replaces the database with a snapshot, after snapshotting its
+    /// current state as `pre_restore`. Returns the restored snapshot.
+    ///
+    /// Fails if another process has the database open.
+    pub fn restore(&self, reference: &str) -> Result<Snapshot> {
+        let snapshot = self.get(reference)?;
+        if self.database.exists() {
+            self.create(Some("pre_restore"), SnapshotMethod::Copy)
+                .context("Failed to snapshot the current state before restoring")?;
+        }
+
+        let staging = self.dir.join(".restore.partial");
+        let _ = fs::remove_file(&staging);
+        match snapshot.method {
+            SnapshotMethod::Copy => {
+                fs::copy(&snapshot.path, &staging)
+                    .with_context(|| format!("Failed to copy snapshot {}", snapshot.id))?;
+            }
+            SnapshotMethod::Export => {
+                let conn = Connection::open(&staging)?;
+                conn.execute_batch(&format!(
+                    "IMPORT DATABASE {};",
+                    path_literal(&snapshot.path)
+                ))
+                .with_context(|| format!("Failed to import snapshot {}", snapshot.id))?;
+                conn.execute_batch("CHECKPOINT;")?;
+            }
+        }
+        // A leftover write-ahead log would be replayed onto the restored file
+        let mut wal = self.database.as_os_str().to_owned();
+        wal.push(".wal");
+        let _ = fs::remove_file(PathBuf::from(wal));
+        fs::rename(&staging, &self.database)
+            .with_context(|| format!("Failed to replace {}", self.database.display()))?;
+        info!(
+            "⏪ Restored {} to snapshot {}",
+            self.database.display(),
+            snapshot.id
+        );
+        Ok(snapshot)
+    }
+
+    /// Deletes the snapshots `retention` doesn't keep and returns them.
+    pub fn prune(&self, retention: &Retention) -> Result<Vec<Snapshot>> {
+        let snapshots = self.list()?;
+        let expired: Vec<Snapshot> = retention
+            .expired(&snapshots, Utc::now().naive_utc())
+            .into_iter()
+            .cloned()
+            .collect();
+        for snapshot in &expired {
+            match snapshot.method {
+                SnapshotMethod::Copy => fs::remove_file(&snapshot.path)?,
+                SnapshotMethod::Export => fs::remove_dir_all(&snapshot.path)?,
+            }
+            info!("🗑️  Removed snapshot {}", snapshot.id);
+        }
+        Ok(expired)
+    }
+
+    /// A new snapshot id, numbered after any taken within the same second.
+    fn next_id(&self, label: Option<&str>) -> Result<String> {
+        let now = Utc::now().naive_utc();
+        let stamp = now.format(ID_FORMAT).to_string();
+        let sequence = self
+            .list()?
+            .iter()
+            .map(Snapshot::order)
+            .filter(|(created_at, _)| created_at.format(ID_FORMAT).to_string() == stamp)
+            .map(|(_, sequence)| sequence + 1)
+            .max();
+        let stamp = match sequence {
+            Some(sequence) => format!("{}-{}", stamp, sequence),
+            None => stamp,
+        };
+        Ok(match label {
+            Some(label) => format!("{}_{}", stamp, label),
+            None => stamp,
+        })
+    }
+}
+
+/// Size of a file, or of the files directly in a directory.
+fn disk_size(path: &Path) -> u64 {
+    match fs::read_dir(path) {
+        Ok(entries) => entries
+            .flatten()
+            .filter_map(|entry| entry.metadata().ok())
+            .map(|metadata| metadata.len())
+            .sum(),
+        Err(_) => fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(id: &str) -> Snapshot {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(format!("{}.duckdb", id));
+        fs::write(&path, "db").unwrap();
+        Snapshot::from_entry(path).unwrap()
+    }
+
+    fn at(time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(time, ID_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn test_snapshot_ids() {
+        let labeled = snapshot("20240105T093000Z_before_backfill");
+        assert_eq!(labeled.label.as_deref(), Some("before_backfill"));
+        assert_eq!(labeled.created_at, at("20240105T093000Z"));
+        assert_eq!(labeled.method, SnapshotMethod::Copy);
+        assert_eq!(labeled.size_bytes, 2);
+
+        let second = snapshot("20240105T093000Z-2");
+        assert_eq!(second.created_at, at("20240105T093000Z"));
+        assert!(second.label.is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        assert!(Snapshot::from_entry(dir.path().join("notes.txt")).is_none());
+    }
+
+    #[test]
+    fn test_retention() {
+        let snapshots: Vec<Snapshot> = ["20240101T000000Z", "20240102T000000Z", "20240103T000000Z"]
+            .iter()
+            .map(|id| snapshot(id))
+            .collect();
+        let now = at("20240103T120000Z");
+        let ids = |expired: Vec<&Snapshot>| -> Vec<String> {
+            expired.iter().map(|s| s.id.clone()).collect()
+        };
+
+        let keep_two = Retention {
+            keep_last: Some(2),
+            max_age: None,
+        };
+        assert_eq!(ids(keep_two.expired(&snapshots, now)), ["20240101T000000Z"]);
+
+        let one_day = Retention {
+            keep_last: None,
+            max_age: Some(Duration::from_secs(86400)),
+        };
+        assert_eq!(
+            ids(one_day.expired(&snapshots, now)),
+            ["20240101T000000Z", "20240102T000000Z"]
+        );
+        assert!(Retention::default().expired(&snapshots, now).is_empty());
+        assert!(!Retention::default().is_set());
+    }
+
+    #[test]
+    fn test_create_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = dir.path().join("warehouse.duckdb");
+        Connection::open(&database)
+            .unwrap()
+            .execute_batch("CREATE TABLE t AS SELECT 1 AS x;")
+            .unwrap();
+
+        let store = SnapshotStore::for_database(&database);
+        let copy = store.create(Some("one_row"), SnapshotMethod::Copy).unwrap();
+        let export = store.create(None, SnapshotMethod::Export).unwrap();
+        assert_eq!(export.method, SnapshotMethod::Export);
+        assert_eq!(store.list().unwrap().len(), 2);
+        assert_eq!(store.get("one_row").unwrap(), copy);
+        assert!(store.get("nope").is_err());
+
+        let count = || -> i64 {
+            Connection::open(&database)
+                .unwrap()
+                .query_row("SELECT count(*) FROM t", [], |row| row.get(0))
+                .unwrap()
+        };
+        Connection::open(&database)
+            .unwrap()
+            .execute_batch("INSERT INTO t VALUES (2), (3);")
+            .unwrap();
+        assert_eq!(count(), 3);
+
+        store.restore(&copy.id).unwrap();
+        assert_eq!(count(), 1);
+        store.restore(&export.id).unwrap();
+        assert_eq!(count(), 1);
+        // Each restore snapshots the state it replaced
+        let labels: Vec<Option<String>> =
+            store.list().unwrap().into_iter().map(|s| s.label).collect();
+        assert_eq!(
+            labels
+                .iter()
+                .filter(|l| l.as_deref() == Some("pre_restore"))
+                .count(),
+            2
+        );
+
+        let removed = store
+            .prune(&Retention {
+                keep_last: Some(1),
+                max_age: None,
+            })
+            .unwrap();
+        assert_eq!(removed.len(), 3);
+        assert_eq!(store.list().unwrap().len(), 1);
+    }
+}
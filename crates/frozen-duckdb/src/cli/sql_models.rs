@@ -0,0 +1,482 @@
+//! # SQL Model Directories
+//!
+//! A lightweight take on dbt: a `models/` directory holds one `SELECT` per
+//! `.sql` file, and `frozen-duckdb build-models` materializes each one as a
+//! table named after its file, in dependency order.
+//!
+//! Models refer to each other with `{{ ref('name') }}`, which compiles to
+//! the quoted table name and adds an edge to the dependency graph:
+//!
+//! ```sql
+//! -- models/daily_revenue.sql
+//! SELECT day, SUM(amount) AS revenue
+//! FROM {{ ref('clean_orders') }}
+//! GROUP BY day
+//! ```
+//!
+//! A model is built as a table unless its file starts with
+//! `-- materialized: view`.
+//!
+//! ## Graph Selection
+//!
+//! | Selector | Models built |
+//! |----------|--------------|
+//! | `name` | Only `name` |
+//! | `name+` | `name` and everything downstream of it |
+//! | `+name` | `name` and everything it depends on |
+//! | `+name+` | Both directions |
+//!
+//! If a model fails, models downstream of it are skipped and the rest of
+//! the graph still builds.
+
+use super::dedupe::quote_identifier;
+use anyhow::{anyhow, bail, Context, Result};
+use duckdb::Connection;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How a model is stored in the target database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Materialization {
+    /// `CREATE OR REPLACE TABLE ... AS`
+    Table,
+    /// `CREATE OR REPLACE VIEW ... AS`
+    View,
+}
+
+/// One `.sql` file of a model directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Model {
+    /// Model (and table) name, the file stem
+    pub name: String,
+    /// File the model was loaded from
+    pub path: PathBuf,
+    /// SQL as written, with `ref()` calls
+    pub sql: String,
+    /// Models referenced with `ref()`
+    pub refs: BTreeSet<String>,
+    /// How the model is stored
+    pub materialized: Materialization,
+}
+
+impl Model {
+    /// Parses a model's SQL.
+    pub fn parse(name: &str, path: PathBuf, sql: &str) -> Result<Self> {
+        let materialized = match sql
+            .lines()
+            .next()
+            .and_then(|line| line.trim().strip_prefix("--"))
+            .and_then(|comment| comment.trim().strip_prefix("materialized:"))
+        {
+            None => Materialization::Table,
+            Some(kind) => match kind.trim() {
+                "table" => Materialization::Table,
+                "view" => Materialization::View,
+                other => bail!("Unknown materialization in {}: {}", name, other),
+            },
+        };
+        let mut refs = BTreeSet::new();
+        compile(sql, |r| {
+            refs.insert(r.to_string());
+        })?;
+        Ok(Self {
+            name: name.to_string(),
+            path,
+            sql: sql.to_string(),
+            refs,
+            materialized,
+        })
+    }
+
+    /// Returns the SQL with every `{{ ref('name') }}` replaced by the
+    /// quoted table name.
+    pub fn compiled_sql(&self) -> Result<String> {
+        compile(&self.sql, |_| {})
+    }
+}
+
+/// Replaces `{{ ref('name') }}` with `"name"`, calling `on_ref` for each.
+fn compile<F: FnMut(&str)>(sql: &str, mut on_ref: F) -> Result<String> {
+    let mut compiled = String::with_capacity(sql.len());
+    let mut rest = sql;
+    while let Some(start) = rest.find("{{") {
+        compiled.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| anyhow!("Unterminated {{{{ in model SQL"))?;
+        let expression = rest[start + 2..start + end].trim();
+        let name = expression
+            .strip_prefix("ref(")
+            .and_then(|e| e.strip_suffix(')'))
+            .map(|e| e.trim().trim_matches(|c| c == '\'' || c == '"'))
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unsupported expression {{{{ {} }}}} (only ref('model') is supported)",
+                    expression
+                )
+            })?;
+        on_ref(name);
+        compiled.push_str(&quote_identifier(name));
+        rest = &rest[start + end + 2..];
+    }
+    compiled.push_str(rest);
+    Ok(compiled)
+}
+
+/// Models of a directory with their dependency graph.
+#[derive(Debug, Clone)]
+pub struct ModelGraph {
+    models: BTreeMap<String, Model>,
+}
+
+impl ModelGraph {
+    /// Loads every `.sql` file under `dir`, including subdirectories.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref();
+        let mut files = Vec::new();
+        collect_sql_files(dir, &mut files)
+            .with_context(|| format!("Failed to read model directory: {}", dir.display()))?;
+
+        let mut models = Vec::with_capacity(files.len());
+        for path in files {
+            let name = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let sql = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read model: {}", path.display()))?;
+            models.push(Model::parse(&name, path, &sql)?);
+        }
+        Self::from_models(models)
+    }
+
+    /// Builds a graph, checking names are unique, every `ref()` names a
+    /// model, and there are no cycles.
+    pub fn from_models(models: Vec<Model>) -> Result<Self> {
+        let mut by_name: BTreeMap<String, Model> = BTreeMap::new();
+        for model in models {
+            if let Some(existing) = by_name.get(&model.name) {
+                bail!(
+                    "Duplicate model name {}: {} and {}",
+                    model.name,
+                    existing.path.display(),
+                    model.path.display()
+                );
+            }
+            by_name.insert(model.name.clone(), model);
+        }
+        for model in by_name.values() {
+            if let Some(missing) = model.refs.iter().find(|r| !by_name.contains_key(*r)) {
+                bail!("Model {} refers to unknown model {}", model.name, missing);
+            }
+        }
+        let graph = Self { models: by_name };
+        graph.order()?;
+        Ok(graph)
+    }
+
+    /// Returns the model named `name`.
+    pub fn get(&self, name: &str) -> Option<&Model> {
+        self.models.get(name)
+    }
+
+    /// Returns model names in build order: every model after the models it
+    /// refers to, ties broken by name.
+    pub fn order(&self) -> Result<Vec<String>> {
+        let mut pending: BTreeMap<&str, BTreeSet<&str>> = self
+            .models
+            .values()
+            .map(|m| (m.name.as_str(), m.refs.iter().map(String::as_str).collect()))
+            .collect();
+
+        let mut order = Vec::with_capacity(pending.len());
+        while !pending.is_empty() {
+            let ready: Vec<&str> = pending
+                .iter()
+                .filter(|(_, refs)| refs.is_empty())
+                .map(|(name, _)| *name)
+                .collect();
+            if ready.is_empty() {
+                bail!(
+                    "Models refer to each other in a cycle: {}",
+                    pending.keys().copied().collect::<Vec<_>>().join(", ")
+                );
+            }
+            for name in &ready {
+                pending.remove(name);
+            }
+            for refs in pending.values_mut() {
+                refs.retain(|r| !ready.contains(r));
+            }
+            order.extend(ready.into_iter().map(String::from));
+        }
+        Ok(order)
+    }
+
+    /// Resolves `--select` selectors to model names; no selectors select
+    /// every model.
+    pub fn select(&self, selectors: &[String]) -> Result<BTreeSet<String>> {
+        if selectors.is_empty() {
+            return Ok(self.models.keys().cloned().collect());
+        }
+        let mut selected = BTreeSet::new();
+        for selector in selectors.iter().flat_map(|s| s.split([',', ' '])) {
+            if selector.is_empty() {
+                continue;
+            }
+            let upstream = selector.starts_with('+');
+            let downstream = selector.ends_with('+') && selector.len() > 1;
+            let name = selector.trim_matches('+');
+            if !self.models.contains_key(name) {
+                bail!("No model named {} (selector {})", name, selector);
+            }
+            selected.insert(name.to_string());
+            if upstream {
+                selected.extend(self.walk(name, true));
+            }
+            if downstream {
+                selected.extend(self.walk(name, false));
+            }
+        }
+        Ok(selected)
+    }
+
+    /// Collects models reachable from `start`, upstream through refs or
+    /// downstream through models that ref the current one.
+    fn walk(&self, start: &str, upstream: bool) -> BTreeSet<String> {
+        let mut found = BTreeSet::new();
+        let mut stack = vec![start.to_string()];
+        while let Some(current) = stack.pop() {
+            let next: Vec<String> = if upstream {
+                self.models[&current].refs.iter().cloned().collect()
+            } else {
+                self.models
+                    .values()
+                    .filter(|m| m.refs.contains(&current))
+                    .map(|m| m.name.clone())
+                    .collect()
+            };
+            for name in next {
+                if found.insert(name.clone()) {
+                    stack.push(name);
+                }
+            }
+        }
+        found
+    }
+
+    /// Builds the `selected` models in dependency order, calling `progress`
+    /// as each finishes.
+    ///
+    /// Models downstream of a failed model are skipped. Unselected models
+    /// are assumed to exist already.
+    pub fn build<F>(
+        &self,
+        conn: &Connection,
+        selected: &BTreeSet<String>,
+        mut progress: F,
+    ) -> Result<Vec<ModelResult>>
+    where
+        F: FnMut(&ModelResult),
+    {
+        let mut results: Vec<ModelResult> = Vec::new();
+        let mut broken: BTreeSet<String> = BTreeSet::new();
+        for name in self.order()? {
+            if !selected.contains(&name) {
+                continue;
+            }
+            let model = &self.models[&name];
+            let status = match model.refs.iter().find(|r| broken.contains(*r)) {
+                Some(upstream) => ModelStatus::Skipped(upstream.clone()),
+                None => {
+                    let timer = Instant::now();
+                    match materialize(conn, model) {
+                        Ok(rows) => ModelStatus::Built {
+                            rows,
+                            elapsed: timer.elapsed(),
+                        },
+                        Err(e) => ModelStatus::Failed(format!("{:#}", e)),
+                    }
+                }
+            };
+            if !matches!(status, ModelStatus::Built { .. }) {
+                broken.insert(name.clone());
+            }
+            let result = ModelResult { name, status };
+            progress(&result);
+            results.push(result);
+        }
+        Ok(results)
+    }
+}
+
+/// Outcome of building one model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelStatus {
+    /// Built; row count is `None` for views
+    Built {
+        /// Rows in the built table
+        rows: Option<usize>,
+        /// Build time
+        elapsed: Duration,
+    },
+    /// The model's SQL failed
+    Failed(String),
+    /// Not built because this upstream model failed or was skipped
+    Skipped(String),
+}
+
+/// A model and how its build went.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelResult {
+    /// Model name
+    pub name: String,
+    /// Build outcome
+    pub status: ModelStatus,
+}
+
+/// Creates or replaces the model's table or view, returning the table's
+/// row count.
+fn materialize(conn: &Connection, model: &Model) -> Result<Option<usize>> {
+    let sql = model.compiled_sql()?;
+    let sql = sql.trim().trim_end_matches(';');
+    let table = quote_identifier(&model.name);
+    match model.materialized {
+        Materialization::Table => {
+            conn.execute_batch(&format!("CREATE OR REPLACE TABLE {} AS {}", table, sql))?;
+            let rows: i64 =
+                conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                    row.get(0)
+                })?;
+            Ok(Some(rows as usize))
+        }
+        Materialization::View => {
+            conn.execute_batch(&format!("CREATE OR REPLACE VIEW {} AS {}", table, sql))?;
+            Ok(None)
+        }
+    }
+}
+
+fn collect_sql_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_sql_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "sql") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(name: &str, sql: &str) -> Model {
+        Model::parse(name, PathBuf::from(format!("models/{}.sql", name)), sql).unwrap()
+    }
+
+    fn graph() -> ModelGraph {
+        ModelGraph::from_models(vec![
+            model(
+                "orders",
+                "SELECT range AS id, range % 3 AS day FROM range(9)",
+            ),
+            model(
+                "daily",
+                "SELECT day, COUNT(*) AS n FROM {{ ref('orders') }} GROUP BY day",
+            ),
+            model(
+                "busiest",
+                "-- materialized: view\nSELECT * FROM {{ ref(\"daily\") }} ORDER BY n DESC LIMIT 1",
+            ),
+            model("other", "SELECT 1 AS x"),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_refs_and_order() {
+        let graph = graph();
+        assert_eq!(
+            graph.order().unwrap(),
+            ["orders", "other", "daily", "busiest"]
+        );
+        let daily = graph.get("daily").unwrap();
+        assert_eq!(daily.refs, BTreeSet::from(["orders".to_string()]));
+        assert!(daily
+            .compiled_sql()
+            .unwrap()
+            .contains("FROM \"orders\" GROUP"));
+        assert_eq!(
+            graph.get("busiest").unwrap().materialized,
+            Materialization::View
+        );
+    }
+
+    #[test]
+    fn test_invalid_graphs() {
+        let unknown = ModelGraph::from_models(vec![model("a", "SELECT * FROM {{ ref('b') }}")]);
+        assert!(unknown.unwrap_err().to_string().contains("unknown model b"));
+
+        let cycle = ModelGraph::from_models(vec![
+            model("a", "SELECT * FROM {{ ref('b') }}"),
+            model("b", "SELECT * FROM {{ ref('a') }}"),
+        ]);
+        assert!(cycle.unwrap_err().to_string().contains("cycle"));
+
+        assert!(Model::parse("a", PathBuf::new(), "SELECT {{ config() }}").is_err());
+    }
+
+    #[test]
+    fn test_select() {
+        let graph = graph();
+        let names = |selectors: &[&str]| -> Vec<String> {
+            let selectors: Vec<String> = selectors.iter().map(|s| s.to_string()).collect();
+            graph.select(&selectors).unwrap().into_iter().collect()
+        };
+        assert_eq!(names(&[]).len(), 4);
+        assert_eq!(names(&["daily"]), ["daily"]);
+        assert_eq!(names(&["daily+"]), ["busiest", "daily"]);
+        assert_eq!(names(&["+daily"]), ["daily", "orders"]);
+        assert_eq!(
+            names(&["orders+,other"]),
+            ["busiest", "daily", "orders", "other"]
+        );
+        assert!(graph.select(&["missing+".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_build_skips_downstream_of_failures() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        let graph = graph();
+        let results = graph.build(&conn, &graph.select(&[])?, |_| {})?;
+        assert!(results
+            .iter()
+            .all(|r| matches!(r.status, ModelStatus::Built { .. })));
+        let n: i64 = conn.query_row("SELECT n FROM busiest", [], |row| row.get(0))?;
+        assert_eq!(n, 3);
+
+        let broken = ModelGraph::from_models(vec![
+            model("a", "SELECT * FROM missing_table"),
+            model("b", "SELECT * FROM {{ ref('a') }}"),
+            model("c", "SELECT 1 AS x"),
+        ])?;
+        let results = broken.build(&conn, &broken.select(&[])?, |_| {})?;
+        let status = |name: &str| &results.iter().find(|r| r.name == name).unwrap().status;
+        assert!(matches!(status("a"), ModelStatus::Failed(_)));
+        assert_eq!(status("b"), &ModelStatus::Skipped("a".to_string()));
+        assert!(matches!(
+            status("c"),
+            ModelStatus::Built { rows: Some(1), .. }
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+//! # File Paths in SQL for Frozen DuckDB CLI
+//!
+//! DuckDB reads and writes files through SQL (`read_csv('…')`,
+//! `COPY … TO '…'`, `ATTACH '…'`), so every path a command touches ends up
+//! inside a SQL string literal. This module turns paths into literals that
+//! DuckDB opens as the same file on Linux, macOS, and Windows.
+//!
+//! | Path | Literal |
+//! |------|---------|
+//! | `/data/Q3 report.csv` | `'/data/Q3 report.csv'` |
+//! | `/data/o'brien.csv` | `'/data/o''brien.csv'` |
+//! | `C:\Users\Zoë\data.csv` | `'C:\Users\Zoë\data.csv'` |
+//! | `\\?\C:\very\long\path.csv` | `'C:\very\long\path.csv'` |
+//! | `\\?\UNC\server\share\data.csv` | `'\\server\share\data.csv'` |
+//!
+//! DuckDB string literals have no backslash escapes, so Windows separators
+//! are kept as they are; only single quotes are doubled. Verbatim `\\?\`
+//! prefixes, which `std::fs::canonicalize` returns on Windows, are removed
+//! because DuckDB's table functions treat `?` as a glob wildcard.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use frozen_duckdb::cli::sql_path::path_literal;
+//!
+//! let sql = format!("SELECT * FROM read_parquet({})", path_literal("exports/o'brien.parquet"));
+//! assert_eq!(sql, "SELECT * FROM read_parquet('exports/o''brien.parquet')");
+//! ```
+
+use std::path::Path;
+
+/// Returns `path` as DuckDB should see it: verbatim Windows prefixes
+/// removed, and non-UTF-8 bytes replaced, since SQL text is UTF-8.
+pub fn sql_path<P: AsRef<Path>>(path: P) -> String {
+    let path = path.as_ref().to_string_lossy();
+    if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", rest)
+    } else if let Some(rest) = path
+        .strip_prefix(r"\\?\")
+        .filter(|rest| rest.as_bytes().get(1) == Some(&b':'))
+    {
+        rest.to_string()
+    } else {
+        path.into_owned()
+    }
+}
+
+/// Returns `path` as a quoted SQL string literal, for embedding in
+/// `read_*`, `COPY`, `ATTACH`, and `EXPORT DATABASE` statements.
+pub fn path_literal<P: AsRef<Path>>(path: P) -> String {
+    format!("'{}'", sql_path(path).replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_literal_escapes_quotes() {
+        assert_eq!(path_literal("/tmp/data.csv"), "'/tmp/data.csv'");
+        assert_eq!(
+            path_literal("/tmp/it's here/a.csv"),
+            "'/tmp/it''s here/a.csv'"
+        );
+        assert_eq!(path_literal("''"), "''''''");
+        assert_eq!(
+            path_literal("/tmp/naïve 数据/ü.parquet"),
+            "'/tmp/naïve 数据/ü.parquet'"
+        );
+    }
+
+    #[test]
+    fn test_windows_paths() {
+        assert_eq!(
+            path_literal(r"C:\Users\Zoë\data.csv"),
+            r"'C:\Users\Zoë\data.csv'"
+        );
+        assert_eq!(
+            path_literal(r"\\server\share\a b.csv"),
+            r"'\\server\share\a b.csv'"
+        );
+        assert_eq!(sql_path(r"\\?\C:\data\a.csv"), r"C:\data\a.csv");
+        assert_eq!(
+            sql_path(r"\\?\UNC\server\share\a.csv"),
+            r"\\server\share\a.csv"
+        );
+        // Volume GUID paths have no shorter form
+        let volume = r"\\?\Volume{26a21bda-a627-11d7-9931-806e6f6e6963}\a.csv";
+        assert_eq!(sql_path(volume), volume);
+    }
+
+    #[test]
+    fn test_long_paths() {
+        let long = format!("/tmp/{}/data.csv", "segment/".repeat(60));
+        assert!(long.len() > 260);
+        assert_eq!(sql_path(&long), long);
+
+        let verbatim = format!(r"\\?\C:\{}data.csv", r"segment\".repeat(60));
+        assert_eq!(sql_path(&verbatim), &verbatim[4..]);
+    }
+}
@@ -0,0 +1,197 @@
+//! # Telemetry Spans and OpenTelemetry Export
+//!
+//! Every operation of [`DatasetManager`](super::DatasetManager) and
+//! [`FlockManager`](super::FlockManager) runs in a `tracing` span named
+//! after the method (`convert_dataset_with`, `complete_rows`, ...), nested
+//! in a `command` span for the CLI command. Methods that only delegate,
+//! such as `complete_text`, share the span of the method they call. Spans
+//! carry what was processed:
+//!
+//! | Field | Recorded by |
+//! |-------|-------------|
+//! | `model` | Flock operations calling a model alias |
+//! | `rows` | Operations reading, writing, or sending rows and texts to a model |
+//! | `bytes_written` | Operations writing a file: its size when they finish |
+//! | `input`, `output`, `table`, `dataset`, `format` | Dataset operations, naming what they read and wrote |
+//!
+//! Durations come from the spans themselves: exported spans have start and
+//! end times, and with `-vv` the log shows each closed span with its
+//! `time.busy` and `time.idle`.
+//!
+//! ## OpenTelemetry Export
+//!
+//! `--otlp-endpoint URL` (or the standard `OTEL_EXPORTER_OTLP_ENDPOINT`
+//! variable) sends spans over OTLP/HTTP to a collector, Jaeger, Tempo, or
+//! any backend accepting OTLP, under the service name `frozen-duckdb`.
+//! `/v1/traces` is appended unless the URL already ends with it. Spans are
+//! exported in batches and flushed when the command finishes, whatever the
+//! `-v` level. Export needs the `otlp` feature:
+//!
+//! ```bash
+//! cargo install frozen-duckdb --features otlp
+//! frozen-duckdb --otlp-endpoint http://localhost:4318 convert --input data.csv --output data.parquet
+//! ```
+//!
+//! # Examples
+//!
+//! ```rust
+//! use frozen_duckdb::cli::telemetry::{record_rows, traces_url};
+//! use tracing::{field, info_span};
+//!
+//! let span = info_span!("load", rows = field::Empty);
+//! let _entered = span.enter();
+//! record_rows(1_000);
+//!
+//! assert_eq!(traces_url("http://localhost:4318/"), "http://localhost:4318/v1/traces");
+//! ```
+
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+use tracing::{Span, Subscriber};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Standard OpenTelemetry variable read when `--otlp-endpoint` isn't given.
+pub const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+/// `service.name` of exported spans.
+pub const SERVICE_NAME: &str = "frozen-duckdb";
+
+/// Path of the OTLP/HTTP traces endpoint.
+const TRACES_PATH: &str = "/v1/traces";
+
+/// Records the number of rows processed on the current span's `rows` field.
+pub fn record_rows(rows: usize) {
+    Span::current().record("rows", rows as u64);
+}
+
+/// Records the size of the file at `path` on the current span's
+/// `bytes_written` field. Directories and missing files are skipped.
+pub fn record_bytes_written<P: AsRef<Path>>(path: P) {
+    if let Ok(metadata) = fs::metadata(path) {
+        if metadata.is_file() {
+            Span::current().record("bytes_written", metadata.len());
+        }
+    }
+}
+
+/// Returns the endpoint from `--otlp-endpoint`, falling back to
+/// [`OTLP_ENDPOINT_ENV`].
+pub fn otlp_endpoint(flag: Option<&str>) -> Option<String> {
+    flag.map(str::to_string)
+        .or_else(|| std::env::var(OTLP_ENDPOINT_ENV).ok())
+        .filter(|endpoint| !endpoint.is_empty())
+}
+
+/// Returns the traces URL for a collector `endpoint`.
+pub fn traces_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    if endpoint.ends_with(TRACES_PATH) {
+        endpoint.to_string()
+    } else {
+        format!("{}{}", endpoint, TRACES_PATH)
+    }
+}
+
+/// Exports spans to an OTLP/HTTP endpoint until [`shutdown`](Self::shutdown).
+#[cfg(feature = "otlp")]
+pub struct OtlpExporter {
+    provider: opentelemetry_sdk::trace::SdkTracerProvider,
+}
+
+#[cfg(feature = "otlp")]
+impl OtlpExporter {
+    /// Starts a batch exporter sending spans to `endpoint`.
+    pub fn start(endpoint: &str) -> Result<Self> {
+        use opentelemetry_otlp::WithExportConfig;
+
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(traces_url(endpoint))
+            .build()
+            .map_err(|e| {
+                anyhow::anyhow!("Failed to create OTLP exporter for {}: {}", endpoint, e)
+            })?;
+        let resource = opentelemetry_sdk::Resource::builder()
+            .with_service_name(SERVICE_NAME)
+            .build();
+        let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(resource)
+            .build();
+        Ok(Self { provider })
+    }
+
+    /// Returns the layer turning `tracing` spans into exported spans.
+    pub fn layer<S>(&self) -> Box<dyn Layer<S> + Send + Sync>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a> + Send + Sync,
+    {
+        use opentelemetry::trace::TracerProvider as _;
+
+        let tracer = self.provider.tracer(SERVICE_NAME);
+        Box::new(tracing_opentelemetry::layer().with_tracer(tracer))
+    }
+
+    /// Flushes the remaining spans and stops the exporter.
+    pub fn shutdown(self) -> Result<()> {
+        self.provider
+            .shutdown()
+            .map_err(|e| anyhow::anyhow!("Failed to flush spans: {}", e))
+    }
+}
+
+/// Stand-in when the `otlp` feature is off: it can't be started.
+#[cfg(not(feature = "otlp"))]
+pub struct OtlpExporter;
+
+#[cfg(not(feature = "otlp"))]
+impl OtlpExporter {
+    /// Always fails: OTLP export was not compiled in.
+    pub fn start(endpoint: &str) -> Result<Self> {
+        anyhow::bail!(
+            "Cannot export spans to {}: frozen-duckdb was built without the `otlp` feature",
+            endpoint
+        )
+    }
+
+    /// Returns a layer that does nothing.
+    pub fn layer<S>(&self) -> Box<dyn Layer<S> + Send + Sync>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a> + Send + Sync,
+    {
+        Box::new(tracing_subscriber::layer::Identity::new())
+    }
+
+    /// Does nothing.
+    pub fn shutdown(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traces_url() {
+        assert_eq!(
+            traces_url("http://collector:4318"),
+            "http://collector:4318/v1/traces"
+        );
+        assert_eq!(
+            traces_url("https://otlp.example.com/v1/traces/"),
+            "https://otlp.example.com/v1/traces"
+        );
+    }
+
+    #[test]
+    fn test_otlp_endpoint_prefers_flag() {
+        assert_eq!(
+            otlp_endpoint(Some("http://flag:4318")).as_deref(),
+            Some("http://flag:4318")
+        );
+        assert_eq!(otlp_endpoint(Some("")), None);
+    }
+}
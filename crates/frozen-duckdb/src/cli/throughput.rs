@@ -0,0 +1,126 @@
+//! # Model Throughput Measurements for Frozen DuckDB CLI
+//!
+//! This module records how fast each LLM model actually runs, so token
+//! estimates (`--estimate`) can predict wall-clock time from real
+//! measurements instead of a fixed guess. Measurements are stored in
+//! `throughput.json` in the [cache directory](super::cache_dir) as
+//! weighted tokens per second
+//! (see [`crate::text::tokens::weighted_tokens`]).
+
+use super::cache_dir::cache_path;
+use crate::text::tokens::{weighted_tokens, DEFAULT_TOKENS_PER_SEC};
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::debug;
+
+const THROUGHPUT_FILE: &str = "throughput.json";
+
+/// Weight of a new measurement in the running average.
+const SMOOTHING: f64 = 0.5;
+
+/// Per-model throughput measurements persisted between runs.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::cli::throughput::ThroughputStore;
+/// use std::time::Duration;
+///
+/// let store = ThroughputStore::new()?;
+/// store.record("text_generator", 120, 512, Duration::from_secs(20))?;
+/// println!("{:.1} tokens/sec", store.tokens_per_sec("text_generator"));
+/// ```
+pub struct ThroughputStore {
+    path: PathBuf,
+}
+
+impl ThroughputStore {
+    /// Opens the default store, `throughput.json` in the cache directory.
+    pub fn new() -> Result<Self> {
+        Ok(Self::with_path(cache_path(THROUGHPUT_FILE)?))
+    }
+
+    /// Opens a store backed by a custom file.
+    pub fn with_path<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns the measured throughput for `model`, or
+    /// [`DEFAULT_TOKENS_PER_SEC`] if it has never been measured.
+    pub fn tokens_per_sec(&self, model: &str) -> f64 {
+        self.measured(model).unwrap_or(DEFAULT_TOKENS_PER_SEC)
+    }
+
+    /// Returns the measured throughput for `model`, if any.
+    pub fn measured(&self, model: &str) -> Option<f64> {
+        self.load().get(model).and_then(Value::as_f64)
+    }
+
+    /// Records a completed model call, folding it into the running average.
+    pub fn record(
+        &self,
+        model: &str,
+        input_tokens: usize,
+        output_tokens: usize,
+        elapsed: Duration,
+    ) -> Result<()> {
+        let secs = elapsed.as_secs_f64();
+        if secs <= 0.0 {
+            return Ok(());
+        }
+
+        let sample = weighted_tokens(input_tokens, output_tokens) / secs;
+        let mut measurements = self.load();
+        let updated = match measurements.get(model).and_then(Value::as_f64) {
+            Some(previous) => previous * (1.0 - SMOOTHING) + sample * SMOOTHING,
+            None => sample,
+        };
+        measurements.insert(model.to_string(), Value::from(updated));
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&measurements)?)
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+
+        debug!("Recorded {:.1} tokens/sec for model {}", updated, model);
+        Ok(())
+    }
+
+    fn load(&self) -> Map<String, Value> {
+        fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_average_measurements() {
+        let temp = tempfile::tempdir().unwrap();
+        let store = ThroughputStore::with_path(temp.path().join("throughput.json"));
+
+        assert_eq!(store.tokens_per_sec("coder"), DEFAULT_TOKENS_PER_SEC);
+
+        // (100 * 0.1 + 90) / 10s = 10 tokens/sec
+        store
+            .record("coder", 100, 90, Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(store.measured("coder"), Some(10.0));
+
+        store
+            .record("coder", 0, 300, Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(store.measured("coder"), Some(20.0));
+        assert_eq!(store.measured("embedder"), None);
+    }
+}
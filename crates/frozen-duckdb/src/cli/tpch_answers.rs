@@ -0,0 +1,293 @@
+//! # TPC-H Answer Verification
+//!
+//! `validate-ffi` checks that the TPC-H queries run; with `--verify` it
+//! also checks that they return the right rows. The `tpch` extension ships
+//! the official answers through `tpch_answers()`, for scale factors 0.01,
+//! 0.1, and 1. Each of the 22 queries is run against freshly generated data
+//! of the chosen scale factor and its result compared with the answer, row
+//! by row and column by column.
+//!
+//! | Value | Matches when |
+//! |-------|--------------|
+//! | Numbers | They differ by at most 0.01 (answers are rounded to two decimals), or by one millionth of the answer for large values |
+//! | Anything else | The text is identical, e.g. dates as `1995-03-15` |
+//! | `NULL` | Both are `NULL` |
+//!
+//! # Examples
+//!
+//! ```rust
+//! use frozen_duckdb::cli::tpch_answers::{compare, parse_answer};
+//!
+//! let expected = parse_answer("l_returnflag|sum_qty\nA|380456.00\nN|8971.00")?;
+//! let actual = vec![
+//!     vec![Some("A".to_string()), Some("380456".to_string())],
+//!     vec![Some("N".to_string()), Some("8970".to_string())],
+//! ];
+//! let mismatches = compare(&expected, &actual);
+//! assert_eq!(mismatches, vec!["row 2, sum_qty: expected 8971.00, got 8970"]);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{Context, Result};
+use duckdb::Connection;
+
+/// Mismatches listed per query; the rest are only counted.
+const MAX_MISMATCHES: usize = 5;
+
+/// Absolute tolerance for numbers, answers being rounded to two decimals.
+const ABSOLUTE_TOLERANCE: f64 = 0.01;
+
+/// Relative tolerance for numbers, for sums too large for two decimals.
+const RELATIVE_TOLERANCE: f64 = 1e-6;
+
+/// An expected result from `tpch_answers()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Answer {
+    /// Column names
+    pub columns: Vec<String>,
+    /// Rows of values, `None` for `NULL`
+    pub rows: Vec<Vec<Option<String>>>,
+}
+
+/// Outcome of verifying one query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryCheck {
+    /// TPC-H query number, 1 to 22
+    pub query_nr: i64,
+    /// Rows in the answer
+    pub expected_rows: usize,
+    /// Rows the query returned
+    pub actual_rows: usize,
+    /// Differences, at most a few; empty when the result is correct
+    pub mismatches: Vec<String>,
+}
+
+impl QueryCheck {
+    /// Whether the result matched the answer.
+    pub fn passed(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Parses an answer of `tpch_answers()`: a `|`-separated header line
+/// followed by one line per row.
+pub fn parse_answer(text: &str) -> Result<Answer> {
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    let header = lines.next().context("Empty TPC-H answer")?;
+    let columns: Vec<String> = header.split('|').map(|c| c.trim().to_string()).collect();
+    let rows = lines
+        .map(|line| {
+            line.split('|')
+                .map(|value| match value.trim() {
+                    "NULL" => None,
+                    value => Some(value.to_string()),
+                })
+                .collect()
+        })
+        .collect();
+    Ok(Answer { columns, rows })
+}
+
+/// Compares a result with its answer and describes the differences.
+pub fn compare(expected: &Answer, actual: &[Vec<Option<String>>]) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    if expected.rows.len() != actual.len() {
+        mismatches.push(format!(
+            "expected {} rows, got {}",
+            expected.rows.len(),
+            actual.len()
+        ));
+    }
+    let mut differing = 0;
+    for (index, (want, got)) in expected.rows.iter().zip(actual).enumerate() {
+        if want.len() != got.len() {
+            differing += 1;
+            if differing <= MAX_MISMATCHES {
+                mismatches.push(format!(
+                    "row {}: expected {} columns, got {}",
+                    index + 1,
+                    want.len(),
+                    got.len()
+                ));
+            }
+            continue;
+        }
+        for (column, (want, got)) in want.iter().zip(got).enumerate() {
+            if values_match(want.as_deref(), got.as_deref()) {
+                continue;
+            }
+            differing += 1;
+            if differing <= MAX_MISMATCHES {
+                let name = expected.columns.get(column).map_or("?", String::as_str);
+                mismatches.push(format!(
+                    "row {}, {}: expected {}, got {}",
+                    index + 1,
+                    name,
+                    want.as_deref().unwrap_or("NULL"),
+                    got.as_deref().unwrap_or("NULL")
+                ));
+            }
+        }
+    }
+    if differing > MAX_MISMATCHES {
+        mismatches.push(format!(
+            "... and {} more differences",
+            differing - MAX_MISMATCHES
+        ));
+    }
+    mismatches
+}
+
+fn values_match(expected: Option<&str>, actual: Option<&str>) -> bool {
+    match (expected, actual) {
+        (None, None) => true,
+        (Some(expected), Some(actual)) => match (expected.parse::<f64>(), actual.parse::<f64>()) {
+            (Ok(e), Ok(a)) => (e - a).abs() <= ABSOLUTE_TOLERANCE.max(e.abs() * RELATIVE_TOLERANCE),
+            _ => expected == actual,
+        },
+        _ => false,
+    }
+}
+
+/// Scale factors `tpch_answers()` has answers for. Installs and loads the
+/// `tpch` extension.
+pub fn answer_scale_factors(conn: &Connection) -> Result<Vec<f64>> {
+    conn.execute_batch("INSTALL tpch; LOAD tpch;")
+        .context("Failed to load the tpch extension")?;
+    let mut stmt =
+        conn.prepare("SELECT DISTINCT scale_factor FROM tpch_answers() ORDER BY scale_factor")?;
+    let factors = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<duckdb::Result<Vec<f64>>>()?;
+    Ok(factors)
+}
+
+/// Generates TPC-H data of `scale_factor` in `conn`, which should be
+/// empty, runs all 22 queries, and checks each against its answer.
+pub fn verify(conn: &Connection, scale_factor: f64) -> Result<Vec<QueryCheck>> {
+    let factors = answer_scale_factors(conn)?;
+    if !factors.iter().any(|f| (f - scale_factor).abs() < 1e-9) {
+        let available: Vec<String> = factors.iter().map(f64::to_string).collect();
+        anyhow::bail!(
+            "No TPC-H answers for scale factor {} (available: {})",
+            scale_factor,
+            available.join(", ")
+        );
+    }
+    conn.execute_batch(&format!("CALL dbgen(sf = {});", scale_factor))
+        .with_context(|| format!("Failed to generate TPC-H data at SF {}", scale_factor))?;
+
+    let mut stmt = conn.prepare(
+        "SELECT q.query_nr, q.query, a.answer FROM tpch_queries() q \
+         JOIN tpch_answers() a USING (query_nr) \
+         WHERE abs(a.scale_factor - ?) < 1e-9 ORDER BY q.query_nr",
+    )?;
+    let queries = stmt
+        .query_map([scale_factor], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<duckdb::Result<Vec<_>>>()?;
+
+    queries
+        .iter()
+        .map(|(query_nr, query, answer)| {
+            let expected = parse_answer(answer)?;
+            let (actual_rows, mismatches) = match run_as_text(conn, query) {
+                Ok(actual) => (actual.len(), compare(&expected, &actual)),
+                Err(e) => (0, vec![format!("query failed: {:#}", e)]),
+            };
+            Ok(QueryCheck {
+                query_nr: *query_nr,
+                expected_rows: expected.rows.len(),
+                actual_rows,
+                mismatches,
+            })
+        })
+        .collect()
+}
+
+/// Runs a query and returns its rows with every value cast to text, as
+/// DuckDB prints it, in the query's order.
+fn run_as_text(conn: &Connection, query: &str) -> Result<Vec<Vec<Option<String>>>> {
+    let query = query.trim().trim_end_matches(';');
+    conn.execute_batch(&format!(
+        "CREATE OR REPLACE TEMP TABLE tpch_result AS {};",
+        query
+    ))?;
+    let mut stmt = conn.prepare("SELECT COLUMNS(*)::VARCHAR FROM tpch_result")?;
+    let mut rows = stmt.query([])?;
+    let mut result = Vec::new();
+    while let Some(row) = rows.next()? {
+        result.push(
+            (0..row.as_ref().column_count())
+                .map(|i| row.get::<_, Option<String>>(i))
+                .collect::<duckdb::Result<Vec<_>>>()?,
+        );
+    }
+    conn.execute_batch("DROP TABLE tpch_result;")?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(values: &[&str]) -> Vec<Option<String>> {
+        values
+            .iter()
+            .map(|v| (*v != "NULL").then(|| v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_answer() {
+        let answer = parse_answer("o_orderdate|revenue\n1995-03-15|4520.55\nNULL|1\n").unwrap();
+        assert_eq!(answer.columns, vec!["o_orderdate", "revenue"]);
+        assert_eq!(
+            answer.rows,
+            vec![row(&["1995-03-15", "4520.55"]), row(&["NULL", "1"])]
+        );
+        assert!(parse_answer("").is_err());
+    }
+
+    #[test]
+    fn test_compare_tolerates_rounding() {
+        let answer = parse_answer("name|avg_qty|total\nA|25.52|532348211.65").unwrap();
+        let actual = vec![row(&["A", "25.522005853257337", "532348211.6500001"])];
+        assert!(compare(&answer, &actual).is_empty());
+
+        let actual = vec![row(&["B", "25.6", "532348211.65"])];
+        assert_eq!(
+            compare(&answer, &actual),
+            vec![
+                "row 1, name: expected A, got B",
+                "row 1, avg_qty: expected 25.52, got 25.6"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare_row_counts_and_limit() {
+        let answer = parse_answer("n\n1\n2\n3\n4\n5\n6\n7").unwrap();
+        assert_eq!(
+            compare(&answer, &[row(&["1"])]),
+            vec!["expected 7 rows, got 1"]
+        );
+
+        let actual: Vec<_> = (0..7).map(|_| row(&["0"])).collect();
+        let mismatches = compare(&answer, &actual);
+        assert_eq!(mismatches.len(), MAX_MISMATCHES + 1);
+        assert_eq!(mismatches.last().unwrap(), "... and 2 more differences");
+    }
+
+    #[test]
+    fn test_values_match_nulls() {
+        assert!(values_match(None, None));
+        assert!(!values_match(None, Some("0")));
+        assert!(!values_match(Some("NULL-ish"), None));
+    }
+}
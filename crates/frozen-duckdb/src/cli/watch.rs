@@ -0,0 +1,155 @@
+//! # Watch Mode for the Query Command
+//!
+//! `frozen-duckdb query --watch 5s` re-runs a query every interval, and
+//! immediately when one of its input files changes, redrawing the result
+//! like `watch(1)`. Values that changed since the previous run are
+//! highlighted, which makes it easy to follow a dataset growing during
+//! ingestion.
+//!
+//! Inputs are the files [`DatasetManager::query_inputs`] finds, so a glob
+//! such as `'events/*.parquet'` also picks up files created while watching.
+//! A failing run (for example while a file is half-written) is shown and
+//! retried on the next tick instead of ending the watch.
+
+use super::dataset_manager::{DatasetManager, QueryOutput};
+use anyhow::Result;
+use notify::{RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::Duration;
+use tracing::debug;
+
+/// Quiet period after a file event before re-running, so a burst of
+/// writes triggers one run.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// ANSI sequence that clears the screen and moves the cursor home.
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+/// Returns the `(row, column)` cells of `current` whose value differs from
+/// `previous`, including every cell of rows `previous` didn't have.
+pub fn changed_cells(previous: &QueryOutput, current: &QueryOutput) -> HashSet<(usize, usize)> {
+    let mut changed = HashSet::new();
+    for (r, row) in current.rows.iter().enumerate() {
+        for (c, value) in row.iter().enumerate() {
+            if previous.rows.get(r).and_then(|p| p.get(c)) != Some(value) {
+                changed.insert((r, c));
+            }
+        }
+    }
+    changed
+}
+
+/// Renders one refresh: a header with the interval, SQL and time, then the
+/// result with changes since `previous` highlighted.
+pub fn render(
+    sql: &str,
+    interval: Duration,
+    current: &QueryOutput,
+    previous: Option<&QueryOutput>,
+) -> String {
+    let changed = previous
+        .map(|previous| changed_cells(previous, current))
+        .unwrap_or_default();
+    format!(
+        "Every {:?}: {}    {}\n\n{}\n",
+        interval,
+        sql,
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+        current.to_table_highlighted(|r, c| changed.contains(&(r, c)))
+    )
+}
+
+/// Re-runs `sql` every `interval` and whenever its input files change,
+/// until the process is interrupted.
+pub fn watch(manager: &DatasetManager, sql: &str, interval: Duration) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    let mut watched: HashSet<PathBuf> = HashSet::new();
+    let mut previous: Option<QueryOutput> = None;
+
+    loop {
+        // Watch the directories of the inputs, so files replaced by rename
+        // or newly matching a glob are noticed too
+        for input in manager.query_inputs(sql) {
+            let dir = match input.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                _ => PathBuf::from("."),
+            };
+            if watched.insert(dir.clone()) {
+                if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+                    debug!("Not watching {}: {}", dir.display(), e);
+                }
+            }
+        }
+
+        let mut stdout = std::io::stdout().lock();
+        match manager.run_query(sql) {
+            Ok(current) => {
+                write!(
+                    stdout,
+                    "{}{}",
+                    CLEAR_SCREEN,
+                    render(sql, interval, &current, previous.as_ref())
+                )?;
+                previous = Some(current);
+            }
+            Err(e) => write!(
+                stdout,
+                "{}Every {:?}: {}\n\n❌ {:#}\n",
+                CLEAR_SCREEN, interval, sql, e
+            )?,
+        }
+        stdout.flush()?;
+        drop(stdout);
+
+        // Wake on the interval or on the first file event, then let the
+        // burst of events settle
+        if let Ok(event) = rx.recv_timeout(interval) {
+            debug!("Input changed: {:?}", event);
+            while rx.recv_timeout(DEBOUNCE).is_ok() {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use duckdb::types::Value;
+
+    fn output(rows: Vec<Vec<Value>>) -> QueryOutput {
+        QueryOutput {
+            columns: vec!["name".to_string(), "count".to_string()],
+            rows,
+        }
+    }
+
+    #[test]
+    fn test_changed_cells() {
+        let before = output(vec![vec![Value::Text("a".into()), Value::BigInt(1)]]);
+        let after = output(vec![
+            vec![Value::Text("a".into()), Value::BigInt(2)],
+            vec![Value::Text("b".into()), Value::BigInt(1)],
+        ]);
+
+        let changed = changed_cells(&before, &after);
+        assert_eq!(changed, HashSet::from([(0, 1), (1, 0), (1, 1)]));
+        assert!(changed_cells(&after, &after).is_empty());
+    }
+
+    #[test]
+    fn test_render_highlights_changes() {
+        let before = output(vec![vec![Value::Text("a".into()), Value::BigInt(1)]]);
+        let after = output(vec![vec![Value::Text("a".into()), Value::BigInt(2)]]);
+
+        let first = render("SELECT 1", Duration::from_secs(5), &before, None);
+        assert!(first.starts_with("Every 5s: SELECT 1"));
+        assert!(!first.contains("\x1b[1;33m"));
+
+        let second = render("SELECT 1", Duration::from_secs(5), &after, Some(&before));
+        assert!(second.contains("\x1b[1;33m2    \x1b[0m"));
+        assert!(!second.contains("\x1b[1;33ma"));
+    }
+}
@@ -3,7 +3,7 @@ use std::{ffi::c_void, fmt, os::raw::c_char};
 
 use crate::{
     error::result_from_duckdb_appender,
-    types::{ToSql, ToSqlOutput},
+    types::{numeric::to_ffi_decimal, ToSql, ToSqlOutput},
     Error,
 };
 
@@ -114,6 +114,12 @@ impl Appender<'_> {
 
             ValueRef::Float(r) => unsafe { ffi::duckdb_append_float(ptr, r) },
             ValueRef::Double(r) => unsafe { ffi::duckdb_append_double(ptr, r) },
+            ValueRef::Decimal(d) => unsafe {
+                let mut value = ffi::duckdb_create_decimal(to_ffi_decimal(&d));
+                let rc = ffi::duckdb_append_value(ptr, value);
+                ffi::duckdb_destroy_value(&mut value);
+                rc
+            },
             ValueRef::Text(s) => unsafe {
                 ffi::duckdb_append_varchar_length(ptr, s.as_ptr() as *const c_char, s.len() as u64)
             },
@@ -68,6 +68,10 @@ pub enum LogicalTypeId {
     Union = DUCKDB_TYPE_DUCKDB_TYPE_UNION,
     /// Timestamp TZ
     TimestampTZ = DUCKDB_TYPE_DUCKDB_TYPE_TIMESTAMP_TZ,
+    /// Any (only valid as a function parameter type)
+    Any = DUCKDB_TYPE_DUCKDB_TYPE_ANY,
+    /// SQL NULL literal
+    SqlNull = DUCKDB_TYPE_DUCKDB_TYPE_SQLNULL,
 }
 
 impl From<u32> for LogicalTypeId {
@@ -103,6 +107,8 @@ impl From<u32> for LogicalTypeId {
             DUCKDB_TYPE_DUCKDB_TYPE_UUID => Self::Uuid,
             DUCKDB_TYPE_DUCKDB_TYPE_UNION => Self::Union,
             DUCKDB_TYPE_DUCKDB_TYPE_TIMESTAMP_TZ => Self::TimestampTZ,
+            DUCKDB_TYPE_DUCKDB_TYPE_ANY => Self::Any,
+            DUCKDB_TYPE_DUCKDB_TYPE_SQLNULL => Self::SqlNull,
             _ => panic!(),
         }
     }
@@ -13,21 +13,46 @@ use crate::{
         Error,
     },
     raw_statement::RawStatement,
-    statement::Statement,
+    statement::{Statement, StatementType},
 };
 
+/// A database handle shared by a connection and all of its clones.
+///
+/// The database is closed when the last connection using it is dropped, so
+/// a clone never connects through a handle that was already closed.
+struct Database {
+    raw: ffi::duckdb_database,
+    owned: bool,
+}
+
+// The DuckDB database handle may be used from any thread
+unsafe impl Send for Database {}
+unsafe impl Sync for Database {}
+
+impl Drop for Database {
+    fn drop(&mut self) {
+        if self.owned {
+            unsafe { ffi::duckdb_close(&mut self.raw) };
+        }
+    }
+}
+
 pub struct InnerConnection {
     pub db: ffi::duckdb_database,
     pub con: ffi::duckdb_connection,
     interrupt: Arc<InterruptHandle>,
-    owned: bool,
+    database: Option<Arc<Database>>,
 }
 
 impl InnerConnection {
     #[inline]
     pub unsafe fn new(db: ffi::duckdb_database, owned: bool) -> Result<Self> {
+        Self::connect(Arc::new(Database { raw: db, owned }))
+    }
+
+    unsafe fn connect(database: Arc<Database>) -> Result<Self> {
         let mut con: ffi::duckdb_connection = ptr::null_mut();
-        let r = ffi::duckdb_connect(db, &mut con);
+        let r = ffi::duckdb_connect(database.raw, &mut con);
         if r != ffi::DuckDBSuccess {
             ffi::duckdb_disconnect(&mut con);
             return Err(Error::DuckDBFailure(
@@ -38,10 +63,10 @@ impl InnerConnection {
         let interrupt = Arc::new(InterruptHandle::new(con));
 
         Ok(Self {
-            db,
+            db: database.raw,
             con,
             interrupt,
-            owned,
+            database: Some(database),
         })
     }
 
@@ -70,18 +95,25 @@ impl InnerConnection {
             ffi::duckdb_disconnect(&mut self.con);
             self.con = ptr::null_mut();
             self.interrupt.clear();
-
-            if self.owned {
-                ffi::duckdb_close(&mut self.db);
-                self.db = ptr::null_mut();
-            }
         }
+        // Closes the database if no clone is still using it
+        self.database = None;
+        self.db = ptr::null_mut();
         Ok(())
     }
 
     /// Creates a new connection to the already-opened database.
+    ///
+    /// The clone keeps the database open, even after this connection is
+    /// closed.
     pub fn try_clone(&self) -> Result<Self> {
-        unsafe { Self::new(self.db, false) }
+        match &self.database {
+            Some(database) => unsafe { Self::connect(Arc::clone(database)) },
+            None => Err(Error::DuckDBFailure(
+                ffi::Error::new(ffi::DuckDBError),
+                Some("connection is closed".to_owned()),
+            )),
+        }
     }
 
     pub fn execute(&mut self, sql: &str) -> Result<()> {
@@ -117,6 +149,25 @@ impl InnerConnection {
         Ok(Statement::new(conn, unsafe { RawStatement::new(final_stmt) }))
     }
 
+    pub fn statement_types(&mut self, sql: &str) -> Result<Vec<StatementType>> {
+        let c_str = CString::new(sql).unwrap();
+
+        let mut extracted = ptr::null_mut();
+        let num_stmts =
+            unsafe { ffi::duckdb_extract_statements(self.con, c_str.as_ptr() as *const c_char, &mut extracted) };
+        result_from_duckdb_extract(num_stmts, extracted)?;
+        let _guard = ExtractedStatementsGuard(extracted);
+
+        (0..num_stmts)
+            .map(|i| {
+                let stmt = self.prepare_extracted_statement(extracted, i)?;
+                // Destroys the prepared statement when dropped
+                let stmt = unsafe { RawStatement::new(stmt) };
+                Ok(stmt.statement_type())
+            })
+            .collect()
+    }
+
     fn prepare_extracted_statement(
         &self,
         extracted: ffi::duckdb_extracted_statements,
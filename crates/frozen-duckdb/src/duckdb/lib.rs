@@ -82,7 +82,8 @@ pub use crate::{
     inner_connection::InterruptHandle,
     params::{params_from_iter, Params, ParamsFromIter},
     row::{AndThenRows, Map, MappedRows, Row, RowIndex, Rows},
-    statement::Statement,
+    savepoint::Savepoint,
+    statement::{Statement, StatementType},
     transaction::{DropBehavior, Transaction},
     types::ToSql,
 };
@@ -116,6 +117,7 @@ mod pragma;
 mod r2d2;
 mod raw_statement;
 mod row;
+mod savepoint;
 mod statement;
 mod transaction;
 
@@ -346,6 +348,32 @@ impl Connection {
         self.db.borrow_mut().execute(sql)
     }
 
+    /// Parse `sql` and return the type of each of its statements, without
+    /// executing any of them.
+    ///
+    /// Unlike [`Connection::prepare`], which runs every statement but the
+    /// last, this only prepares each statement, so it can be used to vet SQL
+    /// before running it.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// # use duckdb::{Connection, Result, StatementType};
+    /// fn is_read_only(conn: &Connection, sql: &str) -> Result<bool> {
+    ///     let types = conn.statement_types(sql)?;
+    ///     Ok(types.iter().all(|t| *t == StatementType::Select))
+    /// }
+    /// ```
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if `sql` doesn't parse, or if a statement fails to
+    /// bind, e.g. because it refers to a table created by an earlier
+    /// statement of the same batch.
+    pub fn statement_types(&self, sql: &str) -> Result<Vec<StatementType>> {
+        self.db.borrow_mut().statement_types(sql)
+    }
+
     /// Convenience method to prepare and execute a single SQL statement.
     ///
     /// On success, returns the number of rows that were changed or inserted or
@@ -1033,6 +1061,20 @@ mod test {
             cloned_con.execute_batch("create table test2 (c1 bigint)")?;
             cloned_con.close().unwrap();
         }
+
+        // 3. Clone a clone after the original is gone. The database must still be open.
+        {
+            let cloned_con = {
+                let owned_con = checked_memory_handle();
+                owned_con.execute_batch("create table test (c1 bigint)")?;
+                owned_con.try_clone().unwrap()
+            };
+            let second_clone = cloned_con.try_clone().unwrap();
+            drop(cloned_con);
+            second_clone.execute_batch("insert into test values (1)")?;
+            let count: i64 = second_clone.query_row("select count(*) from test", [], |r| r.get(0))?;
+            assert_eq!(count, 1);
+        }
         Ok(())
     }
 
@@ -1480,6 +1522,21 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_statement_types() -> Result<()> {
+        let db = checked_memory_handle();
+        db.execute_batch("CREATE TABLE test(x INTEGER)")?;
+
+        let types = db.statement_types("SELECT x FROM test; INSERT INTO test VALUES (1); DROP TABLE test")?;
+        assert_eq!(types, vec![StatementType::Select, StatementType::Insert, StatementType::Drop]);
+        // Nothing ran
+        let count: i64 = db.query_row("SELECT COUNT(*) FROM test", [], |row| row.get(0))?;
+        assert_eq!(count, 0);
+
+        assert!(db.statement_types("SELEC 1").is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_pivot_query() -> Result<()> {
         let db = checked_memory_handle();
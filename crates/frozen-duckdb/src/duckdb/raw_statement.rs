@@ -9,7 +9,7 @@ use arrow::{
 use super::{ffi, Result};
 #[cfg(feature = "polars")]
 use crate::arrow2;
-use crate::{error::result_from_duckdb_arrow, Error};
+use crate::{error::result_from_duckdb_arrow, Error, StatementType};
 
 // Private newtype for raw sqlite3_stmts that finalize themselves when dropped.
 // TODO: destroy statement and result
@@ -306,6 +306,11 @@ impl RawStatement {
         }
     }
 
+    #[inline]
+    pub fn statement_type(&self) -> StatementType {
+        unsafe { ffi::duckdb_prepared_statement_type(self.ptr) }.into()
+    }
+
     #[inline]
     pub fn bind_parameter_count(&self) -> usize {
         unsafe { ffi::duckdb_nparams(self.ptr) as usize }
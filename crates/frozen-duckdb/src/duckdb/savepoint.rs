@@ -0,0 +1,188 @@
+use crate::{ffi, Connection, Error, Result};
+use std::cell::Cell;
+use std::ops::Deref;
+
+/// A scope inside a transaction opened by [`Connection::with_savepoint`].
+///
+/// DuckDB has no `SAVEPOINT` statement, so nested scopes cannot be rolled
+/// back on their own. Instead, a failing nested scope marks the whole
+/// transaction as rollback-only: even if the caller handles the error, the
+/// outermost scope rolls back rather than committing a partially applied
+/// operation.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// # use duckdb::{Connection, Result};
+/// fn move_funds(conn: &Connection) -> Result<()> {
+///     conn.with_savepoint(|sp| {
+///         sp.execute_batch("UPDATE accounts SET balance = balance - 10 WHERE id = 1")?;
+///         sp.with_savepoint(|inner| {
+///             inner.execute_batch("UPDATE accounts SET balance = balance + 10 WHERE id = 2")
+///         })
+///     })
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Savepoint<'conn> {
+    conn: &'conn Connection,
+    depth: usize,
+    rollback_only: &'conn Cell<bool>,
+}
+
+impl Savepoint<'_> {
+    /// Nesting depth, starting at 1 for the scope that owns the transaction.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Whether a nested scope failed, so the transaction will roll back.
+    #[inline]
+    pub fn is_rollback_only(&self) -> bool {
+        self.rollback_only.get()
+    }
+
+    /// Runs `f` in a nested scope of the same transaction.
+    ///
+    /// If `f` returns `Err`, the enclosing transaction is marked
+    /// rollback-only and the error is returned.
+    pub fn with_savepoint<T, E, F>(&self, f: F) -> std::result::Result<T, E>
+    where
+        F: FnOnce(&Savepoint<'_>) -> std::result::Result<T, E>,
+    {
+        let nested = Savepoint {
+            conn: self.conn,
+            depth: self.depth + 1,
+            rollback_only: self.rollback_only,
+        };
+        let result = f(&nested);
+        if result.is_err() {
+            self.rollback_only.set(true);
+        }
+        result
+    }
+}
+
+impl Deref for Savepoint<'_> {
+    type Target = Connection;
+
+    #[inline]
+    fn deref(&self) -> &Connection {
+        self.conn
+    }
+}
+
+impl Connection {
+    /// Runs `f` in a transaction that commits if it returns `Ok` and rolls
+    /// back if it returns `Err`.
+    ///
+    /// Use [`Savepoint::with_savepoint`] on the argument for nested
+    /// operations. If a nested scope failed, the transaction rolls back even
+    /// when `f` returns `Ok`, and an error is returned.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// # use duckdb::{Connection, Result};
+    /// fn load(conn: &Connection) -> Result<()> {
+    ///     conn.with_savepoint(|sp| {
+    ///         sp.execute_batch("CREATE TABLE t (x INTEGER)")?;
+    ///         sp.execute_batch("INSERT INTO t VALUES (1), (2)")
+    ///     })
+    /// }
+    /// ```
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if a transaction is already open on this
+    /// connection, or if `BEGIN`, `COMMIT` or `ROLLBACK` fails.
+    pub fn with_savepoint<T, E, F>(&self, f: F) -> std::result::Result<T, E>
+    where
+        F: FnOnce(&Savepoint<'_>) -> std::result::Result<T, E>,
+        E: From<Error>,
+    {
+        self.execute_batch("BEGIN TRANSACTION")?;
+        let rollback_only = Cell::new(false);
+        let savepoint = Savepoint {
+            conn: self,
+            depth: 1,
+            rollback_only: &rollback_only,
+        };
+
+        match f(&savepoint) {
+            Ok(value) if !rollback_only.get() => {
+                if let Err(e) = self.execute_batch("COMMIT") {
+                    let _ = self.execute_batch("ROLLBACK");
+                    return Err(e.into());
+                }
+                Ok(value)
+            }
+            Ok(_) => {
+                self.execute_batch("ROLLBACK")?;
+                Err(Error::DuckDBFailure(
+                    ffi::Error::new(ffi::DuckDBError),
+                    Some("Transaction rolled back because a nested savepoint failed".to_owned()),
+                )
+                .into())
+            }
+            Err(e) => {
+                self.execute_batch("ROLLBACK")?;
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Connection, Result};
+
+    fn count(db: &Connection) -> Result<i64> {
+        db.query_row("SELECT COUNT(*) FROM t", [], |r| r.get(0))
+    }
+
+    #[test]
+    fn test_commit_and_rollback() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch("CREATE TABLE t (x INTEGER)")?;
+
+        db.with_savepoint(|sp| sp.execute_batch("INSERT INTO t VALUES (1)"))?;
+        assert_eq!(count(&db)?, 1);
+
+        let failed: Result<()> = db.with_savepoint(|sp| {
+            sp.execute_batch("INSERT INTO t VALUES (2)")?;
+            sp.execute_batch("INSERT INTO missing VALUES (3)")
+        });
+        assert!(failed.is_err());
+        assert_eq!(count(&db)?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_nested_scope_rolls_back_everything() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch("CREATE TABLE t (x INTEGER)")?;
+
+        let result: Result<()> = db.with_savepoint(|sp| {
+            sp.execute_batch("INSERT INTO t VALUES (1)")?;
+            let nested: Result<()> = sp.with_savepoint(|inner| {
+                assert_eq!(inner.depth(), 2);
+                inner.execute_batch("INSERT INTO missing VALUES (2)")
+            });
+            // Swallowing the nested error doesn't commit the partial work
+            assert!(nested.is_err());
+            assert!(sp.is_rollback_only());
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(count(&db)?, 0);
+
+        // The connection is usable afterwards
+        db.with_savepoint(|sp| {
+            sp.with_savepoint(|inner| inner.execute_batch("INSERT INTO t VALUES (3)"))
+        })?;
+        assert_eq!(count(&db)?, 1);
+        Ok(())
+    }
+}
@@ -8,7 +8,7 @@ use crate::{arrow2, polars_dataframe::Polars};
 use crate::{
     arrow_batch::{Arrow, ArrowStream},
     error::result_from_duckdb_prepare,
-    types::{TimeUnit, ToSql, ToSqlOutput},
+    types::{numeric::to_ffi_decimal, TimeUnit, ToSql, ToSqlOutput},
 };
 
 /// A prepared statement.
@@ -429,6 +429,12 @@ impl Statement<'_> {
         self.stmt.bind_parameter_count()
     }
 
+    /// Return the type of this statement, as determined by DuckDB's parser.
+    #[inline]
+    pub fn statement_type(&self) -> StatementType {
+        self.stmt.statement_type()
+    }
+
     /// Low level API to directly bind a parameter to a given index.
     ///
     /// Note that the index is one-based, that is, the first parameter index is
@@ -548,6 +554,7 @@ impl Statement<'_> {
             ValueRef::UBigInt(i) => unsafe { ffi::duckdb_bind_uint64(ptr, col as u64, i) },
             ValueRef::Float(r) => unsafe { ffi::duckdb_bind_float(ptr, col as u64, r) },
             ValueRef::Double(r) => unsafe { ffi::duckdb_bind_double(ptr, col as u64, r) },
+            ValueRef::Decimal(d) => unsafe { ffi::duckdb_bind_decimal(ptr, col as u64, to_ffi_decimal(&d)) },
             ValueRef::Text(s) => unsafe {
                 ffi::duckdb_bind_varchar_length(ptr, col as u64, s.as_ptr() as *const c_char, s.len() as u64)
             },
@@ -603,6 +610,139 @@ impl fmt::Debug for Statement<'_> {
     }
 }
 
+/// The type of a SQL statement, as reported by DuckDB's parser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum StatementType {
+    /// A statement DuckDB doesn't classify
+    Invalid,
+    /// `SELECT`, including `FROM`-first queries, `VALUES`, and `SHOW`
+    Select,
+    /// `INSERT`
+    Insert,
+    /// `UPDATE`
+    Update,
+    /// `EXPLAIN`
+    Explain,
+    /// `DELETE`
+    Delete,
+    /// `PREPARE`
+    Prepare,
+    /// `CREATE` of a table, view, schema, sequence, or type
+    Create,
+    /// `EXECUTE`
+    Execute,
+    /// `ALTER`
+    Alter,
+    /// `BEGIN`, `COMMIT`, and `ROLLBACK`
+    Transaction,
+    /// `COPY`
+    Copy,
+    /// `ANALYZE`
+    Analyze,
+    /// `SET VARIABLE`
+    VariableSet,
+    /// `CREATE FUNCTION` and `CREATE MACRO`
+    CreateFunc,
+    /// `DROP`
+    Drop,
+    /// `EXPORT DATABASE`
+    Export,
+    /// `PRAGMA`
+    Pragma,
+    /// `VACUUM`
+    Vacuum,
+    /// `CALL`
+    Call,
+    /// `SET` and `RESET`
+    Set,
+    /// `LOAD` and `INSTALL`
+    Load,
+    /// A statement built from a relation
+    Relation,
+    /// A statement added by an extension
+    Extension,
+    /// A statement built from a logical plan
+    LogicalPlan,
+    /// `ATTACH`
+    Attach,
+    /// `DETACH`
+    Detach,
+    /// Several statements parsed as one
+    Multi,
+}
+
+impl StatementType {
+    /// Returns DuckDB's name for this statement type, e.g. `SELECT`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Invalid => "INVALID",
+            Self::Select => "SELECT",
+            Self::Insert => "INSERT",
+            Self::Update => "UPDATE",
+            Self::Explain => "EXPLAIN",
+            Self::Delete => "DELETE",
+            Self::Prepare => "PREPARE",
+            Self::Create => "CREATE",
+            Self::Execute => "EXECUTE",
+            Self::Alter => "ALTER",
+            Self::Transaction => "TRANSACTION",
+            Self::Copy => "COPY",
+            Self::Analyze => "ANALYZE",
+            Self::VariableSet => "VARIABLE_SET",
+            Self::CreateFunc => "CREATE_FUNC",
+            Self::Drop => "DROP",
+            Self::Export => "EXPORT",
+            Self::Pragma => "PRAGMA",
+            Self::Vacuum => "VACUUM",
+            Self::Call => "CALL",
+            Self::Set => "SET",
+            Self::Load => "LOAD",
+            Self::Relation => "RELATION",
+            Self::Extension => "EXTENSION",
+            Self::LogicalPlan => "LOGICAL_PLAN",
+            Self::Attach => "ATTACH",
+            Self::Detach => "DETACH",
+            Self::Multi => "MULTI",
+        }
+    }
+}
+
+impl From<ffi::duckdb_statement_type> for StatementType {
+    fn from(value: ffi::duckdb_statement_type) -> Self {
+        match value {
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_SELECT => Self::Select,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_INSERT => Self::Insert,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_UPDATE => Self::Update,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_EXPLAIN => Self::Explain,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_DELETE => Self::Delete,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_PREPARE => Self::Prepare,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_CREATE => Self::Create,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_EXECUTE => Self::Execute,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_ALTER => Self::Alter,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_TRANSACTION => Self::Transaction,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_COPY => Self::Copy,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_ANALYZE => Self::Analyze,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_VARIABLE_SET => Self::VariableSet,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_CREATE_FUNC => Self::CreateFunc,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_DROP => Self::Drop,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_EXPORT => Self::Export,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_PRAGMA => Self::Pragma,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_VACUUM => Self::Vacuum,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_CALL => Self::Call,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_SET => Self::Set,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_LOAD => Self::Load,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_RELATION => Self::Relation,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_EXTENSION => Self::Extension,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_LOGICAL_PLAN => Self::LogicalPlan,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_ATTACH => Self::Attach,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_DETACH => Self::Detach,
+            ffi::duckdb_statement_type_DUCKDB_STATEMENT_TYPE_MULTI => Self::Multi,
+            _ => Self::Invalid,
+        }
+    }
+}
+
 impl Statement<'_> {
     #[inline]
     pub(super) fn new(conn: &Connection, stmt: RawStatement) -> Statement<'_> {
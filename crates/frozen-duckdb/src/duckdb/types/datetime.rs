@@ -0,0 +1,163 @@
+//! Time zone aware timestamps backed by ICU.
+//!
+//! DuckDB stores `TIMESTAMP WITH TIME ZONE` as a UTC instant. The session
+//! `TimeZone` setting, provided by the ICU extension compiled into the
+//! frozen binary, only affects how instants are displayed and how local
+//! times are interpreted. [`Timestamptz`] binds and reads such instants
+//! without losing the offset, and [`session_time_zone`] and
+//! [`set_session_time_zone`] bridge the session setting to `chrono_tz`.
+//!
+//! ```rust,no_run
+//! use chrono::TimeZone;
+//! use chrono_tz::Europe::Berlin;
+//! use frozen_duckdb::types::datetime::{set_session_time_zone, Timestamptz};
+//! use frozen_duckdb::Connection;
+//!
+//! let conn = Connection::open_in_memory()?;
+//! set_session_time_zone(&conn, Berlin)?;
+//!
+//! let meeting = Berlin.with_ymd_and_hms(2024, 3, 31, 9, 0, 0).unwrap();
+//! conn.execute_batch("CREATE TABLE events (at TIMESTAMPTZ)")?;
+//! conn.execute("INSERT INTO events VALUES (?)", [Timestamptz(meeting)])?;
+//!
+//! let at: Timestamptz<chrono_tz::Tz> = conn.query_row("SELECT at FROM events", [], |r| r.get(0))?;
+//! assert_eq!(at.in_zone(&Berlin), meeting);
+//! # Ok::<(), frozen_duckdb::DuckDBError>(())
+//! ```
+
+use super::{FromSql, FromSqlError, FromSqlResult, TimeUnit, ToSql, ToSqlOutput, ValueRef};
+use crate::{Connection, Error, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use std::fmt::Display;
+
+/// A `TIMESTAMP WITH TIME ZONE` value as a chrono `DateTime`.
+///
+/// Binding writes the instant with its UTC offset, so DuckDB stores the
+/// same instant whatever the session time zone is. Reading returns the
+/// instant in UTC (`Timestamptz<Utc>` or `Timestamptz<chrono_tz::Tz>`);
+/// use [`Timestamptz::in_zone`] to view it in another zone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timestamptz<Tz: TimeZone>(pub DateTime<Tz>);
+
+impl<Tz: TimeZone> Timestamptz<Tz> {
+    /// Returns the same instant in `tz`.
+    pub fn in_zone<Z: TimeZone>(&self, tz: &Z) -> DateTime<Z> {
+        self.0.with_timezone(tz)
+    }
+}
+
+impl<Tz: TimeZone> ToSql for Timestamptz<Tz>
+where
+    Tz::Offset: Display,
+{
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.0.format("%F %T%.f%:z").to_string()))
+    }
+}
+
+impl FromSql for Timestamptz<Utc> {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        instant(value).map(Timestamptz)
+    }
+}
+
+impl FromSql for Timestamptz<chrono_tz::Tz> {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        instant(value).map(|utc| Timestamptz(utc.with_timezone(&chrono_tz::UTC)))
+    }
+}
+
+/// Reads a `TIMESTAMPTZ` (or `TIMESTAMP`, taken as UTC) as an instant.
+///
+/// Text is accepted in DuckDB's display format, e.g.
+/// `2024-03-31 09:00:00+02` or `2024-03-31 09:00:00.5+05:30`.
+pub fn instant(value: ValueRef<'_>) -> FromSqlResult<DateTime<Utc>> {
+    match value {
+        ValueRef::Timestamp(unit, t) => {
+            let (secs, nanos) = match unit {
+                TimeUnit::Second => (t, 0),
+                TimeUnit::Millisecond => (t.div_euclid(1000), t.rem_euclid(1000) * 1_000_000),
+                TimeUnit::Microsecond => (t.div_euclid(1_000_000), t.rem_euclid(1_000_000) * 1000),
+                TimeUnit::Nanosecond => (t.div_euclid(1_000_000_000), t.rem_euclid(1_000_000_000)),
+            };
+            DateTime::from_timestamp(secs, nanos as u32).ok_or(FromSqlError::OutOfRange(t as i128))
+        }
+        ValueRef::Text(s) => {
+            let s = std::str::from_utf8(s).map_err(|e| FromSqlError::Other(Box::new(e)))?;
+            DateTime::parse_from_str(s, "%F %T%.f%#z")
+                .map(|dt| dt.with_timezone(&Utc))
+                .map_err(|e| FromSqlError::Other(Box::new(e)))
+        }
+        _ => Err(FromSqlError::InvalidType),
+    }
+}
+
+/// Returns the session `TimeZone` setting as a `chrono_tz` zone.
+pub fn session_time_zone(conn: &Connection) -> Result<chrono_tz::Tz> {
+    let name: String = conn.query_row("SELECT current_setting('TimeZone')", [], |r| r.get(0))?;
+    name.parse()
+        .map_err(|e: chrono_tz::ParseError| Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+/// Sets the session `TimeZone`, which controls how `TIMESTAMPTZ` values
+/// are displayed and how casts from `TIMESTAMP` are interpreted.
+pub fn set_session_time_zone(conn: &Connection, tz: chrono_tz::Tz) -> Result<()> {
+    conn.execute_batch(&format!("SET TimeZone = '{}'", tz.name()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{NaiveDate, Timelike};
+    use chrono_tz::{America::New_York, Asia::Kolkata, Europe::Berlin};
+
+    #[test]
+    fn test_round_trip_keeps_instant() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch("CREATE TABLE t (at TIMESTAMPTZ)")?;
+        set_session_time_zone(&db, New_York)?;
+
+        let berlin = Berlin.with_ymd_and_hms(2024, 3, 31, 9, 0, 0).unwrap();
+        db.execute("INSERT INTO t VALUES (?)", [Timestamptz(berlin)])?;
+
+        let utc: Timestamptz<Utc> = db.query_row("SELECT at FROM t", [], |r| r.get(0))?;
+        assert_eq!(utc.0, berlin.with_timezone(&Utc));
+        let zoned: Timestamptz<chrono_tz::Tz> =
+            db.query_row("SELECT at FROM t", [], |r| r.get(0))?;
+        assert_eq!(zoned.in_zone(&Berlin), berlin);
+        assert_eq!(zoned.in_zone(&Kolkata).hour(), 12);
+        Ok(())
+    }
+
+    #[test]
+    fn test_icu_conversions_match_chrono_tz() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        set_session_time_zone(&db, Berlin)?;
+        assert_eq!(session_time_zone(&db)?, Berlin);
+
+        // Across the DST change on 2024-03-31, ICU and chrono-tz agree
+        for hour in [0, 1, 3, 12] {
+            let local = NaiveDate::from_ymd_opt(2024, 3, 31)
+                .unwrap()
+                .and_hms_opt(hour, 30, 0)
+                .unwrap();
+            let expected = Berlin.from_local_datetime(&local).single().unwrap();
+            let icu: Timestamptz<Utc> = db.query_row(
+                "SELECT CAST(CAST(? AS TIMESTAMP) AS TIMESTAMPTZ)",
+                [local.format("%F %T").to_string()],
+                |r| r.get(0),
+            )?;
+            assert_eq!(icu.0, expected.with_timezone(&Utc), "{}", local);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_instant_from_text() {
+        let parse = |s: &str| instant(ValueRef::Text(s.as_bytes())).unwrap();
+        let expected = Utc.with_ymd_and_hms(2024, 3, 31, 7, 0, 0).unwrap();
+        assert_eq!(parse("2024-03-31 09:00:00+02"), expected);
+        assert_eq!(parse("2024-03-31 12:30:00+05:30"), expected);
+        assert!(instant(ValueRef::Text(b"yesterday")).is_err());
+    }
+}
@@ -16,7 +16,10 @@ use std::fmt;
 
 #[cfg(feature = "chrono")]
 mod chrono;
+#[cfg(feature = "chrono-tz")]
+pub mod datetime;
 mod from_sql;
+pub mod numeric;
 #[cfg(feature = "serde_json")]
 mod serde_json;
 mod to_sql;
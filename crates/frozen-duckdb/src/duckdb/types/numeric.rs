@@ -0,0 +1,206 @@
+//! Exact conversions for `DECIMAL` and `HUGEINT`.
+//!
+//! The generic numeric [`FromSql`] implementations accept any numeric
+//! column, including `DOUBLE`, so a financial value can silently pass
+//! through `f64`. The types here only accept columns they can represent
+//! exactly:
+//!
+//! | Rust | DuckDB | Feature |
+//! |------|--------|---------|
+//! | `rust_decimal::Decimal` | `DECIMAL(w, s)`, integers up to `HUGEINT` | `decimal` (implies `hugeint`) |
+//! | [`HugeInt`] | `HUGEINT` and narrower integers | `hugeint` |
+//!
+//! ```rust,no_run
+//! use frozen_duckdb::Connection;
+//! use rust_decimal::Decimal;
+//!
+//! let conn = Connection::open_in_memory()?;
+//! conn.execute_batch("CREATE TABLE ledger (amount DECIMAL(18, 2))")?;
+//! conn.execute("INSERT INTO ledger VALUES (?)", [Decimal::new(1999, 2)])?;
+//!
+//! let total: Decimal = conn.query_row("SELECT SUM(amount) FROM ledger", [], |r| r.get(0))?;
+//! assert_eq!(total, Decimal::new(1999, 2));
+//! # Ok::<(), frozen_duckdb::DuckDBError>(())
+//! ```
+
+use crate::ffi;
+use rust_decimal::Decimal;
+
+#[cfg(feature = "hugeint")]
+use super::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, Value, ValueRef};
+#[cfg(feature = "hugeint")]
+use crate::Result;
+
+/// Widest DuckDB `DECIMAL`; every `rust_decimal::Decimal` fits.
+const MAX_DECIMAL_WIDTH: u8 = 38;
+
+/// Converts a decimal for `duckdb_bind_decimal` and `duckdb_create_decimal`.
+pub(crate) fn to_ffi_decimal(d: &Decimal) -> ffi::duckdb_decimal {
+    let mantissa = d.mantissa();
+    ffi::duckdb_decimal {
+        width: MAX_DECIMAL_WIDTH,
+        scale: d.scale() as u8,
+        value: ffi::duckdb_hugeint {
+            lower: mantissa as u64,
+            upper: (mantissa >> 64) as i64,
+        },
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl ToSql for Decimal {
+    #[inline]
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Owned(Value::Decimal(*self)))
+    }
+}
+
+/// Reads `DECIMAL` and integer columns exactly. Floating point columns are
+/// rejected with [`FromSqlError::InvalidType`]; cast them to `DECIMAL` in
+/// SQL if the rounding is acceptable.
+#[cfg(feature = "decimal")]
+impl FromSql for Decimal {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Decimal(d) => Ok(d),
+            ValueRef::Text(_) => value
+                .as_str()?
+                .parse()
+                .map_err(|e| FromSqlError::Other(Box::new(e))),
+            ValueRef::Float(_) | ValueRef::Double(_) => Err(FromSqlError::InvalidType),
+            _ => {
+                let i = HugeInt::column_result(value)?.0;
+                Decimal::try_from_i128_with_scale(i, 0).map_err(|_| FromSqlError::OutOfRange(i))
+            }
+        }
+    }
+}
+
+/// A `HUGEINT` (128-bit integer) read without going through floating point.
+///
+/// Unlike `i128`'s [`FromSql`], which also accepts `DOUBLE` and `DECIMAL`
+/// columns and truncates them, `HugeInt` only accepts integer columns and
+/// whole-valued decimals.
+#[cfg(feature = "hugeint")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HugeInt(pub i128);
+
+#[cfg(feature = "hugeint")]
+impl From<i128> for HugeInt {
+    fn from(i: i128) -> Self {
+        Self(i)
+    }
+}
+
+#[cfg(feature = "hugeint")]
+impl From<HugeInt> for i128 {
+    fn from(h: HugeInt) -> Self {
+        h.0
+    }
+}
+
+#[cfg(feature = "hugeint")]
+impl ToSql for HugeInt {
+    #[inline]
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Owned(Value::HugeInt(self.0)))
+    }
+}
+
+#[cfg(feature = "hugeint")]
+impl FromSql for HugeInt {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let i = match value {
+            ValueRef::TinyInt(i) => i128::from(i),
+            ValueRef::SmallInt(i) => i128::from(i),
+            ValueRef::Int(i) => i128::from(i),
+            ValueRef::BigInt(i) => i128::from(i),
+            ValueRef::HugeInt(i) => i,
+            ValueRef::UTinyInt(i) => i128::from(i),
+            ValueRef::USmallInt(i) => i128::from(i),
+            ValueRef::UInt(i) => i128::from(i),
+            ValueRef::UBigInt(i) => i128::from(i),
+            ValueRef::Decimal(d) if d.fract().is_zero() => d.mantissa() / 10i128.pow(d.scale()),
+            ValueRef::Text(_) => value
+                .as_str()?
+                .parse()
+                .map_err(|e| FromSqlError::Other(Box::new(e)))?,
+            _ => return Err(FromSqlError::InvalidType),
+        };
+        Ok(Self(i))
+    }
+}
+
+#[cfg(all(test, feature = "decimal"))]
+mod test {
+    use super::*;
+    use crate::Connection;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_decimal_round_trip_is_exact() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch("CREATE TABLE ledger (amount DECIMAL(38, 10))")?;
+
+        let amounts = [
+            "0.1",
+            "0.2",
+            "12345678901234567.8901234567",
+            "-0.0000000001",
+        ];
+        for amount in amounts {
+            db.execute(
+                "INSERT INTO ledger VALUES (?)",
+                [Decimal::from_str(amount).unwrap()],
+            )?;
+        }
+
+        let mut stmt = db.prepare("SELECT amount FROM ledger")?;
+        let read: Vec<Decimal> = stmt.query_map([], |r| r.get(0))?.collect::<Result<_>>()?;
+        let expected: Vec<Decimal> = amounts
+            .iter()
+            .map(|a| Decimal::from_str(a).unwrap())
+            .collect();
+        assert_eq!(read, expected);
+
+        // 0.1 + 0.2 is exact in DECIMAL, unlike f64
+        let sum: Decimal = db.query_row(
+            "SELECT SUM(amount) FROM ledger WHERE amount BETWEEN 0 AND 1",
+            [],
+            |r| r.get(0),
+        )?;
+        assert_eq!(sum, Decimal::from_str("0.3").unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decimal_rejects_floating_point() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        assert!(db
+            .query_row("SELECT 0.1::DOUBLE", [], |r| r.get::<_, Decimal>(0))
+            .is_err());
+        let from_int: Decimal = db.query_row("SELECT 42::BIGINT", [], |r| r.get(0))?;
+        assert_eq!(from_int, Decimal::from(42));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hugeint_round_trip() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch("CREATE TABLE big (h HUGEINT)")?;
+        for h in [i128::MAX, i128::MIN + 1, 0, -1] {
+            db.execute("INSERT INTO big VALUES (?)", [HugeInt(h)])?;
+            let read: HugeInt = db.query_row("SELECT h FROM big", [], |r| r.get(0))?;
+            assert_eq!(read, HugeInt(h));
+            db.execute_batch("DELETE FROM big")?;
+        }
+
+        // Doubles are never truncated into a HUGEINT
+        assert!(db
+            .query_row("SELECT 1.5::DOUBLE", [], |r| r.get::<_, HugeInt>(0))
+            .is_err());
+        let exact: HugeInt = db.query_row("SELECT 7.00::DECIMAL(4, 2)", [], |r| r.get(0))?;
+        assert_eq!(exact, HugeInt(7));
+        Ok(())
+    }
+}
@@ -74,6 +74,7 @@
 //!
 //! At least one binary must be present for validation to succeed.
 
+use crate::error::FrozenDuckdbError;
 use anyhow::Result;
 use std::env;
 use std::path::Path;
@@ -247,7 +248,9 @@ pub fn get_include_dir() -> Option<String> {
 /// load or execute the binaries. It's safe to call even if the binaries
 /// are corrupted or incompatible with the current system.
 pub fn validate_binary() -> Result<()> {
-    let lib_dir = get_lib_dir().ok_or_else(|| anyhow::anyhow!("DUCKDB_LIB_DIR not set"))?;
+    let lib_dir = get_lib_dir().ok_or_else(|| {
+        FrozenDuckdbError::EnvironmentNotConfigured("DUCKDB_LIB_DIR not set".to_string())
+    })?;
 
     let lib_path = Path::new(&lib_dir);
 
@@ -258,10 +261,11 @@ pub fn validate_binary() -> Result<()> {
     if x86_64_binary.exists() || arm64_binary.exists() {
         Ok(())
     } else {
-        Err(anyhow::anyhow!(
+        Err(FrozenDuckdbError::BinaryValidation(format!(
             "No frozen DuckDB binary found in {}",
             lib_dir
         ))
+        .into())
     }
 }
 
@@ -0,0 +1,251 @@
+//! # Typed Errors for Frozen DuckDB
+//!
+//! Methods of [`DatasetManager`] and [`FlockManager`] that can fail in a
+//! way a caller may want to handle return a [`FrozenDuckdbError`], so it
+//! can be matched directly: converting, attaching, sniffing CSV files,
+//! loading extensions, and every model call. DuckDB and file system
+//! failures surface as [`FrozenDuckdbError::Database`] and
+//! [`FrozenDuckdbError::Io`].
+//!
+//! [`FrozenDuckdbError::Other`] is the catch-all for failures a method
+//! added context to ("Failed to convert data.csv: …"). Unwrapping those
+//! to their cause would lose the message, so they keep it, and
+//! [`exit_code`] still finds a typed cause underneath.
+//!
+//! The remaining modules and the commands return [`anyhow::Result`], with
+//! these errors underneath their context; [`exit_code`] finds them there,
+//! and so does [`anyhow::Error::downcast_ref`]:
+//!
+//! | Variant | Exit code |
+//! |---------|-----------|
+//! | [`FrozenDuckdbError::NotFound`] | 1 |
+//! | [`FrozenDuckdbError::UnsupportedFormat`] | 1 |
+//! | [`FrozenDuckdbError::UnsupportedConversion`] | 1 |
+//! | [`FrozenDuckdbError::ExtensionUnavailable`] | 1 |
+//! | [`FrozenDuckdbError::Database`] | 1 |
+//! | [`FrozenDuckdbError::Io`] | 1 |
+//! | [`FrozenDuckdbError::EnvironmentNotConfigured`] | 2 |
+//! | [`FrozenDuckdbError::Build`] | 3 |
+//! | [`FrozenDuckdbError::BinaryValidation`] | 3 |
+//! | [`FrozenDuckdbError::FlockNotReady`] | 4 |
+//! | [`FrozenDuckdbError::MemoryBudgetExceeded`] | 5 |
+//! | [`FrozenDuckdbError::Other`] | That of the error it wraps |
+//!
+//! A [`BuildError`] from `frozen-duckdb-builder` is also exit code 3, and
+//! any other error is exit code 1. CLI commands never exit the process
+//! themselves: every failure is returned to `main`, which exits with
+//! [`exit_code`] after flushing telemetry.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use frozen_duckdb::cli::dataset_manager::DatasetManager;
+//! use frozen_duckdb::error::FrozenDuckdbError;
+//!
+//! let manager = DatasetManager::new()?;
+//! match manager.attach("backup.duckdb", "backup") {
+//!     Err(FrozenDuckdbError::NotFound { path, .. }) => {
+//!         eprintln!("No backup at {}", path.display())
+//!     }
+//!     other => other?,
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! [`DatasetManager`]: crate::cli::DatasetManager
+//! [`FlockManager`]: crate::cli::FlockManager
+
+use frozen_duckdb_builder::BuildError;
+use std::io;
+use std::path::PathBuf;
+
+/// Failures of the frozen-duckdb library and CLI.
+#[derive(Debug, thiserror::Error)]
+pub enum FrozenDuckdbError {
+    /// An input file or database doesn't exist
+    #[error("{what} not found: {}", path.display())]
+    NotFound {
+        /// What was looked for, e.g. "CSV file"
+        what: String,
+        /// Path that doesn't exist
+        path: PathBuf,
+    },
+    /// A file format that can't be read or written
+    #[error("Unsupported {kind} format: {format} (available: {available})")]
+    UnsupportedFormat {
+        /// "input" or "output"
+        kind: &'static str,
+        /// Format that was requested
+        format: String,
+        /// Comma-separated supported formats
+        available: String,
+    },
+    /// Two formats that can't be converted into each other
+    #[error("Unsupported conversion: {from} to {to}")]
+    UnsupportedConversion {
+        /// Input format
+        from: String,
+        /// Output format
+        to: String,
+    },
+    /// A DuckDB extension is neither built in nor installable
+    #[error("The {extension} extension is not available: {reason}")]
+    ExtensionUnavailable {
+        /// Extension name, e.g. "spatial"
+        extension: String,
+        /// Why it couldn't be loaded, and how to fix it
+        reason: String,
+    },
+    /// The Flock extension isn't loaded, so LLM commands can't run
+    #[error("Flock extension not available. Run 'frozen-duckdb flock-setup' first.")]
+    FlockNotReady,
+    /// `DUCKDB_LIB_DIR` or `DUCKDB_INCLUDE_DIR` is missing
+    #[error("Environment not configured: {0}")]
+    EnvironmentNotConfigured(String),
+    /// A command's peak memory was over its `--memory-budget`
+    #[error(
+        "{what} used {} of memory, over its {} budget",
+        crate::memory::format_bytes(*.peak),
+        crate::memory::format_bytes(*.budget)
+    )]
+    MemoryBudgetExceeded {
+        /// Command or benchmark that was measured
+        what: String,
+        /// Peak resident set size in bytes
+        peak: u64,
+        /// Budget in bytes
+        budget: u64,
+    },
+    /// The frozen DuckDB binary is missing or unusable
+    #[error("Binary validation failed: {0}")]
+    BinaryValidation(String),
+    /// The DuckDB library couldn't be provided
+    #[error(transparent)]
+    Build(#[from] BuildError),
+    /// DuckDB rejected a statement
+    #[error(transparent)]
+    Database(#[from] crate::duckdb::Error),
+    /// Reading or writing a file failed
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// Any other failure, with its context
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl From<anyhow::Error> for FrozenDuckdbError {
+    /// Unwraps an error raised as a typed, DuckDB or IO error that had no
+    /// context added since; anything else becomes [`FrozenDuckdbError::Other`].
+    fn from(error: anyhow::Error) -> Self {
+        let outer: &(dyn std::error::Error + Send + Sync + 'static) = error.as_ref();
+        if outer.is::<Self>() {
+            error.downcast().unwrap_or_else(Self::Other)
+        } else if outer.is::<crate::duckdb::Error>() {
+            error
+                .downcast()
+                .map(Self::Database)
+                .unwrap_or_else(Self::Other)
+        } else if outer.is::<io::Error>() {
+            error.downcast().map(Self::Io).unwrap_or_else(Self::Other)
+        } else {
+            Self::Other(error)
+        }
+    }
+}
+
+impl FrozenDuckdbError {
+    /// Process exit code the CLI uses for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::EnvironmentNotConfigured(_) => 2,
+            Self::Build(_) | Self::BinaryValidation(_) => 3,
+            Self::FlockNotReady => 4,
+            Self::MemoryBudgetExceeded { .. } => 5,
+            Self::Other(error) => exit_code(error),
+            _ => 1,
+        }
+    }
+}
+
+/// Process exit code for `error`, from the first [`FrozenDuckdbError`] or
+/// [`BuildError`] in its chain; 1 if there is none.
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    error
+        .chain()
+        .find_map(|cause| {
+            if let Some(error) = cause.downcast_ref::<FrozenDuckdbError>() {
+                Some(error.exit_code())
+            } else {
+                cause.downcast_ref::<BuildError>().map(|_| 3)
+            }
+        })
+        .unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(FrozenDuckdbError::FlockNotReady.exit_code(), 4);
+        assert_eq!(
+            FrozenDuckdbError::EnvironmentNotConfigured("DUCKDB_LIB_DIR not set".into())
+                .exit_code(),
+            2
+        );
+        assert_eq!(
+            FrozenDuckdbError::UnsupportedConversion {
+                from: "csv".into(),
+                to: "csv".into(),
+            }
+            .exit_code(),
+            1
+        );
+
+        let build = anyhow::Error::from(BuildError::CompileFailed("no cmake".into()));
+        assert_eq!(exit_code(&build), 3);
+        assert_eq!(exit_code(&anyhow::anyhow!("disk full")), 1);
+    }
+
+    #[test]
+    fn test_anyhow_errors_are_unwrapped_without_context() {
+        let typed = |error: anyhow::Error| -> Result<(), FrozenDuckdbError> {
+            Err::<(), _>(error)?;
+            Ok(())
+        };
+        let error = typed(anyhow::Error::from(FrozenDuckdbError::FlockNotReady));
+        assert!(matches!(error, Err(FrozenDuckdbError::FlockNotReady)));
+        let error = error.context("Failed to summarize").unwrap_err();
+        assert_eq!(exit_code(&error), 4);
+        let missing = io::Error::new(io::ErrorKind::NotFound, "gone");
+        assert!(matches!(
+            typed(missing.into()),
+            Err(FrozenDuckdbError::Io(_))
+        ));
+
+        let other = typed(anyhow::anyhow!("disk full").context("Failed to write out.csv"));
+        let other = other.unwrap_err();
+        assert!(matches!(other, FrozenDuckdbError::Other(_)));
+        assert_eq!(format!("{:#}", other), "Failed to write out.csv: disk full");
+        assert_eq!(other.exit_code(), 1);
+    }
+
+    #[test]
+    fn test_exit_code_looks_through_context() {
+        let error = Err::<(), _>(FrozenDuckdbError::FlockNotReady)
+            .context("Failed to filter reviews.csv")
+            .unwrap_err();
+        assert_eq!(exit_code(&error), 4);
+        assert!(matches!(
+            error.downcast_ref::<FrozenDuckdbError>(),
+            Some(FrozenDuckdbError::FlockNotReady)
+        ));
+        assert_eq!(
+            format!("{:#}", error),
+            "Failed to filter reviews.csv: Flock extension not available. \
+             Run 'frozen-duckdb flock-setup' first."
+        );
+    }
+}
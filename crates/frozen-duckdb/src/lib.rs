@@ -58,6 +58,12 @@
 //! - **arm64/aarch64**: Uses `libduckdb_arm64.dylib` (50MB)
 //! - **Manual override**: Set `ARCH` environment variable to force selection
 //!
+//! ## WebAssembly
+//!
+//! There is no DuckDB to link on `wasm32-unknown-unknown`. The crate still
+//! compiles there, to the subset described in [`wasm`], so crates shared
+//! with a browser build can depend on it unconditionally.
+//!
 //! ## Performance Benchmarks
 //!
 //! | Build Type | Before (Source) | After (Pre-built) | Improvement |
@@ -111,6 +117,8 @@
 //! - **Comprehensive testing**: Unit tests, integration tests, and property tests
 //! - **Error handling**: Clear error messages with actionable guidance
 //! - **Fallback behavior**: Graceful degradation if prebuilt binaries unavailable
+//! - **Threads**: `Connection` is `Send` but not `Sync`; give each thread its
+//!   own connection with `Connection::try_clone` (see `examples/worker_pool.rs`)
 //!
 //! ## Troubleshooting
 //!
@@ -139,19 +147,70 @@
 //! 3. **Update documentation**: Keep examples and benchmarks current
 //! 4. **Validate architecture support**: Test on both x86_64 and arm64
 
+// Modules that need the native DuckDB library are left out of wasm32
+// builds; see `wasm` for the subset those get instead
+
 // Re-export modules from separate files
+#[cfg(not(target_arch = "wasm32"))]
 pub mod architecture;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod benchmark;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod env_setup;
 
+// Typed errors and CLI exit codes
+#[cfg(not(target_arch = "wasm32"))]
+pub mod error;
+#[cfg(not(target_arch = "wasm32"))]
+pub use error::FrozenDuckdbError;
+
 // Re-export CLI modules
+#[cfg(not(target_arch = "wasm32"))]
 pub mod cli;
 
+// Text processing utilities (chunking) for LLM commands
+pub mod text;
+
+// Stub API and feature checks for wasm32 builds
+pub mod wasm;
+
+// Appender-based bulk inserts with a flush interval
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ingest;
+
 // Re-export our duckdb module (adapted from duckdb-rs)
+#[cfg(not(target_arch = "wasm32"))]
 pub mod duckdb;
 
+// Runtime report of available extensions and ICU support
+#[cfg(not(target_arch = "wasm32"))]
+pub mod capabilities;
+#[cfg(not(target_arch = "wasm32"))]
+pub use capabilities::{capabilities, Capabilities};
+
+// Safe wrappers for user-defined table functions
+#[cfg(all(not(target_arch = "wasm32"), feature = "vtab"))]
+pub mod vtab;
+
+// Closure-based scalar UDF registration
+#[cfg(all(not(target_arch = "wasm32"), feature = "vscalar"))]
+pub mod scalar;
+
+// Declarative data validation rules (expectations)
+#[cfg(not(target_arch = "wasm32"))]
+pub mod validation;
+
+// DuckDB query profiles and flame graph reports
+#[cfg(not(target_arch = "wasm32"))]
+pub mod profiling;
+
+// Peak memory tracking and budgets
+#[cfg(not(target_arch = "wasm32"))]
+pub mod memory;
+
 // Re-export duckdb-rs API for drop-in replacement compatibility
 // This enables frozen-duckdb to be a true drop-in replacement
+#[cfg(not(target_arch = "wasm32"))]
 pub use duckdb::{
     Connection, Config, Statement, Row, Rows, Result as DuckDBResult,
     params, params_from_iter, 
@@ -160,7 +219,7 @@ pub use duckdb::{
     // Error types
     Error as DuckDBError,
     // Transaction support
-    Transaction,
+    Transaction, Savepoint,
     // Appender for bulk inserts
     Appender,
     // Arrow integration
@@ -169,7 +228,17 @@ pub use duckdb::{
 };
 
 // Re-export types from duckdb::types for convenience
+#[cfg(not(target_arch = "wasm32"))]
 pub use duckdb::types::{FromSql, Value, Type};
+#[cfg(not(target_arch = "wasm32"))]
+pub use duckdb::types;
 
 // Re-export Result type for convenience (DuckDB's Result, not anyhow)
+#[cfg(not(target_arch = "wasm32"))]
 pub type Result<T> = DuckDBResult<T>;
+
+// Without DuckDB, `Connection` never opens and queries can't be written
+#[cfg(target_arch = "wasm32")]
+pub use wasm::{Connection, Error as DuckDBError};
+#[cfg(target_arch = "wasm32")]
+pub type Result<T> = std::result::Result<T, DuckDBError>;
@@ -40,8 +40,32 @@
 //! # Generate embeddings for semantic search
 //! frozen-duckdb embed --text "Python programming language"
 //!
+//! # Build a resumable embedding index for a large corpus
+//! frozen-duckdb index --corpus documents.txt --index embeddings.duckdb --resume
+//!
 //! # Perform semantic search
 //! frozen-duckdb search --query "machine learning" --corpus documents.txt
+//!
+//! # Estimate tokens and run time before summarizing a large corpus
+//! frozen-duckdb summarize --input documents/ --strategy map --estimate
+//!
+//! # Check filter criteria against a labeled sample
+//! frozen-duckdb eval --input labeled.csv --label-column truth --criteria "Is this valid Python code?"
+//!
+//! # Add a named model alias and use it
+//! frozen-duckdb models add fast llama3.2:1b
+//! frozen-duckdb complete --prompt "Explain recursion" --model fast
+//!
+//! # Record every prompt and response for compliance, then export them
+//! frozen-duckdb audit enable --policy full
+//! frozen-duckdb audit export --output audit.parquet --since 7d
+//!
+//! # Throttle LLM calls against a shared Ollama server
+//! frozen-duckdb --rate-limit 2 --max-in-flight 1 filter --input items.txt --criteria "is about Rust"
+//!
+//! # Record LLM responses once, then replay them without a model
+//! FROZEN_DUCKDB_LLM_MODE=record frozen-duckdb summarize --input notes.txt
+//! FROZEN_DUCKDB_LLM_MODE=replay frozen-duckdb summarize --input notes.txt
 //! ```
 //!
 //! ## Environment Setup
@@ -75,296 +99,2505 @@
 //! - **Exit code 2**: Environment not configured
 //! - **Exit code 3**: Binary validation failed
 //! - **Exit code 4**: Flock extension not available
+//! - **Exit code 5**: Peak memory over `--memory-budget`
+//!
+//! Errors returned from a command are mapped to these codes by
+//! [`frozen_duckdb::error::exit_code`].
+//!
+//! ## Output
+//!
+//! Command results are written to stdout; logs, progress bars, and errors
+//! go to stderr. `--quiet`, `--no-emoji`, and `NO_COLOR` control the
+//! diagnostics (see [`frozen_duckdb::cli::output`]).
+//! `--track-memory` adds a summary line with the command's elapsed time
+//! and peak memory (see [`frozen_duckdb::memory`]).
 
 use anyhow::{Context, Result};
-use clap::Parser;
-use frozen_duckdb::cli::commands::{Cli, Commands};
-use frozen_duckdb::cli::dataset_manager::DatasetManager;
-use frozen_duckdb::cli::flock_manager::FlockManager;
+use clap::{CommandFactory, FromArgMatches};
+use frozen_duckdb::benchmark::{
+    measure_export_throughput, measure_insert_throughput, measure_query_latency, BenchmarkStats,
+};
+use frozen_duckdb::cli::audit_log::{
+    export as export_audit_log, AuditConfig, AuditLog, AuditPolicy, AuditSink, ExportFilter,
+};
+use frozen_duckdb::cli::build_stats::BuildMetrics;
+use frozen_duckdb::cli::catalog::{download_name, DatasetCatalog};
+use frozen_duckdb::cli::commands::{
+    AuditAction, CacheAction, CacheArgs, CatalogAction, Cli, Commands, ContextArgs, JobsAction,
+    LineageAction, LineageArgs, ModelsAction, ReshapeAction, SecretsAction, SessionAction,
+    SnapshotAction, ViewsAction, VssAction,
+};
+use frozen_duckdb::cli::clustering::{
+    cluster_index, export_clusters, ClusterLabeler, FlockLabeler, KMeansOptions,
+};
+use frozen_duckdb::cli::config::{CliConfig, ModelAlias};
+use frozen_duckdb::cli::dataset_manager::{
+    detect_format, split_statements, ConvertOptions, DatasetManager,
+};
+use frozen_duckdb::cli::dedupe::{dedupe, DedupeOptions};
+use frozen_duckdb::cli::embedding_backends::EmbeddingBackend;
+use frozen_duckdb::cli::embedding_index::{
+    chunk_documents, load_corpus, Embedder, EmbeddingIndex, IndexOptions,
+};
+use frozen_duckdb::cli::eval::{
+    evaluate, evaluate_retrieval, load_labeled, load_retrieval_samples, FlockClassifier,
+    IndexRetriever,
+};
+use frozen_duckdb::cli::extraction::{
+    extract_entities, EntitySpec, ExtractOptions, FlockExtractor,
+};
+use frozen_duckdb::cli::federation::{
+    self, RemoteKind, SecretSpec, SECRET_PASSWORD_ENV,
+};
+use frozen_duckdb::cli::filter_checkpoint::{partial_path, FilterCheckpoint};
+use frozen_duckdb::cli::flock_manager::{
+    estimate_completion, estimate_summary, FlockManager, DEFAULT_OLLAMA_URL, OLLAMA_URL_ENV,
+};
+use frozen_duckdb::cli::fusion::FusionMethod;
+use frozen_duckdb::cli::guardrails::Guardrails;
+use frozen_duckdb::cli::image_input::ImageSource;
+use frozen_duckdb::cli::ingest::IncrementalOptions;
+use frozen_duckdb::cli::jobs::{execute_job, Job, JobFile, JobHistory};
+use frozen_duckdb::cli::join::{join_files, JoinHow, JoinKey};
+use frozen_duckdb::cli::language::{
+    detect_languages, translate, FlockLanguageModel, Language, LanguageModel, LanguageOptions,
+};
+use frozen_duckdb::cli::lakehouse::{rewrite_table_urls, table_format};
+use frozen_duckdb::cli::lineage::{self, embed_in_parquet, Lineage};
+use frozen_duckdb::cli::llm_recording::{
+    LlmMode, ResponseRecorder, DEFAULT_STORE_PATH, LLM_MODE_ENV, LLM_STORE_ENV,
+};
+use frozen_duckdb::cli::masking::{mask, MaskConfig, MASK_SALT_ENV};
+use frozen_duckdb::cli::materialized_views::ViewRegistry;
+use frozen_duckdb::cli::merge::{is_file_target, WhenMatched, WhenNotMatched};
+use frozen_duckdb::cli::multi_query::MultiQuery;
+use frozen_duckdb::cli::output::{mark, plain, quiet, OutputOptions};
+use frozen_duckdb::cli::parquet_parts::SplitBy;
+use frozen_duckdb::cli::pool::PoolOptions;
+use frozen_duckdb::cli::progress::ProgressBar;
+use frozen_duckdb::cli::projection::{project_index, write_points, ProjectionMethod};
+use frozen_duckdb::cli::query_cache::{cache_enabled, QueryCache};
+use frozen_duckdb::cli::rag::{rag_prompt, relevant_turns, retrieval_query, SessionStore};
+use frozen_duckdb::cli::reshape::{pivot, unpivot, PivotOptions, UnpivotOptions};
+use frozen_duckdb::cli::response_cache::parse_ttl;
+use frozen_duckdb::cli::result_table::{export_rows, OutputFormat, SUMMARY_COLUMNS};
+use frozen_duckdb::cli::schema_docs::{
+    document_database, DocumentOptions, FlockDescriber, SchemaDescriber,
+};
+use frozen_duckdb::cli::script::{run_script, OnError, ScriptOptions, TransactionMode};
+use frozen_duckdb::cli::server::{serve, Protocol, QueryLimits, ServeOptions, SERVE_TOKEN_ENV};
+use frozen_duckdb::cli::sharing::{self, SharePolicy};
+use frozen_duckdb::cli::similar_items::{
+    export_neighbors, load_item_ids, similar_items, SimilarItemsOptions,
+};
+use frozen_duckdb::cli::snapshot::{SnapshotMethod, SnapshotStore};
+use frozen_duckdb::cli::sql_models::{ModelGraph, ModelStatus};
+use frozen_duckdb::cli::telemetry::{otlp_endpoint, OtlpExporter};
+use frozen_duckdb::cli::throughput::ThroughputStore;
+use frozen_duckdb::cli::watch::watch;
+use frozen_duckdb::error::{exit_code, FrozenDuckdbError};
+use frozen_duckdb::memory::{budget_from, format_bytes, parse_size, tracking_enabled, MemoryTracker};
+use frozen_duckdb::profiling::{profile, report_paths, QueryProfile};
+use frozen_duckdb::text::tokens::count_tokens;
+use frozen_duckdb::validation::{validate_file, RuleSet, Severity};
 use serde_json::{self, Value};
 use std::io;
 use std::path::Path;
-use tracing::{error, info};
+use std::time::{Duration, Instant};
+use frozen_duckdb_builder::bundle::{bundle, BundleOptions};
+use tracing::{error, info, info_span, warn};
+
+
+fn main() {
+    if let Err(error) = run() {
+        eprintln!("Error: {}", plain(&format!("{:?}", error)));
+        std::process::exit(exit_code(&error));
+    }
+}
+
+fn run() -> Result<()> {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    let exporter = otlp_endpoint(cli.otlp_endpoint.as_deref())
+        .map(|endpoint| OtlpExporter::start(&endpoint))
+        .transpose()?;
+
+    // Logs go to stderr, so stdout only carries command results
+    OutputOptions::from_flags(cli.quiet, cli.no_emoji).init(cli.verbose, exporter.as_ref());
+
+    let budget = budget_from(cli.memory_budget.as_deref())?;
+    let tracker = (tracking_enabled(cli.track_memory) || budget.is_some())
+        .then(|| MemoryTracker::start(matches.subcommand_name().unwrap_or("frozen-duckdb")));
+
+    let command = matches.subcommand_name().unwrap_or("frozen-duckdb");
+    let result = info_span!("command", name = command).in_scope(|| execute(cli));
+    // Flush spans before reporting the command's outcome
+    if let Some(exporter) = exporter {
+        if let Err(e) = exporter.shutdown() {
+            warn!("⚠️  {:#}", e);
+        }
+    }
+
+    // Failed commands are reported too, since memory is often why they failed
+    if let Some(tracker) = tracker {
+        let report = tracker.finish();
+        if !quiet() {
+            eprintln!("{}", plain(&report.format_summary()));
+        }
+        if let (Ok(()), Some(budget)) = (&result, budget) {
+            report.check_budget(budget)?;
+        }
+    }
+    result
+}
+
+fn execute(cli: Cli) -> Result<()> {
+    // The config file is only read by LLM commands, so a bad config file
+    // doesn't break dataset commands
+    let (rate_limit, max_in_flight) = (cli.rate_limit, cli.max_in_flight);
+    let open_flock = |command: &str| -> Result<FlockManager> {
+        let config = CliConfig::load()?;
+        let limits = config
+            .rate_limits()?
+            .with_overrides(rate_limit, max_in_flight)?;
+        let manager = FlockManager::new()?.with_rate_limits(limits)?;
+        if let Some(ollama_url) = config.ollama_url() {
+            manager.create_ollama_secret(ollama_url);
+        }
+        let mut manager = manager.with_model_aliases(&config.models()?)?;
+        let mode = match std::env::var(LLM_MODE_ENV) {
+            Ok(mode) => LlmMode::parse(&mode)?,
+            Err(_) => LlmMode::Live,
+        };
+        if mode != LlmMode::Live {
+            let store = std::env::var(LLM_STORE_ENV).unwrap_or_else(|_| DEFAULT_STORE_PATH.into());
+            info!("🎞️  LLM {} mode using {}", mode.as_str(), store);
+            manager = manager.with_recorder(ResponseRecorder::open(mode, &store)?);
+        }
+        Ok(match config.audit()? {
+            Some(audit) => manager.with_audit_log(AuditLog::open(&audit, command)?),
+            None => manager,
+        })
+    };
+
+    match cli.command {
+        // === DATASET MANAGEMENT COMMANDS ===
+        Commands::Download {
+            dataset,
+            output_dir,
+            format,
+            scale_factor,
+            compression,
+            force,
+            performance,
+        } => {
+            if !matches!(dataset.as_str(), "chinook" | "tpch") {
+                anyhow::bail!("Unknown dataset: {} (available: chinook, tpch)", dataset);
+            }
+
+            let mut dataset_manager = DatasetManager::new()?;
+            dataset_manager.set_parquet_compression(&compression)?;
+            dataset_manager.set_performance(&performance.options())?;
+            dataset_manager.download_cached(&dataset, &output_dir, &format, scale_factor, force)?;
+            if let Err(e) = register_download(&dataset_manager, &dataset, &output_dir, &format) {
+                warn!("⚠️  Couldn't register {} in the dataset catalog: {:#}", dataset, e);
+            }
+        }
+
+        Commands::Convert {
+            input,
+            output,
+            input_format,
+            output_format,
+            sheet,
+            geometry_column,
+            auto,
+            max_file_size,
+            rows_per_file,
+            register,
+            performance,
+            lineage,
+        } => {
+            let mut dataset_manager = DatasetManager::new()?;
+            dataset_manager.set_performance(&performance.options())?;
+            let (input, input_format) = resolve_cataloged(input, input_format)?;
+            let input_format = input_format
+                .or_else(|| table_format(&input).map(|format| format.as_str().to_string()));
+            let input_format = match input_format {
+                Some(format) => format,
+                None if auto => detect_format(&input)
+                    .with_context(|| format!("Can't detect the format of {}; pass --input-format", input))?
+                    .to_string(),
+                None => "csv".to_string(),
+            };
+            let split = SplitBy::from_options(
+                max_file_size.as_deref().map(parse_size).transpose()?,
+                rows_per_file,
+            )?;
+            let provenance = Lineage::new(
+                "convert",
+                &output,
+                &[&input],
+                serde_json::json!({
+                    "input_format": input_format,
+                    "output_format": output_format,
+                    "sheet": sheet,
+                    "geometry_column": geometry_column,
+                    "auto": auto,
+                    "max_file_size": max_file_size,
+                    "rows_per_file": rows_per_file,
+                }),
+            );
+            dataset_manager.convert_dataset_with(
+                &input,
+                &output,
+                &input_format,
+                &output_format,
+                &ConvertOptions {
+                    sheet,
+                    geometry_column,
+                    auto,
+                    split,
+                },
+            )?;
+            record_lineage(&dataset_manager, &provenance, &lineage)?;
+            if let Some(name) = register {
+                let path = match split {
+                    Some(_) => Path::new(&output).join("*.parquet").to_string_lossy().into_owned(),
+                    None => output,
+                };
+                let entry = dataset_manager.catalog_entry(&name, &path, &output_format)?;
+                DatasetCatalog::open_default()?.register(&entry)?;
+                info!("📚 Registered {} ({} rows) in the dataset catalog", name, entry.row_count);
+            }
+        }
+
+        Commands::Schema {
+            input,
+            input_format,
+            sheet,
+        } => {
+            let input_format = input_format
+                .or_else(|| table_format(&input).map(|format| format.as_str().to_string()))
+                .unwrap_or_else(|| "csv".to_string());
+            let dataset_manager = DatasetManager::new()?;
+            let schema =
+                dataset_manager.describe_dataset(&input, &input_format, sheet.as_deref())?;
+            println!("{}", schema.to_table());
+        }
+
+        Commands::Dedupe {
+            input,
+            key_columns,
+            output,
+            report,
+            semantic,
+            threshold,
+            model,
+            lineage,
+        } => {
+            let output = output.unwrap_or_else(|| sibling_path(&input, "deduped"));
+            let report = report.unwrap_or_else(|| sibling_path(&input, "dropped"));
+            let parameters = serde_json::json!({
+                "key_columns": key_columns,
+                "semantic": semantic,
+                "threshold": semantic.then_some(threshold),
+                "model": semantic.then_some(&model),
+            });
+            let options = DedupeOptions {
+                key_columns,
+                semantic_threshold: semantic.then_some(threshold),
+            };
+
+            let dataset_manager = DatasetManager::new()?;
+            let result = if semantic {
+                let embedding = CliConfig::load()?.embedding()?;
+                let flock_manager = match embedding.backend {
+                    EmbeddingBackend::Flock => {
+                        let flock_manager = open_flock("dedupe")?;
+                        require_flock(&flock_manager)?;
+                        Some(flock_manager)
+                    }
+                    _ => None,
+                };
+                let embedder = embedding.embedder(flock_manager.as_ref(), model, true)?;
+                dedupe(
+                    dataset_manager.connection(),
+                    &input,
+                    &output,
+                    &report,
+                    &options,
+                    Some(&embedder),
+                )?
+            } else {
+                dedupe::<Box<dyn Embedder>>(
+                    dataset_manager.connection(),
+                    &input,
+                    &output,
+                    &report,
+                    &options,
+                    None,
+                )?
+            };
+
+            info!(
+                "✅ {} rows in, {} rows out ({} exact, {} semantic duplicates dropped)",
+                result.input_rows,
+                result.output_rows,
+                result.exact_duplicates,
+                result.semantic_duplicates
+            );
+            info!("   Deduplicated data: {}", output);
+            info!("   Dropped rows report: {}", report);
+            for path in [&output, &report] {
+                let provenance = Lineage::new("dedupe", path, &[&input], parameters.clone());
+                record_lineage(&dataset_manager, &provenance, &lineage)?;
+            }
+        }
+
+        Commands::Query {
+            sql,
+            database,
+            attach,
+            format,
+            output,
+            transaction_size,
+            cache,
+            no_cache,
+            watch: watch_interval,
+            profile: profile_dir,
+            federation,
+            performance,
+        } => {
+            let mut dataset_manager = match &database {
+                Some(path) => DatasetManager::open(path)?,
+                None => DatasetManager::new()?,
+            };
+            dataset_manager.set_performance(&performance.options())?;
+            for (alias, path) in &attach {
+                dataset_manager.attach(path, alias)?;
+            }
+            for remote in federation.remotes() {
+                dataset_manager.attach_remote(remote)?;
+            }
+            if let Some(catalog) = DatasetCatalog::open_existing()? {
+                dataset_manager.attach_catalog(&catalog)?;
+            }
+            // 'delta://...' and 'iceberg://...' read lakehouse tables
+            let (sql, tables) = rewrite_table_urls(&sql);
+            for (table, location) in &tables {
+                dataset_manager.enable_table_format(*table, location)?;
+            }
+
+            #[cfg(feature = "vscalar")]
+            frozen_duckdb::scalar::register_builtins(dataset_manager.connection())?;
+
+            if let Some(interval) = watch_interval {
+                let interval = parse_ttl(&interval)
+                    .with_context(|| format!("Invalid --watch interval: {}", interval))?;
+                watch(&dataset_manager, &sql, interval.max(Duration::from_secs(1)))?;
+            } else if let Some(size) = transaction_size {
+                let report =
+                    dataset_manager.execute_in_transactions(&split_statements(&sql), size)?;
+                info!(
+                    "✅ Executed {} statements in {} transactions",
+                    report.statements, report.transactions
+                );
+            } else {
+                let print_results = || -> Result<()> {
+                    if let Some(path) = &output {
+                        dataset_manager.export_jsonl(&sql, path)?;
+                    } else if format == "jsonl" {
+                        dataset_manager.write_jsonl(&sql, io::stdout().lock())?;
+                    } else {
+                        // A cache hit runs no query, so there would be nothing to profile;
+                        // lakehouse tables and remote databases change without their
+                        // files being listed
+                        let output = if cache_enabled(cache, no_cache)
+                            && profile_dir.is_none()
+                            && tables.is_empty()
+                            && federation.remotes().next().is_none()
+                        {
+                            dataset_manager.run_query_cached(&sql, &QueryCache::new()?)?
+                        } else {
+                            dataset_manager.run_query(&sql)?
+                        };
+                        match format.as_str() {
+                            "json" => {
+                                println!("{}", serde_json::to_string_pretty(&output.to_json())?)
+                            }
+                            "csv" => println!("{}", output.to_csv()),
+                            _ => println!("{}", output.to_table()),
+                        }
+                    }
+                    Ok(())
+                };
+                match &profile_dir {
+                    Some(dir) => {
+                        let ((), report) = profile(
+                            dataset_manager.connection(),
+                            Path::new(dir),
+                            "query",
+                            print_results,
+                        )?;
+                        log_profile(&report, Path::new(dir), "query");
+                    }
+                    None => print_results()?,
+                }
+            }
+        }
+
+        Commands::Ingest {
+            input,
+            input_format,
+            database,
+            table,
+            incremental,
+            key_columns,
+            watermark_column,
+            full_refresh,
+            performance,
+        } => {
+            let mut dataset_manager = DatasetManager::open(&database)?;
+            dataset_manager.set_performance(&performance.options())?;
+            let (input, input_format) = resolve_cataloged(input, input_format)?;
+            let input_format = match input_format
+                .or_else(|| table_format(&input).map(|format| format.as_str().to_string()))
+            {
+                Some(format) => format,
+                None => detect_format(&input)
+                    .with_context(|| {
+                        format!("Can't detect the format of {}; pass --input-format", input)
+                    })?
+                    .to_string(),
+            };
+            let incremental = watermark_column
+                .filter(|_| incremental)
+                .map(|watermark_column| IncrementalOptions {
+                    key_columns,
+                    watermark_column,
+                });
+
+            // Watermarks are keyed on absolute paths, so runs from any
+            // directory continue where the last one stopped
+            let source = lineage::absolute(&input);
+            let target = format!("{}#{}", lineage::absolute(&database), table);
+            let catalog = match &incremental {
+                Some(_) => Some(DatasetCatalog::open_default()?),
+                None => None,
+            };
+            let watermark = match (&catalog, &incremental) {
+                (Some(catalog), Some(options)) => {
+                    if full_refresh && catalog.reset_watermark(&source, &target)? {
+                        info!("🔄 Forgot the watermark of {}; reading every row", input);
+                    }
+                    catalog.watermark(&source, &target, &options.watermark_column)?
+                }
+                _ => None,
+            };
+            if let (Some(value), Some(options)) = (&watermark, &incremental) {
+                info!(
+                    "⏩ Reading rows with {} above {}",
+                    options.watermark_column, value
+                );
+            }
+
+            let report = dataset_manager.ingest(
+                &input,
+                &input_format,
+                &table,
+                incremental.as_ref(),
+                watermark.as_deref(),
+            )?;
+            if let (Some(catalog), Some(options), Some(latest)) =
+                (&catalog, &incremental, &report.watermark)
+            {
+                catalog.set_watermark(&source, &target, &options.watermark_column, latest)?;
+            }
+            info!(
+                "✅ {} {} rows into {}.{} ({} inserted, {} updated)",
+                if report.created { "Loaded" } else { "Merged" },
+                report.rows_read,
+                database,
+                table,
+                report.inserted,
+                report.updated
+            );
+        }
+
+        Commands::Merge {
+            source,
+            target,
+            database,
+            key_columns,
+            when_matched,
+            when_not_matched,
+        } => {
+            let when_matched = WhenMatched::parse(&when_matched)?;
+            let when_not_matched = WhenNotMatched::parse(&when_not_matched)?;
+            let dataset_manager = match &database {
+                Some(path) => DatasetManager::open(path)?,
+                None if is_file_target(&target) => DatasetManager::new()?,
+                None => anyhow::bail!("--database is required when the target is a table"),
+            };
+            let report = if is_file_target(&target) {
+                dataset_manager.merge_into_file(
+                    &target,
+                    &source,
+                    &key_columns,
+                    when_matched,
+                    when_not_matched,
+                )?
+            } else {
+                dataset_manager.merge_into(
+                    &target,
+                    &source,
+                    &key_columns,
+                    when_matched,
+                    when_not_matched,
+                )?
+            };
+            info!(
+                "✅ Merged {} rows into {} with {}: {} inserted, {} updated, {} deleted",
+                report.source_rows,
+                target,
+                if report.on_conflict { "INSERT ... ON CONFLICT" } else { "MERGE INTO" },
+                report.inserted,
+                report.updated,
+                report.deleted
+            );
+        }
+
+        Commands::Run {
+            script,
+            database,
+            vars,
+            on_error,
+            transaction,
+            federation,
+            performance,
+        } => {
+            let sql = std::fs::read_to_string(&script)
+                .with_context(|| format!("Failed to read SQL script: {}", script))?;
+            let mut dataset_manager = match &database {
+                Some(path) => DatasetManager::open(path)?,
+                None => DatasetManager::new()?,
+            };
+            dataset_manager.set_performance(&performance.options())?;
+            for remote in federation.remotes() {
+                dataset_manager.attach_remote(remote)?;
+            }
+            let options = ScriptOptions {
+                variables: vars.into_iter().collect(),
+                on_error: OnError::parse(&on_error)?,
+                transaction: TransactionMode::parse(&transaction)?,
+            };
+
+            let report = run_script(dataset_manager.connection(), &sql, &options, |outcome| {
+                match &outcome.error {
+                    None => println!(
+                        "{} [{}] {:>10.1?}  {}",
+                        mark("✅", "ok  "),
+                        outcome.index,
+                        outcome.elapsed,
+                        outcome.summary()
+                    ),
+                    Some(e) => println!(
+                        "{} [{}] {:>10.1?}  {}\n   {}",
+                        mark("❌", "FAIL"),
+                        outcome.index,
+                        outcome.elapsed,
+                        outcome.summary(),
+                        e
+                    ),
+                }
+            })?;
+
+            println!(
+                "{} statements in {:.1?}: {} committed, {} failed, {} rolled back, {} skipped",
+                report.total,
+                report.elapsed,
+                report.committed(),
+                report.failed(),
+                report.rolled_back,
+                report.skipped()
+            );
+            if report.failed() > 0 {
+                anyhow::bail!("{} of {} statements failed", report.failed(), report.total);
+            }
+        }
+
+        Commands::BuildModels {
+            models,
+            database,
+            select,
+        } => {
+            let graph = ModelGraph::load(&models)?;
+            let selected = graph.select(&select)?;
+            let dataset_manager = DatasetManager::open(&database)?;
+
+            info!("🏗️  Building {} models into {}", selected.len(), database);
+            let results = graph.build(dataset_manager.connection(), &selected, |result| {
+                match &result.status {
+                    ModelStatus::Built { rows, elapsed } => println!(
+                        "{} {:<32} {:>10.1?}  {}",
+                        mark("✅", "ok  "),
+                        result.name,
+                        elapsed,
+                        rows.map(|n| format!("{} rows", n))
+                            .unwrap_or_else(|| "view".to_string())
+                    ),
+                    ModelStatus::Failed(e) => {
+                        println!("{} {:<32} {}", mark("❌", "FAIL"), result.name, e)
+                    }
+                    ModelStatus::Skipped(upstream) => println!(
+                        "{} {:<32} skipped ({} did not build)",
+                        mark("⏭️ ", "SKIP"),
+                        result.name,
+                        upstream
+                    ),
+                }
+            })?;
+
+            let built = results
+                .iter()
+                .filter(|r| matches!(r.status, ModelStatus::Built { .. }))
+                .count();
+            println!("{} of {} models built", built, results.len());
+            if built < results.len() {
+                anyhow::bail!(
+                    "{} of {} models failed to build",
+                    results.len() - built,
+                    results.len()
+                );
+            }
+        }
+
+        Commands::Share {
+            database,
+            policy,
+            output,
+            views,
+        } => {
+            let dataset_manager = DatasetManager::open(&database)?;
+            let conn = dataset_manager.connection();
+            let policy = SharePolicy::load(&policy)?;
+            let plan = sharing::plan(&policy, &sharing::source_tables(conn)?)?;
+            for (table, reason) in &plan.skipped {
+                warn!("⚠️  Not sharing {}: {}", table, reason);
+            }
+            if plan.shared.is_empty() {
+                anyhow::bail!("The policy shares no table of {}", database);
+            }
+
+            if views {
+                sharing::create_views(conn, &plan)?;
+            }
+            if let Some(output) = &output {
+                sharing::export(conn, &plan, output)?;
+                info!(
+                    "✅ {} can read it with: ATTACH '{}' AS {} (READ_ONLY)",
+                    plan.consumer, output, plan.consumer
+                );
+            }
+            if !views && output.is_none() {
+                print!("{}", plan.view_sql());
+            }
+        }
+
+        Commands::Mask {
+            input,
+            config,
+            output,
+            salt,
+            lineage,
+        } => {
+            let output = output.unwrap_or_else(|| sibling_path(&input, "masked"));
+            // The salt is a secret, so only the config file is recorded
+            let provenance = Lineage::new(
+                "mask",
+                &output,
+                &[&input],
+                serde_json::json!({ "config": config }),
+            );
+            let config = MaskConfig::load(&config)?;
+            let salt = salt
+                .or_else(|| std::env::var(MASK_SALT_ENV).ok())
+                .or_else(|| config.salt.clone())
+                .unwrap_or_default();
+            if salt.is_empty() {
+                warn!(
+                    "⚠️  No salt set (--salt or {}); hashed values can be guessed",
+                    MASK_SALT_ENV
+                );
+            }
+
+            let dataset_manager = DatasetManager::new()?;
+            let report = mask(dataset_manager.connection(), &input, &output, &config, &salt)?;
+            info!(
+                "✅ Masked {} columns in {} rows to {} ({} columns unchanged)",
+                report.masked_columns, report.rows, output, report.unchanged_columns
+            );
+            record_lineage(&dataset_manager, &provenance, &lineage)?;
+        }
+
+        Commands::Join {
+            left,
+            right,
+            on,
+            how,
+            output,
+            lineage,
+        } => {
+            let parameters = serde_json::json!({ "on": on, "how": how });
+            let keys = on
+                .iter()
+                .map(|key| JoinKey::parse(key))
+                .collect::<Result<Vec<_>>>()?;
+            let how = JoinHow::parse(&how)?;
+            let output = output.unwrap_or_else(|| sibling_path(&left, "joined"));
+
+            let dataset_manager = DatasetManager::new()?;
+            let report = join_files(dataset_manager.connection(), &left, &right, &keys, how, &output)?;
+
+            for cast in &report.cast_keys {
+                warn!("⚠️  Key types differ, compared as text: {}", cast);
+            }
+            println!(
+                "left:  {} rows, {} matched, {} unmatched",
+                report.left_rows,
+                report.left_matched,
+                report.left_unmatched()
+            );
+            if !report.left_unmatched_sample.is_empty() {
+                println!("       unmatched keys: {}", report.left_unmatched_sample.join(" | "));
+            }
+            println!(
+                "right: {} rows, {} matched, {} unmatched",
+                report.right_rows,
+                report.right_matched,
+                report.right_unmatched()
+            );
+            if !report.right_unmatched_sample.is_empty() {
+                println!("       unmatched keys: {}", report.right_unmatched_sample.join(" | "));
+            }
+            info!("✅ Wrote {} rows to {}", report.output_rows, output);
+            let provenance = Lineage::new("join", &output, &[&left, &right], parameters);
+            record_lineage(&dataset_manager, &provenance, &lineage)?;
+        }
+
+        Commands::Reshape { action } => {
+            let dataset_manager = DatasetManager::new()?;
+            let (output, rows) = match action {
+                ReshapeAction::Pivot {
+                    input,
+                    output,
+                    on,
+                    values,
+                    group_by,
+                    aggregate,
+                } => {
+                    let output = output.unwrap_or_else(|| sibling_path(&input, "pivoted"));
+                    let options = PivotOptions {
+                        on,
+                        values,
+                        group_by,
+                        aggregate,
+                    };
+                    let rows = pivot(dataset_manager.connection(), &input, &output, &options)?;
+                    (output, rows)
+                }
+                ReshapeAction::Unpivot {
+                    input,
+                    output,
+                    columns,
+                    keep,
+                    name,
+                    value,
+                    include_nulls,
+                } => {
+                    let output = output.unwrap_or_else(|| sibling_path(&input, "unpivoted"));
+                    let options = UnpivotOptions {
+                        columns,
+                        keep,
+                        name_column: name,
+                        value_column: value,
+                        include_nulls,
+                    };
+                    let rows = unpivot(dataset_manager.connection(), &input, &output, &options)?;
+                    (output, rows)
+                }
+            };
+            info!("✅ Wrote {} rows to {}", rows, output);
+        }
+
+        Commands::ValidateData {
+            rules,
+            input,
+            format,
+        } => {
+            let rules = RuleSet::load(&rules)?;
+            let dataset_manager = DatasetManager::new()?;
+            let report = validate_file(dataset_manager.connection(), &input, &rules)?;
+
+            match format.as_str() {
+                "json" => println!("{}", serde_json::to_string_pretty(&report.to_json())?),
+                "table" => {
+                    for result in &report.results {
+                        let mark = match (result.passed, result.severity) {
+                            (true, _) => mark("✅", "ok  "),
+                            (false, Severity::Warn) => mark("⚠️ ", "WARN"),
+                            (false, Severity::Error) => mark("❌", "FAIL"),
+                        };
+                        println!("{} {:<40} {}", mark, result.name, result.message);
+                    }
+                }
+                other => anyhow::bail!("Unknown format: {} (use json or table)", other),
+            }
+
+            if !report.passed() {
+                anyhow::bail!(
+                    "{} of {} checks failed",
+                    report.failures().count(),
+                    report.results.len()
+                );
+            }
+            info!("✅ All {} checks passed", report.results.len());
+        }
+
+        Commands::Info { format } => {
+            if format == "json" {
+                let capabilities = frozen_duckdb::capabilities()?;
+                println!("{}", serde_json::to_string_pretty(&capabilities.to_json())?);
+            } else {
+                let dataset_manager = DatasetManager::new()?;
+                dataset_manager.show_info()?;
+            }
+        }
+
+        Commands::Bundle {
+            target_dir,
+            executable,
+            library,
+            dry_run,
+        } => {
+            let report = bundle(&BundleOptions {
+                target_dir: target_dir.into(),
+                executable: executable.map(Into::into),
+                library: library.map(Into::into),
+                dry_run,
+            })?;
+
+            if dry_run {
+                info!("📝 Dry run, commands that would be run:");
+                for command in &report.commands {
+                    println!("{}", command);
+                }
+            } else {
+                info!("✅ Bundled library: {}", report.library.display());
+                if let Some(executable) = &report.executable {
+                    info!("✅ Patched executable: {}", executable.display());
+                }
+            }
+        }
+
+        Commands::Stats { format } => {
+            let metrics = BuildMetrics::open_default()?;
+            let imported = metrics.import_log(&BuildMetrics::default_log_path()?)?;
+            if imported > 0 {
+                info!("📥 Imported {} new build events", imported);
+            }
+
+            let summary = metrics.summary()?;
+            match format.as_str() {
+                "json" => println!("{}", serde_json::to_string_pretty(&summary.to_json())?),
+                _ => println!("{}", plain(&summary.format_report())),
+            }
+        }
+
+        // === FLOCK/LLM COMMANDS ===
+        Commands::FlockSetup {
+            ollama_url,
+            text_model,
+            embedding_model,
+            skip_verification,
+            aliases,
+            settings,
+        } => {
+            let settings = settings.settings()?;
+            let flock_manager = FlockManager::new()?;
+
+            // Check if Flock is ready before proceeding
+            if !flock_manager.is_flock_ready()? {
+                error!("   Make sure DuckDB with Flock extension is properly installed");
+                return Err(FrozenDuckdbError::FlockNotReady.into());
+            }
+
+            flock_manager.setup_ollama(
+                &ollama_url,
+                &text_model,
+                &embedding_model,
+                &settings,
+                skip_verification,
+            )?;
 
+            // Save the setup so later commands can re-register the models
+            let mut config = CliConfig::load()?;
+            config.set_ollama_url(&ollama_url);
+            for (name, model) in [("text_generator", &text_model), ("embedder", &embedding_model)] {
+                let mut alias = ModelAlias::ollama(name, model);
+                alias.settings = settings.clone();
+                config.add_model(alias);
+            }
+            for mut alias in aliases {
+                alias.settings = settings.clone();
+                flock_manager.register_model(&alias)?;
+                info!("✅ Created model: {} ({})", alias.name, alias.model);
+                config.add_model(alias);
+            }
+            config.save()?;
+        }
+
+        Commands::Models { action } => {
+            let mut config = CliConfig::load()?;
+            match action {
+                ModelsAction::List { format } => {
+                    let models = config.models()?;
+                    if format == "json" {
+                        let json_models: serde_json::Map<String, Value> = models
+                            .iter()
+                            .map(|m| {
+                                let entry = serde_json::json!({
+                                    "model": m.model,
+                                    "provider": m.provider,
+                                    "batch_size": m.settings.batch_size(),
+                                    "model_parameters": m.settings.model_parameters(),
+                                });
+                                (m.name.clone(), entry)
+                            })
+                            .collect();
+                        println!("{}", serde_json::to_string_pretty(&json_models)?);
+                    } else if models.is_empty() {
+                        info!("No model aliases configured. Run 'frozen-duckdb flock-setup' or 'frozen-duckdb models add'");
+                    } else {
+                        for m in models {
+                            let parameters = m
+                                .settings
+                                .model_parameters()
+                                .iter()
+                                .map(|(key, value)| format!("{}={}", key, value))
+                                .collect::<Vec<_>>()
+                                .join(" ");
+                            println!(
+                                "{:<20} {:<30} {:<10} batch={} {}",
+                                m.name,
+                                m.model,
+                                m.provider,
+                                m.settings.batch_size(),
+                                parameters
+                            );
+                        }
+                    }
+                }
+                ModelsAction::Add { name, model, provider, settings } => {
+                    config.add_model(ModelAlias {
+                        name: name.clone(),
+                        model: model.clone(),
+                        provider,
+                        settings: settings.settings()?,
+                    });
+                    config.save()?;
+                    info!("✅ Added model alias: {} ({})", name, model);
+                }
+                ModelsAction::Update { name, model, provider, settings, unset_params } => {
+                    let Some(mut alias) = config.model(&name)? else {
+                        anyhow::bail!(
+                            "Unknown model alias: {}. Add it with 'frozen-duckdb models add'",
+                            name
+                        );
+                    };
+                    if let Some(model) = model {
+                        alias.model = model;
+                    }
+                    if let Some(provider) = provider {
+                        alias.provider = provider;
+                    }
+                    for key in &unset_params {
+                        alias.settings.parameters.remove(key);
+                    }
+                    alias.settings.update(settings.settings()?);
+                    info!(
+                        "✅ Updated model alias: {} ({}, batch size {})",
+                        name,
+                        alias.model,
+                        alias.settings.batch_size()
+                    );
+                    config.add_model(alias);
+                    config.save()?;
+                }
+                ModelsAction::Remove { name } => {
+                    if !config.remove_model(&name) {
+                        anyhow::bail!("Unknown model alias: {}", name);
+                    }
+                    config.save()?;
+                    info!("✅ Removed model alias: {}", name);
+                }
+            }
+        }
+
+        Commands::Views { database, action } => {
+            let conn = frozen_duckdb::Connection::open(&database)
+                .with_context(|| format!("Failed to open database: {}", database))?;
+            let views = ViewRegistry::new(&conn)?;
+            match action {
+                ViewsAction::Define { name, sql } => {
+                    views.define(&name, &sql)?;
+                }
+                ViewsAction::Refresh { name: Some(name) } => {
+                    let rows = views.refresh(&name)?;
+                    info!("✅ Refreshed {} ({} rows)", name, rows);
+                }
+                ViewsAction::Refresh { name: None } => {
+                    for (name, rows) in views.refresh_all()? {
+                        info!("✅ Refreshed {} ({} rows)", name, rows);
+                    }
+                }
+                ViewsAction::List { format } => {
+                    let list = views.list()?;
+                    if format == "json" {
+                        let json: Vec<Value> = list
+                            .iter()
+                            .map(|v| {
+                                serde_json::json!({
+                                    "name": v.name,
+                                    "sql": v.sql,
+                                    "refreshed_at": v.refreshed_at,
+                                    "row_count": v.row_count,
+                                    "depends_on": v.depends_on,
+                                })
+                            })
+                            .collect();
+                        println!("{}", serde_json::to_string_pretty(&json)?);
+                    } else if list.is_empty() {
+                        info!("No materialized views defined. Run 'frozen-duckdb views define'");
+                    } else {
+                        for v in list {
+                            println!(
+                                "{:<24} {:<20} {:>10}  {}",
+                                v.name,
+                                v.refreshed_at.as_deref().unwrap_or("never"),
+                                v.row_count.map(|n| n.to_string()).unwrap_or_default(),
+                                v.sql
+                            );
+                        }
+                    }
+                }
+                ViewsAction::Drop { name } => {
+                    views.drop_view(&name)?;
+                    info!("✅ Dropped materialized view {}", name);
+                }
+            }
+        }
+
+        Commands::Serve {
+            database,
+            listen,
+            protocol,
+            token,
+            read_write,
+            allow_file_access,
+            cache,
+            metrics_listen,
+            max_connections,
+            max_queue,
+            queue_timeout,
+            timeout,
+            max_rows,
+        } => {
+            let queue_timeout = parse_ttl(&queue_timeout)
+                .with_context(|| format!("Invalid --queue-timeout: {}", queue_timeout))?;
+            let timeout = timeout
+                .map(|t| parse_ttl(&t).with_context(|| format!("Invalid --timeout: {}", t)))
+                .transpose()?;
+            serve(&ServeOptions {
+                database,
+                listen,
+                protocol: Protocol::parse(&protocol)?,
+                token: token.or_else(|| std::env::var(SERVE_TOKEN_ENV).ok()),
+                access: CliConfig::load()?.access_control()?,
+                read_write,
+                allow_file_access,
+                cache,
+                metrics_listen,
+                pool: PoolOptions {
+                    size: max_connections,
+                    max_waiting: max_queue,
+                    wait_timeout: queue_timeout,
+                },
+                limits: QueryLimits { max_rows, timeout },
+            })?;
+        }
+
+        Commands::Jobs { file, action } => {
+            let jobs = JobFile::load(&file)?;
+            let history = JobHistory::open(&jobs.history)?;
+            let open_jobs_flock = || open_flock("jobs");
+            let execute = |job: &Job| execute_job(job, &open_jobs_flock);
+            match action {
+                JobsAction::Run { job: Some(name), .. } => {
+                    if !history.run(jobs.get(&name)?, execute)? {
+                        anyhow::bail!("Job {} failed", name);
+                    }
+                }
+                JobsAction::Run { once: true, .. } => {
+                    let (succeeded, failed) = history.run_due(&jobs.jobs, execute)?;
+                    info!("✅ {} jobs succeeded, {} failed", succeeded, failed);
+                    if failed > 0 {
+                        anyhow::bail!("{} of {} jobs failed", failed, succeeded + failed);
+                    }
+                }
+                JobsAction::Run { .. } => history.run_daemon(&jobs.jobs, execute)?,
+                JobsAction::List => {
+                    let now = chrono::Local::now().naive_local();
+                    for job in &jobs.jobs {
+                        let last = history.runs(Some(&job.name), 1)?.pop();
+                        let next = history.next_run(job, now)?;
+                        println!(
+                            "{:<24} {:<8} {:<16} last: {:<28} next: {}",
+                            job.name,
+                            job.kind_name(),
+                            job.schedule_spec,
+                            last.map(|r| format!("{} ({})", r.started_at, r.status))
+                                .unwrap_or_else(|| "never".to_string()),
+                            next.map(|t| if t <= now {
+                                "due now".to_string()
+                            } else {
+                                t.format("%Y-%m-%d %H:%M").to_string()
+                            })
+                            .unwrap_or_else(|| "never".to_string())
+                        );
+                    }
+                }
+                JobsAction::History { job, limit } => {
+                    for run in history.runs(job.as_deref(), limit)? {
+                        println!(
+                            "{}  {:<24} {:<8} {}",
+                            run.started_at, run.job, run.status, run.message
+                        );
+                    }
+                }
+            }
+        }
+
+        Commands::Cache { action } => match action {
+            CacheAction::Invalidate => {
+                let removed = QueryCache::new()?.invalidate()?;
+                println!("Removed {} cached query results", removed);
+            }
+        },
+
+        Commands::Catalog { action } => {
+            let catalog = DatasetCatalog::open_default()?;
+            match action {
+                CatalogAction::List { format } => {
+                    let entries = catalog.list()?;
+                    if format == "json" {
+                        let json: Vec<Value> = entries
+                            .iter()
+                            .map(|entry| {
+                                serde_json::json!({
+                                    "name": entry.name,
+                                    "path": entry.path,
+                                    "format": entry.format,
+                                    "schema_hash": entry.schema_hash,
+                                    "row_count": entry.row_count,
+                                    "created_at": entry.created_at,
+                                })
+                            })
+                            .collect();
+                        println!("{}", serde_json::to_string_pretty(&json)?);
+                    } else if entries.is_empty() {
+                        info!("No datasets registered. Download one or convert with --register");
+                    } else {
+                        for entry in entries {
+                            println!(
+                                "{:<24} {:<8} {:>12} rows  {}",
+                                entry.name, entry.format, entry.row_count, entry.path
+                            );
+                        }
+                    }
+                }
+                CatalogAction::Show { name } => {
+                    let entry = catalog
+                        .get(&name)?
+                        .with_context(|| format!("No dataset named {} in the catalog", name))?;
+                    println!("Name:        {}", entry.name);
+                    println!("Path:        {}", entry.path);
+                    println!("Format:      {}", entry.format);
+                    println!("Rows:        {}", entry.row_count);
+                    println!("Schema hash: {}", entry.schema_hash);
+                    println!("Created at:  {}", entry.created_at.unwrap_or_default());
+
+                    let status = match DatasetManager::new()?.catalog_entry(
+                        &entry.name,
+                        &entry.path,
+                        &entry.format,
+                    ) {
+                        Ok(current) if current.schema_hash != entry.schema_hash => {
+                            "schema changed since registration".to_string()
+                        }
+                        Ok(current) if current.row_count != entry.row_count => {
+                            format!("now {} rows", current.row_count)
+                        }
+                        Ok(_) => "unchanged".to_string(),
+                        Err(e) => format!("unreadable ({:#})", e),
+                    };
+                    println!("Status:      {}", status);
+                }
+                CatalogAction::Rm { name } => {
+                    if !catalog.remove(&name)? {
+                        anyhow::bail!("No dataset named {} in the catalog", name);
+                    }
+                    info!("✅ Removed {} from the catalog (its files are kept)", name);
+                }
+            }
+        }
+
+        Commands::Snapshot { action } => match action {
+            SnapshotAction::Create {
+                database,
+                label,
+                method,
+                retention,
+            } => {
+                let store = SnapshotStore::for_database(Path::new(&database));
+                let snapshot = store.create(label.as_deref(), SnapshotMethod::parse(&method)?)?;
+                info!("✅ Snapshot {} saved to {}", snapshot.id, snapshot.path.display());
+                let retention = retention.retention()?;
+                if retention.is_set() {
+                    store.prune(&retention)?;
+                }
+            }
+            SnapshotAction::List { database, format } => {
+                let snapshots = SnapshotStore::for_database(Path::new(&database)).list()?;
+                if format == "json" {
+                    let json: Vec<Value> = snapshots.iter().map(|s| s.to_json()).collect();
+                    println!("{}", serde_json::to_string_pretty(&json)?);
+                } else if snapshots.is_empty() {
+                    info!("No snapshots of {}", database);
+                } else {
+                    for snapshot in snapshots {
+                        println!(
+                            "{:<40} {:<7} {:>10}  {}",
+                            snapshot.id,
+                            snapshot.method.as_str(),
+                            format_bytes(snapshot.size_bytes),
+                            snapshot.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+                        );
+                    }
+                }
+            }
+            SnapshotAction::Restore { database, snapshot } => {
+                let store = SnapshotStore::for_database(Path::new(&database));
+                let restored = store.restore(&snapshot)?;
+                info!(
+                    "✅ {} is back at {} (the replaced state was kept as a pre_restore snapshot)",
+                    database, restored.id
+                );
+            }
+            SnapshotAction::Prune {
+                database,
+                retention,
+            } => {
+                let retention = retention.retention()?;
+                if !retention.is_set() {
+                    anyhow::bail!("Give --keep or --max-age");
+                }
+                let removed = SnapshotStore::for_database(Path::new(&database)).prune(&retention)?;
+                info!("✅ Removed {} snapshots", removed.len());
+            }
+        },
+
+        Commands::Lineage { action } => match action {
+            LineageAction::Show { file, format } => {
+                let catalog = DatasetCatalog::open_default()?;
+                // A cataloged name stands for its file
+                let path = match catalog.get(&file)? {
+                    Some(entry) if !Path::new(&file).exists() => entry.path,
+                    _ => lineage::absolute(&file),
+                };
+                let dataset_manager = DatasetManager::new()?;
+                let tree = lineage::trace(&path, &|path: &str| {
+                    lineage::lookup(&catalog, dataset_manager.connection(), path)
+                })?;
+                if format == "json" {
+                    println!("{}", serde_json::to_string_pretty(&tree.to_json())?);
+                } else {
+                    if tree.lineage.is_none() {
+                        info!("No lineage recorded for {}", path);
+                    }
+                    print!("{}", tree.format_tree());
+                }
+            }
+        },
+
+        Commands::Secrets { action } => {
+            let dataset_manager = DatasetManager::new()?;
+            let conn = dataset_manager.connection();
+            match action {
+                SecretsAction::Add {
+                    kind,
+                    name,
+                    host,
+                    port,
+                    user,
+                    database,
+                } => {
+                    let spec = SecretSpec {
+                        name,
+                        kind: RemoteKind::parse(&kind)?,
+                        host,
+                        port,
+                        user,
+                        database,
+                    };
+                    let password = match std::env::var(SECRET_PASSWORD_ENV) {
+                        Ok(password) => password,
+                        Err(_) => {
+                            eprint!("Password for {}: ", spec.name);
+                            let mut password = String::new();
+                            io::stdin().read_line(&mut password)?;
+                            password.trim_end_matches(['\r', '\n']).to_string()
+                        }
+                    };
+                    federation::create_secret(conn, &spec, &password)?;
+                    info!(
+                        "✅ Stored {} secret {} in {}",
+                        spec.kind.as_str(),
+                        spec.name,
+                        federation::secret_directory()?.display()
+                    );
+                }
+                SecretsAction::List => {
+                    let secrets = federation::list_secrets(conn)?;
+                    if secrets.is_empty() {
+                        info!("No stored secrets");
+                    }
+                    for (name, kind) in secrets {
+                        println!("{:<24} {}", name, kind);
+                    }
+                }
+                SecretsAction::Rm { name } => {
+                    federation::drop_secret(conn, &name)?;
+                    info!("✅ Deleted secret {}", name);
+                }
+            }
+        }
+
+        Commands::Audit { action } => {
+            let mut config = CliConfig::load()?;
+            match action {
+                AuditAction::Enable { policy, jsonl } => {
+                    let audit = AuditConfig {
+                        policy: AuditPolicy::parse(&policy)?,
+                        sink: match jsonl {
+                            Some(path) => AuditSink::Jsonl(path.into()),
+                            None => AuditSink::default_database()?,
+                        },
+                    };
+                    config.set_audit(&audit, true);
+                    config.save()?;
+                    info!(
+                        "✅ Audit log enabled ({} policy): {}",
+                        audit.policy.as_str(),
+                        audit.sink.path().display()
+                    );
+                }
+                AuditAction::Disable => {
+                    match config.audit_config()? {
+                        Some(audit) => {
+                            config.set_audit(&audit, false);
+                            config.save()?;
+                            info!("✅ Audit log disabled; records kept in {}", audit.sink.path().display());
+                        }
+                        None => info!("Audit log is not enabled"),
+                    }
+                }
+                AuditAction::Status => match config.audit_config()? {
+                    Some(audit) => {
+                        let state = if config.audit()?.is_some() { "enabled" } else { "disabled" };
+                        println!("Audit log: {}", state);
+                        println!("Policy:    {}", audit.policy.as_str());
+                        println!("Location:  {}", audit.sink.path().display());
+                    }
+                    None => println!("Audit log: disabled"),
+                },
+                AuditAction::Export { output, since, command, model } => {
+                    let Some(audit) = config.audit_config()? else {
+                        anyhow::bail!(
+                            "Audit log has never been enabled. Run 'frozen-duckdb audit enable' first"
+                        );
+                    };
+                    let filter = ExportFilter {
+                        since: since.as_deref().map(parse_ttl).transpose()?,
+                        command,
+                        model,
+                    };
+                    let exported = export_audit_log(&audit, &output, &filter)?;
+                    info!("✅ Exported {} audit records to {}", exported, output);
+                }
+            }
+        }
+
+        Commands::Ask {
+            question,
+            database,
+            model,
+            yes,
+            format,
+        } => {
+            let conn = frozen_duckdb::Connection::open_with_flags(
+                &database,
+                frozen_duckdb::Config::default()
+                    .access_mode(frozen_duckdb::duckdb::AccessMode::ReadOnly)?,
+            )
+            .with_context(|| format!("Failed to open DuckDB database: {}", database))?;
+            let dataset_manager = DatasetManager::with_connection(conn)?;
+            let flock_manager = open_flock("ask")?;
+
+            require_flock(&flock_manager)?;
+
+            let sql = flock_manager.nl_to_sql(&question, dataset_manager.connection(), &model)?;
+            println!("{}", sql);
+
+            if !yes {
+                eprint!("Run this query? [y/N] ");
+                let mut answer = String::new();
+                io::stdin().read_line(&mut answer)?;
+                if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+                    info!("Query not executed");
+                    return Ok(());
+                }
+            }
+
+            let output = dataset_manager.run_query(&sql)?;
+            match format.as_str() {
+                "json" => println!("{}", serde_json::to_string_pretty(&output.to_json())?),
+                "csv" => println!("{}", output.to_csv()),
+                _ => println!("{}", output.to_table()),
+            }
+        }
+
+        Commands::Document {
+            database,
+            output,
+            model,
+            no_llm,
+            samples,
+        } => {
+            let conn = frozen_duckdb::Connection::open_with_flags(
+                &database,
+                frozen_duckdb::Config::default()
+                    .access_mode(frozen_duckdb::duckdb::AccessMode::ReadOnly)?,
+            )
+            .with_context(|| format!("Failed to open DuckDB database: {}", database))?;
+
+            let flock_manager = if no_llm {
+                None
+            } else {
+                let flock_manager = open_flock("document")?;
+                if flock_manager.is_flock_ready()? {
+                    Some(flock_manager)
+                } else {
+                    warn!("⚠️  Flock extension not available, writing schema skeleton only");
+                    warn!("   Run 'frozen-duckdb flock-setup' to add descriptions");
+                    None
+                }
+            };
+            let describer = flock_manager.as_ref().map(|manager| FlockDescriber {
+                manager,
+                model: model.clone(),
+            });
+            let options = DocumentOptions {
+                sample_values: samples,
+            };
+            let doc = document_database(
+                &conn,
+                &database,
+                describer.as_ref().map(|d| d as &dyn SchemaDescriber),
+                &options,
+            )?;
+
+            let markdown = doc.to_markdown();
+            if let Some(output) = output {
+                std::fs::write(&output, &markdown)
+                    .with_context(|| format!("Failed to write documentation: {}", output))?;
+                info!("✅ Documented {} tables in {}", doc.tables.len(), output);
+            } else {
+                print!("{}", markdown);
+            }
+        }
+
+        Commands::Complete {
+            prompt,
+            input,
+            output,
+            model,
+            images,
+            max_tokens,
+            temperature: _,
+            estimate,
+            context,
+            cache,
+        } => {
+            let text_to_complete = if let Some(prompt_text) = prompt {
+                prompt_text
+            } else if let Some(input_file) = input {
+                // Read from input file
+                read_input_file(&input_file)?.trim().to_string()
+            } else {
+                // Read from stdin
+                info!("📝 Enter text to complete (Ctrl+D to finish):");
+                let mut buffer = String::new();
+                io::stdin().read_line(&mut buffer)?;
+                buffer.trim().to_string()
+            };
+
+            let images = images
+                .iter()
+                .map(|image| ImageSource::parse(image))
+                .collect::<Result<Vec<_>>>()?;
+
+            let throughput = ThroughputStore::new()?;
+            if estimate {
+                let tokens_per_sec = throughput.tokens_per_sec(&model);
+                let estimate = estimate_completion(&text_to_complete, max_tokens, tokens_per_sec);
+                println!("{}", plain(&estimate.format_report()));
+                return Ok(());
+            }
+
+            let flock_manager = with_cache_args(open_flock("complete")?, &cache)?;
+            let flock_manager = with_context_args(flock_manager, &context)?;
+            let guardrails =
+                Guardrails::new(CliConfig::load()?.guardrails()?, flock_manager.audit_log());
+            if !guardrails.screen_input("input", &text_to_complete)? {
+                anyhow::bail!("Input blocked by the prompt-injection guardrail");
+            }
+
+            // Check if Flock is ready
+            require_flock(&flock_manager)?;
+
+            let cache_hits = flock_manager.cache_hits();
+            let started = Instant::now();
+            let response = flock_manager.complete_with_images(&text_to_complete, &images, model.as_str())
+                .context("Text completion failed - check if Ollama is running")?;
+
+            // Remember how fast this model runs for future --estimate calls
+            if flock_manager.cache_hits() == cache_hits {
+                let input_tokens = estimate_completion(&text_to_complete, 0, 1.0).input_tokens;
+                let elapsed = started.elapsed();
+                if let Err(e) =
+                    throughput.record(&model, input_tokens, count_tokens(&response), elapsed)
+                {
+                    warn!("⚠️  Failed to record model throughput: {}", e);
+                }
+            }
+            let response = guardrails.screen_output(&flock_manager, &response)?;
+
+            if let Some(output_file) = output {
+                std::fs::write(&output_file, &response)
+                    .with_context(|| format!("Failed to write to output file '{}'", output_file))?;
+                info!("✅ Response written to: {}", output_file);
+            } else {
+                println!("{}", response);
+            }
+        }
+
+        Commands::Embed {
+            text,
+            input,
+            output,
+            model,
+            normalize,
+        } => {
+            let flock_manager = open_flock("embed")?;
+
+            // Check if Flock is ready
+            require_flock(&flock_manager)?;
+
+            let texts_to_embed = if let Some(text_content) = text {
+                vec![text_content.clone()]
+            } else if let Some(input_file) = input {
+                // Read texts from input file (one per line)
+                read_input_file(&input_file)?.lines().map(|s| s.to_string()).collect()
+            } else {
+                anyhow::bail!("Must provide either --text or --input");
+            };
+
+            let embeddings = flock_manager.generate_embeddings(texts_to_embed, &model, normalize)
+                .expect("Embedding generation not implemented yet");
+
+            if let Some(output_file) = output {
+                // Write embeddings as JSON
+                let json_data = serde_json::to_string_pretty(&embeddings)
+                    .context("Failed to serialize embeddings to JSON")?;
+                std::fs::write(&output_file, json_data)
+                    .with_context(|| format!("Failed to write to output file '{}'", output_file))?;
+                info!("✅ Embeddings written to: {}", output_file);
+            } else {
+                // Print embeddings to stdout
+                println!("{}", serde_json::to_string_pretty(&embeddings)?);
+            }
+        }
+
+        Commands::Index {
+            corpus,
+            index,
+            model,
+            batch_size,
+            resume,
+            normalize,
+            metadata,
+            chunking,
+        } => {
+            let embedding = CliConfig::load()?.embedding()?;
+            let flock_manager = match embedding.backend {
+                EmbeddingBackend::Flock => {
+                    let flock_manager = open_flock("index")?;
+
+                    // Check if Flock is ready
+                    require_flock(&flock_manager)?;
+                    Some(flock_manager)
+                }
+                _ => None,
+            };
+
+            let mut documents = load_corpus(&corpus)?;
+            for document in &mut documents {
+                document.metadata.extend(metadata.iter().cloned());
+            }
+            if let Some(chunker) = chunking.chunker()? {
+                documents = chunk_documents(&documents, &chunker);
+            }
+            let embedding_index = EmbeddingIndex::open(&index)?;
+            let embedder = embedding.embedder(flock_manager.as_ref(), model, normalize)?;
+            let options = IndexOptions { batch_size, resume };
+
+            info!(
+                "🗂️  Indexing {} documents into {} ({} embeddings)",
+                documents.len(),
+                index,
+                embedding.backend.as_str()
+            );
+            let report = embedding_index.build(&documents, &embedder, &options)?;
+
+            info!(
+                "✅ Indexed {} documents in {} batches ({} already indexed)",
+                report.indexed_documents, report.batches, report.skipped_documents
+            );
+            info!(
+                "⚡ Throughput: {:.1} embeddings/sec over {:?}",
+                report.embeddings_per_sec(),
+                report.elapsed
+            );
+        }
+
+        Commands::Search {
+            query,
+            corpus,
+            model,
+            threshold,
+            limit,
+            hybrid,
+            fusion,
+            multi_query,
+            expansion_model,
+            filters,
+            rerank,
+            rerank_model,
+            format,
+        } => {
+            let fusion = FusionMethod::parse(&fusion)?;
+            if hybrid && !corpus.ends_with(".duckdb") {
+                anyhow::bail!("--hybrid needs an index built with the index command");
+            }
+            if multi_query > 0 && !corpus.ends_with(".duckdb") {
+                anyhow::bail!("--multi-query needs an index built with the index command");
+            }
+            if !filters.is_empty() && !corpus.ends_with(".duckdb") {
+                anyhow::bail!("--filter needs an index built with the index command");
+            }
+
+            // Flock is only needed for reranking, fusion, paraphrasing,
+            // unindexed corpora, and the flock embedding backend
+            let embedding = CliConfig::load()?.embedding()?;
+            let flock_manager = if rerank
+                || hybrid
+                || multi_query > 0
+                || !corpus.ends_with(".duckdb")
+                || embedding.backend == EmbeddingBackend::Flock
+            {
+                let flock_manager = open_flock("search")?;
+
+                // Check if Flock is ready
+                require_flock(&flock_manager)?;
+                Some(flock_manager)
+            } else {
+                None
+            };
+            let flock = || flock_manager.as_ref().expect("Flock is opened when needed");
+
+            let results = if corpus.ends_with(".duckdb") {
+                let embedding_index = EmbeddingIndex::open(&corpus)?;
+                // Queries must be normalized the same way as the indexed documents
+                let normalize = embedding_index
+                    .metadata()?
+                    .is_some_and(|metadata| metadata.normalized);
+                let embedder = embedding.embedder(flock_manager.as_ref(), model, normalize)?;
+                let multi_query = MultiQuery {
+                    paraphrases: multi_query,
+                    model: expansion_model,
+                    fusion,
+                };
+                let queries = if multi_query.paraphrases > 0 {
+                    multi_query.queries(flock(), &query)?
+                } else {
+                    vec![query.clone()]
+                };
+
+                let mut lists = Vec::new();
+                for query in &queries {
+                    lists.push(embedding_index.search_filtered(
+                        query, &embedder, threshold, limit, &filters,
+                    )?);
+                    if hybrid {
+                        lists.push(embedding_index.keyword_search(query, limit, &filters)?);
+                    }
+                }
+                if lists.len() > 1 {
+                    info!(
+                        "🔀 Fusing {} result lists ({} results) with {}",
+                        lists.len(),
+                        lists.iter().map(Vec::len).sum::<usize>(),
+                        fusion.as_str()
+                    );
+                    multi_query.fuse(flock(), &lists)?.into_iter().take(limit).collect()
+                } else {
+                    lists.pop().unwrap_or_default()
+                }
+            } else {
+                flock().semantic_search(&query, &corpus, threshold, limit)
+                    .expect("Semantic search not implemented yet")
+            };
+
+            let results = if rerank && results.len() > 1 {
+                let documents = results.iter().map(|(doc, _)| doc.clone()).collect();
+                let mut remaining = results;
+                let mut reranked = Vec::with_capacity(remaining.len());
+                for (doc, _rank) in flock().rerank(&query, documents, &rerank_model)? {
+                    if let Some(i) = remaining.iter().position(|(candidate, _)| *candidate == doc) {
+                        reranked.push(remaining.remove(i));
+                    }
+                }
+                info!("🏅 Reranked {} results with model {}", reranked.len(), rerank_model);
+                reranked
+            } else {
+                results
+            };
+
+            match format.as_str() {
+                "json" => {
+                    let json_results: Vec<Value> = results
+                        .into_iter()
+                        .map(|(doc, score)| {
+                            serde_json::json!({
+                                "document": doc,
+                                "similarity_score": score
+                            })
+                        })
+                        .collect();
+                    println!("{}", serde_json::to_string_pretty(&json_results)?);
+                }
+                _ => {
+                    // Text format
+                    if results.is_empty() {
+                        info!("🔍 No similar documents found above threshold {:.3}", threshold);
+                    } else {
+                        info!("🔍 Found {} similar documents:", results.len());
+                        let label = if hybrid { "fused score" } else { "similarity" };
+                        for (i, (doc, score)) in results.iter().enumerate() {
+                            println!("  {}. \"{}\" ({}: {:.3})", i + 1, doc, label, score);
+                        }
+                    }
+                }
+            }
+        }
+
+        Commands::Rag {
+            question,
+            index,
+            model,
+            answer_model,
+            limit,
+            threshold,
+            filters,
+            session,
+            history,
+            format,
+        } => {
+            let flock_manager = open_flock("rag")?;
+            require_flock(&flock_manager)?;
+
+            let store = session.as_ref().map(|_| SessionStore::open_default()).transpose()?;
+            let turns = match (&store, &session) {
+                (Some(store), Some(session)) => store.turns(session)?,
+                _ => Vec::new(),
+            };
+            let prior = relevant_turns(&turns, &question, history);
+            if !prior.is_empty() {
+                info!("🧠 Including {} of {} earlier turns", prior.len(), turns.len());
+            }
+
+            let embedding_index = EmbeddingIndex::open(&index)?;
+            // Queries must be normalized the same way as the indexed documents
+            let normalize = embedding_index
+                .metadata()?
+                .is_some_and(|metadata| metadata.normalized);
+            let config = CliConfig::load()?;
+            let guardrails = Guardrails::new(config.guardrails()?, flock_manager.audit_log());
+            let embedder = config.embedding()?.embedder(Some(&flock_manager), model, normalize)?;
+            let mut sources = Vec::new();
+            for source in embedding_index.search_filtered(
+                &retrieval_query(&question, &prior),
+                &embedder,
+                threshold,
+                limit,
+                &filters,
+            )? {
+                if guardrails.screen_input("context", &source.0)? {
+                    sources.push(source);
+                }
+            }
+            if sources.is_empty() {
+                warn!(
+                    "⚠️  No documents found above threshold {:.3}; answering without context",
+                    threshold
+                );
+            }
+
+            let documents: Vec<String> = sources.iter().map(|(doc, _)| doc.clone()).collect();
+            let answer = flock_manager
+                .complete_text(&rag_prompt(&question, &documents, &prior), &answer_model)?;
+            let answer = guardrails.screen_output(&flock_manager, &answer)?;
+
+            let turn = match (&store, &session) {
+                (Some(store), Some(session)) => {
+                    let turn = store.append(session, &question, &answer)?;
+                    info!("💬 Saved as turn {} of session {}", turn, session);
+                    Some(turn)
+                }
+                _ => None,
+            };
+
+            if format == "json" {
+                let json = serde_json::json!({
+                    "question": question,
+                    "answer": answer,
+                    "session": session,
+                    "turn": turn,
+                    "history_turns": prior.iter().map(|t| t.turn).collect::<Vec<_>>(),
+                    "sources": sources
+                        .iter()
+                        .map(|(doc, score)| {
+                            serde_json::json!({ "document": doc, "similarity_score": score })
+                        })
+                        .collect::<Vec<_>>(),
+                });
+                println!("{}", serde_json::to_string_pretty(&json)?);
+            } else {
+                println!("{}", answer.trim());
+                if !sources.is_empty() {
+                    println!();
+                    println!("Sources:");
+                    for (i, (doc, score)) in sources.iter().enumerate() {
+                        println!("  [{}] \"{}\" (similarity: {:.3})", i + 1, doc, score);
+                    }
+                }
+            }
+        }
+
+        Commands::Sessions { action } => {
+            let store = SessionStore::open_default()?;
+            match action {
+                SessionAction::List { format } => {
+                    let sessions = store.list()?;
+                    if format == "json" {
+                        let json: Vec<Value> = sessions
+                            .iter()
+                            .map(|session| {
+                                serde_json::json!({
+                                    "name": session.name,
+                                    "turns": session.turns,
+                                    "started_at": session.started_at,
+                                    "updated_at": session.updated_at,
+                                })
+                            })
+                            .collect();
+                        println!("{}", serde_json::to_string_pretty(&json)?);
+                    } else if sessions.is_empty() {
+                        info!("No sessions yet. Start one with rag --session NAME");
+                    } else {
+                        for session in sessions {
+                            println!(
+                                "{:<24} {:>5} turns  last used {}",
+                                session.name, session.turns, session.updated_at
+                            );
+                        }
+                    }
+                }
+                SessionAction::Show { name, format } => {
+                    let turns = store.turns(&name)?;
+                    if turns.is_empty() {
+                        anyhow::bail!("No session named {}", name);
+                    }
+                    if format == "json" {
+                        let json: Vec<Value> = turns
+                            .iter()
+                            .map(|turn| {
+                                serde_json::json!({
+                                    "turn": turn.turn,
+                                    "question": turn.question,
+                                    "answer": turn.answer,
+                                    "created_at": turn.created_at,
+                                })
+                            })
+                            .collect();
+                        println!("{}", serde_json::to_string_pretty(&json)?);
+                    } else {
+                        for turn in turns {
+                            println!("#{} ({})", turn.turn, turn.created_at);
+                            println!("Q: {}", turn.question);
+                            println!("A: {}", turn.answer.trim());
+                            println!();
+                        }
+                    }
+                }
+                SessionAction::Clear { name } => {
+                    let removed = store.clear(&name)?;
+                    if removed == 0 {
+                        anyhow::bail!("No session named {}", name);
+                    }
+                    info!("🗑️  Cleared session {} ({} turns)", name, removed);
+                }
+            }
+        }
+
+        Commands::SimilarItems {
+            index,
+            items,
+            id_column,
+            top_n,
+            min_similarity,
+            hnsw,
+            batch_size,
+            output,
+        } => {
+            let embedding_index = EmbeddingIndex::open(&index)?;
+            let item_ids = match items {
+                Some(items) => Some(load_item_ids(
+                    embedding_index.connection(),
+                    &items,
+                    &id_column,
+                )?),
+                None => None,
+            };
+            let options = SimilarItemsOptions {
+                top_n,
+                batch_size,
+                min_similarity,
+                hnsw,
+            };
+
+            let neighbors = similar_items(&embedding_index, item_ids.as_ref(), &options)?;
+            if let Some(output) = output {
+                let rows = export_neighbors(&embedding_index, &output)?;
+                info!("💾 Wrote {} neighbors to {}", rows, output);
+            }
+            info!(
+                "✅ Stored {} neighbors in {} (table item_neighbors)",
+                neighbors.len(),
+                index
+            );
+        }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+        Commands::Vss { action } => match action {
+            VssAction::Project {
+                index,
+                method,
+                output,
+                limit,
+            } => {
+                let method = ProjectionMethod::parse(&method)?;
+                let embedding_index = EmbeddingIndex::open(&index)?;
+                let points = project_index(&embedding_index, method, limit)?;
 
-    // Initialize tracing based on verbosity
-    let subscriber = tracing_subscriber::FmtSubscriber::builder()
-        .with_max_level(match cli.verbose {
-            0 => tracing::Level::WARN,
-            1 => tracing::Level::INFO,
-            2 => tracing::Level::DEBUG,
-            _ => tracing::Level::TRACE,
-        })
-        .finish();
+                let dataset_manager = DatasetManager::new()?;
+                write_points(dataset_manager.connection(), &points, &output)?;
+                info!("✅ Wrote {} points to {}", points.len(), output);
+            }
+            VssAction::Cluster {
+                index,
+                k,
+                output,
+                label,
+                model,
+                seed,
+            } => {
+                let embedding_index = EmbeddingIndex::open(&index)?;
+                let options = KMeansOptions {
+                    k,
+                    seed,
+                    ..Default::default()
+                };
 
-    tracing::subscriber::set_global_default(subscriber).expect("Failed to set tracing subscriber");
+                let flock_manager = if label {
+                    let flock_manager = open_flock("vss cluster")?;
+                    require_flock(&flock_manager)?;
+                    Some(flock_manager)
+                } else {
+                    None
+                };
+                let labeler = flock_manager.as_ref().map(|manager| FlockLabeler {
+                    manager,
+                    model: model.clone(),
+                });
+                let report = cluster_index(
+                    &embedding_index,
+                    &options,
+                    labeler.as_ref().map(|l| l as &dyn ClusterLabeler),
+                )?;
 
-    match cli.command {
-        // === DATASET MANAGEMENT COMMANDS ===
-        Commands::Download {
-            dataset,
-            output_dir,
-            format,
-        } => {
-            let dataset_manager = DatasetManager::new()?;
-            match dataset.as_str() {
-                "chinook" => {
-                    dataset_manager.download_chinook(&output_dir, &format)?;
+                for (cluster, (size, label)) in report.sizes.iter().zip(&report.labels).enumerate() {
+                    println!("{:>4}  {:>8}  {}", cluster, size, label.as_deref().unwrap_or(""));
                 }
-                "tpch" => {
-                    dataset_manager.download_tpch(&output_dir, &format)?;
-                }
-                _ => {
-                    error!("❌ Unknown dataset: {}", dataset);
-                    error!("   Available datasets: chinook, tpch");
-                    std::process::exit(1);
+                if let Some(output) = output {
+                    let rows = export_clusters(&embedding_index, &output)?;
+                    info!("💾 Wrote {} assignments to {}", rows, output);
                 }
+                info!(
+                    "✅ Clustered {} documents in {} iterations; stored in {}",
+                    report.documents, report.iterations, index
+                );
             }
-        }
+        },
 
-        Commands::Convert {
+        Commands::Extract {
             input,
+            column,
+            entities,
             output,
-            input_format,
-            output_format,
+            model,
+            batch_size,
+            context,
+            cache,
         } => {
+            let entities = EntitySpec::parse_list(&entities)?;
             let dataset_manager = DatasetManager::new()?;
-            dataset_manager.convert_dataset(&input, &output, &input_format, &output_format)?;
-        }
+            let flock_manager = with_cache_args(open_flock("extract")?, &cache)?;
+            let flock_manager = with_context_args(flock_manager, &context)?;
 
-        Commands::Info => {
-            let dataset_manager = DatasetManager::new()?;
-            dataset_manager.show_info()?;
-        }
+            require_flock(&flock_manager)?;
 
-        // === FLOCK/LLM COMMANDS ===
-        Commands::FlockSetup {
-            ollama_url,
-            text_model,
-            embedding_model,
-            skip_verification,
-        } => {
-            let flock_manager = FlockManager::new()?;
+            let extractor = FlockExtractor {
+                manager: &flock_manager,
+                model,
+            };
+            let options = ExtractOptions { column, batch_size };
+            let report = extract_entities(
+                dataset_manager.connection(),
+                &input,
+                &entities,
+                &extractor,
+                &output,
+                &options,
+            )?;
 
-            // Check if Flock is ready before proceeding
-            if !flock_manager.is_flock_ready()? {
-                error!("❌ Flock extension not available");
-                error!("   Make sure DuckDB with Flock extension is properly installed");
-                std::process::exit(4);
+            if report.failures > 0 {
+                warn!(
+                    "⚠️  {} of {} responses failed validation; see the extraction_error column",
+                    report.failures, report.extracted
+                );
             }
-
-            flock_manager.setup_ollama(&ollama_url, &text_model, &embedding_model, skip_verification)?;
+            info!("✅ Extracted {} rows to {}", report.rows, output);
         }
 
-        Commands::Complete {
-            prompt,
+        Commands::Translate {
             input,
+            column,
+            to,
             output,
+            output_column,
             model,
-            max_tokens: _,
-            temperature: _,
+            batch_size,
+            context,
+            cache,
         } => {
-            let flock_manager = FlockManager::new()?;
+            let language = Language::parse(&to)?;
+            let output = output.unwrap_or_else(|| sibling_path(&input, &language.code));
+            let dataset_manager = DatasetManager::new()?;
+            let flock_manager = with_cache_args(open_flock("translate")?, &cache)?;
+            let flock_manager = with_context_args(flock_manager, &context)?;
 
-            // Check if Flock is ready
-            if !flock_manager.is_flock_ready()? {
-                error!("❌ Flock extension not available");
-                error!("   Run 'frozen-duckdb flock-setup' first");
-                std::process::exit(4);
-            }
+            require_flock(&flock_manager)?;
 
-            let text_to_complete = if let Some(prompt_text) = prompt {
-                prompt_text
-            } else if let Some(input_file) = input {
-                // Read from input file
-                match std::fs::read_to_string(&input_file) {
-                    Ok(content) => content.trim().to_string(),
-                    Err(e) => {
-                        error!("❌ Failed to read input file '{}': {}", input_file, e);
-                        std::process::exit(1);
-                    }
-                }
-            } else {
-                // Read from stdin
-                info!("📝 Enter text to complete (Ctrl+D to finish):");
-                let mut buffer = String::new();
-                io::stdin().read_line(&mut buffer)?;
-                buffer.trim().to_string()
+            let language_model = FlockLanguageModel {
+                manager: &flock_manager,
+                model,
+            };
+            let options = LanguageOptions {
+                column,
+                batch_size,
+                output_column,
             };
+            let report = translate(
+                dataset_manager.connection(),
+                &input,
+                &output,
+                &language,
+                &language_model,
+                &options,
+            )?;
+            info!(
+                "✅ Translated {} of {} rows into {} ({})",
+                report.processed, report.rows, language.name, output
+            );
+        }
 
-            let response = flock_manager.complete_text(&text_to_complete, model.as_str())
-                .unwrap_or_else(|_| {
-                    error!("❌ Text completion failed - check if Ollama is running");
-                    std::process::exit(1);
-                });
+        Commands::DetectLanguage {
+            input,
+            column,
+            output,
+            heuristic,
+            model,
+            batch_size,
+            cache,
+        } => {
+            let output = output.unwrap_or_else(|| sibling_path(&input, "lang"));
+            let dataset_manager = DatasetManager::new()?;
 
-            if let Some(output_file) = output {
-                match std::fs::write(&output_file, &response) {
-                    Ok(_) => info!("✅ Response written to: {}", output_file),
-                    Err(e) => {
-                        error!("❌ Failed to write to output file '{}': {}", output_file, e);
-                        std::process::exit(1);
-                    }
-                }
+            let flock_manager = if heuristic {
+                None
             } else {
-                println!("{}", response);
+                let flock_manager = with_cache_args(open_flock("detect-language")?, &cache)?;
+                if flock_manager.is_flock_ready()? {
+                    Some(flock_manager)
+                } else {
+                    warn!("⚠️  Flock extension not available, using the built-in heuristic");
+                    None
+                }
+            };
+            let language_model = flock_manager.as_ref().map(|manager| FlockLanguageModel {
+                manager,
+                model: model.clone(),
+            });
+            let options = LanguageOptions {
+                column,
+                batch_size,
+                ..Default::default()
+            };
+            let report = detect_languages(
+                dataset_manager.connection(),
+                &input,
+                &output,
+                language_model.as_ref().map(|m| m as &dyn LanguageModel),
+                &options,
+            )?;
+            if language_model.is_some() && report.fallbacks > 0 {
+                warn!(
+                    "⚠️  {} rows fell back to the heuristic (reply was not a language code)",
+                    report.fallbacks
+                );
             }
+            info!(
+                "✅ Detected languages for {} of {} rows ({})",
+                report.processed, report.rows, output
+            );
         }
 
-        Commands::Embed {
-            text,
+        Commands::Filter {
+            criteria,
+            prompt,
             input,
             output,
+            output_format,
             model,
-            normalize,
+            positive_only,
+            resume,
+            cache,
         } => {
-            let flock_manager = FlockManager::new()?;
+            let output_format = match &output {
+                Some(output_file) => OutputFormat::resolve(output_format.as_deref(), output_file)?
+                    .unwrap_or(OutputFormat::Jsonl),
+                None => OutputFormat::Jsonl,
+            };
+            let flock_manager = with_cache_args(open_flock("filter")?, &cache)?;
+            let guardrails =
+                Guardrails::new(CliConfig::load()?.guardrails()?, flock_manager.audit_log());
 
             // Check if Flock is ready
-            if !flock_manager.is_flock_ready()? {
-                error!("❌ Flock extension not available");
-                error!("   Run 'frozen-duckdb flock-setup' first");
-                std::process::exit(4);
+            require_flock(&flock_manager)?;
+
+            let Some(filter_criteria) = filter_criteria(criteria, prompt) else {
+                anyhow::bail!("Must provide either --criteria or --prompt");
+            };
+
+            let content = read_input_file(&input)?;
+            let lines: Vec<&str> = content.lines().collect();
+
+            let mut checkpoint = match &output {
+                Some(output_file) => Some(FilterCheckpoint::open(
+                    output_file,
+                    &input,
+                    &filter_criteria,
+                    &model,
+                    resume,
+                )?),
+                None => None,
+            };
+
+            // Line numbers are 1-based so they match the input file
+            let pending: Vec<(usize, String)> = lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| (i + 1, line.to_string()))
+                .filter(|(number, _)| !checkpoint.as_ref().is_some_and(|c| c.is_done(*number)))
+                .collect();
+            let already_done = lines.len() - pending.len();
+            if already_done > 0 {
+                info!("⏩ Resuming: {} of {} lines already processed", already_done, lines.len());
+            }
+            if output.is_none() && positive_only {
+                info!("✅ Items that match criteria:");
             }
 
-            let texts_to_embed = if let Some(text_content) = text {
-                vec![text_content.clone()]
-            } else if let Some(input_file) = input {
-                // Read texts from input file (one per line)
-                match std::fs::read_to_string(&input_file) {
-                    Ok(content) => content.lines().map(|s| s.to_string()).collect(),
-                    Err(e) => {
-                        error!("❌ Failed to read input file '{}': {}", input_file, e);
-                        std::process::exit(1);
+            // Lines the injection guardrail blocks never reach the model and don't match
+            let mut blocked = Vec::new();
+            let mut screened = Vec::with_capacity(pending.len());
+            for (number, text) in pending {
+                if guardrails.screen_input("input", &text)? {
+                    screened.push((number, text));
+                } else {
+                    blocked.push((number, text));
+                }
+            }
+            let pending = screened;
+            for (number, text) in &blocked {
+                match checkpoint.as_mut() {
+                    Some(checkpoint) => {
+                        checkpoint.record(*number, text, false, Duration::ZERO, positive_only)?
                     }
+                    None if !positive_only => println!("{} NO MATCH: {}", mark("❌", "-"), text),
+                    None => {}
                 }
-            } else {
-                error!("❌ Must provide either --text or --input");
-                std::process::exit(1);
-            };
-
-            let embeddings = flock_manager.generate_embeddings(texts_to_embed, &model, normalize)
-                .expect("Embedding generation not implemented yet");
+            }
 
-            if let Some(output_file) = output {
-                // Write embeddings as JSON
-                let json_data = serde_json::to_string_pretty(&embeddings)
-                    .context("Failed to serialize embeddings to JSON")?;
-                match std::fs::write(&output_file, json_data) {
-                    Ok(_) => info!("✅ Embeddings written to: {}", output_file),
-                    Err(e) => {
-                        error!("❌ Failed to write to output file '{}': {}", output_file, e);
-                        std::process::exit(1);
+            let texts: Vec<String> = pending.iter().map(|(_, text)| text.clone()).collect();
+            let mut progress =
+                ProgressBar::new(lines.len()).with_position(already_done + blocked.len());
+            let mut matched = 0;
+            let result = flock_manager.classify_each(&filter_criteria, &texts, &model, |i, matches, latency| {
+                let (number, text) = &pending[i];
+                matched += usize::from(matches);
+                match checkpoint.as_mut() {
+                    Some(checkpoint) => {
+                        checkpoint.record(*number, text, matches, latency, positive_only)?
                     }
+                    None if matches => println!("{} MATCH: {}", mark("✅", "+"), text),
+                    None if !positive_only => println!("{} NO MATCH: {}", mark("❌", "-"), text),
+                    None => {}
                 }
-            } else {
-                // Print embeddings to stdout
-                println!("{}", serde_json::to_string_pretty(&embeddings)?);
+                progress.inc(1);
+                Ok(())
+            });
+            progress.finish();
+
+            if let Err(e) = result {
+                if let Some(output_file) = &output {
+                    error!(
+                        "❌ Partial results are in {}; rerun with --resume to continue",
+                        partial_path(output_file).display()
+                    );
+                }
+                return Err(e.context("Filtering stopped"));
+            }
+
+            info!(
+                "✅ Filtered {} lines, {} matches found",
+                texts.len() + blocked.len(),
+                matched
+            );
+            if let (Some(checkpoint), Some(output_file)) = (checkpoint, output) {
+                let rows = checkpoint.finish(output_format)?;
+                info!("✅ {} filter results written to: {}", rows, output_file);
             }
         }
 
-        Commands::Search {
-            query,
-            corpus,
-            threshold,
+        Commands::Eval {
+            input,
+            label_column,
+            text_column,
+            criteria,
+            prompt,
+            models,
             limit,
             format,
+            cache,
         } => {
-            let flock_manager = FlockManager::new()?;
+            let Some(filter_criteria) = filter_criteria(criteria, prompt) else {
+                anyhow::bail!("Must provide either --criteria or --prompt");
+            };
 
-            // Check if Flock is ready
-            if !flock_manager.is_flock_ready()? {
-                error!("❌ Flock extension not available");
-                error!("   Run 'frozen-duckdb flock-setup' first");
-                std::process::exit(4);
+            let dataset_manager = DatasetManager::new()?;
+            let samples = load_labeled(
+                dataset_manager.connection(),
+                &input,
+                &text_column,
+                &label_column,
+                limit,
+            )?;
+            if samples.is_empty() {
+                anyhow::bail!("No labeled rows found in: {}", input);
             }
 
-            let results = flock_manager.semantic_search(&query, &corpus, threshold, limit)
-                .expect("Semantic search not implemented yet");
+            let flock_manager = with_cache_args(open_flock("eval")?, &cache)?;
+
+            // Check if Flock is ready
+            require_flock(&flock_manager)?;
+
+            info!("🧪 Evaluating {} labeled rows with {} model(s)", samples.len(), models.len());
+            let mut reports = Vec::new();
+            for model in models {
+                let classifier = FlockClassifier {
+                    manager: &flock_manager,
+                    criteria: filter_criteria.clone(),
+                    model,
+                };
+                let report = evaluate(&samples, &classifier)?;
+                reports.push((classifier.model, report));
+            }
 
             match format.as_str() {
                 "json" => {
-                    let json_results: Vec<Value> = results
-                        .into_iter()
-                        .map(|(doc, score)| {
-                            serde_json::json!({
-                                "document": doc,
-                                "similarity_score": score
-                            })
-                        })
+                    let json_reports: serde_json::Map<String, Value> = reports
+                        .iter()
+                        .map(|(model, report)| (model.clone(), report.to_json()))
                         .collect();
-                    println!("{}", serde_json::to_string_pretty(&json_results)?);
+                    println!("{}", serde_json::to_string_pretty(&json_reports)?);
                 }
                 _ => {
-                    // Text format
-                    if results.is_empty() {
-                        info!("🔍 No similar documents found above threshold {:.3}", threshold);
-                    } else {
-                        info!("🔍 Found {} similar documents:", results.len());
-                        for (i, (doc, score)) in results.iter().enumerate() {
-                            println!("  {}. \"{}\" (similarity: {:.3})", i + 1, doc, score);
-                        }
+                    for (model, report) in &reports {
+                        let report = format!("📊 Model: {}\n{}\n", model, report.format_report());
+                        println!("{}", plain(&report));
                     }
                 }
             }
         }
 
-        Commands::Filter {
-            criteria,
-            prompt,
+        Commands::EvalRetrieval {
             input,
-            output,
+            index,
+            query_column,
+            relevant_column,
             model,
-            positive_only,
+            k,
+            threshold,
+            multi_query,
+            expansion_model,
+            fusion,
+            limit,
+            format,
         } => {
-            let flock_manager = FlockManager::new()?;
-
-            // Check if Flock is ready
-            if !flock_manager.is_flock_ready()? {
-                error!("❌ Flock extension not available");
-                error!("   Run 'frozen-duckdb flock-setup' first");
-                std::process::exit(4);
+            let fusion = FusionMethod::parse(&fusion)?;
+            let dataset_manager = DatasetManager::new()?;
+            let samples = load_retrieval_samples(
+                dataset_manager.connection(),
+                &input,
+                &query_column,
+                &relevant_column,
+                limit,
+            )?;
+            if samples.is_empty() {
+                anyhow::bail!("No labeled queries found in: {}", input);
             }
 
-            let filter_criteria = if let Some(custom_prompt) = prompt {
-                custom_prompt.clone()
-            } else if let Some(criteria_text) = criteria {
-                format!("{} Answer yes or no: {{text}}", criteria_text)
+            // Flock is only needed for paraphrasing and the flock embedding backend
+            let embedding = CliConfig::load()?.embedding()?;
+            let flock_manager = if multi_query > 0 || embedding.backend == EmbeddingBackend::Flock {
+                let flock_manager = open_flock("eval-retrieval")?;
+
+                // Check if Flock is ready
+                require_flock(&flock_manager)?;
+                Some(flock_manager)
             } else {
-                error!("❌ Must provide either --criteria or --prompt");
-                std::process::exit(1);
+                None
             };
 
-            let results = flock_manager.llm_filter(&filter_criteria, &input, &model, true)
-                .expect("LLM filtering not implemented yet");
+            let embedding_index = EmbeddingIndex::open(&index)?;
+            let normalize = embedding_index
+                .metadata()?
+                .is_some_and(|metadata| metadata.normalized);
+            let embedder = embedding.embedder(flock_manager.as_ref(), model, normalize)?;
 
-            if let Some(output_file) = output {
-                let json_data = serde_json::to_string_pretty(&results)
-                    .context("Failed to serialize filter results to JSON")?;
-                match std::fs::write(&output_file, json_data) {
-                    Ok(_) => info!("✅ Filter results written to: {}", output_file),
-                    Err(e) => {
-                        error!("❌ Failed to write to output file '{}': {}", output_file, e);
-                        std::process::exit(1);
+            info!("🧪 Evaluating recall@{} over {} labeled queries", k, samples.len());
+            let mut retriever = IndexRetriever {
+                index: &embedding_index,
+                embedder: &embedder,
+                threshold,
+                multi_query: None,
+            };
+            let single = evaluate_retrieval(&samples, &retriever, k)?;
+            let mut reports = vec![("single-query".to_string(), single)];
+            if let Some(flock_manager) = flock_manager.as_ref().filter(|_| multi_query > 0) {
+                retriever.multi_query = Some((
+                    flock_manager,
+                    MultiQuery {
+                        paraphrases: multi_query,
+                        model: expansion_model,
+                        fusion,
+                    },
+                ));
+                let report = evaluate_retrieval(&samples, &retriever, k)?;
+                let name =
+                    format!("multi-query ({} paraphrases, {})", multi_query, fusion.as_str());
+                reports.push((name, report));
+            }
+            let improvement =
+                (reports.len() > 1).then(|| reports[1].1.recall - reports[0].1.recall);
+
+            match format.as_str() {
+                "json" => {
+                    let mut json_reports: serde_json::Map<String, Value> = reports
+                        .iter()
+                        .map(|(name, report)| (name.clone(), report.to_json()))
+                        .collect();
+                    if let Some(improvement) = improvement {
+                        json_reports.insert("recall_improvement".to_string(), improvement.into());
                     }
+                    println!("{}", serde_json::to_string_pretty(&json_reports)?);
                 }
-            } else {
-                // Print results to stdout
-                if positive_only {
-                    info!("✅ Items that match criteria:");
-                    for (item, matches) in results {
-                        if matches {
-                            println!("✅ {}", item);
-                        }
+                _ => {
+                    for (name, report) in &reports {
+                        let report =
+                            format!("📊 Retrieval: {}\n{}\n", name, report.format_report());
+                        println!("{}", plain(&report));
                     }
-                } else {
-                    info!("📊 Filter results:");
-                    for (item, matches) in results {
-                        let status = if matches { "✅ MATCH" } else { "❌ NO MATCH" };
-                        println!("{}: {}", status, item);
+                    if let Some(improvement) = improvement {
+                        let summary = format!(
+                            "📈 Recall@{}: {:.3} → {:.3} ({:+.3})",
+                            k, reports[0].1.recall, reports[1].1.recall, improvement
+                        );
+                        println!("{}", plain(&summary));
                     }
                 }
             }
@@ -373,19 +2606,15 @@ fn main() -> Result<()> {
         Commands::Summarize {
             input,
             output,
+            output_format,
             strategy,
             max_length,
             model,
+            estimate,
+            chunking,
+            context,
+            cache,
         } => {
-            let flock_manager = FlockManager::new()?;
-
-            // Check if Flock is ready
-            if !flock_manager.is_flock_ready()? {
-                error!("❌ Flock extension not available");
-                error!("   Run 'frozen-duckdb flock-setup' first");
-                std::process::exit(4);
-            }
-
             // Read input texts
             let texts = if Path::new(&input).is_dir() {
                 // Read all text files in directory
@@ -402,26 +2631,73 @@ fn main() -> Result<()> {
                 all_texts
             } else {
                 // Read from single file (one text per line)
-                match std::fs::read_to_string(&input) {
-                    Ok(content) => content.lines().map(|s| s.to_string()).collect(),
-                    Err(e) => {
-                        error!("❌ Failed to read input file '{}': {}", input, e);
-                        std::process::exit(1);
-                    }
-                }
+                read_input_file(&input)?.lines().map(|s| s.to_string()).collect()
+            };
+
+            // Split long texts so each piece fits the model context
+            let texts = match chunking.chunker()? {
+                Some(chunker) => texts
+                    .iter()
+                    .flat_map(|text| chunker.chunk(text))
+                    .map(|chunk| chunk.text.trim().to_string())
+                    .filter(|chunk| !chunk.is_empty())
+                    .collect(),
+                None => texts,
             };
 
+            let throughput = ThroughputStore::new()?;
+            let summary_estimate =
+                estimate_summary(&texts, &strategy, max_length, throughput.tokens_per_sec(&model));
+            if estimate {
+                println!("{}", plain(&summary_estimate.format_report()));
+                return Ok(());
+            }
+
+            let flock_manager = with_cache_args(open_flock("summarize")?, &cache)?;
+            let flock_manager = with_context_args(flock_manager, &context)?;
+
+            // Check if Flock is ready
+            require_flock(&flock_manager)?;
+
+            let cache_hits = flock_manager.cache_hits();
+            let input_count = texts.len();
+            let started = Instant::now();
             let summary = flock_manager.summarize_texts(texts, &strategy, max_length, &model)
                 .expect("Text summarization not implemented yet");
+            let latency = started.elapsed();
 
-            if let Some(output_file) = output {
-                match std::fs::write(&output_file, &summary) {
-                    Ok(_) => info!("✅ Summary written to: {}", output_file),
-                    Err(e) => {
-                        error!("❌ Failed to write to output file '{}': {}", output_file, e);
-                        std::process::exit(1);
-                    }
+            // Remember how fast this model runs for future --estimate calls
+            if flock_manager.cache_hits() == cache_hits {
+                if let Err(e) = throughput.record(
+                    &model,
+                    summary_estimate.input_tokens,
+                    count_tokens(&summary),
+                    latency,
+                ) {
+                    warn!("⚠️  Failed to record model throughput: {}", e);
                 }
+            }
+
+            let table_format = match &output {
+                Some(output_file) => OutputFormat::resolve(output_format.as_deref(), output_file)?,
+                None => None,
+            };
+
+            if let (Some(output_file), Some(format)) = (&output, table_format) {
+                let row = serde_json::json!({
+                    "input": input,
+                    "strategy": strategy,
+                    "model": model,
+                    "input_count": input_count,
+                    "summary": summary,
+                    "latency_ms": latency.as_millis() as u64,
+                });
+                export_rows(&[row], SUMMARY_COLUMNS, output_file, format)?;
+                info!("✅ Summary written to: {}", output_file);
+            } else if let Some(output_file) = output {
+                std::fs::write(&output_file, &summary)
+                    .with_context(|| format!("Failed to write to output file '{}'", output_file))?;
+                info!("✅ Summary written to: {}", output_file);
             } else {
                 println!("{}", summary);
             }
@@ -439,38 +2715,78 @@ fn main() -> Result<()> {
             operation,
             iterations,
             size,
+            flush_interval,
+            profile: profile_dir,
+            format,
+            performance,
         } => {
             info!(
                 "Benchmarking {} operation with {} iterations (size: {})",
                 operation, iterations, size
             );
-            info!("📊 Performance benchmarking feature coming soon!");
+            let rows = match size.as_str() {
+                "small" => 1_000,
+                "medium" => 10_000,
+                "large" => 100_000,
+                other => anyhow::bail!("Unknown size '{}'. Use small, medium, or large", other),
+            };
+            if profile_dir.is_some() && operation != "query" {
+                warn!("⚠️  --profile only applies to the query benchmark");
+            }
+            match operation.as_str() {
+                "insert" => {
+                    let throughput = measure_insert_throughput(rows, iterations, flush_interval)?;
+                    if format == "json" {
+                        print_benchmark_json(&[&throughput.execute, &throughput.appender])?;
+                    } else {
+                        println!("{}", plain(&throughput.format_report()));
+                    }
+                }
+                "query" => {
+                    let profile_dir = profile_dir.as_deref().map(Path::new);
+                    let (stats, report) = measure_query_latency(rows, iterations, profile_dir)?;
+                    if format == "json" {
+                        print_benchmark_json(&[&stats])?;
+                    } else {
+                        println!("{}", plain(&stats.format_report()));
+                    }
+                    if let (Some(report), Some(dir)) = (report, profile_dir) {
+                        log_profile(&report, dir, "benchmark_query");
+                    }
+                }
+                "export" => {
+                    let export =
+                        measure_export_throughput(rows, iterations, &performance.options())?;
+                    if format == "json" {
+                        print_benchmark_json(&[&export.default, &export.tuned])?;
+                    } else {
+                        println!("{}", plain(&export.format_report()));
+                    }
+                }
+                _ => info!("📊 Benchmarking '{}' is not implemented yet", operation),
+            }
         }
 
         Commands::ValidateFfi {
             skip_llm,
+            ollama_url,
+            verify,
+            scale_factor,
             format,
             verbose,
         } => {
+            let ollama_url = ollama_url
+                .or_else(|| std::env::var(OLLAMA_URL_ENV).ok())
+                .unwrap_or_else(|| DEFAULT_OLLAMA_URL.to_string());
             info!("🦆 Starting FFI validation for frozen-duckdb");
             
             // Create FlockManager for validation
-            let flock_manager = match FlockManager::new() {
-                Ok(manager) => manager,
-                Err(e) => {
-                    error!("❌ Failed to create FlockManager: {}", e);
-                    std::process::exit(1);
-                }
-            };
+            let flock_manager = FlockManager::new().context("Failed to create FlockManager")?;
 
             // Run FFI validation
-            let validation_result = match flock_manager.validate_ffi() {
-                Ok(result) => result,
-                Err(e) => {
-                    error!("❌ FFI validation failed: {}", e);
-                    std::process::exit(1);
-                }
-            };
+            let verify_scale_factor = verify.then_some(scale_factor);
+            let validation = flock_manager.validate_ffi(&ollama_url, verify_scale_factor);
+            let validation_result = validation.context("FFI validation failed")?;
 
             // Display results based on format
             match format.as_str() {
@@ -496,20 +2812,162 @@ fn main() -> Result<()> {
                 }
                 "human" | _ => {
                     // Output human-readable format
-                    println!("{}", validation_result.format_results());
+                    println!("{}", plain(&validation_result.format_results()));
                 }
             }
 
-            // Exit with appropriate code
-            if validation_result.all_passed() {
-                info!("🎉 All FFI validation tests passed!");
-                std::process::exit(0);
-            } else {
-                error!("⚠️  Some FFI validation tests failed");
-                std::process::exit(1);
+            if !validation_result.all_passed() {
+                anyhow::bail!("Some FFI validation tests failed");
             }
+            info!("🎉 All FFI validation tests passed!");
+        }
+    }
+
+    Ok(())
+}
+
+/// Fails with [`FrozenDuckdbError::FlockNotReady`], exit code 4, unless
+/// the Flock extension is loaded.
+fn require_flock(flock_manager: &FlockManager) -> Result<()> {
+    if flock_manager.is_flock_ready()? {
+        Ok(())
+    } else {
+        Err(FrozenDuckdbError::FlockNotReady.into())
+    }
+}
+
+/// Reads an `--input` file, reporting a missing one as
+/// [`FrozenDuckdbError::NotFound`].
+fn read_input_file(path: &str) -> Result<String> {
+    if !Path::new(path).exists() {
+        return Err(FrozenDuckdbError::NotFound {
+            what: "Input file".to_string(),
+            path: path.into(),
         }
+        .into());
     }
+    std::fs::read_to_string(path).with_context(|| format!("Failed to read input file '{}'", path))
+}
+
+/// Prints benchmark statistics as a JSON object keyed by benchmark name,
+/// the same layout as a [`Baselines`](frozen_duckdb::benchmark::Baselines) file.
+fn print_benchmark_json(stats: &[&BenchmarkStats]) -> Result<()> {
+    let object: serde_json::Map<String, Value> = stats
+        .iter()
+        .map(|stats| (stats.name.clone(), stats.to_json()))
+        .collect();
+    println!("{}", serde_json::to_string_pretty(&object)?);
+    Ok(())
+}
 
+/// Records how a file was produced in the dataset catalog and, with
+/// `--lineage-metadata`, in its Parquet metadata. The command already
+/// succeeded, so a catalog that can't be written only warns.
+fn record_lineage(manager: &DatasetManager, lineage: &Lineage, args: &LineageArgs) -> Result<()> {
+    let recorded = DatasetCatalog::open_default().and_then(|catalog| {
+        catalog.record_lineage(lineage)?;
+        catalog.lineage(&lineage.output)
+    });
+    let stored = match recorded {
+        Ok(stored) => stored.unwrap_or_else(|| lineage.clone()),
+        Err(e) => {
+            warn!("⚠️  Couldn't record lineage of {}: {:#}", lineage.output, e);
+            lineage.clone()
+        }
+    };
+    if args.lineage_metadata && !embed_in_parquet(manager.connection(), &stored)? {
+        warn!("⚠️  --lineage-metadata only applies to Parquet files: {}", lineage.output);
+    }
     Ok(())
 }
+
+/// Resolves an input that isn't a file as the name of a cataloged dataset,
+/// returning its path and, unless given, its format.
+fn resolve_cataloged(
+    input: String,
+    input_format: Option<String>,
+) -> Result<(String, Option<String>)> {
+    let cataloged = if Path::new(&input).exists() {
+        None
+    } else {
+        match DatasetCatalog::open_existing()? {
+            Some(catalog) => catalog.get(&input)?,
+            None => None,
+        }
+    };
+    Ok(match cataloged {
+        Some(entry) => {
+            info!("📚 Reading cataloged dataset {} from {}", input, entry.path);
+            (entry.path, input_format.or(Some(entry.format)))
+        }
+        None => (input, input_format),
+    })
+}
+
+/// Registers the files of a downloaded dataset in the dataset catalog, e.g.
+/// `lineitem.parquet` of `tpch` as `tpch_lineitem`. DuckDB database
+/// downloads aren't files of one table, so they aren't registered.
+fn register_download(
+    manager: &DatasetManager,
+    dataset: &str,
+    output_dir: &str,
+    format: &str,
+) -> Result<usize> {
+    if !matches!(format, "csv" | "parquet") {
+        return Ok(0);
+    }
+    let catalog = DatasetCatalog::open_default()?;
+    let mut registered = 0;
+    for file in std::fs::read_dir(output_dir)? {
+        let path = file?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(format) {
+            continue;
+        }
+        let name = download_name(dataset, &path);
+        catalog.register(&manager.catalog_entry(&name, &path.to_string_lossy(), format)?)?;
+        registered += 1;
+    }
+    info!("📚 Registered {} {} tables in the dataset catalog", registered, dataset);
+    Ok(registered)
+}
+
+/// Logs where the profile of `name` was written and its hottest operators.
+fn log_profile(report: &QueryProfile, dir: &Path, name: &str) {
+    let (_, html) = report_paths(dir, name);
+    for line in report.format_report().lines() {
+        info!("{}", line);
+    }
+    info!("📄 Flame graph written to {}", html.display());
+}
+
+/// Returns `<stem>_<suffix>.<ext>` next to `path`, e.g. `data_deduped.parquet`.
+fn sibling_path(path: &str, suffix: &str) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+    let file_name = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}_{}.{}", stem, suffix, ext),
+        None => format!("{}_{}", stem, suffix),
+    };
+    path.with_file_name(file_name).to_string_lossy().into_owned()
+}
+
+/// Builds the `llm_filter` criteria from `--criteria` or `--prompt`.
+fn filter_criteria(criteria: Option<String>, prompt: Option<String>) -> Option<String> {
+    prompt.or_else(|| criteria.map(|text| format!("{} Answer yes or no: {{text}}", text)))
+}
+
+/// Attaches the LLM response cache configured by `cache` to `manager`.
+fn with_cache_args(manager: FlockManager, cache: &CacheArgs) -> Result<FlockManager> {
+    Ok(match cache.open()? {
+        Some(response_cache) => manager.with_response_cache(response_cache),
+        None => manager,
+    })
+}
+
+/// Attaches the context budget configured by `context` to `manager`.
+fn with_context_args(manager: FlockManager, context: &ContextArgs) -> Result<FlockManager> {
+    Ok(match context.budget()? {
+        Some(budget) => manager.with_context_budget(budget),
+        None => manager,
+    })
+}
@@ -0,0 +1,316 @@
+//! # Memory Usage Tracking
+//!
+//! Measures the peak resident set size (RSS) of the current process, so
+//! commands and benchmarks can report how much memory they needed and CI
+//! can fail when a budget is exceeded.
+//!
+//! | Platform | Source of the peak |
+//! |----------|--------------------|
+//! | Linux | `VmHWM` in `/proc/self/status` |
+//! | macOS | `ru_maxrss` from `getrusage` |
+//! | Other | Not available, [`peak_rss`] returns `None` |
+//!
+//! The peak covers the whole process. On Linux it can be reset with
+//! [`reset_peak_rss`], which [`Benchmark`](crate::benchmark::Benchmark)
+//! does so each benchmark reports its own peak.
+//!
+//! Budgets are written like DuckDB's `memory_limit`: `KB`, `MB` and `GB`
+//! are powers of 1000, `KiB`, `MiB` and `GiB` powers of 1024, and a bare
+//! number is bytes.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use frozen_duckdb::memory::{parse_size, MemoryTracker};
+//!
+//! let tracker = MemoryTracker::start("convert");
+//! // ... run the command ...
+//! let report = tracker.finish();
+//! println!("{}", report.format_summary());
+//! report.check_budget(parse_size("4GB")?)?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::error::FrozenDuckdbError;
+use anyhow::{Context, Result};
+use std::time::{Duration, Instant};
+
+/// Set to `1` to report the peak memory of every CLI command
+pub const TRACK_MEMORY_ENV: &str = "FROZEN_DUCKDB_TRACK_MEMORY";
+/// Peak memory budget for CLI commands, e.g. `100MB`
+pub const MEMORY_BUDGET_ENV: &str = "FROZEN_DUCKDB_MEMORY_BUDGET";
+
+/// Whether a CLI command should report its peak memory, given its
+/// `--track-memory` flag and [`TRACK_MEMORY_ENV`].
+pub fn tracking_enabled(track_memory: bool) -> bool {
+    track_memory
+        || std::env::var(TRACK_MEMORY_ENV).is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+/// The `--memory-budget` in bytes, falling back to [`MEMORY_BUDGET_ENV`].
+pub fn budget_from(flag: Option<&str>) -> Result<Option<u64>> {
+    match flag
+        .map(str::to_string)
+        .or_else(|| std::env::var(MEMORY_BUDGET_ENV).ok())
+    {
+        Some(size) => Ok(Some(parse_size(&size)?)),
+        None => Ok(None),
+    }
+}
+
+/// Peak resident set size of this process in bytes, if the platform
+/// reports it.
+pub fn peak_rss() -> Option<u64> {
+    platform::peak_rss()
+}
+
+/// Resets the peak to the current resident set size; returns whether the
+/// platform supports it (Linux only).
+pub fn reset_peak_rss() -> bool {
+    platform::reset_peak_rss()
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    pub fn peak_rss() -> Option<u64> {
+        super::parse_vm_hwm(&std::fs::read_to_string("/proc/self/status").ok()?)
+    }
+
+    pub fn reset_peak_rss() -> bool {
+        // "5" resets the peak RSS, see proc(5)
+        std::fs::write("/proc/self/clear_refs", "5").is_ok()
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    pub fn peak_rss() -> Option<u64> {
+        let mut usage = std::mem::MaybeUninit::<libc::rusage>::zeroed();
+        // SAFETY: getrusage only writes to the struct it is given
+        let usage = unsafe {
+            if libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr()) != 0 {
+                return None;
+            }
+            usage.assume_init()
+        };
+        // Bytes on macOS (kilobytes on Linux)
+        u64::try_from(usage.ru_maxrss).ok()
+    }
+
+    pub fn reset_peak_rss() -> bool {
+        false
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+mod platform {
+    pub fn peak_rss() -> Option<u64> {
+        None
+    }
+
+    pub fn reset_peak_rss() -> bool {
+        false
+    }
+}
+
+/// Reads `VmHWM` (in kB) from the contents of `/proc/<pid>/status`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_vm_hwm(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kilobytes: u64 = line["VmHWM:".len()..]
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Parses a memory size such as `512MB`, `1.5 GiB` or `1048576`.
+pub fn parse_size(text: &str) -> Result<u64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number
+        .parse()
+        .with_context(|| format!("Invalid memory size: {}", text))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" | "k" => 1_000,
+        "mb" | "m" => 1_000_000,
+        "gb" | "g" => 1_000_000_000,
+        "tb" | "t" => 1_000_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        other => anyhow::bail!(
+            "Unknown memory unit '{}' in {} (use B, KB, MB, GB, KiB, MiB, or GiB)",
+            other,
+            text
+        ),
+    };
+    Ok((number * multiplier as f64).round() as u64)
+}
+
+/// Formats `bytes` with a decimal unit, e.g. `84.3 MB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1_000 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if value < 1_000.0 {
+            break;
+        }
+        value /= 1_000.0;
+        unit = next;
+    }
+    format!("{:.1} {}", value, unit)
+}
+
+/// Fails with [`FrozenDuckdbError::MemoryBudgetExceeded`] if `peak` is
+/// over `budget`.
+pub fn check_budget(what: &str, peak: u64, budget: u64) -> Result<(), FrozenDuckdbError> {
+    if peak > budget {
+        return Err(FrozenDuckdbError::MemoryBudgetExceeded {
+            what: what.to_string(),
+            peak,
+            budget,
+        });
+    }
+    Ok(())
+}
+
+/// Times a command and reads the peak memory when it finishes.
+#[derive(Debug)]
+pub struct MemoryTracker {
+    command: String,
+    started: Instant,
+}
+
+impl MemoryTracker {
+    /// Starts tracking `command`.
+    pub fn start(command: &str) -> Self {
+        reset_peak_rss();
+        Self {
+            command: command.to_string(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Stops tracking and reports the elapsed time and peak memory.
+    pub fn finish(self) -> MemoryReport {
+        MemoryReport {
+            command: self.command,
+            elapsed: self.started.elapsed(),
+            peak_rss: peak_rss(),
+        }
+    }
+}
+
+/// Elapsed time and peak memory of a tracked command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryReport {
+    /// Command name, e.g. `convert`
+    pub command: String,
+    /// Wall-clock time of the command
+    pub elapsed: Duration,
+    /// Peak resident set size in bytes, if the platform reports it
+    pub peak_rss: Option<u64>,
+}
+
+impl MemoryReport {
+    /// One-line summary, e.g. `📈 convert finished in 1.2s, peak memory 84.3 MB`.
+    pub fn format_summary(&self) -> String {
+        let peak = match self.peak_rss {
+            Some(bytes) => format_bytes(bytes),
+            None => "unavailable on this platform".to_string(),
+        };
+        format!(
+            "📈 {} finished in {:.1?}, peak memory {}",
+            self.command, self.elapsed, peak
+        )
+    }
+
+    /// Fails if the peak is over `budget` bytes; a platform without peak
+    /// memory reporting always passes.
+    pub fn check_budget(&self, budget: u64) -> Result<(), FrozenDuckdbError> {
+        match self.peak_rss {
+            Some(peak) => check_budget(&self.command, peak, budget),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1048576").unwrap(), 1_048_576);
+        assert_eq!(parse_size("100MB").unwrap(), 100_000_000);
+        assert_eq!(parse_size("1.5 GiB").unwrap(), 1_610_612_736);
+        assert_eq!(parse_size("512mib").unwrap(), 512 << 20);
+        assert_eq!(parse_size("2k").unwrap(), 2_000);
+        assert!(parse_size("lots").is_err());
+        assert!(parse_size("10 parsecs").is_err());
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(84_300_000), "84.3 MB");
+        assert_eq!(format_bytes(2_000_000_000), "2.0 GB");
+    }
+
+    #[test]
+    fn test_parse_vm_hwm() {
+        let status = "Name:\tfrozen-duckdb\nVmPeak:\t  300000 kB\nVmHWM:\t   82344 kB\nVmRSS:\t   80000 kB\n";
+        assert_eq!(parse_vm_hwm(status), Some(82_344 * 1024));
+        assert_eq!(parse_vm_hwm("Name:\tx\n"), None);
+    }
+
+    #[test]
+    fn test_budget() {
+        let report = MemoryReport {
+            command: "convert".to_string(),
+            elapsed: Duration::from_millis(1200),
+            peak_rss: Some(150_000_000),
+        };
+        assert_eq!(
+            report.format_summary(),
+            "📈 convert finished in 1.2s, peak memory 150.0 MB"
+        );
+        assert!(report.check_budget(200_000_000).is_ok());
+
+        let error = report.check_budget(100_000_000).unwrap_err();
+        assert_eq!(error.exit_code(), 5);
+        assert_eq!(
+            error.to_string(),
+            "convert used 150.0 MB of memory, over its 100.0 MB budget"
+        );
+
+        let unknown = MemoryReport {
+            peak_rss: None,
+            ..report
+        };
+        assert!(unknown.check_budget(1).is_ok());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_peak_rss_grows() {
+        let before = peak_rss().expect("Linux reports VmHWM");
+        let buffer = vec![1u8; 64 << 20];
+        let after = peak_rss().unwrap();
+        assert!(buffer.iter().all(|&b| b == 1));
+        assert!(after >= before);
+        assert!(after >= 64 << 20);
+    }
+}
@@ -0,0 +1,506 @@
+//! # Query Profiling Reports
+//!
+//! DuckDB can write a JSON profile of every query: the tree of physical
+//! operators with the time each one took and the rows it produced. This
+//! module enables that profiler around a piece of work, parses the JSON,
+//! and renders it as an HTML flame graph where each operator's width is its
+//! share of the query's operator time.
+//!
+//! | Output | Contents |
+//! |--------|----------|
+//! | `<name>.json` | DuckDB's raw profile of the last query |
+//! | `<name>.html` | Flame graph and operator table, no external assets |
+//!
+//! Operators are drawn top-down from the root of the plan (an icicle
+//! graph): a parent is as wide as its own time plus its children's, and
+//! the hover text shows the operator's details. The profile covers the
+//! last statement run, since DuckDB overwrites the file for each query.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use frozen_duckdb::profiling::profile;
+//! use frozen_duckdb::Connection;
+//! use std::path::Path;
+//!
+//! let conn = Connection::open_in_memory()?;
+//! let (count, profile) = profile(&conn, Path::new("profiles"), "count", || {
+//!     Ok(conn.query_row("SELECT count(*) FROM range(1000000)", [], |row| {
+//!         row.get::<_, i64>(0)
+//!     })?)
+//! })?;
+//!
+//! for operator in profile.hottest(3) {
+//!     println!("{} took {:?} for {} rows", operator.name, operator.timing, operator.cardinality);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::cli::sql_path::path_literal;
+use crate::duckdb::Connection;
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// One physical operator of a profiled query, with its inputs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperatorProfile {
+    /// Operator name, e.g. `HASH_GROUP_BY`
+    pub name: String,
+    /// Time spent in this operator, excluding its children
+    pub timing: Duration,
+    /// Rows the operator produced
+    pub cardinality: u64,
+    /// Operator details, e.g. projections or filters, one per line
+    pub extra_info: String,
+    /// Operators feeding this one
+    pub children: Vec<OperatorProfile>,
+}
+
+impl OperatorProfile {
+    /// Time spent in this operator and all of its inputs.
+    pub fn total_timing(&self) -> Duration {
+        self.timing
+            + self
+                .children
+                .iter()
+                .map(Self::total_timing)
+                .sum::<Duration>()
+    }
+
+    fn from_json(value: &Value) -> Self {
+        let name = ["operator_name", "operator_type", "name"]
+            .iter()
+            .find_map(|key| value.get(key).and_then(Value::as_str))
+            .unwrap_or("UNKNOWN")
+            .trim()
+            .to_string();
+        let extra_info = match value.get("extra_info") {
+            Some(Value::String(info)) => info.trim().to_string(),
+            Some(Value::Object(info)) => info
+                .iter()
+                .map(|(key, value)| match value {
+                    Value::String(value) => format!("{}: {}", key, value.trim()),
+                    other => format!("{}: {}", key, other),
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => String::new(),
+        };
+        Self {
+            name,
+            timing: seconds(value, &["operator_timing", "timing"]),
+            cardinality: ["operator_cardinality", "cardinality"]
+                .iter()
+                .find_map(|key| value.get(key).and_then(Value::as_u64))
+                .unwrap_or(0),
+            extra_info,
+            children: children(value),
+        }
+    }
+}
+
+/// Metrics of one operator, flattened out of the plan tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperatorMetrics {
+    /// Depth in the plan, 0 for the operator producing the result
+    pub depth: usize,
+    /// Operator name
+    pub name: String,
+    /// Time spent in this operator, excluding its children
+    pub timing: Duration,
+    /// Rows the operator produced
+    pub cardinality: u64,
+    /// Share of the query's operator time, from 0 to 1
+    pub share: f64,
+}
+
+/// A parsed DuckDB JSON query profile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryProfile {
+    /// SQL of the profiled query
+    pub query: String,
+    /// Wall-clock time of the whole query
+    pub latency: Duration,
+    /// Rows returned to the client, if reported
+    pub rows_returned: Option<u64>,
+    /// Root operators of the plan
+    pub operators: Vec<OperatorProfile>,
+}
+
+impl QueryProfile {
+    /// Parses the output of `PRAGMA enable_profiling = 'json'`.
+    ///
+    /// Both the current format (`operator_name`, `operator_timing`, …)
+    /// and the one from DuckDB 0.x (`name`, `timing`, …) are accepted.
+    pub fn parse(json: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(json).context("Invalid JSON query profile")?;
+        if !value.is_object() {
+            anyhow::bail!("Invalid JSON query profile: expected an object");
+        }
+        Ok(Self {
+            query: value
+                .get("query_name")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+            latency: seconds(&value, &["latency", "timing", "result"]),
+            rows_returned: value.get("rows_returned").and_then(Value::as_u64),
+            operators: children(&value),
+        })
+    }
+
+    /// Reads and parses a JSON profile file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read query profile: {}", path.display()))?;
+        Self::parse(&json).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Time spent in all operators.
+    pub fn operator_timing(&self) -> Duration {
+        self.operators
+            .iter()
+            .map(OperatorProfile::total_timing)
+            .sum()
+    }
+
+    /// Every operator in plan order (each operator before its inputs).
+    pub fn metrics(&self) -> Vec<OperatorMetrics> {
+        fn walk(
+            operator: &OperatorProfile,
+            depth: usize,
+            total: f64,
+            metrics: &mut Vec<OperatorMetrics>,
+        ) {
+            metrics.push(OperatorMetrics {
+                depth,
+                name: operator.name.clone(),
+                timing: operator.timing,
+                cardinality: operator.cardinality,
+                share: share(operator.timing, total),
+            });
+            for child in &operator.children {
+                walk(child, depth + 1, total, metrics);
+            }
+        }
+
+        let total = self.operator_timing().as_secs_f64();
+        let mut metrics = Vec::new();
+        for operator in &self.operators {
+            walk(operator, 0, total, &mut metrics);
+        }
+        metrics
+    }
+
+    /// The `n` operators that took the most time, slowest first.
+    pub fn hottest(&self, n: usize) -> Vec<OperatorMetrics> {
+        let mut metrics = self.metrics();
+        metrics.sort_by_key(|operator| std::cmp::Reverse(operator.timing));
+        metrics.truncate(n);
+        metrics
+    }
+
+    /// Formats the latency and the slowest operators as a short report.
+    pub fn format_report(&self) -> String {
+        let mut report = format!(
+            "🔥 Query profile: {:?} latency, {:?} in operators",
+            self.latency,
+            self.operator_timing()
+        );
+        for operator in self.hottest(5) {
+            let _ = write!(
+                report,
+                "\n  {:<24} {:>12?} {:>5.1}%  {} rows",
+                operator.name,
+                operator.timing,
+                operator.share * 100.0,
+                operator.cardinality
+            );
+        }
+        report
+    }
+
+    /// Renders the profile as a standalone HTML page with a flame graph
+    /// and a table of all operators.
+    pub fn to_html(&self) -> String {
+        let total = self.operator_timing().as_secs_f64();
+        let mut graph = String::new();
+        for operator in &self.operators {
+            render_operator(operator, total, &mut graph);
+        }
+
+        let mut rows = String::new();
+        let mut metrics = self.metrics();
+        metrics.sort_by_key(|operator| std::cmp::Reverse(operator.timing));
+        for operator in metrics {
+            let _ = write!(
+                rows,
+                "<tr><td>{}</td><td>{:?}</td><td>{:.1}%</td><td>{}</td><td>{}</td></tr>",
+                escape_html(&operator.name),
+                operator.timing,
+                operator.share * 100.0,
+                operator.cardinality,
+                operator.depth
+            );
+        }
+
+        format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Query profile</title>
+<style>
+body {{ font-family: system-ui, sans-serif; margin: 1.5em; }}
+pre {{ background: #f4f4f4; padding: 0.8em; white-space: pre-wrap; }}
+.graph, .children {{ display: flex; }}
+.node {{ display: flex; flex-direction: column; min-width: 0; }}
+.bar {{ height: 1.7em; line-height: 1.7em; margin: 1px; padding: 0 0.3em; font-size: 12px;
+       border-radius: 2px; overflow: hidden; white-space: nowrap; text-overflow: ellipsis; }}
+table {{ border-collapse: collapse; margin-top: 1em; }}
+td, th {{ border: 1px solid #ddd; padding: 0.2em 0.6em; text-align: right; }}
+td:first-child, th:first-child {{ text-align: left; }}
+</style>
+</head>
+<body>
+<h1>Query profile</h1>
+<pre>{query}</pre>
+<p>Latency {latency:?}, {operator_timing:?} in operators, {rows} rows returned</p>
+<div class="graph">{graph}</div>
+<h2>Operators</h2>
+<table>
+<tr><th>Operator</th><th>Time</th><th>Share</th><th>Rows</th><th>Depth</th></tr>
+{rows_html}
+</table>
+</body>
+</html>
+"#,
+            query = escape_html(&self.query),
+            latency = self.latency,
+            operator_timing = self.operator_timing(),
+            rows = self
+                .rows_returned
+                .map_or_else(|| "?".to_string(), |rows| rows.to_string()),
+            graph = graph,
+            rows_html = rows,
+        )
+    }
+}
+
+/// Profiles the queries `run` makes on `conn`, and writes `<name>.json`
+/// and `<name>.html` to `dir`.
+///
+/// Profiling is turned off again before returning, also when `run` fails.
+pub fn profile<T, F>(conn: &Connection, dir: &Path, name: &str, run: F) -> Result<(T, QueryProfile)>
+where
+    F: FnOnce() -> Result<T>,
+{
+    let (json, html) = report_paths(dir, name);
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create profile directory: {}", dir.display()))?;
+    conn.execute_batch(&format!(
+        "PRAGMA profiling_output = {}; PRAGMA enable_profiling = 'json';",
+        path_literal(&json)
+    ))
+    .context("Failed to enable query profiling")?;
+
+    // Read the profile before disabling, so it is the last query's of `run`
+    let result = run().and_then(|value| Ok((value, QueryProfile::load(&json)?)));
+    conn.execute_batch("PRAGMA disable_profiling")
+        .context("Failed to disable query profiling")?;
+    let (value, profile) = result?;
+
+    fs::write(&html, profile.to_html())
+        .with_context(|| format!("Failed to write profile report: {}", html.display()))?;
+    Ok((value, profile))
+}
+
+/// Paths of the JSON profile and HTML report [`profile`] writes.
+pub fn report_paths(dir: &Path, name: &str) -> (PathBuf, PathBuf) {
+    (
+        dir.join(format!("{}.json", name)),
+        dir.join(format!("{}.html", name)),
+    )
+}
+
+fn children(value: &Value) -> Vec<OperatorProfile> {
+    value
+        .get("children")
+        .and_then(Value::as_array)
+        .map(|children| children.iter().map(OperatorProfile::from_json).collect())
+        .unwrap_or_default()
+}
+
+/// Reads the first of `keys` holding a number of seconds.
+fn seconds(value: &Value, keys: &[&str]) -> Duration {
+    keys.iter()
+        .find_map(|key| value.get(key).and_then(Value::as_f64))
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        .map(Duration::from_secs_f64)
+        .unwrap_or_default()
+}
+
+fn share(timing: Duration, total: f64) -> f64 {
+    if total > 0.0 {
+        timing.as_secs_f64() / total
+    } else {
+        0.0
+    }
+}
+
+/// Appends an operator and its inputs as nested flex boxes, each as wide
+/// as its total time.
+fn render_operator(operator: &OperatorProfile, total: f64, html: &mut String) {
+    let own = share(operator.timing, total);
+    // Yellow for cheap operators through red for the most expensive
+    let hue = 60.0 * (1.0 - own.min(1.0));
+    let mut title = format!(
+        "{}\nself {:?} ({:.1}%), total {:?}\n{} rows",
+        operator.name,
+        operator.timing,
+        own * 100.0,
+        operator.total_timing(),
+        operator.cardinality
+    );
+    if !operator.extra_info.is_empty() {
+        title.push('\n');
+        title.push_str(&operator.extra_info);
+    }
+
+    let _ = write!(
+        html,
+        r#"<div class="node" style="flex: {} 1 0"><div class="bar" style="background: hsl({:.0}, 85%, 65%)" title="{}">{} {:?}</div><div class="children">"#,
+        weight(operator.total_timing()),
+        hue,
+        escape_html(&title),
+        escape_html(&operator.name),
+        operator.total_timing()
+    );
+    for child in &operator.children {
+        render_operator(child, total, html);
+    }
+    // The operator's own time, left empty below it
+    let _ = write!(
+        html,
+        r#"<div style="flex: {} 1 0"></div></div></div>"#,
+        weight(operator.timing)
+    );
+}
+
+/// Flex weight of a duration; operators that took no measurable time stay visible.
+fn weight(timing: Duration) -> f64 {
+    timing.as_secs_f64().max(1e-7)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILE: &str = r##"{
+        "query_name": "SELECT region, sum(amount) FROM sales WHERE amount > 0 GROUP BY region",
+        "latency": 0.004,
+        "rows_returned": 3,
+        "children": [{
+            "operator_type": "PROJECTION",
+            "operator_name": "PROJECTION",
+            "operator_timing": 0.0001,
+            "operator_cardinality": 3,
+            "extra_info": {"Projections": "region\nsum(amount)"},
+            "children": [{
+                "operator_name": "HASH_GROUP_BY",
+                "operator_timing": 0.002,
+                "operator_cardinality": 3,
+                "extra_info": {"Groups": "#0", "Estimated Cardinality": "3"},
+                "children": [{
+                    "operator_name": "TABLE_SCAN",
+                    "operator_timing": 0.0009,
+                    "operator_cardinality": 1000,
+                    "extra_info": {"Table": "sales", "Filters": "amount>0"},
+                    "children": []
+                }]
+            }]
+        }]
+    }"##;
+
+    #[test]
+    fn test_parse_profile() {
+        let profile = QueryProfile::parse(PROFILE).unwrap();
+        assert_eq!(profile.latency, Duration::from_secs_f64(0.004));
+        assert_eq!(profile.rows_returned, Some(3));
+        assert_eq!(profile.operators.len(), 1);
+
+        let projection = &profile.operators[0];
+        assert_eq!(projection.name, "PROJECTION");
+        assert_eq!(projection.extra_info, "Projections: region\nsum(amount)");
+        assert_eq!(projection.total_timing(), Duration::from_secs_f64(0.003));
+
+        let names: Vec<_> = profile
+            .metrics()
+            .into_iter()
+            .map(|m| (m.depth, m.name))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                (0, "PROJECTION".to_string()),
+                (1, "HASH_GROUP_BY".to_string()),
+                (2, "TABLE_SCAN".to_string())
+            ]
+        );
+
+        let hottest = profile.hottest(2);
+        assert_eq!(hottest[0].name, "HASH_GROUP_BY");
+        assert!((hottest[0].share - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(hottest[1].name, "TABLE_SCAN");
+        assert_eq!(hottest[1].cardinality, 1000);
+    }
+
+    #[test]
+    fn test_parse_legacy_profile() {
+        let profile = QueryProfile::parse(
+            r#"{"result": 0.5, "name": "Query", "children": [
+                {"name": "ORDER_BY", "timing": 0.25, "cardinality": 10,
+                 "extra_info": "x ASC\n", "children": []}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(profile.latency, Duration::from_millis(500));
+        assert_eq!(profile.rows_returned, None);
+        assert_eq!(profile.operators[0].name, "ORDER_BY");
+        assert_eq!(profile.operators[0].extra_info, "x ASC");
+        assert_eq!(profile.operators[0].cardinality, 10);
+
+        assert!(QueryProfile::parse("[]").is_err());
+        assert!(QueryProfile::parse("not json").is_err());
+    }
+
+    #[test]
+    fn test_html_report() {
+        let profile = QueryProfile::parse(PROFILE).unwrap();
+        let html = profile.to_html();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("WHERE amount &gt; 0"));
+        assert!(html.contains(">HASH_GROUP_BY 2.9ms<"));
+        assert!(html.contains("Filters: amount&gt;0"));
+        assert_eq!(html.matches(r#"<div class="node""#).count(), 3);
+        assert_eq!(html.matches("<tr><td>").count(), 3);
+
+        let report = profile.format_report();
+        assert!(report.contains("HASH_GROUP_BY"), "{}", report);
+        assert_eq!(report.lines().count(), 4);
+    }
+}
@@ -0,0 +1,530 @@
+//! # Scalar UDFs for Frozen DuckDB
+//!
+//! This module provides a closure-based helper for registering Rust scalar
+//! functions on a [`Connection`]. It wraps the duckdb-rs `VScalar` API (which
+//! is re-exported for full control) so simple functions need no unsafe code
+//! and no manual vector handling.
+//!
+//! ## Usage Examples
+//!
+//! ```rust
+//! use frozen_duckdb::{scalar_args, Connection};
+//!
+//! let conn = Connection::open_in_memory()?;
+//!
+//! conn.register_scalar("shout", |args| {
+//!     let (text, times) = scalar_args!(args; String, i64);
+//!     Ok(format!("{}{}", text.to_uppercase(), "!".repeat(times as usize)))
+//! })?;
+//!
+//! let value: String = conn.query_row("SELECT shout('duck', 3)", [], |row| row.get(0))?;
+//! assert_eq!(value, "DUCK!!!");
+//! ```
+//!
+//! ## Argument Extraction
+//!
+//! Functions accept any number of arguments of any type. Use the
+//! [`scalar_args!`](crate::scalar_args) macro (or [`ScalarArgs::get`]) to
+//! extract typed values; arity and type mismatches become SQL errors.
+//!
+//! | Rust Type | Accepted SQL Types |
+//! |-----------|--------------------|
+//! | `String` | VARCHAR |
+//! | `i64` | TINYINT, SMALLINT, INTEGER, BIGINT |
+//! | `f64` | FLOAT, DOUBLE, DECIMAL, and all integer types |
+//! | `bool` | BOOLEAN |
+//! | `Option<T>` | Any of the above, `None` for NULL |
+//!
+//! The return type is inferred from the closure: `String`, `i64`, `f64`,
+//! `bool`, or `Option` of those (returning `None` produces NULL).
+//!
+//! ## Built-in Functions
+//!
+//! [`register_builtins`] registers the helpers the CLI `query` command makes
+//! available, such as `normalize_text(text)`.
+
+use crate::duckdb::core::{DataChunkHandle, FlatVector, Inserter, LogicalTypeId};
+use crate::duckdb::types::DuckString;
+use crate::duckdb::vtab::arrow::WritableVector;
+use crate::Connection;
+use frozen_duckdb_sys::{duckdb_hugeint, duckdb_string_t};
+use std::error::Error;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+pub use crate::duckdb::vscalar::{ScalarFunctionSignature, VScalar};
+
+/// Result type returned by scalar UDF closures.
+pub type ScalarResult<T> = Result<T, Box<dyn Error>>;
+
+/// Boxed closure type stored as the scalar function state.
+type ScalarFn<R> = Arc<dyn Fn(&ScalarArgs) -> ScalarResult<R> + Send + Sync>;
+
+/// The arguments of a single row passed to a scalar UDF.
+pub struct ScalarArgs<'a> {
+    vectors: &'a [FlatVector],
+    types: &'a [LogicalTypeId],
+    /// Width and scale of DECIMAL arguments, `(0, 0)` for other types
+    decimals: &'a [(u8, u8)],
+    len: usize,
+    row: usize,
+}
+
+impl<'a> ScalarArgs<'a> {
+    /// Number of arguments passed to the function.
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    /// Returns true if the function was called without arguments.
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// Returns an error unless exactly `expected` arguments were passed.
+    pub fn expect_len(&self, expected: usize) -> ScalarResult<()> {
+        if self.len() != expected {
+            return Err(format!("expected {} argument(s), got {}", expected, self.len()).into());
+        }
+        Ok(())
+    }
+
+    /// Returns true if the argument at `index` is NULL.
+    pub fn is_null(&self, index: usize) -> bool {
+        self.types[index] == LogicalTypeId::SqlNull
+            || self.vectors[index].row_is_null(self.row as u64)
+    }
+
+    /// Extracts the argument at `index` as `T`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `index` is out of range, the SQL type cannot be
+    /// converted to `T`, or the value is NULL and `T` is not an `Option`.
+    pub fn get<T: FromScalarArg>(&self, index: usize) -> ScalarResult<T> {
+        if index >= self.len() {
+            return Err(format!("argument {} out of range ({} given)", index, self.len()).into());
+        }
+        T::from_arg(self, index)
+    }
+
+    /// Returns a cursor that extracts arguments left to right.
+    pub fn reader(&self) -> ScalarArgReader<'_, 'a> {
+        ScalarArgReader {
+            args: self,
+            index: 0,
+        }
+    }
+
+    fn type_of(&self, index: usize) -> &LogicalTypeId {
+        &self.types[index]
+    }
+
+    fn read<T: Copy>(&self, index: usize) -> T {
+        self.vectors[index].as_slice_with_len::<T>(self.len)[self.row]
+    }
+
+    /// Reads a DECIMAL, stored as an integer scaled by `10^scale` whose size
+    /// depends on the width.
+    fn read_decimal(&self, index: usize) -> f64 {
+        let (width, scale) = self.decimals[index];
+        let unscaled = match width {
+            0..=4 => self.read::<i16>(index) as f64,
+            5..=9 => self.read::<i32>(index) as f64,
+            10..=18 => self.read::<i64>(index) as f64,
+            _ => {
+                let value = self.read::<duckdb_hugeint>(index);
+                (((value.upper as i128) << 64) | value.lower as i128) as f64
+            }
+        };
+        unscaled / 10f64.powi(scale as i32)
+    }
+
+    fn read_string(&self, index: usize) -> String {
+        let mut value = self.read::<duckdb_string_t>(index);
+        DuckString::new(&mut value).as_str().to_string()
+    }
+}
+
+/// Sequential argument reader used by [`scalar_args!`](crate::scalar_args).
+pub struct ScalarArgReader<'r, 'a> {
+    args: &'r ScalarArgs<'a>,
+    index: usize,
+}
+
+impl ScalarArgReader<'_, '_> {
+    /// Extracts the next argument as `T`.
+    pub fn next<T: FromScalarArg>(&mut self) -> ScalarResult<T> {
+        let value = self.args.get::<T>(self.index)?;
+        self.index += 1;
+        Ok(value)
+    }
+}
+
+/// Conversion from a scalar UDF argument to a Rust value.
+pub trait FromScalarArg: Sized {
+    /// Converts the argument at `index`.
+    fn from_arg(args: &ScalarArgs, index: usize) -> ScalarResult<Self>;
+}
+
+fn null_error(index: usize) -> Box<dyn Error> {
+    format!(
+        "argument {} is NULL; use Option<T> to accept NULL values",
+        index
+    )
+    .into()
+}
+
+fn type_error(index: usize, expected: &str, actual: &LogicalTypeId) -> Box<dyn Error> {
+    format!("argument {} must be {}, got {:?}", index, expected, actual).into()
+}
+
+impl FromScalarArg for String {
+    fn from_arg(args: &ScalarArgs, index: usize) -> ScalarResult<Self> {
+        if args.is_null(index) {
+            return Err(null_error(index));
+        }
+        match args.type_of(index) {
+            LogicalTypeId::Varchar => Ok(args.read_string(index)),
+            other => Err(type_error(index, "VARCHAR", other)),
+        }
+    }
+}
+
+impl FromScalarArg for i64 {
+    fn from_arg(args: &ScalarArgs, index: usize) -> ScalarResult<Self> {
+        if args.is_null(index) {
+            return Err(null_error(index));
+        }
+        match args.type_of(index) {
+            LogicalTypeId::Tinyint => Ok(args.read::<i8>(index) as i64),
+            LogicalTypeId::Smallint => Ok(args.read::<i16>(index) as i64),
+            LogicalTypeId::Integer => Ok(args.read::<i32>(index) as i64),
+            LogicalTypeId::Bigint => Ok(args.read::<i64>(index)),
+            other => Err(type_error(index, "an integer", other)),
+        }
+    }
+}
+
+impl FromScalarArg for f64 {
+    fn from_arg(args: &ScalarArgs, index: usize) -> ScalarResult<Self> {
+        if args.is_null(index) {
+            return Err(null_error(index));
+        }
+        match args.type_of(index) {
+            LogicalTypeId::Float => Ok(args.read::<f32>(index) as f64),
+            LogicalTypeId::Double => Ok(args.read::<f64>(index)),
+            LogicalTypeId::Decimal => Ok(args.read_decimal(index)),
+            _ => i64::from_arg(args, index)
+                .map(|v| v as f64)
+                .map_err(|_| type_error(index, "numeric", args.type_of(index))),
+        }
+    }
+}
+
+impl FromScalarArg for bool {
+    fn from_arg(args: &ScalarArgs, index: usize) -> ScalarResult<Self> {
+        if args.is_null(index) {
+            return Err(null_error(index));
+        }
+        match args.type_of(index) {
+            LogicalTypeId::Boolean => Ok(args.read::<bool>(index)),
+            other => Err(type_error(index, "BOOLEAN", other)),
+        }
+    }
+}
+
+impl<T: FromScalarArg> FromScalarArg for Option<T> {
+    fn from_arg(args: &ScalarArgs, index: usize) -> ScalarResult<Self> {
+        if args.is_null(index) {
+            return Ok(None);
+        }
+        T::from_arg(args, index).map(Some)
+    }
+}
+
+/// Conversion from a Rust value to a scalar UDF result.
+pub trait ScalarOutput: 'static {
+    /// The SQL return type of the function.
+    fn logical_type_id() -> LogicalTypeId;
+
+    /// Writes the value into `row` of the output vector.
+    fn write(self, output: &mut FlatVector, row: usize);
+}
+
+impl ScalarOutput for String {
+    fn logical_type_id() -> LogicalTypeId {
+        LogicalTypeId::Varchar
+    }
+
+    fn write(self, output: &mut FlatVector, row: usize) {
+        output.insert(row, self.as_str());
+    }
+}
+
+impl ScalarOutput for i64 {
+    fn logical_type_id() -> LogicalTypeId {
+        LogicalTypeId::Bigint
+    }
+
+    fn write(self, output: &mut FlatVector, row: usize) {
+        output.as_mut_slice::<i64>()[row] = self;
+    }
+}
+
+impl ScalarOutput for f64 {
+    fn logical_type_id() -> LogicalTypeId {
+        LogicalTypeId::Double
+    }
+
+    fn write(self, output: &mut FlatVector, row: usize) {
+        output.as_mut_slice::<f64>()[row] = self;
+    }
+}
+
+impl ScalarOutput for bool {
+    fn logical_type_id() -> LogicalTypeId {
+        LogicalTypeId::Boolean
+    }
+
+    fn write(self, output: &mut FlatVector, row: usize) {
+        output.as_mut_slice::<bool>()[row] = self;
+    }
+}
+
+impl<T: ScalarOutput> ScalarOutput for Option<T> {
+    fn logical_type_id() -> LogicalTypeId {
+        T::logical_type_id()
+    }
+
+    fn write(self, output: &mut FlatVector, row: usize) {
+        match self {
+            Some(value) => value.write(output, row),
+            None => output.set_null(row),
+        }
+    }
+}
+
+/// [`VScalar`] adapter that calls a boxed closure once per row.
+struct ClosureScalar<R>(PhantomData<R>);
+
+impl<R: ScalarOutput> VScalar for ClosureScalar<R> {
+    type State = ScalarFn<R>;
+
+    unsafe fn invoke(
+        state: &Self::State,
+        input: &mut DataChunkHandle,
+        output: &mut dyn WritableVector,
+    ) -> Result<(), Box<dyn Error>> {
+        let len = input.len();
+        let vectors: Vec<FlatVector> = (0..input.num_columns())
+            .map(|i| input.flat_vector(i))
+            .collect();
+        let logical_types: Vec<_> = vectors.iter().map(|v| v.logical_type()).collect();
+        let types: Vec<LogicalTypeId> = logical_types.iter().map(|t| t.id()).collect();
+        let decimals: Vec<(u8, u8)> = logical_types
+            .iter()
+            .map(|t| (t.decimal_width(), t.decimal_scale()))
+            .collect();
+        let mut output = output.flat_vector();
+
+        for row in 0..len {
+            let args = ScalarArgs {
+                vectors: &vectors,
+                types: &types,
+                decimals: &decimals,
+                len,
+                row,
+            };
+            state(&args)?.write(&mut output, row);
+        }
+        Ok(())
+    }
+
+    fn signatures() -> Vec<ScalarFunctionSignature> {
+        vec![ScalarFunctionSignature::variadic(
+            LogicalTypeId::Any.into(),
+            R::logical_type_id().into(),
+        )]
+    }
+}
+
+impl Connection {
+    /// Registers a Rust closure as a SQL scalar function.
+    ///
+    /// The function accepts any number of arguments of any type; extract
+    /// them with [`scalar_args!`](crate::scalar_args). The return type is
+    /// inferred from the closure's `Ok` type.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - SQL name of the function
+    /// * `func` - Closure called once per row
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::{scalar_args, Connection};
+    ///
+    /// let conn = Connection::open_in_memory()?;
+    /// conn.register_scalar("add_one", |args| {
+    ///     let (n,) = scalar_args!(args; i64);
+    ///     Ok(n + 1)
+    /// })?;
+    ///
+    /// let value: i64 = conn.query_row("SELECT add_one(41)", [], |row| row.get(0))?;
+    /// assert_eq!(value, 42);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if DuckDB rejects the registration. Errors returned
+    /// by the closure surface as SQL errors when the function is executed.
+    pub fn register_scalar<R, F>(&self, name: &str, func: F) -> crate::Result<()>
+    where
+        R: ScalarOutput,
+        F: Fn(&ScalarArgs) -> ScalarResult<R> + Send + Sync + 'static,
+    {
+        let state: ScalarFn<R> = Arc::new(func);
+        self.register_scalar_function_with_state::<ClosureScalar<R>>(name, &state)
+    }
+}
+
+/// Extracts typed arguments from [`ScalarArgs`] as a tuple.
+///
+/// Checks that the number of arguments matches the number of listed types,
+/// then converts each argument in order. Must be used inside a closure that
+/// returns [`ScalarResult`], since conversion errors are propagated with `?`.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::{scalar_args, Connection};
+///
+/// let conn = Connection::open_in_memory()?;
+/// conn.register_scalar("greet", |args| {
+///     let (name, excited) = scalar_args!(args; String, Option<bool>);
+///     let suffix = if excited.unwrap_or(false) { "!" } else { "." };
+///     Ok(format!("Hello, {}{}", name, suffix))
+/// })?;
+/// ```
+#[macro_export]
+macro_rules! scalar_args {
+    ($args:expr; $($ty:ty),+ $(,)?) => {{
+        let args: &$crate::scalar::ScalarArgs = $args;
+        args.expect_len(<[&str]>::len(&[$(stringify!($ty)),+]))?;
+        let mut reader = args.reader();
+        ($(reader.next::<$ty>()?,)+)
+    }};
+}
+
+/// Normalizes text for comparison: trims, lowercases and collapses whitespace.
+///
+/// This is the implementation behind the `normalize_text` SQL function.
+///
+/// # Examples
+///
+/// ```rust
+/// use frozen_duckdb::scalar::normalize_text;
+///
+/// assert_eq!(normalize_text("  Hello,\tWORLD  "), "hello, world");
+/// ```
+pub fn normalize_text(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Registers the built-in scalar functions used by the CLI.
+///
+/// # Functions Registered
+///
+/// - `normalize_text(text VARCHAR) -> VARCHAR`: see [`normalize_text`]
+pub fn register_builtins(conn: &Connection) -> crate::Result<()> {
+    conn.register_scalar("normalize_text", |args| {
+        let (text,) = scalar_args!(args; Option<String>);
+        Ok(text.map(|t| normalize_text(&t)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_text() {
+        assert_eq!(normalize_text("  Hello,\tWORLD  "), "hello, world");
+        assert_eq!(normalize_text("a\n\nb"), "a b");
+        assert_eq!(normalize_text(""), "");
+    }
+
+    #[test]
+    fn test_register_scalar_typed_args() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.register_scalar("scale", |args| {
+            let (value, factor) = scalar_args!(args; f64, i64);
+            Ok(value * factor as f64)
+        })
+        .unwrap();
+
+        let value: f64 = conn
+            .query_row("SELECT scale(1.5, 4)", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(value, 6.0);
+
+        // DECIMAL(38, 2) is stored as a 128-bit integer
+        let value: f64 = conn
+            .query_row(
+                "SELECT scale(CAST(-12.25 AS DECIMAL(38, 2)), 2)",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(value, -24.5);
+    }
+
+    #[test]
+    fn test_register_scalar_over_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        register_builtins(&conn).unwrap();
+
+        conn.execute_batch(
+            "CREATE TABLE names (name VARCHAR);
+             INSERT INTO names VALUES ('  Alice '), ('ALICE'), ('bob'), (NULL);",
+        )
+        .unwrap();
+
+        let distinct: i64 = conn
+            .query_row(
+                "SELECT COUNT(DISTINCT normalize_text(name)) FROM names",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(distinct, 2);
+
+        let nulls: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM names WHERE normalize_text(name) IS NULL",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(nulls, 1);
+    }
+
+    #[test]
+    fn test_register_scalar_argument_errors() {
+        let conn = Connection::open_in_memory().unwrap();
+        register_builtins(&conn).unwrap();
+
+        let wrong_arity: crate::Result<String> =
+            conn.query_row("SELECT normalize_text('a', 'b')", [], |row| row.get(0));
+        assert!(wrong_arity.is_err());
+
+        let wrong_type: crate::Result<String> =
+            conn.query_row("SELECT normalize_text(42)", [], |row| row.get(0));
+        assert!(wrong_type.is_err());
+    }
+}
@@ -0,0 +1,377 @@
+//! # Text Chunking
+//!
+//! This module splits long texts into chunks that fit LLM context windows,
+//! so the summarize and index commands chunk documents consistently.
+//!
+//! ## Chunkers
+//!
+//! - **Fixed**: Consecutive, non-overlapping pieces of a fixed size
+//! - **Window**: Fixed-size pieces where each chunk overlaps the previous one
+//! - **Sentence**: Whole sentences packed greedily up to a maximum size;
+//!   sentences longer than the maximum are split at the size limit
+//!
+//! Sizes are measured in characters or in approximate tokens
+//! ([`CHARS_PER_TOKEN`] characters per token). Chunks never split a UTF-8
+//! character, and every chunk records its byte range in the source text.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use frozen_duckdb::text::chunk::{ChunkUnit, Chunker};
+//!
+//! let chunker = Chunker::Sentence { max_size: 512, unit: ChunkUnit::Tokens };
+//! for chunk in chunker.chunk("First sentence. Second sentence.") {
+//!     println!("{}..{}: {}", chunk.start, chunk.end, chunk.text);
+//! }
+//! ```
+
+use anyhow::Result;
+
+/// Approximate number of characters per token for English text.
+pub const CHARS_PER_TOKEN: usize = 4;
+
+/// Unit in which chunk sizes are measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkUnit {
+    /// Unicode characters
+    Chars,
+    /// Approximate tokens ([`CHARS_PER_TOKEN`] characters each)
+    Tokens,
+}
+
+impl ChunkUnit {
+    /// Converts a size in this unit to characters.
+    pub fn to_chars(self, size: usize) -> usize {
+        match self {
+            ChunkUnit::Chars => size,
+            ChunkUnit::Tokens => size.saturating_mul(CHARS_PER_TOKEN),
+        }
+    }
+}
+
+/// A contiguous piece of a source text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// Chunk content, equal to `source[start..end]`
+    pub text: String,
+    /// Byte offset of the chunk start in the source text
+    pub start: usize,
+    /// Byte offset one past the chunk end in the source text
+    pub end: usize,
+}
+
+/// Chunking strategy and size configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chunker {
+    /// Non-overlapping chunks of exactly `size` (the last may be shorter)
+    Fixed { size: usize, unit: ChunkUnit },
+    /// Chunks of `size` where consecutive chunks share `overlap`
+    Window {
+        size: usize,
+        overlap: usize,
+        unit: ChunkUnit,
+    },
+    /// Whole sentences packed into chunks of at most `max_size`
+    Sentence { max_size: usize, unit: ChunkUnit },
+}
+
+impl Chunker {
+    /// Builds a chunker from CLI-style options.
+    ///
+    /// # Arguments
+    ///
+    /// * `strategy` - One of `fixed`, `window`, `sentence`
+    /// * `size` - Chunk size (maximum size for `sentence`)
+    /// * `overlap` - Overlap between chunks, only used by `window`
+    /// * `unit` - One of `chars`, `tokens`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use frozen_duckdb::text::chunk::Chunker;
+    ///
+    /// let chunker = Chunker::parse("window", 256, 32, "tokens")?;
+    /// ```
+    pub fn parse(strategy: &str, size: usize, overlap: usize, unit: &str) -> Result<Self> {
+        let unit = match unit {
+            "chars" => ChunkUnit::Chars,
+            "tokens" => ChunkUnit::Tokens,
+            other => {
+                return Err(anyhow::anyhow!(
+                    "Unknown chunk unit: {} (available: chars, tokens)",
+                    other
+                ))
+            }
+        };
+        if size == 0 {
+            return Err(anyhow::anyhow!("Chunk size must be greater than zero"));
+        }
+
+        match strategy {
+            "fixed" => Ok(Chunker::Fixed { size, unit }),
+            "window" => {
+                if overlap >= size {
+                    return Err(anyhow::anyhow!(
+                        "Chunk overlap ({}) must be smaller than chunk size ({})",
+                        overlap,
+                        size
+                    ));
+                }
+                Ok(Chunker::Window {
+                    size,
+                    overlap,
+                    unit,
+                })
+            }
+            "sentence" => Ok(Chunker::Sentence {
+                max_size: size,
+                unit,
+            }),
+            other => Err(anyhow::anyhow!(
+                "Unknown chunker: {} (available: fixed, window, sentence)",
+                other
+            )),
+        }
+    }
+
+    /// Splits `text` into chunks. Empty text yields no chunks.
+    pub fn chunk(&self, text: &str) -> Vec<Chunk> {
+        match *self {
+            Chunker::Fixed { size, unit } => fixed_chunks(text, unit.to_chars(size)),
+            Chunker::Window {
+                size,
+                overlap,
+                unit,
+            } => window_chunks(text, unit.to_chars(size), unit.to_chars(overlap)),
+            Chunker::Sentence { max_size, unit } => sentence_chunks(text, unit.to_chars(max_size)),
+        }
+    }
+}
+
+/// Splits `text` into consecutive chunks of `size` characters.
+///
+/// A `size` of zero is treated as one.
+pub fn fixed_chunks(text: &str, size: usize) -> Vec<Chunk> {
+    window_chunks(text, size, 0)
+}
+
+/// Splits `text` into chunks of `size` characters, each starting
+/// `size - overlap` characters after the previous one.
+///
+/// A `size` of zero is treated as one, and `overlap` is capped at
+/// `size - 1` so every chunk makes progress.
+pub fn window_chunks(text: &str, size: usize, overlap: usize) -> Vec<Chunk> {
+    let size = size.max(1);
+    let step = size - overlap.min(size - 1);
+    let offsets = char_offsets(text);
+    let char_count = offsets.len() - 1;
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < char_count {
+        let end = (start + size).min(char_count);
+        chunks.push(make_chunk(text, offsets[start], offsets[end]));
+        if end == char_count {
+            break;
+        }
+        start += step;
+    }
+    chunks
+}
+
+/// Packs whole sentences into chunks of at most `max_size` characters.
+///
+/// Sentences end after `.`, `!` or `?` followed by whitespace, or at a
+/// newline; trailing whitespace stays with its sentence so the chunks
+/// cover the text without gaps. Sentences longer than `max_size` are
+/// split into fixed-size pieces.
+pub fn sentence_chunks(text: &str, max_size: usize) -> Vec<Chunk> {
+    let max_size = max_size.max(1);
+    let offsets = char_offsets(text);
+
+    let mut chunks = Vec::new();
+    let mut current: Option<(usize, usize)> = None;
+    for (start, end) in sentence_spans(text) {
+        if end - start > max_size {
+            if let Some((cs, ce)) = current.take() {
+                chunks.push(make_chunk(text, offsets[cs], offsets[ce]));
+            }
+            let mut piece = start;
+            while piece < end {
+                let piece_end = (piece + max_size).min(end);
+                chunks.push(make_chunk(text, offsets[piece], offsets[piece_end]));
+                piece = piece_end;
+            }
+            continue;
+        }
+
+        current = match current {
+            Some((cs, _)) if end - cs <= max_size => Some((cs, end)),
+            Some((cs, ce)) => {
+                chunks.push(make_chunk(text, offsets[cs], offsets[ce]));
+                Some((start, end))
+            }
+            None => Some((start, end)),
+        };
+    }
+    if let Some((cs, ce)) = current {
+        chunks.push(make_chunk(text, offsets[cs], offsets[ce]));
+    }
+    chunks
+}
+
+/// Returns sentence spans as character index ranges covering `text`.
+fn sentence_spans(text: &str) -> Vec<(usize, usize)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let ends_sentence = c == '\n'
+            || (matches!(c, '.' | '!' | '?')
+                && chars.get(i + 1).map_or(true, |next| next.is_whitespace()));
+        i += 1;
+
+        if ends_sentence {
+            while i < chars.len() && chars[i].is_whitespace() {
+                i += 1;
+            }
+            spans.push((start, i));
+            start = i;
+        }
+    }
+    if start < chars.len() {
+        spans.push((start, chars.len()));
+    }
+    spans
+}
+
+/// Byte offset of every character, followed by `text.len()`.
+fn char_offsets(text: &str) -> Vec<usize> {
+    text.char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(text.len()))
+        .collect()
+}
+
+fn make_chunk(text: &str, start: usize, end: usize) -> Chunk {
+    Chunk {
+        text: text[start..end].to_string(),
+        start,
+        end,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn char_len(chunk: &Chunk) -> usize {
+        chunk.text.chars().count()
+    }
+
+    fn reassemble(chunks: &[Chunk]) -> String {
+        chunks.iter().map(|c| c.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sentence_chunks_keep_sentences_whole() {
+        let text = "One. Two two. Three three three.";
+        let chunks = sentence_chunks(text, 18);
+        let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec!["One. Two two. ", "Three three three."]);
+    }
+
+    #[test]
+    fn test_window_chunks_overlap() {
+        let chunks = window_chunks("abcdefghij", 4, 2);
+        let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec!["abcd", "cdef", "efgh", "ghij"]);
+    }
+
+    #[test]
+    fn test_token_unit_scales_size() {
+        let chunker = Chunker::Fixed {
+            size: 2,
+            unit: ChunkUnit::Tokens,
+        };
+        let chunks = chunker.chunk(&"x".repeat(20));
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(char_len(&chunks[0]), 2 * CHARS_PER_TOKEN);
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_options() {
+        assert!(Chunker::parse("window", 10, 10, "chars").is_err());
+        assert!(Chunker::parse("fixed", 0, 0, "chars").is_err());
+        assert!(Chunker::parse("paragraph", 10, 0, "chars").is_err());
+        assert!(Chunker::parse("sentence", 10, 0, "words").is_err());
+        assert_eq!(
+            Chunker::parse("sentence", 10, 0, "tokens").unwrap(),
+            Chunker::Sentence {
+                max_size: 10,
+                unit: ChunkUnit::Tokens
+            }
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_fixed_chunks_cover_text(text in "[a-zé世 .!?\n]{0,300}", size in 1usize..40) {
+            let chunks = fixed_chunks(&text, size);
+            prop_assert_eq!(reassemble(&chunks), text.clone());
+            for (i, chunk) in chunks.iter().enumerate() {
+                prop_assert_eq!(&text[chunk.start..chunk.end], chunk.text.as_str());
+                prop_assert!(char_len(chunk) >= 1 && char_len(chunk) <= size);
+                if i + 1 < chunks.len() {
+                    prop_assert_eq!(char_len(chunk), size);
+                }
+            }
+        }
+
+        #[test]
+        fn prop_window_chunks_overlap_exactly(
+            text in "[a-zé世 .!?\n]{0,300}",
+            size in 1usize..40,
+            overlap in 0usize..40,
+        ) {
+            let overlap = overlap.min(size - 1);
+            let chunks = window_chunks(&text, size, overlap);
+            prop_assert_eq!(chunks.is_empty(), text.is_empty());
+            if let (Some(first), Some(last)) = (chunks.first(), chunks.last()) {
+                prop_assert_eq!(first.start, 0);
+                prop_assert_eq!(last.end, text.len());
+            }
+            for pair in chunks.windows(2) {
+                let shared = &text[pair[1].start..pair[0].end];
+                prop_assert_eq!(shared.chars().count(), overlap);
+                prop_assert_eq!(char_len(&pair[0]), size);
+            }
+        }
+
+        #[test]
+        fn prop_sentence_chunks_respect_boundaries(
+            text in "[a-zé世 .!?\n]{0,300}",
+            max_size in 1usize..60,
+        ) {
+            let chunks = sentence_chunks(&text, max_size);
+            prop_assert_eq!(reassemble(&chunks), text.clone());
+
+            let boundaries: Vec<usize> = sentence_spans(&text)
+                .iter()
+                .map(|(_, end)| char_offsets(&text)[*end])
+                .collect();
+            for chunk in &chunks {
+                prop_assert!(char_len(chunk) >= 1 && char_len(chunk) <= max_size);
+                // A chunk shorter than the limit only ends mid-sentence at the end of a
+                // sentence that was itself too long to fit
+                if char_len(chunk) < max_size && chunk.end != text.len() {
+                    prop_assert!(boundaries.contains(&chunk.end));
+                }
+            }
+        }
+    }
+}
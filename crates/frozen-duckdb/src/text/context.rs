@@ -0,0 +1,510 @@
+//! # Context Window Budgeting
+//!
+//! This module fits the context sent with an LLM call into the model's
+//! context window before the call is made, so oversized inputs are reduced
+//! predictably instead of failing inside the provider.
+//!
+//! The budget is the window minus the tokens reserved for the response and
+//! the tokens of the fixed prompt; the documents share what remains. Token
+//! counts are estimates from [`crate::text::tokens`].
+//!
+//! ## Strategies
+//!
+//! Documents are assumed to be ordered by rank, most important first.
+//!
+//! - **truncate-tail**: Keep the beginning; cut the document that crosses
+//!   the budget and drop everything after it
+//! - **truncate-head**: Keep the end; cut the document that crosses the
+//!   budget and drop everything before it
+//! - **drop-lowest**: Drop whole documents from the end; if the top
+//!   document alone is too large, cut its tail
+//! - **summarize**: Summarize documents larger than their share of the
+//!   budget, then drop the lowest-ranked ones if still over
+//!
+//! Every change is reported as a [`ContextAction`].
+//!
+//! # Examples
+//!
+//! ```rust
+//! use frozen_duckdb::text::context::{ContextBudget, ContextStrategy};
+//!
+//! let budget = ContextBudget {
+//!     max_tokens: 4096,
+//!     strategy: ContextStrategy::DropLowestRanked,
+//!     ..Default::default()
+//! };
+//! let fitted = budget.fit("Summarize:", documents, |text, _| Ok(text.to_string()))?;
+//! for action in &fitted.actions {
+//!     println!("{}", action);
+//! }
+//! ```
+
+use super::tokens::count_tokens;
+use anyhow::{anyhow, Result};
+use std::fmt;
+
+/// How [`ContextBudget::fit`] reduces documents that exceed the budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextStrategy {
+    /// Keep the end of the context
+    TruncateHead,
+    /// Keep the beginning of the context
+    TruncateTail,
+    /// Drop the lowest-ranked documents
+    DropLowestRanked,
+    /// Summarize oversized documents before inserting them
+    Summarize,
+}
+
+impl ContextStrategy {
+    /// Parses `truncate-head`, `truncate-tail`, `drop-lowest`, or `summarize`.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "truncate-head" => Ok(Self::TruncateHead),
+            "truncate-tail" => Ok(Self::TruncateTail),
+            "drop-lowest" => Ok(Self::DropLowestRanked),
+            "summarize" => Ok(Self::Summarize),
+            other => Err(anyhow!(
+                "Unknown context strategy: {} (available: truncate-head, truncate-tail, drop-lowest, summarize)",
+                other
+            )),
+        }
+    }
+}
+
+/// A context window and the strategy used to stay within it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextBudget {
+    /// Model context window in tokens
+    pub max_tokens: usize,
+    /// Tokens kept free for the model's response
+    pub reserved_output: usize,
+    /// Reduction applied when the context does not fit
+    pub strategy: ContextStrategy,
+}
+
+impl Default for ContextBudget {
+    fn default() -> Self {
+        Self {
+            max_tokens: 8192,
+            reserved_output: 512,
+            strategy: ContextStrategy::TruncateTail,
+        }
+    }
+}
+
+/// One change made by [`ContextBudget::fit`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContextAction {
+    /// Document cut from `from` to `to` tokens
+    Truncated {
+        index: usize,
+        from: usize,
+        to: usize,
+    },
+    /// Document removed entirely
+    Dropped { index: usize, tokens: usize },
+    /// Document replaced by a summary
+    Summarized {
+        index: usize,
+        from: usize,
+        to: usize,
+    },
+}
+
+impl fmt::Display for ContextAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated { index, from, to } => write!(
+                f,
+                "truncated document {} from ~{} to ~{} tokens",
+                index + 1,
+                from,
+                to
+            ),
+            Self::Dropped { index, tokens } => {
+                write!(f, "dropped document {} (~{} tokens)", index + 1, tokens)
+            }
+            Self::Summarized { index, from, to } => write!(
+                f,
+                "summarized document {} from ~{} to ~{} tokens",
+                index + 1,
+                from,
+                to
+            ),
+        }
+    }
+}
+
+/// Documents after [`ContextBudget::fit`], with what was changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FittedContext {
+    /// Remaining documents, in input order
+    pub documents: Vec<String>,
+    /// Changes made, in the order they were applied
+    pub actions: Vec<ContextAction>,
+}
+
+impl ContextBudget {
+    /// Tokens available to documents sent with `prompt`.
+    pub fn available(&self, prompt: &str) -> usize {
+        self.max_tokens
+            .saturating_sub(self.reserved_output)
+            .saturating_sub(count_tokens(prompt))
+    }
+
+    /// Reduces `documents` so that they fit alongside `prompt`.
+    ///
+    /// `summarize(text, max_tokens)` is only called by the summarize
+    /// strategy; it receives text that fits the window on its own.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prompt alone leaves no room for documents,
+    /// or if `summarize` fails.
+    pub fn fit<F>(
+        &self,
+        prompt: &str,
+        documents: Vec<String>,
+        mut summarize: F,
+    ) -> Result<FittedContext>
+    where
+        F: FnMut(&str, usize) -> Result<String>,
+    {
+        let available = self.available(prompt);
+        let tokens: Vec<usize> = documents.iter().map(|d| count_tokens(d)).collect();
+        if tokens.iter().sum::<usize>() <= available {
+            return Ok(FittedContext {
+                documents,
+                actions: Vec::new(),
+            });
+        }
+        if available == 0 {
+            return Err(anyhow!(
+                "Prompt (~{} tokens) leaves no room for context in a {}-token window with {} tokens reserved",
+                count_tokens(prompt),
+                self.max_tokens,
+                self.reserved_output
+            ));
+        }
+
+        let mut slots: Vec<Option<String>> = documents.into_iter().map(Some).collect();
+        let mut actions = Vec::new();
+        match self.strategy {
+            ContextStrategy::TruncateTail => {
+                keep_within(&mut slots, &tokens, available, false, &mut actions);
+            }
+            ContextStrategy::TruncateHead => {
+                keep_within(&mut slots, &tokens, available, true, &mut actions);
+            }
+            ContextStrategy::DropLowestRanked => {
+                drop_lowest(&mut slots, tokens, available, &mut actions);
+            }
+            ContextStrategy::Summarize => {
+                let share = (available / slots.len()).max(1);
+                let mut sizes = tokens.clone();
+                for (index, slot) in slots.iter_mut().enumerate() {
+                    if tokens[index] <= share {
+                        continue;
+                    }
+                    let text = slot.take().unwrap_or_default();
+                    let input = truncate_tokens(&text, available, false);
+                    let summary = summarize(&input, share)?;
+                    sizes[index] = count_tokens(&summary);
+                    actions.push(ContextAction::Summarized {
+                        index,
+                        from: tokens[index],
+                        to: sizes[index],
+                    });
+                    *slot = Some(summary);
+                }
+                drop_lowest(&mut slots, sizes, available, &mut actions);
+            }
+        }
+
+        Ok(FittedContext {
+            documents: slots.into_iter().flatten().collect(),
+            actions,
+        })
+    }
+}
+
+/// Keeps documents from the front (or back, with `from_end`) until the
+/// budget is used up, cutting the one that crosses it.
+fn keep_within(
+    slots: &mut [Option<String>],
+    tokens: &[usize],
+    available: usize,
+    from_end: bool,
+    actions: &mut Vec<ContextAction>,
+) {
+    let mut order: Vec<usize> = (0..slots.len()).collect();
+    if from_end {
+        order.reverse();
+    }
+    let mut used = 0;
+    let mut dropped = Vec::new();
+    for index in order {
+        let remaining = available - used;
+        if tokens[index] <= remaining {
+            used += tokens[index];
+        } else if remaining > 0 {
+            let text = slots[index].take().unwrap_or_default();
+            let cut = truncate_tokens(&text, remaining, from_end);
+            let size = count_tokens(&cut);
+            used += size;
+            actions.push(ContextAction::Truncated {
+                index,
+                from: tokens[index],
+                to: size,
+            });
+            slots[index] = Some(cut);
+        } else {
+            slots[index] = None;
+            dropped.push(index);
+        }
+    }
+    dropped.sort_unstable();
+    actions.extend(dropped.into_iter().map(|index| ContextAction::Dropped {
+        index,
+        tokens: tokens[index],
+    }));
+}
+
+/// Drops documents from the back until the rest fits, cutting the first
+/// document's tail if it is too large on its own.
+fn drop_lowest(
+    slots: &mut [Option<String>],
+    tokens: Vec<usize>,
+    available: usize,
+    actions: &mut Vec<ContextAction>,
+) {
+    let mut total: usize = tokens.iter().sum();
+    for index in (1..slots.len()).rev() {
+        if total <= available {
+            return;
+        }
+        if slots[index].take().is_some() {
+            total -= tokens[index];
+            actions.push(ContextAction::Dropped {
+                index,
+                tokens: tokens[index],
+            });
+        }
+    }
+    if total > available {
+        if let Some(text) = slots[0].take() {
+            let cut = truncate_tokens(&text, available, false);
+            actions.push(ContextAction::Truncated {
+                index: 0,
+                from: tokens[0],
+                to: count_tokens(&cut),
+            });
+            slots[0] = Some(cut);
+        }
+    }
+}
+
+/// Returns the longest prefix (or suffix, with `keep_end`) of `text`
+/// estimated at no more than `max_tokens` tokens.
+///
+/// Cuts fall on character boundaries, preferring whitespace.
+pub fn truncate_tokens(text: &str, max_tokens: usize, keep_end: bool) -> String {
+    if count_tokens(text) <= max_tokens {
+        return text.to_string();
+    }
+    let boundaries: Vec<usize> = text
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain([text.len()])
+        .collect();
+    let piece = |cut: usize| {
+        if keep_end {
+            &text[boundaries[boundaries.len() - 1 - cut]..]
+        } else {
+            &text[..boundaries[cut]]
+        }
+    };
+
+    // Binary search on the number of characters kept
+    let (mut low, mut high) = (0, boundaries.len() - 1);
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        if count_tokens(piece(mid)) <= max_tokens {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+
+    let kept = piece(low);
+    // Back off to whitespace when the cut falls inside a word, as long as
+    // that keeps most of the text
+    let splits_word = if keep_end {
+        !kept.starts_with(char::is_whitespace)
+            && text[..text.len() - kept.len()].ends_with(|c: char| !c.is_whitespace())
+    } else {
+        !kept.ends_with(char::is_whitespace)
+            && text[kept.len()..].starts_with(|c: char| !c.is_whitespace())
+    };
+    let trimmed = match (splits_word, keep_end) {
+        (true, true) => kept.find(char::is_whitespace).map(|i| &kept[i..]),
+        (true, false) => kept.rfind(char::is_whitespace).map(|i| &kept[..i]),
+        (false, _) => Some(kept),
+    }
+    .filter(|trimmed| trimmed.len() * 2 >= kept.len())
+    .unwrap_or(kept);
+
+    if keep_end {
+        trimmed.trim_start().to_string()
+    } else {
+        trimmed.trim_end().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(n: usize, word: &str) -> String {
+        vec![word; n].join(" ")
+    }
+
+    fn budget(max_tokens: usize, strategy: ContextStrategy) -> ContextBudget {
+        ContextBudget {
+            max_tokens,
+            reserved_output: 0,
+            strategy,
+        }
+    }
+
+    fn no_summary(_: &str, _: usize) -> Result<String> {
+        panic!("summarize called")
+    }
+
+    #[test]
+    fn test_strategy_parse() {
+        assert_eq!(
+            ContextStrategy::parse("drop-lowest").unwrap(),
+            ContextStrategy::DropLowestRanked
+        );
+        assert!(ContextStrategy::parse("random").is_err());
+    }
+
+    #[test]
+    fn test_fitting_context_is_untouched() -> Result<()> {
+        let documents = vec![words(10, "alpha"), words(10, "beta")];
+        let fitted =
+            budget(100, ContextStrategy::TruncateTail).fit("", documents.clone(), no_summary)?;
+        assert_eq!(fitted.documents, documents);
+        assert!(fitted.actions.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate_tail_and_head() -> Result<()> {
+        let documents = vec![words(30, "alpha"), words(30, "beta"), words(30, "gamma")];
+
+        let tail =
+            budget(45, ContextStrategy::TruncateTail).fit("", documents.clone(), no_summary)?;
+        assert_eq!(tail.documents.len(), 2);
+        assert_eq!(tail.documents[0], documents[0]);
+        assert!(tail.documents[1].starts_with("beta"));
+        assert_eq!(count_tokens(&tail.documents[1]), 15);
+        assert_eq!(
+            tail.actions,
+            vec![
+                ContextAction::Truncated {
+                    index: 1,
+                    from: 30,
+                    to: 15
+                },
+                ContextAction::Dropped {
+                    index: 2,
+                    tokens: 30
+                },
+            ]
+        );
+
+        let head =
+            budget(45, ContextStrategy::TruncateHead).fit("", documents.clone(), no_summary)?;
+        assert_eq!(head.documents.len(), 2);
+        assert!(head.documents[0].starts_with("beta"));
+        assert_eq!(head.documents[1], documents[2]);
+        assert_eq!(
+            head.actions[1],
+            ContextAction::Dropped {
+                index: 0,
+                tokens: 30
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_lowest_ranked() -> Result<()> {
+        let documents = vec![words(30, "alpha"), words(30, "beta"), words(30, "gamma")];
+        let fitted =
+            budget(65, ContextStrategy::DropLowestRanked).fit("", documents.clone(), no_summary)?;
+        assert_eq!(fitted.documents, documents[..2].to_vec());
+        assert_eq!(
+            fitted.actions,
+            vec![ContextAction::Dropped {
+                index: 2,
+                tokens: 30
+            }]
+        );
+
+        // A single oversized document is cut rather than dropped
+        let fitted =
+            budget(20, ContextStrategy::DropLowestRanked).fit("", documents, no_summary)?;
+        assert_eq!(fitted.documents.len(), 1);
+        assert_eq!(count_tokens(&fitted.documents[0]), 20);
+        Ok(())
+    }
+
+    #[test]
+    fn test_summarize_oversized_documents() -> Result<()> {
+        let documents = vec![words(10, "alpha"), words(80, "beta")];
+        let mut calls = Vec::new();
+        let fitted =
+            budget(50, ContextStrategy::Summarize).fit("", documents.clone(), |text, max| {
+                calls.push((count_tokens(text), max));
+                Ok(words(5, "brief"))
+            })?;
+        assert_eq!(calls, vec![(50, 25)]);
+        assert_eq!(
+            fitted.documents,
+            vec![documents[0].clone(), words(5, "brief")]
+        );
+        assert_eq!(
+            fitted.actions,
+            vec![ContextAction::Summarized {
+                index: 1,
+                from: 80,
+                to: 5
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_prompt_must_leave_room() {
+        let budget = ContextBudget {
+            max_tokens: 10,
+            reserved_output: 8,
+            strategy: ContextStrategy::TruncateTail,
+        };
+        assert!(budget
+            .fit(&words(5, "word"), vec![words(5, "doc")], no_summary)
+            .is_err());
+    }
+
+    #[test]
+    fn test_truncate_tokens_prefers_whitespace() {
+        let text = "one two three four five";
+        assert_eq!(truncate_tokens(text, 2, false), "one two");
+        assert_eq!(truncate_tokens(text, 2, true), "four five");
+        assert_eq!(truncate_tokens(text, 10, false), text);
+    }
+}
@@ -0,0 +1,8 @@
+//! # Text Processing Utilities for Frozen DuckDB
+//!
+//! This module contains text processing shared by the LLM commands
+//! (complete, summarize, index), organized into logical sub-modules.
+
+pub mod chunk;
+pub mod context;
+pub mod tokens;
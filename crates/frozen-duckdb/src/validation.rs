@@ -0,0 +1,557 @@
+//! # Data Validation Rules
+//!
+//! Declarative expectations about a dataset, checked with SQL. Rules are
+//! written in YAML and run with `frozen-duckdb validate-data`, or loaded and
+//! run from Rust, for example in a pipeline's integration tests.
+//!
+//! ## Rule File
+//!
+//! ```yaml
+//! # Name the data is queried as in custom SQL (default: data)
+//! table: orders
+//! rules:
+//!   - type: not_null
+//!     column: id
+//!   - type: unique
+//!     columns: [customer_id, order_date]
+//!   - type: accepted_values
+//!     column: status
+//!     values: [pending, shipped, delivered]
+//!   - type: row_count_between
+//!     min: 1
+//!     max: 10000000
+//!   - type: sql
+//!     name: no negative totals
+//!     sql: SELECT * FROM orders WHERE total < 0
+//!     severity: warn
+//! ```
+//!
+//! | Rule | Fails when |
+//! |------|------------|
+//! | `not_null` | `column` has NULLs |
+//! | `unique` | `column` (or the `columns` combination) has duplicates |
+//! | `accepted_values` | `column` has a non-NULL value outside `values` |
+//! | `row_count_between` | The row count is below `min` or above `max` |
+//! | `sql` | The query returns any rows (each row is a violation) |
+//!
+//! Every rule takes an optional `name` and a `severity` of `error`
+//! (default) or `warn`; only failing `error` rules fail the report. A rule
+//! whose SQL can't run, such as one naming a missing column, fails with
+//! the database error as its message.
+//!
+//! ## Usage Examples
+//!
+//! ```rust
+//! use frozen_duckdb::validation::{validate_table, RuleSet};
+//! use frozen_duckdb::Connection;
+//!
+//! let conn = Connection::open_in_memory()?;
+//! conn.execute_batch("CREATE TABLE orders AS SELECT range AS id FROM range(10)")?;
+//!
+//! let rules = RuleSet::parse("rules:\n  - type: unique\n    column: id\n")?;
+//! let report = validate_table(&conn, "orders", &rules)?;
+//! assert!(report.passed());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::cli::sql_path::path_literal;
+use crate::duckdb::Connection;
+use anyhow::{anyhow, bail, Context, Result};
+use std::fs;
+use std::path::Path;
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// Name the data is queried as unless the rule file sets `table`.
+pub const DEFAULT_TABLE: &str = "data";
+
+/// Violating values quoted in a failure message.
+const SAMPLE_VALUES: usize = 5;
+
+/// An expectation about the data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rule {
+    /// The column has no NULLs
+    NotNull {
+        /// Column checked
+        column: String,
+    },
+    /// The combination of columns has no duplicates
+    Unique {
+        /// Columns forming the key
+        columns: Vec<String>,
+    },
+    /// Every non-NULL value is one of `values`
+    AcceptedValues {
+        /// Column checked
+        column: String,
+        /// Allowed values, compared as text
+        values: Vec<String>,
+    },
+    /// The row count is within bounds
+    RowCountBetween {
+        /// Smallest allowed count
+        min: Option<u64>,
+        /// Largest allowed count
+        max: Option<u64>,
+    },
+    /// The query returns no rows
+    Sql {
+        /// Query selecting violating rows
+        sql: String,
+    },
+}
+
+impl Rule {
+    /// Rule type as written in the rule file.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::NotNull { .. } => "not_null",
+            Self::Unique { .. } => "unique",
+            Self::AcceptedValues { .. } => "accepted_values",
+            Self::RowCountBetween { .. } => "row_count_between",
+            Self::Sql { .. } => "sql",
+        }
+    }
+
+    /// Default check name, such as `not_null(id)`.
+    fn default_name(&self) -> String {
+        match self {
+            Self::NotNull { column } | Self::AcceptedValues { column, .. } => {
+                format!("{}({})", self.kind(), column)
+            }
+            Self::Unique { columns } => format!("unique({})", columns.join(", ")),
+            Self::RowCountBetween { min, max } => format!(
+                "row_count_between({}, {})",
+                min.map_or("-".to_string(), |n| n.to_string()),
+                max.map_or("-".to_string(), |n| n.to_string())
+            ),
+            Self::Sql { .. } => "sql".to_string(),
+        }
+    }
+}
+
+/// How a failing check affects the report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// A failure fails the report
+    Error,
+    /// A failure is reported but doesn't fail the report
+    Warn,
+}
+
+/// A rule with its name and severity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    /// Name shown in the report
+    pub name: String,
+    /// What is checked
+    pub rule: Rule,
+    /// How a failure affects the report
+    pub severity: Severity,
+}
+
+/// Checks loaded from a rule file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleSet {
+    /// Name the data is queried as
+    pub table: String,
+    /// Checks in file order
+    pub checks: Vec<Check>,
+}
+
+impl RuleSet {
+    /// Loads a YAML rule file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read rules: {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid rules: {}", path.display()))
+    }
+
+    /// Parses YAML rules.
+    pub fn parse(yaml: &str) -> Result<Self> {
+        let documents = YamlLoader::load_from_str(yaml)?;
+        let root = documents
+            .first()
+            .ok_or_else(|| anyhow!("Rule file is empty"))?;
+        let table = match &root["table"] {
+            Yaml::BadValue => DEFAULT_TABLE.to_string(),
+            value => value
+                .as_str()
+                .ok_or_else(|| anyhow!("'table' must be a string"))?
+                .to_string(),
+        };
+        let rules = root["rules"]
+            .as_vec()
+            .ok_or_else(|| anyhow!("'rules' must be a list"))?;
+        let checks = rules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| parse_check(rule).with_context(|| format!("Invalid rule {}", i + 1)))
+            .collect::<Result<_>>()?;
+        Ok(Self { table, checks })
+    }
+}
+
+fn parse_check(rule: &Yaml) -> Result<Check> {
+    let string = |key: &str| -> Result<String> {
+        rule[key]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| anyhow!("'{}' is required and must be a string", key))
+    };
+    let count = |key: &str| -> Result<Option<u64>> {
+        match &rule[key] {
+            Yaml::BadValue | Yaml::Null => Ok(None),
+            Yaml::Integer(n) if *n >= 0 => Ok(Some(*n as u64)),
+            _ => bail!("'{}' must be a non-negative integer", key),
+        }
+    };
+
+    let parsed = match string("type")?.as_str() {
+        "not_null" => Rule::NotNull {
+            column: string("column")?,
+        },
+        "unique" => Rule::Unique {
+            columns: match rule["columns"].as_vec() {
+                Some(columns) => columns
+                    .iter()
+                    .map(|c| scalar_text(c).ok_or_else(|| anyhow!("'columns' must be names")))
+                    .collect::<Result<_>>()?,
+                None => vec![string("column")?],
+            },
+        },
+        "accepted_values" => Rule::AcceptedValues {
+            column: string("column")?,
+            values: rule["values"]
+                .as_vec()
+                .ok_or_else(|| anyhow!("'values' must be a list"))?
+                .iter()
+                .map(|v| scalar_text(v).ok_or_else(|| anyhow!("'values' must be scalars")))
+                .collect::<Result<_>>()?,
+        },
+        "row_count_between" => {
+            let (min, max) = (count("min")?, count("max")?);
+            if min.is_none() && max.is_none() {
+                bail!("row_count_between needs 'min', 'max', or both");
+            }
+            Rule::RowCountBetween { min, max }
+        }
+        "sql" => Rule::Sql {
+            sql: string("sql")?,
+        },
+        other => bail!(
+            "Unknown rule type: {} (use not_null, unique, accepted_values, row_count_between, or sql)",
+            other
+        ),
+    };
+
+    let severity = match rule["severity"].as_str() {
+        None | Some("error") => Severity::Error,
+        Some("warn") | Some("warning") => Severity::Warn,
+        Some(other) => bail!("Unknown severity: {} (use error or warn)", other),
+    };
+    Ok(Check {
+        name: rule["name"]
+            .as_str()
+            .map(String::from)
+            .unwrap_or_else(|| parsed.default_name()),
+        rule: parsed,
+        severity,
+    })
+}
+
+/// Text of a scalar YAML value, so `values: [1, true, x]` compare as text.
+fn scalar_text(value: &Yaml) -> Option<String> {
+    match value {
+        Yaml::String(s) | Yaml::Real(s) => Some(s.clone()),
+        Yaml::Integer(n) => Some(n.to_string()),
+        Yaml::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Outcome of one check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckResult {
+    /// Check name
+    pub name: String,
+    /// Rule type
+    pub kind: &'static str,
+    /// Severity of the check
+    pub severity: Severity,
+    /// Whether the expectation holds
+    pub passed: bool,
+    /// Violating rows (or duplicated keys for `unique`); 0 if the check
+    /// couldn't run
+    pub violations: u64,
+    /// What was found, or why the check couldn't run
+    pub message: String,
+}
+
+/// Results of validating one dataset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// File or table validated
+    pub input: String,
+    /// Result of every check, in rule order
+    pub results: Vec<CheckResult>,
+}
+
+impl ValidationReport {
+    /// Whether every `error` check passed.
+    pub fn passed(&self) -> bool {
+        self.results
+            .iter()
+            .all(|r| r.passed || r.severity == Severity::Warn)
+    }
+
+    /// Checks that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &CheckResult> {
+        self.results.iter().filter(|r| !r.passed)
+    }
+
+    /// The report as JSON.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "input": self.input,
+            "passed": self.passed(),
+            "checks": self.results.iter().map(|r| serde_json::json!({
+                "name": r.name,
+                "type": r.kind,
+                "severity": match r.severity {
+                    Severity::Error => "error",
+                    Severity::Warn => "warn",
+                },
+                "passed": r.passed,
+                "violations": r.violations,
+                "message": r.message,
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Validates a CSV, Parquet, or JSON file.
+///
+/// The file is queried through a temporary view named after
+/// [`RuleSet::table`].
+pub fn validate_file(conn: &Connection, path: &str, rules: &RuleSet) -> Result<ValidationReport> {
+    conn.execute_batch(&format!(
+        "CREATE OR REPLACE TEMP VIEW {} AS SELECT * FROM {}",
+        quote_identifier(&rules.table),
+        path_literal(path)
+    ))
+    .with_context(|| format!("Failed to read {}", path))?;
+    Ok(run_checks(conn, path, rules))
+}
+
+/// Validates a table or view of `conn`.
+///
+/// If the rule file names the data differently, a temporary view with that
+/// name is created over `table` for custom SQL rules.
+pub fn validate_table(conn: &Connection, table: &str, rules: &RuleSet) -> Result<ValidationReport> {
+    if rules.table != table {
+        conn.execute_batch(&format!(
+            "CREATE OR REPLACE TEMP VIEW {} AS SELECT * FROM {}",
+            quote_identifier(&rules.table),
+            quote_identifier(table)
+        ))?;
+    }
+    Ok(run_checks(conn, table, rules))
+}
+
+fn run_checks(conn: &Connection, input: &str, rules: &RuleSet) -> ValidationReport {
+    let table = quote_identifier(&rules.table);
+    let results = rules
+        .checks
+        .iter()
+        .map(|check| {
+            let (passed, violations, message) = match run_check(conn, &table, &check.rule) {
+                Ok(outcome) => outcome,
+                Err(e) => (false, 0, format!("check failed to run: {:#}", e)),
+            };
+            CheckResult {
+                name: check.name.clone(),
+                kind: check.rule.kind(),
+                severity: check.severity,
+                passed,
+                violations,
+                message,
+            }
+        })
+        .collect();
+    ValidationReport {
+        input: input.to_string(),
+        results,
+    }
+}
+
+/// Runs one rule, returning whether it passed, the violation count, and a
+/// message.
+fn run_check(conn: &Connection, table: &str, rule: &Rule) -> Result<(bool, u64, String)> {
+    let count = |sql: String| -> Result<u64> {
+        let n: i64 = conn.query_row(&sql, [], |row| row.get(0))?;
+        Ok(n as u64)
+    };
+    Ok(match rule {
+        Rule::NotNull { column } => {
+            let nulls = count(format!(
+                "SELECT COUNT(*) FROM {} WHERE {} IS NULL",
+                table,
+                quote_identifier(column)
+            ))?;
+            (nulls == 0, nulls, format!("{} NULL values", nulls))
+        }
+        Rule::Unique { columns } => {
+            let key = columns
+                .iter()
+                .map(|c| quote_identifier(c))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let duplicated = count(format!(
+                "SELECT COUNT(*) FROM (SELECT {key} FROM {table} GROUP BY {key} HAVING COUNT(*) > 1)"
+            ))?;
+            (
+                duplicated == 0,
+                duplicated,
+                format!("{} duplicated values", duplicated),
+            )
+        }
+        Rule::AcceptedValues { column, values } => {
+            let column = quote_identifier(column);
+            let accepted = values
+                .iter()
+                .map(|v| format!("'{}'", v.replace('\'', "''")))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let filter =
+                format!("{column} IS NOT NULL AND CAST({column} AS VARCHAR) NOT IN ({accepted})");
+            let rejected = count(format!("SELECT COUNT(*) FROM {} WHERE {}", table, filter))?;
+            let message = if rejected == 0 {
+                "all values accepted".to_string()
+            } else {
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT DISTINCT CAST({} AS VARCHAR) FROM {} WHERE {} LIMIT {}",
+                    column, table, filter, SAMPLE_VALUES
+                ))?;
+                let samples = stmt
+                    .query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                format!(
+                    "{} rows with unexpected values, e.g. {}",
+                    rejected,
+                    samples.join(", ")
+                )
+            };
+            (rejected == 0, rejected, message)
+        }
+        Rule::RowCountBetween { min, max } => {
+            let rows = count(format!("SELECT COUNT(*) FROM {}", table))?;
+            let passed = min.is_none_or(|min| rows >= min) && max.is_none_or(|max| rows <= max);
+            (
+                passed,
+                if passed { 0 } else { rows },
+                format!("{} rows", rows),
+            )
+        }
+        Rule::Sql { sql } => {
+            let sql = sql.trim().trim_end_matches(';');
+            let rows = count(format!("SELECT COUNT(*) FROM ({})", sql))?;
+            (rows == 0, rows, format!("{} violating rows", rows))
+        }
+    })
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.trim().replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"
+table: orders
+rules:
+  - type: not_null
+    column: id
+  - type: unique
+    column: id
+  - type: accepted_values
+    column: status
+    values: [open, shipped]
+  - type: row_count_between
+    min: 1
+    max: 3
+  - type: sql
+    name: no negative totals
+    sql: SELECT * FROM orders WHERE total < 0
+    severity: warn
+"#;
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = RuleSet::parse(RULES).unwrap();
+        assert_eq!(rules.table, "orders");
+        let names: Vec<&str> = rules.checks.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "not_null(id)",
+                "unique(id)",
+                "accepted_values(status)",
+                "row_count_between(1, 3)",
+                "no negative totals"
+            ]
+        );
+        assert_eq!(rules.checks[4].severity, Severity::Warn);
+
+        assert!(RuleSet::parse("rules:\n  - type: sometimes\n").is_err());
+        assert!(RuleSet::parse("rules:\n  - type: not_null\n").is_err());
+        assert!(RuleSet::parse("rules:\n  - type: row_count_between\n").is_err());
+    }
+
+    #[test]
+    fn test_validate_table() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute_batch(
+            "CREATE TABLE orders (id INTEGER, status VARCHAR, total INTEGER);
+             INSERT INTO orders VALUES (1, 'open', 10), (2, 'lost', -5), (2, NULL, 3), (NULL, 'open', 1);",
+        )?;
+        let report = validate_table(&conn, "orders", &RuleSet::parse(RULES)?)?;
+
+        let outcome: Vec<(bool, u64)> = report
+            .results
+            .iter()
+            .map(|r| (r.passed, r.violations))
+            .collect();
+        assert_eq!(
+            outcome,
+            [(false, 1), (false, 1), (false, 1), (false, 4), (false, 1)]
+        );
+        assert!(report.results[2].message.contains("lost"));
+        assert!(!report.passed());
+        assert_eq!(report.to_json()["checks"][4]["severity"], "warn");
+
+        // Only the warning fails once the data is fixed
+        conn.execute_batch(
+            "DELETE FROM orders WHERE id IS NULL OR status IS DISTINCT FROM 'open'",
+        )?;
+        conn.execute_batch("UPDATE orders SET total = -1")?;
+        let report = validate_table(&conn, "orders", &RuleSet::parse(RULES)?)?;
+        assert_eq!(report.failures().count(), 1);
+        assert!(report.passed());
+        Ok(())
+    }
+
+    #[test]
+    fn test_broken_check_fails_with_message() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute_batch("CREATE TABLE t AS SELECT 1 AS x")?;
+        let rules = RuleSet::parse("rules:\n  - type: not_null\n    column: missing\n")?;
+        let report = validate_table(&conn, "t", &rules)?;
+        assert!(!report.passed());
+        assert!(report.results[0].message.starts_with("check failed to run"));
+        Ok(())
+    }
+}
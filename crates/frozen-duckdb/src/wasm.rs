@@ -0,0 +1,162 @@
+//! # WebAssembly Stub API
+//!
+//! DuckDB is a native C++ library, so the frozen binaries can't be linked
+//! into a `wasm32-unknown-unknown` build. On that target frozen-duckdb
+//! compiles to a small subset instead, so crates shared between a native
+//! backend and a browser frontend can depend on it unconditionally and
+//! keep database access behind their own `cfg` or a runtime error.
+//!
+//! | API | On wasm32 |
+//! |-----|-----------|
+//! | [`crate::text`] (chunking, context packing, token estimates) | ✅ Same as native |
+//! | `Connection`, `DuckDBError`, `Result` | ⚠️ These stubs: opening a connection returns [`Error::Unsupported`] |
+//! | `Statement`, `Row`, `Rows`, `Appender`, `Transaction`, `params!`, `types`, Arrow | ❌ Not compiled |
+//! | `cli`, `ingest`, `validation`, `profiling`, `benchmark`, `capabilities`, `memory` | ❌ Not compiled |
+//! | `architecture`, `env_setup`, `error` (native build and CLI concerns) | ❌ Not compiled |
+//! | Features listed in [`NATIVE_FEATURES`] | ❌ Compile error naming the feature |
+//!
+//! Code that names an item from the ❌ rows fails to compile for wasm32
+//! with an unresolved import, so gate it with
+//! `#[cfg(not(target_arch = "wasm32"))]`.
+//!
+//! ## Why Not duckdb-wasm?
+//!
+//! duckdb-wasm runs DuckDB in a Web Worker behind an asynchronous
+//! JavaScript API. The synchronous duckdb-rs API this crate mirrors can't
+//! wait on a worker from the browser's main thread, and
+//! `wasm32-unknown-unknown` has no C++ runtime or threads to build DuckDB
+//! against directly. Browser code should call duckdb-wasm through
+//! `wasm-bindgen`, or query a `frozen-duckdb serve` instance over HTTP.
+//!
+//! ## Usage Examples
+//!
+//! ```bash
+//! # Check that a shared crate still builds for the browser
+//! cargo build -p frozen-duckdb --lib --target wasm32-unknown-unknown
+//! ```
+//!
+//! # Examples
+//!
+//! The stubs are compiled on every target, so they can be tried natively:
+//!
+//! ```rust
+//! use frozen_duckdb::wasm::{Connection, Error};
+//!
+//! let error = Connection::open_in_memory().unwrap_err();
+//! assert_eq!(error, Error::Unsupported("Connection::open_in_memory"));
+//! println!("{}", error);
+//! ```
+
+use std::path::Path;
+
+/// Declares the native-only features and emits a compile error for each
+/// one enabled on wasm32.
+macro_rules! native_features {
+    ($($feature:literal => $reason:literal,)*) => {
+        /// Cargo features that need the native library, with what they
+        /// provide. Enabling one for wasm32 is a compile error.
+        pub const NATIVE_FEATURES: &[(&str, &str)] = &[$(($feature, $reason)),*];
+
+        $(
+            #[cfg(all(target_arch = "wasm32", feature = $feature))]
+            compile_error!(concat!(
+                "frozen-duckdb feature `",
+                $feature,
+                "` needs the native DuckDB library and is not available on wasm32 (",
+                $reason,
+                ")"
+            ));
+        )*
+    };
+}
+
+native_features! {
+    "vtab" => "table functions run inside DuckDB",
+    "vtab-arrow" => "table functions run inside DuckDB",
+    "vscalar" => "scalar functions run inside DuckDB",
+    "chrono-tz" => "time zone conversions of DuckDB values",
+    "decimal" => "DECIMAL conversions of DuckDB values",
+    "hugeint" => "HUGEINT conversions of DuckDB values",
+    "umap" => "layouts of the `vss project` command",
+    "onnx" => "ONNX Runtime is a native library",
+    "pdf" => "document loaders of the `index` command",
+    "markdown" => "document loaders of the `index` command",
+    "html" => "document loaders of the `index` command",
+    "loaders" => "document loaders of the `index` command",
+    "otlp" => "span export of the CLI",
+}
+
+/// Error returned by every stub.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The named API needs the native DuckDB library
+    #[error(
+        "{0} is not available on wasm32: DuckDB is a native library \
+         (use duckdb-wasm from JavaScript, or query `frozen-duckdb serve` over HTTP)"
+    )]
+    Unsupported(&'static str),
+}
+
+/// Result of the stubs.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Stand-in for [`Connection`](crate::Connection) that can never be opened.
+#[derive(Debug)]
+pub struct Connection {
+    _private: (),
+}
+
+impl Connection {
+    /// Fails with [`Error::Unsupported`].
+    pub fn open<P: AsRef<Path>>(_path: P) -> Result<Self> {
+        Err(Error::Unsupported("Connection::open"))
+    }
+
+    /// Fails with [`Error::Unsupported`].
+    pub fn open_in_memory() -> Result<Self> {
+        Err(Error::Unsupported("Connection::open_in_memory"))
+    }
+
+    /// Fails with [`Error::Unsupported`].
+    pub fn execute_batch(&self, _sql: &str) -> Result<()> {
+        Err(Error::Unsupported("Connection::execute_batch"))
+    }
+
+    /// Fails with [`Error::Unsupported`].
+    pub fn try_clone(&self) -> Result<Self> {
+        Err(Error::Unsupported("Connection::try_clone"))
+    }
+
+    /// Fails with [`Error::Unsupported`].
+    pub fn version(&self) -> Result<String> {
+        Err(Error::Unsupported("Connection::version"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connections_are_unsupported() {
+        let error = Connection::open("analytics.duckdb").unwrap_err();
+        assert_eq!(error, Error::Unsupported("Connection::open"));
+        assert!(error
+            .to_string()
+            .starts_with("Connection::open is not available on wasm32"));
+        assert!(Connection::open_in_memory().is_err());
+    }
+
+    #[test]
+    fn test_native_features_are_declared() {
+        let manifest = include_str!("../Cargo.toml");
+        for (feature, _) in NATIVE_FEATURES {
+            assert!(
+                manifest.contains(&format!("\n{} = ", feature)),
+                "{} is not a feature of frozen-duckdb",
+                feature
+            );
+        }
+    }
+}
@@ -0,0 +1,323 @@
+//! Concurrency stress tests for one database file
+//!
+//! Hammers a single DuckDB file from many threads (readers, writers, and
+//! appenders on cloned connections) and from several processes, then
+//! reopens the file and checks that no row was lost, duplicated, or
+//! corrupted.
+//!
+//! Child processes are this test binary run again with
+//! `STRESS_CHILD_ENV` set, so they need no separate executable.
+
+use frozen_duckdb::cli::dataset_manager::DatasetManager;
+use frozen_duckdb::cli::flock_manager::FlockManager;
+use frozen_duckdb::duckdb::AccessMode;
+use frozen_duckdb::{params, Config, Connection, Result};
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+/// Writer threads, half inserting with SQL and half with an appender
+const WRITERS: i64 = 8;
+/// Rows each writer adds
+const ROWS_PER_WRITER: i64 = 2_000;
+/// Rows per transaction or appender flush
+const BATCH: i64 = 100;
+/// Reader threads checking invariants while the writers run
+const READERS: usize = 4;
+
+/// Set in child processes to the database they should open
+const STRESS_CHILD_ENV: &str = "FROZEN_DUCKDB_STRESS_CHILD";
+/// Set in child processes to `read` or `write`
+const STRESS_MODE_ENV: &str = "FROZEN_DUCKDB_STRESS_MODE";
+
+fn create_events(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE events (writer BIGINT NOT NULL, seq BIGINT NOT NULL, \
+         value DOUBLE NOT NULL, payload VARCHAR NOT NULL)",
+    )
+}
+
+/// Value derived from the key, so any torn or mixed-up row is detected.
+fn value(writer: i64, seq: i64) -> f64 {
+    (writer * 1_000_003 + seq) as f64 / 7.0
+}
+
+fn payload(writer: i64, seq: i64) -> String {
+    format!("w{}-s{}-{}", writer, seq, "x".repeat((seq % 17) as usize))
+}
+
+/// Adds `ROWS_PER_WRITER` rows for `writer`, in committed batches.
+fn write_rows(conn: &Connection, writer: i64) -> Result<()> {
+    for start in (0..ROWS_PER_WRITER).step_by(BATCH as usize) {
+        let end = (start + BATCH).min(ROWS_PER_WRITER);
+        if writer % 2 == 0 {
+            let mut appender = conn.appender("events")?;
+            for seq in start..end {
+                appender.append_row(params![
+                    writer,
+                    seq,
+                    value(writer, seq),
+                    payload(writer, seq)
+                ])?;
+            }
+            appender.flush()?;
+        } else {
+            conn.execute_batch("BEGIN TRANSACTION")?;
+            let mut insert = conn.prepare("INSERT INTO events VALUES (?, ?, ?, ?)")?;
+            for seq in start..end {
+                insert.execute(params![
+                    writer,
+                    seq,
+                    value(writer, seq),
+                    payload(writer, seq)
+                ])?;
+            }
+            conn.execute_batch("COMMIT")?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks that every writer's rows so far are a gap-free prefix of its
+/// sequence with the expected values, and returns the total row count.
+fn check_consistent(conn: &Connection) -> Result<i64> {
+    let mut statement = conn.prepare(
+        "SELECT writer, count(*), count(DISTINCT seq), max(seq), \
+         sum(CASE WHEN payload LIKE 'w' || writer || '-s' || seq || '-%' THEN 0 ELSE 1 END) \
+         FROM events GROUP BY writer",
+    )?;
+    let groups = statement.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, i64>(3)?,
+            row.get::<_, i64>(4)?,
+        ))
+    })?;
+
+    let mut total = 0;
+    for group in groups {
+        let (writer, count, distinct, max_seq, bad_payloads) = group?;
+        assert!((0..WRITERS).contains(&writer), "unknown writer {}", writer);
+        assert_eq!(count, distinct, "writer {} has duplicate rows", writer);
+        assert_eq!(count, max_seq + 1, "writer {} has a gap", writer);
+        assert_eq!(bad_payloads, 0, "writer {} has corrupted payloads", writer);
+        total += count;
+    }
+
+    let bad_values: i64 = conn.query_row(
+        "SELECT count(*) FROM events WHERE abs(value - (writer * 1000003 + seq) / 7.0) > 1e-6",
+        [],
+        |row| row.get(0),
+    )?;
+    assert_eq!(bad_values, 0, "rows with corrupted values");
+    Ok(total)
+}
+
+#[test]
+fn test_threads_hammer_one_file() -> Result<()> {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("stress.duckdb");
+    {
+        let conn = Connection::open(&path)?;
+        create_events(&conn)?;
+
+        let start = Arc::new(Barrier::new(WRITERS as usize + READERS));
+        let writing = Arc::new(AtomicBool::new(true));
+
+        let writers: Vec<_> = (0..WRITERS)
+            .map(|writer| {
+                let conn = conn.try_clone()?;
+                let start = Arc::clone(&start);
+                Ok(thread::spawn(move || -> Result<()> {
+                    start.wait();
+                    write_rows(&conn, writer)
+                }))
+            })
+            .collect::<Result<_>>()?;
+
+        let readers: Vec<_> = (0..READERS)
+            .map(|_| {
+                let conn = conn.try_clone()?;
+                let start = Arc::clone(&start);
+                let writing = Arc::clone(&writing);
+                Ok(thread::spawn(move || -> Result<usize> {
+                    start.wait();
+                    let mut last = 0;
+                    let mut checks = 0;
+                    // Check at least once, even if the writers finish first
+                    while checks == 0 || writing.load(Ordering::Relaxed) {
+                        let total = check_consistent(&conn)?;
+                        assert!(
+                            total >= last,
+                            "row count went back from {} to {}",
+                            last,
+                            total
+                        );
+                        last = total;
+                        checks += 1;
+                    }
+                    Ok(checks)
+                }))
+            })
+            .collect::<Result<_>>()?;
+
+        for writer in writers {
+            writer.join().expect("writer panicked")?;
+        }
+        writing.store(false, Ordering::Relaxed);
+        for reader in readers {
+            assert!(reader.join().expect("reader panicked")? > 0);
+        }
+    }
+
+    // Reopen from disk: everything was committed and nothing was damaged
+    let conn = Connection::open(&path)?;
+    assert_eq!(check_consistent(&conn)?, WRITERS * ROWS_PER_WRITER);
+    let writers: i64 = conn.query_row("SELECT count(DISTINCT writer) FROM events", [], |row| {
+        row.get(0)
+    })?;
+    assert_eq!(writers, WRITERS);
+    Ok(())
+}
+
+#[test]
+fn test_clones_outlive_original() -> Result<()> {
+    let conn = Connection::open_in_memory()?;
+    create_events(&conn)?;
+    let check = conn.try_clone()?;
+    let clones = (0..4)
+        .map(|_| conn.try_clone())
+        .collect::<Result<Vec<_>>>()?;
+    drop(conn);
+
+    // Each thread clones its clone again after the original is gone
+    let handles: Vec<_> = clones
+        .into_iter()
+        .zip(0i64..)
+        .map(|(clone, writer)| {
+            thread::spawn(move || -> Result<()> {
+                let again = clone.try_clone()?;
+                drop(clone);
+                again.execute(
+                    "INSERT INTO events VALUES (?, 0, ?, ?)",
+                    params![writer, value(writer, 0), payload(writer, 0)],
+                )?;
+                Ok(())
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().expect("clone thread panicked")?;
+    }
+    assert_eq!(check_consistent(&check)?, 4);
+    Ok(())
+}
+
+/// Runs this test binary again as a child process on `path`.
+fn spawn_child(path: &Path, mode: &str) -> std::process::Child {
+    Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "stress_child", "--nocapture", "--test-threads=1"])
+        .env(STRESS_CHILD_ENV, path)
+        .env(STRESS_MODE_ENV, mode)
+        .spawn()
+        .expect("Failed to start child process")
+}
+
+/// Entry point of child processes; does nothing in a normal test run.
+#[test]
+fn stress_child() -> Result<()> {
+    let Ok(path) = std::env::var(STRESS_CHILD_ENV) else {
+        return Ok(());
+    };
+    match std::env::var(STRESS_MODE_ENV).as_deref() {
+        Ok("read") => {
+            let config = Config::default().access_mode(AccessMode::ReadOnly)?;
+            let conn = Connection::open_with_flags(&path, config)?;
+            let clones = (0..4)
+                .map(|_| conn.try_clone())
+                .collect::<Result<Vec<_>>>()?;
+            let handles: Vec<_> = clones
+                .into_iter()
+                .map(|clone| thread::spawn(move || check_consistent(&clone)))
+                .collect();
+            for handle in handles {
+                assert_eq!(
+                    handle.join().expect("reader panicked")?,
+                    WRITERS * ROWS_PER_WRITER
+                );
+            }
+        }
+        Ok("write") => {
+            // Another process holds the write lock: opening must fail
+            // cleanly instead of sharing the file
+            assert!(Connection::open(&path).is_err());
+        }
+        other => panic!("unknown child mode {:?}", other),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_processes_share_one_file() -> Result<()> {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("shared.duckdb");
+
+    let conn = Connection::open(&path)?;
+    create_events(&conn)?;
+    let writers: Vec<_> = (0..WRITERS)
+        .map(|writer| {
+            let conn = conn.try_clone()?;
+            Ok(thread::spawn(move || write_rows(&conn, writer)))
+        })
+        .collect::<Result<_>>()?;
+    for writer in writers {
+        writer.join().expect("writer panicked")?;
+    }
+
+    // DuckDB allows one writing process: a second one is turned away
+    assert!(spawn_child(&path, "write").wait().unwrap().success());
+    drop(conn);
+
+    // Any number of processes may read the file at once
+    let readers: Vec<_> = (0..4).map(|_| spawn_child(&path, "read")).collect();
+    for mut reader in readers {
+        assert!(reader.wait().unwrap().success(), "reader process failed");
+    }
+
+    let conn = Connection::open(&path)?;
+    assert_eq!(check_consistent(&conn)?, WRITERS * ROWS_PER_WRITER);
+    Ok(())
+}
+
+/// Managers own their connection: they can be moved to a worker thread,
+/// but the compiler rejects sharing one between threads
+#[test]
+fn test_managers_are_send() {
+    fn assert_send<T: Send>() {}
+    assert_send::<Connection>();
+    assert_send::<DatasetManager>();
+    assert_send::<FlockManager>();
+}
+
+#[test]
+fn test_dataset_manager_per_thread() -> Result<()> {
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            thread::spawn(move || -> anyhow::Result<i64> {
+                let manager = DatasetManager::new()?;
+                let output = manager.run_query(&format!("SELECT {} * 2 AS doubled", i))?;
+                Ok(output.to_csv().lines().nth(1).unwrap().parse()?)
+            })
+        })
+        .collect();
+    let results: Vec<i64> = handles
+        .into_iter()
+        .map(|handle| handle.join().expect("manager thread panicked").unwrap())
+        .collect();
+    assert_eq!(results, vec![0, 2, 4, 6]);
+    Ok(())
+}
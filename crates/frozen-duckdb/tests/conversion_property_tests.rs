@@ -0,0 +1,237 @@
+//! Property-based round-trip tests for format conversion
+//!
+//! Generates tables with random schemas and edge-case values (nulls,
+//! unicode, extreme integers and doubles, embedded delimiters, quotes, and
+//! newlines), converts them between CSV, Parquet, and JSON Lines, and
+//! checks that every value survives.
+
+use anyhow::Result;
+use duckdb::types::Value;
+use duckdb::Connection;
+use frozen_duckdb::cli::dataset_manager::{
+    split_statements, CsvColumn, CsvSchema, DatasetManager, QueryOutput,
+};
+use proptest::prelude::*;
+
+/// Column types generated for the source table.
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Int,
+    Float,
+    Text,
+    Bool,
+}
+
+impl Kind {
+    fn sql_type(self) -> &'static str {
+        match self {
+            Kind::Int => "BIGINT",
+            Kind::Float => "DOUBLE",
+            Kind::Text => "VARCHAR",
+            Kind::Bool => "BOOLEAN",
+        }
+    }
+
+    fn values(self) -> BoxedStrategy<Value> {
+        let value = match self {
+            Kind::Int => prop_oneof![Just(i64::MIN), Just(i64::MAX), Just(0), any::<i64>()]
+                .prop_map(Value::BigInt)
+                .boxed(),
+            Kind::Float => prop_oneof![
+                Just(-0.0),
+                Just(f64::MAX),
+                Just(f64::MIN_POSITIVE),
+                prop::num::f64::NORMAL | prop::num::f64::ZERO
+            ]
+            .prop_map(Value::Double)
+            .boxed(),
+            // The `s` prefix and `x` suffix keep text from being detected as
+            // a number, date, or boolean when CSV and JSON types are inferred
+            Kind::Text => prop_oneof![Just(String::new()), "s[a-z0-9 ,;|\t\"'\\\\\né中🦆]{0,12}x"]
+                .prop_map(Value::Text)
+                .boxed(),
+            Kind::Bool => any::<bool>().prop_map(Value::Boolean).boxed(),
+        };
+        prop_oneof![1 => Just(Value::Null), 4 => value].boxed()
+    }
+}
+
+/// A generated table: named, typed columns and rows of values.
+#[derive(Debug, Clone)]
+struct Table {
+    columns: Vec<(String, Kind)>,
+    rows: Vec<Vec<Value>>,
+}
+
+fn table() -> impl Strategy<Value = Table> {
+    let kind = prop_oneof![
+        Just(Kind::Int),
+        Just(Kind::Float),
+        Just(Kind::Text),
+        Just(Kind::Bool)
+    ];
+    prop::collection::vec((kind, "[a-zé中,;\"|-]{0,6}"), 1..5).prop_flat_map(|columns| {
+        let columns: Vec<(String, Kind)> = columns
+            .into_iter()
+            .enumerate()
+            .map(|(i, (kind, suffix))| (format!("c{}{}", i, suffix), kind))
+            .collect();
+        let row: Vec<BoxedStrategy<Value>> =
+            columns.iter().map(|(_, kind)| kind.values()).collect();
+        // JSON Lines files carry no schema, so an empty table can't round-trip
+        prop::collection::vec(row, 1..12).prop_map(move |rows| Table {
+            columns: columns.clone(),
+            rows,
+        })
+    })
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Writes `table` to a Parquet file with an `id` column giving row order.
+fn write_parquet(table: &Table, path: &str) -> Result<()> {
+    let conn = Connection::open_in_memory()?;
+    let columns: Vec<String> = table
+        .columns
+        .iter()
+        .map(|(name, kind)| format!("{} {}", quote(name), kind.sql_type()))
+        .collect();
+    conn.execute_batch(&format!(
+        "CREATE TABLE source (id BIGINT, {})",
+        columns.join(", ")
+    ))?;
+    let placeholders = vec!["?"; table.columns.len() + 1].join(", ");
+    let mut insert = conn.prepare(&format!("INSERT INTO source VALUES ({})", placeholders))?;
+    for (id, row) in table.rows.iter().enumerate() {
+        let mut values = vec![Value::BigInt(id as i64)];
+        values.extend(row.iter().cloned());
+        insert.execute(duckdb::params_from_iter(values))?;
+    }
+    conn.execute_batch(&format!(
+        "COPY source TO '{}' (FORMAT PARQUET)",
+        path.replace('\'', "''")
+    ))?;
+    Ok(())
+}
+
+/// Reads a Parquet file back as column names and rows of text values.
+fn read_parquet(
+    manager: &DatasetManager,
+    path: &str,
+    table: &Table,
+) -> Result<(Vec<String>, Vec<Vec<Value>>)> {
+    let projection: Vec<String> = table
+        .columns
+        .iter()
+        .map(|(name, _)| format!("CAST({} AS VARCHAR)", quote(name)))
+        .collect();
+    let described = manager.run_query(&format!(
+        "SELECT column_name FROM (DESCRIBE SELECT * FROM read_parquet('{}'))",
+        path
+    ))?;
+    let names = described
+        .rows
+        .into_iter()
+        .map(|row| match &row[0] {
+            Value::Text(name) => name.clone(),
+            other => panic!("unexpected column name {:?}", other),
+        })
+        .collect();
+    let output = manager.run_query(&format!(
+        "SELECT {} FROM read_parquet('{}') ORDER BY id",
+        projection.join(", "),
+        path
+    ))?;
+    Ok((names, output.rows))
+}
+
+/// Converts a Parquet copy of `table` through `formats` and back to
+/// Parquet, and checks that names and values are unchanged.
+fn assert_round_trip(table: &Table, formats: &[&str]) -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let path = |name: &str| temp_dir.path().join(name).to_str().unwrap().to_string();
+    let manager = DatasetManager::new()?;
+
+    write_parquet(table, &path("source.parquet"))?;
+    let expected = read_parquet(&manager, &path("source.parquet"), table)?;
+
+    let mut current = (path("source.parquet"), "parquet");
+    for (step, format) in formats.iter().chain(["parquet"].iter()).enumerate() {
+        let next = path(&format!("step{}.{}", step, format));
+        manager.convert_dataset(&current.0, &next, current.1, format)?;
+        current = (next, format);
+    }
+    let actual = read_parquet(&manager, &current.0, table)?;
+
+    assert_eq!(
+        actual, expected,
+        "round trip through {:?} changed data",
+        formats
+    );
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn prop_csv_round_trip(table in table()) {
+        assert_round_trip(&table, &["csv"]).unwrap();
+    }
+
+    #[test]
+    fn prop_jsonl_round_trip(table in table()) {
+        assert_round_trip(&table, &["jsonl"]).unwrap();
+    }
+
+    #[test]
+    fn prop_csv_jsonl_round_trip(table in table()) {
+        assert_round_trip(&table, &["csv", "jsonl"]).unwrap();
+        assert_round_trip(&table, &["jsonl", "csv"]).unwrap();
+    }
+}
+
+proptest! {
+    #[test]
+    fn prop_read_csv_is_one_statement(
+        delimiter in ".{1,2}",
+        quote in ".{0,1}",
+        escape in ".{0,1}",
+        path in ".{0,20}",
+        names in prop::collection::vec(".{0,10}", 0..4),
+        date_format in prop::option::of(".{0,10}"),
+    ) {
+        let schema = CsvSchema {
+            delimiter,
+            quote,
+            escape,
+            has_header: true,
+            skip_rows: 0,
+            date_format,
+            timestamp_format: None,
+            columns: names
+                .into_iter()
+                .map(|name| CsvColumn { name, data_type: "VARCHAR".to_string() })
+                .collect(),
+            sample: QueryOutput { columns: Vec::new(), rows: Vec::new() },
+        };
+        let query = format!("SELECT * FROM {}; SELECT 1", schema.read_csv(&path));
+        prop_assert_eq!(split_statements(&query).len(), 2);
+    }
+}
+
+/// Empty strings and nulls are distinct in CSV and must stay distinct
+#[test]
+fn test_csv_keeps_empty_strings() -> Result<()> {
+    let table = Table {
+        columns: vec![("name".to_string(), Kind::Text)],
+        rows: vec![
+            vec![Value::Text(String::new())],
+            vec![Value::Null],
+            vec![Value::Text("s,\"x".to_string())],
+        ],
+    };
+    assert_round_trip(&table, &["csv"])
+}
@@ -562,3 +562,1111 @@ fn test_chinook_dataset() -> Result<()> {
     );
     Ok(())
 }
+
+/// Test joining tables across attached database files
+#[test]
+fn test_attach_multiple_databases() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let prod = temp_dir.path().join("prod.duckdb");
+    let backup = temp_dir.path().join("backup.duckdb");
+    Connection::open(&prod)?
+        .execute_batch("CREATE TABLE orders AS SELECT range AS id FROM range(5);")?;
+    Connection::open(&backup)?
+        .execute_batch("CREATE TABLE orders AS SELECT range AS id FROM range(3);")?;
+
+    let manager = frozen_duckdb::cli::DatasetManager::new()?;
+    manager.attach(prod.to_str().unwrap(), "prod")?;
+    manager.attach(backup.to_str().unwrap(), "backup")?;
+    let missing = manager
+        .run_query("SELECT id FROM prod.orders EXCEPT SELECT id FROM backup.orders ORDER BY id")?;
+    assert_eq!(missing.rows.len(), 2);
+
+    // Reusing an alias or attaching a missing file is an error
+    assert!(manager.attach(backup.to_str().unwrap(), "prod").is_err());
+    let missing_file = temp_dir.path().join("missing.duckdb");
+    assert!(manager
+        .attach(missing_file.to_str().unwrap(), "other")
+        .is_err());
+    assert!(!missing_file.exists());
+
+    info!("✅ Cross-database queries working");
+    Ok(())
+}
+
+/// Test Excel round trips and worksheet selection in `convert`
+#[test]
+fn test_excel_convert_and_schema() -> Result<()> {
+    use frozen_duckdb::cli::dataset_manager::ConvertOptions;
+
+    let temp_dir = tempfile::tempdir()?;
+    let path = |name: &str| temp_dir.path().join(name).to_str().unwrap().to_string();
+    std::fs::write(path("input.csv"), "id,name\n1,alpha\n2,beta\n")?;
+
+    let manager = frozen_duckdb::cli::DatasetManager::new()?;
+    let options = ConvertOptions {
+        sheet: Some("Q3".to_string()),
+        ..Default::default()
+    };
+    manager.convert_dataset_with(
+        &path("input.csv"),
+        &path("report.xlsx"),
+        "csv",
+        "xlsx",
+        &options,
+    )?;
+    manager.convert_dataset_with(
+        &path("report.xlsx"),
+        &path("roundtrip.parquet"),
+        "xlsx",
+        "parquet",
+        &options,
+    )?;
+    let rows = manager.run_query(&format!(
+        "SELECT * FROM read_parquet('{}')",
+        path("roundtrip.parquet")
+    ))?;
+    assert_eq!(rows.columns, vec!["id", "name"]);
+    assert_eq!(rows.rows.len(), 2);
+
+    let schema = manager.describe_dataset(&path("report.xlsx"), "xlsx", Some("Q3"))?;
+    assert_eq!(schema.rows.len(), 2);
+    assert!(manager
+        .describe_dataset(&path("report.xlsx"), "xlsx", Some("Missing"))
+        .is_err());
+
+    info!("✅ Excel conversion working");
+    Ok(())
+}
+
+/// Test streaming JSON Lines output, plain and compressed
+#[test]
+fn test_jsonl_export() -> Result<()> {
+    use frozen_duckdb::cli::dataset_manager::jsonl_compression;
+
+    let temp_dir = tempfile::tempdir()?;
+    let path = |name: &str| temp_dir.path().join(name).to_str().unwrap().to_string();
+    let manager = frozen_duckdb::cli::DatasetManager::new()?;
+
+    let mut buffer = Vec::new();
+    let rows = manager.write_jsonl("SELECT range AS id, 'x' AS tag FROM range(3)", &mut buffer)?;
+    assert_eq!(rows, 3);
+    let lines: Vec<&str> = std::str::from_utf8(&buffer)?.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            r#"{"id":0,"tag":"x"}"#,
+            r#"{"id":1,"tag":"x"}"#,
+            r#"{"id":2,"tag":"x"}"#
+        ]
+    );
+
+    for name in ["out.jsonl", "out.jsonl.gz", "out.jsonl.zst"] {
+        manager.export_jsonl("SELECT * FROM range(100)", &path(name))?;
+        let count = manager.run_query(&format!(
+            "SELECT COUNT(*) FROM read_json('{}', format = 'newline_delimited')",
+            path(name)
+        ))?;
+        assert_eq!(count.rows, vec![vec![duckdb::types::Value::BigInt(100)]]);
+    }
+    assert_eq!(jsonl_compression("OUT.JSONL.ZST")?, "zstd");
+    assert!(jsonl_compression("out.json").is_err());
+
+    std::fs::write(path("input.csv"), "id,name\n1,alpha\n2,beta\n")?;
+    manager.convert_dataset(
+        &path("input.csv"),
+        &path("converted.jsonl.gz"),
+        "csv",
+        "jsonl",
+    )?;
+    let converted = std::fs::read(path("converted.jsonl.gz"))?;
+    assert_eq!(&converted[..2], &[0x1f, 0x8b]);
+
+    info!("✅ JSON Lines export working");
+    Ok(())
+}
+
+/// Test GeoJSON to GeoParquet conversion, when the spatial extension is available
+#[test]
+fn test_geojson_geoparquet_convert() -> Result<()> {
+    use frozen_duckdb::cli::dataset_manager::ConvertOptions;
+
+    let temp_dir = tempfile::tempdir()?;
+    let path = |name: &str| temp_dir.path().join(name).to_str().unwrap().to_string();
+    let manager = frozen_duckdb::cli::DatasetManager::new()?;
+
+    // Spatial conversions never mix with plain formats
+    assert!(manager
+        .convert_dataset(&path("in.geojson"), &path("out.csv"), "geojson", "csv")
+        .is_err());
+
+    if let Err(e) = manager.enable_spatial() {
+        assert!(e.to_string().contains("spatial extension is not available"));
+        info!("⚠️  Skipping GeoParquet test: {}", e);
+        return Ok(());
+    }
+    assert!(manager.has_spatial());
+
+    std::fs::write(
+        path("places.geojson"),
+        r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {"name": "a"}, "geometry": {"type": "Point", "coordinates": [1, 2]}},
+            {"type": "Feature", "properties": {"name": "b"}, "geometry": {"type": "Point", "coordinates": [3, 4]}}
+        ]}"#,
+    )?;
+    let options = ConvertOptions {
+        geometry_column: Some("shape".to_string()),
+        ..Default::default()
+    };
+    manager.convert_dataset_with(
+        &path("places.geojson"),
+        &path("places.parquet"),
+        "geojson",
+        "geoparquet",
+        &options,
+    )?;
+    let rows = manager.run_query(&format!(
+        "SELECT name, ST_X(shape) FROM read_parquet('{}') ORDER BY name",
+        path("places.parquet")
+    ))?;
+    assert_eq!(rows.rows.len(), 2);
+
+    manager.convert_dataset_with(
+        &path("places.parquet"),
+        &path("roundtrip.geojson"),
+        "geoparquet",
+        "geojson",
+        &options,
+    )?;
+    assert!(std::fs::read_to_string(path("roundtrip.geojson"))?.contains("Point"));
+
+    info!("✅ GeoParquet conversion working");
+    Ok(())
+}
+
+/// Test the runtime capability report
+#[test]
+fn test_capabilities_report() -> Result<()> {
+    let caps = frozen_duckdb::capabilities()?;
+    assert!(caps.version.starts_with('v'));
+
+    // Parquet and JSON are compiled into every frozen binary
+    for name in ["parquet", "json"] {
+        assert!(caps.is_available(name), "{} should be available", name);
+        assert!(caps.statically_linked().contains(&name));
+    }
+    assert!(!caps.is_available("no_such_extension"));
+    assert!(caps
+        .loadable()
+        .iter()
+        .all(|name| !caps.statically_linked().contains(name)));
+
+    let json = caps.to_json();
+    assert_eq!(json["version"], caps.version.as_str());
+    assert!(json["icu"]["time_zones"].is_boolean());
+
+    info!("✅ Capabilities: {:?}", caps.statically_linked());
+    Ok(())
+}
+
+/// Test batching write statements into explicit transactions
+#[test]
+fn test_transaction_batching() -> Result<()> {
+    use frozen_duckdb::cli::dataset_manager::split_statements;
+
+    let statements = split_statements(
+        "CREATE TABLE t (id INTEGER, note VARCHAR);
+         INSERT INTO t VALUES (1, 'a;b'); /* ; */ -- trailing;
+         INSERT INTO t VALUES (2, 'c');",
+    );
+    assert_eq!(statements.len(), 3);
+    assert_eq!(statements[1], "INSERT INTO t VALUES (1, 'a;b')");
+
+    let manager = frozen_duckdb::cli::DatasetManager::new()?;
+    let report = manager.execute_in_transactions(&statements, 2)?;
+    assert_eq!((report.statements, report.transactions), (3, 2));
+
+    // The failing statement's batch is rolled back; earlier batches stay
+    let failing = [
+        "INSERT INTO t VALUES (3, 'd')",
+        "INSERT INTO t VALUES (4, 'e')",
+        "INSERT INTO t VALUES (5, 'f')",
+        "INSERT INTO missing VALUES (6)",
+    ];
+    let error = manager.execute_in_transactions(&failing, 2).unwrap_err();
+    assert!(error.to_string().contains("Statement 4 failed"));
+    assert!(error.to_string().contains("rolled back statements 3-4"));
+    let ids = manager.run_query("SELECT id FROM t ORDER BY id")?;
+    assert_eq!(ids.rows.len(), 4);
+
+    // Autocommit keeps every statement before the failure
+    let error = manager.execute_in_transactions(&["INSERT INTO t VALUES (7, 'g')", "SELEC"], 0);
+    assert!(error.is_err());
+    assert_eq!(manager.run_query("SELECT id FROM t")?.rows.len(), 5);
+
+    info!("✅ Transaction batching working");
+    Ok(())
+}
+
+/// Test that cached query results are reused until an input changes
+#[test]
+fn test_query_result_cache() -> Result<()> {
+    use frozen_duckdb::cli::query_cache::QueryCache;
+
+    let temp_dir = tempfile::tempdir()?;
+    let cache = QueryCache::with_root(temp_dir.path().join("queries"))?;
+    let data = temp_dir.path().join("sales.csv");
+    std::fs::write(&data, "region,amount\neu,10\nus,5\neu,1\n")?;
+
+    let manager = frozen_duckdb::cli::DatasetManager::new()?;
+    let sql = format!(
+        "SELECT region, SUM(amount) AS total FROM '{}' GROUP BY region ORDER BY region",
+        data.display()
+    );
+    let first = manager.run_query_cached(&sql, &cache)?;
+    let second = manager.run_query_cached(&sql, &cache)?;
+    assert_eq!(first.columns, ["region", "total"]);
+    assert_eq!(first.to_csv(), second.to_csv());
+    assert_eq!(std::fs::read_dir(cache.root())?.count(), 1);
+
+    // Changing the input invalidates the entry
+    std::fs::write(&data, "region,amount\neu,10\nus,5\neu,1\nus,100\n")?;
+    let changed = manager.run_query_cached(&sql, &cache)?;
+    assert!(changed.to_csv().contains("105"));
+    assert_eq!(std::fs::read_dir(cache.root())?.count(), 2);
+
+    // Statements COPY can't wrap run uncached
+    manager.run_query_cached("CREATE TABLE t AS SELECT 1 AS x", &cache)?;
+    assert_eq!(cache.invalidate()?, 2);
+
+    info!("✅ Query result cache working");
+    Ok(())
+}
+
+/// Test CSV sniffing and auto-detected conversion
+#[test]
+fn test_sniff_csv_and_auto_convert() -> Result<()> {
+    use frozen_duckdb::cli::dataset_manager::{detect_format, ConvertOptions};
+
+    let temp_dir = tempfile::tempdir()?;
+    let path = |name: &str| temp_dir.path().join(name).to_str().unwrap().to_string();
+    std::fs::write(
+        path("export.csv"),
+        "id;name;joined\n1;\"Ada; Countess\";2024-01-02\n2;Grace;2024-03-04\n",
+    )?;
+
+    let manager = frozen_duckdb::cli::DatasetManager::new()?;
+    let schema = manager.sniff_csv(&path("export.csv"))?;
+    assert_eq!(schema.delimiter, ";");
+    assert_eq!(schema.quote, "\"");
+    assert!(schema.has_header);
+    let columns: Vec<(&str, &str)> = schema
+        .columns
+        .iter()
+        .map(|c| (c.name.as_str(), c.data_type.as_str()))
+        .collect();
+    assert_eq!(
+        columns,
+        [("id", "BIGINT"), ("name", "VARCHAR"), ("joined", "DATE")]
+    );
+    assert_eq!(schema.sample.rows.len(), 2);
+    assert!(manager.sniff_csv(&path("missing.csv")).is_err());
+
+    assert_eq!(detect_format("EXPORT.CSV"), Some("csv"));
+    assert_eq!(detect_format("events.jsonl.zst"), Some("jsonl"));
+    assert_eq!(detect_format("notes.txt"), None);
+
+    let options = ConvertOptions {
+        auto: true,
+        ..Default::default()
+    };
+    manager.convert_dataset_with(
+        &path("export.csv"),
+        &path("export.parquet"),
+        "csv",
+        "parquet",
+        &options,
+    )?;
+    let output = manager.run_query(&format!(
+        "SELECT name FROM '{}' WHERE id = 1",
+        path("export.parquet")
+    ))?;
+    assert_eq!(output.to_csv().lines().nth(1), Some("Ada; Countess"));
+
+    info!("✅ CSV sniffing working");
+    Ok(())
+}
+
+#[test]
+fn test_validation_rules_on_parquet() -> Result<()> {
+    use frozen_duckdb::validation::{validate_file, RuleSet};
+
+    let temp_dir = tempfile::tempdir()?;
+    let parquet = temp_dir.path().join("orders.parquet");
+    let parquet = parquet.to_str().unwrap();
+
+    let conn = frozen_duckdb::Connection::open_in_memory()?;
+    conn.execute_batch(&format!(
+        "COPY (SELECT range AS id, CASE WHEN range % 2 = 0 THEN 'open' ELSE 'closed' END AS status
+               FROM range(100)) TO '{}' (FORMAT parquet)",
+        parquet
+    ))?;
+
+    let rules = RuleSet::parse(
+        "table: orders
+rules:
+  - type: not_null
+    column: id
+  - type: unique
+    column: id
+  - type: accepted_values
+    column: status
+    values: [open, closed]
+  - type: row_count_between
+    min: 100
+  - type: sql
+    sql: SELECT * FROM orders WHERE id < 0
+",
+    )?;
+    let report = validate_file(&conn, parquet, &rules)?;
+    assert!(report.passed(), "{}", report.to_json());
+
+    let strict = RuleSet::parse("rules:\n  - type: row_count_between\n    max: 10\n")?;
+    let report = validate_file(&conn, parquet, &strict)?;
+    assert!(!report.passed());
+    assert_eq!(report.to_json()["checks"][0]["violations"], 100);
+
+    info!("✅ Validation rules working");
+    Ok(())
+}
+
+/// Test schema context and SQL validation used by `ask`
+#[test]
+fn test_nl_to_sql_schema_and_validation() -> Result<()> {
+    use frozen_duckdb::cli::flock_manager::{extract_sql, schema_context, validate_sql};
+
+    info!("🧪 Testing natural-language SQL helpers");
+
+    let conn = Connection::open_in_memory()?;
+    assert!(schema_context(&conn).is_err());
+
+    conn.execute_batch(
+        "CREATE TABLE orders (id INTEGER, region VARCHAR, revenue DOUBLE);
+         CREATE SCHEMA crm;
+         CREATE TABLE crm.customers (id INTEGER, name VARCHAR);",
+    )?;
+    let schema = schema_context(&conn)?;
+    assert!(schema.contains("CREATE TABLE orders (id INTEGER, region VARCHAR, revenue DOUBLE);"));
+    assert!(schema.contains("CREATE TABLE crm.customers (id INTEGER, name VARCHAR);"));
+
+    let sql = extract_sql(
+        "Here you go:\n```sql\nSELECT region, sum(revenue) FROM orders GROUP BY region;\n```\nThis sums revenue.",
+    );
+    assert_eq!(
+        sql,
+        "SELECT region, sum(revenue) FROM orders GROUP BY region"
+    );
+    assert_eq!(
+        extract_sql("SELECT ';' AS x; DROP TABLE orders;"),
+        "SELECT ';' AS x"
+    );
+
+    validate_sql(&conn, &sql)?;
+    assert!(validate_sql(&conn, "SELECT missing FROM orders").is_err());
+    assert!(validate_sql(&conn, "SELECT 1; SELECT 2").is_err());
+    assert!(validate_sql(&conn, "").is_err());
+
+    info!("✅ Natural-language SQL helpers working");
+    Ok(())
+}
+
+/// Test reading llm_rerank responses back into document order
+#[test]
+fn test_rerank_order() {
+    use frozen_duckdb::cli::flock_manager::rerank_order;
+
+    let documents: Vec<String> = ["rust", "bread", "memory"]
+        .iter()
+        .map(|d| d.to_string())
+        .collect();
+
+    let response = r#"[{"id": "2", "content": "memory"}, {"id": 0, "content": "rust"}]"#;
+    assert_eq!(rerank_order(response, &documents), vec![2, 0, 1]);
+
+    // Entries without ids are matched by content; duplicates are ignored
+    let response = r#"[{"content": "bread"}, "memory", {"id": "1"}]"#;
+    assert_eq!(rerank_order(response, &documents), vec![1, 2, 0]);
+
+    // Unparseable responses keep the retrieval order
+    assert_eq!(rerank_order("not json", &documents), vec![0, 1, 2]);
+}
+
+/// Test reading llm_first/llm_last responses back into a candidate
+#[test]
+fn test_picked_index() {
+    use frozen_duckdb::cli::flock_manager::picked_index;
+
+    let candidates: Vec<String> = ["Short title", "A much longer title"]
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+
+    assert_eq!(
+        picked_index(
+            r#"{"id": "1", "content": "A much longer title"}"#,
+            &candidates
+        ),
+        Some(1)
+    );
+    assert_eq!(
+        picked_index(r#"[{"content": "Short title"}]"#, &candidates),
+        Some(0)
+    );
+    assert_eq!(picked_index(" Short title\n", &candidates), Some(0));
+    assert_eq!(picked_index(r#"{"id": 7}"#, &candidates), None);
+}
+
+/// Test rendering per-model settings as Flock model options
+#[test]
+fn test_model_options() {
+    use frozen_duckdb::cli::config::ModelSettings;
+    use frozen_duckdb::cli::flock_manager::model_options;
+    use std::collections::BTreeMap;
+
+    let settings = ModelSettings {
+        batch_size: Some(8),
+        temperature: Some(0.2),
+        num_ctx: Some(16384),
+        parameters: BTreeMap::from([
+            ("stop".to_string(), serde_json::json!(["END", "it's"])),
+            ("top_p".to_string(), serde_json::json!(0.9)),
+        ]),
+    };
+    assert_eq!(
+        model_options(&settings),
+        "{'tuple_format': 'json', 'batch_size': 8, 'model_parameters': \
+         {'num_ctx': 16384, 'stop': ['END', 'it''s'], 'temperature': 0.2, 'top_p': 0.9}}"
+    );
+}
+
+/// Test the HTTP embedding backend against the mock Ollama server
+#[test]
+fn test_http_embedder_with_mock_server() -> Result<()> {
+    use frozen_duckdb::cli::embedding_backends::HttpEmbedder;
+    use frozen_duckdb::cli::embedding_index::Embedder;
+    use frozen_duckdb_test_support::{mock_embedding, MockOllama, MockOptions};
+    use std::time::Duration;
+
+    let ollama = MockOllama::start_with(MockOptions {
+        dimension: 8,
+        ..Default::default()
+    })?;
+    let embedder = HttpEmbedder {
+        url: format!("{}/v1", ollama.url()),
+        model: "embedder".to_string(),
+        api_key: Some("test-key".to_string()),
+        normalize: true,
+        timeout: Duration::from_secs(5),
+    };
+
+    let texts = vec![
+        "ducks quack".to_string(),
+        "databases store data".to_string(),
+    ];
+    let embeddings = embedder.embed_batch(texts.clone())?;
+    assert_eq!(embeddings.len(), 2);
+    for (embedding, text) in embeddings.iter().zip(&texts) {
+        let expected = mock_embedding(text, 8);
+        assert!(embedding
+            .iter()
+            .zip(&expected)
+            .all(|(a, b)| (a - b).abs() < 1e-5));
+    }
+
+    let requests = ollama.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/v1/embeddings");
+    assert_eq!(requests[0].body["model"], "embedder");
+    Ok(())
+}
+
+/// Test converting, sniffing, and attaching files under awkward paths
+#[test]
+fn test_awkward_paths() -> Result<()> {
+    use frozen_duckdb::cli::sql_path::path_literal;
+
+    let temp_dir = tempfile::tempdir()?;
+    let mut dir = temp_dir.path().join("Q3 report's data");
+    dir.push("données 数据 🦆");
+    // Push the full path past Windows' 260-character MAX_PATH
+    while dir.as_os_str().len() <= 300 {
+        dir.push("a deeply nested directory");
+    }
+    std::fs::create_dir_all(&dir)?;
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+
+    std::fs::write(path("o'brien.csv"), "id,name\n1,Zoë\n2,O'Brien\n")?;
+    let manager = frozen_duckdb::cli::DatasetManager::new()?;
+    manager.convert_dataset(
+        &path("o'brien.csv"),
+        &path("o'brien.parquet"),
+        "csv",
+        "parquet",
+    )?;
+    manager.convert_dataset(
+        &path("o'brien.parquet"),
+        &path("o'brien.jsonl"),
+        "parquet",
+        "jsonl",
+    )?;
+    let schema = manager.sniff_csv(&path("o'brien.csv"))?;
+    assert_eq!(schema.columns.len(), 2);
+
+    let output = manager.run_query(&format!(
+        "SELECT name FROM read_json({}, format = 'newline_delimited') ORDER BY id",
+        path_literal(path("o'brien.jsonl"))
+    ))?;
+    assert_eq!(output.to_csv(), "name\nZoë\nO'Brien");
+
+    let database = path("it's.duckdb");
+    Connection::open(&database)?.execute_batch("CREATE TABLE t AS SELECT 42 AS answer")?;
+    manager.attach(&database, "awkward")?;
+    let output = manager.run_query("SELECT answer FROM awkward.t")?;
+    assert_eq!(output.rows, vec![vec![duckdb::types::Value::Int(42)]]);
+
+    info!("✅ Awkward paths working");
+    Ok(())
+}
+
+#[test]
+fn test_convert_with_performance_options() -> Result<()> {
+    use frozen_duckdb::cli::dataset_manager::{DatasetManager, PerformanceOptions};
+
+    let dir = tempfile::tempdir()?;
+    let csv = dir.path().join("events.csv");
+    let parquet = dir.path().join("events.parquet");
+    let mut content = String::from("id,name\n");
+    for i in 0..10_000 {
+        content.push_str(&format!("{},event_{}\n", i, i));
+    }
+    std::fs::write(&csv, content)?;
+
+    let mut manager = DatasetManager::new()?;
+    manager.set_performance(&PerformanceOptions {
+        threads: Some(2),
+        preserve_insertion_order: Some(false),
+        row_group_size: Some(2048),
+    })?;
+    let conn = manager.connection();
+    let (threads, preserve): (i64, bool) = conn.query_row(
+        "SELECT current_setting('threads'), current_setting('preserve_insertion_order')",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    assert_eq!((threads, preserve), (2, false));
+
+    manager.convert_dataset(
+        csv.to_str().unwrap(),
+        parquet.to_str().unwrap(),
+        "csv",
+        "parquet",
+    )?;
+    let parquet = frozen_duckdb::cli::sql_path::path_literal(&parquet);
+    let (rows, ids): (i64, i64) = conn.query_row(
+        &format!(
+            "SELECT count(*), count(DISTINCT id) FROM read_parquet({})",
+            parquet
+        ),
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    assert_eq!((rows, ids), (10_000, 10_000));
+    let row_groups: i64 = conn.query_row(
+        &format!(
+            "SELECT count(DISTINCT row_group_id) FROM parquet_metadata({})",
+            parquet
+        ),
+        [],
+        |row| row.get(0),
+    )?;
+    assert!(
+        row_groups > 1,
+        "expected several row groups, got {}",
+        row_groups
+    );
+
+    assert!(manager
+        .set_performance(&PerformanceOptions {
+            threads: Some(0),
+            ..Default::default()
+        })
+        .is_err());
+
+    info!("✅ Conversion performance options working");
+    Ok(())
+}
+
+#[test]
+fn test_convert_to_split_parquet() -> Result<()> {
+    use frozen_duckdb::cli::dataset_manager::{ConvertOptions, DatasetManager};
+    use frozen_duckdb::cli::parquet_parts::{SplitBy, MANIFEST_FILE};
+
+    let dir = tempfile::tempdir()?;
+    let csv = dir.path().join("events.csv");
+    let mut content = String::from("id,name\n");
+    for i in 0..20_000 {
+        content.push_str(&format!("{},event_{}\n", i, i));
+    }
+    std::fs::write(&csv, content)?;
+
+    let manager = DatasetManager::new()?;
+    let output = dir.path().join("events");
+    manager.convert_dataset_with(
+        csv.to_str().unwrap(),
+        output.to_str().unwrap(),
+        "csv",
+        "parquet",
+        &ConvertOptions {
+            split: Some(SplitBy::Rows(4096)),
+            ..Default::default()
+        },
+    )?;
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(output.join(MANIFEST_FILE))?)?;
+    assert_eq!(manifest["format"], "parquet");
+    assert_eq!(manifest["total_rows"], 20_000);
+    let parts = manifest["parts"].as_array().unwrap();
+    assert!(
+        parts.len() > 1,
+        "expected several parts, got {}",
+        parts.len()
+    );
+    let mut rows = 0;
+    for (i, part) in parts.iter().enumerate() {
+        assert_eq!(part["path"], format!("part_{}.parquet", i));
+        assert!(output.join(part["path"].as_str().unwrap()).exists());
+        assert!(part["bytes"].as_u64().unwrap() > 0);
+        rows += part["rows"].as_u64().unwrap();
+    }
+    assert_eq!(rows, 20_000);
+
+    // The parts read back as the whole dataset
+    let count: i64 = manager.connection().query_row(
+        &format!(
+            "SELECT count(DISTINCT id) FROM read_parquet({})",
+            frozen_duckdb::cli::sql_path::path_literal(output.join("*.parquet"))
+        ),
+        [],
+        |row| row.get(0),
+    )?;
+    assert_eq!(count, 20_000);
+
+    // Splitting only applies to Parquet output
+    assert!(manager
+        .convert_dataset_with(
+            csv.to_str().unwrap(),
+            dir.path().join("events.jsonl").to_str().unwrap(),
+            "csv",
+            "jsonl",
+            &ConvertOptions {
+                split: Some(SplitBy::FileSize(1_000_000)),
+                ..Default::default()
+            },
+        )
+        .is_err());
+
+    info!("✅ Split Parquet export working");
+    Ok(())
+}
+
+#[test]
+fn test_query_profile_report() -> Result<()> {
+    use frozen_duckdb::profiling::{profile, report_paths};
+
+    let dir = tempfile::tempdir()?;
+    let conn = Connection::open_in_memory()?;
+    conn.execute_batch(
+        "CREATE TABLE sales AS SELECT i % 7 AS store, i * 1.5 AS amount FROM range(50000) t(i)",
+    )?;
+
+    let (stores, report) = profile(&conn, dir.path(), "sales", || {
+        let mut stmt =
+            conn.prepare("SELECT store, sum(amount) FROM sales GROUP BY store ORDER BY store")?;
+        let rows = stmt.query_map([], |row| row.get::<_, i64>(0))?;
+        Ok(rows.collect::<duckdb::Result<Vec<_>>>()?)
+    })?;
+    assert_eq!(stores, (0..7).collect::<Vec<i64>>());
+
+    let metrics = report.metrics();
+    assert!(metrics.iter().any(|m| m.name.contains("GROUP_BY")));
+    assert!(metrics.iter().any(|m| m.name.contains("SCAN")));
+    assert!(report.operator_timing() > std::time::Duration::ZERO);
+
+    let (json, html) = report_paths(dir.path(), "sales");
+    assert!(json.exists());
+    assert!(std::fs::read_to_string(html)?.contains("GROUP_BY"));
+
+    // Profiling is switched off again afterwards
+    std::fs::remove_file(&json)?;
+    conn.execute_batch("SELECT 1")?;
+    assert!(!json.exists());
+
+    info!("✅ Query profile report working");
+    Ok(())
+}
+
+/// Test registering datasets in the catalog and querying them by name
+#[test]
+fn test_dataset_catalog() -> Result<()> {
+    use frozen_duckdb::cli::catalog::DatasetCatalog;
+    use frozen_duckdb::cli::dataset_manager::DatasetManager;
+
+    let dir = tempfile::tempdir()?;
+    let csv = dir.path().join("sales.csv");
+    std::fs::write(&csv, "region,amount\neast,10\nwest,20\neast,5\n")?;
+
+    let manager = DatasetManager::new()?;
+    let catalog = DatasetCatalog::open(&dir.path().join("catalog.duckdb"))?;
+    let entry = manager.catalog_entry("sales", csv.to_str().unwrap(), "csv")?;
+    assert_eq!(entry.row_count, 3);
+    assert_eq!(entry.schema_hash.len(), 64);
+    assert!(std::path::Path::new(&entry.path).is_absolute());
+    catalog.register(&entry)?;
+    assert!(manager
+        .catalog_entry("bad name", "sales.csv", "csv")
+        .is_err());
+
+    // The same schema hashes the same; a new column changes it
+    let same = manager.catalog_entry("sales", csv.to_str().unwrap(), "csv")?;
+    assert_eq!(same.schema_hash, entry.schema_hash);
+    std::fs::write(
+        dir.path().join("wide.csv"),
+        "region,amount,note\neast,10,x\n",
+    )?;
+    let wide =
+        manager.catalog_entry("wide", dir.path().join("wide.csv").to_str().unwrap(), "csv")?;
+    assert_ne!(wide.schema_hash, entry.schema_hash);
+
+    // Cataloged datasets are queryable as datasets.<name>
+    let query = DatasetManager::new()?;
+    assert_eq!(query.attach_catalog(&catalog)?, 1);
+    let total: i64 = query.connection().query_row(
+        "SELECT sum(amount)::BIGINT FROM datasets.sales WHERE region = 'east'",
+        [],
+        |row| row.get(0),
+    )?;
+    assert_eq!(total, 15);
+
+    // The query cache sees the file behind the view
+    assert!(query
+        .query_inputs("SELECT * FROM datasets.sales")
+        .iter()
+        .any(|path| path.ends_with("sales.csv")));
+
+    assert_eq!(catalog.list()?.len(), 1);
+    assert!(catalog.remove("sales")?);
+    assert!(catalog.get("sales")?.is_none());
+
+    info!("✅ Dataset catalog working");
+    Ok(())
+}
+
+/// Test recording lineage in the catalog and in Parquet metadata
+#[test]
+fn test_lineage_tracking() -> Result<()> {
+    use frozen_duckdb::cli::catalog::DatasetCatalog;
+    use frozen_duckdb::cli::dataset_manager::DatasetManager;
+    use frozen_duckdb::cli::lineage::{self, Lineage};
+
+    let dir = tempfile::tempdir()?;
+    let csv = dir.path().join("raw.csv");
+    let parquet = dir.path().join("clean.parquet");
+    std::fs::write(&csv, "id,name\n1,a\n2,b\n")?;
+    let (csv, parquet) = (csv.to_str().unwrap(), parquet.to_str().unwrap());
+
+    let manager = DatasetManager::new()?;
+    manager.convert_dataset(csv, parquet, "csv", "parquet")?;
+    let catalog = DatasetCatalog::open(&dir.path().join("catalog.duckdb"))?;
+    let record = Lineage::new(
+        "convert",
+        parquet,
+        &[csv],
+        serde_json::json!({"output_format": "parquet"}),
+    );
+    catalog.record_lineage(&record)?;
+
+    // Embedded metadata travels with the file and keeps its rows
+    assert!(lineage::embed_in_parquet(manager.connection(), &record)?);
+    let embedded = lineage::read_from_parquet(manager.connection(), parquet)?.unwrap();
+    assert_eq!(embedded, record);
+    let rows: i64 = manager.connection().query_row(
+        &format!("SELECT count(*) FROM read_parquet('{}')", parquet),
+        [],
+        |row| row.get(0),
+    )?;
+    assert_eq!(rows, 2);
+    assert!(!lineage::embed_in_parquet(
+        manager.connection(),
+        &Lineage::new("convert", csv, &[], serde_json::json!({}))
+    )?);
+
+    let tree = lineage::trace(parquet, &|path: &str| {
+        lineage::lookup(&catalog, manager.connection(), path)
+    })?;
+    assert_eq!(tree.lineage.as_ref().unwrap().command, "convert");
+    assert!(tree.lineage.as_ref().unwrap().created_at.is_some());
+    assert_eq!(tree.inputs.len(), 1);
+    assert_eq!(tree.inputs[0].path, csv);
+    assert!(tree.inputs[0].lineage.is_none());
+    assert!(tree.format_tree().contains("(source)"));
+
+    info!("✅ Lineage tracking working");
+    Ok(())
+}
+
+/// Test that lakehouse tables are input-only formats
+#[test]
+fn test_lakehouse_formats() -> Result<()> {
+    use frozen_duckdb::cli::dataset_manager::DatasetManager;
+    use frozen_duckdb::cli::lakehouse::{rewrite_table_urls, table_format, TableFormat};
+
+    let dir = tempfile::tempdir()?;
+    let table = dir.path().join("events");
+    std::fs::create_dir_all(table.join("_delta_log"))?;
+    assert_eq!(
+        table_format(table.to_str().unwrap()),
+        Some(TableFormat::Delta)
+    );
+
+    let manager = DatasetManager::new()?;
+    let csv = dir.path().join("events.csv");
+    std::fs::write(&csv, "id\n1\n")?;
+    let error = manager
+        .convert_dataset(
+            csv.to_str().unwrap(),
+            table.to_str().unwrap(),
+            "csv",
+            "delta",
+        )
+        .unwrap_err();
+    assert!(error.to_string().contains("delta"), "{}", error);
+
+    // Unknown formats list the table formats among the accepted ones
+    let error = manager
+        .describe_dataset(csv.to_str().unwrap(), "hudi", None)
+        .unwrap_err();
+    assert!(error.to_string().contains("delta, iceberg"), "{}", error);
+
+    // Plain queries are left alone
+    let (sql, tables) = rewrite_table_urls("SELECT 'delta' AS word");
+    assert_eq!(sql, "SELECT 'delta' AS word");
+    assert!(tables.is_empty());
+
+    info!("✅ Lakehouse formats working");
+    Ok(())
+}
+
+/// Test that remote databases are attached without inline passwords
+#[test]
+fn test_federation_attachments() -> Result<()> {
+    use frozen_duckdb::cli::dataset_manager::DatasetManager;
+    use frozen_duckdb::cli::federation::{RemoteAttachment, RemoteKind};
+
+    let error = RemoteAttachment::parse(
+        RemoteKind::Postgres,
+        "host=db dbname=shop password=hunter2 AS pg",
+    )
+    .unwrap_err();
+    assert!(!error.to_string().contains("hunter2"), "{}", error);
+
+    // Attaching an unreachable server fails with context instead of hanging
+    let manager = DatasetManager::new()?;
+    let remote = RemoteAttachment::parse(
+        RemoteKind::Postgres,
+        "host=127.0.0.1 port=1 dbname=shop connect_timeout=1 AS pg",
+    )?;
+    assert!(manager.attach_remote(&remote).is_err());
+
+    info!("✅ Federation attachments working");
+    Ok(())
+}
+
+/// Test full and incremental ingest into a database table
+#[test]
+fn test_ingest() -> Result<()> {
+    use frozen_duckdb::cli::dataset_manager::DatasetManager;
+    use frozen_duckdb::cli::ingest::IncrementalOptions;
+
+    let dir = tempfile::tempdir()?;
+    let csv = dir.path().join("orders.csv");
+    let csv = csv.to_str().unwrap();
+    std::fs::write(
+        csv,
+        "id,amount,updated_at\n1,10,2024-01-01\n2,20,2024-01-02\n",
+    )?;
+    let database = dir.path().join("shop.duckdb");
+    let manager = DatasetManager::open(database.to_str().unwrap())?;
+
+    let full = manager.ingest(csv, "csv", "orders_copy", None, None)?;
+    assert_eq!(full.inserted, 2);
+    assert!(full.watermark.is_none());
+
+    let options = IncrementalOptions {
+        key_columns: vec!["id".to_string()],
+        watermark_column: "updated_at".to_string(),
+    };
+    let first = manager.ingest(csv, "csv", "orders", Some(&options), None)?;
+    assert_eq!(first.watermark.as_deref(), Some("2024-01-02"));
+
+    std::fs::write(
+        csv,
+        "id,amount,updated_at\n1,15,2024-01-03\n2,20,2024-01-02\n3,30,2024-01-03\n",
+    )?;
+    let second = manager.ingest(
+        csv,
+        "csv",
+        "orders",
+        Some(&options),
+        first.watermark.as_deref(),
+    )?;
+    assert_eq!((second.inserted, second.updated), (1, 1));
+    let total = manager.run_query("SELECT sum(amount) FROM orders")?;
+    assert_eq!(total.rows.len(), 1);
+
+    // Tables without a key constraint can't be merged into
+    let error = manager
+        .ingest(csv, "csv", "orders_copy", Some(&options), None)
+        .unwrap_err();
+    assert!(
+        format!("{:#}", error).contains("PRIMARY KEY"),
+        "{:#}",
+        error
+    );
+
+    info!("✅ Ingest working");
+    Ok(())
+}
+
+/// Test upserts into tables and files
+#[test]
+fn test_merge_into() -> Result<()> {
+    use frozen_duckdb::cli::dataset_manager::DatasetManager;
+    use frozen_duckdb::cli::merge::{WhenMatched, WhenNotMatched};
+
+    let dir = tempfile::tempdir()?;
+    let changes = dir.path().join("changes.csv");
+    std::fs::write(&changes, "id,amount\n2,25\n3,30\n")?;
+    let changes = changes.to_str().unwrap();
+    let keys = vec!["id".to_string()];
+
+    let manager = DatasetManager::new()?;
+    manager.connection().execute_batch(
+        "CREATE TABLE orders (id INTEGER PRIMARY KEY, amount INTEGER);
+         INSERT INTO orders VALUES (1, 10), (2, 20);",
+    )?;
+    let report = manager.merge_into(
+        "orders",
+        changes,
+        &keys,
+        WhenMatched::Update,
+        WhenNotMatched::Insert,
+    )?;
+    assert!(report.on_conflict);
+    assert_eq!((report.inserted, report.updated), (1, 1));
+
+    // Only update rows that exist
+    let target = dir.path().join("orders.csv");
+    std::fs::write(&target, "id,amount\n1,10\n2,20\n")?;
+    let target = target.to_str().unwrap();
+    let report = manager.merge_into_file(
+        target,
+        changes,
+        &keys,
+        WhenMatched::Update,
+        WhenNotMatched::Ignore,
+    )?;
+    assert!(!report.on_conflict);
+    assert_eq!((report.inserted, report.updated), (0, 1));
+    let written = std::fs::read_to_string(target)?;
+    assert!(
+        written.contains("2,25") && !written.contains("3,30"),
+        "{}",
+        written
+    );
+
+    info!("✅ Merge working");
+    Ok(())
+}
+
+/// Test exporting a per-tenant subset of a database
+#[test]
+fn test_share_policy_export() -> Result<()> {
+    use frozen_duckdb::cli::dataset_manager::DatasetManager;
+    use frozen_duckdb::cli::sharing::{export, plan, source_tables, SharePolicy};
+
+    let dir = tempfile::tempdir()?;
+    let database = dir.path().join("saas.duckdb");
+    let manager = DatasetManager::open(database.to_str().unwrap())?;
+    manager.connection().execute_batch(
+        "CREATE TABLE invoices (id INTEGER, tenant_id INTEGER, amount DOUBLE, notes VARCHAR);
+         INSERT INTO invoices VALUES (1, 1, 10, 'a'), (2, 2, 20, 'b'), (3, 1, 30, 'c');
+         CREATE TABLE api_keys (tenant VARCHAR, secret VARCHAR);",
+    )?;
+
+    let policy = SharePolicy::parse(
+        "consumer: tenant_one\nwhere:\n  tenant_id: 1\ndrop_columns: [notes]\n",
+    )?;
+    let plan = plan(&policy, &source_tables(manager.connection())?)?;
+    assert_eq!(plan.shared.len(), 1);
+    assert_eq!(plan.skipped[0].0, "api_keys");
+
+    let output = dir.path().join("tenant_one.duckdb");
+    let rows = export(manager.connection(), &plan, output.to_str().unwrap())?;
+    assert_eq!(rows, 2);
+
+    info!("✅ Share policies working");
+    Ok(())
+}
+
+/// Test that a copy snapshot restores the database it was taken from
+#[test]
+fn test_snapshots() -> Result<()> {
+    use frozen_duckdb::cli::snapshot::{Retention, SnapshotMethod, SnapshotStore};
+
+    let dir = tempfile::tempdir()?;
+    let database = dir.path().join("warehouse.duckdb");
+    {
+        let conn = Connection::open(&database)?;
+        conn.execute_batch("CREATE TABLE t AS SELECT range AS id FROM range(10);")?;
+    }
+
+    let store = SnapshotStore::for_database(&database);
+    let snapshot = store.create(Some("before_load"), SnapshotMethod::Copy)?;
+    {
+        let conn = Connection::open(&database)?;
+        conn.execute_batch("DELETE FROM t WHERE id < 5;")?;
+    }
+
+    let restored = store.restore("before_load")?;
+    assert_eq!(restored.id, snapshot.id);
+    let conn = Connection::open(&database)?;
+    let count: i64 = conn.query_row("SELECT count(*) FROM t", [], |row| row.get(0))?;
+    assert_eq!(count, 10);
+    drop(conn);
+
+    let snapshots = store.list()?;
+    assert_eq!(snapshots.len(), 2);
+    assert!(snapshots
+        .iter()
+        .any(|s| s.label.as_deref() == Some("pre_restore")));
+
+    let removed = store.prune(&Retention {
+        keep_last: Some(1),
+        max_age: None,
+    })?;
+    assert_eq!(removed.len(), 1);
+    assert_eq!(store.list()?.len(), 1);
+
+    info!("✅ Snapshots working");
+    Ok(())
+}
@@ -1,9 +1,13 @@
 //! Flock extension tests - FAIL FAST mode
-//! Provider: Ollama (REQUIRED)
+//! Provider: Ollama (REQUIRED), or the mock server with FROZEN_DUCKDB_MOCK_OLLAMA=1
 //! Models: qwen3-coder:30b (text), qwen3-embedding:8b (embeddings)
 //! Based on: https://duckdb.org/community_extensions/extensions/flock.html
+//!
+//! Set FROZEN_DUCKDB_OLLAMA_URL to target another server (see
+//! frozen_duckdb_test_support::ollama_url).
 
 use duckdb::Connection;
+use frozen_duckdb_test_support::ollama_url;
 use tracing::info;
 
 /// Verbose logging function (only logs if verbose mode is enabled)
@@ -65,7 +69,10 @@ fn test_ollama_setup_and_models() {
 
     // Try different secret creation syntaxes
     let secret_creation_result = conn.execute(
-        "CREATE SECRET ollama_secret (TYPE OLLAMA, API_URL 'http://localhost:11434')",
+        &format!(
+            "CREATE SECRET ollama_secret (TYPE OLLAMA, API_URL '{}')",
+            ollama_url()
+        ),
         [],
     );
 
@@ -76,7 +83,7 @@ fn test_ollama_setup_and_models() {
         Err(_) => {
             // Try alternative syntax
             match conn.execute(
-                "CREATE SECRET (TYPE OLLAMA, API_URL 'http://localhost:11434')",
+                &format!("CREATE SECRET (TYPE OLLAMA, API_URL '{}')", ollama_url()),
                 [],
             ) {
                 Ok(_) => {
@@ -115,7 +122,10 @@ fn test_llm_complete_with_coder() {
     // But we need to handle the case where they might not exist yet
     // Create the default Ollama secret that Flock expects
     let secret_result = conn.execute(
-        "CREATE SECRET __default_ollama (TYPE OLLAMA, API_URL 'http://localhost:11434')",
+        &format!(
+            "CREATE SECRET __default_ollama (TYPE OLLAMA, API_URL '{}')",
+            ollama_url()
+        ),
         [],
     );
     if secret_result.is_err() {
@@ -155,7 +165,10 @@ fn test_llm_embedding_generation() {
     // But we need to handle the case where they might not exist yet
     // Create the default Ollama secret that Flock expects
     let secret_result = conn.execute(
-        "CREATE SECRET __default_ollama (TYPE OLLAMA, API_URL 'http://localhost:11434')",
+        &format!(
+            "CREATE SECRET __default_ollama (TYPE OLLAMA, API_URL '{}')",
+            ollama_url()
+        ),
         [],
     );
     if secret_result.is_err() {
@@ -213,7 +226,10 @@ fn test_llm_filter_boolean() {
     // But we need to handle the case where they might not exist yet
     // Create the default Ollama secret that Flock expects
     let secret_result = conn.execute(
-        "CREATE SECRET __default_ollama (TYPE OLLAMA, API_URL 'http://localhost:11434')",
+        &format!(
+            "CREATE SECRET __default_ollama (TYPE OLLAMA, API_URL '{}')",
+            ollama_url()
+        ),
         [],
     );
     if secret_result.is_err() {
@@ -262,7 +278,10 @@ fn test_semantic_similarity_search() {
     // But we need to handle the case where they might not exist yet
     // Create the default Ollama secret that Flock expects
     let secret_result = conn.execute(
-        "CREATE SECRET __default_ollama (TYPE OLLAMA, API_URL 'http://localhost:11434')",
+        &format!(
+            "CREATE SECRET __default_ollama (TYPE OLLAMA, API_URL '{}')",
+            ollama_url()
+        ),
         [],
     );
     if secret_result.is_err() {
@@ -323,7 +342,10 @@ fn test_hybrid_search_rag() {
     // But we need to handle the case where they might not exist yet
     // Create the default Ollama secret that Flock expects
     let secret_result = conn.execute(
-        "CREATE SECRET __default_ollama (TYPE OLLAMA, API_URL 'http://localhost:11434')",
+        &format!(
+            "CREATE SECRET __default_ollama (TYPE OLLAMA, API_URL '{}')",
+            ollama_url()
+        ),
         [],
     );
     if secret_result.is_err() {
@@ -390,7 +412,10 @@ fn test_llm_performance() {
     // But we need to handle the case where they might not exist yet
     // Create the default Ollama secret that Flock expects
     let secret_result = conn.execute(
-        "CREATE SECRET __default_ollama (TYPE OLLAMA, API_URL 'http://localhost:11434')",
+        &format!(
+            "CREATE SECRET __default_ollama (TYPE OLLAMA, API_URL '{}')",
+            ollama_url()
+        ),
         [],
     );
     if secret_result.is_err() {
@@ -445,7 +470,10 @@ fn test_llm_aggregate_functions() {
     // But we need to handle the case where they might not exist yet
     // Create the default Ollama secret that Flock expects
     let secret_result = conn.execute(
-        "CREATE SECRET __default_ollama (TYPE OLLAMA, API_URL 'http://localhost:11434')",
+        &format!(
+            "CREATE SECRET __default_ollama (TYPE OLLAMA, API_URL '{}')",
+            ollama_url()
+        ),
         [],
     );
     if secret_result.is_err() {
@@ -523,6 +551,33 @@ fn test_fusion_functions() {
     assert!(combsum_score > 0.0);
 }
 
+/// Test the typed fusion API and merging of result lists
+#[test]
+fn test_fusion_api() {
+    use frozen_duckdb::cli::fusion::{self, fuse_rankings, FusionMethod};
+
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch("INSTALL flock FROM community; LOAD flock;")
+        .unwrap();
+
+    // A document ranked first by both retrievers beats one found by only one
+    let both = fusion::rrf(&conn, &[Some(1.0), Some(1.0)]).unwrap();
+    let one = fusion::rrf(&conn, &[Some(1.0), None]).unwrap();
+    assert!(both > one && one > 0.0);
+    assert_eq!(fusion::rrf(&conn, &[None, None]).unwrap(), 0.0);
+
+    assert!(fusion::combsum(&conn, &[Some(0.4), Some(0.5)]).unwrap() > 0.8);
+    assert!(fusion::combmnz(&conn, &[Some(0.5), None]).is_ok());
+    assert!(fusion::combsum(&conn, &[Some(3.2)]).is_err());
+    assert!(fusion::rrf(&conn, &[Some(0.0)]).is_err());
+
+    let vector = vec![("a".to_string(), 0.9), ("b".to_string(), 0.8)];
+    let keyword = vec![("b".to_string(), 12.0), ("c".to_string(), 4.0)];
+    let fused = fuse_rankings(&conn, FusionMethod::Rrf, &[vector, keyword]).unwrap();
+    assert_eq!(fused.len(), 3);
+    assert_eq!(fused[0].0, "b");
+}
+
 /// Test complete RAG pipeline
 #[test]
 fn test_complete_rag_pipeline() {
@@ -533,7 +588,10 @@ fn test_complete_rag_pipeline() {
     // Create secret and models (these should already exist from setup script)
     // But we need to handle the case where they might not exist yet
     let secret_result = conn.execute(
-        "CREATE SECRET ollama_secret (TYPE OLLAMA, API_URL 'http://localhost:11434')",
+        &format!(
+            "CREATE SECRET ollama_secret (TYPE OLLAMA, API_URL '{}')",
+            ollama_url()
+        ),
         [],
     );
     if secret_result.is_err() {
@@ -264,6 +264,40 @@ fn test_benchmark_integration() {
     env::remove_var("DUCKDB_INCLUDE_DIR");
 }
 
+#[test]
+fn test_benchmark_performance_budget() {
+    // Architecture detection runs on every build, so it must stay cheap
+    let stats = benchmark::Benchmark::new("architecture_detect")
+        .warmup(5)
+        .iterations(200)
+        .run(|| {
+            assert!(!architecture::detect().is_empty());
+            Ok(())
+        })
+        .unwrap();
+
+    assert_eq!(stats.iterations, 200);
+    assert!(stats.min <= stats.median && stats.median <= stats.p95 && stats.p95 <= stats.max);
+    stats
+        .check_p95(std::time::Duration::from_millis(10))
+        .unwrap();
+
+    // A stored baseline far slower than the current run is never a regression
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("baselines.json");
+    let mut slow = stats.clone();
+    slow.median = stats.median * 10 + std::time::Duration::from_millis(1);
+    let mut baselines = benchmark::Baselines::load(&path).unwrap();
+    baselines.record(&slow);
+    baselines.save(&path).unwrap();
+
+    let baseline = benchmark::Baselines::load(&path)
+        .unwrap()
+        .get("architecture_detect")
+        .unwrap();
+    stats.check_regression(&baseline, 0.0).unwrap();
+}
+
 #[test]
 fn test_real_world_scenario() {
     // Simulate a real-world usage scenario
@@ -243,3 +243,75 @@ fn test_tpch_performance_characteristics() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_tpch_concurrent_parquet_export() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let output_dir = temp_dir.path().to_str().unwrap();
+
+    let mut manager = frozen_duckdb::cli::DatasetManager::new()?;
+    manager.set_parquet_compression("zstd")?;
+    assert!(manager.set_parquet_compression("lz77").is_err());
+
+    let start = Instant::now();
+    manager.download_tpch(output_dir, "parquet", 0.01)?;
+    info!(
+        "🔄 Concurrent TPC-H Parquet export took: {:?}",
+        start.elapsed()
+    );
+
+    // Every table should round-trip with the same row count
+    let conn = Connection::open_in_memory()?;
+    conn.execute_batch("INSTALL tpch; LOAD tpch; CALL dbgen(sf = 0.01);")?;
+    for table in [
+        "customer", "lineitem", "nation", "orders", "part", "partsupp", "region", "supplier",
+    ] {
+        let path = temp_dir.path().join(format!("{}.parquet", table));
+        let exported: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM read_parquet('{}')", path.display()),
+            [],
+            |row| row.get(0),
+        )?;
+        let expected: i64 =
+            conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                row.get(0)
+            })?;
+        assert_eq!(exported, expected, "Row count mismatch for {}", table);
+
+        let codec: String = conn.query_row(
+            &format!(
+                "SELECT DISTINCT compression FROM parquet_metadata('{}') LIMIT 1",
+                path.display()
+            ),
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(codec.to_uppercase(), "ZSTD");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_tpch_answer_verification() -> Result<()> {
+    use frozen_duckdb::cli::tpch_answers::verify;
+
+    let conn = Connection::open_in_memory()?;
+    let checks = verify(&conn, 0.01)?;
+    assert_eq!(checks.len(), 22);
+    for check in &checks {
+        assert!(
+            check.passed(),
+            "Query {}: {:?}",
+            check.query_nr,
+            check.mismatches
+        );
+    }
+
+    // Scale factors without answers are rejected
+    let conn = Connection::open_in_memory()?;
+    assert!(verify(&conn, 0.5).is_err());
+
+    info!("✅ All 22 TPC-H results match the answers at SF 0.01");
+    Ok(())
+}